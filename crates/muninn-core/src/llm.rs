@@ -115,6 +115,36 @@ impl CompletionRequest {
         self.muninn.as_ref().is_some_and(|m| m.recursive)
     }
 
+    /// Text of the last user message that has any, i.e. the question
+    /// being asked (tool results carry no text blocks).
+    pub fn question(&self) -> Option<String> {
+        self.messages
+            .iter()
+            .rev()
+            .filter(|m| m.role == Role::User)
+            .map(|m| {
+                m.content
+                    .blocks()
+                    .iter()
+                    .filter_map(|b| match b {
+                        ContentBlock::Text { text, .. } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .find(|text| !text.trim().is_empty())
+    }
+
+    /// Names of the server tools offered, which the provider runs itself.
+    pub fn server_tools(&self) -> Vec<&str> {
+        self.tools
+            .iter()
+            .filter(|t| t.is_server_tool())
+            .map(|t| t.name.as_str())
+            .collect()
+    }
+
     /// Create a new completion request with the given model and messages.
     pub fn new(model: impl Into<String>, messages: Vec<Message>, max_tokens: u32) -> Self {
         Self {
@@ -258,6 +288,8 @@ impl Content {
                         ContentBlock::ToolUse { .. } => "ToolUse",
                         ContentBlock::ToolResult { .. } => "ToolResult",
                         ContentBlock::Thinking { .. } => "Thinking",
+                        ContentBlock::ServerToolUse { .. } => "ServerToolUse",
+                        ContentBlock::ServerToolResult(_) => "ServerToolResult",
                     }).collect::<Vec<_>>(),
                     "Content::to_text"
                 );
@@ -334,6 +366,34 @@ pub enum ContentBlock {
         /// Signature for verification.
         signature: String,
     },
+    /// Call to a server tool, which the provider runs itself
+    /// (`web_search`, `code_execution`).
+    ServerToolUse {
+        /// Unique ID for this tool use (`srvtoolu_…`).
+        id: String,
+        /// Name of the server tool.
+        name: String,
+        /// Input arguments for the tool.
+        input: serde_json::Value,
+    },
+    /// Result of a server tool call. Matches any other block type with a
+    /// `tool_use_id`, so new server tools pass through unchanged.
+    #[serde(untagged)]
+    ServerToolResult(ServerToolResultBlock),
+}
+
+/// Result of a server tool call (`web_search_tool_result`,
+/// `code_execution_tool_result`, ...), kept as the provider sent it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerToolResultBlock {
+    /// Block type, naming the tool the result is for.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// ID of the server tool use this is a result for.
+    pub tool_use_id: String,
+    /// The result, in the tool's own shape.
+    #[serde(default)]
+    pub content: serde_json::Value,
 }
 
 /// Tool result content - can be a string or array of content blocks.
@@ -459,16 +519,32 @@ impl ContentBlock {
 }
 
 /// Definition of a tool available to the model.
+///
+/// Server tools (`{"type": "web_search_20250305", "name": "web_search"}`)
+/// have no description or schema; their `type` and settings are kept so
+/// they reach the provider as sent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDefinition {
     /// Name of the tool.
     pub name: String,
 
     /// Description of what the tool does.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub description: String,
 
     /// JSON Schema for the tool's input parameters.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
     pub input_schema: serde_json::Value,
+
+    /// Tool type: absent or `custom` for client tools, versioned for
+    /// server tools (`web_search_20250305`).
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub tool_type: Option<String>,
+
+    /// Other fields, as sent: `cache_control`, server tool settings such
+    /// as `max_uses`.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl ToolDefinition {
@@ -482,8 +558,15 @@ impl ToolDefinition {
             name: name.into(),
             description: description.into(),
             input_schema,
+            tool_type: None,
+            extra: serde_json::Map::new(),
         }
     }
+
+    /// Whether the provider runs this tool (a server tool).
+    pub fn is_server_tool(&self) -> bool {
+        self.tool_type.as_deref().is_some_and(|t| t != "custom")
+    }
 }
 
 /// How the model should choose which tool to use.
@@ -658,6 +741,16 @@ pub struct MuninnConfig {
     /// Whether to include exploration metadata in response.
     #[serde(default = "default_true")]
     pub include_metadata: bool,
+
+    /// Request category picked by the router (e.g. `"debugging"`), used to
+    /// select a category-specific exploration prompt and tool subset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+
+    /// Files the client's agent just read, most recent first. Seeded into
+    /// the exploration so "this file" resolves to what the user is looking at.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub visible_files: Vec<String>,
 }
 
 fn default_true() -> bool {
@@ -670,6 +763,8 @@ impl Default for MuninnConfig {
             recursive: false,
             budget: BudgetConfig::default(),
             include_metadata: true, // Include metadata by default
+            category: None,
+            visible_files: Vec::new(),
         }
     }
 }
@@ -681,6 +776,8 @@ impl MuninnConfig {
             recursive: true,
             budget: BudgetConfig::default(),
             include_metadata: true,
+            category: None,
+            visible_files: Vec::new(),
         }
     }
 
@@ -733,6 +830,25 @@ pub struct ExplorationMetadata {
     pub tool_calls: u32,
     /// Total duration in milliseconds.
     pub duration_ms: u64,
+    /// Trace ID the exploration was recorded under, for finding the
+    /// matching trace/session files on disk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Self-assessed answer quality from 0 to 1, when the engine scores
+    /// its explorations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality_score: Option<f64>,
+    /// Model the exploration switched to when its token budget ran low.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downshifted_to: Option<String>,
+    /// Why the answer is partial (`timeout`: written from what was
+    /// gathered when `max_duration_secs` ran out), when it is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<String>,
+    /// Continuation requests stitched onto an answer that hit
+    /// `max_tokens`, when there were any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuations: Option<u32>,
 }
 
 #[cfg(test)]
@@ -854,4 +970,163 @@ mod tests {
         ]);
         assert_eq!(blocks.to_text(), "onetwo");
     }
+
+    /// Property tests: deserializing arbitrary agent payloads must never
+    /// panic, and anything that parses must survive a serialize round-trip.
+    mod props {
+        use super::*;
+        use proptest::prelude::*;
+        use serde_json::{Map, Value};
+
+        fn json_value() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                any::<i64>().prop_map(Value::from),
+                any::<f64>().prop_map(Value::from),
+                ".{0,12}".prop_map(Value::String),
+            ];
+            leaf.prop_recursive(4, 48, 6, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                    prop::collection::btree_map("[a-z_]{1,10}", inner, 0..6)
+                        .prop_map(|m| Value::Object(m.into_iter().collect())),
+                ]
+            })
+        }
+
+        /// Content blocks with real and unknown `type` tags and loose fields.
+        fn content_block() -> impl Strategy<Value = Value> {
+            let block_type = prop::sample::select(vec![
+                "text",
+                "tool_use",
+                "tool_result",
+                "image",
+                "thinking",
+                "redacted_thinking",
+                "server_tool_use",
+                "mystery",
+            ]);
+            let field = prop::sample::select(vec![
+                "text",
+                "id",
+                "name",
+                "input",
+                "tool_use_id",
+                "content",
+                "is_error",
+                "source",
+                "thinking",
+                "signature",
+                "cache_control",
+            ]);
+            (
+                block_type,
+                prop::collection::vec((field, json_value()), 0..5),
+            )
+                .prop_map(|(block_type, fields)| {
+                    let mut map: Map<String, Value> = fields
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v))
+                        .collect();
+                    map.insert("type".into(), Value::String(block_type.into()));
+                    Value::Object(map)
+                })
+        }
+
+        fn message() -> impl Strategy<Value = Value> {
+            let role = prop::sample::select(vec!["user", "assistant", "system", "tool"]);
+            let content = prop_oneof![
+                ".{0,24}".prop_map(Value::String),
+                prop::collection::vec(content_block(), 0..4).prop_map(Value::Array),
+                json_value(),
+            ];
+            (role, content).prop_map(|(role, content)| {
+                serde_json::json!({
+                    "role": role,
+                    "content": content,
+                })
+            })
+        }
+
+        fn request_payload() -> impl Strategy<Value = Value> {
+            (
+                prop::collection::vec(message(), 0..4),
+                prop::collection::vec(("[a-z_]{1,12}", json_value()), 0..6),
+                prop_oneof![json_value(), ".{0,24}".prop_map(Value::String)],
+                any::<u32>(),
+            )
+                .prop_map(|(messages, extra, system, max_tokens)| {
+                    let mut map: Map<String, Value> = extra.into_iter().collect();
+                    map.insert("model".into(), "claude-test".into());
+                    map.insert("max_tokens".into(), max_tokens.into());
+                    map.insert("messages".into(), Value::Array(messages));
+                    map.insert("system".into(), system);
+                    Value::Object(map)
+                })
+        }
+
+        fn assert_round_trips(request: &CompletionRequest) -> Result<(), TestCaseError> {
+            let encoded = serde_json::to_string(request)
+                .map_err(|e| TestCaseError::fail(format!("serialize: {e}")))?;
+            let decoded: CompletionRequest = serde_json::from_str(&encoded)
+                .map_err(|e| TestCaseError::fail(format!("reparse: {e}\n{encoded}")))?;
+            prop_assert_eq!(serde_json::to_string(&decoded).unwrap(), encoded);
+            Ok(())
+        }
+
+        proptest! {
+            #[test]
+            fn arbitrary_json_never_panics(value in json_value()) {
+                let _ = serde_json::from_value::<CompletionRequest>(value);
+            }
+
+            #[test]
+            fn arbitrary_text_never_panics(body in ".{0,256}") {
+                let _ = serde_json::from_str::<CompletionRequest>(&body);
+            }
+
+            #[test]
+            fn parsed_requests_round_trip(payload in request_payload()) {
+                if let Ok(request) = serde_json::from_value::<CompletionRequest>(payload) {
+                    assert_round_trips(&request)?;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_server_tools_round_trip() {
+        let body = serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "max_tokens": 1024,
+            "tools": [
+                {"name": "read_file", "description": "Read a file",
+                 "input_schema": {"type": "object"}, "cache_control": {"type": "ephemeral"}},
+                {"type": "web_search_20250305", "name": "web_search", "max_uses": 5}
+            ],
+            "messages": [
+                {"role": "user", "content": "What changed in tokio 1.40?"},
+                {"role": "assistant", "content": [
+                    {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search",
+                     "input": {"query": "tokio 1.40 changelog"}},
+                    {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1",
+                     "content": [{"type": "web_search_result", "url": "https://tokio.rs"}]},
+                    {"type": "text", "text": "It added..."}
+                ]},
+                {"role": "user", "content": "Thanks"}
+            ]
+        });
+        let request: CompletionRequest = serde_json::from_value(body.clone()).unwrap();
+        assert_eq!(request.server_tools(), vec!["web_search"]);
+        assert!(!request.tools[0].is_server_tool());
+        let blocks = request.messages[1].content.blocks();
+        assert!(matches!(&blocks[1], ContentBlock::ServerToolResult(r)
+            if r.kind == "web_search_tool_result" && r.tool_use_id == "srvtoolu_1"));
+
+        // Everything reaches the provider as sent
+        let forwarded = serde_json::to_value(&request).unwrap();
+        assert_eq!(forwarded["tools"], body["tools"]);
+        assert_eq!(forwarded["messages"], body["messages"]);
+    }
 }
@@ -0,0 +1,65 @@
+/*
+ * C ABI for the muninn code graph (libmuninn_graph_ffi).
+ *
+ * Strings in are NUL-terminated UTF-8. Strings out are JSON owned by the
+ * caller; release them with muninn_string_free(). On failure a function
+ * returns NULL (or -1) and muninn_last_error() describes why.
+ *
+ * A handle may be used from any thread, one call at a time.
+ */
+
+#ifndef MUNINN_GRAPH_H
+#define MUNINN_GRAPH_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MUNINN_GRAPH_ABI_VERSION 1
+
+typedef struct MuninnGraph MuninnGraph;
+
+typedef struct MuninnBuildStats {
+    uint64_t files_processed;
+    uint64_t nodes_added;
+    uint64_t edges_added;
+} MuninnBuildStats;
+
+/* ABI version of the loaded library; compare with MUNINN_GRAPH_ABI_VERSION. */
+uint32_t muninn_graph_abi_version(void);
+
+/* Open (or create) a graph database, e.g. ".muninn/graph.db". */
+MuninnGraph *muninn_graph_open(const char *path);
+
+/* Open for exclusive writing; fails while another process holds the lock. */
+MuninnGraph *muninn_graph_open_for_write(const char *path);
+
+/* Close a handle. NULL is ignored. */
+void muninn_graph_close(MuninnGraph *graph);
+
+/* Run a Cypher query. Returns a JSON array of row objects keyed by column. */
+char *muninn_graph_query(MuninnGraph *graph, const char *cypher);
+
+/* Symbols with exactly this name, as a JSON array of nodes. */
+char *muninn_graph_find_symbols(MuninnGraph *graph, const char *name);
+
+/* {"node_count": N, "edge_count": N} */
+char *muninn_graph_stats(MuninnGraph *graph);
+
+/* Index every supported file under root. Returns 0 and fills stats (may be
+ * NULL) on success, -1 on failure. Needs a muninn_graph_open_for_write handle. */
+int32_t muninn_graph_build(MuninnGraph *graph, const char *root, MuninnBuildStats *stats);
+
+/* Last error on this thread, or NULL. Valid until the next call; do not free. */
+const char *muninn_last_error(void);
+
+/* Free a string returned by this library. NULL is ignored. */
+void muninn_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MUNINN_GRAPH_H */
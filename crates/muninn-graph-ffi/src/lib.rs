@@ -0,0 +1,428 @@
+//! C ABI for the muninn code graph.
+//!
+//! Lets editor plugins in other languages open `.muninn/graph.db`, run
+//! Cypher queries and (re)build the index in-process instead of spawning
+//! the CLI. The header is `include/muninn_graph.h`.
+//!
+//! Conventions:
+//!
+//! - Strings in are NUL-terminated UTF-8; strings out are JSON, owned by the
+//!   caller and released with [`muninn_string_free`].
+//! - Failures return `NULL` (or a negative status) and leave a message for
+//!   [`muninn_last_error`] on the calling thread.
+//! - A [`MuninnGraph`] handle may be used from any thread, one call at a
+//!   time.
+//!
+//! [`MUNINN_GRAPH_ABI_VERSION`] is bumped on any incompatible change.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::Path;
+
+use muninn_graph::{GraphBuilder, GraphStore, value_to_json};
+
+/// Version of this ABI, returned by [`muninn_graph_abi_version`].
+pub const MUNINN_GRAPH_ABI_VERSION: u32 = 1;
+
+/// Opaque handle to an open graph store.
+pub struct MuninnGraph {
+    // `None` only while a build has the store checked out
+    store: Option<GraphStore>,
+    // Opened with `muninn_graph_open_for_write`, holding the write lock
+    writable: bool,
+}
+
+/// Counts from [`muninn_graph_build`].
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MuninnBuildStats {
+    pub files_processed: u64,
+    pub nodes_added: u64,
+    pub edges_added: u64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Run `f`, turning errors and panics into `None` plus a last-error message.
+fn guard<T>(f: impl FnOnce() -> Result<T, String>) -> Option<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(message)) => {
+            set_last_error(message);
+            None
+        }
+        Err(_) => {
+            set_last_error("internal error (panic)");
+            None
+        }
+    }
+}
+
+/// # Safety
+///
+/// `s` must be NULL or a valid NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{} is NULL", name));
+    }
+    // SAFETY: non-null and NUL-terminated per the caller's contract
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| format!("{} is not UTF-8", name))
+}
+
+/// # Safety
+///
+/// `graph` must be NULL or a live handle from `muninn_graph_open*`.
+unsafe fn store_arg<'a>(graph: *mut MuninnGraph) -> Result<&'a mut GraphStore, String> {
+    // SAFETY: live handle per the caller's contract
+    unsafe { graph.as_mut() }
+        .ok_or("graph is NULL")?
+        .store
+        .as_mut()
+        .ok_or_else(|| "graph is being rebuilt".to_string())
+}
+
+fn json_out(value: serde_json::Value) -> Result<*mut c_char, String> {
+    CString::new(value.to_string())
+        .map(CString::into_raw)
+        .map_err(|e| e.to_string())
+}
+
+fn open_with(
+    path: *const c_char,
+    open: fn(&Path) -> muninn_graph::store::Result<GraphStore>,
+    writable: bool,
+) -> *mut MuninnGraph {
+    guard(|| {
+        // SAFETY: forwarded from the exported function's contract
+        let path = unsafe { str_arg(path, "path") }?;
+        let store = open(Path::new(path)).map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(MuninnGraph {
+            store: Some(store),
+            writable,
+        })))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// A store checked out of its handle for a build. Dropping it puts the
+/// store back, whether the build succeeded, failed or panicked.
+struct CheckedOut<'a> {
+    slot: &'a mut Option<GraphStore>,
+    builder: Option<GraphBuilder>,
+}
+
+impl CheckedOut<'_> {
+    fn builder(&mut self) -> &mut GraphBuilder {
+        self.builder.as_mut().expect("builder present until drop")
+    }
+}
+
+impl Drop for CheckedOut<'_> {
+    fn drop(&mut self) {
+        if let Some(builder) = self.builder.take() {
+            *self.slot = Some(builder.into_store());
+        }
+    }
+}
+
+/// The ABI version this library implements.
+#[unsafe(no_mangle)]
+pub extern "C" fn muninn_graph_abi_version() -> u32 {
+    MUNINN_GRAPH_ABI_VERSION
+}
+
+/// Open (or create) a graph database for reading. Builds need a handle
+/// from [`muninn_graph_open_for_write`].
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_open(path: *const c_char) -> *mut MuninnGraph {
+    open_with(path, |p| GraphStore::open(p), false)
+}
+
+/// Open a graph database for exclusive writing, failing if another
+/// process (e.g. `muninn index`) holds the write lock.
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_open_for_write(path: *const c_char) -> *mut MuninnGraph {
+    open_with(path, |p| GraphStore::open_for_write(p), true)
+}
+
+/// Close a handle. NULL is ignored.
+///
+/// # Safety
+///
+/// `graph` must be NULL or a handle not yet closed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_close(graph: *mut MuninnGraph) {
+    if !graph.is_null() {
+        // SAFETY: created by Box::into_raw in open_with, closed once
+        drop(unsafe { Box::from_raw(graph) });
+    }
+}
+
+/// Run a Cypher query. Returns a JSON array with one object per row,
+/// keyed by column.
+///
+/// # Safety
+///
+/// `graph` must be a live handle and `cypher` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_query(
+    graph: *mut MuninnGraph,
+    cypher: *const c_char,
+) -> *mut c_char {
+    guard(|| {
+        // SAFETY: per this function's contract
+        let (store, cypher) = unsafe { (store_arg(graph)?, str_arg(cypher, "cypher")?) };
+        let result = store.query(cypher).map_err(|e| e.to_string())?;
+        let rows: Vec<serde_json::Value> = result
+            .iter()
+            .map(|row| {
+                row.columns()
+                    .iter()
+                    .filter_map(|col| row.get_value(col).map(|v| (col.clone(), value_to_json(v))))
+                    .collect::<serde_json::Map<_, _>>()
+                    .into()
+            })
+            .collect();
+        json_out(rows.into())
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Find symbols with exactly this name. Returns a JSON array of nodes.
+///
+/// # Safety
+///
+/// `graph` must be a live handle and `name` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_find_symbols(
+    graph: *mut MuninnGraph,
+    name: *const c_char,
+) -> *mut c_char {
+    guard(|| {
+        // SAFETY: per this function's contract
+        let (store, name) = unsafe { (store_arg(graph)?, str_arg(name, "name")?) };
+        let nodes = store.find_by_name(name).map_err(|e| e.to_string())?;
+        json_out(nodes.iter().map(value_to_json).collect())
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Node and edge counts as `{"node_count": .., "edge_count": ..}`.
+///
+/// # Safety
+///
+/// `graph` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_stats(graph: *mut MuninnGraph) -> *mut c_char {
+    guard(|| {
+        // SAFETY: per this function's contract
+        let store = unsafe { store_arg(graph) }?;
+        let stats = store.stats().map_err(|e| e.to_string())?;
+        json_out(serde_json::json!({
+            "node_count": stats.node_count,
+            "edge_count": stats.edge_count,
+        }))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Index every supported file under `root` into the graph. Returns 0 and
+/// fills `stats` (if non-NULL) on success, -1 on failure. The handle must
+/// come from [`muninn_graph_open_for_write`].
+///
+/// # Safety
+///
+/// `graph` must be a live handle, `root` a valid NUL-terminated string, and
+/// `stats` NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_graph_build(
+    graph: *mut MuninnGraph,
+    root: *const c_char,
+    stats: *mut MuninnBuildStats,
+) -> i32 {
+    let built = guard(|| {
+        // SAFETY: per this function's contract
+        let (handle, root) = unsafe {
+            (
+                graph.as_mut().ok_or("graph is NULL")?,
+                str_arg(root, "root")?,
+            )
+        };
+        if !handle.writable {
+            return Err(
+                "graph is open read-only; build through muninn_graph_open_for_write".into(),
+            );
+        }
+        // Set the builder up around a scratch store, so failing to create
+        // it leaves the handle's store where it is
+        let scratch = GraphStore::open_in_memory().map_err(|e| e.to_string())?;
+        let mut builder = GraphBuilder::new(scratch).map_err(|e| e.to_string())?;
+        *builder.store_mut() = handle.store.take().ok_or("graph is being rebuilt")?;
+        let mut checked_out = CheckedOut {
+            slot: &mut handle.store,
+            builder: Some(builder),
+        };
+        checked_out
+            .builder()
+            .build_directory(Path::new(root))
+            .map_err(|e| e.to_string())
+    });
+    match built {
+        Some(built) => {
+            // SAFETY: NULL or valid for writes per this function's contract
+            if let Some(out) = unsafe { stats.as_mut() } {
+                *out = MuninnBuildStats {
+                    files_processed: built.files_processed as u64,
+                    nodes_added: built.nodes_added as u64,
+                    edges_added: built.edges_added as u64,
+                };
+            }
+            0
+        }
+        None => -1,
+    }
+}
+
+/// The last error on this thread, or NULL. Valid until the next call on
+/// the same thread; do not free.
+#[unsafe(no_mangle)]
+pub extern "C" fn muninn_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Free a string returned by this library. NULL is ignored.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string from this library, not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn muninn_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: created by CString::into_raw in json_out, freed once
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    /// Take ownership of a returned JSON string.
+    unsafe fn take_json(s: *mut c_char) -> serde_json::Value {
+        assert!(!s.is_null(), "{:?}", last_error());
+        let value = serde_json::from_str(unsafe { CStr::from_ptr(s) }.to_str().unwrap()).unwrap();
+        unsafe { muninn_string_free(s) };
+        value
+    }
+
+    fn last_error() -> Option<String> {
+        let e = muninn_last_error();
+        (!e.is_null()).then(|| unsafe { CStr::from_ptr(e) }.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_build_then_query() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src");
+        std::fs::create_dir(&src).unwrap();
+        std::fs::write(
+            src.join("lib.rs"),
+            "pub fn helper() {}\npub fn entry() { helper(); }\n",
+        )
+        .unwrap();
+        let db = c(temp.path().join("graph.db").to_str().unwrap());
+
+        unsafe {
+            let graph = muninn_graph_open_for_write(db.as_ptr());
+            assert!(!graph.is_null(), "{:?}", last_error());
+            let mut stats = MuninnBuildStats::default();
+            let root = c(src.to_str().unwrap());
+            assert_eq!(muninn_graph_build(graph, root.as_ptr(), &mut stats), 0);
+            assert_eq!(stats.files_processed, 1);
+            assert!(stats.nodes_added >= 2);
+
+            let found = take_json(muninn_graph_find_symbols(graph, c("helper").as_ptr()));
+            assert_eq!(found.as_array().unwrap().len(), 1);
+
+            let rows = take_json(muninn_graph_query(
+                graph,
+                c("MATCH (n {name: 'entry'}) RETURN n.name AS name").as_ptr(),
+            ));
+            assert_eq!(rows, serde_json::json!([{"name": "entry"}]));
+
+            let counts = take_json(muninn_graph_stats(graph));
+            assert!(counts["node_count"].as_i64().unwrap() >= 2);
+            muninn_graph_close(graph);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        unsafe {
+            assert!(muninn_graph_query(std::ptr::null_mut(), c("x").as_ptr()).is_null());
+            assert_eq!(last_error().as_deref(), Some("graph is NULL"));
+
+            let graph = muninn_graph_open(c(":memory:").as_ptr());
+            assert!(!graph.is_null());
+            assert!(muninn_graph_query(graph, c("NOT CYPHER").as_ptr()).is_null());
+            assert!(last_error().is_some());
+            muninn_graph_close(graph);
+        }
+        assert_eq!(muninn_graph_abi_version(), MUNINN_GRAPH_ABI_VERSION);
+    }
+
+    #[test]
+    fn test_failed_builds_keep_the_store() {
+        let temp = tempfile::tempdir().unwrap();
+        let db = c(temp.path().join("graph.db").to_str().unwrap());
+        let missing = c(temp.path().join("missing").to_str().unwrap());
+
+        unsafe {
+            let reader = muninn_graph_open(db.as_ptr());
+            assert!(!reader.is_null(), "{:?}", last_error());
+            assert_eq!(
+                muninn_graph_build(reader, missing.as_ptr(), std::ptr::null_mut()),
+                -1
+            );
+            assert!(last_error().unwrap().contains("read-only"));
+            assert_eq!(take_json(muninn_graph_stats(reader))["node_count"], 0);
+            muninn_graph_close(reader);
+
+            let writer = muninn_graph_open_for_write(db.as_ptr());
+            assert!(!writer.is_null(), "{:?}", last_error());
+            assert_eq!(
+                muninn_graph_build(writer, missing.as_ptr(), std::ptr::null_mut()),
+                -1
+            );
+            let counts = take_json(muninn_graph_stats(writer));
+            assert_eq!(counts["node_count"], 0, "{:?}", last_error());
+            muninn_graph_close(writer);
+        }
+    }
+}
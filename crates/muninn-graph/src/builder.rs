@@ -9,19 +9,34 @@
 //! `CallNode`/`CallEdge` into our `Symbol`/`Edge` types and
 //! persist them through [`GraphStore`].
 //!
+//! Runs are incremental: the store keeps a content hash per file, and
+//! files whose hash still matches are neither parsed nor rewritten.
+//!
 //! What this module does NOT do anymore: hand-written tree-sitter
 //! queries, manual cross-file resolution, SCIP ingest. All of that
 //! was removed when we vendored narsil — see
 //! `crates/muninn-narsil-vendor/NOTICE.md`.
 
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use muninn_narsil_vendor::callgraph::{CallGraph, CallNode};
 use muninn_narsil_vendor::parser::LanguageParser;
 use muninn_narsil_vendor::tree_sitter::Tree;
+use sha2::{Digest, Sha256};
 
+use crate::cfamily;
+use crate::csharp;
 use crate::edges::{CallType, Edge, EdgeKind};
-use crate::store::{GraphStore, StoreError};
+use crate::index_queue::IndexQueue;
+use crate::java;
+use crate::kotlin;
+use crate::language::{LanguageMap, SourceKind};
+use crate::sensitivity::scan_source;
+use crate::store::{GraphStore, IncomingEdge, StoreError, node_line, node_str};
 use crate::symbols::{Symbol, SymbolKind, Visibility};
 
 /// Error type for graph building operations.
@@ -33,6 +48,8 @@ pub enum BuildError {
     Io(#[from] std::io::Error),
     #[error("extractor: {0}")]
     Extractor(String),
+    #[error("ignore rules: {0}")]
+    Ignore(#[from] ignore::Error),
 }
 
 impl From<anyhow::Error> for BuildError {
@@ -49,18 +66,177 @@ pub struct BuildStats {
     pub files_processed: usize,
     pub nodes_added: usize,
     pub edges_added: usize,
+    /// Files left out for exceeding [`BuildLimits::max_file_bytes`].
+    pub skipped: Vec<SkippedFile>,
+    /// Files (or directories) that could not be read or parsed. The run
+    /// carries on without them.
+    pub errors: Vec<FileError>,
+    /// Files left as they were because their content hash matched the store.
+    pub unchanged: usize,
+    /// Files dropped from the store because they no longer exist.
+    pub removed: usize,
+    /// Files parsed this run per grammar, or per format for files stored
+    /// as a bare node (`"dockerfile"`, `"shell"`, ...).
+    pub languages: BTreeMap<&'static str, usize>,
+}
+
+/// Why one path was left out of an otherwise successful run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileError {
+    pub path: String,
+    pub reason: String,
+}
+
+/// A supported file the builder did not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub path: String,
+    pub bytes: u64,
+    /// Counted without loading the file.
+    pub lines: usize,
+}
+
+/// Memory bounds for one [`GraphBuilder::build_directory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildLimits {
+    /// Files larger than this are skipped (typically generated code or
+    /// bundles) and listed in [`BuildStats::skipped`].
+    pub max_file_bytes: u64,
+    /// Source bytes held in parsed form before the files so far are
+    /// persisted and dropped. Calls between files on either side of such
+    /// a flush are not resolved, so the default is meant to be hit only
+    /// on very large trees.
+    pub max_parsed_bytes: u64,
 }
 
+impl Default for BuildLimits {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 2 * 1024 * 1024,
+            max_parsed_bytes: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// Which files under the root [`GraphBuilder::build_directory`] leaves
+/// out. Hidden, `target` and `node_modules` directories are always skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreRules {
+    /// Honour `.gitignore`, `.ignore` and `.git/info/exclude` files the way
+    /// ripgrep does, also outside a git repository.
+    pub gitignore: bool,
+    /// Extra gitignore-style patterns, relative to the root
+    /// (e.g. `vendor/`, `*.generated.rs`).
+    pub exclude: Vec<String>,
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self {
+            gitignore: true,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl IgnoreRules {
+    /// Matcher for [`exclude`](Self::exclude), anchored at `root`.
+    fn exclude_matcher(&self, root: &Path) -> Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &self.exclude {
+            builder.add_line(None, pattern)?;
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// How far an indexing run has got, reported after each parsed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct IndexProgress {
+    /// Files parsed so far.
+    pub files_done: usize,
+    /// Files whose symbols are already in the store. Non-zero before the
+    /// end only when a priority batch or a memory flush has been persisted.
+    pub files_indexed: usize,
+    /// Supported files found under the root.
+    pub files_total: usize,
+    /// Time since the walk started.
+    pub elapsed: Duration,
+}
+
+impl IndexProgress {
+    /// Estimated time left at the rate so far; `None` before the first file.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.files_done == 0 {
+            return None;
+        }
+        let remaining = self.files_total.saturating_sub(self.files_done) as u32;
+        Some(self.elapsed / self.files_done as u32 * remaining)
+    }
+}
+
+type ProgressFn = Box<dyn Fn(&IndexProgress) + Send + Sync>;
+
 /// Walks source, drives the vendored extractor, persists to the store.
 pub struct GraphBuilder {
     parser: LanguageParser,
     store: GraphStore,
+    progress: Option<ProgressFn>,
+    queue: Option<IndexQueue>,
+    limits: BuildLimits,
+    ignore: IgnoreRules,
+    languages: LanguageMap,
 }
 
 impl GraphBuilder {
     pub fn new(store: GraphStore) -> Result<Self> {
         let parser = LanguageParser::new().map_err(BuildError::from)?;
-        Ok(Self { parser, store })
+        Ok(Self {
+            parser,
+            store,
+            progress: None,
+            queue: None,
+            limits: BuildLimits::default(),
+            ignore: IgnoreRules::default(),
+            languages: LanguageMap::default(),
+        })
+    }
+
+    /// Call `f` after each file is parsed. Persisting the call graph
+    /// happens after the last report.
+    pub fn with_progress(mut self, f: impl Fn(&IndexProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Between files, index the directories hinted on `queue` first and
+    /// persist each as its own batch, so they are queryable before the
+    /// whole tree is done. The final pass still resolves calls across
+    /// every file.
+    pub fn with_queue(mut self, queue: IndexQueue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Replace the default [`BuildLimits`].
+    pub fn with_limits(mut self, limits: BuildLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Replace the default [`IgnoreRules`]. Files they leave out are
+    /// dropped from the store on the next build, like deleted ones.
+    pub fn with_ignore_rules(mut self, rules: IgnoreRules) -> Self {
+        self.ignore = rules;
+        self
+    }
+
+    /// Replace the default extension-to-grammar [`LanguageMap`]. Files of
+    /// extensions it no longer maps are dropped from the store on the next
+    /// build.
+    pub fn with_languages(mut self, languages: LanguageMap) -> Self {
+        self.languages = languages;
+        self
     }
 
     pub fn store(&self) -> &GraphStore {
@@ -75,58 +251,432 @@ impl GraphBuilder {
         self.store
     }
 
-    /// Index every supported file under `root`. Returns counts.
+    /// Index the supported files under `root` that changed since the last
+    /// build into this store. Returns counts.
+    ///
+    /// Files whose content hash matches the one the store recorded keep
+    /// their nodes and are not parsed; changed and deleted files lose
+    /// theirs first. Calls from the parsed files into the others resolve
+    /// against the store, and edges from the others into a re-indexed file
+    /// are attached again by target name. A change to a C, C++ or Java file
+    /// re-parses every file of that family, as those passes resolve across
+    /// files. A call from an unchanged file that had nothing to resolve to
+    /// stays unresolved until that file changes, even once its target is
+    /// added; a build into an empty store resolves everything.
     pub fn build_directory(&mut self, root: &Path) -> Result<BuildStats> {
-        let parsed_files = self.collect_parsed_files(root)?;
-        self.persist_call_graph(&parsed_files)
+        let mut stats = BuildStats::default();
+        let walked = if root.is_file() {
+            vec![root.to_path_buf()]
+        } else {
+            let mut paths = Vec::new();
+            walk_directory(
+                root,
+                &self.ignore,
+                &self.languages,
+                &mut paths,
+                &mut stats.errors,
+            )?;
+            paths
+        };
+        let plan = self.plan(root, walked, &mut stats)?;
+        let mut stored = if plan.partial {
+            Some(self.stored_functions()?)
+        } else {
+            None
+        };
+        let mut pending: VecDeque<PathBuf> = plan.parse.into();
+        let mut run = Run {
+            started: Instant::now(),
+            files_total: pending.len(),
+            files_done: 0,
+            files_indexed: 0,
+            bytes_held: 0,
+        };
+
+        let mut parsed = Vec::new();
+        // File -> priority batch it was persisted in.
+        let mut batches: HashMap<String, usize> = HashMap::new();
+        loop {
+            if run.bytes_held > self.limits.max_parsed_bytes {
+                tracing::debug!(
+                    files = parsed.len(),
+                    bytes = run.bytes_held,
+                    "Parsed-source ceiling reached; persisting"
+                );
+                self.flush(
+                    &mut parsed,
+                    &mut batches,
+                    &mut stats,
+                    &mut run,
+                    stored.as_mut(),
+                )?;
+                self.report(&run);
+            }
+            if let Some(dir) = self.next_hint(root) {
+                let (hot, rest): (VecDeque<_>, VecDeque<_>) =
+                    pending.into_iter().partition(|p| p.starts_with(&dir));
+                pending = rest;
+                if hot.is_empty() {
+                    continue;
+                }
+                let start = parsed.len();
+                for path in &hot {
+                    self.parse_into(path, &mut parsed, &mut stats, &mut run)?;
+                }
+                let batch = &parsed[start..];
+                let batch_stats =
+                    self.persist_call_graph(batch, &HashMap::new(), stored.as_mut())?;
+                tracing::debug!(
+                    dir = %dir.display(),
+                    files = batch.len(),
+                    nodes = batch_stats.nodes_added,
+                    "Indexed priority batch"
+                );
+                let id = batches.values().max().map_or(0, |n| n + 1);
+                batches.extend(batch.iter().map(|(path, _, _)| (path.clone(), id)));
+                stats.nodes_added += batch_stats.nodes_added;
+                stats.edges_added += batch_stats.edges_added;
+                run.files_indexed += batch.len();
+                self.report(&run);
+                continue;
+            }
+            let Some(path) = pending.pop_front() else {
+                break;
+            };
+            self.parse_into(&path, &mut parsed, &mut stats, &mut run)?;
+        }
+
+        self.flush(
+            &mut parsed,
+            &mut batches,
+            &mut stats,
+            &mut run,
+            stored.as_mut(),
+        )?;
+        stats.edges_added += self.relink(&plan.incoming)?;
+
+        // Files that failed are tried again next time
+        let failed: HashSet<&str> = stats
+            .skipped
+            .iter()
+            .map(|s| s.path.as_str())
+            .chain(stats.errors.iter().map(|e| e.path.as_str()))
+            .collect();
+        let hashes: Vec<(String, String)> = plan
+            .hashes
+            .into_iter()
+            .filter(|(path, _)| !failed.contains(path.as_str()))
+            .collect();
+        self.store.set_file_hashes(&hashes)?;
+        Ok(stats)
+    }
+
+    /// Compare the walked files with the content hashes in the store, drop
+    /// the nodes of changed and deleted files, and return what to parse.
+    fn plan(&self, root: &Path, walked: Vec<PathBuf>, stats: &mut BuildStats) -> Result<Plan> {
+        let stored = self.store.file_hashes()?;
+        let mut hashes = Vec::new();
+        let mut dirty: HashSet<String> = HashSet::new();
+        for path in &walked {
+            let key = path.to_string_lossy().to_string();
+            // Oversized and unreadable files get no hash and are reported
+            // by the parse; oversized ones aren't read at all
+            let hash = match std::fs::metadata(path) {
+                Ok(meta) if meta.len() > self.limits.max_file_bytes => None,
+                _ => hash_file(path).ok(),
+            };
+            if hash.is_some() && stored.get(&key) == hash.as_ref() {
+                continue;
+            }
+            if let Some(hash) = hash {
+                hashes.push((key.clone(), hash));
+            }
+            dirty.insert(key);
+        }
+        let walked_keys: HashSet<String> = walked
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let removed: HashSet<String> = stored
+            .keys()
+            .filter(|p| Path::new(p).starts_with(root) && !walked_keys.contains(*p))
+            .cloned()
+            .collect();
+
+        let families: [fn(&LanguageMap, &str) -> bool; 4] = [
+            cfamily::is_c_family,
+            java::is_java,
+            kotlin::is_kotlin,
+            csharp::is_csharp,
+        ];
+        for is_member in families {
+            let in_family = |path: &str| is_member(&self.languages, path);
+            if !dirty.iter().chain(&removed).any(|p| in_family(p)) {
+                continue;
+            }
+            for path in walked_keys.iter().filter(|p| in_family(p)) {
+                if dirty.insert(path.clone())
+                    && let Some(hash) = stored.get(path)
+                {
+                    hashes.push((path.clone(), hash.clone()));
+                }
+            }
+        }
+
+        let parse: Vec<PathBuf> = walked
+            .into_iter()
+            .filter(|p| dirty.contains(p.to_string_lossy().as_ref()))
+            .collect();
+        stats.unchanged = walked_keys.len() - parse.len();
+        stats.removed = removed.len();
+        let partial = stats.unchanged > 0;
+
+        let existing = self.store.file_paths()?;
+        let replaced: Vec<&String> = dirty
+            .iter()
+            .chain(&removed)
+            .filter(|p| existing.contains(*p))
+            .collect();
+        let mut incoming = Vec::new();
+        if partial {
+            for file in &replaced {
+                incoming.extend(self.store.edges_into_file(file)?);
+            }
+            incoming.retain(|e: &IncomingEdge| {
+                !dirty.contains(&e.source_file) && !removed.contains(&e.source_file)
+            });
+        }
+        // Forget the hashes before the nodes go, so a run that stops before
+        // they are back parses those files again instead of skipping them.
+        // The build records them again once the files are in.
+        let forgotten: Vec<String> = dirty
+            .iter()
+            .chain(&removed)
+            .filter(|p| stored.contains_key(*p))
+            .cloned()
+            .collect();
+        self.store.remove_file_hashes(&forgotten)?;
+        for file in &replaced {
+            self.store.delete_file(file)?;
+        }
+        if !replaced.is_empty() || partial {
+            tracing::debug!(
+                parse = parse.len(),
+                unchanged = stats.unchanged,
+                removed = stats.removed,
+                incoming = incoming.len(),
+                "Incremental build"
+            );
+        }
+        Ok(Plan {
+            parse,
+            hashes,
+            incoming,
+            partial,
+        })
     }
 
-    fn collect_parsed_files(&self, root: &Path) -> Result<Vec<(String, String, Tree)>> {
-        let mut out = Vec::new();
-        if root.is_file() {
-            if let Some(triple) = self.parse_one(root)? {
-                out.push(triple);
+    /// Attach `edges` to the nodes now in their target files, matched by
+    /// name and kind. Edges whose target is gone are dropped. Returns how
+    /// many were attached.
+    fn relink(&self, edges: &[IncomingEdge]) -> Result<usize> {
+        let mut targets: HashMap<&str, HashMap<(String, String), String>> = HashMap::new();
+        let mut relinked = Vec::new();
+        for edge in edges {
+            let file = edge.target_file.as_str();
+            if !targets.contains_key(file) {
+                let mut by_name = HashMap::new();
+                for node in self.store.find_symbols_in_file(file)? {
+                    if let (Some(id), Some(name), Some(kind)) = (
+                        node_str(&node, "id"),
+                        node_str(&node, "name"),
+                        node_str(&node, "kind"),
+                    ) {
+                        by_name
+                            .entry((name.to_string(), kind.to_string()))
+                            .or_insert_with(|| id.to_string());
+                    }
+                }
+                targets.insert(file, by_name);
+            }
+            let key = (edge.target_name.clone(), edge.target_kind.clone());
+            if let Some(target_id) = targets[file].get(&key) {
+                relinked.push(Edge {
+                    source_id: edge.source_id.clone(),
+                    target_id: target_id.clone(),
+                    kind: edge.kind.clone(),
+                });
             }
-            return Ok(out);
         }
-        self.walk_recursive(root, &mut out)?;
-        Ok(out)
+        self.store.insert_edges_batch_slow(&relinked)?;
+        Ok(relinked.len())
     }
 
-    fn walk_recursive(&self, dir: &Path, out: &mut Vec<(String, String, Tree)>) -> Result<()> {
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                let skip = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .is_some_and(|n| n.starts_with('.') || n == "target" || n == "node_modules");
-                if skip {
+    /// The functions and methods in the store, scanned once per partial
+    /// build.
+    fn stored_functions(&self) -> Result<StoredFunctions> {
+        let mut functions = StoredFunctions::default();
+        for label in ["Function", "Method"] {
+            for node in self
+                .store
+                .query(&format!("MATCH (n:{label}) RETURN n"))?
+                .iter()
+                .filter_map(|r| r.get_value("n"))
+            {
+                let (Some(id), Some(name), Some(file)) = (
+                    node_str(node, "id"),
+                    node_str(node, "name"),
+                    node_str(node, "file_path"),
+                ) else {
                     continue;
-                }
-                self.walk_recursive(&path, out)?;
-            } else if is_supported_source_file(&path) {
-                if let Some(triple) = self.parse_one(&path)? {
+                };
+                functions.add(name, file, node_line(node).unwrap_or(0), id);
+            }
+        }
+        Ok(functions)
+    }
+
+    /// Persist everything parsed so far (minus what priority batches
+    /// already stored) and release it.
+    fn flush(
+        &self,
+        parsed: &mut Vec<(String, String, Tree)>,
+        batches: &mut HashMap<String, usize>,
+        stats: &mut BuildStats,
+        run: &mut Run,
+        stored: Option<&mut StoredFunctions>,
+    ) -> Result<()> {
+        let rest = self.persist_call_graph(parsed, batches, stored)?;
+        stats.files_processed += parsed.len();
+        stats.nodes_added += rest.nodes_added;
+        stats.edges_added += rest.edges_added;
+        run.files_indexed += parsed.len() - batches.len();
+        run.bytes_held = 0;
+        parsed.clear();
+        batches.clear();
+        Ok(())
+    }
+
+    /// Next hinted directory under `root`, resolved to the form the walk
+    /// produces.
+    fn next_hint(&self, root: &Path) -> Option<PathBuf> {
+        let queue = self.queue.as_ref()?;
+        while let Some(hint) = queue.pop() {
+            let path = if hint.is_absolute() {
+                hint
+            } else {
+                root.join(hint)
+            };
+            let dir = if path.is_file() {
+                path.parent().map(Path::to_path_buf)?
+            } else {
+                path
+            };
+            let (Ok(dir_canonical), Ok(root_canonical)) = (dir.canonicalize(), root.canonicalize())
+            else {
+                continue;
+            };
+            if let Ok(rel) = dir_canonical.strip_prefix(&root_canonical) {
+                return Some(root.join(rel));
+            }
+        }
+        None
+    }
+
+    fn parse_into(
+        &self,
+        path: &Path,
+        out: &mut Vec<(String, String, Tree)>,
+        stats: &mut BuildStats,
+        run: &mut Run,
+    ) -> Result<()> {
+        let bytes = std::fs::metadata(path).map_or(0, |m| m.len());
+        if bytes > self.limits.max_file_bytes {
+            let lines = count_lines(path).unwrap_or(0);
+            tracing::debug!(path = %path.display(), bytes, lines, "Skipping oversized file");
+            stats.skipped.push(SkippedFile {
+                path: path.to_string_lossy().to_string(),
+                bytes,
+                lines,
+            });
+        } else {
+            match self.parse_one(path) {
+                Ok(Parsed::Code(language, triple)) => {
+                    *stats.languages.entry(language).or_default() += 1;
+                    run.bytes_held += triple.1.len() as u64;
                     out.push(triple);
                 }
+                Ok(Parsed::FileOnly(label, node)) => {
+                    *stats.languages.entry(label).or_default() += 1;
+                    // Nothing to resolve, so it can go in straight away
+                    self.store.insert_nodes_batch(std::slice::from_ref(&node))?;
+                    stats.files_processed += 1;
+                    stats.nodes_added += 1;
+                    run.files_indexed += 1;
+                }
+                Err(reason) => {
+                    tracing::debug!(path = %path.display(), %reason, "Skipping unparseable file");
+                    stats.errors.push(FileError {
+                        path: path.to_string_lossy().to_string(),
+                        reason,
+                    });
+                }
             }
         }
+        run.files_done += 1;
+        self.report(run);
         Ok(())
     }
 
-    fn parse_one(&self, path: &Path) -> Result<Option<(String, String, Tree)>> {
-        let Ok(content) = std::fs::read_to_string(path) else {
-            return Ok(None); // binary / unreadable — skip silently
+    fn report(&self, run: &Run) {
+        if let Some(progress) = &self.progress {
+            progress(&IndexProgress {
+                files_done: run.files_done,
+                files_indexed: run.files_indexed,
+                files_total: run.files_total,
+                elapsed: run.started.elapsed(),
+            });
+        }
+    }
+
+    /// Read and parse one file, picking the grammar by extension, name or
+    /// `#!` line. An `Err` is the reason to report for it; a panic inside
+    /// tree-sitter or narsil is caught and reported the same way.
+    fn parse_one(&self, path: &Path) -> std::result::Result<Parsed, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let first_line = content.lines().next();
+        let language = match self.languages.detect(path, first_line) {
+            Some(SourceKind::Code(language)) => language,
+            Some(SourceKind::FileOnly(label)) => {
+                return Ok(Parsed::FileOnly(label, file_node(path, &content)));
+            }
+            None => return Err("unsupported file type".to_string()),
         };
-        let tree = match self.parser.parse_to_tree(path, &content) {
-            Ok(t) => t,
-            Err(_) => return Ok(None), // unsupported language for narsil's parser
+        let parsed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.parser.parse_to_tree_as(language, &content)
+        }));
+        let tree = match parsed {
+            Ok(Ok(tree)) => tree,
+            Ok(Err(e)) => return Err(format!("{e:#}")),
+            Err(_) => return Err("parser panicked".to_string()),
         };
-        Ok(Some((path.to_string_lossy().to_string(), content, tree)))
+        Ok(Parsed::Code(
+            language,
+            (path.to_string_lossy().to_string(), content, tree),
+        ))
     }
 
-    fn persist_call_graph(&self, files: &[(String, String, Tree)]) -> Result<BuildStats> {
+    /// Resolve calls across `files` and persist the result, leaving out
+    /// what an earlier priority batch already stored: symbols from files in
+    /// `persisted`, and edges whose ends share one batch. In a partial
+    /// build, calls that resolve to none of `files` are looked up among the
+    /// `stored` functions, which then take in the ones persisted here.
+    fn persist_call_graph(
+        &self,
+        files: &[(String, String, Tree)],
+        persisted: &HashMap<String, usize>,
+        stored: Option<&mut StoredFunctions>,
+    ) -> Result<BuildStats> {
         let cg = CallGraph::new();
         cg.build_from_files(files).map_err(BuildError::from)?;
 
@@ -136,11 +686,9 @@ impl GraphBuilder {
         // both for the node lookup AND for `CallEdge.target` / `.called_by`.
         // Map that qualified KEY (the entry's key, not `CallNode.name`)
         // to our Symbol::id() so CallEdge.target lookups hit.
-        let mut qkey_to_id: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
+        let mut qkeys: Vec<String> = Vec::new();
 
         for entry in cg.iter_nodes() {
-            let qkey = entry.key().clone();
             let node: &CallNode = entry.value();
             // call_degree = inbound + outbound edges. This is the same
             // signal narsil's `get_hotspots` uses to rank functions —
@@ -148,11 +696,21 @@ impl GraphBuilder {
             // `graph_query` cheaply find the load-bearing code without
             // a second pass.
             let degree = node.calls.len() + node.called_by.len();
-            let sym = call_node_to_symbol(node, degree);
-            qkey_to_id.insert(qkey, sym.id());
-            symbols.push(sym);
+            qkeys.push(entry.key().clone());
+            symbols.push(call_node_to_symbol(node, degree));
         }
 
+        // Java and C# methods and Kotlin functions get qualified (and
+        // members become methods), which changes their ids, so these
+        // passes run before the ids are taken.
+        let java = java::extract(files, &mut symbols, &self.languages);
+        let kotlin = kotlin::extract(files, &mut symbols, &self.languages);
+        let csharp = csharp::extract(files, &mut symbols, &self.languages);
+        let qkey_to_id: std::collections::HashMap<String, String> = qkeys
+            .into_iter()
+            .zip(symbols.iter().map(Symbol::id))
+            .collect();
+
         for entry in cg.iter_nodes() {
             let qkey = entry.key();
             let node: &CallNode = entry.value();
@@ -160,7 +718,10 @@ impl GraphBuilder {
                 continue;
             };
             for ce in &node.calls {
-                let Some(target_id) = qkey_to_id.get(&ce.target).cloned() else {
+                let resolved = qkey_to_id
+                    .get(&ce.target)
+                    .or_else(|| stored.as_deref().and_then(|stored| stored.get(&ce.target)));
+                let Some(target_id) = resolved.cloned() else {
                     // Narsil's resolver couldn't pin this callee to a
                     // workspace symbol (commonly: stdlib / dep / extern).
                     // Skipping is the right move — graphqlite would drop
@@ -178,15 +739,48 @@ impl GraphBuilder {
             }
         }
 
+        tag_sensitivity(&mut symbols, files);
+
+        let c_family = cfamily::extract(files, &symbols, &self.languages);
+        symbols.extend(c_family.symbols);
+        edges.extend(c_family.edges);
+        symbols.extend(java.symbols);
+        edges.extend(java.edges);
+        symbols.extend(kotlin.symbols);
+        edges.extend(kotlin.edges);
+        symbols.extend(csharp.symbols);
+        edges.extend(csharp.edges);
+
+        if !persisted.is_empty() {
+            let batch_of: HashMap<String, Option<usize>> = symbols
+                .iter()
+                .map(|sym| (sym.id(), persisted.get(&sym.file_path).copied()))
+                .collect();
+            edges.retain(|edge| {
+                let source = batch_of.get(&edge.source_id).copied().flatten();
+                let target = batch_of.get(&edge.target_id).copied().flatten();
+                source.is_none() || source != target
+            });
+            symbols.retain(|sym| !persisted.contains_key(&sym.file_path));
+        }
+
         let mut stats = BuildStats {
             files_processed: files.len(),
             nodes_added: 0,
             edges_added: 0,
+            ..BuildStats::default()
         };
         if !symbols.is_empty() {
             self.store.insert_nodes_batch(&symbols)?;
             stats.nodes_added = symbols.len();
         }
+        if let Some(stored) = stored {
+            for sym in &symbols {
+                if matches!(sym.kind, SymbolKind::Function | SymbolKind::Method) {
+                    stored.add(&sym.name, &sym.file_path, sym.start_line, &sym.id());
+                }
+            }
+        }
         if !edges.is_empty() {
             self.store.insert_edges_batch_slow(&edges)?;
             stats.edges_added = edges.len();
@@ -195,11 +789,237 @@ impl GraphBuilder {
     }
 }
 
-fn is_supported_source_file(path: &Path) -> bool {
-    matches!(
-        path.extension().and_then(|e| e.to_str()),
-        Some("rs" | "py" | "js" | "ts" | "tsx" | "jsx" | "go" | "c" | "cpp" | "h" | "hpp" | "java")
-    )
+/// What one [`GraphBuilder::build_directory`] has left to do.
+struct Plan {
+    /// Files to parse, in walk order.
+    parse: Vec<PathBuf>,
+    /// Content hashes of the files to parse, recorded once they are in.
+    hashes: Vec<(String, String)>,
+    /// Edges into re-indexed files from files left alone.
+    incoming: Vec<IncomingEdge>,
+    /// Whether some files were left alone.
+    partial: bool,
+}
+
+/// Functions and methods already in the store, by name, for resolving
+/// calls in a partial build. A shared name resolves to the first by path
+/// and line (narsil's fallback for an ambiguous callee).
+#[derive(Default)]
+struct StoredFunctions {
+    /// Name -> file, line and id of the first candidate.
+    first: HashMap<String, (String, usize, String)>,
+}
+
+impl StoredFunctions {
+    fn add(&mut self, name: &str, file: &str, line: usize, id: &str) {
+        let candidate = (file.to_string(), line, id.to_string());
+        let first = self
+            .first
+            .entry(name.to_string())
+            .or_insert_with(|| candidate.clone());
+        if candidate < *first {
+            *first = candidate;
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&String> {
+        self.first.get(name).map(|(_, _, id)| id)
+    }
+}
+
+/// Hex SHA-256 of the content of `path`, read in chunks.
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A file read by [`GraphBuilder::parse_one`].
+enum Parsed {
+    /// Grammar, then path, source and tree for call-graph resolution.
+    Code(&'static str, (String, String, Tree)),
+    /// A file without a grammar, with its format label, already turned
+    /// into its node.
+    FileOnly(&'static str, Symbol),
+}
+
+/// A [`SymbolKind::File`] node spanning all of `content`. A `#!` line is
+/// kept as the signature.
+pub(crate) fn file_node(path: &Path, content: &str) -> Symbol {
+    let file_path = path.to_string_lossy().to_string();
+    Symbol {
+        name: path
+            .file_name()
+            .map_or_else(|| file_path.clone(), |n| n.to_string_lossy().to_string()),
+        kind: SymbolKind::File,
+        start_line: 1,
+        end_line: content.lines().count().max(1),
+        signature: content
+            .lines()
+            .next()
+            .filter(|l| l.starts_with("#!"))
+            .map(str::to_string),
+        qualified_name: None,
+        doc_comment: None,
+        visibility: Visibility::Public,
+        cyclomatic: None,
+        cognitive: None,
+        call_degree: None,
+        sensitivity: Vec::new(),
+        file_path,
+    }
+}
+
+/// Counters for the progress reports of one [`GraphBuilder::build_directory`].
+struct Run {
+    started: Instant,
+    files_total: usize,
+    files_done: usize,
+    files_indexed: usize,
+    /// Source bytes of the files currently held parsed.
+    bytes_held: u64,
+}
+
+/// Count the lines of `path` a buffer at a time.
+fn count_lines(path: &Path) -> std::io::Result<usize> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let (mut lines, mut last) = (0, b'\n');
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        last = buf[n - 1];
+    }
+    // A final line without a newline still counts
+    Ok(lines + usize::from(last != b'\n'))
+}
+
+/// Tag each symbol with the sensitive APIs its body touches.
+///
+/// Narsil only records a function's start line, so a body is taken to run
+/// from its start line up to the next symbol in the same file (or EOF).
+/// That over-approximates for the last function before a large non-function
+/// block, which is acceptable for an audit aid.
+fn tag_sensitivity(symbols: &mut [Symbol], files: &[(String, String, Tree)]) {
+    let sources: HashMap<&str, &str> = files
+        .iter()
+        .map(|(path, content, _)| (path.as_str(), content.as_str()))
+        .collect();
+    let mut starts: HashMap<String, Vec<usize>> = HashMap::new();
+    for sym in symbols.iter() {
+        starts
+            .entry(sym.file_path.clone())
+            .or_default()
+            .push(sym.start_line);
+    }
+    for lines in starts.values_mut() {
+        lines.sort_unstable();
+        lines.dedup();
+    }
+
+    for sym in symbols.iter_mut() {
+        let (Some(content), Some(lines)) = (
+            sources.get(sym.file_path.as_str()),
+            starts.get(&sym.file_path),
+        ) else {
+            continue;
+        };
+        let start = sym.start_line.max(1);
+        let len = lines
+            .iter()
+            .find(|&&l| l > start)
+            .map_or(usize::MAX, |&next| next - start);
+        let body: Vec<&str> = content.lines().skip(start - 1).take(len).collect();
+        sym.sensitivity = scan_source(&body.join("\n"));
+    }
+}
+
+/// Collect the files under `dir` that `languages` can index (see
+/// [`LanguageMap::detect_file`]), skipping hidden, `target` and
+/// `node_modules` directories and whatever `rules` ignore. Hidden files are
+/// kept, and symlinks are followed only while they stay under `dir`. The
+/// user's global gitignore is not read, so the same tree indexes the same
+/// on every machine. Only `dir` itself failing to list is an error;
+/// unreadable entries below it go to `errors`.
+fn walk_directory(
+    dir: &Path,
+    rules: &IgnoreRules,
+    languages: &LanguageMap,
+    out: &mut Vec<PathBuf>,
+    errors: &mut Vec<FileError>,
+) -> Result<()> {
+    std::fs::read_dir(dir)?;
+    let root = dir.canonicalize()?;
+    let exclude = rules.exclude_matcher(dir)?;
+    let walker = ignore::WalkBuilder::new(dir)
+        .hidden(false)
+        .parents(rules.gitignore)
+        .ignore(rules.gitignore)
+        .git_ignore(rules.gitignore)
+        .git_global(false)
+        .git_exclude(rules.gitignore)
+        .require_git(false)
+        .follow_links(true)
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            // A dangling link is kept so the walk reports it
+            if entry.path_is_symlink()
+                && !entry
+                    .path()
+                    .canonicalize()
+                    .ok()
+                    .is_none_or(|target| target.starts_with(&root))
+            {
+                return false;
+            }
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let skipped = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|n| n.starts_with('.') || n == "target" || n == "node_modules");
+            if is_dir && skipped {
+                return false;
+            }
+            !exclude.matched(entry.path(), is_dir).is_ignore()
+        })
+        .build();
+    for entry in walker {
+        match entry {
+            Ok(entry) => {
+                if entry.file_type().is_some_and(|t| t.is_file())
+                    && languages.detect_file(entry.path()).is_some()
+                {
+                    out.push(entry.into_path());
+                }
+            }
+            Err(e) => errors.push(FileError {
+                path: walk_error_path(&e)
+                    .unwrap_or(dir)
+                    .to_string_lossy()
+                    .to_string(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// The file or directory a walk error is about, when it names one.
+fn walk_error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        ignore::Error::Partial(errors) => errors.iter().find_map(walk_error_path),
+        _ => None,
+    }
 }
 
 /// Adapt narsil's `CallNode` to our `Symbol`. Narsil stores the
@@ -231,6 +1051,7 @@ fn call_node_to_symbol(node: &CallNode, degree: usize) -> Symbol {
         cyclomatic: Some(node.metrics.cyclomatic),
         cognitive: Some(node.metrics.cognitive),
         call_degree: Some(degree),
+        sensitivity: Vec::new(),
     }
 }
 
@@ -247,3 +1068,480 @@ fn map_call_type(t: &muninn_narsil_vendor::callgraph::CallType) -> CallType {
         N::Async | N::Spawn | N::Unknown => CallType::Direct,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_progress_reports_each_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { b(); }\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not code\n").unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap())
+            .unwrap()
+            .with_progress(move |p| sink.lock().unwrap().push(*p));
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(stats.files_processed, 2);
+        assert_eq!(
+            seen.iter()
+                .map(|p| (p.files_done, p.files_total))
+                .collect::<Vec<_>>(),
+            vec![(1, 2), (2, 2)]
+        );
+        assert_eq!(seen[1].eta(), Some(Duration::ZERO));
+        assert!(seen.iter().all(|p| p.files_indexed == 0));
+    }
+
+    fn two_dir_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["app", "lib"] {
+            std::fs::create_dir(dir.path().join(sub)).unwrap();
+        }
+        std::fs::write(
+            dir.path().join("app/main.rs"),
+            "fn main() { helper(); run(); }\nfn run() {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("lib/util.rs"),
+            "fn helper() { inner(); }\nfn inner() {}\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_queue_indexes_hinted_dir_first_without_duplicates() {
+        let dir = two_dir_tree();
+        let mut plain = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let expected = plain.build_directory(dir.path()).unwrap();
+        assert!(expected.edges_added >= 3, "{expected:?}");
+
+        let queue = IndexQueue::new();
+        queue.push("lib/util.rs");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap())
+            .unwrap()
+            .with_queue(queue.clone())
+            .with_progress(move |p| sink.lock().unwrap().push(*p));
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        // lib/ was parsed and persisted before app/ was touched
+        let seen = seen.lock().unwrap();
+        let first_indexed = seen.iter().find(|p| p.files_indexed > 0).unwrap();
+        assert_eq!(
+            (first_indexed.files_done, first_indexed.files_indexed),
+            (1, 1)
+        );
+        assert!(queue.is_empty());
+
+        assert_eq!(stats.files_processed, expected.files_processed);
+        assert_eq!(stats.nodes_added, expected.nodes_added);
+        assert_eq!(stats.edges_added, expected.edges_added);
+        let store_stats = builder.store().stats().unwrap();
+        let plain_stats = plain.store().stats().unwrap();
+        assert_eq!(store_stats.node_count, plain_stats.node_count);
+        assert_eq!(store_stats.edge_count, plain_stats.edge_count);
+    }
+
+    #[test]
+    fn test_oversized_files_are_skipped_and_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("small.rs"), "fn small() {}\n").unwrap();
+        let generated = "fn gen() {}\n".repeat(200) + "fn tail() {}";
+        std::fs::write(dir.path().join("generated.rs"), &generated).unwrap();
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap())
+            .unwrap()
+            .with_limits(BuildLimits {
+                max_file_bytes: 1024,
+                ..BuildLimits::default()
+            });
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_processed, 1);
+        assert_eq!(stats.skipped.len(), 1);
+        let skipped = &stats.skipped[0];
+        assert!(skipped.path.ends_with("generated.rs"));
+        assert_eq!(skipped.bytes, generated.len() as u64);
+        assert_eq!(skipped.lines, 201);
+    }
+
+    #[test]
+    fn test_oversized_files_are_not_hashed() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.rs");
+        std::fs::write(&small, "fn small() {}\n").unwrap();
+        std::fs::write(dir.path().join("generated.rs"), "fn gen() {}\n".repeat(200)).unwrap();
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap())
+            .unwrap()
+            .with_limits(BuildLimits {
+                max_file_bytes: 1024,
+                ..BuildLimits::default()
+            });
+        builder.build_directory(dir.path()).unwrap();
+
+        let hashes = builder.store().file_hashes().unwrap();
+        assert_eq!(hashes.len(), 1);
+        let small_hash = &hashes[small.to_string_lossy().as_ref()];
+        assert_eq!(
+            *small_hash,
+            format!("{:x}", Sha256::digest(b"fn small() {}\n"))
+        );
+
+        // Still over the limit next time, so reported again
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!(stats.unchanged, 1);
+        assert_eq!(stats.skipped.len(), 1);
+        assert!(stats.skipped[0].path.ends_with("generated.rs"));
+    }
+
+    #[test]
+    fn test_parsed_ceiling_flushes_without_losing_symbols() {
+        let dir = two_dir_tree();
+        let mut plain = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        plain.build_directory(dir.path()).unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap())
+            .unwrap()
+            .with_limits(BuildLimits {
+                max_parsed_bytes: 1,
+                ..BuildLimits::default()
+            })
+            .with_progress(move |p| sink.lock().unwrap().push(*p));
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_processed, 2);
+        assert!(seen.lock().unwrap().iter().any(|p| p.files_indexed == 1));
+        let store_stats = builder.store().stats().unwrap();
+        let plain_stats = plain.store().stats().unwrap();
+        assert_eq!(store_stats.node_count, plain_stats.node_count);
+        // main -> helper crosses the flush and is not resolved
+        assert!(store_stats.edge_count < plain_stats.edge_count);
+    }
+
+    #[test]
+    fn test_unreadable_file_is_reported_and_run_continues() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("good.rs"), "fn good() {}\n").unwrap();
+        std::fs::write(dir.path().join("bad.rs"), [0xff, 0xfe, 0x00, 0x9f]).unwrap();
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_processed, 1);
+        assert_eq!(stats.nodes_added, 1);
+        assert_eq!(stats.errors.len(), 1);
+        assert!(stats.errors[0].path.ends_with("bad.rs"));
+        assert!(
+            stats.errors[0].reason.contains("UTF-8"),
+            "{:?}",
+            stats.errors
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_errors_name_the_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/good.rs"), "fn good() {}\n").unwrap();
+        let dangling = dir.path().join("src/dangling.rs");
+        std::os::unix::fs::symlink(dir.path().join("gone.rs"), &dangling).unwrap();
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_processed, 1);
+        assert_eq!(stats.errors.len(), 1, "{:?}", stats.errors);
+        assert_eq!(stats.errors[0].path, dangling.to_string_lossy());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_keeps_hidden_files_and_stays_under_the_root() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.rs"), "fn secret() {}\n").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/inner")).unwrap();
+        std::fs::create_dir(dir.path().join(".cache")).unwrap();
+        std::fs::write(dir.path().join("src/inner/lib.rs"), "fn inner() {}\n").unwrap();
+        std::fs::write(dir.path().join("src/.build.rs"), "fn dotfile() {}\n").unwrap();
+        std::fs::write(dir.path().join(".cache/cached.rs"), "fn cached() {}\n").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("src/escape")).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.rs"),
+            dir.path().join("src/secret.rs"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(dir.path().join("src/inner"), dir.path().join("linked"))
+            .unwrap();
+
+        let mut paths = Vec::new();
+        let mut errors = Vec::new();
+        walk_directory(
+            dir.path(),
+            &IgnoreRules::default(),
+            &LanguageMap::default(),
+            &mut paths,
+            &mut errors,
+        )
+        .unwrap();
+        let mut walked: Vec<String> = paths
+            .iter()
+            .map(|p| {
+                p.strip_prefix(dir.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        walked.sort();
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(
+            walked,
+            ["linked/lib.rs", "src/.build.rs", "src/inner/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_extensionless_scripts_and_build_files_are_indexed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("bin")).unwrap();
+        std::fs::write(
+            dir.path().join("bin/tool"),
+            "#!/usr/bin/env python3\ndef main():\n    run()\n\ndef run():\n    pass\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("Dockerfile"), "FROM rust\nRUN make\n").unwrap();
+        std::fs::write(dir.path().join("LICENSE"), "MIT\n").unwrap();
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_processed, 2);
+        assert!(stats.errors.is_empty(), "{:?}", stats.errors);
+        let store = builder.store();
+        let tool = dir.path().join("bin/tool").to_string_lossy().to_string();
+        assert_eq!(store.find_symbols_in_file(&tool).unwrap().len(), 2);
+        assert_eq!(stats.edges_added, 1);
+        let docker = store.find_by_name("Dockerfile").unwrap();
+        assert_eq!(docker.len(), 1);
+        assert_eq!(crate::store::node_str(&docker[0], "kind"), Some("file"));
+    }
+
+    /// Node and edge counts of a fresh build of `dir`.
+    fn fresh_counts(dir: &Path) -> (i64, i64) {
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir).unwrap();
+        let stats = builder.store().stats().unwrap();
+        (stats.node_count, stats.edge_count)
+    }
+
+    fn counts(builder: &GraphBuilder) -> (i64, i64) {
+        let stats = builder.store().stats().unwrap();
+        (stats.node_count, stats.edge_count)
+    }
+
+    #[test]
+    fn test_unchanged_files_are_not_parsed_again() {
+        let dir = two_dir_tree();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let first = builder.build_directory(dir.path()).unwrap();
+        assert_eq!((first.files_processed, first.unchanged), (2, 0));
+        let before = counts(&builder);
+
+        let second = builder.build_directory(dir.path()).unwrap();
+        assert_eq!(second.files_processed, 0);
+        assert_eq!(second.unchanged, 2);
+        assert_eq!(counts(&builder), before);
+    }
+
+    #[test]
+    fn test_changed_file_is_reindexed_keeping_cross_file_edges() {
+        let dir = two_dir_tree();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+
+        // Shift helper and inner down a line, so their node ids change;
+        // main -> helper must follow them
+        std::fs::write(
+            dir.path().join("lib/util.rs"),
+            "// helpers\nfn helper() { inner(); }\nfn inner() {}\n",
+        )
+        .unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!((stats.files_processed, stats.unchanged), (1, 1));
+        assert_eq!(counts(&builder), fresh_counts(dir.path()));
+        let helper = builder.store().find_by_name("helper").unwrap();
+        assert_eq!(helper.len(), 1);
+        let helper_id = node_str(&helper[0], "id").unwrap();
+        let callers = builder.store().find_callers(helper_id).unwrap();
+        assert_eq!(node_str(&callers[0], "name"), Some("main"));
+
+        // A new call from main into the untouched util.rs resolves from the store
+        std::fs::write(
+            dir.path().join("app/main.rs"),
+            "fn main() { helper(); run(); inner(); }\nfn run() {}\n",
+        )
+        .unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!((stats.files_processed, stats.unchanged), (1, 1));
+        assert_eq!(counts(&builder), fresh_counts(dir.path()));
+    }
+
+    #[test]
+    fn test_interrupted_build_reparses_the_files_it_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.c"), "int a(void) { return b(); }\n").unwrap();
+        std::fs::write(dir.path().join("b.c"), "int b(void) { return 1; }\n").unwrap();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+        let before = counts(&builder);
+
+        // a.c changes, so both C files lose their nodes; then the run stops
+        // before parsing anything
+        std::fs::write(dir.path().join("a.c"), "int a(void) { return b() + 1; }\n").unwrap();
+        let walked = vec![dir.path().join("a.c"), dir.path().join("b.c")];
+        builder
+            .plan(dir.path(), walked, &mut BuildStats::default())
+            .unwrap();
+        assert!(builder.store().find_by_name("b").unwrap().is_empty());
+
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!((stats.files_processed, stats.unchanged), (2, 0));
+        assert_eq!(builder.store().find_by_name("b").unwrap().len(), 1);
+        assert_eq!(counts(&builder), before);
+    }
+
+    #[test]
+    fn test_deleted_file_is_dropped_from_the_store() {
+        let dir = two_dir_tree();
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+
+        std::fs::remove_file(dir.path().join("lib/util.rs")).unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!(stats.removed, 1);
+        assert_eq!(stats.files_processed, 0);
+        assert!(builder.store().find_by_name("helper").unwrap().is_empty());
+        assert_eq!(counts(&builder), fresh_counts(dir.path()));
+
+        // Restoring it indexes it again. main's call to helper went
+        // unresolved while util.rs was gone and main.rs is unchanged, so
+        // that edge waits for main.rs to change (or a reset)
+        std::fs::write(
+            dir.path().join("lib/util.rs"),
+            "fn helper() { inner(); }\nfn inner() {}\n",
+        )
+        .unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!((stats.files_processed, stats.removed), (1, 0));
+        let (nodes, edges) = fresh_counts(dir.path());
+        assert_eq!(counts(&builder), (nodes, edges - 1));
+    }
+
+    #[test]
+    fn test_ignore_rules_leave_files_out() {
+        let dir = two_dir_tree();
+        std::fs::create_dir_all(dir.path().join("vendor/dep")).unwrap();
+        std::fs::write(dir.path().join("vendor/dep/lib.rs"), "fn dep() {}\n").unwrap();
+        std::fs::write(dir.path().join("app/schema.gen.rs"), "fn gen() {}\n").unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "vendor/\n").unwrap();
+        let names = |builder: &GraphBuilder| {
+            ["helper", "dep", "gen"]
+                .into_iter()
+                .filter(|name| !builder.store().find_by_name(name).unwrap().is_empty())
+                .collect::<Vec<_>>()
+        };
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!(stats.files_processed, 3);
+        assert_eq!(names(&builder), ["helper", "gen"]);
+
+        // Files an exclude pattern newly covers are dropped like deleted ones
+        let mut builder = builder.with_ignore_rules(IgnoreRules {
+            exclude: vec!["*.gen.rs".to_string()],
+            ..IgnoreRules::default()
+        });
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!((stats.removed, stats.unchanged), (1, 2));
+        assert_eq!(names(&builder), ["helper"]);
+
+        let mut builder = builder.with_ignore_rules(IgnoreRules {
+            gitignore: false,
+            exclude: vec!["lib/".to_string()],
+        });
+        builder.build_directory(dir.path()).unwrap();
+        assert_eq!(names(&builder), ["dep", "gen"]);
+
+        let mut builder = builder.with_ignore_rules(IgnoreRules {
+            exclude: vec!["[z-a]".to_string()],
+            ..IgnoreRules::default()
+        });
+        assert!(matches!(
+            builder.build_directory(dir.path()),
+            Err(BuildError::Ignore(_))
+        ));
+    }
+
+    #[test]
+    fn test_language_map_picks_what_is_indexed() {
+        let dir = two_dir_tree();
+        std::fs::write(
+            dir.path().join("lib/util.mjs"),
+            "function tool() { helper(); }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("Makefile"), "all:\n").unwrap();
+
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!(
+            stats.languages.into_iter().collect::<Vec<_>>(),
+            [("make", 1), ("rust", 2)]
+        );
+
+        let languages =
+            LanguageMap::with_overrides([("mjs", "javascript"), ("rs", "none")]).unwrap();
+        let mut builder = builder.with_languages(languages);
+        let stats = builder.build_directory(dir.path()).unwrap();
+        assert_eq!(stats.removed, 2);
+        assert_eq!(
+            stats.languages.into_iter().collect::<Vec<_>>(),
+            [("javascript", 1)]
+        );
+        assert!(builder.store().find_by_name("helper").unwrap().is_empty());
+        assert_eq!(builder.store().find_by_name("tool").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_eta_scales_with_remaining_files() {
+        let progress = IndexProgress {
+            files_done: 10,
+            files_indexed: 0,
+            files_total: 40,
+            elapsed: Duration::from_secs(5),
+        };
+        assert_eq!(progress.eta(), Some(Duration::from_secs(15)));
+        let start = IndexProgress {
+            files_done: 0,
+            ..progress
+        };
+        assert_eq!(start.eta(), None);
+    }
+}
@@ -0,0 +1,535 @@
+//! C/C++ structure beyond the call graph.
+//!
+//! Narsil's call graph only knows function definitions. For C and C++
+//! files this pass adds what a header-based language needs on top: a file
+//! node per file with `INCLUDES` edges between them, prototypes and
+//! in-class method declarations, structs/classes/unions/enums, typedefs,
+//! namespaces and macros. Each definition gets a `DEFINES` edge to the
+//! declarations it implements, so the callers of a header prototype are
+//! the callers of its definition in another translation unit (see
+//! [`GraphStore::find_callers`](crate::GraphStore::find_callers)).
+//!
+//! Includes resolve against the files indexed in the same batch: the
+//! including file's directory first, then any file whose path ends with
+//! the included one (covering `-I` directories). Headers outside the tree
+//! are left out.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use muninn_narsil_vendor::tree_sitter::{Node, Tree};
+
+use crate::builder::file_node;
+use crate::edges::Edge;
+use crate::language::LanguageMap;
+use crate::symbols::{Symbol, SymbolKind, Visibility};
+
+/// Symbols and edges added by [`extract`].
+#[derive(Debug, Default)]
+pub(crate) struct CFamily {
+    pub symbols: Vec<Symbol>,
+    pub edges: Vec<Edge>,
+}
+
+/// Run the pass over the C/C++ files among `files`, as `languages` maps
+/// them. `definitions` are the functions already built from the call
+/// graph; declarations are linked to them.
+pub(crate) fn extract(
+    files: &[(String, String, Tree)],
+    definitions: &[Symbol],
+    languages: &LanguageMap,
+) -> CFamily {
+    let mut out = CFamily::default();
+    let mut file_ids: HashMap<&str, String> = HashMap::new();
+    let mut includes: Vec<(&str, String, usize)> = Vec::new();
+    let mut declarations: Vec<Symbol> = Vec::new();
+
+    for (path, content, tree) in files {
+        if !is_c_family(languages, path) {
+            continue;
+        }
+        let file = file_node(Path::new(path), content);
+        file_ids.insert(path.as_str(), file.id());
+        out.symbols.push(file);
+
+        let mut walker = Walker {
+            path,
+            source: content.as_bytes(),
+            scope: Vec::new(),
+            symbols: Vec::new(),
+            declarations: Vec::new(),
+            includes: Vec::new(),
+        };
+        walker.walk(tree.root_node());
+        out.symbols.append(&mut walker.symbols);
+        declarations.append(&mut walker.declarations);
+        includes.extend(
+            walker
+                .includes
+                .into_iter()
+                .map(|(target, line)| (path.as_str(), target, line)),
+        );
+    }
+    if file_ids.is_empty() {
+        return out;
+    }
+
+    let mut included_by: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (from, target, line) in &includes {
+        let Some(to) = resolve_include(from, target, &file_ids) else {
+            continue;
+        };
+        included_by.entry(to).or_default().push(from);
+        out.edges
+            .push(Edge::includes(&file_ids[from], &file_ids[to], *line));
+    }
+
+    let definitions: Vec<&Symbol> = definitions
+        .iter()
+        .filter(|d| file_ids.contains_key(d.file_path.as_str()))
+        .collect();
+    for declaration in &declarations {
+        for definition in defining(declaration, &definitions, &included_by) {
+            out.edges
+                .push(Edge::defines(definition.id(), declaration.id()));
+        }
+    }
+    out.symbols.extend(declarations);
+
+    let mut seen = HashSet::new();
+    out.symbols.retain(|s| seen.insert(s.id()));
+    out
+}
+
+/// Whether `path` is parsed with the C or C++ grammar.
+pub(crate) fn is_c_family(languages: &LanguageMap, path: &str) -> bool {
+    matches!(languages.language_of(path), Some("c" | "cpp"))
+}
+
+/// Collects the symbols of one file.
+struct Walker<'a> {
+    path: &'a str,
+    source: &'a [u8],
+    /// Enclosing namespaces and classes, and whether each is a class.
+    scope: Vec<(String, bool)>,
+    symbols: Vec<Symbol>,
+    declarations: Vec<Symbol>,
+    /// `#include` targets as written, with their line.
+    includes: Vec<(String, usize)>,
+}
+
+impl Walker<'_> {
+    fn text(&self, node: Node) -> &str {
+        node.utf8_text(self.source).unwrap_or("")
+    }
+
+    fn qualify(&self, name: &str) -> String {
+        self.scope
+            .iter()
+            .map(|(s, _)| s.as_str())
+            .chain([name])
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    fn symbol(&self, node: Node, name: &str, kind: SymbolKind) -> Symbol {
+        Symbol::new(
+            name,
+            kind,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(signature(self.text(node)))
+        .with_qualified_name(self.qualify(name))
+        .with_visibility(Visibility::Public)
+    }
+
+    fn walk(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            match child.kind() {
+                // Narsil's; nothing declared in a body is visible outside it
+                "function_definition" => {}
+                "preproc_include" => {
+                    if let Some(path) = child.child_by_field_name("path") {
+                        let target = self.text(path).trim_matches(['"', '<', '>']).to_string();
+                        self.includes.push((target, child.start_position().row + 1));
+                    }
+                }
+                "preproc_def" | "preproc_function_def" => {
+                    if let Some(name) = child.child_by_field_name("name") {
+                        let name = self.text(name).to_string();
+                        let symbol = self.symbol(child, &name, SymbolKind::Macro);
+                        self.symbols.push(symbol);
+                    }
+                }
+                "declaration" | "field_declaration" => self.declaration(child),
+                "struct_specifier" | "union_specifier" | "class_specifier" | "enum_specifier" => {
+                    self.specifier(child)
+                }
+                "type_definition" => {
+                    if let Some(ty) = child.child_by_field_name("type") {
+                        self.specifier(ty);
+                    }
+                    let mut cursor = child.walk();
+                    for declarator in child.children_by_field_name("declarator", &mut cursor) {
+                        if declarator.kind() == "type_identifier" {
+                            let name = self.text(declarator).to_string();
+                            let symbol = self.symbol(child, &name, SymbolKind::Type);
+                            self.symbols.push(symbol);
+                        }
+                    }
+                }
+                "namespace_definition" => {
+                    let name = child
+                        .child_by_field_name("name")
+                        .map(|n| self.text(n).to_string());
+                    if let Some(name) = &name {
+                        let symbol = self.symbol(child, name, SymbolKind::Module);
+                        self.symbols.push(symbol);
+                        self.scope.push((name.clone(), false));
+                    }
+                    if let Some(body) = child.child_by_field_name("body") {
+                        self.walk(body);
+                    }
+                    if name.is_some() {
+                        self.scope.pop();
+                    }
+                }
+                // Include guards, `extern "C"`, templates, access sections...
+                _ => self.walk(child),
+            }
+        }
+    }
+
+    /// A struct, union, class or enum with a body; forward declarations
+    /// and plain uses are skipped.
+    fn specifier(&mut self, node: Node) {
+        let kind = match node.kind() {
+            "struct_specifier" | "union_specifier" => SymbolKind::Struct,
+            "class_specifier" => SymbolKind::Class,
+            "enum_specifier" => SymbolKind::Enum,
+            _ => return,
+        };
+        let (Some(name), Some(body)) = (
+            node.child_by_field_name("name"),
+            node.child_by_field_name("body"),
+        ) else {
+            return;
+        };
+        let name = self.text(name).to_string();
+        let symbol = self.symbol(node, &name, kind);
+        self.symbols.push(symbol);
+        if kind != SymbolKind::Enum {
+            self.scope.push((name, true));
+            self.walk(body);
+            self.scope.pop();
+        }
+    }
+
+    /// Function prototypes and method declarations; variables are skipped.
+    fn declaration(&mut self, node: Node) {
+        if let Some(ty) = node.child_by_field_name("type") {
+            self.specifier(ty);
+        }
+        let is_static = {
+            let mut cursor = node.walk();
+            node.named_children(&mut cursor)
+                .any(|c| c.kind() == "storage_class_specifier" && self.text(c) == "static")
+        };
+        let in_class = self.scope.last().is_some_and(|(_, class)| *class);
+
+        let mut cursor = node.walk();
+        let declarators: Vec<Node> = node
+            .children_by_field_name("declarator", &mut cursor)
+            .collect();
+        for declarator in declarators {
+            let Some(name) = function_name(declarator) else {
+                continue;
+            };
+            let name = self.text(name).to_string();
+            let kind = if in_class || name.contains("::") {
+                SymbolKind::Method
+            } else {
+                SymbolKind::Function
+            };
+            let short = name.rsplit("::").next().unwrap_or(&name).to_string();
+            let mut symbol = self
+                .symbol(node, &short, kind)
+                .with_qualified_name(self.qualify(&name));
+            if is_static {
+                symbol.visibility = Visibility::Private;
+            }
+            self.declarations.push(symbol);
+        }
+    }
+}
+
+/// The name inside a function declarator, looking through pointer and
+/// reference declarators. `None` for anything else, including function
+/// pointer variables.
+fn function_name(declarator: Node) -> Option<Node> {
+    match declarator.kind() {
+        "function_declarator" => {
+            let name = declarator.child_by_field_name("declarator")?;
+            matches!(
+                name.kind(),
+                "identifier"
+                    | "field_identifier"
+                    | "qualified_identifier"
+                    | "destructor_name"
+                    | "operator_name"
+            )
+            .then_some(name)
+        }
+        "pointer_declarator" | "reference_declarator" => {
+            let mut cursor = declarator.walk();
+            let inner = declarator
+                .named_children(&mut cursor)
+                .find(|c| c.kind().ends_with("declarator"));
+            inner.and_then(function_name)
+        }
+        _ => None,
+    }
+}
+
+/// First line of `text`, at most 200 bytes.
+fn signature(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("").trim_end();
+    let mut end = line.len().min(200);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    line[..end].to_string()
+}
+
+/// The indexed file `#include "target"` in `from` refers to.
+fn resolve_include<'a>(
+    from: &str,
+    target: &str,
+    files: &HashMap<&'a str, String>,
+) -> Option<&'a str> {
+    if let Some(dir) = Path::new(from).parent() {
+        let local = normalize(&dir.join(target));
+        if let Some((path, _)) = files.get_key_value(local.to_string_lossy().as_ref()) {
+            return Some(path);
+        }
+    }
+    let suffix = format!("/{}", target.trim_start_matches("./"));
+    files
+        .keys()
+        .copied()
+        .filter(|path| path.ends_with(&suffix))
+        .max_by_key(|path| (common_prefix(path, from), std::cmp::Reverse(*path)))
+}
+
+/// `path` with `.` and `..` components folded away.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+/// The definitions `declaration` declares. A definition in the same file
+/// wins, then one in a file with the same stem (`util.h` and `util.c`),
+/// then one in a file including the declaration's. `static` declarations
+/// only match in their own file.
+fn defining<'a>(
+    declaration: &Symbol,
+    definitions: &[&'a Symbol],
+    included_by: &HashMap<&str, Vec<&str>>,
+) -> Vec<&'a Symbol> {
+    let declared = declaration.qualified_name.as_deref().unwrap_or_default();
+    let candidates: Vec<&Symbol> = definitions
+        .iter()
+        .copied()
+        .filter(|d| d.name == declaration.name)
+        .filter(|d| {
+            let defined = d.qualified_name.as_deref().unwrap_or(&d.name);
+            if declared.contains("::") {
+                defined == declared
+                    || declared.ends_with(&format!("::{}", defined))
+                    || defined.ends_with(&format!("::{}", declared))
+            } else {
+                defined == declared
+            }
+        })
+        .collect();
+
+    let stem = |path: &str| {
+        Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+    };
+    let includers = included_by
+        .get(declaration.file_path.as_str())
+        .cloned()
+        .unwrap_or_default();
+    let tiers: [&dyn Fn(&Symbol) -> bool; 3] = [
+        &|d| d.file_path == declaration.file_path,
+        &|d| stem(&d.file_path) == stem(&declaration.file_path),
+        &|d| includers.contains(&d.file_path.as_str()),
+    ];
+    for tier in tiers {
+        let matched: Vec<&Symbol> = candidates.iter().copied().filter(|d| tier(d)).collect();
+        if !matched.is_empty() {
+            return matched;
+        }
+    }
+    if declaration.visibility == Visibility::Private {
+        return Vec::new();
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphBuilder, GraphStore, store::node_str};
+    use serial_test::serial;
+
+    fn build(files: &[(&str, &str)]) -> (tempfile::TempDir, GraphStore) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+        (dir, builder.into_store())
+    }
+
+    fn ids(store: &GraphStore, name: &str, kind: &str) -> Vec<String> {
+        store
+            .find_by_name(name)
+            .unwrap()
+            .iter()
+            .filter(|n| node_str(n, "kind") == Some(kind))
+            .filter_map(|n| node_str(n, "id").map(str::to_string))
+            .collect()
+    }
+
+    fn caller_names(store: &GraphStore, id: &str) -> Vec<String> {
+        store
+            .find_callers(id)
+            .unwrap()
+            .iter()
+            .filter_map(|n| node_str(n, "name").map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    #[serial]
+    fn test_c_header_declarations_link_to_definitions() {
+        let (_dir, store) = build(&[
+            (
+                "include/util.h",
+                "#ifndef UTIL_H\n#define UTIL_H\n#define MAX(a, b) ((a) > (b) ? (a) : (b))\n\
+                 typedef struct point { int x; int y; } point_t;\n\
+                 int add(int a, int b);\nextern int (*hook)(int);\n#endif\n",
+            ),
+            (
+                "src/util.c",
+                "#include \"util.h\"\nint add(int a, int b) { return a + b; }\n",
+            ),
+            (
+                "src/main.c",
+                "#include <stdio.h>\n#include \"../include/util.h\"\n\
+                 int main(void) { return add(1, 2); }\n",
+            ),
+        ]);
+
+        assert_eq!(ids(&store, "MAX", "macro").len(), 1);
+        assert_eq!(ids(&store, "point", "struct").len(), 1);
+        assert_eq!(ids(&store, "point_t", "type").len(), 1);
+        assert!(ids(&store, "hook", "function").is_empty());
+
+        // The prototype and the definition are separate nodes; callers of
+        // the prototype are the callers of the definition in util.c
+        let add = ids(&store, "add", "function");
+        assert_eq!(add.len(), 2);
+        let declaration = add.iter().find(|id| id.contains("util_h")).unwrap();
+        assert_eq!(caller_names(&store, declaration), ["main"]);
+
+        // `<stdio.h>` isn't in the tree; `util.h` is, from both files
+        let includes = store
+            .query("MATCH (a)-[:INCLUDES]->(b) RETURN a.name AS src, b.name AS dst")
+            .unwrap();
+        let mut pairs: Vec<(String, String)> = includes
+            .iter()
+            .map(|r| (r.get("src").unwrap(), r.get("dst").unwrap()))
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            [
+                ("main.c".to_string(), "util.h".to_string()),
+                ("util.c".to_string(), "util.h".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_cpp_method_declarations_link_to_out_of_class_definitions() {
+        let (_dir, store) = build(&[
+            (
+                "shape.hpp",
+                "namespace geo {\nclass Shape {\npublic:\n    double area() const;\n};\n}\n",
+            ),
+            (
+                "shape.cpp",
+                "#include \"shape.hpp\"\nnamespace geo {\n\
+                 double Shape::area() const { return 0.0; }\n}\n",
+            ),
+            (
+                "main.cpp",
+                "#include \"shape.hpp\"\nint main() { geo::Shape s; return s.area(); }\n",
+            ),
+        ]);
+
+        // One per file that opens the namespace
+        assert_eq!(ids(&store, "geo", "module").len(), 2);
+        assert_eq!(ids(&store, "Shape", "class").len(), 1);
+        let declaration = ids(&store, "area", "method");
+        assert_eq!(declaration.len(), 1);
+        let node = store.get_node(&declaration[0]).unwrap().unwrap();
+        assert_eq!(node_str(&node, "qualified_name"), Some("geo::Shape::area"));
+        assert_eq!(caller_names(&store, &declaration[0]), ["main"]);
+    }
+
+    #[test]
+    fn test_static_declarations_stay_in_their_file() {
+        let definitions = [
+            Symbol::new("helper", SymbolKind::Function, "/r/a.c", 5, 5)
+                .with_qualified_name("helper"),
+            Symbol::new("helper", SymbolKind::Function, "/r/b.c", 9, 9)
+                .with_qualified_name("helper"),
+        ];
+        let definitions: Vec<&Symbol> = definitions.iter().collect();
+        let mut declaration = Symbol::new("helper", SymbolKind::Function, "/r/c.c", 1, 1)
+            .with_qualified_name("helper")
+            .with_visibility(Visibility::Private);
+        assert!(defining(&declaration, &definitions, &HashMap::new()).is_empty());
+
+        declaration.file_path = "/r/b.c".to_string();
+        let found = defining(&declaration, &definitions, &HashMap::new());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file_path, "/r/b.c");
+    }
+}
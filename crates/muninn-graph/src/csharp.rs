@@ -0,0 +1,742 @@
+//! C# structure beyond the call graph.
+//!
+//! Narsil's call graph has a node per C# method and an edge per call it
+//! can resolve, including `helper.Build()` member calls. This pass adds a
+//! file node per file, each namespace (block or file-scoped) as a module,
+//! classes, records, structs, interfaces, enums, constructors and
+//! properties. Narsil's method nodes become methods qualified by their
+//! type (`Example.Demo.Greeter.Greet`); properties become variables
+//! qualified the same way. Files get `IMPORTS` edges to the types their
+//! `using` directives bring in. Types get `INHERITS` edges to the classes
+//! and interfaces they extend, and `IMPLEMENTS` edges to the interfaces a
+//! class, record or struct implements.
+//!
+//! Signatures are the declaration header with its attributes, so
+//! `n.signature CONTAINS '[Obsolete]'` finds attributed symbols.
+//!
+//! Type names resolve against the types indexed in the same batch: nested
+//! types of the enclosing ones, the enclosing namespaces from the
+//! innermost out, `using` aliases, `using` namespaces, then a name unique
+//! across the batch. BCL and library types are left out.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use muninn_narsil_vendor::tree_sitter::{Node, Tree};
+
+use crate::builder::file_node;
+use crate::edges::Edge;
+use crate::language::LanguageMap;
+use crate::symbols::{Symbol, SymbolKind, Visibility};
+
+/// Symbols and edges added by [`extract`].
+#[derive(Debug, Default)]
+pub(crate) struct CSharp {
+    pub symbols: Vec<Symbol>,
+    pub edges: Vec<Edge>,
+}
+
+/// Run the pass over the C# files among `files`, as `languages` maps
+/// them. `methods` are the functions already built from the call graph;
+/// the C# ones are updated in place, so this must run before their ids
+/// are used.
+pub(crate) fn extract(
+    files: &[(String, String, Tree)],
+    methods: &mut [Symbol],
+    languages: &LanguageMap,
+) -> CSharp {
+    let mut out = CSharp::default();
+    let mut units: Vec<(String, Walker)> = Vec::new();
+    for (path, content, tree) in files {
+        if !is_csharp(languages, path) {
+            continue;
+        }
+        let file = file_node(Path::new(path), content);
+        let mut walker = Walker {
+            path,
+            source: content.as_bytes(),
+            namespace: String::new(),
+            scope: Vec::new(),
+            usings: Vec::new(),
+            types: Vec::new(),
+            methods: Vec::new(),
+            symbols: Vec::new(),
+        };
+        walker.walk(tree.root_node());
+        units.push((file.id(), walker));
+        out.symbols.push(file);
+    }
+    if units.is_empty() {
+        return out;
+    }
+
+    let mut types = TypeIndex::default();
+    for (_, unit) in &units {
+        for ty in &unit.types {
+            types.insert(&ty.qualified, &ty.id, ty.interface);
+        }
+    }
+
+    for (file_id, unit) in &units {
+        for using in &unit.usings {
+            for target in types.imported(using) {
+                out.edges.push(Edge::imports(file_id, target, using.line));
+            }
+        }
+        for ty in &unit.types {
+            for name in &ty.supertypes {
+                let Some((target, interface)) = types.resolve(name, unit, ty) else {
+                    continue;
+                };
+                out.edges.push(if interface && !ty.interface {
+                    Edge::implements(&ty.id, target)
+                } else {
+                    Edge::inherits(&ty.id, target)
+                });
+            }
+        }
+    }
+
+    let by_line: HashMap<(&str, usize, &str), &Method> = units
+        .iter()
+        .flat_map(|(_, unit)| &unit.methods)
+        .map(|m| ((m.file_path.as_str(), m.line, m.name.as_str()), m))
+        .collect();
+    for symbol in methods.iter_mut() {
+        let key = (
+            symbol.file_path.as_str(),
+            symbol.start_line,
+            symbol.name.as_str(),
+        );
+        if let Some(method) = by_line.get(&key) {
+            symbol.kind = SymbolKind::Method;
+            symbol.end_line = method.end_line;
+            symbol.qualified_name = Some(method.qualified.clone());
+            symbol.signature = Some(method.signature.clone());
+            symbol.visibility = method.visibility.clone();
+        }
+    }
+
+    for (_, unit) in units {
+        out.symbols.extend(unit.symbols);
+    }
+    out
+}
+
+/// Whether `path` is parsed with the C# grammar.
+pub(crate) fn is_csharp(languages: &LanguageMap, path: &str) -> bool {
+    matches!(languages.language_of(path), Some("csharp"))
+}
+
+/// A `using` directive.
+struct Using {
+    /// The namespace or type named.
+    path: String,
+    /// `using Alias = A.B.Type;`
+    alias: Option<String>,
+    /// `using static A.B.Type;`: the members of a type, not a namespace.
+    is_static: bool,
+    line: usize,
+}
+
+/// A type declared in a file.
+struct TypeDecl {
+    id: String,
+    qualified: String,
+    interface: bool,
+    /// The namespace the type is declared in.
+    namespace: String,
+    /// Qualified names of the enclosing types and the type itself,
+    /// outermost first.
+    scope: Vec<String>,
+    /// Names of the types in the base list, as written and without type
+    /// arguments.
+    supertypes: Vec<String>,
+}
+
+/// A method narsil already has a node for, located by file and line.
+struct Method {
+    file_path: String,
+    line: usize,
+    name: String,
+    end_line: usize,
+    qualified: String,
+    signature: String,
+    visibility: Visibility,
+}
+
+/// Collects the declarations of one file.
+struct Walker<'a> {
+    path: &'a str,
+    source: &'a [u8],
+    /// The enclosing namespace, dotted.
+    namespace: String,
+    /// Enclosing types, and whether each is an interface (members
+    /// default to public).
+    scope: Vec<(String, bool)>,
+    usings: Vec<Using>,
+    types: Vec<TypeDecl>,
+    methods: Vec<Method>,
+    symbols: Vec<Symbol>,
+}
+
+impl Walker<'_> {
+    fn text(&self, node: Node) -> &str {
+        node.utf8_text(self.source).unwrap_or("")
+    }
+
+    fn qualify(&self, name: &str) -> String {
+        match self.scope.last() {
+            Some((outer, _)) => format!("{}.{}", outer, name),
+            None if self.namespace.is_empty() => name.to_string(),
+            None => format!("{}.{}", self.namespace, name),
+        }
+    }
+
+    /// The access modifiers as written. Without any, interface members are
+    /// public, other members private, and top-level types internal
+    /// (crate).
+    fn visibility(&self, node: Node) -> Visibility {
+        let mut cursor = node.walk();
+        let access: Vec<&str> = node
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "modifier")
+            .map(|c| self.text(c))
+            .filter(|m| matches!(*m, "public" | "private" | "protected" | "internal"))
+            .collect();
+        match access.as_slice() {
+            ["public"] => Visibility::Public,
+            ["private"] => Visibility::Private,
+            ["internal"] => Visibility::Crate,
+            [] => match self.scope.last() {
+                Some((_, true)) => Visibility::Public,
+                Some((_, false)) => Visibility::Private,
+                None => Visibility::Crate,
+            },
+            // `protected`, `protected internal`, `private protected`
+            _ => Visibility::Restricted(access.join(" ")),
+        }
+    }
+
+    /// The declaration up to its body, on one line.
+    fn header(&self, node: Node) -> String {
+        let mut cursor = node.walk();
+        let end = node
+            .child_by_field_name("body")
+            .or_else(|| node.child_by_field_name("accessors"))
+            .or_else(|| {
+                node.named_children(&mut cursor)
+                    .find(|c| c.kind() == "arrow_expression_clause")
+            })
+            .map_or(node.end_byte(), |body| body.start_byte());
+        let text = std::str::from_utf8(&self.source[node.start_byte()..end]).unwrap_or("");
+        let header = text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches(';')
+            .to_string();
+        let mut end = header.len().min(200);
+        while !header.is_char_boundary(end) {
+            end -= 1;
+        }
+        header[..end].to_string()
+    }
+
+    /// The name of a type as written (`A.B.Base` for `A.B.Base<T>`).
+    fn type_name(&self, node: Node) -> Option<String> {
+        match node.kind() {
+            "identifier" => Some(self.text(node).to_string()),
+            "generic_name" => {
+                let mut cursor = node.walk();
+                let name = node
+                    .named_children(&mut cursor)
+                    .find(|c| c.kind() == "identifier");
+                name.map(|name| self.text(name).to_string())
+            }
+            "qualified_name" => {
+                let qualifier = node.child_by_field_name("qualifier")?;
+                let name = node.child_by_field_name("name")?;
+                Some(format!(
+                    "{}.{}",
+                    self.type_name(qualifier)?,
+                    self.type_name(name)?
+                ))
+            }
+            // `global::A.B`
+            "alias_qualified_name" => node
+                .child_by_field_name("name")
+                .and_then(|name| self.type_name(name)),
+            _ => None,
+        }
+    }
+
+    fn walk(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            match child.kind() {
+                "using_directive" => self.using(child),
+                "namespace_declaration" | "file_scoped_namespace_declaration" => {
+                    self.namespace(child)
+                }
+                "class_declaration"
+                | "record_declaration"
+                | "struct_declaration"
+                | "record_struct_declaration"
+                | "interface_declaration"
+                | "enum_declaration" => self.declare_type(child),
+                "method_declaration" | "constructor_declaration" => self.member(child),
+                "property_declaration" => self.property(child),
+                // Fields, events, indexers, operators and delegates
+                _ => {}
+            }
+        }
+    }
+
+    fn using(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        let alias = node.child_by_field_name("name");
+        let Some(path) = children
+            .iter()
+            .filter(|c| Some(c.id()) != alias.map(|a| a.id()))
+            .find_map(|c| self.type_name(*c))
+        else {
+            return;
+        };
+        self.usings.push(Using {
+            path,
+            alias: alias.map(|a| self.text(a).to_string()),
+            is_static: children.iter().any(|c| c.kind() == "static"),
+            line: node.start_position().row + 1,
+        });
+    }
+
+    /// A block namespace walks its body; a file-scoped one applies to the
+    /// rest of the file.
+    fn namespace(&mut self, node: Node) {
+        let Some(name) = node
+            .child_by_field_name("name")
+            .and_then(|name| self.type_name(name))
+        else {
+            return;
+        };
+        let outer = self.namespace.clone();
+        self.namespace = if outer.is_empty() {
+            name
+        } else {
+            format!("{}.{}", outer, name)
+        };
+        let symbol = Symbol::new(
+            &self.namespace,
+            SymbolKind::Module,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(self.header(node))
+        .with_qualified_name(&self.namespace)
+        .with_visibility(Visibility::Public);
+        self.symbols.push(symbol);
+        if let Some(body) = node.child_by_field_name("body") {
+            self.walk(body);
+            self.namespace = outer;
+        }
+    }
+
+    fn declare_type(&mut self, node: Node) {
+        let (kind, interface) = match node.kind() {
+            "class_declaration" | "record_declaration" => (SymbolKind::Class, false),
+            "struct_declaration" | "record_struct_declaration" => (SymbolKind::Struct, false),
+            "interface_declaration" => (SymbolKind::Interface, true),
+            "enum_declaration" => (SymbolKind::Enum, false),
+            _ => return,
+        };
+        let Some(name) = node.child_by_field_name("name") else {
+            return;
+        };
+        let name = self.text(name).to_string();
+        let qualified = self.qualify(&name);
+        let symbol = Symbol::new(
+            &name,
+            kind,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(self.header(node))
+        .with_qualified_name(&qualified)
+        .with_visibility(self.visibility(node));
+
+        let mut cursor = node.walk();
+        let bases = node
+            .named_children(&mut cursor)
+            .find(|c| c.kind() == "base_list");
+        let mut supertypes = Vec::new();
+        if let Some(bases) = bases {
+            let mut cursor = bases.walk();
+            // Record bases carry their arguments: `record B(int X) : A(X)`
+            for base in bases.named_children(&mut cursor) {
+                let ty = match base.kind() {
+                    "primary_constructor_base_type" => base.named_child(0),
+                    _ => Some(base),
+                };
+                supertypes.extend(ty.and_then(|ty| self.type_name(ty)));
+            }
+        }
+
+        self.scope.push((qualified.clone(), interface));
+        self.types.push(TypeDecl {
+            id: symbol.id(),
+            qualified,
+            interface,
+            namespace: self.namespace.clone(),
+            scope: self.scope.iter().map(|(s, _)| s.clone()).collect(),
+            supertypes,
+        });
+        self.symbols.push(symbol);
+        if let Some(body) = node.child_by_field_name("body") {
+            self.walk(body);
+        }
+        self.scope.pop();
+    }
+
+    /// A method or constructor of the enclosing type. Methods are narsil's
+    /// nodes and only recorded for [`extract`] to update; constructors
+    /// aren't in the call graph and become symbols here.
+    fn member(&mut self, node: Node) {
+        let (Some((owner, _)), Some(name)) = (self.scope.last(), node.child_by_field_name("name"))
+        else {
+            return;
+        };
+        let name = self.text(name).to_string();
+        let qualified = format!("{}.{}", owner, name);
+        let signature = self.header(node);
+        let visibility = self.visibility(node);
+        if node.kind() == "method_declaration" {
+            self.methods.push(Method {
+                file_path: self.path.to_string(),
+                line: node.start_position().row + 1,
+                name,
+                end_line: node.end_position().row + 1,
+                qualified,
+                signature,
+                visibility,
+            });
+        } else {
+            let symbol = Symbol::new(
+                name,
+                SymbolKind::Method,
+                self.path,
+                node.start_position().row + 1,
+                node.end_position().row + 1,
+            )
+            .with_signature(signature)
+            .with_qualified_name(qualified)
+            .with_visibility(visibility);
+            self.symbols.push(symbol);
+        }
+    }
+
+    /// A property, as a variable of the enclosing type.
+    fn property(&mut self, node: Node) {
+        let (Some((owner, _)), Some(name)) = (self.scope.last(), node.child_by_field_name("name"))
+        else {
+            return;
+        };
+        let name = self.text(name).to_string();
+        let symbol = Symbol::new(
+            &name,
+            SymbolKind::Variable,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(self.header(node))
+        .with_qualified_name(format!("{}.{}", owner, name))
+        .with_visibility(self.visibility(node));
+        self.symbols.push(symbol);
+    }
+}
+
+/// The types of a batch by qualified and simple name.
+#[derive(Default)]
+struct TypeIndex {
+    /// Id and whether the type is an interface, by qualified name.
+    ids: HashMap<String, (String, bool)>,
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl TypeIndex {
+    fn insert(&mut self, qualified: &str, id: &str, interface: bool) {
+        self.ids
+            .insert(qualified.to_string(), (id.to_string(), interface));
+        let name = qualified.rsplit('.').next().unwrap_or(qualified);
+        self.by_name
+            .entry(name.to_string())
+            .or_default()
+            .push(qualified.to_string());
+    }
+
+    /// The types a `using` brings in: the aliased or static type, or every
+    /// type directly in a namespace.
+    fn imported(&self, using: &Using) -> Vec<&str> {
+        if using.alias.is_some() || using.is_static {
+            return self
+                .ids
+                .get(&using.path)
+                .map(|(id, _)| id.as_str())
+                .into_iter()
+                .collect();
+        }
+        let prefix = format!("{}.", using.path);
+        let mut ids: Vec<&str> = self
+            .ids
+            .iter()
+            .filter(|(q, _)| q.strip_prefix(&prefix).is_some_and(|n| !n.contains('.')))
+            .map(|(_, (id, _))| id.as_str())
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The type `name` (as written in `unit`, in the base list of `ty`)
+    /// refers to, and whether it is an interface.
+    fn resolve(&self, name: &str, unit: &Walker, ty: &TypeDecl) -> Option<(&str, bool)> {
+        let (first, rest) = match name.split_once('.') {
+            Some((first, rest)) => (first, format!(".{}", rest)),
+            None => (name, String::new()),
+        };
+        // A.B.C, A.B, A
+        let namespaces = std::iter::successors(Some(ty.namespace.as_str()), |ns| {
+            ns.rsplit_once('.').map(|(outer, _)| outer)
+        })
+        .filter(|ns| !ns.is_empty())
+        .map(|ns| format!("{}.{}", ns, name));
+        let aliased = unit
+            .usings
+            .iter()
+            .filter(|u| u.alias.as_deref() == Some(first))
+            .map(|u| format!("{}{}", u.path, rest));
+        let used = unit
+            .usings
+            .iter()
+            .filter(|u| u.alias.is_none() && !u.is_static)
+            .map(|u| format!("{}.{}", u.path, name));
+        let candidates = ty
+            .scope
+            .iter()
+            .rev()
+            .map(|outer| format!("{}.{}", outer, name))
+            .chain(namespaces)
+            .chain(aliased)
+            .chain([name.to_string()])
+            .chain(used);
+        let found = candidates
+            .into_iter()
+            .find_map(|candidate| self.ids.get(&candidate))
+            .or_else(|| match self.by_name.get(name).map(Vec::as_slice) {
+                Some([only]) => self.ids.get(only),
+                _ => None,
+            });
+        found.map(|(id, interface)| (id.as_str(), *interface))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphBuilder, GraphStore, store::node_str};
+    use serial_test::serial;
+
+    fn build(files: &[(&str, &str)]) -> (tempfile::TempDir, GraphStore) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+        (dir, builder.into_store())
+    }
+
+    fn pairs(store: &GraphStore, rel: &str) -> Vec<(String, String)> {
+        let rows = store
+            .query(&format!(
+                "MATCH (a)-[:{}]->(b) RETURN a.name AS src, b.name AS dst",
+                rel
+            ))
+            .unwrap();
+        let mut pairs: Vec<(String, String)> = rows
+            .iter()
+            .map(|r| (r.get("src").unwrap(), r.get("dst").unwrap()))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Property `key` of the one node named `name` of `kind`.
+    fn prop(store: &GraphStore, name: &str, kind: &str, key: &str) -> Option<String> {
+        let nodes = store.find_by_name(name).unwrap();
+        let found: Vec<_> = nodes
+            .iter()
+            .filter(|n| node_str(n, "kind") == Some(kind))
+            .collect();
+        assert_eq!(found.len(), 1, "{} {}: {:?}", kind, name, nodes);
+        node_str(found[0], key).map(str::to_string)
+    }
+
+    fn pair(src: &str, dst: &str) -> (String, String) {
+        (src.to_string(), dst.to_string())
+    }
+
+    #[test]
+    #[serial]
+    fn test_csharp_namespaces_types_properties_and_calls() {
+        let (_dir, store) = build(&[
+            (
+                "src/Model/Model.cs",
+                "namespace Example.Model\n{\n\
+                 \x20   public interface INamed\n    {\n        string Label { get; }\n    }\n\n\
+                 \x20   public abstract class Base<T>\n    {\n\
+                 \x20       protected abstract T Load();\n    }\n}\n",
+            ),
+            (
+                "src/Demo/Greeter.cs",
+                "using System;\nusing Example.Model;\n\n\
+                 namespace Example.Demo;\n\n\
+                 [Obsolete]\n\
+                 public class Greeter : Base<string>, INamed\n{\n\
+                 \x20   public Greeter(string prefix) { Name = prefix; }\n\n\
+                 \x20   public string Name { get; private set; }\n\n\
+                 \x20   protected string Raw() => Format(Name);\n\n\
+                 \x20   public Widget Build(Helper helper) { return helper.Make(Raw()); }\n\n\
+                 \x20   static string Format(string s) => s.Trim();\n\n\
+                 \x20   internal record Loud(string Text) : Greeter(Text);\n}\n\n\
+                 public class Helper\n{\n    public Widget Make(string text) => new Widget();\n}\n\n\
+                 public struct Widget { }\n",
+            ),
+        ]);
+
+        assert_eq!(
+            prop(&store, "Example.Demo", "module", "qualified_name").as_deref(),
+            Some("Example.Demo")
+        );
+        assert_eq!(
+            prop(&store, "Greeter", "class", "signature").as_deref(),
+            Some("[Obsolete] public class Greeter : Base<string>, INamed")
+        );
+        assert_eq!(
+            prop(&store, "Loud", "class", "qualified_name").as_deref(),
+            Some("Example.Demo.Greeter.Loud")
+        );
+        assert!(prop(&store, "Widget", "struct", "qualified_name").is_some());
+
+        // Narsil's method nodes are named by the method, not the return
+        // type, and qualified by their type
+        assert_eq!(
+            prop(&store, "Build", "method", "qualified_name").as_deref(),
+            Some("Example.Demo.Greeter.Build")
+        );
+        assert_eq!(
+            prop(&store, "Format", "method", "visibility").as_deref(),
+            Some("private")
+        );
+        assert_eq!(
+            prop(&store, "Raw", "method", "visibility").as_deref(),
+            Some("restricted:protected")
+        );
+        assert_eq!(
+            prop(&store, "Greeter", "method", "signature").as_deref(),
+            Some("public Greeter(string prefix)")
+        );
+        assert_eq!(
+            prop(&store, "Name", "variable", "signature").as_deref(),
+            Some("public string Name")
+        );
+        assert_eq!(
+            prop(&store, "Name", "variable", "qualified_name").as_deref(),
+            Some("Example.Demo.Greeter.Name")
+        );
+
+        assert_eq!(
+            pairs(&store, "INHERITS"),
+            [pair("Greeter", "Base"), pair("Loud", "Greeter")]
+        );
+        assert_eq!(pairs(&store, "IMPLEMENTS"), [pair("Greeter", "INamed")]);
+        assert_eq!(
+            pairs(&store, "IMPORTS"),
+            [pair("Greeter.cs", "Base"), pair("Greeter.cs", "INamed")]
+        );
+        // `Trim` is a BCL method and left out
+        assert_eq!(
+            pairs(&store, "CALLS"),
+            [
+                pair("Build", "Make"),
+                pair("Build", "Raw"),
+                pair("Raw", "Format")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolution_walks_out_through_namespaces() {
+        let mut types = TypeIndex::default();
+        types.insert("A.Shape", "a_shape", false);
+        types.insert("A.B.Shape", "ab_shape", true);
+        types.insert("C.Util", "c_util", false);
+        types.insert("D.Util", "d_util", false);
+
+        let unit = Walker {
+            path: "/r/Main.cs",
+            source: b"",
+            namespace: String::new(),
+            scope: Vec::new(),
+            usings: vec![
+                Using {
+                    path: "C.Util".to_string(),
+                    alias: Some("U".to_string()),
+                    is_static: false,
+                    line: 1,
+                },
+                Using {
+                    path: "D".to_string(),
+                    alias: None,
+                    is_static: false,
+                    line: 2,
+                },
+            ],
+            types: Vec::new(),
+            methods: Vec::new(),
+            symbols: Vec::new(),
+        };
+        let in_namespace = |namespace: &str| TypeDecl {
+            id: String::new(),
+            qualified: String::new(),
+            interface: false,
+            namespace: namespace.to_string(),
+            scope: Vec::new(),
+            supertypes: Vec::new(),
+        };
+        assert_eq!(
+            types.resolve("Shape", &unit, &in_namespace("A.B.C")),
+            Some(("ab_shape", true))
+        );
+        assert_eq!(
+            types.resolve("Shape", &unit, &in_namespace("A")),
+            Some(("a_shape", false))
+        );
+        assert_eq!(
+            types.resolve("U", &unit, &in_namespace("")),
+            Some(("c_util", false))
+        );
+        assert_eq!(
+            types.resolve("Util", &unit, &in_namespace("")),
+            Some(("d_util", false))
+        );
+        assert_eq!(types.resolve("Shape", &unit, &in_namespace("E")), None);
+        assert_eq!(types.imported(&unit.usings[1]), ["d_util"]);
+    }
+}
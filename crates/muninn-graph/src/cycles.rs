@@ -0,0 +1,453 @@
+//! Dependency cycle detection.
+//!
+//! [`CycleReport::from_store`] collapses the call graph to one of three
+//! [`CycleLevel`]s (symbols, files, or top-level packages such as
+//! `crates/foo`) and reports every strongly connected component with more
+//! than one member. Each dependency inside a cycle is backed by a concrete
+//! call site, so the report says where to cut as well as what loops.
+//!
+//! Direct recursion (a symbol calling itself) and calls within one file or
+//! package are not cycles at their level and are ignored.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::findings::{Finding, FindingLevel, Findings, Location};
+use crate::overview::{relative_path, top_directory};
+use crate::store::{GraphStore, Result, node_line, node_str};
+
+/// Maximum dependency edges listed per cycle in Markdown output.
+const MAX_EDGES_SHOWN: usize = 20;
+
+/// What the graph is collapsed to before looking for cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CycleLevel {
+    /// Individual functions and methods (mutual recursion).
+    Calls,
+    /// Source files (module-level cycles).
+    Files,
+    /// Top-level directories such as `crates/foo` (crate-level cycles).
+    Packages,
+}
+
+impl CycleLevel {
+    /// Parse `calls`, `files` or `packages`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "calls" => Some(CycleLevel::Calls),
+            "files" => Some(CycleLevel::Files),
+            "packages" => Some(CycleLevel::Packages),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CycleLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CycleLevel::Calls => "calls",
+            CycleLevel::Files => "files",
+            CycleLevel::Packages => "packages",
+        })
+    }
+}
+
+/// One participant in a cycle.
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleMember {
+    /// Qualified name, file path, or package directory, per level.
+    pub name: String,
+    /// `path:line` of the definition, for symbol-level cycles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+/// A dependency between two members, with a call site that creates it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleEdge {
+    pub from: String,
+    pub to: String,
+    /// `path:line` of a call behind the dependency.
+    pub site: String,
+    /// Calls behind the dependency (always 1 at symbol level).
+    pub calls: usize,
+}
+
+/// A strongly connected component.
+#[derive(Debug, Clone, Serialize)]
+pub struct Cycle {
+    /// Members, ordered by name.
+    pub members: Vec<CycleMember>,
+    /// Dependencies between members, ordered by `from` then `to`.
+    pub edges: Vec<CycleEdge>,
+}
+
+/// Every cycle at one level.
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleReport {
+    pub level: CycleLevel,
+    /// Cycles, largest first.
+    pub cycles: Vec<Cycle>,
+}
+
+impl CycleReport {
+    /// Find cycles in the graph at `level`. File paths under `root` are
+    /// reported relative to it.
+    pub fn from_store(store: &GraphStore, root: &Path, level: CycleLevel) -> Result<Self> {
+        // Node ID -> (group key, member, file path)
+        let mut groups: HashMap<String, (String, CycleMember, String)> = HashMap::new();
+        for node in store.all_nodes()? {
+            let Some(id) = node_str(&node, "id") else {
+                continue;
+            };
+            let file_path = relative_path(node_str(&node, "file_path").unwrap_or_default(), root);
+            let (key, member) = match level {
+                CycleLevel::Calls => {
+                    let name = node_str(&node, "qualified_name")
+                        .or_else(|| node_str(&node, "name"))
+                        .unwrap_or(id);
+                    let location =
+                        format!("{}:{}", file_path, node_line(&node).unwrap_or_default());
+                    let member = CycleMember {
+                        name: name.to_string(),
+                        location: Some(location),
+                    };
+                    (id.to_string(), member)
+                }
+                CycleLevel::Files => (file_path.clone(), plain_member(&file_path)),
+                CycleLevel::Packages => {
+                    let package = top_directory(&file_path);
+                    (package.clone(), plain_member(&package))
+                }
+            };
+            groups.insert(id.to_string(), (key, member, file_path));
+        }
+
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut members: Vec<CycleMember> = Vec::new();
+        for (key, member, _) in groups.values() {
+            if !index.contains_key(key) {
+                index.insert(key.clone(), members.len());
+                members.push(member.clone());
+            }
+        }
+
+        // (from, to) -> (earliest call site, calls); BTreeMap keeps the
+        // adjacency lists deterministic
+        let mut dependencies: BTreeMap<(usize, usize), ((String, usize), usize)> = BTreeMap::new();
+        for edge in store.call_edges()? {
+            let (Some((from_key, _, caller_file)), Some((to_key, _, _))) =
+                (groups.get(&edge.caller), groups.get(&edge.callee))
+            else {
+                continue;
+            };
+            let (from, to) = (index[from_key], index[to_key]);
+            if from == to {
+                continue;
+            }
+            let site = (caller_file.clone(), edge.line.unwrap_or_default());
+            let entry = dependencies
+                .entry((from, to))
+                .or_insert_with(|| (site.clone(), 0));
+            entry.0 = entry.0.clone().min(site);
+            entry.1 += 1;
+        }
+
+        let mut adjacency = vec![Vec::new(); members.len()];
+        for &(from, to) in dependencies.keys() {
+            adjacency[from].push(to);
+        }
+
+        let mut cycles: Vec<Cycle> = strongly_connected(&adjacency)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let in_cycle: HashSet<usize> = component.iter().copied().collect();
+                let mut cycle_members: Vec<CycleMember> =
+                    component.iter().map(|&i| members[i].clone()).collect();
+                cycle_members.sort_by(|a, b| a.name.cmp(&b.name));
+                let mut edges: Vec<CycleEdge> = dependencies
+                    .iter()
+                    .filter(|((from, to), _)| in_cycle.contains(from) && in_cycle.contains(to))
+                    .map(|((from, to), ((file, line), calls))| CycleEdge {
+                        from: members[*from].name.clone(),
+                        to: members[*to].name.clone(),
+                        site: format!("{}:{}", file, line),
+                        calls: *calls,
+                    })
+                    .collect();
+                edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+                Cycle {
+                    members: cycle_members,
+                    edges,
+                }
+            })
+            .collect();
+        cycles.sort_by(|a, b| {
+            b.members
+                .len()
+                .cmp(&a.members.len())
+                .then_with(|| a.members[0].name.cmp(&b.members[0].name))
+        });
+
+        Ok(Self { level, cycles })
+    }
+
+    /// Render the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Dependency cycles ({})\n\n", self.level);
+        if self.cycles.is_empty() {
+            out.push_str("No cycles found.\n");
+            return out;
+        }
+        let plural = if self.cycles.len() == 1 { "" } else { "s" };
+        out.push_str(&format!(
+            "{} cycle{} found; the largest has {} members.\n",
+            self.cycles.len(),
+            plural,
+            self.cycles[0].members.len()
+        ));
+
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            out.push_str(&format!(
+                "\n## Cycle {} ({} members)\n\n",
+                i + 1,
+                cycle.members.len()
+            ));
+            for member in &cycle.members {
+                match &member.location {
+                    Some(location) => {
+                        out.push_str(&format!("- `{}` at `{}`\n", member.name, location))
+                    }
+                    None => out.push_str(&format!("- `{}`\n", member.name)),
+                }
+            }
+            out.push_str("\nDependencies:\n\n");
+            for edge in cycle.edges.iter().take(MAX_EDGES_SHOWN) {
+                let calls = if edge.calls > 1 {
+                    format!(" ({} calls)", edge.calls)
+                } else {
+                    String::new()
+                };
+                out.push_str(&format!(
+                    "- `{}` -> `{}` via `{}`{}\n",
+                    edge.from, edge.to, edge.site, calls
+                ));
+            }
+            if cycle.edges.len() > MAX_EDGES_SHOWN {
+                out.push_str(&format!(
+                    "- ... and {} more\n",
+                    cycle.edges.len() - MAX_EDGES_SHOWN
+                ));
+            }
+        }
+        out
+    }
+
+    /// One `cycle/<level>` warning per cycle, for SARIF output. The finding
+    /// sits at the first dependency's call site; the other call sites are
+    /// related locations.
+    pub fn findings(&self) -> Findings {
+        let rule = format!("cycle/{}", self.level);
+        let mut findings = Findings::new();
+        findings.rule(&rule, format!("Dependency cycle between {}", self.level));
+        for cycle in &self.cycles {
+            let names: Vec<&str> = cycle.members.iter().map(|m| m.name.as_str()).collect();
+            let mut finding = Finding::new(
+                &rule,
+                FindingLevel::Warning,
+                format!(
+                    "Dependency cycle of {} members: {}",
+                    names.len(),
+                    names.join(", ")
+                ),
+            );
+            let mut sites = cycle.edges.iter().map(|e| Location::parse(&e.site));
+            if let Some(first) = sites.next() {
+                finding = finding.at(first);
+            }
+            for site in sites {
+                finding = finding.related(site);
+            }
+            findings.push(finding);
+        }
+        findings
+    }
+}
+
+fn plain_member(name: &str) -> CycleMember {
+    CycleMember {
+        name: name.to_string(),
+        location: None,
+    }
+}
+
+/// Tarjan's algorithm, iterative so deep call chains can't overflow the
+/// stack. Returns components in reverse topological order.
+fn strongly_connected(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = adjacency.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut next = 0;
+    let mut components = Vec::new();
+
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // (node, next neighbour to visit)
+        let mut work = vec![(root, 0)];
+        index[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(frame) = work.last_mut() {
+            let v = frame.0;
+            if let Some(&w) = adjacency[v].get(frame.1) {
+                frame.1 += 1;
+                if index[w] == UNVISITED {
+                    index[w] = next;
+                    low[w] = next;
+                    next += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    work.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edges::{CallType, Edge};
+    use crate::symbols::{Symbol, SymbolKind, Visibility};
+    use serial_test::serial;
+
+    fn function(name: &str, file: &str, line: usize) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            file_path: file.to_string(),
+            start_line: line,
+            end_line: line + 5,
+            signature: None,
+            qualified_name: None,
+            doc_comment: None,
+            visibility: Visibility::Public,
+            cyclomatic: None,
+            cognitive: None,
+            call_degree: None,
+            sensitivity: Vec::new(),
+        }
+    }
+
+    /// `a -> b -> a` across two files of one package, `c` calls `a` from
+    /// another package, and `a` recurses.
+    fn store_with_cycle() -> GraphStore {
+        let store = GraphStore::open_in_memory().unwrap();
+        let a = store
+            .insert_node(&function("a", "pkg/one/a.rs", 1))
+            .unwrap();
+        let b = store
+            .insert_node(&function("b", "pkg/one/b.rs", 1))
+            .unwrap();
+        let c = store
+            .insert_node(&function("c", "pkg/two/c.rs", 1))
+            .unwrap();
+        for (from, to, line) in [(&a, &b, 2), (&b, &a, 3), (&c, &a, 4), (&a, &a, 5)] {
+            store
+                .insert_edge(&Edge::calls(from, to, CallType::Direct, line))
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    #[serial]
+    fn test_call_and_file_cycles() {
+        let store = store_with_cycle();
+
+        let calls = CycleReport::from_store(&store, Path::new(""), CycleLevel::Calls).unwrap();
+        assert_eq!(calls.cycles.len(), 1);
+        let cycle = &calls.cycles[0];
+        let names: Vec<_> = cycle.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(cycle.members[0].location.as_deref(), Some("pkg/one/a.rs:1"));
+        let edges: Vec<_> = cycle
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.site.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![("a", "b", "pkg/one/a.rs:2"), ("b", "a", "pkg/one/b.rs:3")]
+        );
+        let findings = calls.findings();
+        assert_eq!(findings.len(), 1);
+        let finding = &findings.results[0];
+        assert_eq!(finding.rule_id, "cycle/calls");
+        assert_eq!(finding.message, "Dependency cycle of 2 members: a, b");
+        assert_eq!(finding.location, Some(Location::new("pkg/one/a.rs", 2)));
+        assert_eq!(finding.related, vec![Location::new("pkg/one/b.rs", 3)]);
+
+        let files = CycleReport::from_store(&store, Path::new(""), CycleLevel::Files).unwrap();
+        assert_eq!(files.cycles.len(), 1);
+        let markdown = files.to_markdown();
+        assert!(markdown.starts_with("# Dependency cycles (files)\n"));
+        assert!(markdown.contains("- `pkg/one/a.rs` -> `pkg/one/b.rs` via `pkg/one/a.rs:2`"));
+
+        // Both files sit in one package, and pkg/two only calls into it
+        let packages =
+            CycleReport::from_store(&store, Path::new(""), CycleLevel::Packages).unwrap();
+        assert!(packages.cycles.is_empty());
+        assert!(packages.to_markdown().contains("No cycles found."));
+    }
+
+    #[test]
+    fn test_strongly_connected() {
+        // 0 -> 1 -> 2 -> 0, 2 -> 3, 3 -> 4 -> 3
+        let adjacency = vec![vec![1], vec![2], vec![0, 3], vec![4], vec![3]];
+        let mut components: Vec<Vec<usize>> = strongly_connected(&adjacency)
+            .into_iter()
+            .map(|mut c| {
+                c.sort();
+                c
+            })
+            .collect();
+        components.sort();
+        assert_eq!(components, vec![vec![0, 1, 2], vec![3, 4]]);
+        assert_eq!(CycleLevel::parse("files"), Some(CycleLevel::Files));
+        assert_eq!(CycleLevel::parse("modules"), None);
+    }
+}
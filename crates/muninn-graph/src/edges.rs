@@ -1,11 +1,13 @@
 //! Edge types representing relationships between symbols.
 //!
-//! Calls-only graph: the vendored narsil extractor produces a call
-//! graph and nothing else, so this module mirrors that. Earlier
-//! variants (Contains, Imports, Inherits, Implements, UsesType,
-//! Instantiates, References, ExpandsTo, GeneratedBy) were removed
-//! when we vendored narsil — the data was never produced by the
-//! new pipeline and the dead enum arms just rotted query code.
+//! The vendored narsil extractor produces a call graph; the C/C++ pass
+//! in `cfamily.rs` adds `#include` edges between files and links
+//! header declarations to their definitions, and the Java, Kotlin and
+//! C# passes in `java.rs`, `kotlin.rs` and `csharp.rs` add imports and
+//! the type hierarchy. Earlier variants
+//! (Contains, UsesType, Instantiates, References, ExpandsTo, GeneratedBy)
+//! were removed when we vendored narsil — the data was never produced by
+//! the new pipeline and the dead enum arms just rotted query code.
 //! Restore selectively if the extractor learns to emit them.
 
 use serde::{Deserialize, Serialize};
@@ -39,11 +41,6 @@ impl CallType {
 }
 
 /// The kind of relationship between two symbols.
-///
-/// Currently the graph models call relationships only. The enum
-/// stays an enum (rather than collapsing to a struct) so adding
-/// future edge kinds — IMPLEMENTS, IMPORTS, etc., when the
-/// extractor learns to emit them — stays additive.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum EdgeKind {
@@ -54,6 +51,23 @@ pub enum EdgeKind {
         /// Line number where the call occurs.
         line: usize,
     },
+    /// A C/C++ file `#include`s another file.
+    Includes {
+        /// Line number of the `#include`.
+        line: usize,
+    },
+    /// A function definition defines a declaration made elsewhere
+    /// (typically a prototype in a header).
+    Defines,
+    /// A file imports a type (Java or Kotlin `import`, C# `using`).
+    Imports {
+        /// Line number of the import.
+        line: usize,
+    },
+    /// A type extends another class or interface.
+    Inherits,
+    /// A class, enum or record implements an interface.
+    Implements,
 }
 
 impl EdgeKind {
@@ -61,6 +75,11 @@ impl EdgeKind {
     pub fn as_str(&self) -> &'static str {
         match self {
             EdgeKind::Calls { .. } => "calls",
+            EdgeKind::Includes { .. } => "includes",
+            EdgeKind::Defines => "defines",
+            EdgeKind::Imports { .. } => "imports",
+            EdgeKind::Inherits => "inherits",
+            EdgeKind::Implements => "implements",
         }
     }
 }
@@ -97,6 +116,35 @@ impl Edge {
     ) -> Self {
         Self::new(caller_id, callee_id, EdgeKind::Calls { call_type, line })
     }
+
+    /// Create an INCLUDES edge between two file nodes.
+    pub fn includes(
+        file_id: impl Into<String>,
+        included_id: impl Into<String>,
+        line: usize,
+    ) -> Self {
+        Self::new(file_id, included_id, EdgeKind::Includes { line })
+    }
+
+    /// Create a DEFINES edge from a definition to its declaration.
+    pub fn defines(definition_id: impl Into<String>, declaration_id: impl Into<String>) -> Self {
+        Self::new(definition_id, declaration_id, EdgeKind::Defines)
+    }
+
+    /// Create an IMPORTS edge from a file node to an imported type.
+    pub fn imports(file_id: impl Into<String>, type_id: impl Into<String>, line: usize) -> Self {
+        Self::new(file_id, type_id, EdgeKind::Imports { line })
+    }
+
+    /// Create an INHERITS edge from a type to the type it extends.
+    pub fn inherits(type_id: impl Into<String>, super_id: impl Into<String>) -> Self {
+        Self::new(type_id, super_id, EdgeKind::Inherits)
+    }
+
+    /// Create an IMPLEMENTS edge from a type to an interface it implements.
+    pub fn implements(type_id: impl Into<String>, interface_id: impl Into<String>) -> Self {
+        Self::new(type_id, interface_id, EdgeKind::Implements)
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +168,11 @@ mod tests {
             .as_str(),
             "calls"
         );
+        assert_eq!(EdgeKind::Includes { line: 1 }.as_str(), "includes");
+        assert_eq!(EdgeKind::Defines.as_str(), "defines");
+        assert_eq!(EdgeKind::Imports { line: 1 }.as_str(), "imports");
+        assert_eq!(EdgeKind::Inherits.as_str(), "inherits");
+        assert_eq!(EdgeKind::Implements.as_str(), "implements");
     }
 
     #[test]
@@ -140,10 +193,9 @@ mod tests {
     #[test]
     fn test_edge_factory_calls() {
         let calls = Edge::calls("caller", "callee", CallType::Method, 42);
-        // EdgeKind only has the Calls variant now, so the destructure
-        // is irrefutable — but we still want to confirm the fields
-        // come through correctly.
-        let EdgeKind::Calls { call_type, line } = calls.kind;
+        let EdgeKind::Calls { call_type, line } = calls.kind else {
+            panic!("expected a CALLS edge, got {:?}", calls.kind);
+        };
         assert_eq!(call_type, CallType::Method);
         assert_eq!(line, 42);
     }
@@ -0,0 +1,278 @@
+//! Findings shared by the analysis reports, and their SARIF rendering.
+//!
+//! Orphan, cycle and audit reports, and the CI review, each turn their
+//! results into [`Findings`]: one [`Finding`] per problem, tagged with the
+//! rule that produced it. [`Findings::to_sarif`] renders them as a SARIF
+//! 2.1.0 log that GitHub code scanning and other analysis tools accept.
+//! Paths are relative to the repository root (`%SRCROOT%`).
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// SARIF schema the log declares.
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How serious a finding is, in SARIF's terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingLevel {
+    Error,
+    Warning,
+    Note,
+}
+
+impl FindingLevel {
+    /// Returns the SARIF `level` value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FindingLevel::Error => "error",
+            FindingLevel::Warning => "warning",
+            FindingLevel::Note => "note",
+        }
+    }
+}
+
+impl fmt::Display for FindingLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A place in the source tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// File path relative to the repository root.
+    pub path: String,
+    /// 1-indexed line, or 0 when only the file is known.
+    pub line: usize,
+}
+
+impl Location {
+    pub fn new(path: impl Into<String>, line: usize) -> Self {
+        Self {
+            path: path.into(),
+            line,
+        }
+    }
+
+    /// Parse a `path:line` site; a missing or invalid line is 0.
+    pub fn parse(site: &str) -> Self {
+        let split = site
+            .rsplit_once(':')
+            .and_then(|(path, line)| Some((path, line.parse().ok()?)));
+        match split {
+            Some((path, line)) if !path.is_empty() => Self::new(path, line),
+            _ => Self::new(site, 0),
+        }
+    }
+
+    fn to_sarif(&self) -> Value {
+        let mut physical = json!({
+            "artifactLocation": { "uri": self.path, "uriBaseId": "%SRCROOT%" },
+        });
+        if self.line > 0 {
+            physical["region"] = json!({ "startLine": self.line });
+        }
+        json!({ "physicalLocation": physical })
+    }
+}
+
+/// One problem reported by an analysis.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// Rule that produced it, e.g. `orphan` or `audit/exec`.
+    pub rule_id: String,
+    pub level: FindingLevel,
+    pub message: String,
+    /// Where the problem is, when it has a place.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// Other places involved, e.g. the rest of a cycle.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<Location>,
+}
+
+impl Finding {
+    pub fn new(
+        rule_id: impl Into<String>,
+        level: FindingLevel,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            rule_id: rule_id.into(),
+            level,
+            message: message.into(),
+            location: None,
+            related: Vec::new(),
+        }
+    }
+
+    /// Set where the problem is.
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Add another place involved in the problem.
+    pub fn related(mut self, location: Location) -> Self {
+        self.related.push(location);
+        self
+    }
+}
+
+/// Findings of one analysis run, with the rules behind them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Findings {
+    /// Rule ID -> short description.
+    pub rules: BTreeMap<String, String>,
+    pub results: Vec<Finding>,
+}
+
+impl Findings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describe a rule. Rules are listed in the SARIF log even without
+    /// results, so a clean run still says what was checked.
+    pub fn rule(&mut self, id: impl Into<String>, description: impl Into<String>) {
+        self.rules.insert(id.into(), description.into());
+    }
+
+    /// Record a finding. Its rule is added with a bare description when it
+    /// wasn't described yet.
+    pub fn push(&mut self, finding: Finding) {
+        self.rules
+            .entry(finding.rule_id.clone())
+            .or_insert_with(|| finding.rule_id.clone());
+        self.results.push(finding);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Render as a SARIF 2.1.0 log with a single run.
+    pub fn to_sarif(&self) -> Value {
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|(id, description)| {
+                json!({
+                    "id": id,
+                    "shortDescription": { "text": description },
+                })
+            })
+            .collect();
+        let results: Vec<Value> = self
+            .results
+            .iter()
+            .map(|finding| {
+                let mut result = json!({
+                    "ruleId": finding.rule_id,
+                    "level": finding.level.as_str(),
+                    "message": { "text": finding.message },
+                });
+                if let Some(index) = self.rules.keys().position(|id| *id == finding.rule_id) {
+                    result["ruleIndex"] = json!(index);
+                }
+                if let Some(location) = &finding.location {
+                    result["locations"] = json!([location.to_sarif()]);
+                }
+                if !finding.related.is_empty() {
+                    result["relatedLocations"] = finding
+                        .related
+                        .iter()
+                        .enumerate()
+                        .map(|(id, location)| {
+                            let mut related = location.to_sarif();
+                            related["id"] = json!(id);
+                            related
+                        })
+                        .collect();
+                }
+                result
+            })
+            .collect();
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "muninn",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                        "rules": rules,
+                    }
+                },
+                "results": results,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_parse() {
+        assert_eq!(
+            Location::parse("src/a.rs:12"),
+            Location::new("src/a.rs", 12)
+        );
+        assert_eq!(Location::parse("src/a.rs"), Location::new("src/a.rs", 0));
+        assert_eq!(
+            Location::parse("src/a.rs:x"),
+            Location::new("src/a.rs:x", 0)
+        );
+    }
+
+    #[test]
+    fn test_to_sarif() {
+        let mut findings = Findings::new();
+        findings.rule("orphan", "Public symbol nothing refers to");
+        findings.rule("cycle/module", "Modules that depend on each other");
+        findings.push(
+            Finding::new("orphan", FindingLevel::Warning, "`unused` is never called")
+                .at(Location::new("src/lib.rs", 7)),
+        );
+        findings.push(
+            Finding::new("cycle/module", FindingLevel::Warning, "a.rs <-> b.rs")
+                .at(Location::new("src/a.rs", 3))
+                .related(Location::new("src/b.rs", 0)),
+        );
+        findings.push(Finding::new("review", FindingLevel::Note, "general remark"));
+
+        let sarif = findings.to_sarif();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "muninn");
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0]["id"], "cycle/module");
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleId"], "orphan");
+        assert_eq!(results[0]["ruleIndex"], 1);
+        assert_eq!(results[0]["level"], "warning");
+        let physical = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(physical["artifactLocation"]["uri"], "src/lib.rs");
+        assert_eq!(physical["artifactLocation"]["uriBaseId"], "%SRCROOT%");
+        assert_eq!(physical["region"]["startLine"], 7);
+
+        let related = &results[1]["relatedLocations"][0];
+        assert_eq!(related["id"], 0);
+        assert!(related["physicalLocation"].get("region").is_none());
+        assert!(results[2].get("locations").is_none());
+    }
+}
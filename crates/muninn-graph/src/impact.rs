@@ -0,0 +1,482 @@
+//! Rename/signature-change impact analysis.
+//!
+//! [`ImpactReport::from_graph`] collects the sites that would need updating
+//! if a symbol were renamed or its signature changed: its definitions and
+//! every call the graph knows about, each with a [`Confidence`].
+//! [`ImpactReport::add_text_matches`] then sweeps the source tree for
+//! mentions the calls-only graph can't see (imports, re-exports, docs,
+//! macro bodies, unindexed files) and adds them at low confidence.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::store::{GraphStore, Result, node_line, node_str};
+
+/// Maximum text matches added to one report.
+const MAX_TEXT_MATCHES: usize = 500;
+
+/// How sure the report is that a site refers to the symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Confidence::High => "high",
+            Confidence::Medium => "medium",
+            Confidence::Low => "low",
+        })
+    }
+}
+
+/// What kind of site this is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SiteKind {
+    /// Where the symbol is defined.
+    Definition,
+    /// A call recorded in the graph.
+    Call,
+    /// A textual mention found by scanning source files.
+    TextMatch,
+}
+
+/// One place that would need updating.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactSite {
+    /// File path as stored in the graph (relative to the repo root).
+    pub file_path: String,
+    /// 1-indexed line.
+    pub line: usize,
+    pub kind: SiteKind,
+    pub confidence: Confidence,
+    /// Enclosing symbol (for definitions and calls) or the matched line.
+    pub context: String,
+    /// Why the site has this confidence.
+    pub reason: String,
+}
+
+/// Every site affected by renaming or changing a symbol.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactReport {
+    /// The symbol as requested.
+    pub symbol: String,
+    /// Qualified names (or IDs) of the matching definitions.
+    pub definitions: Vec<String>,
+    /// Affected sites, ordered by confidence, then file and line.
+    pub sites: Vec<ImpactSite>,
+    /// Free-form review notes (e.g. from an RLM pass over the report).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review: Option<String>,
+}
+
+impl ImpactReport {
+    /// Build a report from the graph's definitions and call edges.
+    ///
+    /// `symbol` may be a plain name (`parse_config`) or a qualified name
+    /// (`crate::config::parse_config`); qualified names that don't match
+    /// exactly fall back to their last segment.
+    pub fn from_graph(store: &GraphStore, symbol: &str) -> Result<Self> {
+        let mut nodes = store.find_by_qualified_name(symbol)?;
+        if nodes.is_empty() {
+            nodes = store.find_by_name(short_name(symbol))?;
+        }
+
+        let mut report = Self {
+            symbol: symbol.to_string(),
+            definitions: Vec::new(),
+            sites: Vec::new(),
+            review: None,
+        };
+        let ambiguous = nodes.len() > 1;
+
+        for node in &nodes {
+            let Some(id) = node_str(node, "id") else {
+                continue;
+            };
+            let display = node_str(node, "qualified_name").unwrap_or(id).to_string();
+            report.definitions.push(display.clone());
+            report.sites.push(ImpactSite {
+                file_path: node_str(node, "file_path").unwrap_or_default().to_string(),
+                line: node_line(node).unwrap_or(0),
+                kind: SiteKind::Definition,
+                confidence: Confidence::High,
+                context: display.clone(),
+                reason: "definition".to_string(),
+            });
+
+            for call in store.find_call_sites(id)? {
+                let caller = &call.caller;
+                let (confidence, reason) = if ambiguous {
+                    (
+                        Confidence::Medium,
+                        format!(
+                            "{} definitions share this name; the call may target another",
+                            nodes.len()
+                        ),
+                    )
+                } else if call.call_type.as_deref() == Some("method") {
+                    (
+                        Confidence::Medium,
+                        "method call resolved by name".to_string(),
+                    )
+                } else {
+                    (Confidence::High, "call resolved in the graph".to_string())
+                };
+                report.sites.push(ImpactSite {
+                    file_path: node_str(caller, "file_path")
+                        .unwrap_or_default()
+                        .to_string(),
+                    line: call.line.or_else(|| node_line(caller)).unwrap_or(0),
+                    kind: SiteKind::Call,
+                    confidence,
+                    context: format!(
+                        "called from {}",
+                        node_str(caller, "qualified_name")
+                            .or_else(|| node_str(caller, "name"))
+                            .unwrap_or("<unknown>")
+                    ),
+                    reason,
+                });
+            }
+        }
+
+        report.sort();
+        Ok(report)
+    }
+
+    /// Scan source files under `root` for mentions of the symbol's name not
+    /// already covered by a graph site, adding them at low confidence.
+    ///
+    /// Honors `.gitignore`. Graph sites under `root` are rewritten to paths
+    /// relative to it, so both kinds of site line up. Returns the number of
+    /// sites added.
+    pub fn add_text_matches(&mut self, root: &Path) -> usize {
+        let name = short_name(&self.symbol).to_string();
+        for site in &mut self.sites {
+            if let Ok(relative) = Path::new(&site.file_path).strip_prefix(root) {
+                site.file_path = relative.to_string_lossy().replace('\\', "/");
+            }
+        }
+        let mut known: HashSet<(String, usize)> = self
+            .sites
+            .iter()
+            .map(|s| (normalize_path(&s.file_path).to_string(), s.line))
+            .collect();
+
+        let mut added = 0;
+        for entry in ignore::WalkBuilder::new(root).build().flatten() {
+            if added >= MAX_TEXT_MATCHES {
+                break;
+            }
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            // Binary and non-UTF-8 files are skipped
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            for (index, line) in content.lines().enumerate() {
+                if !contains_identifier(line, &name) {
+                    continue;
+                }
+                let line_number = index + 1;
+                if !known.insert((relative.clone(), line_number)) {
+                    continue;
+                }
+                self.sites.push(ImpactSite {
+                    file_path: relative.clone(),
+                    line: line_number,
+                    kind: SiteKind::TextMatch,
+                    confidence: Confidence::Low,
+                    context: line.trim().chars().take(160).collect(),
+                    reason: "text match (import, doc, macro or unindexed code)".to_string(),
+                });
+                added += 1;
+                if added >= MAX_TEXT_MATCHES {
+                    break;
+                }
+            }
+        }
+
+        self.sort();
+        added
+    }
+
+    /// Number of sites at the given confidence.
+    pub fn count(&self, confidence: Confidence) -> usize {
+        self.sites
+            .iter()
+            .filter(|s| s.confidence == confidence)
+            .count()
+    }
+
+    /// Render the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Refactor check: `{}`\n\n", self.symbol);
+        if self.definitions.is_empty() {
+            out.push_str("No definition found in the graph.\n");
+        } else {
+            out.push_str(&format!("Definitions: {}\n", self.definitions.join(", ")));
+        }
+
+        let files: BTreeSet<&str> = self
+            .sites
+            .iter()
+            .map(|s| normalize_path(&s.file_path))
+            .collect();
+        out.push_str(&format!(
+            "\n{} sites across {} files ({} high, {} medium, {} low confidence).\n",
+            self.sites.len(),
+            files.len(),
+            self.count(Confidence::High),
+            self.count(Confidence::Medium),
+            self.count(Confidence::Low),
+        ));
+
+        for confidence in [Confidence::High, Confidence::Medium, Confidence::Low] {
+            let sites: Vec<&ImpactSite> = self
+                .sites
+                .iter()
+                .filter(|s| s.confidence == confidence)
+                .collect();
+            if sites.is_empty() {
+                continue;
+            }
+            let heading = match confidence {
+                Confidence::High => "High",
+                Confidence::Medium => "Medium",
+                Confidence::Low => "Low",
+            };
+            out.push_str(&format!("\n## {} confidence\n\n", heading));
+            for site in sites {
+                out.push_str(&format!(
+                    "- `{}:{}` {}: {}\n",
+                    normalize_path(&site.file_path),
+                    site.line,
+                    site.context,
+                    site.reason
+                ));
+            }
+        }
+
+        if let Some(review) = &self.review {
+            out.push_str("\n## Review\n\n");
+            out.push_str(review.trim());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn sort(&mut self) {
+        self.sites.sort_by(|a, b| {
+            (a.confidence, normalize_path(&a.file_path), a.line).cmp(&(
+                b.confidence,
+                normalize_path(&b.file_path),
+                b.line,
+            ))
+        });
+    }
+}
+
+/// Last `::`/`.` segment of a possibly qualified name.
+fn short_name(symbol: &str) -> &str {
+    symbol.rsplit([':', '.']).next().unwrap_or(symbol)
+}
+
+/// Graph paths may carry a `./` prefix depending on how indexing was run.
+fn normalize_path(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+/// Whether `line` contains `name` as a whole identifier.
+fn contains_identifier(line: &str, name: &str) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(name).any(|(start, _)| {
+        let before = line[..start].chars().next_back();
+        let after = line[start + name.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edges::{CallType, Edge};
+    use crate::symbols::{Symbol, SymbolKind, Visibility};
+    use serial_test::serial;
+
+    fn symbol(name: &str, file: &str, line: usize) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            file_path: file.to_string(),
+            start_line: line,
+            end_line: line + 5,
+            signature: None,
+            qualified_name: Some(format!("crate::{}", name)),
+            doc_comment: None,
+            visibility: Visibility::Public,
+            cyclomatic: None,
+            cognitive: None,
+            call_degree: None,
+            sensitivity: Vec::new(),
+        }
+    }
+
+    fn store_with_calls() -> GraphStore {
+        let store = GraphStore::open_in_memory().unwrap();
+        let target = store
+            .insert_node(&symbol("parse_config", "src/config.rs", 10))
+            .unwrap();
+        let main = store
+            .insert_node(&symbol("main", "src/main.rs", 1))
+            .unwrap();
+        let load = store
+            .insert_node(&symbol("load", "src/loader.rs", 20))
+            .unwrap();
+        store
+            .insert_edge(&Edge::calls(&main, &target, CallType::Direct, 3))
+            .unwrap();
+        store
+            .insert_edge(&Edge::calls(&load, &target, CallType::Method, 24))
+            .unwrap();
+        store
+    }
+
+    #[test]
+    #[serial]
+    fn test_report_from_graph() {
+        let store = store_with_calls();
+        let report = ImpactReport::from_graph(&store, "parse_config").unwrap();
+
+        assert_eq!(report.definitions, vec!["crate::parse_config"]);
+        let sites: Vec<_> = report
+            .sites
+            .iter()
+            .map(|s| (s.file_path.as_str(), s.line, s.kind, s.confidence))
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                ("src/config.rs", 10, SiteKind::Definition, Confidence::High),
+                ("src/main.rs", 3, SiteKind::Call, Confidence::High),
+                ("src/loader.rs", 24, SiteKind::Call, Confidence::Medium),
+            ]
+        );
+
+        // Qualified names resolve too, and unknown symbols give an empty report
+        let qualified = ImpactReport::from_graph(&store, "crate::parse_config").unwrap();
+        assert_eq!(qualified.sites.len(), 3);
+        assert!(
+            ImpactReport::from_graph(&store, "missing")
+                .unwrap()
+                .sites
+                .is_empty()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_ambiguous_name_lowers_confidence() {
+        let store = store_with_calls();
+        store
+            .insert_node(&symbol("parse_config", "src/other.rs", 5))
+            .unwrap();
+
+        let report = ImpactReport::from_graph(&store, "parse_config").unwrap();
+        assert_eq!(report.definitions.len(), 2);
+        assert!(
+            report
+                .sites
+                .iter()
+                .filter(|s| s.kind == SiteKind::Call)
+                .all(|s| s.confidence == Confidence::Medium)
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_text_matches_skip_known_sites() {
+        let store = store_with_calls();
+        let mut report = ImpactReport::from_graph(&store, "parse_config").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/main.rs"),
+            "use crate::parse_config;\nfn main() {\n    parse_config();\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("README.md"),
+            "Call `parse_config` first. Not parse_config_v2.\n",
+        )
+        .unwrap();
+
+        let added = report.add_text_matches(dir.path());
+        // main.rs:1 (the import) and README.md:1; main.rs:3 is a known call
+        assert_eq!(added, 2);
+        let low: Vec<_> = report
+            .sites
+            .iter()
+            .filter(|s| s.confidence == Confidence::Low)
+            .map(|s| (s.file_path.as_str(), s.line))
+            .collect();
+        assert_eq!(low, vec![("README.md", 1), ("src/main.rs", 1)]);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("5 sites across 4 files (2 high, 1 medium, 2 low confidence)"));
+        assert!(markdown.contains("- `src/main.rs:3` called from crate::main"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_text_matches_relativize_absolute_graph_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "pub fn helper() {}
+",
+        )
+        .unwrap();
+        let absolute = dir.path().join("lib.rs").to_string_lossy().to_string();
+
+        let store = GraphStore::open_in_memory().unwrap();
+        store.insert_node(&symbol("helper", &absolute, 1)).unwrap();
+        let mut report = ImpactReport::from_graph(&store, "helper").unwrap();
+
+        // The definition line is the same site as the text match
+        assert_eq!(report.add_text_matches(dir.path()), 0);
+        assert_eq!(report.sites.len(), 1);
+        assert_eq!(report.sites[0].file_path, "lib.rs");
+    }
+
+    #[test]
+    fn test_contains_identifier() {
+        assert!(contains_identifier("foo(bar)", "foo"));
+        assert!(contains_identifier("x.foo", "foo"));
+        assert!(!contains_identifier("foobar", "foo"));
+        assert!(!contains_identifier("_foo", "foo"));
+        assert_eq!(short_name("a::b::c"), "c");
+        assert_eq!(short_name("Type.method"), "method");
+    }
+}
@@ -0,0 +1,82 @@
+//! Priority hints for a running index.
+//!
+//! An [`IndexQueue`] collects the paths a session is touching (proxy
+//! requests, fs tool calls) so [`GraphBuilder`](crate::GraphBuilder) can
+//! index those directories ahead of the rest of the tree and persist them
+//! straight away. The most recently touched path is taken first.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Cloneable handle shared by whatever produces hints and the builder.
+#[derive(Debug, Clone, Default)]
+pub struct IndexQueue {
+    inner: Arc<Mutex<QueueInner>>,
+}
+
+#[derive(Debug, Default)]
+struct QueueInner {
+    pending: VecDeque<PathBuf>,
+    taken: HashSet<PathBuf>,
+}
+
+impl IndexQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hint that `path` (a file or directory, relative to the index root
+    /// or absolute) is in use. A path already taken by the builder is
+    /// ignored; one still pending moves to the front.
+    pub fn push(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        let mut inner = self.inner.lock().expect("index queue lock poisoned");
+        if inner.taken.contains(&path) {
+            return;
+        }
+        inner.pending.retain(|p| p != &path);
+        inner.pending.push_back(path);
+    }
+
+    /// Take the most recent hint.
+    pub fn pop(&self) -> Option<PathBuf> {
+        let mut inner = self.inner.lock().expect("index queue lock poisoned");
+        let path = inner.pending.pop_back()?;
+        inner.taken.insert(path.clone());
+        Some(path)
+    }
+
+    /// Number of hints not yet taken.
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .expect("index queue lock poisoned")
+            .pending
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pop_takes_most_recent_once() {
+        let queue = IndexQueue::new();
+        queue.push("src/a.rs");
+        queue.push("src/b");
+        queue.push("src/a.rs");
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.pop(), Some(PathBuf::from("src/a.rs")));
+        queue.push("src/a.rs");
+        assert_eq!(queue.pop(), Some(PathBuf::from("src/b")));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+}
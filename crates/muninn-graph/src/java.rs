@@ -0,0 +1,661 @@
+//! Java structure beyond the call graph.
+//!
+//! Narsil's call graph has a node per Java method but knows nothing of
+//! the types around it. For Java files this pass adds a file node per
+//! file, the package as a module, classes, interfaces, enums, records,
+//! annotation types and constructors, and turns narsil's method nodes
+//! into methods qualified by their type (`com.example.Greeter.greet`).
+//! Files get `IMPORTS` edges to the types they import; types get
+//! `INHERITS` edges to what they extend and `IMPLEMENTS` edges to the
+//! interfaces they implement.
+//!
+//! Signatures are the declaration header with its annotations, so
+//! `n.signature CONTAINS '@Deprecated'` finds annotated symbols.
+//!
+//! Type names resolve against the types indexed in the same batch: nested
+//! types of the enclosing ones, single-type imports, the file's package,
+//! on-demand (`.*`) imports, then a name unique across the batch. JDK and
+//! library types are left out.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use muninn_narsil_vendor::tree_sitter::{Node, Tree};
+
+use crate::builder::file_node;
+use crate::edges::Edge;
+use crate::language::LanguageMap;
+use crate::symbols::{Symbol, SymbolKind, Visibility};
+
+/// Symbols and edges added by [`extract`].
+#[derive(Debug, Default)]
+pub(crate) struct Java {
+    pub symbols: Vec<Symbol>,
+    pub edges: Vec<Edge>,
+}
+
+/// Run the pass over the Java files among `files`, as `languages` maps
+/// them. `methods` are the functions already built from the call graph;
+/// the Java ones are updated in place, so this must run before their ids
+/// are used.
+pub(crate) fn extract(
+    files: &[(String, String, Tree)],
+    methods: &mut [Symbol],
+    languages: &LanguageMap,
+) -> Java {
+    let mut out = Java::default();
+    let mut units: Vec<(String, Walker)> = Vec::new();
+    for (path, content, tree) in files {
+        if !is_java(languages, path) {
+            continue;
+        }
+        let file = file_node(Path::new(path), content);
+        let mut walker = Walker {
+            path,
+            source: content.as_bytes(),
+            package: String::new(),
+            scope: Vec::new(),
+            imports: Vec::new(),
+            types: Vec::new(),
+            methods: Vec::new(),
+            symbols: Vec::new(),
+        };
+        walker.walk(tree.root_node());
+        units.push((file.id(), walker));
+        out.symbols.push(file);
+    }
+    if units.is_empty() {
+        return out;
+    }
+
+    let mut types = TypeIndex::default();
+    for (_, unit) in &units {
+        for ty in &unit.types {
+            types.insert(&ty.qualified, &ty.id);
+        }
+    }
+
+    for (file_id, unit) in &units {
+        for import in &unit.imports {
+            for target in types.imported(import) {
+                out.edges.push(Edge::imports(file_id, target, import.line));
+            }
+        }
+        for ty in &unit.types {
+            for (name, implements) in &ty.supertypes {
+                let Some(target) = types.resolve(name, unit, &ty.scope) else {
+                    continue;
+                };
+                out.edges.push(if *implements {
+                    Edge::implements(&ty.id, target)
+                } else {
+                    Edge::inherits(&ty.id, target)
+                });
+            }
+        }
+    }
+
+    let by_line: HashMap<(&str, usize, &str), &Method> = units
+        .iter()
+        .flat_map(|(_, unit)| &unit.methods)
+        .map(|m| ((m.file_path.as_str(), m.line, m.name.as_str()), m))
+        .collect();
+    for symbol in methods.iter_mut() {
+        let key = (
+            symbol.file_path.as_str(),
+            symbol.start_line,
+            symbol.name.as_str(),
+        );
+        if let Some(method) = by_line.get(&key) {
+            symbol.kind = SymbolKind::Method;
+            symbol.end_line = method.end_line;
+            symbol.qualified_name = Some(method.qualified.clone());
+            symbol.signature = Some(method.signature.clone());
+            symbol.visibility = method.visibility.clone();
+        }
+    }
+
+    for (_, unit) in units {
+        out.symbols.extend(unit.symbols);
+    }
+    out
+}
+
+/// Whether `path` is parsed with the Java grammar.
+pub(crate) fn is_java(languages: &LanguageMap, path: &str) -> bool {
+    matches!(languages.language_of(path), Some("java"))
+}
+
+/// An `import` declaration.
+struct Import {
+    /// The imported name, without `.*`.
+    path: String,
+    /// `import a.b.*;`
+    on_demand: bool,
+    /// `import static`: the last segment may be a member, not a type.
+    is_static: bool,
+    line: usize,
+}
+
+/// A type declared in a file.
+struct TypeDecl {
+    id: String,
+    qualified: String,
+    /// Qualified names of the enclosing types and the type itself,
+    /// outermost first.
+    scope: Vec<String>,
+    /// Names of the extended and implemented types as written, and
+    /// whether the type implements rather than extends them.
+    supertypes: Vec<(String, bool)>,
+}
+
+/// A method narsil already has a node for, located by file and line.
+struct Method {
+    file_path: String,
+    line: usize,
+    name: String,
+    end_line: usize,
+    qualified: String,
+    signature: String,
+    visibility: Visibility,
+}
+
+/// Collects the declarations of one file.
+struct Walker<'a> {
+    path: &'a str,
+    source: &'a [u8],
+    package: String,
+    /// Enclosing types, and whether each is an interface (members
+    /// default to public).
+    scope: Vec<(String, bool)>,
+    imports: Vec<Import>,
+    types: Vec<TypeDecl>,
+    methods: Vec<Method>,
+    symbols: Vec<Symbol>,
+}
+
+impl Walker<'_> {
+    fn text(&self, node: Node) -> &str {
+        node.utf8_text(self.source).unwrap_or("")
+    }
+
+    fn qualify(&self, name: &str) -> String {
+        match self.scope.last() {
+            Some((outer, _)) => format!("{}.{}", outer, name),
+            None if self.package.is_empty() => name.to_string(),
+            None => format!("{}.{}", self.package, name),
+        }
+    }
+
+    /// `public`, `protected` and `private` as written; otherwise public
+    /// inside an interface and package-private (crate) elsewhere.
+    fn visibility(&self, node: Node) -> Visibility {
+        let mut cursor = node.walk();
+        let modifiers = node
+            .named_children(&mut cursor)
+            .find(|c| c.kind() == "modifiers");
+        if let Some(modifiers) = modifiers {
+            let mut cursor = modifiers.walk();
+            for keyword in modifiers.children(&mut cursor) {
+                match keyword.kind() {
+                    "public" => return Visibility::Public,
+                    "private" => return Visibility::Private,
+                    "protected" => return Visibility::Restricted("protected".to_string()),
+                    _ => {}
+                }
+            }
+        }
+        if self.scope.last().is_some_and(|(_, interface)| *interface) {
+            Visibility::Public
+        } else {
+            Visibility::Crate
+        }
+    }
+
+    /// The declaration up to its body, on one line.
+    fn header(&self, node: Node) -> String {
+        let end = node
+            .child_by_field_name("body")
+            .map_or(node.end_byte(), |body| body.start_byte());
+        let text = std::str::from_utf8(&self.source[node.start_byte()..end]).unwrap_or("");
+        let header = text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches(';')
+            .to_string();
+        let mut end = header.len().min(200);
+        while !header.is_char_boundary(end) {
+            end -= 1;
+        }
+        header[..end].to_string()
+    }
+
+    fn walk(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            match child.kind() {
+                "package_declaration" => self.package(child),
+                "import_declaration" => self.import(child),
+                "class_declaration"
+                | "interface_declaration"
+                | "enum_declaration"
+                | "record_declaration"
+                | "annotation_type_declaration" => self.declare_type(child),
+                "method_declaration"
+                | "constructor_declaration"
+                | "compact_constructor_declaration" => self.member(child),
+                "enum_body_declarations" => self.walk(child),
+                // Fields, initializers, and local or anonymous classes
+                _ => {}
+            }
+        }
+    }
+
+    fn package(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let name = node
+            .named_children(&mut cursor)
+            .find(|c| matches!(c.kind(), "scoped_identifier" | "identifier"));
+        if let Some(name) = name {
+            self.package = self.text(name).to_string();
+            let symbol = Symbol::new(
+                &self.package,
+                SymbolKind::Module,
+                self.path,
+                node.start_position().row + 1,
+                node.end_position().row + 1,
+            )
+            .with_signature(self.header(node))
+            .with_qualified_name(&self.package)
+            .with_visibility(Visibility::Public);
+            self.symbols.push(symbol);
+        }
+    }
+
+    fn import(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        let Some(path) = children
+            .iter()
+            .find(|c| matches!(c.kind(), "scoped_identifier" | "identifier"))
+        else {
+            return;
+        };
+        self.imports.push(Import {
+            path: self.text(*path).to_string(),
+            on_demand: children.iter().any(|c| c.kind() == "asterisk"),
+            is_static: children.iter().any(|c| c.kind() == "static"),
+            line: node.start_position().row + 1,
+        });
+    }
+
+    fn declare_type(&mut self, node: Node) {
+        let (kind, interface) = match node.kind() {
+            "class_declaration" | "record_declaration" => (SymbolKind::Class, false),
+            "interface_declaration" | "annotation_type_declaration" => {
+                (SymbolKind::Interface, true)
+            }
+            "enum_declaration" => (SymbolKind::Enum, false),
+            _ => return,
+        };
+        let Some(name) = node.child_by_field_name("name") else {
+            return;
+        };
+        let name = self.text(name).to_string();
+        let qualified = self.qualify(&name);
+        let symbol = Symbol::new(
+            &name,
+            kind,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(self.header(node))
+        .with_qualified_name(&qualified)
+        .with_visibility(self.visibility(node));
+
+        let mut supertypes = Vec::new();
+        if let Some(superclass) = node.child_by_field_name("superclass") {
+            supertypes.extend(self.type_names(superclass).into_iter().map(|n| (n, false)));
+        }
+        if let Some(interfaces) = node.child_by_field_name("interfaces") {
+            supertypes.extend(self.type_names(interfaces).into_iter().map(|n| (n, true)));
+        }
+        let mut cursor = node.walk();
+        let extends = node
+            .named_children(&mut cursor)
+            .find(|c| c.kind() == "extends_interfaces");
+        if let Some(extends) = extends {
+            supertypes.extend(self.type_names(extends).into_iter().map(|n| (n, false)));
+        }
+
+        self.scope.push((qualified.clone(), interface));
+        self.types.push(TypeDecl {
+            id: symbol.id(),
+            qualified,
+            scope: self.scope.iter().map(|(s, _)| s.clone()).collect(),
+            supertypes,
+        });
+        self.symbols.push(symbol);
+        if let Some(body) = node.child_by_field_name("body") {
+            self.walk(body);
+        }
+        self.scope.pop();
+    }
+
+    /// Names of the types listed under a `superclass`, `super_interfaces`
+    /// or `extends_interfaces` node, without type arguments.
+    fn type_names(&self, node: Node) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            match child.kind() {
+                "type_identifier" | "scoped_type_identifier" => {
+                    names.push(self.text(child).to_string())
+                }
+                "generic_type" => {
+                    let mut cursor = child.walk();
+                    let raw = child
+                        .named_children(&mut cursor)
+                        .find(|c| matches!(c.kind(), "type_identifier" | "scoped_type_identifier"));
+                    names.extend(raw.map(|raw| self.text(raw).to_string()));
+                }
+                "type_list" => names.extend(self.type_names(child)),
+                _ => {}
+            }
+        }
+        names
+    }
+
+    /// A method or constructor of the enclosing type. Methods are narsil's
+    /// nodes and only recorded for [`extract`] to update; constructors
+    /// aren't in the call graph and become symbols here.
+    fn member(&mut self, node: Node) {
+        let (Some((owner, _)), Some(name)) = (self.scope.last(), node.child_by_field_name("name"))
+        else {
+            return;
+        };
+        let name = self.text(name).to_string();
+        let qualified = format!("{}.{}", owner, name);
+        let signature = self.header(node);
+        let visibility = self.visibility(node);
+        if node.kind() == "method_declaration" {
+            self.methods.push(Method {
+                file_path: self.path.to_string(),
+                line: node.start_position().row + 1,
+                name,
+                end_line: node.end_position().row + 1,
+                qualified,
+                signature,
+                visibility,
+            });
+        } else {
+            let symbol = Symbol::new(
+                name,
+                SymbolKind::Method,
+                self.path,
+                node.start_position().row + 1,
+                node.end_position().row + 1,
+            )
+            .with_signature(signature)
+            .with_qualified_name(qualified)
+            .with_visibility(visibility);
+            self.symbols.push(symbol);
+        }
+    }
+}
+
+/// The types of a batch by qualified and simple name.
+#[derive(Default)]
+struct TypeIndex {
+    ids: HashMap<String, String>,
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl TypeIndex {
+    fn insert(&mut self, qualified: &str, id: &str) {
+        self.ids.insert(qualified.to_string(), id.to_string());
+        let name = qualified.rsplit('.').next().unwrap_or(qualified);
+        self.by_name
+            .entry(name.to_string())
+            .or_default()
+            .push(qualified.to_string());
+    }
+
+    /// The types an import brings in: the named type, the type of a
+    /// static member, or every type directly in an on-demand package.
+    fn imported(&self, import: &Import) -> Vec<&str> {
+        if let Some(id) = self.ids.get(&import.path) {
+            return vec![id.as_str()];
+        }
+        if import.on_demand {
+            let prefix = format!("{}.", import.path);
+            let mut ids: Vec<&str> = self
+                .ids
+                .iter()
+                .filter(|(q, _)| q.strip_prefix(&prefix).is_some_and(|n| !n.contains('.')))
+                .map(|(_, id)| id.as_str())
+                .collect();
+            ids.sort_unstable();
+            return ids;
+        }
+        if import.is_static
+            && let Some((owner, _)) = import.path.rsplit_once('.')
+            && let Some(id) = self.ids.get(owner)
+        {
+            return vec![id.as_str()];
+        }
+        Vec::new()
+    }
+
+    /// The type `name` (as written in `unit`, inside the types `scope`)
+    /// refers to.
+    fn resolve(&self, name: &str, unit: &Walker, scope: &[String]) -> Option<&str> {
+        let (first, rest) = match name.split_once('.') {
+            Some((first, rest)) => (first, format!(".{}", rest)),
+            None => (name, String::new()),
+        };
+        let single = unit
+            .imports
+            .iter()
+            .filter(|i| !i.on_demand)
+            .filter(|i| i.path.rsplit('.').next() == Some(first))
+            .map(|i| format!("{}{}", i.path, rest));
+        let package = if unit.package.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", unit.package, name)
+        };
+        let on_demand = unit
+            .imports
+            .iter()
+            .filter(|i| i.on_demand)
+            .map(|i| format!("{}.{}", i.path, name));
+        let candidates = scope
+            .iter()
+            .rev()
+            .map(|outer| format!("{}.{}", outer, name))
+            .chain(single)
+            .chain([package, name.to_string()])
+            .chain(on_demand);
+        for candidate in candidates {
+            if let Some(id) = self.ids.get(&candidate) {
+                return Some(id);
+            }
+        }
+        match self.by_name.get(name).map(Vec::as_slice) {
+            Some([only]) => self.ids.get(only).map(String::as_str),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphBuilder, GraphStore, store::node_str};
+    use serial_test::serial;
+
+    fn build(files: &[(&str, &str)]) -> (tempfile::TempDir, GraphStore) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+        (dir, builder.into_store())
+    }
+
+    fn pairs(store: &GraphStore, rel: &str) -> Vec<(String, String)> {
+        let rows = store
+            .query(&format!(
+                "MATCH (a)-[:{}]->(b) RETURN a.name AS src, b.name AS dst",
+                rel
+            ))
+            .unwrap();
+        let mut pairs: Vec<(String, String)> = rows
+            .iter()
+            .map(|r| (r.get("src").unwrap(), r.get("dst").unwrap()))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Property `key` of the one node named `name` of `kind`.
+    fn prop(store: &GraphStore, name: &str, kind: &str, key: &str) -> Option<String> {
+        let nodes = store.find_by_name(name).unwrap();
+        let found: Vec<_> = nodes
+            .iter()
+            .filter(|n| node_str(n, "kind") == Some(kind))
+            .collect();
+        assert_eq!(found.len(), 1, "{} {}: {:?}", kind, name, nodes);
+        node_str(found[0], key).map(str::to_string)
+    }
+
+    fn pair(src: &str, dst: &str) -> (String, String) {
+        (src.to_string(), dst.to_string())
+    }
+
+    #[test]
+    #[serial]
+    fn test_java_types_imports_and_hierarchy() {
+        let (_dir, store) = build(&[
+            (
+                "src/main/java/com/example/model/Named.java",
+                "package com.example.model;\n\n\
+                 public interface Named extends Comparable<Named> {\n    String name();\n}\n",
+            ),
+            (
+                "src/main/java/com/example/model/Base.java",
+                "package com.example.model;\n\n\
+                 public abstract class Base<T> {\n    protected abstract T raw();\n}\n",
+            ),
+            (
+                "src/main/java/com/example/demo/Greeter.java",
+                "package com.example.demo;\n\n\
+                 import java.util.List;\nimport com.example.model.Base;\nimport com.example.model.*;\n\n\
+                 @Service\npublic class Greeter extends Base<String> implements Named {\n\
+                 \x20   private final String prefix;\n\n\
+                 \x20   Greeter(String prefix) { this.prefix = prefix; }\n\n\
+                 \x20   @Override\n    public String name() { return prefix; }\n\n\
+                 \x20   @Deprecated\n    protected String value() { return name(); }\n\n\
+                 \x20   static class Loud extends Greeter {\n\
+                 \x20       Loud() { super(\"!\"); }\n    }\n}\n",
+            ),
+        ]);
+
+        assert_eq!(
+            prop(&store, "com.example.demo", "module", "qualified_name").as_deref(),
+            Some("com.example.demo")
+        );
+        assert_eq!(
+            prop(&store, "Greeter", "class", "qualified_name").as_deref(),
+            Some("com.example.demo.Greeter")
+        );
+        assert_eq!(
+            prop(&store, "Loud", "class", "qualified_name").as_deref(),
+            Some("com.example.demo.Greeter.Loud")
+        );
+        assert_eq!(
+            prop(&store, "Named", "interface", "visibility").as_deref(),
+            Some("public")
+        );
+
+        // Narsil's method nodes are qualified, with annotated signatures
+        assert_eq!(
+            prop(&store, "value", "method", "signature").as_deref(),
+            Some("@Deprecated protected String value()")
+        );
+        assert_eq!(
+            prop(&store, "value", "method", "qualified_name").as_deref(),
+            Some("com.example.demo.Greeter.value")
+        );
+        assert_eq!(
+            prop(&store, "Loud", "method", "visibility").as_deref(),
+            Some("crate")
+        );
+
+        // `Comparable` and `List` aren't in the tree; `Base` is imported
+        // twice but linked once
+        assert_eq!(
+            pairs(&store, "INHERITS"),
+            [pair("Greeter", "Base"), pair("Loud", "Greeter")]
+        );
+        assert_eq!(pairs(&store, "IMPLEMENTS"), [pair("Greeter", "Named")]);
+        assert_eq!(
+            pairs(&store, "IMPORTS"),
+            [pair("Greeter.java", "Base"), pair("Greeter.java", "Named")]
+        );
+    }
+
+    #[test]
+    fn test_resolution_prefers_imports_over_unique_names() {
+        let mut types = TypeIndex::default();
+        types.insert("a.Shape", "a_shape");
+        types.insert("b.Shape", "b_shape");
+        types.insert("b.Shape.Edge", "b_edge");
+        types.insert("c.Util", "c_util");
+
+        let unit = Walker {
+            path: "/r/x/Main.java",
+            source: b"",
+            package: "x".to_string(),
+            scope: Vec::new(),
+            imports: vec![Import {
+                path: "b.Shape".to_string(),
+                on_demand: false,
+                is_static: false,
+                line: 2,
+            }],
+            types: Vec::new(),
+            methods: Vec::new(),
+            symbols: Vec::new(),
+        };
+        assert_eq!(types.resolve("Shape", &unit, &[]), Some("b_shape"));
+        assert_eq!(types.resolve("Shape.Edge", &unit, &[]), Some("b_edge"));
+        assert_eq!(types.resolve("a.Shape", &unit, &[]), Some("a_shape"));
+        assert_eq!(types.resolve("Util", &unit, &[]), Some("c_util"));
+        assert_eq!(types.resolve("Missing", &unit, &[]), None);
+
+        let import = |path: &str, on_demand, is_static| Import {
+            path: path.to_string(),
+            on_demand,
+            is_static,
+            line: 1,
+        };
+        assert_eq!(
+            types.imported(&import("b.Shape.area", false, true)),
+            ["b_shape"]
+        );
+        assert_eq!(types.imported(&import("b", true, false)), ["b_shape"]);
+        assert!(
+            types
+                .imported(&import("java.util.List", false, false))
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,730 @@
+//! Kotlin structure beyond the call graph.
+//!
+//! Narsil's call graph has a node per Kotlin `fun`. Calls made inside
+//! lambdas count as calls of the enclosing function, so a `launch { }`,
+//! `async { }` or `withContext(io) { }` block doesn't cut its body off
+//! from the caller. This pass adds a file node per file, the package as a
+//! module, classes, interfaces, enum classes, objects, companion objects
+//! and secondary constructors. Narsil's function nodes are qualified:
+//! members by their type (`com.example.Greeter.greet`), extension
+//! functions by their receiver (`com.example.String.shout`).
+//! Files get `IMPORTS` edges to the types they import. Types get
+//! `INHERITS` edges to the classes and interfaces they extend, and
+//! `IMPLEMENTS` edges to the interfaces a class or object implements.
+//!
+//! Signatures are the declaration header with its modifiers, so
+//! `n.signature CONTAINS 'suspend'` finds the coroutines.
+//!
+//! Type names resolve against the types indexed in the same batch: nested
+//! types of the enclosing ones, imports (with their `as` aliases), the
+//! file's package, star imports, then a name unique across the batch.
+//! Kotlin, JDK and library types are left out.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use muninn_narsil_vendor::tree_sitter::{Node, Tree};
+
+use crate::builder::file_node;
+use crate::edges::Edge;
+use crate::language::LanguageMap;
+use crate::symbols::{Symbol, SymbolKind, Visibility};
+
+/// Symbols and edges added by [`extract`].
+#[derive(Debug, Default)]
+pub(crate) struct Kotlin {
+    pub symbols: Vec<Symbol>,
+    pub edges: Vec<Edge>,
+}
+
+/// Run the pass over the Kotlin files among `files`, as `languages` maps
+/// them. `functions` are the functions already built from the call
+/// graph; the Kotlin ones are updated in place, so this must run before
+/// their ids are used.
+pub(crate) fn extract(
+    files: &[(String, String, Tree)],
+    functions: &mut [Symbol],
+    languages: &LanguageMap,
+) -> Kotlin {
+    let mut out = Kotlin::default();
+    let mut units: Vec<(String, Walker)> = Vec::new();
+    for (path, content, tree) in files {
+        if !is_kotlin(languages, path) {
+            continue;
+        }
+        let file = file_node(Path::new(path), content);
+        let mut walker = Walker {
+            path,
+            source: content.as_bytes(),
+            package: String::new(),
+            scope: Vec::new(),
+            imports: Vec::new(),
+            types: Vec::new(),
+            functions: Vec::new(),
+            symbols: Vec::new(),
+        };
+        walker.walk(tree.root_node());
+        units.push((file.id(), walker));
+        out.symbols.push(file);
+    }
+    if units.is_empty() {
+        return out;
+    }
+
+    let mut types = TypeIndex::default();
+    for (_, unit) in &units {
+        for ty in &unit.types {
+            types.insert(&ty.qualified, &ty.id, ty.interface);
+        }
+    }
+
+    for (file_id, unit) in &units {
+        for import in &unit.imports {
+            for target in types.imported(import) {
+                out.edges.push(Edge::imports(file_id, target, import.line));
+            }
+        }
+        for ty in &unit.types {
+            for name in &ty.supertypes {
+                let Some((target, interface)) = types.resolve(name, unit, &ty.scope) else {
+                    continue;
+                };
+                out.edges.push(if interface && !ty.interface {
+                    Edge::implements(&ty.id, target)
+                } else {
+                    Edge::inherits(&ty.id, target)
+                });
+            }
+        }
+    }
+
+    let by_line: HashMap<(&str, usize, &str), &Function> = units
+        .iter()
+        .flat_map(|(_, unit)| &unit.functions)
+        .map(|f| ((f.file_path.as_str(), f.line, f.name.as_str()), f))
+        .collect();
+    for symbol in functions.iter_mut() {
+        let key = (
+            symbol.file_path.as_str(),
+            symbol.start_line,
+            symbol.name.as_str(),
+        );
+        if let Some(function) = by_line.get(&key) {
+            if function.member {
+                symbol.kind = SymbolKind::Method;
+            }
+            symbol.end_line = function.end_line;
+            symbol.qualified_name = Some(function.qualified.clone());
+            symbol.signature = Some(function.signature.clone());
+            symbol.visibility = function.visibility.clone();
+        }
+    }
+
+    for (_, unit) in units {
+        out.symbols.extend(unit.symbols);
+    }
+    out
+}
+
+/// Whether `path` is parsed with the Kotlin grammar.
+pub(crate) fn is_kotlin(languages: &LanguageMap, path: &str) -> bool {
+    matches!(languages.language_of(path), Some("kotlin"))
+}
+
+/// An `import` directive.
+struct Import {
+    /// The imported name, without `.*`.
+    path: String,
+    /// `import a.b.*`
+    on_demand: bool,
+    /// `import a.b.C as D`
+    alias: Option<String>,
+    line: usize,
+}
+
+/// A class, interface or object declared in a file.
+struct TypeDecl {
+    id: String,
+    qualified: String,
+    interface: bool,
+    /// Qualified names of the enclosing types and the type itself,
+    /// outermost first.
+    scope: Vec<String>,
+    /// Names of the types in the delegation specifiers, as written and
+    /// without type arguments.
+    supertypes: Vec<String>,
+}
+
+/// A function narsil already has a node for, located by file and line.
+struct Function {
+    file_path: String,
+    line: usize,
+    name: String,
+    end_line: usize,
+    qualified: String,
+    signature: String,
+    visibility: Visibility,
+    /// Declared in a class, interface or object body.
+    member: bool,
+}
+
+/// Collects the declarations of one file.
+struct Walker<'a> {
+    path: &'a str,
+    source: &'a [u8],
+    package: String,
+    /// Qualified names of the enclosing types.
+    scope: Vec<String>,
+    imports: Vec<Import>,
+    types: Vec<TypeDecl>,
+    functions: Vec<Function>,
+    symbols: Vec<Symbol>,
+}
+
+impl Walker<'_> {
+    fn text(&self, node: Node) -> &str {
+        node.utf8_text(self.source).unwrap_or("")
+    }
+
+    fn qualify(&self, name: &str) -> String {
+        match self.scope.last() {
+            Some(outer) => format!("{}.{}", outer, name),
+            None if self.package.is_empty() => name.to_string(),
+            None => format!("{}.{}", self.package, name),
+        }
+    }
+
+    /// The visibility modifier as written; Kotlin declarations are public
+    /// without one. `internal` (visible in the module) maps to crate.
+    fn visibility(&self, node: Node) -> Visibility {
+        let mut cursor = node.walk();
+        let modifiers = node
+            .named_children(&mut cursor)
+            .find(|c| c.kind() == "modifiers");
+        if let Some(modifiers) = modifiers {
+            let mut cursor = modifiers.walk();
+            for modifier in modifiers.named_children(&mut cursor) {
+                if modifier.kind() != "visibility_modifier" {
+                    continue;
+                }
+                match self.text(modifier) {
+                    "private" => return Visibility::Private,
+                    "internal" => return Visibility::Crate,
+                    "protected" => return Visibility::Restricted("protected".to_string()),
+                    _ => {}
+                }
+            }
+        }
+        Visibility::Public
+    }
+
+    /// The declaration up to its body, on one line.
+    fn header(&self, node: Node) -> String {
+        let mut cursor = node.walk();
+        let end = node
+            .named_children(&mut cursor)
+            .find(|c| {
+                matches!(
+                    c.kind(),
+                    "class_body" | "enum_class_body" | "function_body" | "block"
+                )
+            })
+            .map_or(node.end_byte(), |body| body.start_byte());
+        let text = std::str::from_utf8(&self.source[node.start_byte()..end]).unwrap_or("");
+        let header = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut end = header.len().min(200);
+        while !header.is_char_boundary(end) {
+            end -= 1;
+        }
+        header[..end].to_string()
+    }
+
+    /// The name of a type as written (`com.x.Base` for
+    /// `com.x.Base<T>`), looking through `?` and parentheses.
+    fn type_name(&self, node: Node) -> Option<String> {
+        let mut cursor = node.walk();
+        if node.kind() == "user_type" {
+            let segments: Vec<&str> = node
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "identifier")
+                .map(|c| self.text(c))
+                .collect();
+            return Some(segments.join("."));
+        }
+        let inner: Vec<Node> = node.named_children(&mut cursor).collect();
+        inner.into_iter().find_map(|c| self.type_name(c))
+    }
+
+    fn walk(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            match child.kind() {
+                "package_header" => self.package(child),
+                "import" => self.import(child),
+                "class_declaration" | "object_declaration" | "companion_object" => {
+                    self.declare_type(child)
+                }
+                "function_declaration" => self.function(child),
+                "secondary_constructor" => self.constructor(child),
+                // Properties, initializers, enum entries and type aliases
+                _ => {}
+            }
+        }
+    }
+
+    fn package(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let name = node
+            .named_children(&mut cursor)
+            .find(|c| matches!(c.kind(), "qualified_identifier" | "identifier"));
+        if let Some(name) = name {
+            self.package = self.text(name).to_string();
+            let symbol = Symbol::new(
+                &self.package,
+                SymbolKind::Module,
+                self.path,
+                node.start_position().row + 1,
+                node.end_position().row + 1,
+            )
+            .with_signature(self.header(node))
+            .with_qualified_name(&self.package)
+            .with_visibility(Visibility::Public);
+            self.symbols.push(symbol);
+        }
+    }
+
+    fn import(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        let Some(path) = children
+            .iter()
+            .find(|c| matches!(c.kind(), "qualified_identifier" | "identifier"))
+        else {
+            return;
+        };
+        let alias = children
+            .iter()
+            .skip_while(|c| c.kind() != "as")
+            .find(|c| c.kind() == "identifier")
+            .map(|c| self.text(*c).to_string());
+        self.imports.push(Import {
+            path: self.text(*path).to_string(),
+            on_demand: children.iter().any(|c| c.kind() == "*"),
+            alias,
+            line: node.start_position().row + 1,
+        });
+    }
+
+    fn declare_type(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        let is_enum = children
+            .iter()
+            .filter(|c| c.kind() == "modifiers")
+            .any(|m| {
+                let mut cursor = m.walk();
+                m.named_children(&mut cursor)
+                    .any(|c| c.kind() == "class_modifier" && self.text(c) == "enum")
+            });
+        let interface = children.iter().any(|c| c.kind() == "interface");
+        let kind = if interface {
+            SymbolKind::Interface
+        } else if is_enum {
+            SymbolKind::Enum
+        } else {
+            SymbolKind::Class
+        };
+        let name = match node.child_by_field_name("name") {
+            Some(name) => self.text(name).to_string(),
+            None if node.kind() == "companion_object" => "Companion".to_string(),
+            None => return,
+        };
+        let qualified = self.qualify(&name);
+        let symbol = Symbol::new(
+            &name,
+            kind,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(self.header(node))
+        .with_qualified_name(&qualified)
+        .with_visibility(self.visibility(node));
+
+        let mut supertypes = Vec::new();
+        if let Some(specifiers) = children
+            .iter()
+            .find(|c| c.kind() == "delegation_specifiers")
+        {
+            let mut cursor = specifiers.walk();
+            for specifier in specifiers.named_children(&mut cursor) {
+                // `Base()`, `Named` and `Named by impl` all name the type
+                // first
+                let mut cursor = specifier.walk();
+                let first = specifier.named_children(&mut cursor).next();
+                supertypes.extend(first.and_then(|ty| self.type_name(ty)));
+            }
+        }
+
+        self.scope.push(qualified.clone());
+        self.types.push(TypeDecl {
+            id: symbol.id(),
+            qualified,
+            interface,
+            scope: self.scope.clone(),
+            supertypes,
+        });
+        self.symbols.push(symbol);
+        let body = children
+            .iter()
+            .find(|c| matches!(c.kind(), "class_body" | "enum_class_body"));
+        if let Some(body) = body {
+            self.walk(*body);
+        }
+        self.scope.pop();
+    }
+
+    /// A `fun` in a file or type body. Narsil has the node; this records
+    /// its qualified name for [`extract`] to apply.
+    fn function(&mut self, node: Node) {
+        let Some(name_node) = node.child_by_field_name("name") else {
+            return;
+        };
+        let name = self.text(name_node).to_string();
+        // The receiver is the type written between `fun` and the name
+        let mut cursor = node.walk();
+        let receiver = node
+            .named_children(&mut cursor)
+            .take_while(|c| c.id() != name_node.id())
+            .filter(|c| !matches!(c.kind(), "modifiers" | "type_parameters"))
+            .last()
+            .and_then(|ty| self.type_name(ty));
+        let member = !self.scope.is_empty();
+        let qualified = match receiver {
+            Some(receiver) if !member => self.qualify(&format!("{}.{}", receiver, name)),
+            _ => self.qualify(&name),
+        };
+        self.functions.push(Function {
+            file_path: self.path.to_string(),
+            line: node.start_position().row + 1,
+            name,
+            end_line: node.end_position().row + 1,
+            qualified,
+            signature: self.header(node),
+            visibility: self.visibility(node),
+            member,
+        });
+    }
+
+    /// A secondary constructor, named after its type. They aren't in the
+    /// call graph and become symbols here.
+    fn constructor(&mut self, node: Node) {
+        let Some(owner) = self.scope.last() else {
+            return;
+        };
+        let name = owner.rsplit('.').next().unwrap_or(owner).to_string();
+        let symbol = Symbol::new(
+            &name,
+            SymbolKind::Method,
+            self.path,
+            node.start_position().row + 1,
+            node.end_position().row + 1,
+        )
+        .with_signature(self.header(node))
+        .with_qualified_name(format!("{}.{}", owner, name))
+        .with_visibility(self.visibility(node));
+        self.symbols.push(symbol);
+    }
+}
+
+/// The types of a batch by qualified and simple name.
+#[derive(Default)]
+struct TypeIndex {
+    /// Id and whether the type is an interface, by qualified name.
+    ids: HashMap<String, (String, bool)>,
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl TypeIndex {
+    fn insert(&mut self, qualified: &str, id: &str, interface: bool) {
+        self.ids
+            .insert(qualified.to_string(), (id.to_string(), interface));
+        let name = qualified.rsplit('.').next().unwrap_or(qualified);
+        self.by_name
+            .entry(name.to_string())
+            .or_default()
+            .push(qualified.to_string());
+    }
+
+    fn id(&self, qualified: &str) -> Option<&str> {
+        self.ids.get(qualified).map(|(id, _)| id.as_str())
+    }
+
+    /// The types an import brings in: the named type, the type owning an
+    /// imported member, or every type directly in a star-imported package.
+    fn imported(&self, import: &Import) -> Vec<&str> {
+        if import.on_demand {
+            let prefix = format!("{}.", import.path);
+            let mut ids: Vec<&str> = self
+                .ids
+                .iter()
+                .filter(|(q, _)| q.strip_prefix(&prefix).is_some_and(|n| !n.contains('.')))
+                .map(|(_, (id, _))| id.as_str())
+                .collect();
+            ids.sort_unstable();
+            return ids;
+        }
+        let owner = import.path.rsplit_once('.').map(|(owner, _)| owner);
+        self.id(&import.path)
+            .or_else(|| owner.and_then(|owner| self.id(owner)))
+            .into_iter()
+            .collect()
+    }
+
+    /// The type `name` (as written in `unit`, inside the types `scope`)
+    /// refers to, and whether it is an interface.
+    fn resolve(&self, name: &str, unit: &Walker, scope: &[String]) -> Option<(&str, bool)> {
+        let (first, rest) = match name.split_once('.') {
+            Some((first, rest)) => (first, format!(".{}", rest)),
+            None => (name, String::new()),
+        };
+        let single = unit
+            .imports
+            .iter()
+            .filter(|i| !i.on_demand)
+            .filter(|i| {
+                let local = i.alias.as_deref().or_else(|| i.path.rsplit('.').next());
+                local == Some(first)
+            })
+            .map(|i| format!("{}{}", i.path, rest));
+        let package = if unit.package.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", unit.package, name)
+        };
+        let on_demand = unit
+            .imports
+            .iter()
+            .filter(|i| i.on_demand)
+            .map(|i| format!("{}.{}", i.path, name));
+        let candidates = scope
+            .iter()
+            .rev()
+            .map(|outer| format!("{}.{}", outer, name))
+            .chain(single)
+            .chain([package, name.to_string()])
+            .chain(on_demand);
+        let found = candidates
+            .into_iter()
+            .find_map(|candidate| self.ids.get(&candidate))
+            .or_else(|| match self.by_name.get(name).map(Vec::as_slice) {
+                Some([only]) => self.ids.get(only),
+                _ => None,
+            });
+        found.map(|(id, interface)| (id.as_str(), *interface))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphBuilder, GraphStore, store::node_str};
+    use serial_test::serial;
+
+    fn build(files: &[(&str, &str)]) -> (tempfile::TempDir, GraphStore) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let mut builder = GraphBuilder::new(GraphStore::open_in_memory().unwrap()).unwrap();
+        builder.build_directory(dir.path()).unwrap();
+        (dir, builder.into_store())
+    }
+
+    fn pairs(store: &GraphStore, rel: &str) -> Vec<(String, String)> {
+        let rows = store
+            .query(&format!(
+                "MATCH (a)-[:{}]->(b) RETURN a.name AS src, b.name AS dst",
+                rel
+            ))
+            .unwrap();
+        let mut pairs: Vec<(String, String)> = rows
+            .iter()
+            .map(|r| (r.get("src").unwrap(), r.get("dst").unwrap()))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Property `key` of the one node named `name` of `kind`.
+    fn prop(store: &GraphStore, name: &str, kind: &str, key: &str) -> Option<String> {
+        let nodes = store.find_by_name(name).unwrap();
+        let found: Vec<_> = nodes
+            .iter()
+            .filter(|n| node_str(n, "kind") == Some(kind))
+            .collect();
+        assert_eq!(found.len(), 1, "{} {}: {:?}", kind, name, nodes);
+        node_str(found[0], key).map(str::to_string)
+    }
+
+    fn pair(src: &str, dst: &str) -> (String, String) {
+        (src.to_string(), dst.to_string())
+    }
+
+    #[test]
+    #[serial]
+    fn test_kotlin_types_objects_and_extensions() {
+        let (_dir, store) = build(&[
+            (
+                "src/main/kotlin/com/example/model/Model.kt",
+                "package com.example.model\n\n\
+                 interface Named : Comparable<Named> {\n    fun name(): String\n}\n\n\
+                 abstract class Base<T> {\n    protected abstract fun raw(): T\n}\n",
+            ),
+            (
+                "src/main/kotlin/com/example/demo/Greeter.kt",
+                "package com.example.demo\n\n\
+                 import kotlinx.coroutines.delay\n\
+                 import com.example.model.Base as Root\n\
+                 import com.example.model.*\n\n\
+                 class Greeter(private val prefix: String) : Root<String>(), Named {\n\
+                 \x20   constructor(count: Int) : this(\"x\".repeat(count))\n\n\
+                 \x20   override fun name(): String = prefix\n\n\
+                 \x20   internal suspend fun greet(): String {\n\
+                 \x20       delay(10)\n        return name()\n    }\n\n\
+                 \x20   companion object : Named {\n\
+                 \x20       override fun name() = \"default\"\n    }\n}\n\n\
+                 object Registry {\n    fun all(): List<Greeter> = emptyList()\n}\n\n\
+                 enum class Tone {\n    LOUD,\n    SOFT\n}\n\n\
+                 fun String.shout(): String = uppercase()\n",
+            ),
+        ]);
+
+        assert_eq!(
+            prop(&store, "com.example.demo", "module", "qualified_name").as_deref(),
+            Some("com.example.demo")
+        );
+        assert_eq!(
+            prop(&store, "Greeter", "class", "qualified_name").as_deref(),
+            Some("com.example.demo.Greeter")
+        );
+        assert_eq!(
+            prop(&store, "Companion", "class", "qualified_name").as_deref(),
+            Some("com.example.demo.Greeter.Companion")
+        );
+        assert_eq!(
+            prop(&store, "Registry", "class", "signature").as_deref(),
+            Some("object Registry")
+        );
+        assert!(prop(&store, "Tone", "enum", "qualified_name").is_some());
+        assert!(prop(&store, "Named", "interface", "qualified_name").is_some());
+
+        // Narsil's function nodes are qualified, with modifiers in the
+        // signature
+        assert_eq!(
+            prop(&store, "greet", "method", "signature").as_deref(),
+            Some("internal suspend fun greet(): String")
+        );
+        assert_eq!(
+            prop(&store, "greet", "method", "visibility").as_deref(),
+            Some("crate")
+        );
+        assert_eq!(
+            prop(&store, "all", "method", "qualified_name").as_deref(),
+            Some("com.example.demo.Registry.all")
+        );
+        assert_eq!(
+            prop(&store, "shout", "function", "qualified_name").as_deref(),
+            Some("com.example.demo.String.shout")
+        );
+        assert_eq!(
+            prop(&store, "Greeter", "method", "signature").as_deref(),
+            Some("constructor(count: Int) : this(\"x\".repeat(count))")
+        );
+
+        // `Comparable` isn't in the tree; `Root` is an alias of `Base`,
+        // which is also star-imported but linked once
+        assert_eq!(pairs(&store, "INHERITS"), [pair("Greeter", "Base")]);
+        assert_eq!(
+            pairs(&store, "IMPLEMENTS"),
+            [pair("Companion", "Named"), pair("Greeter", "Named")]
+        );
+        assert_eq!(
+            pairs(&store, "IMPORTS"),
+            [pair("Greeter.kt", "Base"), pair("Greeter.kt", "Named")]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_calls_inside_coroutine_builders_belong_to_the_function() {
+        let (_dir, store) = build(&[(
+            "src/main/kotlin/Sync.kt",
+            "class Repo {\n    suspend fun load(): Int = 1\n}\n\n\
+             suspend fun save(value: Int) {}\n\n\
+             fun sync(scope: CoroutineScope, repo: Repo) {\n\
+             \x20   scope.launch {\n\
+             \x20       val value = async { repo.load() }.await()\n\
+             \x20       withContext(Dispatchers.IO) {\n            save(value)\n        }\n    }\n}\n",
+        )]);
+
+        assert_eq!(
+            pairs(&store, "CALLS"),
+            [pair("sync", "load"), pair("sync", "save")]
+        );
+    }
+
+    #[test]
+    fn test_resolution_follows_aliases_and_reports_interfaces() {
+        let mut types = TypeIndex::default();
+        types.insert("a.Shape", "a_shape", true);
+        types.insert("b.Shape", "b_shape", false);
+        types.insert("b.Shape.Edge", "b_edge", false);
+
+        let unit = Walker {
+            path: "/r/x/Main.kt",
+            source: b"",
+            package: "x".to_string(),
+            scope: Vec::new(),
+            imports: vec![Import {
+                path: "b.Shape".to_string(),
+                on_demand: false,
+                alias: Some("Outline".to_string()),
+                line: 2,
+            }],
+            types: Vec::new(),
+            functions: Vec::new(),
+            symbols: Vec::new(),
+        };
+        assert_eq!(
+            types.resolve("Outline", &unit, &[]),
+            Some(("b_shape", false))
+        );
+        assert_eq!(
+            types.resolve("Outline.Edge", &unit, &[]),
+            Some(("b_edge", false))
+        );
+        assert_eq!(
+            types.resolve("a.Shape", &unit, &[]),
+            Some(("a_shape", true))
+        );
+        // Two types named `Shape` and no import of that name
+        assert_eq!(types.resolve("Shape", &unit, &[]), None);
+
+        let import = |path: &str| Import {
+            path: path.to_string(),
+            on_demand: false,
+            alias: None,
+            line: 1,
+        };
+        assert_eq!(types.imported(&import("b.Shape.Companion")), ["b_shape"]);
+        assert!(
+            types
+                .imported(&import("kotlinx.coroutines.launch"))
+                .is_empty()
+        );
+    }
+}
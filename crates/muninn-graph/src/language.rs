@@ -0,0 +1,294 @@
+//! Deciding what the builder does with a file.
+//!
+//! Most files are recognised by extension. Files without a known extension
+//! fall back to their name (`Dockerfile`, `Makefile`, `BUILD`, ...) and then
+//! to a `#!` line, so build scripts and tools under `bin/` still reach the
+//! graph: as parsed code when narsil has the grammar, as a bare file node
+//! otherwise.
+//!
+//! The extension table is a [`LanguageMap`]: [`DEFAULT_EXTENSIONS`] with
+//! the `[graph.languages]` overrides from the config applied on top.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+/// Grammars a file can be parsed with, by the names `[graph.languages]`
+/// uses.
+pub const LANGUAGES: &[&str] = &[
+    "rust",
+    "python",
+    "javascript",
+    "typescript",
+    "tsx",
+    "go",
+    "c",
+    "cpp",
+    "java",
+    "kotlin",
+    "csharp",
+];
+
+/// Value of a `[graph.languages]` entry that stops an extension from being
+/// indexed.
+pub const DISABLED: &str = "none";
+
+/// Extensions recognised out of the box, with their grammar.
+pub const DEFAULT_EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("py", "python"),
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("ts", "typescript"),
+    ("tsx", "tsx"),
+    ("go", "go"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cpp", "cpp"),
+    ("cc", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("hh", "cpp"),
+    ("hxx", "cpp"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("cs", "csharp"),
+];
+
+/// A `[graph.languages]` entry naming a grammar muninn doesn't have.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "unknown language '{language}' for extension '{extension}' (expected one of: {}, or \"{DISABLED}\")",
+    LANGUAGES.join(", ")
+)]
+pub struct UnknownLanguage {
+    pub extension: String,
+    pub language: String,
+}
+
+/// How a file is indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// Parsed with the narsil grammar of this name.
+    Code(&'static str),
+    /// No grammar; stored as a single file node. The label names the format
+    /// (`"dockerfile"`, `"shell"`, ...).
+    FileOnly(&'static str),
+}
+
+/// Which grammar each file extension is parsed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageMap {
+    extensions: BTreeMap<String, &'static str>,
+}
+
+impl Default for LanguageMap {
+    fn default() -> Self {
+        Self {
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|(ext, language)| (ext.to_string(), *language))
+                .collect(),
+        }
+    }
+}
+
+impl LanguageMap {
+    /// [`DEFAULT_EXTENSIONS`] with `overrides` applied. Each maps an
+    /// extension (with or without the leading dot) to one of [`LANGUAGES`],
+    /// or to [`DISABLED`] to leave its files out.
+    pub fn with_overrides<'a>(
+        overrides: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, UnknownLanguage> {
+        let mut map = Self::default();
+        for (extension, language) in overrides {
+            let extension = extension.trim_start_matches('.');
+            if language == DISABLED {
+                map.extensions.remove(extension);
+                continue;
+            }
+            let Some(language) = LANGUAGES.iter().find(|l| **l == language) else {
+                return Err(UnknownLanguage {
+                    extension: extension.to_string(),
+                    language: language.to_string(),
+                });
+            };
+            map.extensions.insert(extension.to_string(), language);
+        }
+        Ok(map)
+    }
+
+    /// Grammar for files ending in `.{ext}`.
+    pub fn language_for_extension(&self, ext: &str) -> Option<&'static str> {
+        self.extensions.get(ext).copied()
+    }
+
+    /// Every indexed extension with its grammar, by extension.
+    pub fn extensions(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        self.extensions.iter().map(|(ext, l)| (ext.as_str(), *l))
+    }
+
+    /// Detect from the path alone, or from `first_line` when the path says
+    /// nothing.
+    pub fn detect(&self, path: &Path, first_line: Option<&str>) -> Option<SourceKind> {
+        let name = path.file_name()?.to_str()?;
+        if let Some(label) = build_file_label(name) {
+            return Some(SourceKind::FileOnly(label));
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => self.language_for_extension(ext).map(SourceKind::Code),
+            None => first_line.and_then(detect_shebang),
+        }
+    }
+
+    /// [`detect`](Self::detect), reading the first line of `path` only when
+    /// it has no extension.
+    pub fn detect_file(&self, path: &Path) -> Option<SourceKind> {
+        if path.extension().is_some() {
+            return self.detect(path, None);
+        }
+        let mut head = Vec::with_capacity(256);
+        std::fs::File::open(path)
+            .and_then(|f| f.take(256).read_to_end(&mut head))
+            .ok()?;
+        let head = String::from_utf8_lossy(&head);
+        self.detect(path, head.lines().next())
+    }
+
+    /// Grammar `path` is parsed with, judged by its extension.
+    pub(crate) fn language_of(&self, path: &str) -> Option<&'static str> {
+        match self.detect(Path::new(path), None)? {
+            SourceKind::Code(language) => Some(language),
+            SourceKind::FileOnly(_) => None,
+        }
+    }
+}
+
+/// [`LanguageMap::detect`] with the default extensions.
+pub fn detect(path: &Path, first_line: Option<&str>) -> Option<SourceKind> {
+    LanguageMap::default().detect(path, first_line)
+}
+
+/// [`LanguageMap::detect_file`] with the default extensions.
+pub fn detect_file(path: &Path) -> Option<SourceKind> {
+    LanguageMap::default().detect_file(path)
+}
+
+fn build_file_label(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Dockerfile" | "Containerfile" => "dockerfile",
+        _ if name.starts_with("Dockerfile.") => "dockerfile",
+        "Makefile" | "makefile" | "GNUmakefile" => "make",
+        "BUILD" | "BUILD.bazel" | "WORKSPACE" | "WORKSPACE.bazel" | "MODULE.bazel" => "bazel",
+        "CMakeLists.txt" => "cmake",
+        "Justfile" | "justfile" => "just",
+        "Jenkinsfile" => "jenkins",
+        "Rakefile" | "Gemfile" | "Vagrantfile" => "ruby",
+        _ => return None,
+    })
+}
+
+/// Map `#!/usr/bin/env python3` and the like to a kind by interpreter.
+fn detect_shebang(line: &str) -> Option<SourceKind> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip `-S` and friends and `VAR=value` assignments
+        interpreter = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    // python3.12 -> python
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    Some(match interpreter {
+        "python" | "pypy" => SourceKind::Code("python"),
+        "node" | "nodejs" => SourceKind::Code("javascript"),
+        "deno" | "ts-node" | "tsx" => SourceKind::Code("typescript"),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => SourceKind::FileOnly("shell"),
+        "ruby" => SourceKind::FileOnly("ruby"),
+        "perl" => SourceKind::FileOnly("perl"),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_extension_name_and_shebang() {
+        let cases = [
+            ("src/lib.rs", None, Some(SourceKind::Code("rust"))),
+            ("web/App.tsx", None, Some(SourceKind::Code("tsx"))),
+            ("src/shape.cc", None, Some(SourceKind::Code("cpp"))),
+            ("notes.txt", None, None),
+            ("Dockerfile", None, Some(SourceKind::FileOnly("dockerfile"))),
+            (
+                "Dockerfile.dev",
+                None,
+                Some(SourceKind::FileOnly("dockerfile")),
+            ),
+            ("pkg/BUILD.bazel", None, Some(SourceKind::FileOnly("bazel"))),
+            (
+                "Makefile",
+                Some("all: build"),
+                Some(SourceKind::FileOnly("make")),
+            ),
+            (
+                "bin/tool",
+                Some("#!/usr/bin/env python3"),
+                Some(SourceKind::Code("python")),
+            ),
+            (
+                "bin/serve",
+                Some("#!/usr/bin/env -S node --no-warnings"),
+                Some(SourceKind::Code("javascript")),
+            ),
+            (
+                "bin/setup",
+                Some("#!/bin/bash -e"),
+                Some(SourceKind::FileOnly("shell")),
+            ),
+            (
+                "bin/run",
+                Some("#!/usr/bin/python3.12"),
+                Some(SourceKind::Code("python")),
+            ),
+            ("LICENSE", Some("MIT License"), None),
+            ("bin/odd", Some("#!/usr/bin/env awk -f"), None),
+        ];
+        for (path, first_line, expected) in cases {
+            assert_eq!(detect(Path::new(path), first_line), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_overrides_remap_and_disable_extensions() {
+        let map =
+            LanguageMap::with_overrides([(".mjs", "javascript"), ("h", "cpp"), ("java", "none")])
+                .unwrap();
+        assert_eq!(
+            map.detect(Path::new("web/app.mjs"), None),
+            Some(SourceKind::Code("javascript"))
+        );
+        assert_eq!(map.language_for_extension("h"), Some("cpp"));
+        assert_eq!(map.detect(Path::new("src/Main.java"), None), None);
+        assert_eq!(map.language_of("src/lib.rs"), Some("rust"));
+        assert!(map.extensions().all(|(ext, _)| ext != "java"));
+
+        let err = LanguageMap::with_overrides([("swift", "swift")]).unwrap_err();
+        assert_eq!(err.extension, "swift");
+        assert!(err.to_string().contains("rust, python"), "{err}");
+    }
+
+    #[test]
+    fn test_detect_file_reads_shebang_only_without_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("deploy");
+        std::fs::write(&script, "#!/bin/sh\necho hi\n").unwrap();
+        let misnamed = dir.path().join("script.txt");
+        std::fs::write(&misnamed, "#!/bin/sh\necho hi\n").unwrap();
+
+        assert_eq!(detect_file(&script), Some(SourceKind::FileOnly("shell")));
+        assert_eq!(detect_file(&misnamed), None);
+    }
+}
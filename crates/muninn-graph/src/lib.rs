@@ -9,19 +9,58 @@
 //! our `Symbol`/`Edge` types and persists them through `GraphStore`.
 
 pub mod builder;
+mod cfamily;
+mod csharp;
+pub mod cycles;
 pub mod doc_store;
 pub mod edges;
+pub mod findings;
+pub mod impact;
+pub mod index_queue;
+mod java;
+mod kotlin;
+pub mod language;
+pub mod lock;
+pub mod orphans;
+pub mod overview;
 pub mod registry;
+pub mod remote;
+pub mod revision;
+pub mod sensitivity;
+pub mod snapshot;
 pub mod store;
 pub mod symbols;
 pub mod watcher;
 
-pub use builder::{BuildError, BuildStats, GraphBuilder};
+pub use builder::{
+    BuildError, BuildLimits, BuildStats, FileError, GraphBuilder, IgnoreRules, IndexProgress,
+    SkippedFile,
+};
+pub use cycles::{Cycle, CycleEdge, CycleLevel, CycleMember, CycleReport};
 pub use doc_store::{
     DocChunk, DocChunkInput, DocLibrary, DocStore, DocStoreError, Ecosystem, ItemType, ScoredChunk,
     SearchMode,
 };
 pub use edges::{CallType, Edge, EdgeKind};
-pub use store::{GraphStats, GraphStore, StoreError};
+pub use findings::{Finding, FindingLevel, Findings, Location};
+pub use impact::{Confidence, ImpactReport, ImpactSite, SiteKind};
+pub use index_queue::IndexQueue;
+pub use language::{LanguageMap, SourceKind, UnknownLanguage};
+pub use lock::{FileLock, LockError};
+pub use orphans::{Orphan, OrphanFilter, OrphanReport};
+pub use overview::{EntryKind, EntryPoint, GraphOverview, Hub};
+pub use remote::{RemoteGraph, is_read_only_cypher};
+pub use revision::{
+    ChangedSymbol, GraphPartition, REVISIONS_DIR, RevisionDiff, RevisionManifest, RevisionSymbol,
+};
+pub use sensitivity::{AuditReport, SensitiveSymbol, Sensitivity, scan_source};
+pub use snapshot::{
+    SNAPSHOT_FORMAT, SnapshotError, SnapshotManifest, pack_snapshot, read_snapshot_manifest,
+    unpack_snapshot,
+};
+pub use store::{
+    CallEdge, CallSite, DUMP_FORMAT, DumpRecord, DumpStats, GraphStats, GraphStore, IncomingEdge,
+    StoreError, value_to_json,
+};
 pub use symbols::{Symbol, SymbolKind, Visibility};
 pub use watcher::{FileEvent, FileWatcher, WatchError, WatcherConfig};
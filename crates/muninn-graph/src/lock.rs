@@ -0,0 +1,234 @@
+//! Advisory lock files.
+//!
+//! Guards on-disk state (the graph database, OAuth token files) against
+//! concurrent writers from separate muninn processes. A lock is a sidecar
+//! file (`<path>.lock`) holding the owner's PID, which is only used to name
+//! the owner in [`LockError::Held`].
+//!
+//! On Unix the lock itself is an `flock` on the sidecar, held for as long
+//! as the [`FileLock`] guard keeps it open. The kernel drops it when the
+//! owner exits, so a `kill -9` mid-index never leaves a lock to clean up,
+//! and the sidecar is never removed: unlinking it could delete a lock that
+//! another process has just taken. Elsewhere the sidecar is created with
+//! `O_EXCL` and removed on drop; one left behind by a crash must be
+//! deleted by hand.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Poll interval while waiting for a contended lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Error type for lock operations.
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error(
+        "{} is locked by another muninn process (pid {pid}); wait for it to finish or stop it",
+        path.display()
+    )]
+    Held { path: PathBuf, pid: u32 },
+    #[error("IO error on lock file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, LockError>;
+
+/// An acquired advisory lock. Released on drop.
+#[derive(Debug)]
+pub struct FileLock {
+    lock_path: PathBuf,
+    /// The open sidecar. On Unix the lock lives as long as this descriptor.
+    _file: File,
+}
+
+impl FileLock {
+    /// Sidecar lock path for a guarded file (`graph.db` → `graph.db.lock`).
+    pub fn lock_path_for(path: &Path) -> PathBuf {
+        let mut s = path.as_os_str().to_owned();
+        s.push(".lock");
+        PathBuf::from(s)
+    }
+
+    /// Try to acquire the lock for `path` without waiting.
+    ///
+    /// Returns [`LockError::Held`] if another process, or another guard in
+    /// this one, owns it.
+    pub fn try_acquire(path: &Path) -> Result<Self> {
+        let lock_path = Self::lock_path_for(path);
+        let io_err = |source| LockError::Io {
+            path: lock_path.clone(),
+            source,
+        };
+        let mut file = match open_exclusive(&lock_path) {
+            Ok(file) => file,
+            Err(e) if is_contended(&e) => {
+                return Err(LockError::Held {
+                    path: path.to_path_buf(),
+                    pid: read_owner(&lock_path),
+                });
+            }
+            Err(source) => return Err(io_err(source)),
+        };
+        file.set_len(0).map_err(io_err)?;
+        write!(file, "{}", std::process::id()).map_err(io_err)?;
+        file.sync_all().map_err(io_err)?;
+        Ok(Self {
+            lock_path,
+            _file: file,
+        })
+    }
+
+    /// Acquire the lock for `path`, polling until `timeout` elapses.
+    ///
+    /// Intended for short critical sections (token file writes) where the
+    /// other holder is expected to finish quickly.
+    pub fn acquire_with_timeout(path: &Path, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            match Self::try_acquire(path) {
+                Err(LockError::Held { .. }) if start.elapsed() < timeout => {
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                other => return other,
+            }
+        }
+    }
+
+    /// Path of the on-disk lock file.
+    pub fn path(&self) -> &Path {
+        &self.lock_path
+    }
+}
+
+#[cfg(not(unix))]
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.lock_path);
+    }
+}
+
+/// Open the sidecar and take the lock on it.
+#[cfg(unix)]
+fn open_exclusive(lock_path: &Path) -> std::io::Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)?;
+    // SAFETY: `file` owns the descriptor for the duration of the call.
+    let rc = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn open_exclusive(lock_path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(lock_path)
+}
+
+/// Whether opening the lock failed because someone else holds it.
+#[cfg(unix)]
+fn is_contended(e: &std::io::Error) -> bool {
+    e.raw_os_error() == Some(libc::EWOULDBLOCK)
+}
+
+#[cfg(not(unix))]
+fn is_contended(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::AlreadyExists
+}
+
+/// PID recorded by the current owner, or 0 if it hasn't been written yet.
+fn read_owner(lock_path: &Path) -> u32 {
+    std::fs::read_to_string(lock_path)
+        .ok()
+        .and_then(|content| content.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_acquire_and_release() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("graph.db");
+
+        let lock = FileLock::try_acquire(&target).expect("should acquire");
+        assert!(lock.path().exists());
+        assert_eq!(lock.path(), temp.path().join("graph.db.lock"));
+
+        drop(lock);
+        FileLock::try_acquire(&target).expect("released on drop");
+    }
+
+    #[test]
+    fn test_second_acquire_is_rejected() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("graph.db");
+
+        let _lock = FileLock::try_acquire(&target).unwrap();
+        let err = FileLock::try_acquire(&target).unwrap_err();
+        assert!(err.to_string().contains("graph.db"));
+        match err {
+            LockError::Held { pid, .. } => assert_eq!(pid, std::process::id()),
+            other => panic!("expected Held, got {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock_is_reclaimed() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("graph.db");
+        std::fs::write(FileLock::lock_path_for(&target), "999999999").unwrap();
+
+        let lock = FileLock::try_acquire(&target).expect("stale lock should be reclaimed");
+        let owner = std::fs::read_to_string(lock.path()).unwrap();
+        assert_eq!(owner, std::process::id().to_string());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_held_lock_is_never_reclaimed() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("graph.db");
+
+        // A held lock whose sidecar names a dead PID (as another process
+        // could see it mid-write) still belongs to its holder.
+        let lock = FileLock::try_acquire(&target).unwrap();
+        std::fs::write(lock.path(), "999999999").unwrap();
+        assert!(matches!(
+            FileLock::try_acquire(&target),
+            Err(LockError::Held { .. })
+        ));
+        assert!(lock.path().exists());
+    }
+
+    #[test]
+    fn test_acquire_with_timeout_gives_up() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("tokens.json");
+
+        let _lock = FileLock::try_acquire(&target).unwrap();
+        let start = Instant::now();
+        let result = FileLock::acquire_with_timeout(&target, Duration::from_millis(150));
+        assert!(matches!(result, Err(LockError::Held { .. })));
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
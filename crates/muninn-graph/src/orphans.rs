@@ -0,0 +1,345 @@
+//! Dead-code candidates: public symbols nothing refers to.
+//!
+//! [`OrphanReport::from_store`] lists public functions and methods with no
+//! incoming edge in the graph. Names that are reached from outside the graph
+//! (entry points, tests, FFI exports) are skipped by default, and an
+//! [`OrphanFilter`] adds project-specific allowlist patterns on top.
+//!
+//! The graph is calls-only and resolves calls by name, so an orphan is a
+//! candidate, not a verdict: callers through trait objects, macros, or
+//! reflection are invisible to it.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::findings::{Finding, FindingLevel, Findings, Location};
+use crate::overview::{entry_kind, relative_path};
+use crate::store::{GraphStore, Result, node_line, node_str};
+
+/// Which symbols are never reported as orphans.
+#[derive(Debug, Clone)]
+pub struct OrphanFilter {
+    /// Skip `main` functions and handlers.
+    pub skip_entry_points: bool,
+    /// Skip tests and symbols defined in test files.
+    pub skip_tests: bool,
+    /// Skip FFI exports (`extern` functions, `ffi` modules).
+    pub skip_ffi: bool,
+    /// Patterns matched against the name, qualified name and file path.
+    /// `*` matches any run of characters.
+    pub allow: Vec<String>,
+}
+
+impl Default for OrphanFilter {
+    fn default() -> Self {
+        Self {
+            skip_entry_points: true,
+            skip_tests: true,
+            skip_ffi: true,
+            allow: Vec::new(),
+        }
+    }
+}
+
+impl OrphanFilter {
+    /// Add an allowlist pattern.
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Why `orphan` is allowlisted, if it is.
+    fn skip_reason(&self, orphan: &Orphan, signature: &str) -> Option<&'static str> {
+        if self.skip_entry_points && entry_kind(&orphan.name, &orphan.file_path).is_some() {
+            return Some("entry point");
+        }
+        if self.skip_tests && is_test(orphan) {
+            return Some("test");
+        }
+        if self.skip_ffi && is_ffi(orphan, signature) {
+            return Some("ffi");
+        }
+        let fields = [&orphan.name, &orphan.qualified_name, &orphan.file_path];
+        self.allow
+            .iter()
+            .any(|p| fields.iter().any(|f| wildcard_match(p, f)))
+            .then_some("allowlist")
+    }
+}
+
+/// A public symbol with no incoming edges.
+#[derive(Debug, Clone, Serialize)]
+pub struct Orphan {
+    pub name: String,
+    /// Qualified name, falling back to the plain name.
+    pub qualified_name: String,
+    /// Symbol kind (`function`, `method`).
+    pub kind: String,
+    /// File path relative to the report root.
+    pub file_path: String,
+    /// 1-indexed line of the definition.
+    pub line: usize,
+}
+
+/// Public symbols with no incoming edges.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanReport {
+    /// Public functions and methods examined.
+    pub public_count: usize,
+    /// Orphans skipped by the filter, per reason.
+    pub skipped: Vec<(String, usize)>,
+    /// Remaining orphans, ordered by file and line.
+    pub orphans: Vec<Orphan>,
+}
+
+impl OrphanReport {
+    /// Find orphans in the graph. File paths under `root` are reported
+    /// relative to it.
+    pub fn from_store(store: &GraphStore, root: &Path, filter: &OrphanFilter) -> Result<Self> {
+        let referenced = store.referenced_ids()?;
+        let mut public_count = 0;
+        let mut skipped: Vec<(String, usize)> = Vec::new();
+        let mut orphans = Vec::new();
+
+        for node in store.all_nodes()? {
+            let kind = node_str(&node, "kind").unwrap_or_default();
+            if !matches!(kind, "function" | "method")
+                || node_str(&node, "visibility") != Some("public")
+            {
+                continue;
+            }
+            public_count += 1;
+            if node_str(&node, "id").is_some_and(|id| referenced.contains(id)) {
+                continue;
+            }
+
+            let name = node_str(&node, "name").unwrap_or_default();
+            let orphan = Orphan {
+                name: name.to_string(),
+                qualified_name: node_str(&node, "qualified_name")
+                    .unwrap_or(name)
+                    .to_string(),
+                kind: kind.to_string(),
+                file_path: relative_path(node_str(&node, "file_path").unwrap_or_default(), root),
+                line: node_line(&node).unwrap_or(0),
+            };
+            let signature = node_str(&node, "signature").unwrap_or_default();
+            match filter.skip_reason(&orphan, signature) {
+                Some(reason) => match skipped.iter_mut().find(|(r, _)| r == reason) {
+                    Some((_, count)) => *count += 1,
+                    None => skipped.push((reason.to_string(), 1)),
+                },
+                None => orphans.push(orphan),
+            }
+        }
+
+        orphans.sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+        skipped.sort();
+        Ok(Self {
+            public_count,
+            skipped,
+            orphans,
+        })
+    }
+
+    /// Render the report as Markdown, grouped by file.
+    pub fn to_markdown(&self) -> String {
+        let files: BTreeSet<&str> = self.orphans.iter().map(|o| o.file_path.as_str()).collect();
+        let mut out = String::from("# Orphan symbols\n\n");
+        out.push_str(&format!(
+            "{} of {} public functions/methods have no incoming edges, across {} files.\n",
+            self.orphans.len(),
+            self.public_count,
+            files.len()
+        ));
+        if !self.skipped.is_empty() {
+            let skipped: Vec<String> = self
+                .skipped
+                .iter()
+                .map(|(reason, count)| format!("{} {}", count, reason))
+                .collect();
+            out.push_str(&format!("Skipped: {}.\n", skipped.join(", ")));
+        }
+
+        for file in files {
+            out.push_str(&format!("\n## `{}`\n\n", file));
+            for orphan in self.orphans.iter().filter(|o| o.file_path == file) {
+                out.push_str(&format!(
+                    "- `{}` ({}) at line {}\n",
+                    orphan.qualified_name, orphan.kind, orphan.line
+                ));
+            }
+        }
+        out
+    }
+
+    /// One `orphan` warning per orphan, for SARIF output.
+    pub fn findings(&self) -> Findings {
+        let mut findings = Findings::new();
+        findings.rule("orphan", "Public function or method nothing refers to");
+        for orphan in &self.orphans {
+            findings.push(
+                Finding::new(
+                    "orphan",
+                    FindingLevel::Warning,
+                    format!(
+                        "`{}` ({}) has no incoming edges",
+                        orphan.qualified_name, orphan.kind
+                    ),
+                )
+                .at(Location::new(&orphan.file_path, orphan.line)),
+            );
+        }
+        findings
+    }
+}
+
+/// Whether a symbol is a test or lives in a test file.
+fn is_test(orphan: &Orphan) -> bool {
+    let path = orphan.file_path.as_str();
+    let file = path.rsplit('/').next().unwrap_or(path);
+    orphan.name.starts_with("test_")
+        || orphan.qualified_name.contains("::tests::")
+        || path.starts_with("tests/")
+        || path.contains("/tests/")
+        || file.starts_with("test_")
+        || file.contains("_test.")
+        || file.contains(".test.")
+        || file.contains(".spec.")
+}
+
+/// Whether a symbol is exported over FFI.
+fn is_ffi(orphan: &Orphan, signature: &str) -> bool {
+    signature.contains("extern ")
+        || signature.contains("no_mangle")
+        || orphan.qualified_name.contains("ffi")
+        || orphan.file_path.contains("ffi")
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edges::{CallType, Edge};
+    use crate::symbols::{Symbol, SymbolKind, Visibility};
+    use serial_test::serial;
+
+    fn function(name: &str, file: &str, visibility: Visibility) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            file_path: file.to_string(),
+            start_line: 1,
+            end_line: 5,
+            signature: None,
+            qualified_name: None,
+            doc_comment: None,
+            visibility,
+            cyclomatic: None,
+            cognitive: None,
+            call_degree: None,
+            sensitivity: Vec::new(),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_orphans_skip_called_private_and_allowlisted() {
+        let store = GraphStore::open_in_memory().unwrap();
+        let main = store
+            .insert_node(&function("main", "src/main.rs", Visibility::Private))
+            .unwrap();
+        let used = store
+            .insert_node(&function("used", "src/lib.rs", Visibility::Public))
+            .unwrap();
+        store
+            .insert_edge(&Edge::calls(&main, &used, CallType::Direct, 2))
+            .unwrap();
+        for (name, file) in [
+            ("unused", "src/lib.rs"),
+            ("handle_event", "src/server.rs"),
+            ("test_helper", "src/lib.rs"),
+            ("fixture", "tests/common.rs"),
+            ("legacy_api", "src/compat.rs"),
+        ] {
+            store
+                .insert_node(&function(name, file, Visibility::Public))
+                .unwrap();
+        }
+        store
+            .insert_node(&function(
+                "private_unused",
+                "src/lib.rs",
+                Visibility::Private,
+            ))
+            .unwrap();
+
+        let filter = OrphanFilter::default().allow("src/compat*");
+        let report = OrphanReport::from_store(&store, Path::new("/repo"), &filter).unwrap();
+        let names: Vec<_> = report.orphans.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["unused"]);
+        assert_eq!(report.public_count, 6);
+        assert_eq!(
+            report.skipped,
+            vec![
+                ("allowlist".to_string(), 1),
+                ("entry point".to_string(), 1),
+                ("test".to_string(), 2),
+            ]
+        );
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("1 of 6 public functions/methods have no incoming edges"));
+        assert!(markdown.contains("## `src/lib.rs`\n\n- `unused` (function) at line 1"));
+        let findings = report.findings();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings.results[0].rule_id, "orphan");
+        assert_eq!(
+            findings.results[0].location,
+            Some(Location::new("src/lib.rs", 1))
+        );
+
+        // Turning the default skips off reports everything unreferenced
+        let everything = OrphanFilter {
+            skip_entry_points: false,
+            skip_tests: false,
+            skip_ffi: false,
+            allow: Vec::new(),
+        };
+        let report = OrphanReport::from_store(&store, Path::new("/repo"), &everything).unwrap();
+        assert_eq!(report.orphans.len(), 5);
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("parse", "parse"));
+        assert!(!wildcard_match("parse", "parser"));
+        assert!(wildcard_match("parse*", "parser"));
+        assert!(wildcard_match("*::ffi::*", "crate::ffi::init"));
+        assert!(wildcard_match("src/*/mod.rs", "src/a/b/mod.rs"));
+        assert!(!wildcard_match("src/*.rs", "lib/x.rs"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+}
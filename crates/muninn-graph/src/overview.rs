@@ -0,0 +1,353 @@
+//! Whole-graph summary for orientation.
+//!
+//! [`GraphOverview::from_store`] condenses the graph into what a newcomer
+//! wants first: how big the project is, how it's laid out, where execution
+//! starts ([`EntryPoint`]s: `main` functions, binaries, request/event
+//! handlers) and which symbols everything else leans on ([`Hub`]s).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::store::{GraphStore, Result, node_line, node_str};
+
+/// Maximum entry points kept in an overview.
+const MAX_ENTRY_POINTS: usize = 20;
+
+/// Maximum hubs kept in an overview.
+const MAX_HUBS: usize = 10;
+
+/// Maximum directories listed in an overview.
+const MAX_DIRECTORIES: usize = 15;
+
+/// Why a symbol counts as an entry point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// A `main` function in the primary binary or library.
+    Main,
+    /// A `main` function under a `bin/` directory.
+    Binary,
+    /// A function named like a request/event handler (`handle_*`, `*_handler`).
+    Handler,
+}
+
+impl fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EntryKind::Main => "main",
+            EntryKind::Binary => "binary",
+            EntryKind::Handler => "handler",
+        })
+    }
+}
+
+/// A place where execution starts.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPoint {
+    pub name: String,
+    /// Qualified name, falling back to the plain name.
+    pub qualified_name: String,
+    /// File path relative to the overview root.
+    pub file_path: String,
+    /// 1-indexed line of the definition.
+    pub line: usize,
+    pub kind: EntryKind,
+}
+
+/// A heavily-called symbol.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hub {
+    /// Qualified name, falling back to the plain name.
+    pub qualified_name: String,
+    /// File path relative to the overview root.
+    pub file_path: String,
+    /// 1-indexed line of the definition.
+    pub line: usize,
+    /// Number of distinct callers in the graph.
+    pub callers: usize,
+}
+
+/// Summary of a whole graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphOverview {
+    /// Total symbols.
+    pub symbol_count: usize,
+    /// Total `CALLS` edges.
+    pub call_count: usize,
+    /// Distinct source files.
+    pub file_count: usize,
+    /// Symbol count per kind (`function`, `struct`, ...).
+    pub kinds: BTreeMap<String, usize>,
+    /// Directories with the most symbols, largest first.
+    pub directories: Vec<(String, usize)>,
+    /// Detected entry points, mains first.
+    pub entry_points: Vec<EntryPoint>,
+    /// Most-called symbols, largest first.
+    pub hubs: Vec<Hub>,
+}
+
+impl GraphOverview {
+    /// Summarize the graph. File paths under `root` are reported relative
+    /// to it.
+    pub fn from_store(store: &GraphStore, root: &Path) -> Result<Self> {
+        let nodes = store.all_nodes()?;
+        let edges = store.call_edges()?;
+
+        let mut kinds: BTreeMap<String, usize> = BTreeMap::new();
+        let mut files: BTreeMap<String, usize> = BTreeMap::new();
+        let mut entry_points = Vec::new();
+        let mut by_id = HashMap::new();
+
+        for node in &nodes {
+            let kind = node_str(node, "kind").unwrap_or("unknown");
+            *kinds.entry(kind.to_string()).or_default() += 1;
+
+            let file_path = relative_path(node_str(node, "file_path").unwrap_or_default(), root);
+            *files.entry(file_path.clone()).or_default() += 1;
+
+            let name = node_str(node, "name").unwrap_or_default();
+            let qualified_name = node_str(node, "qualified_name").unwrap_or(name).to_string();
+            let line = node_line(node).unwrap_or(0);
+
+            if matches!(kind, "function" | "method")
+                && let Some(entry_kind) = entry_kind(name, &file_path)
+            {
+                entry_points.push(EntryPoint {
+                    name: name.to_string(),
+                    qualified_name: qualified_name.clone(),
+                    file_path: file_path.clone(),
+                    line,
+                    kind: entry_kind,
+                });
+            }
+            if let Some(id) = node_str(node, "id") {
+                by_id.insert(id.to_string(), (qualified_name, file_path, line));
+            }
+        }
+
+        entry_points
+            .sort_by(|a, b| (a.kind, &a.file_path, a.line).cmp(&(b.kind, &b.file_path, b.line)));
+        entry_points.truncate(MAX_ENTRY_POINTS);
+
+        let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &edges {
+            callers.entry(&edge.callee).or_default().push(&edge.caller);
+        }
+        let mut hubs: Vec<Hub> = callers
+            .into_iter()
+            .filter_map(|(callee, mut from)| {
+                from.sort_unstable();
+                from.dedup();
+                let (qualified_name, file_path, line) = by_id.get(callee)?.clone();
+                Some(Hub {
+                    qualified_name,
+                    file_path,
+                    line,
+                    callers: from.len(),
+                })
+            })
+            .collect();
+        hubs.sort_by(|a, b| {
+            b.callers
+                .cmp(&a.callers)
+                .then_with(|| a.qualified_name.cmp(&b.qualified_name))
+        });
+        hubs.truncate(MAX_HUBS);
+
+        let mut directories: BTreeMap<String, usize> = BTreeMap::new();
+        for (file, count) in &files {
+            *directories.entry(top_directory(file)).or_default() += count;
+        }
+        let mut directories: Vec<(String, usize)> = directories.into_iter().collect();
+        directories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        directories.truncate(MAX_DIRECTORIES);
+
+        Ok(Self {
+            symbol_count: nodes.len(),
+            call_count: edges.len(),
+            file_count: files.len(),
+            kinds,
+            directories,
+            entry_points,
+            hubs,
+        })
+    }
+
+    /// Render the overview as Markdown sections (level-2 headings).
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("## Overview\n\n");
+        out.push_str(&format!(
+            "{} symbols across {} files, {} call edges.\n\n",
+            self.symbol_count, self.file_count, self.call_count
+        ));
+        for (kind, count) in &self.kinds {
+            out.push_str(&format!("- {}: {}\n", kind, count));
+        }
+
+        if !self.directories.is_empty() {
+            out.push_str("\n### Layout\n\n");
+            for (dir, count) in &self.directories {
+                out.push_str(&format!("- `{}/` ({} symbols)\n", dir, count));
+            }
+        }
+
+        out.push_str("\n## Entry points\n\n");
+        if self.entry_points.is_empty() {
+            out.push_str("No `main` functions or handlers found in the graph.\n");
+        }
+        for entry in &self.entry_points {
+            out.push_str(&format!(
+                "- `{}` ({}) at `{}:{}`\n",
+                entry.qualified_name, entry.kind, entry.file_path, entry.line
+            ));
+        }
+
+        if !self.hubs.is_empty() {
+            out.push_str("\n## Core symbols\n\nMost-called symbols in the graph:\n\n");
+            for hub in &self.hubs {
+                out.push_str(&format!(
+                    "- `{}` at `{}:{}` ({} callers)\n",
+                    hub.qualified_name, hub.file_path, hub.line, hub.callers
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Classify a function as an entry point by name and location.
+pub(crate) fn entry_kind(name: &str, file_path: &str) -> Option<EntryKind> {
+    if name == "main" {
+        let in_bin = file_path.starts_with("bin/") || file_path.contains("/bin/");
+        return Some(if in_bin {
+            EntryKind::Binary
+        } else {
+            EntryKind::Main
+        });
+    }
+    let lower = name.to_ascii_lowercase();
+    (lower.starts_with("handle") || lower.ends_with("handler")).then_some(EntryKind::Handler)
+}
+
+/// Path relative to `root`, with `/` separators and no `./` prefix.
+pub(crate) fn relative_path(path: &str, root: &Path) -> String {
+    let path = Path::new(path);
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    relative
+        .strip_prefix("./")
+        .map(str::to_string)
+        .unwrap_or(relative)
+}
+
+/// Group a file under its first two directory components
+/// (`crates/foo/src/lib.rs` -> `crates/foo`), or `.` for top-level files.
+pub(crate) fn top_directory(file: &str) -> String {
+    let mut dirs: Vec<&str> = file.split('/').collect();
+    dirs.pop();
+    if dirs.is_empty() {
+        ".".to_string()
+    } else {
+        dirs.into_iter().take(2).collect::<Vec<_>>().join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edges::{CallType, Edge};
+    use crate::symbols::{Symbol, SymbolKind, Visibility};
+    use serial_test::serial;
+
+    fn function(name: &str, file: &str, line: usize) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            file_path: file.to_string(),
+            start_line: line,
+            end_line: line + 5,
+            signature: None,
+            qualified_name: None,
+            doc_comment: None,
+            visibility: Visibility::Public,
+            cyclomatic: None,
+            cognitive: None,
+            call_degree: None,
+            sensitivity: Vec::new(),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_overview_from_store() {
+        let store = GraphStore::open_in_memory().unwrap();
+        let main = store
+            .insert_node(&function("main", "/repo/src/main.rs", 1))
+            .unwrap();
+        let tool = store
+            .insert_node(&function("main", "/repo/src/bin/tool.rs", 1))
+            .unwrap();
+        let handler = store
+            .insert_node(&function("handle_request", "/repo/src/server.rs", 10))
+            .unwrap();
+        let util = store
+            .insert_node(&function("parse", "/repo/src/util.rs", 3))
+            .unwrap();
+        for caller in [&main, &tool, &handler] {
+            store
+                .insert_edge(&Edge::calls(caller, &util, CallType::Direct, 2))
+                .unwrap();
+        }
+
+        let overview = GraphOverview::from_store(&store, Path::new("/repo")).unwrap();
+        assert_eq!(overview.symbol_count, 4);
+        assert_eq!(overview.call_count, 3);
+        assert_eq!(overview.file_count, 4);
+        assert_eq!(overview.kinds.get("function"), Some(&4));
+        assert_eq!(
+            overview.directories,
+            vec![("src".to_string(), 3), ("src/bin".to_string(), 1)]
+        );
+
+        let entries: Vec<_> = overview
+            .entry_points
+            .iter()
+            .map(|e| (e.file_path.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("src/main.rs", EntryKind::Main),
+                ("src/bin/tool.rs", EntryKind::Binary),
+                ("src/server.rs", EntryKind::Handler),
+            ]
+        );
+
+        assert_eq!(overview.hubs.len(), 1);
+        assert_eq!(overview.hubs[0].qualified_name, "parse");
+        assert_eq!(overview.hubs[0].callers, 3);
+
+        let markdown = overview.to_markdown();
+        assert!(markdown.contains("4 symbols across 4 files, 3 call edges."));
+        assert!(markdown.contains("- `main` (main) at `src/main.rs:1`"));
+        assert!(markdown.contains("- `parse` at `src/util.rs:3` (3 callers)"));
+    }
+
+    #[test]
+    fn test_entry_kind() {
+        assert_eq!(entry_kind("main", "src/main.rs"), Some(EntryKind::Main));
+        assert_eq!(entry_kind("main", "bin/cli.rs"), Some(EntryKind::Binary));
+        assert_eq!(
+            entry_kind("handleClick", "web/app.ts"),
+            Some(EntryKind::Handler)
+        );
+        assert_eq!(
+            entry_kind("error_handler", "src/api.rs"),
+            Some(EntryKind::Handler)
+        );
+        assert_eq!(entry_kind("mainly", "src/lib.rs"), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Client for a remote muninn graph service.
+//!
+//! For repositories too large to index on every laptop, one machine serves
+//! its graph over HTTP (`muninn graph serve`) and clients open it with
+//! [`GraphStore::open_remote`](crate::GraphStore::open_remote). Queries are
+//! sent as Cypher and answered with JSON rows, so every read on
+//! [`GraphStore`](crate::GraphStore) works unchanged; writes are refused.
+//!
+//! The protocol is two endpoints:
+//! - `POST /query` with `{"cypher": "..."}` answers `{"rows": [{column: value}]}`
+//! - `GET /stats` answers `{"node_count": n, "edge_count": n}`
+
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use graphqlite::CypherResult;
+use serde::{Deserialize, Serialize};
+
+use crate::store::{GraphStats, StoreError, value_to_json};
+
+/// Timeout for one request to the graph service.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Body of `POST /query`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRequest {
+    pub cypher: String,
+}
+
+/// Response of `POST /query`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResponse {
+    pub rows: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl QueryResponse {
+    /// Convert a local query result to the wire format.
+    pub fn from_result(result: &CypherResult) -> Self {
+        Self {
+            rows: result
+                .iter()
+                .map(|row| {
+                    row.columns()
+                        .iter()
+                        .map(|col| {
+                            let value = row
+                                .get_value(col)
+                                .map_or(serde_json::Value::Null, value_to_json);
+                            (col.clone(), value)
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Response of `GET /stats`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsResponse {
+    pub node_count: i64,
+    pub edge_count: i64,
+}
+
+/// Whether `cypher` only reads. Keywords inside string literals are
+/// ignored, so `MATCH (n {name: 'set'})` still counts as a read.
+pub fn is_read_only_cypher(cypher: &str) -> bool {
+    const WRITE_KEYWORDS: &[&str] = &[
+        "CREATE", "MERGE", "DELETE", "DETACH", "SET", "REMOVE", "DROP",
+    ];
+
+    let mut unquoted = String::with_capacity(cypher.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in cypher.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None if c == '\'' || c == '"' || c == '`' => {
+                quote = Some(c);
+                unquoted.push(' ');
+            }
+            None => unquoted.push(c),
+        }
+    }
+
+    !unquoted
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .any(|word| WRITE_KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k)))
+}
+
+/// One request to the worker thread.
+struct Call {
+    path: &'static str,
+    body: Option<serde_json::Value>,
+    reply: Sender<std::result::Result<serde_json::Value, String>>,
+}
+
+/// Connection to a remote graph service.
+///
+/// Requests go through a background thread owning a blocking HTTP client,
+/// so the store stays synchronous and usable from inside a tokio runtime.
+pub struct RemoteGraph {
+    url: String,
+    tx: Sender<Call>,
+}
+
+impl RemoteGraph {
+    /// Connect to the service at `url` (e.g. `http://indexer:7700`).
+    pub fn connect(url: &str) -> Result<Self, StoreError> {
+        let url = url.trim_end_matches('/').to_string();
+        let (tx, rx) = mpsc::channel::<Call>();
+        let base = url.clone();
+        std::thread::Builder::new()
+            .name("muninn-graph-remote".to_string())
+            .spawn(move || {
+                let client = match reqwest::blocking::Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                {
+                    Ok(c) => c,
+                    Err(e) => {
+                        for call in rx {
+                            let _ = call
+                                .reply
+                                .send(Err(format!("cannot build HTTP client: {}", e)));
+                        }
+                        return;
+                    }
+                };
+                for call in rx {
+                    let endpoint = format!("{}{}", base, call.path);
+                    let request = match &call.body {
+                        Some(body) => client.post(&endpoint).json(body),
+                        None => client.get(&endpoint),
+                    };
+                    let result = request
+                        .send()
+                        .map_err(|e| format!("{}: {}", endpoint, e))
+                        .and_then(|r| {
+                            let status = r.status();
+                            if status.is_success() {
+                                r.json::<serde_json::Value>()
+                                    .map_err(|e| format!("{}: bad response: {}", endpoint, e))
+                            } else {
+                                let text = r.text().unwrap_or_default();
+                                Err(format!("{}: {} {}", endpoint, status, text.trim()))
+                            }
+                        });
+                    let _ = call.reply.send(result);
+                }
+            })
+            .map_err(|e| StoreError::Remote(format!("spawn graph client thread: {}", e)))?;
+        Ok(Self { url, tx })
+    }
+
+    /// The service's base URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn call(
+        &self,
+        path: &'static str,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, StoreError> {
+        let (reply, rx) = mpsc::channel();
+        self.tx
+            .send(Call { path, body, reply })
+            .map_err(|_| StoreError::Remote("graph client thread stopped".to_string()))?;
+        rx.recv()
+            .map_err(|_| StoreError::Remote("graph client thread stopped".to_string()))?
+            .map_err(StoreError::Remote)
+    }
+
+    /// Run a read-only Cypher query on the service.
+    pub fn query(&self, cypher: &str) -> Result<CypherResult, StoreError> {
+        let body = serde_json::to_value(QueryRequest {
+            cypher: cypher.to_string(),
+        })
+        .map_err(|e| StoreError::InvalidData(e.to_string()))?;
+        let response: QueryResponse = serde_json::from_value(self.call("/query", Some(body))?)
+            .map_err(|e| StoreError::Remote(format!("bad query response: {}", e)))?;
+        let rows = serde_json::Value::Array(
+            response
+                .rows
+                .into_iter()
+                .map(serde_json::Value::Object)
+                .collect(),
+        );
+        Ok(CypherResult::from_json(&rows.to_string())?)
+    }
+
+    /// Node and edge counts of the served graph.
+    pub fn stats(&self) -> Result<GraphStats, StoreError> {
+        let response: StatsResponse = serde_json::from_value(self.call("/stats", None)?)
+            .map_err(|e| StoreError::Remote(format!("bad stats response: {}", e)))?;
+        Ok(GraphStats {
+            node_count: response.node_count,
+            edge_count: response.edge_count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_cypher() {
+        assert!(is_read_only_cypher("MATCH (n) RETURN n"));
+        assert!(is_read_only_cypher(
+            "MATCH (n {name: 'set'}) RETURN n.offset AS offset"
+        ));
+        assert!(is_read_only_cypher(
+            r#"MATCH (n {name: "it\"s CREATE"}) RETURN n"#
+        ));
+        assert!(!is_read_only_cypher("CREATE (n:Function {name: 'x'})"));
+        assert!(!is_read_only_cypher("MATCH (n) SET n.name = 'y'"));
+        assert!(!is_read_only_cypher("match (n) detach delete n"));
+    }
+}
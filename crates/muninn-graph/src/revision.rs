@@ -0,0 +1,345 @@
+//! Commit-pinned graph partitions and diffs between them.
+//!
+//! A [`GraphPartition`] is a graph indexed from one git revision, kept in
+//! its own directory under `.muninn/revisions/` next to the live graph.
+//! [`RevisionDiff::between`] compares the symbol sets of two graphs — two
+//! partitions, or a partition and the live graph — so questions like
+//! "what did this branch add?" can be answered structurally.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::overview::relative_path;
+use crate::store::{GraphStore, Result, StoreError, node_line, node_str};
+
+/// Directory under `.muninn/` holding the partitions.
+pub const REVISIONS_DIR: &str = "revisions";
+
+const MANIFEST_FILE: &str = "revision.json";
+
+/// What a partition was indexed from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevisionManifest {
+    /// Partition name (e.g. `main`, `feature/login`).
+    pub name: String,
+    /// The revision as given (branch, tag or sha).
+    pub revision: String,
+    /// Commit the revision resolved to.
+    pub commit: String,
+    /// Directory the revision was checked out to while indexing; stored
+    /// file paths start with it.
+    pub root: PathBuf,
+    /// When it was indexed, as Unix seconds.
+    pub indexed_at: u64,
+}
+
+/// A graph indexed from one git revision.
+#[derive(Debug, Clone)]
+pub struct GraphPartition {
+    dir: PathBuf,
+}
+
+impl GraphPartition {
+    /// The partition `name` under `revisions_dir`. Names may contain `/`
+    /// (branch names); it maps to `__` on disk.
+    pub fn new(revisions_dir: &Path, name: &str) -> Result<Self> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+        if !valid {
+            return Err(StoreError::InvalidData(format!(
+                "invalid revision name '{}'",
+                name
+            )));
+        }
+        Ok(Self {
+            dir: revisions_dir.join(name.replace('/', "__")),
+        })
+    }
+
+    /// The partition's directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the partition's graph database.
+    pub fn graph_path(&self) -> PathBuf {
+        self.dir.join("graph.db")
+    }
+
+    /// Whether the partition has been indexed.
+    pub fn exists(&self) -> bool {
+        self.dir.join(MANIFEST_FILE).exists() && self.graph_path().exists()
+    }
+
+    /// The partition's manifest.
+    pub fn manifest(&self) -> Result<RevisionManifest> {
+        let text = std::fs::read_to_string(self.dir.join(MANIFEST_FILE))
+            .map_err(|e| StoreError::InvalidData(format!("{}: {}", self.dir.display(), e)))?;
+        serde_json::from_str(&text).map_err(|e| StoreError::InvalidData(e.to_string()))
+    }
+
+    /// Record what the partition was indexed from.
+    pub fn write_manifest(&self, manifest: &RevisionManifest) -> Result<()> {
+        let text = serde_json::to_string_pretty(manifest)
+            .map_err(|e| StoreError::InvalidData(e.to_string()))?;
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(self.dir.join(MANIFEST_FILE), text))
+            .map_err(|e| StoreError::InvalidData(format!("{}: {}", self.dir.display(), e)))
+    }
+
+    /// Open the partition's graph.
+    pub fn open(&self) -> Result<GraphStore> {
+        if !self.exists() {
+            return Err(StoreError::InvalidData(format!(
+                "no indexed revision at {}",
+                self.dir.display()
+            )));
+        }
+        GraphStore::open(self.graph_path())
+    }
+
+    /// Manifests of every indexed partition, by name.
+    pub fn list(revisions_dir: &Path) -> Vec<RevisionManifest> {
+        let Ok(entries) = std::fs::read_dir(revisions_dir) else {
+            return Vec::new();
+        };
+        let mut manifests: Vec<RevisionManifest> = entries
+            .flatten()
+            .map(|entry| Self { dir: entry.path() })
+            .filter(GraphPartition::exists)
+            .filter_map(|partition| partition.manifest().ok())
+            .collect();
+        manifests.sort_by(|a, b| a.name.cmp(&b.name));
+        manifests
+    }
+}
+
+/// A symbol as compared across revisions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RevisionSymbol {
+    /// Qualified name, falling back to `file::name`.
+    pub key: String,
+    pub name: String,
+    pub kind: String,
+    /// File path relative to the revision's root.
+    pub file_path: String,
+    pub line: usize,
+    pub signature: Option<String>,
+}
+
+/// A symbol present in both revisions whose signature or file changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedSymbol {
+    pub before: RevisionSymbol,
+    pub after: RevisionSymbol,
+}
+
+/// Symbols added, removed and changed between two graphs.
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub base: String,
+    pub head: String,
+    pub added: Vec<RevisionSymbol>,
+    pub removed: Vec<RevisionSymbol>,
+    pub changed: Vec<ChangedSymbol>,
+}
+
+/// Symbols of `store` keyed for comparison, with paths relative to `root`.
+/// File nodes are skipped: they only say a file exists.
+fn revision_symbols(store: &GraphStore, root: &Path) -> Result<BTreeMap<String, RevisionSymbol>> {
+    let mut symbols = BTreeMap::new();
+    for node in store.all_nodes()? {
+        let kind = node_str(&node, "kind").unwrap_or_default();
+        if kind == "file" {
+            continue;
+        }
+        let name = node_str(&node, "name").unwrap_or_default().to_string();
+        let file_path = relative_path(node_str(&node, "file_path").unwrap_or_default(), root);
+        let key = node_str(&node, "qualified_name")
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}::{}", file_path, name));
+        symbols.entry(key.clone()).or_insert(RevisionSymbol {
+            key,
+            name,
+            kind: kind.to_string(),
+            file_path,
+            line: node_line(&node).unwrap_or(0),
+            signature: node_str(&node, "signature").map(str::to_string),
+        });
+    }
+    Ok(symbols)
+}
+
+impl RevisionDiff {
+    /// Compare the symbols of `base` (indexed under `base_root`) with those
+    /// of `head` (under `head_root`).
+    pub fn between(
+        (base_name, base, base_root): (&str, &GraphStore, &Path),
+        (head_name, head, head_root): (&str, &GraphStore, &Path),
+    ) -> Result<Self> {
+        let mut before = revision_symbols(base, base_root)?;
+        let after = revision_symbols(head, head_root)?;
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for (key, symbol) in after {
+            match before.remove(&key) {
+                None => added.push(symbol),
+                Some(old)
+                    if old.signature != symbol.signature || old.file_path != symbol.file_path =>
+                {
+                    changed.push(ChangedSymbol {
+                        before: old,
+                        after: symbol,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(Self {
+            base: base_name.to_string(),
+            head: head_name.to_string(),
+            added,
+            removed: before.into_values().collect(),
+            changed,
+        })
+    }
+
+    /// Render the diff as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Symbol changes from `{}` to `{}`\n\n",
+            self.base, self.head
+        );
+        out.push_str(&format!(
+            "{} added, {} removed, {} changed.\n",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        ));
+        let list = |out: &mut String, title: &str, symbols: &[RevisionSymbol]| {
+            if symbols.is_empty() {
+                return;
+            }
+            out.push_str(&format!("\n## {}\n\n", title));
+            for s in symbols {
+                out.push_str(&format!(
+                    "- `{}` ({}) in `{}`:{}\n",
+                    s.key, s.kind, s.file_path, s.line
+                ));
+            }
+        };
+        list(&mut out, "Added", &self.added);
+        list(&mut out, "Removed", &self.removed);
+        if !self.changed.is_empty() {
+            out.push_str("\n## Changed\n\n");
+            for c in &self.changed {
+                out.push_str(&format!("- `{}` ({})", c.after.key, c.after.kind));
+                if c.before.file_path != c.after.file_path {
+                    out.push_str(&format!(
+                        " moved from `{}` to `{}`",
+                        c.before.file_path, c.after.file_path
+                    ));
+                }
+                if c.before.signature != c.after.signature {
+                    out.push_str(&format!(
+                        ": `{}` -> `{}`",
+                        c.before.signature.as_deref().unwrap_or(""),
+                        c.after.signature.as_deref().unwrap_or("")
+                    ));
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Symbol, SymbolKind};
+    use serial_test::serial;
+    use tempfile::TempDir;
+
+    fn function(root: &str, name: &str, signature: &str) -> Symbol {
+        let mut symbol = Symbol::new(
+            name,
+            SymbolKind::Function,
+            format!("{}/src/lib.rs", root),
+            1,
+            2,
+        );
+        symbol.signature = Some(signature.to_string());
+        symbol
+    }
+
+    #[test]
+    #[serial]
+    fn test_diff_between_revisions() {
+        let base = GraphStore::open_in_memory().unwrap();
+        base.insert_node(&function("/tmp/base", "kept", "fn kept()"))
+            .unwrap();
+        base.insert_node(&function("/tmp/base", "gone", "fn gone()"))
+            .unwrap();
+        base.insert_node(&function("/tmp/base", "edited", "fn edited()"))
+            .unwrap();
+
+        let head = GraphStore::open_in_memory().unwrap();
+        head.insert_node(&function("/repo", "kept", "fn kept()"))
+            .unwrap();
+        head.insert_node(&function("/repo", "edited", "fn edited(x: u32)"))
+            .unwrap();
+        head.insert_node(&function("/repo", "fresh", "fn fresh()"))
+            .unwrap();
+
+        let diff = RevisionDiff::between(
+            ("main", &base, Path::new("/tmp/base")),
+            ("current", &head, Path::new("/repo")),
+        )
+        .unwrap();
+
+        let keys = |symbols: &[RevisionSymbol]| -> Vec<String> {
+            symbols.iter().map(|s| s.key.clone()).collect()
+        };
+        assert_eq!(keys(&diff.added), ["src/lib.rs::fresh"]);
+        assert_eq!(keys(&diff.removed), ["src/lib.rs::gone"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.changed[0].after.signature.as_deref(),
+            Some("fn edited(x: u32)")
+        );
+        assert!(
+            diff.to_markdown()
+                .contains("1 added, 1 removed, 1 changed.")
+        );
+    }
+
+    #[test]
+    fn test_partition_names_and_listing() {
+        let dir = TempDir::new().unwrap();
+        assert!(GraphPartition::new(dir.path(), "../escape").is_err());
+        assert!(GraphPartition::new(dir.path(), "").is_err());
+
+        let partition = GraphPartition::new(dir.path(), "feature/login").unwrap();
+        assert_eq!(partition.dir(), dir.path().join("feature__login"));
+        assert!(!partition.exists());
+
+        let manifest = RevisionManifest {
+            name: "feature/login".to_string(),
+            revision: "feature/login".to_string(),
+            commit: "abc123".to_string(),
+            root: PathBuf::from("/tmp/checkout"),
+            indexed_at: 0,
+        };
+        partition.write_manifest(&manifest).unwrap();
+        std::fs::write(partition.graph_path(), b"").unwrap();
+        assert_eq!(GraphPartition::list(dir.path()), [manifest]);
+    }
+}
@@ -0,0 +1,405 @@
+//! Security-sensitive API detection.
+//!
+//! During indexing, each function's source span is scanned for calls into
+//! APIs a security review cares about ([`Sensitivity`]: process execution,
+//! dynamic evaluation, `unsafe`, raw SQL, filesystem writes, network I/O).
+//! Matches are stored on the node as a comma-separated `sensitivity`
+//! property, so `graph_query` can filter on it and [`AuditReport`] can list
+//! them grouped by category.
+//!
+//! Detection is pattern-based and deliberately broad: it points a reviewer
+//! at code worth reading, it doesn't prove anything is exploitable.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::findings::{Finding, FindingLevel, Findings, Location};
+use crate::overview::relative_path;
+use crate::store::{GraphStore, Result, node_line, node_str};
+
+/// A category of security-sensitive API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sensitivity {
+    /// Spawns processes or runs shell commands.
+    Exec,
+    /// Evaluates code or deserializes objects dynamically.
+    Eval,
+    /// `unsafe` code, raw pointers, FFI escapes.
+    Unsafe,
+    /// SQL built from strings.
+    RawSql,
+    /// Writes, deletes or renames files.
+    FsWrite,
+    /// Opens network connections or makes HTTP requests.
+    Network,
+}
+
+impl Sensitivity {
+    /// Every category, in report order.
+    pub const ALL: [Sensitivity; 6] = [
+        Sensitivity::Exec,
+        Sensitivity::Eval,
+        Sensitivity::Unsafe,
+        Sensitivity::RawSql,
+        Sensitivity::FsWrite,
+        Sensitivity::Network,
+    ];
+
+    /// Returns the string representation stored on nodes.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sensitivity::Exec => "exec",
+            Sensitivity::Eval => "eval",
+            Sensitivity::Unsafe => "unsafe",
+            Sensitivity::RawSql => "raw_sql",
+            Sensitivity::FsWrite => "fs_write",
+            Sensitivity::Network => "network",
+        }
+    }
+
+    /// Parse the stored string representation.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == s)
+    }
+
+    fn patterns(&self) -> &'static [Regex] {
+        &PATTERNS[*self as usize]
+    }
+}
+
+impl fmt::Display for Sensitivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Patterns per category, indexed in [`Sensitivity::ALL`] order.
+static PATTERNS: LazyLock<Vec<Vec<Regex>>> = LazyLock::new(|| {
+    let compile = |patterns: &[&str]| -> Vec<Regex> {
+        patterns
+            .iter()
+            .map(|p| Regex::new(p).expect("valid sensitivity pattern"))
+            .collect()
+    };
+    vec![
+        // Exec
+        compile(&[
+            r"\bCommand::new\s*\(",
+            r"\bsubprocess\.",
+            r"\bos\.(system|popen|exec[lv]p?e?|spawn[lv]p?e?)\s*\(",
+            r"\bchild_process\b",
+            r"(^|[^\w.])exec(Sync|File|FileSync)?\s*\(",
+            r"\bexec\.Command\s*\(",
+            r"\bRuntime\.getRuntime\(\)\.exec\b",
+            r"\bProcessBuilder\b",
+            r"(^|[^\w.])system\s*\(",
+        ]),
+        // Eval
+        compile(&[
+            r"(^|[^\w.])eval\s*\(",
+            r"\bnew\s+Function\s*\(",
+            r"\bpickle\.loads?\s*\(",
+            r"\bmarshal\.loads?\s*\(",
+            r"\byaml\.load\s*\(",
+            r"\bvm\.runIn\w*\s*\(",
+        ]),
+        // Unsafe
+        compile(&[
+            r"\bunsafe\s*\{",
+            r"\bunsafe\s+(fn|impl|extern)\b",
+            r"\bmem::transmute\b",
+            r"\bunsafe\.Pointer\b",
+            r"\bctypes\.",
+        ]),
+        // RawSql
+        compile(&[
+            r#"(?i)["'`]\s*(select\s.+\sfrom\s|insert\s+into\s|update\s+\w+\s+set\s|delete\s+from\s|drop\s+table\s|create\s+table\s)"#,
+            r"\bcursor\.execute\s*\(",
+            r"\b(execute_batch|raw_sql|query_raw|executeQuery|executeUpdate)\s*\(",
+        ]),
+        // FsWrite
+        compile(&[
+            r"\bfs::(write|remove_file|remove_dir|remove_dir_all|rename|copy|create_dir_all)\s*\(",
+            r"\bFile::create\s*\(",
+            r"\bOpenOptions::new\s*\(",
+            r#"\bopen\s*\([^)]*,\s*["'][wax]\+?b?["']"#,
+            r"\b(writeFile|writeFileSync|appendFile|appendFileSync|unlink|unlinkSync|rmSync)\s*\(",
+            r"\b(os\.(remove|unlink|rename|rmdir|replace)|shutil\.(rmtree|move|copy\w*))\s*\(",
+            r"\b(os|ioutil)\.(WriteFile|Remove|RemoveAll|Create)\s*\(",
+            r#"\bfopen\s*\([^)]*"[wa]"#,
+        ]),
+        // Network
+        compile(&[
+            r"\breqwest::",
+            r"\b(TcpStream|UdpSocket)::(connect|bind)\b",
+            r"\bTcpListener::bind\b",
+            r"\bhyper::",
+            r"\brequests\.(get|post|put|patch|delete|head|request)\s*\(",
+            r"\burllib\.request\b",
+            r"\bhttp\.client\b",
+            r"\bsocket\.socket\s*\(",
+            r"(^|[^\w.])fetch\s*\(",
+            r"\baxios\b",
+            r"\bXMLHttpRequest\b",
+            r"\bhttp\.(Get|Post|NewRequest)\s*\(",
+            r"\bnet\.(Dial|Listen)\s*\(",
+        ]),
+    ]
+});
+
+/// Categories whose patterns appear in `source`, sorted.
+///
+/// Lines that are plainly comments (`//`, `#`, `*`, `--` prefixes) are
+/// skipped so documentation mentioning an API doesn't count.
+pub fn scan_source(source: &str) -> Vec<Sensitivity> {
+    let code: Vec<&str> = source
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim_start();
+            !(trimmed.starts_with("//")
+                || (trimmed.starts_with('#') && !trimmed.starts_with("#["))
+                || trimmed.starts_with("* ")
+                || trimmed.starts_with("/*")
+                || trimmed.starts_with("--"))
+        })
+        .collect();
+    Sensitivity::ALL
+        .into_iter()
+        .filter(|category| {
+            category
+                .patterns()
+                .iter()
+                .any(|re| code.iter().any(|line| re.is_match(line)))
+        })
+        .collect()
+}
+
+/// Join categories into the stored property value.
+pub(crate) fn to_property(categories: &[Sensitivity]) -> String {
+    categories
+        .iter()
+        .map(Sensitivity::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parse a stored property value, ignoring unknown categories.
+pub(crate) fn from_property(value: &str) -> Vec<Sensitivity> {
+    value.split(',').filter_map(Sensitivity::parse).collect()
+}
+
+/// A symbol tagged with at least one sensitive category.
+#[derive(Debug, Clone, Serialize)]
+pub struct SensitiveSymbol {
+    /// Qualified name, falling back to the plain name.
+    pub name: String,
+    /// File path relative to the report root.
+    pub file_path: String,
+    /// 1-indexed line of the definition.
+    pub line: usize,
+    pub sensitivity: Vec<Sensitivity>,
+}
+
+/// Tagged symbols grouped by category.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    /// Every tagged symbol, ordered by file and line.
+    pub symbols: Vec<SensitiveSymbol>,
+}
+
+impl AuditReport {
+    /// Collect tagged symbols, optionally restricted to one category. File
+    /// paths under `root` are reported relative to it.
+    pub fn from_store(
+        store: &GraphStore,
+        root: &Path,
+        category: Option<Sensitivity>,
+    ) -> Result<Self> {
+        let mut symbols: Vec<SensitiveSymbol> = store
+            .find_sensitive(category)?
+            .iter()
+            .map(|node| SensitiveSymbol {
+                name: node_str(node, "qualified_name")
+                    .or_else(|| node_str(node, "name"))
+                    .unwrap_or_default()
+                    .to_string(),
+                file_path: relative_path(node_str(node, "file_path").unwrap_or_default(), root),
+                line: node_line(node).unwrap_or(0),
+                sensitivity: from_property(node_str(node, "sensitivity").unwrap_or_default()),
+            })
+            .collect();
+        symbols.sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+        Ok(Self { symbols })
+    }
+
+    /// Tagged symbols per category, in [`Sensitivity::ALL`] order.
+    pub fn by_category(&self) -> BTreeMap<Sensitivity, Vec<&SensitiveSymbol>> {
+        let mut groups: BTreeMap<Sensitivity, Vec<&SensitiveSymbol>> = BTreeMap::new();
+        for symbol in &self.symbols {
+            for category in &symbol.sensitivity {
+                groups.entry(*category).or_default().push(symbol);
+            }
+        }
+        groups
+    }
+
+    /// Render the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Security audit\n\n");
+        if self.symbols.is_empty() {
+            out.push_str(
+                "No symbols touch sensitive APIs. (Graphs built before sensitivity \
+                 tagging need `muninn index --reset`.)\n",
+            );
+            return out;
+        }
+
+        let groups = self.by_category();
+        let counts: Vec<String> = groups
+            .iter()
+            .map(|(category, symbols)| format!("{} {}", symbols.len(), category))
+            .collect();
+        out.push_str(&format!(
+            "{} symbols touch sensitive APIs ({}).\n",
+            self.symbols.len(),
+            counts.join(", ")
+        ));
+        for (category, symbols) in groups {
+            out.push_str(&format!("\n## {}\n\n", category));
+            for symbol in symbols {
+                out.push_str(&format!(
+                    "- `{}` at `{}:{}`\n",
+                    symbol.name, symbol.file_path, symbol.line
+                ));
+            }
+        }
+        out
+    }
+
+    /// One `audit/<category>` note per tagged symbol and category, for
+    /// SARIF output. Touching a sensitive API is a pointer for review, not
+    /// a defect.
+    pub fn findings(&self) -> Findings {
+        let mut findings = Findings::new();
+        for category in Sensitivity::ALL {
+            findings.rule(
+                format!("audit/{}", category),
+                format!("Symbol touches {} APIs", category),
+            );
+        }
+        for (category, symbols) in self.by_category() {
+            for symbol in symbols {
+                findings.push(
+                    Finding::new(
+                        format!("audit/{}", category),
+                        FindingLevel::Note,
+                        format!("`{}` touches {} APIs", symbol.name, category),
+                    )
+                    .at(Location::new(&symbol.file_path, symbol.line)),
+                );
+            }
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbols::{Symbol, SymbolKind};
+    use serial_test::serial;
+
+    #[test]
+    fn test_scan_source() {
+        let source = r#"
+fn run(path: &Path, user: &str) -> Result<()> {
+    // Command::new("ignored") in a comment
+    let out = std::process::Command::new("git").arg("log").output()?;
+    let sql = format!("SELECT * FROM users WHERE name = '{}'", user);
+    std::fs::write(path, out.stdout)?;
+    let body = reqwest::blocking::get(URL)?.text()?;
+    Ok(())
+}
+"#;
+        assert_eq!(
+            scan_source(source),
+            vec![
+                Sensitivity::Exec,
+                Sensitivity::RawSql,
+                Sensitivity::FsWrite,
+                Sensitivity::Network
+            ]
+        );
+
+        assert_eq!(
+            scan_source("data = pickle.loads(blob)\nos.system(cmd)"),
+            vec![Sensitivity::Exec, Sensitivity::Eval]
+        );
+        assert_eq!(
+            scan_source("let p = unsafe { ptr.read() };"),
+            vec![Sensitivity::Unsafe]
+        );
+        assert!(scan_source("fn evaluate(x: i32) -> i32 { x.system_time() }").is_empty());
+        assert!(scan_source("let name = \"selected items from list\";").is_empty());
+    }
+
+    #[test]
+    fn test_property_round_trip() {
+        let categories = vec![Sensitivity::Exec, Sensitivity::FsWrite];
+        assert_eq!(to_property(&categories), "exec,fs_write");
+        assert_eq!(from_property("exec,fs_write,bogus"), categories);
+    }
+
+    #[test]
+    #[serial]
+    fn test_audit_report() {
+        let store = GraphStore::open_in_memory().unwrap();
+        let mut runner = Symbol::new("run", SymbolKind::Function, "/repo/src/run.rs", 4, 4);
+        runner.sensitivity = vec![Sensitivity::Exec, Sensitivity::Network];
+        let mut saver = Symbol::new("save", SymbolKind::Function, "/repo/src/io.rs", 9, 9);
+        saver.sensitivity = vec![Sensitivity::FsWrite];
+        for symbol in [
+            runner,
+            saver,
+            Symbol::new("pure", SymbolKind::Function, "src/math.rs", 1, 1),
+        ] {
+            store.insert_node(&symbol).unwrap();
+        }
+
+        let report = AuditReport::from_store(&store, Path::new("/repo"), None).unwrap();
+        let names: Vec<_> = report.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["save", "run"]);
+        let markdown = report.to_markdown();
+        assert!(
+            markdown.contains("2 symbols touch sensitive APIs (1 exec, 1 fs_write, 1 network)")
+        );
+        assert!(markdown.contains("## exec\n\n- `run` at `src/run.rs:4`"));
+        let findings = report.findings();
+        assert_eq!(findings.rules.len(), Sensitivity::ALL.len());
+        let results: Vec<_> = findings
+            .results
+            .iter()
+            .map(|f| (f.rule_id.as_str(), f.location.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("audit/exec", Location::new("src/run.rs", 4)),
+                ("audit/fs_write", Location::new("src/io.rs", 9)),
+                ("audit/network", Location::new("src/run.rs", 4)),
+            ]
+        );
+
+        let network =
+            AuditReport::from_store(&store, Path::new("/repo"), Some(Sensitivity::Network))
+                .unwrap();
+        assert_eq!(network.symbols.len(), 1);
+        assert_eq!(network.symbols[0].name, "run");
+    }
+}
@@ -0,0 +1,219 @@
+//! Portable graph snapshots.
+//!
+//! A snapshot is a gzipped tarball holding a consistent copy of `graph.db`
+//! and a [`SnapshotManifest`] naming the git commit it was indexed at, so
+//! CI can publish prebuilt indexes and checkouts can install one instead
+//! of re-indexing.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+/// Snapshot format written by this version.
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const GRAPH_ENTRY: &str = "graph.db";
+
+/// Error type for snapshot operations.
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("Invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("Invalid snapshot: {0}")]
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, SnapshotError>;
+
+/// What a snapshot was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Snapshot format version.
+    pub format: u32,
+    /// Git commit the graph was indexed at.
+    pub commit: String,
+    /// Whether the checkout had uncommitted changes when packed.
+    pub dirty: bool,
+    /// When the snapshot was packed, as Unix seconds.
+    pub created_at: u64,
+    /// Version of muninn that packed it.
+    pub muninn_version: String,
+    pub node_count: i64,
+    pub edge_count: i64,
+}
+
+/// Pack the graph database at `db_path` into a snapshot at `output`.
+///
+/// The database is copied with `VACUUM INTO`, so a store open elsewhere
+/// is captured consistently.
+pub fn pack_snapshot(db_path: &Path, output: &Path, manifest: &SnapshotManifest) -> Result<()> {
+    let copy = sibling(output, "graph.db.pack");
+    let _ = std::fs::remove_file(&copy);
+    let result = (|| {
+        let conn = rusqlite::Connection::open(db_path)?;
+        conn.execute("VACUUM INTO ?1", [copy.to_string_lossy()])?;
+        drop(conn);
+
+        let encoder = GzEncoder::new(File::create(output)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+
+        let manifest = serde_json::to_vec_pretty(manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, MANIFEST_ENTRY, manifest.as_slice())?;
+        archive.append_path_with_name(&copy, GRAPH_ENTRY)?;
+
+        archive.into_inner()?.finish()?;
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&copy);
+    result
+}
+
+/// Read the manifest of the snapshot at `path`.
+pub fn read_snapshot_manifest(path: &Path) -> Result<SnapshotManifest> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_ref() == Path::new(MANIFEST_ENTRY) {
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            return check_format(serde_json::from_str(&text)?);
+        }
+    }
+    Err(SnapshotError::Invalid(format!(
+        "no {} in {}",
+        MANIFEST_ENTRY,
+        path.display()
+    )))
+}
+
+/// Install the snapshot at `path` as the graph database `db_path`,
+/// replacing any existing one, and return its manifest.
+pub fn unpack_snapshot(path: &Path, db_path: &Path) -> Result<SnapshotManifest> {
+    let staged = sibling(db_path, "unpack");
+    let mut manifest = None;
+    let mut graph = false;
+
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.into_owned();
+        if name == Path::new(MANIFEST_ENTRY) {
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            manifest = Some(check_format(serde_json::from_str(&text)?)?);
+        } else if name == Path::new(GRAPH_ENTRY) {
+            if let Some(parent) = db_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::io::copy(&mut entry, &mut File::create(&staged)?)?;
+            graph = true;
+        }
+    }
+
+    let manifest = match (manifest, graph) {
+        (Some(manifest), true) => manifest,
+        _ => {
+            let _ = std::fs::remove_file(&staged);
+            return Err(SnapshotError::Invalid(format!(
+                "{} is missing {} or {}",
+                path.display(),
+                MANIFEST_ENTRY,
+                GRAPH_ENTRY
+            )));
+        }
+    };
+    std::fs::rename(&staged, db_path)?;
+    Ok(manifest)
+}
+
+fn check_format(manifest: SnapshotManifest) -> Result<SnapshotManifest> {
+    if manifest.format > SNAPSHOT_FORMAT {
+        return Err(SnapshotError::Invalid(format!(
+            "snapshot format {} is newer than this muninn supports ({})",
+            manifest.format, SNAPSHOT_FORMAT
+        )));
+    }
+    Ok(manifest)
+}
+
+/// `path` with `suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphStore, Symbol, SymbolKind};
+    use serial_test::serial;
+    use tempfile::TempDir;
+
+    fn manifest() -> SnapshotManifest {
+        SnapshotManifest {
+            format: SNAPSHOT_FORMAT,
+            commit: "0123456789abcdef".to_string(),
+            dirty: false,
+            created_at: 1_700_000_000,
+            muninn_version: "0.0.0".to_string(),
+            node_count: 1,
+            edge_count: 0,
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_pack_unpack_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let db = dir.path().join("graph.db");
+        {
+            let store = GraphStore::open(&db).unwrap();
+            store
+                .insert_node(&Symbol::new(
+                    "main",
+                    SymbolKind::Function,
+                    "src/main.rs",
+                    1,
+                    3,
+                ))
+                .unwrap();
+        }
+
+        let snapshot = dir.path().join("graph.tar.gz");
+        pack_snapshot(&db, &snapshot, &manifest()).unwrap();
+        assert_eq!(read_snapshot_manifest(&snapshot).unwrap(), manifest());
+
+        let restored = dir.path().join("other/graph.db");
+        assert_eq!(unpack_snapshot(&snapshot, &restored).unwrap(), manifest());
+        let store = GraphStore::open(&restored).unwrap();
+        assert_eq!(store.find_by_name("main").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_newer_format_rejected() {
+        let newer = SnapshotManifest {
+            format: SNAPSHOT_FORMAT + 1,
+            ..manifest()
+        };
+        assert!(matches!(
+            check_format(newer),
+            Err(SnapshotError::Invalid(_))
+        ));
+    }
+}
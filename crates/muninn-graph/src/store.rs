@@ -1,16 +1,24 @@
 //! Graph storage using graphqlite.
 //!
-//! Provides persistent storage for the code graph using SQLite with Cypher query support.
+//! Provides persistent storage for the code graph using SQLite with Cypher query support,
+//! or read-only access to a graph served remotely (see [`crate::remote`]).
+//! [`GraphStore::dump`] and [`GraphStore::load`] move a graph through a
+//! portable JSON Lines file instead of the SQLite database.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
 use std::path::Path;
 
 use graphqlite::{CypherResult, Graph, Value};
+use serde::{Deserialize, Serialize};
 
 /// Type alias for the node ID map returned by bulk insert.
 pub type NodeIdMap = HashMap<String, i64>;
 
-use crate::edges::{Edge, EdgeKind};
+use crate::edges::{CallType, Edge, EdgeKind};
+use crate::lock::{FileLock, LockError};
+use crate::remote::RemoteGraph;
+use crate::sensitivity::{self, Sensitivity};
 use crate::symbols::{Symbol, SymbolKind, Visibility};
 
 /// Error type for graph store operations.
@@ -18,18 +26,56 @@ use crate::symbols::{Symbol, SymbolKind, Visibility};
 pub enum StoreError {
     #[error("Database error: {0}")]
     Database(#[from] graphqlite::Error),
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
     #[error("Invalid data: {0}")]
     InvalidData(String),
+    #[error("Lock error: {0}")]
+    Lock(#[from] LockError),
+    #[error("Remote graph error: {0}")]
+    Remote(String),
+    #[error("Remote graph at {0} is read-only")]
+    ReadOnly(String),
 }
 
 pub type Result<T> = std::result::Result<T, StoreError>;
 
+/// Content hash per indexed file, kept next to the graph tables so an
+/// index run can skip the files that haven't changed.
+const FILE_HASHES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS muninn_file_hashes (
+    path TEXT PRIMARY KEY,
+    hash TEXT NOT NULL
+)";
+
+/// Dump format written by [`GraphStore::dump`].
+pub const DUMP_FORMAT: u32 = 1;
+
+/// Relationship types, for queries that go through each in turn.
+const REL_TYPES: &[&str] = &[
+    "CALLS",
+    "INCLUDES",
+    "DEFINES",
+    "IMPORTS",
+    "INHERITS",
+    "IMPLEMENTS",
+];
+
+/// Where a [`GraphStore`] keeps its graph.
+enum Backend {
+    Local(Graph),
+    Remote(RemoteGraph),
+}
+
 /// Persistent storage for the code graph.
 ///
 /// Uses graphqlite to store symbols as nodes and relationships as edges,
-/// supporting Cypher queries for graph traversal.
+/// supporting Cypher queries for graph traversal. A store opened with
+/// [`GraphStore::open_remote`] answers the same queries from a graph
+/// service and refuses writes.
 pub struct GraphStore {
-    graph: Graph,
+    backend: Backend,
+    /// Held for the store's lifetime when opened via [`GraphStore::open_for_write`].
+    _lock: Option<FileLock>,
 }
 
 impl GraphStore {
@@ -39,13 +85,88 @@ impl GraphStore {
     /// Use `:memory:` for an in-memory database.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let graph = Graph::open(path)?;
-        Ok(Self { graph })
+        Ok(Self {
+            backend: Backend::Local(graph),
+            _lock: None,
+        })
+    }
+
+    /// Open a graph database for exclusive writing.
+    ///
+    /// Takes an advisory lock (`<path>.lock`) before opening so two
+    /// concurrent indexers can't interleave writes into the same file.
+    /// Fails fast with [`StoreError::Lock`] if another live process holds
+    /// it; locks left by a crashed process are reclaimed. The lock is
+    /// released when the store is dropped.
+    pub fn open_for_write<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let lock = FileLock::try_acquire(path.as_ref())?;
+        Self::open_locked(path, lock)
+    }
+
+    /// Open a graph database under a lock the caller already holds.
+    ///
+    /// For callers that need to do destructive work on the file (e.g.
+    /// `muninn index --reset` deleting it) before opening, under the same
+    /// lock that then guards the writes.
+    pub fn open_locked<P: AsRef<Path>>(path: P, lock: FileLock) -> Result<Self> {
+        let graph = Graph::open(path)?;
+        Ok(Self {
+            backend: Backend::Local(graph),
+            _lock: Some(lock),
+        })
     }
 
     /// Create an in-memory graph database.
     pub fn open_in_memory() -> Result<Self> {
         let graph = Graph::open_in_memory()?;
-        Ok(Self { graph })
+        Ok(Self {
+            backend: Backend::Local(graph),
+            _lock: None,
+        })
+    }
+
+    /// Connect to a graph served by `muninn graph serve` at `url`.
+    ///
+    /// Reads are answered by the service; writes fail with
+    /// [`StoreError::ReadOnly`]. Nothing is sent until the first query.
+    pub fn open_remote(url: &str) -> Result<Self> {
+        Ok(Self {
+            backend: Backend::Remote(RemoteGraph::connect(url)?),
+            _lock: None,
+        })
+    }
+
+    /// Whether this store reads from a remote graph service.
+    pub fn is_remote(&self) -> bool {
+        matches!(self.backend, Backend::Remote(_))
+    }
+
+    /// The local graph, or [`StoreError::ReadOnly`] for a remote store.
+    fn local(&self) -> Result<&Graph> {
+        match &self.backend {
+            Backend::Local(graph) => Ok(graph),
+            Backend::Remote(remote) => Err(StoreError::ReadOnly(remote.url().to_string())),
+        }
+    }
+
+    /// Run a Cypher query on whichever backend holds the graph.
+    fn run(&self, cypher: &str) -> Result<CypherResult> {
+        match &self.backend {
+            Backend::Local(graph) => Ok(graph.query(cypher)?),
+            Backend::Remote(remote) => remote.query(cypher),
+        }
+    }
+
+    /// Look a node up by ID with a query, for backends without direct access.
+    fn query_node(&self, node_id: &str) -> Result<Option<Value>> {
+        let cypher = format!(
+            "MATCH (n {{id: '{}'}}) RETURN n",
+            graphqlite::escape_string(node_id)
+        );
+        Ok(self
+            .run(&cypher)?
+            .iter()
+            .find_map(|r| r.get_value("n").cloned()))
     }
 
     /// Insert or update a symbol as a node in the graph.
@@ -56,7 +177,7 @@ impl GraphStore {
         let label = symbol_kind_to_label(symbol.kind);
         let props = symbol_to_properties(symbol);
 
-        self.graph.upsert_node(&node_id, props, label)?;
+        self.local()?.upsert_node(&node_id, props, label)?;
 
         Ok(node_id)
     }
@@ -92,7 +213,7 @@ impl GraphStore {
             })
             .collect();
 
-        let id_map = self.graph.insert_nodes_bulk(node_refs)?;
+        let id_map = self.local()?.insert_nodes_bulk(node_refs)?;
         Ok(id_map)
     }
 
@@ -101,7 +222,7 @@ impl GraphStore {
         let rel_type = edge_kind_to_rel_type(&edge.kind);
         let props = edge_to_properties(&edge.kind);
 
-        self.graph
+        self.local()?
             .upsert_edge(&edge.source_id, &edge.target_id, props, rel_type)?;
 
         Ok(())
@@ -142,7 +263,7 @@ impl GraphStore {
             })
             .collect();
 
-        let inserted = self.graph.insert_edges_bulk(edge_refs, id_map)?;
+        let inserted = self.local()?.insert_edges_bulk(edge_refs, id_map)?;
         Ok(inserted)
     }
 
@@ -156,12 +277,13 @@ impl GraphStore {
         }
 
         // Wrap in transaction for better performance
-        self.graph.connection().execute("BEGIN")?;
+        let graph = self.local()?;
+        graph.connection().execute("BEGIN")?;
         let result = edges.iter().try_for_each(|edge| self.insert_edge(edge));
         if result.is_ok() {
-            self.graph.connection().execute("COMMIT")?;
+            graph.connection().execute("COMMIT")?;
         } else {
-            let _ = self.graph.connection().execute("ROLLBACK");
+            let _ = graph.connection().execute("ROLLBACK");
         }
         result
     }
@@ -169,6 +291,7 @@ impl GraphStore {
     /// Delete all nodes and edges associated with a file.
     ///
     /// This is used when a file is modified or deleted to clear stale data.
+    /// Its content hash is forgotten too, so the next build re-indexes it.
     /// Wraps deletes in a transaction for performance.
     pub fn delete_file(&self, file_path: &str) -> Result<usize> {
         // Find all nodes from this file using inline property matching
@@ -177,7 +300,7 @@ impl GraphStore {
             graphqlite::escape_string(file_path)
         );
 
-        let result = self.graph.query(&cypher)?;
+        let result = self.run(&cypher)?;
 
         // Collect IDs first to avoid modifying during iteration
         let ids: Vec<String> = result
@@ -186,63 +309,215 @@ impl GraphStore {
             .collect();
 
         if ids.is_empty() {
+            self.remove_file_hashes(&[file_path.to_string()])?;
             return Ok(0);
         }
 
         // Wrap in transaction for performance
-        self.graph.connection().execute("BEGIN")?;
+        let graph = self.local()?;
+        graph.connection().execute("BEGIN")?;
         let mut deleted = 0;
         for id in &ids {
-            if let Err(e) = self.graph.delete_node(id) {
-                let _ = self.graph.connection().execute("ROLLBACK");
+            if let Err(e) = graph.delete_node(id) {
+                let _ = graph.connection().execute("ROLLBACK");
                 return Err(e.into());
             }
             deleted += 1;
         }
-        self.graph.connection().execute("COMMIT")?;
+        graph.connection().execute("COMMIT")?;
+        self.remove_file_hashes(&[file_path.to_string()])?;
 
         Ok(deleted)
     }
 
+    /// Edges into the nodes of `file_path` from nodes of other files, with
+    /// the name and kind of the node each points at, so they can be
+    /// attached again after the file is re-indexed.
+    pub fn edges_into_file(&self, file_path: &str) -> Result<Vec<IncomingEdge>> {
+        let file = graphqlite::escape_string(file_path);
+        let mut edges = Vec::new();
+        for rel in REL_TYPES {
+            let cypher = format!(
+                "MATCH (a)-[r:{rel}]->(b {{file_path: '{file}'}}) \
+                 RETURN a.id AS source, a.file_path AS source_file, b.name AS name, \
+                 b.kind AS kind, r.call_type AS call_type, r.line AS line"
+            );
+            for row in self.run(&cypher)?.iter() {
+                let (Ok(source_id), Ok(source_file), Ok(target_name), Ok(target_kind)) = (
+                    row.get::<String>("source"),
+                    row.get::<String>("source_file"),
+                    row.get::<String>("name"),
+                    row.get::<String>("kind"),
+                ) else {
+                    continue;
+                };
+                if source_file == file_path {
+                    continue;
+                }
+                let call_type = row.get::<String>("call_type").ok();
+                let line = value_line(row.get_value("line")).unwrap_or(0);
+                let Some(kind) = rel_type_to_edge_kind(rel, call_type.as_deref(), line) else {
+                    continue;
+                };
+                edges.push(IncomingEdge {
+                    source_id,
+                    source_file,
+                    target_file: file_path.to_string(),
+                    target_name,
+                    target_kind,
+                    kind,
+                });
+            }
+        }
+        Ok(edges)
+    }
+
+    /// Content hashes recorded with [`GraphStore::set_file_hashes`], by
+    /// file path.
+    pub fn file_hashes(&self) -> Result<HashMap<String, String>> {
+        let conn = self.file_hash_table()?;
+        let mut stmt = conn.prepare("SELECT path, hash FROM muninn_file_hashes")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Record the content hash of each `(path, hash)`, replacing earlier ones.
+    pub fn set_file_hashes(&self, hashes: &[(String, String)]) -> Result<()> {
+        let conn = self.file_hash_table()?;
+        conn.execute("BEGIN", [])?;
+        let result = hashes.iter().try_for_each(|(path, hash)| {
+            conn.execute(
+                "INSERT OR REPLACE INTO muninn_file_hashes (path, hash) VALUES (?1, ?2)",
+                rusqlite::params![path, hash],
+            )
+            .map(|_| ())
+        });
+        match result {
+            Ok(()) => conn.execute("COMMIT", []).map(|_| ())?,
+            Err(e) => {
+                let _ = conn.execute("ROLLBACK", []);
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget the content hashes of `paths`.
+    pub fn remove_file_hashes(&self, paths: &[String]) -> Result<()> {
+        let conn = self.file_hash_table()?;
+        for path in paths {
+            conn.execute(
+                "DELETE FROM muninn_file_hashes WHERE path = ?1",
+                rusqlite::params![path],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The local connection, with the file hash table created.
+    fn file_hash_table(&self) -> Result<&rusqlite::Connection> {
+        let conn = self.local()?.connection().sqlite_connection();
+        conn.execute(FILE_HASHES_SCHEMA, [])?;
+        Ok(conn)
+    }
+
     /// Delete a specific node by ID.
     pub fn delete_node(&self, node_id: &str) -> Result<()> {
-        self.graph.delete_node(node_id)?;
+        self.local()?.delete_node(node_id)?;
         Ok(())
     }
 
     /// Delete an edge between two nodes.
     pub fn delete_edge(&self, source_id: &str, target_id: &str) -> Result<()> {
-        self.graph.delete_edge(source_id, target_id)?;
+        self.local()?.delete_edge(source_id, target_id)?;
         Ok(())
     }
 
     /// Check if a node exists.
     pub fn has_node(&self, node_id: &str) -> Result<bool> {
-        Ok(self.graph.has_node(node_id)?)
+        match &self.backend {
+            Backend::Local(graph) => Ok(graph.has_node(node_id)?),
+            Backend::Remote(_) => Ok(self.query_node(node_id)?.is_some()),
+        }
     }
 
     /// Get a node by ID.
     pub fn get_node(&self, node_id: &str) -> Result<Option<Value>> {
-        Ok(self.graph.get_node(node_id)?)
+        match &self.backend {
+            Backend::Local(graph) => Ok(graph.get_node(node_id)?),
+            Backend::Remote(_) => self.query_node(node_id),
+        }
     }
 
     /// Execute a Cypher query.
     pub fn query(&self, cypher: &str) -> Result<CypherResult> {
-        Ok(self.graph.query(cypher)?)
+        self.run(cypher)
     }
 
     /// Find all callers of a function/method.
+    ///
+    /// For a C/C++ declaration, these are the callers of the definitions
+    /// linked to it.
     pub fn find_callers(&self, callee_id: &str) -> Result<Vec<Value>> {
-        // Use inline property matching for callee node
+        let mut callers = Vec::new();
+        for id in self.call_targets(callee_id)? {
+            // Use inline property matching for callee node
+            let cypher = format!(
+                "MATCH (caller)-[:CALLS]->(callee {{id: '{}'}}) RETURN caller",
+                graphqlite::escape_string(&id)
+            );
+            let result = self.run(&cypher)?;
+            callers.extend(result.iter().filter_map(|r| r.get_value("caller").cloned()));
+        }
+        Ok(callers)
+    }
+
+    /// Find every call of a function/method, with the caller node and
+    /// the line and kind of each call. Declarations resolve as in
+    /// [`Self::find_callers`].
+    pub fn find_call_sites(&self, callee_id: &str) -> Result<Vec<CallSite>> {
+        let mut sites = Vec::new();
+        for id in self.call_targets(callee_id)? {
+            let cypher = format!(
+                "MATCH (caller)-[r:CALLS]->(callee {{id: '{}'}}) \
+                 RETURN caller, r.line AS line, r.call_type AS call_type",
+                graphqlite::escape_string(&id)
+            );
+            let result = self.run(&cypher)?;
+            sites.extend(result.iter().filter_map(|r| {
+                let caller = r.get_value("caller").cloned()?;
+                let line = value_line(r.get_value("line"));
+                let call_type = match r.get_value("call_type") {
+                    Some(Value::String(call_type)) => Some(call_type.clone()),
+                    _ => None,
+                };
+                Some(CallSite {
+                    caller,
+                    line,
+                    call_type,
+                })
+            }));
+        }
+        Ok(sites)
+    }
+
+    /// `callee_id` plus the definitions that define it, when it is a
+    /// declaration: calls resolve to definitions, not prototypes.
+    fn call_targets(&self, callee_id: &str) -> Result<Vec<String>> {
         let cypher = format!(
-            "MATCH (caller)-[:CALLS]->(callee {{id: '{}'}}) RETURN caller",
+            "MATCH (def)-[:DEFINES]->(decl {{id: '{}'}}) RETURN def.id AS id",
             graphqlite::escape_string(callee_id)
         );
-        let result = self.graph.query(&cypher)?;
-        Ok(result
-            .iter()
-            .filter_map(|r| r.get_value("caller").cloned())
-            .collect())
+        let mut ids = vec![callee_id.to_string()];
+        ids.extend(
+            self.run(&cypher)?
+                .iter()
+                .filter_map(|r| match r.get_value("id") {
+                    Some(Value::String(id)) => Some(id.clone()),
+                    _ => None,
+                }),
+        );
+        Ok(ids)
     }
 
     /// Find all functions/methods called by a caller.
@@ -252,7 +527,7 @@ impl GraphStore {
             "MATCH (caller {{id: '{}'}})-[:CALLS]->(callee) RETURN callee",
             graphqlite::escape_string(caller_id)
         );
-        let result = self.graph.query(&cypher)?;
+        let result = self.run(&cypher)?;
         Ok(result
             .iter()
             .filter_map(|r| r.get_value("callee").cloned())
@@ -266,7 +541,7 @@ impl GraphStore {
             "MATCH (n {{file_path: '{}'}}) RETURN n ORDER BY n.start_line",
             graphqlite::escape_string(file_path)
         );
-        let result = self.graph.query(&cypher)?;
+        let result = self.run(&cypher)?;
         Ok(result
             .iter()
             .filter_map(|r| r.get_value("n").cloned())
@@ -286,7 +561,7 @@ impl GraphStore {
             "MATCH (n {{qualified_name: '{}'}}) RETURN n",
             graphqlite::escape_string(qualified_name)
         );
-        let result = self.graph.query(&cypher)?;
+        let result = self.run(&cypher)?;
         Ok(result
             .iter()
             .filter_map(|r| r.get_value("n").cloned())
@@ -300,28 +575,299 @@ impl GraphStore {
             "MATCH (n {{name: '{}'}}) RETURN n",
             graphqlite::escape_string(name)
         );
-        let result = self.graph.query(&cypher)?;
+        let result = self.run(&cypher)?;
+        Ok(result
+            .iter()
+            .filter_map(|r| r.get_value("n").cloned())
+            .collect())
+    }
+
+    /// Find symbols tagged as touching sensitive APIs, optionally only
+    /// those in one category.
+    pub fn find_sensitive(&self, category: Option<Sensitivity>) -> Result<Vec<Value>> {
+        let cypher = match category {
+            Some(category) => format!(
+                "MATCH (n) WHERE n.sensitivity CONTAINS '{}' RETURN n",
+                category.as_str()
+            ),
+            None => "MATCH (n) WHERE n.sensitivity IS NOT NULL RETURN n".to_string(),
+        };
+        let result = self.run(&cypher)?;
+        Ok(result
+            .iter()
+            .filter_map(|r| r.get_value("n").cloned())
+            .collect())
+    }
+
+    /// Every node in the graph.
+    pub fn all_nodes(&self) -> Result<Vec<Value>> {
+        let result = self.run("MATCH (n) RETURN n")?;
         Ok(result
             .iter()
             .filter_map(|r| r.get_value("n").cloned())
             .collect())
     }
 
+    /// Distinct file paths of the indexed symbols.
+    pub fn file_paths(&self) -> Result<HashSet<String>> {
+        let result = self.run("MATCH (n) RETURN DISTINCT n.file_path AS file_path")?;
+        Ok(result
+            .iter()
+            .filter_map(|r| r.get::<String>("file_path").ok())
+            .collect())
+    }
+
+    /// Every `CALLS` edge in the graph.
+    pub fn call_edges(&self) -> Result<Vec<CallEdge>> {
+        let result = self.run(
+            "MATCH (a)-[r:CALLS]->(b) RETURN a.id AS caller, b.id AS callee, r.line AS line",
+        )?;
+        Ok(result
+            .iter()
+            .filter_map(|r| match (r.get_value("caller"), r.get_value("callee")) {
+                (Some(Value::String(caller)), Some(Value::String(callee))) => Some(CallEdge {
+                    caller: caller.clone(),
+                    callee: callee.clone(),
+                    line: value_line(r.get_value("line")),
+                }),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// IDs of every node with at least one incoming edge, of any type.
+    ///
+    /// Besides `CALLS`, this counts `DEFINES` (a linked C/C++ declaration)
+    /// and `INCLUDES` (an included file).
+    pub fn referenced_ids(&self) -> Result<HashSet<String>> {
+        let result = self.run("MATCH (a)-[r]->(b) RETURN DISTINCT b.id AS id")?;
+        Ok(result
+            .iter()
+            .filter_map(|r| match r.get_value("id") {
+                Some(Value::String(id)) => Some(id.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
     /// Get graph statistics.
     pub fn stats(&self) -> Result<GraphStats> {
-        let stats = self.graph.stats()?;
+        let graph = match &self.backend {
+            Backend::Local(graph) => graph,
+            Backend::Remote(remote) => return remote.stats(),
+        };
+        let stats = graph.stats()?;
         Ok(GraphStats {
             node_count: stats.nodes,
             edge_count: stats.edges,
         })
     }
 
+    /// Write the graph as JSON Lines: a header, then every node, every
+    /// edge and every file content hash, one [`DumpRecord`] per line.
+    ///
+    /// Records are sorted so dumps of the same graph are identical and
+    /// diff cleanly when committed. A remote store dumps without hashes.
+    pub fn dump<W: Write>(&self, mut writer: W) -> Result<DumpStats> {
+        let mut records = Vec::new();
+        for node in self.all_nodes()? {
+            let Some(id) = node_str(&node, "id") else {
+                continue;
+            };
+            let label = match &node {
+                Value::Object(map) => match map.get("labels") {
+                    Some(Value::Array(labels)) => labels.iter().find_map(|l| match l {
+                        Value::String(l) => Some(l.clone()),
+                        _ => None,
+                    }),
+                    _ => None,
+                },
+                _ => None,
+            };
+            records.push(DumpRecord::Node {
+                id: id.to_string(),
+                label: label.unwrap_or_default(),
+                properties: dump_properties(&node, &["id"]),
+            });
+        }
+        let node_count = records.len();
+
+        let result = self.run("MATCH (a)-[r]->(b) RETURN a.id AS source, b.id AS target, r")?;
+        for row in result.iter() {
+            let (Ok(source), Ok(target), Some(edge)) = (
+                row.get::<String>("source"),
+                row.get::<String>("target"),
+                row.get_value("r"),
+            ) else {
+                continue;
+            };
+            let Value::Object(map) = edge else {
+                continue;
+            };
+            let Some(Value::String(rel)) = map.get("type") else {
+                continue;
+            };
+            records.push(DumpRecord::Edge {
+                source,
+                target,
+                rel: rel.clone(),
+                properties: dump_properties(edge, &[]),
+            });
+        }
+        let edge_count = records.len() - node_count;
+
+        if !self.is_remote() {
+            records.extend(
+                self.file_hashes()?
+                    .into_iter()
+                    .map(|(path, hash)| DumpRecord::FileHash { path, hash }),
+            );
+        }
+        let file_hash_count = records.len() - node_count - edge_count;
+
+        let mut lines: Vec<String> = records
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| StoreError::InvalidData(e.to_string()))?;
+        lines[..node_count].sort();
+        lines[node_count..node_count + edge_count].sort();
+        lines[node_count + edge_count..].sort();
+
+        let header = DumpRecord::Header {
+            format: DUMP_FORMAT,
+        };
+        let header =
+            serde_json::to_string(&header).map_err(|e| StoreError::InvalidData(e.to_string()))?;
+        for line in std::iter::once(&header).chain(&lines) {
+            writeln!(writer, "{}", line).map_err(|e| StoreError::InvalidData(e.to_string()))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| StoreError::InvalidData(e.to_string()))?;
+
+        Ok(DumpStats {
+            nodes: node_count,
+            edges: edge_count,
+            file_hashes: file_hash_count,
+        })
+    }
+
+    /// Read a dump written by [`GraphStore::dump`] into this store, which
+    /// must be empty.
+    pub fn load<R: BufRead>(&self, reader: R) -> Result<DumpStats> {
+        let graph = self.local()?;
+        if graph.stats()?.nodes > 0 {
+            return Err(StoreError::InvalidData(
+                "can only load a dump into an empty graph".to_string(),
+            ));
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut hashes = Vec::new();
+        let mut header = false;
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| StoreError::InvalidData(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: DumpRecord = serde_json::from_str(&line)
+                .map_err(|e| StoreError::InvalidData(format!("line {}: {}", index + 1, e)))?;
+            match record {
+                DumpRecord::Header { format } if format > DUMP_FORMAT => {
+                    return Err(StoreError::InvalidData(format!(
+                        "dump format {} is newer than this muninn supports ({})",
+                        format, DUMP_FORMAT
+                    )));
+                }
+                DumpRecord::Header { .. } => header = true,
+                _ if !header => {
+                    return Err(StoreError::InvalidData(
+                        "not a graph dump: missing header".to_string(),
+                    ));
+                }
+                DumpRecord::Node {
+                    id,
+                    label,
+                    properties,
+                } => nodes.push((id, load_properties(properties), label)),
+                DumpRecord::Edge {
+                    source,
+                    target,
+                    rel,
+                    properties,
+                } => edges.push((source, target, load_properties(properties), rel)),
+                DumpRecord::FileHash { path, hash } => hashes.push((path, hash)),
+            }
+        }
+
+        let id_map = graph.insert_nodes_bulk(nodes.iter().map(|(id, props, label)| {
+            let props = props.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+            (id.as_str(), props, label.as_str())
+        }))?;
+        let edge_count = graph.insert_edges_bulk(
+            edges.iter().map(|(source, target, props, rel)| {
+                let props = props.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+                (source.as_str(), target.as_str(), props, rel.as_str())
+            }),
+            &id_map,
+        )?;
+        self.set_file_hashes(&hashes)?;
+
+        Ok(DumpStats {
+            nodes: nodes.len(),
+            edges: edge_count,
+            file_hashes: hashes.len(),
+        })
+    }
+
     /// Get the underlying graphqlite Graph for advanced operations.
-    pub fn inner(&self) -> &Graph {
-        &self.graph
+    ///
+    /// `None` for a remote store.
+    pub fn inner(&self) -> Option<&Graph> {
+        match &self.backend {
+            Backend::Local(graph) => Some(graph),
+            Backend::Remote(_) => None,
+        }
     }
 }
 
+/// One `CALLS` edge into a symbol, as returned by [`GraphStore::find_call_sites`].
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    /// The calling node.
+    pub caller: Value,
+    /// Line of the call, when the extractor recorded one.
+    pub line: Option<usize>,
+    /// Call type (`direct`, `method`, `static_method`).
+    pub call_type: Option<String>,
+}
+
+/// A `CALLS` edge by node ID, as returned by [`GraphStore::call_edges`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+    /// Line of the call, when the extractor recorded one.
+    pub line: Option<usize>,
+}
+
+/// An edge into a file's node from another file, as returned by
+/// [`GraphStore::edges_into_file`]. The target is identified by name and
+/// kind, which survive re-indexing where the node ID (which includes the
+/// line) may not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncomingEdge {
+    pub source_id: String,
+    pub source_file: String,
+    pub target_file: String,
+    pub target_name: String,
+    /// [`SymbolKind::as_str`] of the target.
+    pub target_kind: String,
+    pub kind: EdgeKind,
+}
+
 /// Graph statistics.
 #[derive(Debug, Clone)]
 pub struct GraphStats {
@@ -329,6 +875,121 @@ pub struct GraphStats {
     pub edge_count: i64,
 }
 
+/// One line of a graph dump.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DumpRecord {
+    /// First line, naming the [`DUMP_FORMAT`].
+    Header { format: u32 },
+    Node {
+        id: String,
+        label: String,
+        properties: serde_json::Map<String, serde_json::Value>,
+    },
+    Edge {
+        source: String,
+        target: String,
+        /// Relationship type (`CALLS`, `INCLUDES`, ...).
+        rel: String,
+        #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+        properties: serde_json::Map<String, serde_json::Value>,
+    },
+    /// Content hash of an indexed file, so an index run after loading
+    /// only re-parses what changed.
+    FileHash { path: String, hash: String },
+}
+
+/// What a dump wrote or a load read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DumpStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub file_hashes: usize,
+}
+
+/// Convert a graphqlite Value to a serde_json Value.
+pub fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Integer(i) => serde_json::Value::Number((*i).into()),
+        Value::Float(f) => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::Array(arr) => serde_json::Value::Array(arr.iter().map(value_to_json).collect()),
+        Value::Object(map) => {
+            let obj: serde_json::Map<String, serde_json::Value> = map
+                .iter()
+                .map(|(k, v)| (k.clone(), value_to_json(v)))
+                .collect();
+            serde_json::Value::Object(obj)
+        }
+    }
+}
+
+/// The properties of a node or edge as JSON, without the `skip` keys.
+fn dump_properties(value: &Value, skip: &[&str]) -> serde_json::Map<String, serde_json::Value> {
+    let Value::Object(map) = value else {
+        return serde_json::Map::new();
+    };
+    match map.get("properties") {
+        Some(Value::Object(props)) => props
+            .iter()
+            .filter(|(k, _)| !skip.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), value_to_json(v)))
+            .collect(),
+        _ => serde_json::Map::new(),
+    }
+}
+
+/// Dumped properties back in the string form the store inserts.
+fn load_properties(
+    properties: serde_json::Map<String, serde_json::Value>,
+) -> Vec<(String, String)> {
+    properties
+        .into_iter()
+        .filter(|(_, v)| !v.is_null())
+        .map(|(k, v)| match v {
+            serde_json::Value::String(s) => (k, s),
+            other => (k, other.to_string()),
+        })
+        .collect()
+}
+
+/// Read a property from a graphqlite node (properties may be nested).
+pub(crate) fn node_prop<'a>(node: &'a Value, key: &str) -> Option<&'a Value> {
+    let Value::Object(map) = node else {
+        return None;
+    };
+    match map.get("properties") {
+        Some(Value::Object(props)) => props.get(key),
+        _ => map.get(key),
+    }
+}
+
+/// Read a string property from a graphqlite node.
+pub(crate) fn node_str<'a>(node: &'a Value, key: &str) -> Option<&'a str> {
+    match node_prop(node, key) {
+        Some(Value::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+/// Read a node's `start_line` (stored as a string, parsed back).
+pub(crate) fn node_line(node: &Value) -> Option<usize> {
+    value_line(node_prop(node, "start_line"))
+}
+
+/// Parse a line number stored as an integer or a string.
+fn value_line(value: Option<&Value>) -> Option<usize> {
+    match value {
+        Some(Value::Integer(line)) => usize::try_from(*line).ok(),
+        Some(Value::String(line)) => line.parse().ok(),
+        _ => None,
+    }
+}
+
 /// Convert a SymbolKind to a node label.
 fn symbol_kind_to_label(kind: SymbolKind) -> &'static str {
     match kind {
@@ -378,6 +1039,9 @@ fn symbol_to_properties(symbol: &Symbol) -> Vec<(&'static str, String)> {
     if let Some(v) = symbol.call_degree {
         props.push(("call_degree", v.to_string()));
     }
+    if !symbol.sensitivity.is_empty() {
+        props.push(("sensitivity", sensitivity::to_property(&symbol.sensitivity)));
+    }
 
     props
 }
@@ -396,9 +1060,34 @@ fn visibility_to_string(vis: &Visibility) -> String {
 fn edge_kind_to_rel_type(kind: &EdgeKind) -> &'static str {
     match kind {
         EdgeKind::Calls { .. } => "CALLS",
+        EdgeKind::Includes { .. } => "INCLUDES",
+        EdgeKind::Defines => "DEFINES",
+        EdgeKind::Imports { .. } => "IMPORTS",
+        EdgeKind::Inherits => "INHERITS",
+        EdgeKind::Implements => "IMPLEMENTS",
     }
 }
 
+/// Rebuild an EdgeKind from a relationship type and its properties.
+fn rel_type_to_edge_kind(rel: &str, call_type: Option<&str>, line: usize) -> Option<EdgeKind> {
+    Some(match rel {
+        "CALLS" => EdgeKind::Calls {
+            call_type: match call_type {
+                Some("method") => CallType::Method,
+                Some("static_method") => CallType::StaticMethod,
+                _ => CallType::Direct,
+            },
+            line,
+        },
+        "INCLUDES" => EdgeKind::Includes { line },
+        "DEFINES" => EdgeKind::Defines,
+        "IMPORTS" => EdgeKind::Imports { line },
+        "INHERITS" => EdgeKind::Inherits,
+        "IMPLEMENTS" => EdgeKind::Implements,
+        _ => return None,
+    })
+}
+
 /// Convert an EdgeKind to property key-value pairs (as strings).
 fn edge_to_properties(kind: &EdgeKind) -> Vec<(&'static str, String)> {
     match kind {
@@ -408,6 +1097,10 @@ fn edge_to_properties(kind: &EdgeKind) -> Vec<(&'static str, String)> {
                 ("line", line.to_string()),
             ]
         }
+        EdgeKind::Includes { line } | EdgeKind::Imports { line } => {
+            vec![("line", line.to_string())]
+        }
+        EdgeKind::Defines | EdgeKind::Inherits | EdgeKind::Implements => Vec::new(),
     }
 }
 
@@ -431,6 +1124,7 @@ mod tests {
             cyclomatic: None,
             cognitive: None,
             call_degree: None,
+            sensitivity: Vec::new(),
         }
     }
 
@@ -443,6 +1137,26 @@ mod tests {
         assert_eq!(stats.edge_count, 0);
     }
 
+    #[test]
+    #[serial]
+    fn test_open_for_write_is_exclusive() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("graph.db");
+
+        let writer = GraphStore::open_for_write(&path).expect("first writer should open");
+        let second = GraphStore::open_for_write(&path);
+        assert!(matches!(
+            second,
+            Err(StoreError::Lock(LockError::Held { .. }))
+        ));
+
+        // Readers are not blocked by the write lock.
+        GraphStore::open(&path).expect("reader should open");
+
+        drop(writer);
+        GraphStore::open_for_write(&path).expect("lock released on drop");
+    }
+
     #[test]
     #[serial]
     fn test_insert_node() {
@@ -521,6 +1235,63 @@ mod tests {
         assert_eq!(store.stats().unwrap().node_count, 1);
     }
 
+    #[test]
+    #[serial]
+    fn test_file_hashes_and_incoming_edges() {
+        let store = GraphStore::open_in_memory().unwrap();
+        assert!(store.file_hashes().unwrap().is_empty());
+
+        let caller = Symbol {
+            file_path: "main.rs".to_string(),
+            ..create_test_symbol("main", SymbolKind::Function)
+        };
+        let callee = Symbol {
+            file_path: "util.rs".to_string(),
+            ..create_test_symbol("helper", SymbolKind::Function)
+        };
+        store.insert_node(&caller).unwrap();
+        store.insert_node(&callee).unwrap();
+        store
+            .insert_edge(&Edge {
+                source_id: caller.id(),
+                target_id: callee.id(),
+                kind: EdgeKind::Calls {
+                    call_type: CallType::Method,
+                    line: 3,
+                },
+            })
+            .unwrap();
+        store
+            .set_file_hashes(&[
+                ("main.rs".to_string(), "aa".to_string()),
+                ("util.rs".to_string(), "bb".to_string()),
+            ])
+            .unwrap();
+
+        let incoming = store.edges_into_file("util.rs").unwrap();
+        assert_eq!(
+            incoming,
+            vec![IncomingEdge {
+                source_id: caller.id(),
+                source_file: "main.rs".to_string(),
+                target_file: "util.rs".to_string(),
+                target_name: "helper".to_string(),
+                target_kind: "function".to_string(),
+                kind: EdgeKind::Calls {
+                    call_type: CallType::Method,
+                    line: 3,
+                },
+            }]
+        );
+        assert!(store.edges_into_file("main.rs").unwrap().is_empty());
+
+        // Deleting a file forgets its hash
+        store.delete_file("util.rs").unwrap();
+        let hashes = store.file_hashes().unwrap();
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(hashes["main.rs"], "aa");
+    }
+
     #[test]
     #[serial]
     fn test_find_callers() {
@@ -546,6 +1317,100 @@ mod tests {
         assert_eq!(callers.len(), 2);
     }
 
+    #[test]
+    #[serial]
+    fn test_find_call_sites() {
+        let store = GraphStore::open_in_memory().unwrap();
+
+        let main_id = store
+            .insert_node(&create_test_symbol("main", SymbolKind::Function))
+            .unwrap();
+        let helper_id = store
+            .insert_node(&create_test_symbol("helper", SymbolKind::Function))
+            .unwrap();
+        store
+            .insert_edge(&Edge::calls(&main_id, &helper_id, CallType::Method, 7))
+            .unwrap();
+
+        let sites = store.find_call_sites(&helper_id).unwrap();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].line, Some(7));
+        assert_eq!(sites[0].call_type.as_deref(), Some("method"));
+        assert!(store.find_call_sites(&main_id).unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_all_nodes_and_call_edges() {
+        let store = GraphStore::open_in_memory().unwrap();
+
+        let main_id = store
+            .insert_node(&create_test_symbol("main", SymbolKind::Function))
+            .unwrap();
+        let helper_id = store
+            .insert_node(&create_test_symbol("helper", SymbolKind::Function))
+            .unwrap();
+        store
+            .insert_edge(&Edge::calls(&main_id, &helper_id, CallType::Direct, 3))
+            .unwrap();
+
+        let mut names: Vec<_> = store
+            .all_nodes()
+            .unwrap()
+            .iter()
+            .filter_map(|n| node_str(n, "name").map(str::to_string))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["helper", "main"]);
+        assert_eq!(
+            store.file_paths().unwrap(),
+            HashSet::from(["test.rs".to_string()])
+        );
+        assert_eq!(
+            store.referenced_ids().unwrap(),
+            HashSet::from([helper_id.clone()])
+        );
+        assert_eq!(
+            store.call_edges().unwrap(),
+            vec![CallEdge {
+                caller: main_id,
+                callee: helper_id,
+                line: Some(3),
+            }]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_find_sensitive() {
+        let store = GraphStore::open_in_memory().unwrap();
+
+        let mut runner = create_test_symbol("run", SymbolKind::Function);
+        runner.sensitivity = vec![Sensitivity::Exec, Sensitivity::Network];
+        store.insert_node(&runner).unwrap();
+        store
+            .insert_node(&create_test_symbol("plain", SymbolKind::Function))
+            .unwrap();
+
+        let names = |nodes: Vec<Value>| -> Vec<String> {
+            nodes
+                .iter()
+                .filter_map(|n| node_str(n, "name").map(str::to_string))
+                .collect()
+        };
+        assert_eq!(names(store.find_sensitive(None).unwrap()), vec!["run"]);
+        assert_eq!(
+            names(store.find_sensitive(Some(Sensitivity::Network)).unwrap()),
+            vec!["run"]
+        );
+        assert!(
+            store
+                .find_sensitive(Some(Sensitivity::RawSql))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     #[serial]
     fn test_find_callees() {
@@ -639,4 +1504,70 @@ mod tests {
 
         assert!(names.contains(&"MyStruct".to_string()));
     }
+
+    #[test]
+    #[serial]
+    fn test_dump_and_load_round_trip() {
+        let store = GraphStore::open_in_memory().unwrap();
+        let caller = create_test_symbol("caller", SymbolKind::Function)
+            .with_signature("fn caller()")
+            .with_qualified_name("crate::caller");
+        let mut callee = create_test_symbol("callee", SymbolKind::Method);
+        callee.start_line = 12;
+        callee.sensitivity = vec![Sensitivity::Exec];
+        let caller_id = store.insert_node(&caller).unwrap();
+        let callee_id = store.insert_node(&callee).unwrap();
+        store
+            .insert_edge(&Edge::calls(&caller_id, &callee_id, CallType::Method, 4))
+            .unwrap();
+        store
+            .set_file_hashes(&[("test.rs".to_string(), "abc".to_string())])
+            .unwrap();
+
+        let mut dump = Vec::new();
+        let stats = store.dump(&mut dump).unwrap();
+        assert_eq!(
+            stats,
+            DumpStats {
+                nodes: 2,
+                edges: 1,
+                file_hashes: 1
+            }
+        );
+        let text = String::from_utf8(dump.clone()).unwrap();
+        assert!(text.starts_with("{\"type\":\"header\",\"format\":1}\n"));
+        assert_eq!(text.lines().count(), 5);
+
+        let loaded = GraphStore::open_in_memory().unwrap();
+        assert_eq!(loaded.load(dump.as_slice()).unwrap(), stats);
+        let node = loaded.get_node(&callee_id).unwrap().unwrap();
+        assert_eq!(node_line(&node), Some(12));
+        assert_eq!(node_str(&node, "sensitivity"), Some("exec"));
+        let sites = loaded.find_call_sites(&callee_id).unwrap();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].line, Some(4));
+        assert_eq!(sites[0].call_type.as_deref(), Some("method"));
+        assert_eq!(loaded.file_hashes().unwrap()["test.rs"], "abc");
+        let labelled = loaded.query("MATCH (n:Method) RETURN n.name").unwrap();
+        assert_eq!(labelled.iter().count(), 1);
+
+        // Dumping the loaded graph gives the same file
+        let mut again = Vec::new();
+        loaded.dump(&mut again).unwrap();
+        assert_eq!(again, dump);
+
+        // Loading twice, or something that isn't a dump, is refused
+        assert!(loaded.load(dump.as_slice()).is_err());
+        let empty = GraphStore::open_in_memory().unwrap();
+        assert!(
+            empty
+                .load(&b"{\"type\":\"file_hash\",\"path\":\"a\",\"hash\":\"b\"}\n"[..])
+                .is_err()
+        );
+        assert!(
+            empty
+                .load(&b"{\"type\":\"header\",\"format\":99}\n"[..])
+                .is_err()
+        );
+    }
 }
@@ -6,6 +6,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::sensitivity::Sensitivity;
+
 /// The kind of symbol extracted from source code.
 ///
 /// These correspond to the node types defined in ADR-002 and cover
@@ -144,6 +146,11 @@ pub struct Symbol {
     /// (highly-connected functions are usually load-bearing).
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub call_degree: Option<usize>,
+
+    /// Security-sensitive API categories the symbol's body touches,
+    /// detected at index time. See [`crate::sensitivity`].
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sensitivity: Vec<Sensitivity>,
 }
 
 impl Symbol {
@@ -168,6 +175,7 @@ impl Symbol {
             cyclomatic: None,
             cognitive: None,
             call_degree: None,
+            sensitivity: Vec::new(),
         }
     }
 
@@ -494,7 +494,10 @@ impl CallGraph {
                 "identifier" | "field_identifier" => {
                     target = child.utf8_text(source).ok().map(|s| s.to_string());
                 }
-                "field_expression" | "member_expression" => {
+                "field_expression"
+                | "member_expression"
+                | "navigation_expression"
+                | "member_access_expression" => {
                     // Method call: extract the method name
                     if let Some(method) = self.get_last_identifier(child, source) {
                         target = Some(method);
@@ -1195,6 +1198,17 @@ impl CallGraph {
 
 /// Helper function to extract function name from a node (not a method to avoid recursion warning)
 fn extract_function_name(node: Node, source: &[u8]) -> Option<String> {
+    // Prefer the grammar's `name` field: C# methods declare their return
+    // type first, so the first identifier child can be a type.
+    if let Some(name) = node.child_by_field_name("name") {
+        if matches!(
+            name.kind(),
+            "identifier" | "name" | "field_identifier" | "property_identifier"
+        ) {
+            return name.utf8_text(source).ok().map(|s| s.to_string());
+        }
+    }
+
     // Look for name in children
     let mut cursor = node.walk();
     cursor.goto_first_child();
@@ -1211,6 +1225,12 @@ fn extract_function_name(node: Node, source: &[u8]) -> Option<String> {
             return child.utf8_text(source).ok().map(|s| s.to_string());
         }
 
+        // Out-of-class C++ definitions (`void Foo::bar() {}`): keep the
+        // qualified text so the method is a node at all.
+        if kind == "qualified_identifier" {
+            return child.utf8_text(source).ok().map(|s| s.to_string());
+        }
+
         // For declarators (C/C++)
         if kind.contains("declarator") {
             if let Some(name) = extract_function_name(child, source) {
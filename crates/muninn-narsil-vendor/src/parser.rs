@@ -187,6 +187,31 @@ impl LanguageParser {
                     (enum_declaration name: (identifier) @enum.name) @enum.def
                 "#,
             },
+            // Kotlin (not in narsil upstream)
+            LanguageConfig {
+                name: "kotlin".to_string(),
+                language: tree_sitter_kotlin_ng::LANGUAGE.into(),
+                extensions: vec!["kt", "kts"],
+                symbol_query: r#"
+                    (function_declaration name: (identifier) @function.name) @function.def
+                    (class_declaration name: (identifier) @class.name) @class.def
+                    (object_declaration name: (identifier) @class.name) @class.def
+                "#,
+            },
+            // C# (not in narsil upstream)
+            LanguageConfig {
+                name: "csharp".to_string(),
+                language: tree_sitter_c_sharp::LANGUAGE.into(),
+                extensions: vec!["cs"],
+                symbol_query: r#"
+                    (method_declaration name: (identifier) @method.name) @method.def
+                    (class_declaration name: (identifier) @class.name) @class.def
+                    (struct_declaration name: (identifier) @struct.name) @struct.def
+                    (interface_declaration name: (identifier) @interface.name) @interface.def
+                    (enum_declaration name: (identifier) @enum.name) @enum.def
+                    (namespace_declaration name: (_) @namespace.name) @namespace.def
+                "#,
+            },
         ];
 
         // Wrap configs in lazy wrappers (queries compiled on first use, not during init)
@@ -242,6 +267,23 @@ impl LanguageParser {
             .ok_or_else(|| anyhow!("Failed to parse file"))
     }
 
+    /// Parse with the grammar named `language` (e.g. `"python"`), for files
+    /// whose language is not given by their extension.
+    pub fn parse_to_tree_as(&self, language: &str, content: &str) -> Result<Tree> {
+        let lazy_config = self
+            .configs
+            .iter()
+            .find(|c| c.config.name == language)
+            .ok_or_else(|| anyhow!("Unsupported language: {}", language))?;
+
+        let mut parser = Parser::new();
+        parser.set_language(&lazy_config.config.language)?;
+
+        parser
+            .parse(content, None)
+            .ok_or_else(|| anyhow!("Failed to parse file"))
+    }
+
     /// Extract symbols using tree-sitter queries
     fn extract_symbols(
         &self,
@@ -437,6 +479,74 @@ enum Color {
         );
     }
 
+    #[test]
+    fn test_parse_kotlin() {
+        let parser = LanguageParser::new().unwrap();
+        let content = r#"
+package com.example.demo
+
+class Greeter(private val name: String) {
+    suspend fun hello(): String {
+        return "Hi " + name
+    }
+}
+
+object Registry {
+    fun all(): List<Greeter> = emptyList()
+}
+
+fun String.shout(): String = uppercase()
+"#;
+        let parsed = parser
+            .parse_file(Path::new("src/main/kotlin/Greeter.kt"), content)
+            .expect("Kotlin should parse");
+        assert_eq!(parsed.language, "kotlin");
+
+        let names: Vec<_> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        for name in ["Greeter", "hello", "Registry", "all", "shout"] {
+            assert!(names.contains(&name), "missing {name}; got {names:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_csharp() {
+        let parser = LanguageParser::new().unwrap();
+        let content = r#"
+namespace Example.Demo
+{
+    public class Greeter : IGreeter
+    {
+        public Widget Hello(string name) => new Widget(name);
+    }
+
+    public interface IGreeter
+    {
+        Widget Hello(string name);
+    }
+
+    struct Point { }
+
+    enum Color { Red, Green }
+}
+"#;
+        let parsed = parser
+            .parse_file(Path::new("src/Demo/Greeter.cs"), content)
+            .expect("C# should parse");
+        assert_eq!(parsed.language, "csharp");
+
+        let names: Vec<_> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        for name in [
+            "Example.Demo",
+            "Greeter",
+            "Hello",
+            "IGreeter",
+            "Point",
+            "Color",
+        ] {
+            assert!(names.contains(&name), "missing {name}; got {names:?}");
+        }
+    }
+
     #[test]
     fn test_parse_cpp() {
         let parser = LanguageParser::new().unwrap();
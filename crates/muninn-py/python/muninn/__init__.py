@@ -0,0 +1,9 @@
+"""Script muninn's recursive exploration engine from Python.
+
+Requests and responses are Anthropic Messages API dicts, so transcripts
+can be compared directly against what the proxy serves.
+"""
+
+from muninn._muninn import Backend, MuninnError, RecursiveEngine, Tools
+
+__all__ = ["Backend", "MuninnError", "RecursiveEngine", "Tools"]
@@ -0,0 +1,386 @@
+//! Python bindings for the muninn RLM engine.
+//!
+//! Exposes the same [`RecursiveEngine`], tools and backends the proxy uses
+//! so explorations, evals and notebooks can be scripted from Python.
+//! Requests and responses cross the boundary as Anthropic Messages dicts.
+//!
+//! ```python
+//! import muninn
+//!
+//! engine = muninn.RecursiveEngine(
+//!     muninn.Backend.anthropic(),
+//!     muninn.Tools(".", graph=".muninn/graph.db"),
+//!     max_depth=3,
+//! )
+//! print(engine.ask("Where is the retry policy applied?"))
+//! ```
+
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use muninn_rlm::engine::{EngineConfig, EngineDeps};
+use muninn_rlm::{
+    AnthropicBackend, AnthropicConfig, BudgetConfig, CompletionRequest, CompletionResponse,
+    ContentBlock, GroqBackend, GroqConfig, LLMBackend, Message, MockBackend, OllamaBackend,
+    OllamaConfig, RecursiveEngine as Engine, RlmError, StopReason, ToolEnvironment, ToolRegistry,
+    ToolUseBlock, Usage,
+};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyString};
+
+create_exception!(
+    _muninn,
+    MuninnError,
+    PyException,
+    "Error raised by the muninn engine."
+);
+
+/// Runtime shared by every blocking call into async code.
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("muninn-py")
+            .build()
+            .expect("failed to start tokio runtime")
+    })
+}
+
+fn to_py_err(e: RlmError) -> PyErr {
+    match e {
+        RlmError::InvalidRequest(msg) | RlmError::Serialization(msg) => PyValueError::new_err(msg),
+        e => MuninnError::new_err(e.to_string()),
+    }
+}
+
+/// Convert a Python value to JSON with the `json` module.
+fn to_json(value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let text: String = value
+        .py()
+        .import("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+    serde_json::from_str(&text).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Convert JSON to Python values with the `json` module.
+fn from_json(py: Python<'_>, value: &serde_json::Value) -> PyResult<Py<PyAny>> {
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (value.to_string(),))?
+        .unbind())
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &Bound<'_, PyAny>, what: &str) -> PyResult<T> {
+    serde_json::from_value(to_json(value)?)
+        .map_err(|e| PyValueError::new_err(format!("invalid {}: {}", what, e)))
+}
+
+fn response_to_py(py: Python<'_>, response: &CompletionResponse) -> PyResult<Py<PyAny>> {
+    let value = serde_json::to_value(response).map_err(|e| MuninnError::new_err(e.to_string()))?;
+    from_json(py, &value)
+}
+
+// ============================================================================
+// Backend
+// ============================================================================
+
+/// An LLM backend.
+#[pyclass(frozen, module = "muninn")]
+struct Backend {
+    inner: Arc<dyn LLMBackend>,
+}
+
+#[pymethods]
+impl Backend {
+    /// Anthropic Messages API. The key defaults to `ANTHROPIC_API_KEY`.
+    #[staticmethod]
+    #[pyo3(signature = (api_key=None, base_url=None))]
+    fn anthropic(api_key: Option<String>, base_url: Option<String>) -> PyResult<Self> {
+        let mut config = match api_key {
+            Some(key) => AnthropicConfig::new(key),
+            None => AnthropicConfig::from_env().map_err(to_py_err)?,
+        };
+        if let Some(url) = base_url {
+            config = config.with_base_url(url);
+        }
+        let backend = AnthropicBackend::new(config).map_err(to_py_err)?;
+        Ok(Self {
+            inner: Arc::new(backend),
+        })
+    }
+
+    /// Groq's OpenAI-compatible API. The key defaults to `GROQ_API_KEY`.
+    #[staticmethod]
+    #[pyo3(signature = (api_key=None, model=None, base_url=None))]
+    fn groq(
+        api_key: Option<String>,
+        model: Option<String>,
+        base_url: Option<String>,
+    ) -> PyResult<Self> {
+        let mut config = match api_key {
+            Some(key) => GroqConfig::new(key),
+            None => GroqConfig::from_env().map_err(to_py_err)?,
+        };
+        if let Some(model) = model {
+            config = config.with_model(model);
+        }
+        if let Some(url) = base_url {
+            config = config.with_base_url(url);
+        }
+        let backend = GroqBackend::new(config).map_err(to_py_err)?;
+        Ok(Self {
+            inner: Arc::new(backend),
+        })
+    }
+
+    /// Ollama, local or Ollama Cloud (with `api_key`).
+    #[staticmethod]
+    #[pyo3(signature = (model=None, base_url=None, api_key=None))]
+    fn ollama(
+        model: Option<String>,
+        base_url: Option<String>,
+        api_key: Option<String>,
+    ) -> PyResult<Self> {
+        let mut config = OllamaConfig::new();
+        if let Some(model) = model {
+            config = config.with_model(model);
+        }
+        if let Some(url) = base_url {
+            config = config.with_base_url(url);
+        }
+        if let Some(key) = api_key {
+            config = config.with_api_key(key);
+        }
+        let backend = OllamaBackend::new(config).map_err(to_py_err)?;
+        Ok(Self {
+            inner: Arc::new(backend),
+        })
+    }
+
+    /// Scripted backend returning `responses` in order: strings become
+    /// text replies, dicts are full Messages responses.
+    #[staticmethod]
+    fn mock(responses: &Bound<'_, PyList>) -> PyResult<Self> {
+        let responses = responses
+            .iter()
+            .map(|item| match item.cast::<PyString>() {
+                Ok(text) => Ok(text_response(text.to_str()?)),
+                Err(_) => parse(&item, "mock response"),
+            })
+            .collect::<PyResult<Vec<CompletionResponse>>>()?;
+        Ok(Self {
+            inner: Arc::new(MockBackend::new(responses)),
+        })
+    }
+
+    /// The backend's name.
+    #[getter]
+    fn name(&self) -> String {
+        self.inner.name().to_string()
+    }
+
+    /// Send one Messages request straight to the backend (no exploration).
+    fn complete(&self, py: Python<'_>, request: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+        let request: CompletionRequest = parse(request, "request")?;
+        let backend = self.inner.clone();
+        let response = py
+            .detach(|| runtime().block_on(backend.complete(request)))
+            .map_err(to_py_err)?;
+        response_to_py(py, &response)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Backend({:?})", self.inner.name())
+    }
+}
+
+fn text_response(text: &str) -> CompletionResponse {
+    CompletionResponse::new(
+        "mock_msg",
+        "mock-model",
+        vec![ContentBlock::Text {
+            text: text.to_string(),
+            cache_control: None,
+        }],
+        StopReason::EndTurn,
+        Usage::new(0, 0),
+    )
+}
+
+// ============================================================================
+// Tools
+// ============================================================================
+
+/// The tool environment explorations run with.
+#[pyclass(frozen, module = "muninn")]
+struct Tools {
+    inner: Arc<ToolRegistry>,
+}
+
+#[pymethods]
+impl Tools {
+    /// Filesystem tools rooted at `root` (default: the current directory), plus code graph tools when
+    /// `graph` names an indexed graph database.
+    #[new]
+    #[pyo3(signature = (root=None, graph=None))]
+    fn new(root: Option<PathBuf>, graph: Option<PathBuf>) -> PyResult<Self> {
+        let root = root.unwrap_or_else(|| PathBuf::from("."));
+        let mut registry = ToolRegistry::new();
+        for tool in muninn_rlm::create_fs_tools(&root) {
+            registry.register_arc(Arc::from(tool));
+        }
+        if let Some(path) = graph {
+            let store = muninn_graph::GraphStore::open(&path).map_err(|e| {
+                MuninnError::new_err(format!("cannot open graph {}: {}", path.display(), e))
+            })?;
+            for tool in muninn_rlm::create_graph_tools(muninn_rlm::wrap_store(store)) {
+                registry.register_arc(Arc::from(tool));
+            }
+        }
+        Ok(Self {
+            inner: Arc::new(registry),
+        })
+    }
+
+    /// Names of the available tools.
+    fn names(&self) -> Vec<String> {
+        self.inner
+            .available_tools()
+            .into_iter()
+            .map(|t| t.name)
+            .collect()
+    }
+
+    /// Tool definitions as sent to the model.
+    fn definitions(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let value = serde_json::to_value(self.inner.available_tools())
+            .map_err(|e| MuninnError::new_err(e.to_string()))?;
+        from_json(py, &value)
+    }
+
+    /// Run one tool and return its `tool_result` block.
+    #[pyo3(signature = (name, input=None))]
+    fn call(
+        &self,
+        py: Python<'_>,
+        name: String,
+        input: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Py<PyAny>> {
+        let input = match input {
+            Some(input) => to_json(input)?,
+            None => serde_json::json!({}),
+        };
+        let tool_use = ToolUseBlock {
+            id: "py_call".to_string(),
+            name,
+            input,
+        };
+        let tools = self.inner.clone();
+        let result = py
+            .detach(|| runtime().block_on(tools.execute_tool(&tool_use)))
+            .map_err(to_py_err)?;
+        let value =
+            serde_json::to_value(result).map_err(|e| MuninnError::new_err(e.to_string()))?;
+        from_json(py, &value)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Tools({:?})", self.names())
+    }
+}
+
+// ============================================================================
+// RecursiveEngine
+// ============================================================================
+
+/// The recursive exploration engine.
+#[pyclass(frozen, module = "muninn")]
+struct RecursiveEngine {
+    inner: Arc<Engine>,
+}
+
+#[pymethods]
+impl RecursiveEngine {
+    /// Budget arguments default to the engine's own defaults.
+    #[new]
+    #[pyo3(signature = (
+        backend,
+        tools=None,
+        *,
+        work_dir=None,
+        max_depth=None,
+        max_tool_calls=None,
+        max_tokens=None,
+        max_duration_secs=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        backend: &Backend,
+        tools: Option<&Tools>,
+        work_dir: Option<PathBuf>,
+        max_depth: Option<u32>,
+        max_tool_calls: Option<u32>,
+        max_tokens: Option<u64>,
+        max_duration_secs: Option<u64>,
+    ) -> Self {
+        let tools: Arc<dyn ToolEnvironment> = match tools {
+            Some(tools) => tools.inner.clone(),
+            None => Arc::new(muninn_rlm::EmptyToolEnvironment),
+        };
+        let defaults = BudgetConfig::default();
+        let budget = BudgetConfig {
+            max_depth: max_depth.or(defaults.max_depth),
+            max_tool_calls: max_tool_calls.or(defaults.max_tool_calls),
+            max_tokens: max_tokens.or(defaults.max_tokens),
+            max_duration_secs: max_duration_secs.or(defaults.max_duration_secs),
+        };
+        let mut config = EngineConfig::default().with_budget(budget);
+        if let Some(dir) = work_dir {
+            config = config.with_work_dir(dir);
+        }
+        let deps = EngineDeps::new(backend.inner.clone(), tools);
+        Self {
+            inner: Arc::new(Engine::new(deps, config)),
+        }
+    }
+
+    /// Run a Messages request through recursive exploration.
+    fn complete(&self, py: Python<'_>, request: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+        let request: CompletionRequest = parse(request, "request")?;
+        let engine = self.inner.clone();
+        let response = py
+            .detach(|| runtime().block_on(engine.complete(request)))
+            .map_err(to_py_err)?;
+        response_to_py(py, &response)
+    }
+
+    /// Explore to answer `question` and return the reply text.
+    #[pyo3(signature = (question, *, model="muninn", max_tokens=4096))]
+    fn ask(
+        &self,
+        py: Python<'_>,
+        question: String,
+        model: &str,
+        max_tokens: u32,
+    ) -> PyResult<String> {
+        let request = CompletionRequest::new(model, vec![Message::user(question)], max_tokens);
+        let engine = self.inner.clone();
+        let response = py
+            .detach(|| runtime().block_on(engine.complete(request)))
+            .map_err(to_py_err)?;
+        Ok(response.text())
+    }
+}
+
+#[pymodule]
+fn _muninn(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Backend>()?;
+    m.add_class::<Tools>()?;
+    m.add_class::<RecursiveEngine>()?;
+    m.add("MuninnError", m.py().get_type::<MuninnError>())?;
+    Ok(())
+}
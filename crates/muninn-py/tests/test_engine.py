@@ -0,0 +1,39 @@
+"""Smoke tests for the Python bindings. Run with `maturin develop && pytest`."""
+
+import pytest
+
+import muninn
+
+
+def test_engine_answers_from_mock_backend():
+    engine = muninn.RecursiveEngine(muninn.Backend.mock(["forty-two"]), max_depth=1)
+    assert engine.ask("What is the answer?") == "forty-two"
+
+
+def test_complete_returns_messages_response():
+    backend = muninn.Backend.mock(["hello"])
+    response = backend.complete(
+        {"model": "m", "max_tokens": 10, "messages": [{"role": "user", "content": "hi"}]}
+    )
+    assert response["content"][0]["text"] == "hello"
+    assert response["stop_reason"] == "end_turn"
+
+
+def test_tools_read_files(tmp_path):
+    (tmp_path / "notes.txt").write_text("remember the milk")
+    tools = muninn.Tools(tmp_path)
+    assert "read_file" in tools.names()
+    result = tools.call("read_file", {"path": "notes.txt"})
+    assert not result["is_error"]
+    assert "remember the milk" in str(result["content"])
+
+
+def test_invalid_request_raises_value_error():
+    with pytest.raises(ValueError):
+        muninn.Backend.mock([]).complete({"messages": "not a list"})
+
+
+def test_exhausted_backend_raises_muninn_error():
+    engine = muninn.RecursiveEngine(muninn.Backend.mock([]))
+    with pytest.raises(muninn.MuninnError):
+        engine.ask("anything")
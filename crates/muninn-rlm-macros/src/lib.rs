@@ -0,0 +1,217 @@
+//! Derive macros for muninn-rlm tools.
+//!
+//! `#[derive(ToolParams)]` turns a plain struct into a tool's parameter
+//! type: the JSON Schema advertised to the LLM is generated from the fields,
+//! and inputs deserialize straight into the struct via serde.
+//!
+//! ```rust,ignore
+//! use muninn_rlm::tool_params::ToolParams;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, ToolParams)]
+//! struct ReadFileParams {
+//!     /// Path to the file
+//!     path: String,
+//!     /// First line to read (1-indexed)
+//!     start_line: Option<usize>,
+//!     /// Output format
+//!     #[tool(values("plain", "numbered"))]
+//!     #[serde(default)]
+//!     format: String,
+//! }
+//! ```
+//!
+//! Field attributes:
+//! - doc comments (or `#[tool(description = "...")]`) become descriptions
+//! - `#[tool(values("a", "b"))]` restricts a field to an `enum` of values
+//! - `#[serde(rename = "...")]` and `#[serde(default)]` are honoured, so the
+//!   schema always matches what deserialization accepts
+//! - `#[serde(skip)]` / `#[serde(skip_deserializing)]` fields are omitted
+//!
+//! `Option<T>` fields and fields with a serde default are optional; the rest
+//! are listed under `required`.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, Fields, Lit, LitStr, Meta, Token, parse_macro_input};
+
+/// Derive `muninn_rlm::tool_params::ToolParams` for a struct with named fields.
+#[proc_macro_derive(ToolParams, attributes(tool))]
+pub fn derive_tool_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// What the macro needs to know about one field.
+struct FieldSpec {
+    name: String,
+    ty: syn::Type,
+    description: Option<String>,
+    values: Vec<LitStr>,
+    has_default: bool,
+}
+
+/// Serde attributes that affect the schema.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "ToolParams requires a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "ToolParams can only be derived for structs",
+            ));
+        }
+    };
+
+    let container = serde_attrs(&input.attrs)?;
+
+    let mut specs = Vec::new();
+    for field in fields {
+        let serde = serde_attrs(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let (description, values) = tool_attrs(&field.attrs)?;
+        specs.push(FieldSpec {
+            name: serde.rename.unwrap_or_else(|| unraw(ident)),
+            ty: field.ty.clone(),
+            description: description.or_else(|| doc_comment(&field.attrs)),
+            values,
+            has_default: serde.default || container.default,
+        });
+    }
+
+    let params = specs.iter().map(|spec| {
+        let FieldSpec {
+            name,
+            ty,
+            description,
+            values,
+            has_default,
+        } = spec;
+        let description = match description {
+            Some(d) => quote!(::std::option::Option::Some(#d)),
+            None => quote!(::std::option::Option::None),
+        };
+        let required = !has_default;
+        quote! {
+            ::muninn_rlm::tool_params::ParamSpec {
+                name: #name,
+                description: #description,
+                values: &[#(#values),*],
+                schema: <#ty as ::muninn_rlm::tool_params::ToolParamType>::schema(),
+                required: #required
+                    && <#ty as ::muninn_rlm::tool_params::ToolParamType>::REQUIRED,
+            }
+        }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::muninn_rlm::tool_params::ToolParams for #ident #ty_generics
+            #where_clause
+        {
+            fn parameters_schema() -> ::muninn_rlm::tool_params::Schema {
+                ::muninn_rlm::tool_params::object_schema(::std::vec![#(#params),*])
+            }
+        }
+    })
+}
+
+/// Field name as serde sees it (without an `r#` prefix).
+fn unraw(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    name.strip_prefix("r#").map(str::to_string).unwrap_or(name)
+}
+
+/// Join `///` lines into a single-line description.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(s) => Some(s.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// Parse `#[tool(description = "...", values("a", "b"))]`.
+fn tool_attrs(attrs: &[syn::Attribute]) -> syn::Result<(Option<String>, Vec<LitStr>)> {
+    let mut description = None;
+    let mut values = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("tool")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("description") {
+                let lit: LitStr = meta.value()?.parse()?;
+                description = Some(lit.value());
+                Ok(())
+            } else if meta.path.is_ident("values") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let list = content.parse_terminated(|input| input.parse::<LitStr>(), Token![,])?;
+                values.extend(list);
+                Ok(())
+            } else {
+                Err(meta.error("expected `description = \"...\"` or `values(...)`"))
+            }
+        })?;
+    }
+    Ok((description, values))
+}
+
+/// Pick out the serde attributes that change the schema, ignoring the rest.
+fn serde_attrs(attrs: &[syn::Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut out = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                let lit: LitStr = meta.value()?.parse()?;
+                out.rename = Some(lit.value());
+            } else if meta.path.is_ident("default") {
+                out.default = true;
+                if meta.input.peek(Token![=]) {
+                    let _: Expr = meta.value()?.parse()?;
+                }
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                out.skip = true;
+            } else if meta.input.peek(Token![=]) {
+                let _: Expr = meta.value()?.parse()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let _: proc_macro2::TokenStream = content.parse()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
@@ -0,0 +1,254 @@
+//! Formatting RLM answers as context for the agent.
+//!
+//! The RLM's answer reaches the agent as the assistant's reply, and a
+//! plain-text reply tends to get restated or skipped. [`AnswerFormatter`]
+//! is a [`ResponseTransform`] that splits RLM answers into a summary, the
+//! files they cite and their code excerpts, and returns them as one
+//! delimited context section in the agent's preferred [`AnswerFormat`].
+//! Passthrough responses are never touched.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::engine::cited_paths;
+use crate::response_transform::{ResponseSource, ResponseTransform, TransformContext};
+
+/// How RLM answers are presented to the agent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerFormat {
+    /// The answer as the RLM wrote it.
+    #[default]
+    Plain,
+    /// Markdown headings: summary, files, excerpts.
+    Markdown,
+    /// A `<muninn_context>` element with `<summary>`, `<files>` and
+    /// `<excerpts>` children, the shape Claude models read most reliably.
+    Xml,
+}
+
+/// Line telling the agent what the section is and how to use it.
+const PREAMBLE: &str = "Codebase context gathered by muninn exploring the repository. \
+Build on it rather than re-reading the files it covers.";
+
+static FENCE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?ms)^[ \t]*```[^\n]*\n.*?^[ \t]*```[ \t]*$").unwrap());
+
+static BLANK_LINES_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n{3,}").unwrap());
+
+/// An RLM answer split into its parts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnswerSections {
+    /// The prose, with code blocks taken out.
+    pub summary: String,
+    /// Files the prose cites, in order of first mention.
+    pub files: Vec<String>,
+    /// Fenced code blocks, verbatim.
+    pub excerpts: Vec<String>,
+}
+
+impl AnswerSections {
+    /// Split `answer` into summary, cited files and code excerpts.
+    pub fn parse(answer: &str) -> Self {
+        let excerpts = FENCE_RE
+            .find_iter(answer)
+            .map(|m| m.as_str().trim().to_string())
+            .collect();
+        let prose = FENCE_RE.replace_all(answer, "");
+        let summary = BLANK_LINES_RE
+            .replace_all(prose.trim(), "\n\n")
+            .into_owned();
+        Self {
+            files: cited_paths(&summary),
+            summary,
+            excerpts,
+        }
+    }
+
+    /// Render in `format`. [`AnswerFormat::Plain`] gives back the summary
+    /// and excerpts unlabelled.
+    pub fn render(&self, format: AnswerFormat) -> String {
+        match format {
+            AnswerFormat::Plain => {
+                let mut parts = vec![self.summary.clone()];
+                parts.extend(self.excerpts.iter().cloned());
+                parts.retain(|p| !p.is_empty());
+                parts.join("\n\n")
+            }
+            AnswerFormat::Markdown => self.render_markdown(),
+            AnswerFormat::Xml => self.render_xml(),
+        }
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut out = format!("## Codebase context (muninn)\n\n_{}_\n", PREAMBLE);
+        if !self.summary.is_empty() {
+            out.push_str(&format!("\n### Summary\n\n{}\n", self.summary));
+        }
+        if !self.files.is_empty() {
+            out.push_str("\n### Files\n\n");
+            for file in &self.files {
+                out.push_str(&format!("- `{}`\n", file));
+            }
+        }
+        if !self.excerpts.is_empty() {
+            out.push_str(&format!(
+                "\n### Excerpts\n\n{}\n",
+                self.excerpts.join("\n\n")
+            ));
+        }
+        out
+    }
+
+    fn render_xml(&self) -> String {
+        let mut out = format!("<muninn_context>\n{}\n", PREAMBLE);
+        if !self.summary.is_empty() {
+            out.push_str(&format!("<summary>\n{}\n</summary>\n", self.summary));
+        }
+        if !self.files.is_empty() {
+            out.push_str(&format!("<files>\n{}\n</files>\n", self.files.join("\n")));
+        }
+        if !self.excerpts.is_empty() {
+            out.push_str(&format!(
+                "<excerpts>\n{}\n</excerpts>\n",
+                self.excerpts.join("\n\n")
+            ));
+        }
+        out.push_str("</muninn_context>");
+        out
+    }
+}
+
+/// Rewrites RLM answers into an [`AnswerFormat`] context section.
+///
+/// The text blocks of the response are joined, reformatted and put back as
+/// one text block where the first one was; other blocks stay as they are.
+#[derive(Debug, Clone, Copy)]
+pub struct AnswerFormatter {
+    format: AnswerFormat,
+}
+
+impl AnswerFormatter {
+    pub fn new(format: AnswerFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl ResponseTransform for AnswerFormatter {
+    fn name(&self) -> &str {
+        "answer_format"
+    }
+
+    fn transform(&self, response: &mut Value, ctx: &TransformContext) {
+        if ctx.source != ResponseSource::Rlm || self.format == AnswerFormat::Plain {
+            return;
+        }
+        let Some(content) = response.get_mut("content").and_then(Value::as_array_mut) else {
+            return;
+        };
+        let is_text = |block: &Value| block.get("type").and_then(Value::as_str) == Some("text");
+        let answer: String = content
+            .iter()
+            .filter(|block| is_text(block))
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("");
+        if answer.trim().is_empty() {
+            return;
+        }
+        let mut formatted = Some(AnswerSections::parse(&answer).render(self.format));
+        *content = std::mem::take(content)
+            .into_iter()
+            .filter_map(|mut block| {
+                if is_text(&block) {
+                    block["text"] = Value::String(formatted.take()?);
+                }
+                Some(block)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ANSWER: &str = "Config is loaded in src/config.rs:42 by `load`.\n\n\
+```rust\npub fn load() -> Config {\n    todo!()\n}\n```\n\n\n\nIt is called from main.rs.";
+
+    fn ctx(source: ResponseSource) -> TransformContext {
+        TransformContext {
+            trace_id: "t".to_string(),
+            source,
+            streaming: false,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_splits_answer() {
+        let sections = AnswerSections::parse(ANSWER);
+        assert_eq!(
+            sections.summary,
+            "Config is loaded in src/config.rs:42 by `load`.\n\nIt is called from main.rs."
+        );
+        assert_eq!(sections.files, ["src/config.rs:42", "main.rs"]);
+        assert_eq!(
+            sections.excerpts,
+            ["```rust\npub fn load() -> Config {\n    todo!()\n}\n```"]
+        );
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let sections = AnswerSections::parse(ANSWER);
+        let xml = sections.render(AnswerFormat::Xml);
+        assert!(xml.starts_with("<muninn_context>\n"), "{xml}");
+        assert!(
+            xml.contains("<files>\nsrc/config.rs:42\nmain.rs\n</files>"),
+            "{xml}"
+        );
+        assert!(xml.contains("<excerpts>\n```rust\n"), "{xml}");
+        assert!(xml.ends_with("</muninn_context>"));
+
+        let markdown = sections.render(AnswerFormat::Markdown);
+        assert!(markdown.contains("### Files\n\n- `src/config.rs:42`\n- `main.rs`\n"));
+
+        let summary_only = AnswerSections::parse("Nothing cites a file here.");
+        let xml = summary_only.render(AnswerFormat::Xml);
+        assert!(
+            !xml.contains("<files>") && !xml.contains("<excerpts>"),
+            "{xml}"
+        );
+    }
+
+    #[test]
+    fn test_formatter_rewrites_rlm_text_only() {
+        let formatter = AnswerFormatter::new(AnswerFormat::Xml);
+        let response = json!({
+            "type": "message",
+            "content": [
+                {"type": "thinking", "thinking": "hmm", "signature": "s"},
+                {"type": "text", "text": "See "},
+                {"type": "text", "text": "lib.rs"}
+            ]
+        });
+
+        let mut passthrough = response.clone();
+        formatter.transform(&mut passthrough, &ctx(ResponseSource::Passthrough));
+        assert_eq!(passthrough, response);
+
+        let mut rlm = response;
+        formatter.transform(&mut rlm, &ctx(ResponseSource::Rlm));
+        let content = rlm["content"].as_array().unwrap();
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[0]["type"], "thinking");
+        let text = content[1]["text"].as_str().unwrap();
+        assert!(text.contains("<summary>\nSee lib.rs\n</summary>"), "{text}");
+        assert!(text.contains("<files>\nlib.rs\n</files>"), "{text}");
+    }
+}
@@ -11,7 +11,10 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::backend::{ContentDelta, LLMBackend, ResponseStream, StreamEvent, with_retry};
+use crate::backend::{
+    ContentDelta, LLMBackend, PayloadCapture, ResponseStream, StreamEvent, capture_call,
+    with_retry, with_trace_context,
+};
 use crate::error::{Result, RlmError};
 use crate::types::{CompletionRequest, CompletionResponse, ContentBlock, Role, StopReason, Usage};
 
@@ -44,6 +47,9 @@ pub struct AnthropicConfig {
 
     /// Initial backoff duration for retries.
     pub retry_backoff: Duration,
+
+    /// How much of each request/response to record in traces.
+    pub capture: PayloadCapture,
 }
 
 impl AnthropicConfig {
@@ -56,6 +62,7 @@ impl AnthropicConfig {
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             max_retries: 3,
             retry_backoff: Duration::from_millis(500),
+            capture: PayloadCapture::None,
         }
     }
 
@@ -84,6 +91,12 @@ impl AnthropicConfig {
         self.max_retries = retries;
         self
     }
+
+    /// Set how much of each request/response to record in traces.
+    pub fn with_capture(mut self, capture: PayloadCapture) -> Self {
+        self.capture = capture;
+        self
+    }
 }
 
 /// Anthropic API backend.
@@ -115,7 +128,7 @@ impl AnthropicBackend {
 
     /// Add authentication and API headers to a request.
     fn add_headers(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        builder
+        with_trace_context(builder)
             .header("x-api-key", &self.config.api_key)
             .header("anthropic-version", &self.config.api_version)
             .header(header::CONTENT_TYPE, "application/json")
@@ -129,7 +142,7 @@ impl LLMBackend for AnthropicBackend {
         let mut request = request;
         request.stream = false;
 
-        with_retry(
+        let call = with_retry(
             self.config.max_retries,
             self.config.retry_backoff,
             "anthropic",
@@ -142,6 +155,13 @@ impl LLMBackend for AnthropicBackend {
 
                 Self::handle_response(response).await
             },
+        );
+        capture_call(
+            self.config.capture,
+            "anthropic",
+            &request.model,
+            &request,
+            call,
         )
         .await
     }
@@ -414,10 +434,18 @@ fn parse_stream_event(event_type: &str, data: &str) -> Option<StreamEvent> {
         }
         "content_block_start" => {
             if let Ok(parsed) = serde_json::from_str::<ContentBlockStartEvent>(data) {
-                Some(StreamEvent::ContentBlockStart {
-                    index: parsed.index,
-                    content_type: parsed.content_block.block_type,
-                })
+                let block = parsed.content_block;
+                match (block.block_type.as_str(), block.id, block.name) {
+                    ("tool_use", Some(id), Some(name)) => Some(StreamEvent::ToolUseStart {
+                        index: parsed.index,
+                        id,
+                        name,
+                    }),
+                    _ => Some(StreamEvent::ContentBlockStart {
+                        index: parsed.index,
+                        content_type: block.block_type,
+                    }),
+                }
             } else {
                 None
             }
@@ -504,6 +532,11 @@ struct ContentBlockStartEvent {
 struct ContentBlockType {
     #[serde(rename = "type")]
     block_type: String,
+    /// Set on `tool_use` blocks.
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize)]
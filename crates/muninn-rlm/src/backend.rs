@@ -5,15 +5,165 @@
 
 use async_trait::async_trait;
 use futures::Stream;
+use serde::Serialize;
+use std::fmt;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::engine::TIMING_BACKEND_LATENCY;
 use crate::error::{Result, RlmError};
 use crate::types::{
     CompletionRequest, CompletionResponse, ContentBlock, StopReason, ToolDefinition, Usage,
 };
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Trace Context Propagation
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Add the current W3C `traceparent` (if any) to an outgoing request, so
+/// upstream providers see the same distributed trace as muninn.
+pub fn with_trace_context(builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    match muninn_tracing::current_traceparent() {
+        Some(parent) => builder.header(muninn_tracing::TRACEPARENT_HEADER, parent.to_string()),
+        None => builder,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Payload Capture
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// How much of each backend request/response is recorded in traces.
+///
+/// Anything other than `None` adds a `backend_call` span per non-streaming
+/// completion; the span data names the capture setting that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadCapture {
+    /// Record nothing.
+    #[default]
+    None,
+    /// Model, timing, usage and payload sizes only.
+    Metadata,
+    /// Metadata plus payloads cut to at most this many bytes.
+    Truncated(usize),
+    /// Metadata plus complete payloads.
+    Full,
+}
+
+impl PayloadCapture {
+    /// The payload as recorded under this setting, and whether it was cut.
+    fn apply(&self, payload: String) -> (Option<String>, bool) {
+        match *self {
+            Self::None | Self::Metadata => (None, false),
+            Self::Full => (Some(payload), false),
+            Self::Truncated(max) if payload.len() <= max => (Some(payload), false),
+            Self::Truncated(max) => {
+                let mut end = max;
+                while !payload.is_char_boundary(end) {
+                    end -= 1;
+                }
+                (Some(payload[..end].to_string()), true)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PayloadCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Metadata => f.write_str("metadata"),
+            Self::Truncated(max) => write!(f, "truncated:{}", max),
+            Self::Full => f.write_str("full"),
+        }
+    }
+}
+
+/// Trace data for one backend call.
+#[derive(Debug, Serialize)]
+struct BackendCallTraceData {
+    backend: String,
+    model: String,
+    capture: String,
+    duration_ms: u64,
+    request_bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<String>,
+    /// True when either payload was cut by `Truncated`.
+    truncated: bool,
+}
+
+/// Run a backend call and record it as a `backend_call` span according to
+/// `capture`. `request_body` is the provider wire request.
+pub async fn capture_call<F>(
+    capture: PayloadCapture,
+    backend: &str,
+    model: &str,
+    request_body: &impl Serialize,
+    call: F,
+) -> Result<CompletionResponse>
+where
+    F: std::future::Future<Output = Result<CompletionResponse>>,
+{
+    if capture == PayloadCapture::None || !muninn_tracing::is_tracing_active() {
+        return call.await;
+    }
+
+    let request_json = serde_json::to_string(request_body).unwrap_or_default();
+    let request_bytes = request_json.len();
+    let start = std::time::Instant::now();
+    let result = call.await;
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let (request, request_cut) = capture.apply(request_json);
+    let mut data = BackendCallTraceData {
+        backend: backend.to_string(),
+        model: model.to_string(),
+        capture: capture.to_string(),
+        duration_ms,
+        request_bytes,
+        response_bytes: None,
+        input_tokens: None,
+        output_tokens: None,
+        stop_reason: None,
+        request,
+        response: None,
+        truncated: request_cut,
+    };
+    match &result {
+        Ok(response) => {
+            let response_json = serde_json::to_string(response).unwrap_or_default();
+            data.response_bytes = Some(response_json.len());
+            data.input_tokens = Some(response.usage.input_tokens);
+            data.output_tokens = Some(response.usage.output_tokens);
+            data.stop_reason = response.stop_reason.as_ref().map(|r| format!("{:?}", r));
+            let (response, response_cut) = capture.apply(response_json);
+            data.response = response;
+            data.truncated |= response_cut;
+            let span = muninn_tracing::span_with_data("backend_call", &data);
+            span.timing_segment(TIMING_BACKEND_LATENCY, duration_ms);
+            span.ok();
+        }
+        Err(e) => {
+            let span = muninn_tracing::span_with_data("backend_call", &data);
+            span.timing_segment(TIMING_BACKEND_LATENCY, duration_ms);
+            span.error(e.to_string());
+        }
+    }
+    result
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Shared Retry Logic
 // ─────────────────────────────────────────────────────────────────────────────
@@ -123,6 +273,12 @@ pub enum StreamEvent {
     MessageStart { id: String, model: String },
     /// Content block started.
     ContentBlockStart { index: usize, content_type: String },
+    /// Tool use block started; its input arrives as `InputJsonDelta`s.
+    ToolUseStart {
+        index: usize,
+        id: String,
+        name: String,
+    },
     /// Text delta within a content block.
     ContentBlockDelta { index: usize, delta: ContentDelta },
     /// Content block finished.
@@ -149,6 +305,124 @@ pub enum ContentDelta {
     InputJsonDelta(String),
 }
 
+/// Drain a stream into the equivalent non-streaming response.
+///
+/// Text deltas are concatenated per block and tool input deltas are parsed
+/// once the block is complete (see [`parse_tool_arguments`]), so callers that want the early bytes of a
+/// streaming request can still work with a plain [`CompletionResponse`].
+pub async fn collect_stream(mut stream: ResponseStream) -> Result<CompletionResponse> {
+    use futures::StreamExt;
+
+    enum Partial {
+        Text(String),
+        Tool {
+            id: String,
+            name: String,
+            json: String,
+        },
+    }
+
+    let mut id = String::new();
+    let mut model = String::new();
+    let mut blocks: Vec<(usize, Partial)> = Vec::new();
+    let mut stop_reason = None;
+    let mut usage = Usage::default();
+
+    while let Some(event) = stream.next().await {
+        match event? {
+            StreamEvent::MessageStart { id: i, model: m } => {
+                id = i;
+                model = m;
+            }
+            StreamEvent::ContentBlockStart { index, .. } => {
+                blocks.push((index, Partial::Text(String::new())));
+            }
+            StreamEvent::ToolUseStart { index, id, name } => {
+                blocks.push((
+                    index,
+                    Partial::Tool {
+                        id,
+                        name,
+                        json: String::new(),
+                    },
+                ));
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                let block = match blocks.iter_mut().rev().find(|(i, _)| *i == index) {
+                    Some((_, block)) => block,
+                    None => {
+                        // Providers that skip the block start still stream text
+                        blocks.push((index, Partial::Text(String::new())));
+                        &mut blocks.last_mut().expect("just pushed").1
+                    }
+                };
+                match (block, delta) {
+                    (Partial::Text(text), ContentDelta::TextDelta(t)) => text.push_str(&t),
+                    (Partial::Tool { json, .. }, ContentDelta::InputJsonDelta(j)) => {
+                        json.push_str(&j)
+                    }
+                    (_, delta) => {
+                        return Err(RlmError::Protocol(format!(
+                            "stream delta {delta:?} does not match content block {index}"
+                        )));
+                    }
+                }
+            }
+            StreamEvent::MessageDelta {
+                stop_reason: reason,
+                usage: u,
+            } => {
+                stop_reason = Some(reason);
+                usage.output_tokens = usage.output_tokens.max(u.output_tokens);
+                usage.input_tokens = usage.input_tokens.max(u.input_tokens);
+            }
+            StreamEvent::MessageStop => break,
+            StreamEvent::Error { message } => return Err(RlmError::Backend(message)),
+            StreamEvent::ContentBlockStop { .. } | StreamEvent::Ping => {}
+        }
+    }
+
+    let mut content = Vec::with_capacity(blocks.len());
+    for (_, block) in blocks {
+        match block {
+            Partial::Text(text) if text.is_empty() => {}
+            Partial::Text(text) => content.push(ContentBlock::Text {
+                text,
+                cache_control: None,
+            }),
+            Partial::Tool { id, name, json } => content.push(ContentBlock::ToolUse {
+                id,
+                name,
+                input: parse_tool_arguments(&json),
+                cache_control: None,
+            }),
+        }
+    }
+
+    Ok(CompletionResponse {
+        id,
+        response_type: "message".to_string(),
+        role: crate::types::Role::Assistant,
+        content,
+        model,
+        stop_reason,
+        usage,
+        muninn: None,
+    })
+}
+
+/// Tool-call arguments from a provider's JSON string.
+///
+/// Arguments that aren't valid JSON are kept as a raw string value rather
+/// than dropped, so the engine can repair them against the tool's schema
+/// (see [`crate::tool_repair`]).
+pub fn parse_tool_arguments(raw: &str) -> serde_json::Value {
+    if raw.trim().is_empty() {
+        return serde_json::Value::Object(Default::default());
+    }
+    serde_json::from_str(raw).unwrap_or_else(|_| serde_json::Value::String(raw.to_string()))
+}
+
 /// A parsed tool call from model output.
 #[derive(Debug, Clone)]
 pub struct ParsedToolCall {
@@ -160,6 +434,24 @@ pub struct ParsedToolCall {
     pub arguments: serde_json::Value,
 }
 
+impl ParsedToolCall {
+    /// Build a call from argument text found in model output, repairing
+    /// almost-JSON (see [`crate::tool_repair::repair_json`]).
+    ///
+    /// Returns `None` when the arguments can't be turned into an object.
+    pub fn parse(id: impl Into<String>, name: impl Into<String>, arguments: &str) -> Option<Self> {
+        let arguments = match crate::tool_repair::repair_json(arguments)? {
+            value @ serde_json::Value::Object(_) => value,
+            _ => return None,
+        };
+        Some(Self {
+            id: id.into(),
+            name: name.into(),
+            arguments,
+        })
+    }
+}
+
 impl From<ParsedToolCall> for ContentBlock {
     fn from(call: ParsedToolCall) -> Self {
         ContentBlock::ToolUse {
@@ -369,26 +661,44 @@ impl LLMBackend for MockBackend {
         // For mock, just convert the sync response to a stream
         let response = self.complete(request).await?;
 
-        let events = vec![
-            Ok(StreamEvent::MessageStart {
-                id: response.id.clone(),
-                model: response.model.clone(),
-            }),
-            Ok(StreamEvent::ContentBlockStart {
-                index: 0,
-                content_type: "text".to_string(),
-            }),
-            Ok(StreamEvent::ContentBlockDelta {
-                index: 0,
-                delta: ContentDelta::TextDelta(response.text()),
-            }),
-            Ok(StreamEvent::ContentBlockStop { index: 0 }),
-            Ok(StreamEvent::MessageDelta {
-                stop_reason: response.stop_reason.unwrap_or(StopReason::EndTurn),
-                usage: response.usage,
-            }),
-            Ok(StreamEvent::MessageStop),
-        ];
+        let mut events = vec![Ok(StreamEvent::MessageStart {
+            id: response.id.clone(),
+            model: response.model.clone(),
+        })];
+        for (index, block) in response.content.iter().enumerate() {
+            match block {
+                ContentBlock::ToolUse {
+                    id, name, input, ..
+                } => {
+                    events.push(Ok(StreamEvent::ToolUseStart {
+                        index,
+                        id: id.clone(),
+                        name: name.clone(),
+                    }));
+                    events.push(Ok(StreamEvent::ContentBlockDelta {
+                        index,
+                        delta: ContentDelta::InputJsonDelta(input.to_string()),
+                    }));
+                }
+                ContentBlock::Text { text, .. } => {
+                    events.push(Ok(StreamEvent::ContentBlockStart {
+                        index,
+                        content_type: "text".to_string(),
+                    }));
+                    events.push(Ok(StreamEvent::ContentBlockDelta {
+                        index,
+                        delta: ContentDelta::TextDelta(text.clone()),
+                    }));
+                }
+                _ => continue,
+            }
+            events.push(Ok(StreamEvent::ContentBlockStop { index }));
+        }
+        events.push(Ok(StreamEvent::MessageDelta {
+            stop_reason: response.stop_reason.unwrap_or(StopReason::EndTurn),
+            usage: response.usage,
+        }));
+        events.push(Ok(StreamEvent::MessageStop));
 
         Ok(Box::pin(futures::stream::iter(events)))
     }
@@ -596,6 +906,62 @@ mod tests {
         assert!(backend.health_check().await.is_ok());
     }
 
+    #[test]
+    fn test_payload_capture_truncates_on_char_boundary() {
+        let payload = "héllo".to_string();
+        assert_eq!(
+            PayloadCapture::Metadata.apply(payload.clone()),
+            (None, false)
+        );
+        assert_eq!(
+            PayloadCapture::Full.apply(payload.clone()),
+            (Some(payload.clone()), false)
+        );
+        // "é" spans bytes 1..3; cutting at 2 backs off to 1
+        assert_eq!(
+            PayloadCapture::Truncated(2).apply(payload.clone()),
+            (Some("h".to_string()), true)
+        );
+        assert_eq!(
+            PayloadCapture::Truncated(64).apply(payload.clone()),
+            (Some(payload), false)
+        );
+        assert_eq!(PayloadCapture::Truncated(512).to_string(), "truncated:512");
+    }
+
+    #[tokio::test]
+    async fn test_capture_call_records_backend_span() {
+        let request = CompletionRequest::new("test-model", vec![Message::user("Hi")], 100);
+        let (_, trace) = muninn_tracing::with_tracing(async {
+            for capture in [
+                PayloadCapture::None,
+                PayloadCapture::Metadata,
+                PayloadCapture::Truncated(10),
+            ] {
+                let backend = MockBackend::with_text("captured reply");
+                let call = backend.complete(request.clone());
+                capture_call(capture, "mock", "test-model", &request, call)
+                    .await
+                    .unwrap();
+            }
+        })
+        .await;
+
+        // `None` records nothing
+        assert_eq!(trace.spans.len(), 2);
+        let metadata = trace.spans[0].data.as_ref().unwrap();
+        assert_eq!(metadata["capture"], "metadata");
+        assert_eq!(metadata["output_tokens"], 20);
+        assert!(metadata.get("request").is_none());
+        assert_eq!(metadata["truncated"], false);
+
+        let truncated = trace.spans[1].data.as_ref().unwrap();
+        assert_eq!(truncated["capture"], "truncated:10");
+        assert_eq!(truncated["request"].as_str().unwrap().len(), 10);
+        assert_eq!(truncated["truncated"], true);
+        assert!(truncated["request_bytes"].as_u64().unwrap() > 10);
+    }
+
     #[tokio::test]
     async fn test_logging_backend() {
         let inner = MockBackend::with_text("Logged!");
@@ -0,0 +1,565 @@
+//! Managed client keys for shared proxies.
+//!
+//! A team running one muninn gateway issues each member a key
+//! (`muninn keys create --name alice`). Keys live in `.muninn/keys.json`
+//! as SHA-256 hashes, so the plaintext is shown once, at creation. Keys
+//! are long and random, so a fast hash is enough.
+//!
+//! With [`ClientKeys`] on the proxy, requests without a valid key are
+//! refused. Each key's quota is enforced: tokens per UTC day and requests
+//! per minute. Tokens a key already spent today are read back from the
+//! sessions' spend ledgers at startup, so a restart doesn't reset the
+//! daily budget; the per-minute window starts empty. Traces and
+//! the spend ledger are tagged with the key's name. The client key never
+//! goes upstream. The proxy's own credentials (OAuth, or the configured
+//! upstream key) are used instead.
+//!
+//! A key may also carry a role. [`ClientKeys::with_roles`] maps roles, or
+//! key names, to the [`ToolCategory`]s their explorations may use, so
+//! that, for example, only trusted users get code execution. Keys with the
+//! [`ADMIN_ROLE`] may also export every session and trace from the proxy.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, RlmError};
+use crate::spend::SpendLedger;
+use crate::tools::ToolCategory;
+
+/// File name of the key store inside `.muninn/`.
+pub const CLIENT_KEYS_FILE: &str = "keys.json";
+
+/// Prefix of every issued key, so they are easy to spot in configs.
+pub const KEY_PREFIX: &str = "mk_";
+
+/// Role of the keys allowed to export sessions and traces, which hold
+/// every key holder's requests.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Window for `requests_per_minute`.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits on one key; unset limits don't apply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyQuota {
+    /// Input plus output tokens per UTC day.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_tokens: Option<u64>,
+    /// Requests per rolling minute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+}
+
+/// One issued key, as stored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientKey {
+    /// Who the key was issued to; used for attribution.
+    pub name: String,
+    /// SHA-256 of the key, hex encoded.
+    pub hash: String,
+    /// The key's first characters, to tell keys apart in listings.
+    pub hint: String,
+    /// When the key was issued.
+    pub created_at: DateTime<Utc>,
+    /// The key's limits.
+    #[serde(default)]
+    pub quota: KeyQuota,
+    /// Role picking the key's tool grant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// Revoked keys are kept for the record but no longer accepted.
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+/// The key store file.
+#[derive(Debug, Clone)]
+pub struct ClientKeyFile {
+    path: PathBuf,
+}
+
+impl ClientKeyFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The store in a `.muninn` directory.
+    pub fn in_dir(muninn_dir: &Path) -> Self {
+        Self::new(muninn_dir.join(CLIENT_KEYS_FILE))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every key, revoked ones included; none if the file doesn't exist.
+    pub fn load(&self) -> Result<Vec<ClientKey>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                RlmError::Config(format!("Invalid key store {}: {}", self.path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, keys: &[ClientKey]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(keys)
+            .map_err(|e| RlmError::Serialization(e.to_string()))?;
+        std::fs::write(&self.path, json + "\n")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    /// Issue a key for `name`, optionally with a `role`. Returns the
+    /// plaintext key, which is not stored, and its record.
+    pub fn create(
+        &self,
+        name: &str,
+        quota: KeyQuota,
+        role: Option<&str>,
+    ) -> Result<(String, ClientKey)> {
+        let mut keys = self.load()?;
+        if keys.iter().any(|k| k.name == name && !k.revoked) {
+            return Err(RlmError::InvalidRequest(format!(
+                "A key named '{}' already exists; revoke it first",
+                name
+            )));
+        }
+        let mut bytes = [0u8; 24];
+        rand::rng().fill_bytes(&mut bytes);
+        let key = format!("{}{}", KEY_PREFIX, URL_SAFE_NO_PAD.encode(bytes));
+        let record = ClientKey {
+            name: name.to_string(),
+            hash: hash_key(&key),
+            hint: format!("{}…", &key[..KEY_PREFIX.len() + 4]),
+            created_at: Utc::now(),
+            quota,
+            role: role.map(String::from),
+            revoked: false,
+        };
+        keys.push(record.clone());
+        self.save(&keys)?;
+        Ok((key, record))
+    }
+
+    /// Revoke the active key named `name`. Returns whether there was one.
+    pub fn revoke(&self, name: &str) -> Result<bool> {
+        let mut keys = self.load()?;
+        let Some(key) = keys.iter_mut().find(|k| k.name == name && !k.revoked) else {
+            return Ok(false);
+        };
+        key.revoked = true;
+        self.save(&keys)?;
+        Ok(true)
+    }
+}
+
+/// SHA-256 of a key, hex encoded.
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The key holder a request was admitted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// The key's name.
+    pub name: String,
+    /// The key's role, if it has one.
+    pub role: Option<String>,
+    /// Tool categories the key is granted; `None` grants all of them.
+    pub tools: Option<Vec<ToolCategory>>,
+}
+
+impl ClientIdentity {
+    /// Whether the key has the [`ADMIN_ROLE`].
+    pub fn is_admin(&self) -> bool {
+        self.role.as_deref() == Some(ADMIN_ROLE)
+    }
+}
+
+/// Why a request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyRejection {
+    /// No key was sent.
+    Missing,
+    /// The key is unknown or revoked.
+    Invalid,
+    /// The key's daily token budget is used up.
+    DailyTokens { limit: u64 },
+    /// The key sent too many requests in the last minute.
+    RateLimited { per_minute: u32 },
+}
+
+impl KeyRejection {
+    /// Whether the key was fine but over its quota.
+    pub fn is_quota(&self) -> bool {
+        matches!(self, Self::DailyTokens { .. } | Self::RateLimited { .. })
+    }
+
+    /// Message for the error response.
+    pub fn message(&self) -> String {
+        match self {
+            Self::Missing => "muninn client key required (x-api-key or Authorization)".to_string(),
+            Self::Invalid => "Invalid or revoked muninn client key".to_string(),
+            Self::DailyTokens { limit } => format!(
+                "Daily token budget of {} used up for this key; it resets at 00:00 UTC",
+                limit
+            ),
+            Self::RateLimited { per_minute } => format!(
+                "Rate limit of {} requests per minute reached for this key",
+                per_minute
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct KeyUsage {
+    day: NaiveDate,
+    tokens: u64,
+    recent: VecDeque<Instant>,
+}
+
+impl KeyUsage {
+    fn new() -> Self {
+        Self {
+            day: Utc::now().date_naive(),
+            tokens: 0,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Start a new day's count once the date changes.
+    fn roll_over(&mut self) {
+        let today = Utc::now().date_naive();
+        if self.day != today {
+            self.day = today;
+            self.tokens = 0;
+        }
+    }
+}
+
+/// Keys a proxy accepts, and what each has used.
+#[derive(Debug)]
+pub struct ClientKeys {
+    /// Active keys by hash.
+    keys: HashMap<String, ClientKey>,
+    /// Usage by key name.
+    usage: Mutex<HashMap<String, KeyUsage>>,
+    /// Credential sent upstream in place of client keys.
+    upstream_key: Option<String>,
+    /// Tool grants by role or key name.
+    roles: HashMap<String, Vec<ToolCategory>>,
+}
+
+impl ClientKeys {
+    /// Accept the active keys among `keys`.
+    pub fn new(keys: Vec<ClientKey>) -> Self {
+        Self {
+            keys: keys
+                .into_iter()
+                .filter(|k| !k.revoked)
+                .map(|k| (k.hash.clone(), k))
+                .collect(),
+            usage: Mutex::new(HashMap::new()),
+            upstream_key: None,
+            roles: HashMap::new(),
+        }
+    }
+
+    /// Accept the active keys in `file`.
+    pub fn load(file: &ClientKeyFile) -> Result<Self> {
+        Ok(Self::new(file.load()?))
+    }
+
+    /// Send `key` upstream in place of client keys. Without one, upstream
+    /// auth relies on OAuth tokens.
+    pub fn with_upstream_key(mut self, key: impl Into<String>) -> Self {
+        self.upstream_key = Some(key.into());
+        self
+    }
+
+    /// Grant tool categories by role, or by key name for keys without a
+    /// matching role. Keys matching neither get only [`ToolCategory::Core`]
+    /// tools. With no roles at all, every key keeps every tool.
+    pub fn with_roles(mut self, roles: HashMap<String, Vec<ToolCategory>>) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    /// Roles given to active keys that [`Self::with_roles`] doesn't map.
+    pub fn unmapped_roles(&self) -> Vec<&str> {
+        let mut roles: Vec<&str> = self
+            .keys
+            .values()
+            .filter_map(|k| k.role.as_deref())
+            .filter(|role| !self.roles.contains_key(*role))
+            .collect();
+        roles.sort_unstable();
+        roles.dedup();
+        roles
+    }
+
+    /// The tool grant for `key`.
+    fn tools_for(&self, key: &ClientKey) -> Option<Vec<ToolCategory>> {
+        if self.roles.is_empty() {
+            return None;
+        }
+        let grant = key
+            .role
+            .as_ref()
+            .and_then(|role| self.roles.get(role))
+            .or_else(|| self.roles.get(&key.name));
+        Some(grant.cloned().unwrap_or_default())
+    }
+
+    pub fn upstream_key(&self) -> Option<&str> {
+        self.upstream_key.as_deref()
+    }
+
+    /// Number of active keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Admit a request presenting `key`, counting it against the key's
+    /// rate limit.
+    pub fn admit(&self, key: Option<&str>) -> std::result::Result<ClientIdentity, KeyRejection> {
+        let key = key.filter(|k| !k.is_empty()).ok_or(KeyRejection::Missing)?;
+        let record = self.keys.get(&hash_key(key)).ok_or(KeyRejection::Invalid)?;
+
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = usage
+            .entry(record.name.clone())
+            .or_insert_with(KeyUsage::new);
+        usage.roll_over();
+        if let Some(limit) = record.quota.daily_tokens
+            && usage.tokens >= limit
+        {
+            return Err(KeyRejection::DailyTokens { limit });
+        }
+        if let Some(per_minute) = record.quota.requests_per_minute {
+            let now = Instant::now();
+            while usage
+                .recent
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW)
+            {
+                usage.recent.pop_front();
+            }
+            if usage.recent.len() >= per_minute as usize {
+                return Err(KeyRejection::RateLimited { per_minute });
+            }
+            usage.recent.push_back(now);
+        }
+        Ok(ClientIdentity {
+            name: record.name.clone(),
+            role: record.role.clone(),
+            tools: self.tools_for(record),
+        })
+    }
+
+    /// Count `tokens` against the daily budget of the key named `name`.
+    pub fn record_usage(&self, name: &str, tokens: u64) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = usage.entry(name.to_string()).or_insert_with(KeyUsage::new);
+        usage.roll_over();
+        usage.tokens += tokens;
+    }
+
+    /// Count the tokens each key spent today, as recorded in the spend
+    /// ledgers of the sessions in `sessions_dir`. Run once at startup so a
+    /// restarted proxy keeps enforcing the day's budgets. Returns the
+    /// tokens counted.
+    pub fn seed_usage(&self, sessions_dir: &Path) -> u64 {
+        let Ok(entries) = std::fs::read_dir(sessions_dir) else {
+            return 0;
+        };
+        let today = Utc::now().date_naive();
+        let mut counted = 0;
+        for session in entries.flatten() {
+            let ledger = SpendLedger::in_dir(&session.path());
+            // A ledger last written before today holds nothing for today
+            let modified = std::fs::metadata(ledger.path()).and_then(|m| m.modified());
+            if modified.is_ok_and(|m| DateTime::<Utc>::from(m).date_naive() < today) {
+                continue;
+            }
+            for entry in ledger.read().unwrap_or_default() {
+                if let Some(client) = &entry.client
+                    && entry.timestamp.date_naive() == today
+                {
+                    self.record_usage(client, entry.usage.total());
+                    counted += entry.usage.total();
+                }
+            }
+        }
+        counted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_revoke() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ClientKeyFile::in_dir(dir.path());
+
+        let (key, record) = file.create("alice", KeyQuota::default(), None).unwrap();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(record.hash, hash_key(&key));
+        let stored = std::fs::read_to_string(file.path()).unwrap();
+        assert!(!stored.contains(&key), "plaintext key stored");
+        assert!(file.create("alice", KeyQuota::default(), None).is_err());
+
+        let keys = ClientKeys::load(&file).unwrap();
+        assert_eq!(keys.admit(Some(&key)).unwrap().name, "alice");
+        assert_eq!(keys.admit(Some("mk_nope")), Err(KeyRejection::Invalid));
+        assert_eq!(keys.admit(None), Err(KeyRejection::Missing));
+
+        assert!(file.revoke("alice").unwrap());
+        assert!(!file.revoke("alice").unwrap());
+        let keys = ClientKeys::load(&file).unwrap();
+        assert_eq!(keys.admit(Some(&key)), Err(KeyRejection::Invalid));
+        // The name can be reissued once revoked
+        assert!(file.create("alice", KeyQuota::default(), None).is_ok());
+    }
+
+    #[test]
+    fn test_quotas() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ClientKeyFile::in_dir(dir.path());
+        let quota = KeyQuota {
+            daily_tokens: Some(100),
+            requests_per_minute: Some(2),
+        };
+        let (key, _) = file.create("bob", quota, None).unwrap();
+        let keys = ClientKeys::load(&file).unwrap();
+
+        assert!(keys.admit(Some(&key)).is_ok());
+        assert!(keys.admit(Some(&key)).is_ok());
+        assert_eq!(
+            keys.admit(Some(&key)),
+            Err(KeyRejection::RateLimited { per_minute: 2 })
+        );
+
+        let keys = ClientKeys::load(&file).unwrap();
+        keys.record_usage("bob", 100);
+        let rejection = keys.admit(Some(&key)).unwrap_err();
+        assert_eq!(rejection, KeyRejection::DailyTokens { limit: 100 });
+        assert!(rejection.is_quota());
+    }
+
+    #[test]
+    fn test_daily_usage_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ClientKeyFile::in_dir(dir.path());
+        let quota = KeyQuota {
+            daily_tokens: Some(100),
+            ..Default::default()
+        };
+        let (alice, _) = file.create("alice", quota, None).unwrap();
+        let (bob, _) = file.create("bob", quota, None).unwrap();
+
+        // Earlier today, before the restart, across two sessions
+        let sessions = dir.path().join("sessions");
+        let spend = |client: &str, tokens: u64| {
+            let usage = crate::spend::TokenUsage {
+                input_tokens: tokens,
+                ..Default::default()
+            };
+            crate::spend::SpendEntry::new("t", "m", "rlm", false, usage)
+                .with_client(Some(client.to_string()))
+        };
+        let morning = SpendLedger::in_dir(&sessions.join("s1"));
+        morning.record(&spend("alice", 60));
+        let mut yesterday = spend("bob", 500);
+        yesterday.timestamp -= chrono::Duration::days(1);
+        morning.record(&yesterday);
+        SpendLedger::in_dir(&sessions.join("s2")).record(&spend("alice", 50));
+
+        let keys = ClientKeys::load(&file).unwrap();
+        assert_eq!(keys.seed_usage(&sessions), 110);
+        assert_eq!(
+            keys.admit(Some(&alice)),
+            Err(KeyRejection::DailyTokens { limit: 100 })
+        );
+        assert!(keys.admit(Some(&bob)).is_ok());
+        assert_eq!(keys.seed_usage(&dir.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_role_tool_grants() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ClientKeyFile::in_dir(dir.path());
+        let (reader, _) = file
+            .create("alice", KeyQuota::default(), Some("reader"))
+            .unwrap();
+        let (intern, _) = file
+            .create("carol", KeyQuota::default(), Some("intern"))
+            .unwrap();
+        let (owner, _) = file.create("bob", KeyQuota::default(), None).unwrap();
+        let (other, _) = file.create("dave", KeyQuota::default(), None).unwrap();
+
+        let roles = HashMap::from([
+            (
+                "reader".to_string(),
+                vec![ToolCategory::Fs, ToolCategory::Graph],
+            ),
+            ("bob".to_string(), vec![ToolCategory::Repl]),
+        ]);
+        let keys = ClientKeys::load(&file).unwrap().with_roles(roles);
+        assert_eq!(
+            keys.admit(Some(&reader)).unwrap().tools,
+            Some(vec![ToolCategory::Fs, ToolCategory::Graph])
+        );
+        // A role with no grant gets only the core tools
+        assert_eq!(keys.admit(Some(&intern)).unwrap().tools, Some(Vec::new()));
+        assert_eq!(keys.unmapped_roles(), vec!["intern"]);
+        // Keys without a role fall back to their name, then to the core tools
+        assert_eq!(
+            keys.admit(Some(&owner)).unwrap().tools,
+            Some(vec![ToolCategory::Repl])
+        );
+        assert_eq!(keys.admit(Some(&other)).unwrap().tools, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_unmapped_keys_get_core_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ClientKeyFile::in_dir(dir.path());
+        let (key, _) = file.create("erin", KeyQuota::default(), None).unwrap();
+
+        // Without roles, tool grants are off
+        let keys = ClientKeys::load(&file).unwrap();
+        assert_eq!(keys.admit(Some(&key)).unwrap().tools, None);
+
+        let roles = HashMap::from([("reader".to_string(), vec![ToolCategory::Fs])]);
+        let keys = ClientKeys::load(&file).unwrap().with_roles(roles);
+        assert_eq!(keys.admit(Some(&key)).unwrap().tools, Some(Vec::new()));
+    }
+}
@@ -0,0 +1,372 @@
+//! Conversation compaction for long agent sessions.
+//!
+//! Agent conversations grow every turn until they crowd out the context
+//! window. With a [`Compactor`] configured, the proxy checks each
+//! `/v1/messages` request. Once its messages pass the token threshold, the
+//! older turns are summarized by the RLM backend and replaced by the
+//! summary, and the most recent turns are kept verbatim. The summary keeps
+//! a reference to every tool result it relies on (`[tool_result <id>]`,
+//! the tool and the files it touched), so the agent can re-run a call
+//! rather than trust a paraphrase.
+//!
+//! The compacted request is handled as usual. The response carries the
+//! edit under `context_management.applied_edits`, the shape Anthropic uses
+//! for server-side context edits, along with the compacted messages. The
+//! agent can adopt those as its history and stop resending the old turns.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::backend::LLMBackend;
+use crate::response_transform::StreamTransform;
+use crate::types::{CompletionRequest, Message};
+
+/// Estimated message tokens past which a request is compacted.
+pub const DEFAULT_COMPACT_THRESHOLD: u64 = 100_000;
+
+/// Messages at the end of the conversation kept verbatim.
+pub const DEFAULT_KEEP_RECENT: usize = 8;
+
+/// `type` of the edit in `context_management.applied_edits`.
+pub const COMPACTION_EDIT: &str = "muninn_compaction";
+
+const COMPACTION_PROMPT: &str = "You compact a coding agent's conversation so it can continue without the \
+transcript. Summarize: what the user asked for, decisions made, what was found and where (file paths \
+and lines), changes made, and work still open. For every tool result the summary relies on, keep a \
+reference like [tool_result <id>] with the tool name and the files it touched, so the agent can re-run \
+the call instead of trusting the summary. Use short paragraphs and bullet lists, with no preamble.";
+
+/// Output cap for the summary.
+const SUMMARY_MAX_TOKENS: u32 = 2048;
+
+/// Characters of each tool input or result shown to the summarizer.
+const MAX_BLOCK_CHARS: usize = 2000;
+
+/// Summarizes the older turns of long conversations.
+#[derive(Clone)]
+pub struct Compactor {
+    backend: Arc<dyn LLMBackend>,
+    model: String,
+    threshold_tokens: u64,
+    keep_recent: usize,
+}
+
+impl Compactor {
+    /// Summarize with `model` on `backend`, at the default threshold.
+    pub fn new(backend: Arc<dyn LLMBackend>, model: impl Into<String>) -> Self {
+        Self {
+            backend,
+            model: model.into(),
+            threshold_tokens: DEFAULT_COMPACT_THRESHOLD,
+            keep_recent: DEFAULT_KEEP_RECENT,
+        }
+    }
+
+    /// Compact once the messages pass `tokens` (estimated).
+    pub fn with_threshold(mut self, tokens: u64) -> Self {
+        self.threshold_tokens = tokens;
+        self
+    }
+
+    /// Keep at least the last `messages` verbatim.
+    pub fn with_keep_recent(mut self, messages: usize) -> Self {
+        self.keep_recent = messages.max(1);
+        self
+    }
+
+    /// Compact the `messages` of a raw Messages request in place if they
+    /// pass the threshold. Returns the applied edit, or `None` when the
+    /// request was left alone (under the threshold, nowhere to split, or
+    /// the summary failed).
+    pub async fn compact(&self, request: &mut Value) -> Option<Compaction> {
+        let messages = request.get("messages")?.as_array()?;
+        let tokens = estimate_tokens(messages);
+        if tokens < self.threshold_tokens {
+            return None;
+        }
+        let Some(split) = split_point(messages, self.keep_recent) else {
+            tracing::debug!(
+                tokens,
+                "Conversation over threshold but has no turn to split at"
+            );
+            return None;
+        };
+
+        let transcript = transcript(&messages[..split]);
+        let mut summary_request = CompletionRequest::new(
+            &self.model,
+            vec![Message::user(transcript)],
+            SUMMARY_MAX_TOKENS,
+        )
+        .with_system(COMPACTION_PROMPT);
+        summary_request.temperature = Some(0.0);
+        let summary = match self.backend.complete(summary_request).await {
+            Ok(response) => response.text(),
+            Err(e) => {
+                tracing::warn!(error = %e, "Conversation compaction failed");
+                return None;
+            }
+        };
+        if summary.trim().is_empty() {
+            return None;
+        }
+
+        let mut kept = messages[split..].to_vec();
+        prepend_text(
+            &mut kept[0],
+            format!(
+                "<conversation-summary>\nThe {} earlier messages of this conversation were \
+                 compacted into this summary.\n\n{}\n</conversation-summary>",
+                split,
+                summary.trim()
+            ),
+        );
+        let compaction = Compaction {
+            compacted_messages: split,
+            cleared_input_tokens: tokens.saturating_sub(estimate_tokens(&kept)),
+            messages: kept.clone(),
+        };
+        request["messages"] = Value::Array(kept);
+        tracing::info!(
+            compacted_messages = compaction.compacted_messages,
+            cleared_input_tokens = compaction.cleared_input_tokens,
+            "Compacted conversation"
+        );
+        Some(compaction)
+    }
+}
+
+impl std::fmt::Debug for Compactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Compactor")
+            .field("backend", &self.backend.name())
+            .field("model", &self.model)
+            .field("threshold_tokens", &self.threshold_tokens)
+            .field("keep_recent", &self.keep_recent)
+            .finish()
+    }
+}
+
+/// A compaction applied to one request.
+#[derive(Debug, Clone, Serialize)]
+pub struct Compaction {
+    /// Messages replaced by the summary.
+    pub compacted_messages: usize,
+    /// Estimated input tokens saved.
+    pub cleared_input_tokens: u64,
+    /// The conversation as sent on: the summary, then the kept messages.
+    pub messages: Vec<Value>,
+}
+
+impl Compaction {
+    /// The response's `context_management` object.
+    pub fn context_management(&self) -> Value {
+        json!({
+            "applied_edits": [{
+                "type": COMPACTION_EDIT,
+                "compacted_messages": self.compacted_messages,
+                "cleared_input_tokens": self.cleared_input_tokens,
+                "messages": self.messages,
+            }]
+        })
+    }
+
+    /// Add `context_management` to a complete Messages response.
+    pub fn annotate(&self, response: &mut Value) {
+        if let Some(response) = response.as_object_mut() {
+            response.insert("context_management".to_string(), self.context_management());
+        }
+    }
+
+    /// Stream stage adding `context_management` to the `message_delta`
+    /// event.
+    pub fn stream(self: &Arc<Self>) -> Box<dyn StreamTransform> {
+        struct Annotate(Arc<Compaction>);
+        impl StreamTransform for Annotate {
+            fn event(&mut self, mut event: Value) -> Vec<Value> {
+                if event["type"] == "message_delta" {
+                    self.0.annotate(&mut event);
+                }
+                vec![event]
+            }
+        }
+        Box::new(Annotate(self.clone()))
+    }
+}
+
+/// Rough token count: four characters of JSON per token.
+fn estimate_tokens(messages: &[Value]) -> u64 {
+    let chars: usize = messages.iter().map(|m| m.to_string().len()).sum();
+    (chars / 4) as u64
+}
+
+/// Index of the first kept message: the latest user turn (not a tool
+/// result) that leaves at least `keep_recent` messages and one to
+/// compact. Splitting there never separates a tool call from its result.
+fn split_point(messages: &[Value], keep_recent: usize) -> Option<usize> {
+    let last = messages.len().checked_sub(keep_recent)?;
+    (1..=last).rev().find(|&i| is_user_turn(&messages[i]))
+}
+
+fn is_user_turn(message: &Value) -> bool {
+    if message["role"] != "user" {
+        return false;
+    }
+    match &message["content"] {
+        Value::String(_) => true,
+        Value::Array(blocks) => !blocks.iter().any(|b| b["type"] == "tool_result"),
+        _ => false,
+    }
+}
+
+/// The messages as a plain transcript for the summarizer.
+fn transcript(messages: &[Value]) -> String {
+    let mut out = String::new();
+    for message in messages {
+        let role = message["role"].as_str().unwrap_or("user");
+        match &message["content"] {
+            Value::String(text) => out.push_str(&format!("{}: {}\n\n", role, text)),
+            Value::Array(blocks) => {
+                for block in blocks {
+                    out.push_str(&format!("{}: {}\n\n", role, render_block(block)));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn render_block(block: &Value) -> String {
+    match block["type"].as_str().unwrap_or("") {
+        "text" => block["text"].as_str().unwrap_or("").to_string(),
+        "tool_use" | "server_tool_use" => format!(
+            "[{} {}] {} {}",
+            block["type"].as_str().unwrap_or(""),
+            block["id"].as_str().unwrap_or(""),
+            block["name"].as_str().unwrap_or(""),
+            clip(&block["input"].to_string())
+        ),
+        "tool_result" => {
+            let content = match &block["content"] {
+                Value::String(text) => text.clone(),
+                Value::Array(parts) => parts
+                    .iter()
+                    .filter_map(|p| p["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            };
+            format!(
+                "[tool_result {}] {}",
+                block["tool_use_id"].as_str().unwrap_or(""),
+                clip(&content)
+            )
+        }
+        // Server tool results (`web_search_tool_result`, ...)
+        other if block.get("tool_use_id").is_some() => format!(
+            "[{} {}] {}",
+            other,
+            block["tool_use_id"].as_str().unwrap_or(""),
+            clip(&block["content"].to_string())
+        ),
+        other => format!("[{}]", other),
+    }
+}
+
+fn clip(text: &str) -> String {
+    match text.char_indices().nth(MAX_BLOCK_CHARS) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/// Put `text` before the message's existing content.
+fn prepend_text(message: &mut Value, text: String) {
+    let summary = json!({"type": "text", "text": text});
+    let content = match message["content"].take() {
+        Value::String(original) => vec![summary, json!({"type": "text", "text": original})],
+        Value::Array(mut blocks) => {
+            blocks.insert(0, summary);
+            blocks
+        }
+        _ => vec![summary],
+    };
+    message["content"] = Value::Array(content);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+
+    fn conversation() -> Value {
+        json!({
+            "model": "claude-sonnet",
+            "messages": [
+                {"role": "user", "content": "Why is routing slow?"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {"path": "src/router.rs"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "fn route() {}"}
+                ]},
+                {"role": "assistant", "content": "The regexes are rebuilt per call."},
+                {"role": "user", "content": "Fix it."},
+                {"role": "assistant", "content": "Done."}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_split_point() {
+        let request = conversation();
+        let messages = request["messages"].as_array().unwrap();
+        // Index 2 is a tool result, so the split falls back to index 4
+        assert_eq!(split_point(messages, 2), Some(4));
+        // The only user turns left are the first message and a tool result
+        assert_eq!(split_point(messages, 3), None);
+        assert_eq!(split_point(messages, 10), None);
+    }
+
+    #[tokio::test]
+    async fn test_compact() {
+        let backend = Arc::new(MockBackend::with_text(
+            "Routing is slow because of regex rebuilds [tool_result toolu_1].",
+        ));
+        let compactor = Compactor::new(backend.clone(), "qwen")
+            .with_threshold(1)
+            .with_keep_recent(2);
+        let mut request = conversation();
+
+        let compaction = compactor.compact(&mut request).await.unwrap();
+        assert_eq!(compaction.compacted_messages, 4);
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        let summary = messages[0]["content"][0]["text"].as_str().unwrap();
+        assert!(summary.contains("[tool_result toolu_1]"));
+        assert_eq!(messages[0]["content"][1]["text"], "Fix it.");
+
+        let transcript = backend.requests()[0].messages[0].content.to_text();
+        assert!(transcript.contains("[tool_use toolu_1] read_file"));
+        assert!(transcript.contains("[tool_result toolu_1] fn route() {}"));
+
+        let mut response = json!({"type": "message"});
+        compaction.annotate(&mut response);
+        let edit = &response["context_management"]["applied_edits"][0];
+        assert_eq!(edit["type"], COMPACTION_EDIT);
+        assert_eq!(edit["messages"], request["messages"]);
+    }
+
+    #[tokio::test]
+    async fn test_under_threshold_untouched() {
+        let backend = Arc::new(MockBackend::new(vec![]));
+        let compactor = Compactor::new(backend.clone(), "qwen");
+        let mut request = conversation();
+        assert!(compactor.compact(&mut request).await.is_none());
+        assert_eq!(request, conversation());
+        assert_eq!(backend.request_count(), 0);
+    }
+}
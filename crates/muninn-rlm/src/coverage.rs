@@ -0,0 +1,444 @@
+//! Exploration coverage across sessions.
+//!
+//! Every tool call the engine makes is traced as a `tool_execution` span
+//! (see [`ToolExecutionTraceData`](crate::engine::ToolExecutionTraceData)).
+//! [`ExplorationCoverage`] replays those spans from the trace files under
+//! `.muninn/` and counts which files and symbols the RLM has read, so
+//! `muninn coverage` can show hot spots and never-explored areas and the
+//! engine can be told what earlier sessions already looked at.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use muninn_tracing::{Span, Trace};
+use serde::Serialize;
+
+/// Tools whose `path` argument names a file that was read.
+const FILE_READ_TOOLS: &[&str] = &["read_file"];
+
+/// Tools whose `name` argument names a symbol that was read.
+const SYMBOL_READ_TOOLS: &[&str] = &["read_symbol", "get_symbol"];
+
+/// How often one file or symbol was read.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ReadCount {
+    /// Number of reads across all traces.
+    pub reads: usize,
+    /// Number of distinct traces (requests) that read it.
+    pub traces: usize,
+    /// When it was last read.
+    pub last_read: DateTime<Utc>,
+}
+
+/// Files and symbols read by the RLM, aggregated over traces.
+#[derive(Debug, Clone, Default)]
+pub struct ExplorationCoverage {
+    /// Traces that made at least one tool call.
+    pub traces: usize,
+    /// Reads per file, keyed by path relative to the project root.
+    pub files: BTreeMap<String, ReadCount>,
+    /// Reads per symbol name.
+    pub symbols: BTreeMap<String, ReadCount>,
+}
+
+impl ExplorationCoverage {
+    /// Load coverage from every trace file under `muninn_dir`: per-session
+    /// `sessions/*/traces.jsonl` and the daily `traces/*.jsonl`. Paths under
+    /// `root` are made relative to it. Unreadable lines are skipped.
+    pub fn from_muninn_dir(muninn_dir: &Path, root: &Path) -> std::io::Result<Self> {
+        let mut coverage = Self::default();
+        for path in trace_files(muninn_dir) {
+            let content = std::fs::read_to_string(&path)?;
+            for trace in content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Trace>(line).ok())
+            {
+                coverage.record_trace(&trace, root);
+            }
+        }
+        Ok(coverage)
+    }
+
+    /// Add the reads recorded in one trace.
+    pub fn record_trace(&mut self, trace: &Trace, root: &Path) {
+        let mut reads = Vec::new();
+        for span in &trace.spans {
+            collect_tool_spans(span, &mut reads);
+        }
+        if reads.is_empty() {
+            return;
+        }
+        self.traces += 1;
+
+        let mut seen_files = BTreeSet::new();
+        let mut seen_symbols = BTreeSet::new();
+        for (span, tool, input) in reads {
+            let at = span.ended_at.unwrap_or(span.started_at);
+            if FILE_READ_TOOLS.contains(&tool)
+                && let Some(path) = input.get("path").and_then(|v| v.as_str())
+            {
+                let path = normalize_path(path, root);
+                let first = seen_files.insert(path.clone());
+                bump(&mut self.files, path, at, first);
+            } else if SYMBOL_READ_TOOLS.contains(&tool)
+                && let Some(name) = input.get("name").and_then(|v| v.as_str())
+            {
+                let first = seen_symbols.insert(name.to_string());
+                bump(&mut self.symbols, name.to_string(), at, first);
+            }
+        }
+    }
+
+    /// Files ordered by read count, most-read first.
+    pub fn hot_files(&self) -> Vec<(&str, &ReadCount)> {
+        let mut files: Vec<_> = self.files.iter().map(|(p, c)| (p.as_str(), c)).collect();
+        files.sort_by(|a, b| b.1.reads.cmp(&a.1.reads).then(a.0.cmp(b.0)));
+        files
+    }
+
+    /// Symbols ordered by read count, most-read first.
+    pub fn hot_symbols(&self) -> Vec<(&str, &ReadCount)> {
+        let mut symbols: Vec<_> = self.symbols.iter().map(|(s, c)| (s.as_str(), c)).collect();
+        symbols.sort_by(|a, b| b.1.reads.cmp(&a.1.reads).then(a.0.cmp(b.0)));
+        symbols
+    }
+
+    /// Build a report against the project's known files (e.g. every file
+    /// in the code graph), listing those never read.
+    pub fn report(
+        &self,
+        known_files: impl IntoIterator<Item = String>,
+        top: usize,
+    ) -> CoverageReport {
+        let known: BTreeSet<String> = known_files.into_iter().collect();
+        let unexplored: Vec<String> = known
+            .iter()
+            .filter(|f| !self.files.contains_key(f.as_str()))
+            .cloned()
+            .collect();
+
+        let mut by_dir: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for file in &known {
+            let entry = by_dir.entry(parent_dir(file)).or_default();
+            entry.0 += 1;
+            if !self.files.contains_key(file.as_str()) {
+                entry.1 += 1;
+            }
+        }
+        let unexplored_dirs = by_dir
+            .into_iter()
+            .filter(|(_, (total, unread))| total == unread)
+            .map(|(dir, (total, _))| (dir, total))
+            .collect();
+
+        let entry = |(name, count): (&str, &ReadCount)| CoverageEntry {
+            name: name.to_string(),
+            reads: count.reads,
+            traces: count.traces,
+            last_read: count.last_read,
+        };
+        CoverageReport {
+            traces: self.traces,
+            files_read: self.files.len(),
+            known_files: known.len(),
+            hot_files: self.hot_files().into_iter().take(top).map(entry).collect(),
+            hot_symbols: self
+                .hot_symbols()
+                .into_iter()
+                .take(top)
+                .map(entry)
+                .collect(),
+            unexplored_dirs,
+            unexplored,
+        }
+    }
+
+    /// A system-prompt hint naming files that more than one earlier request
+    /// read, or `None` when there are none.
+    pub fn engine_hint(&self, max_files: usize) -> Option<String> {
+        let hot: Vec<String> = self
+            .hot_files()
+            .into_iter()
+            .filter(|(_, c)| c.traces > 1)
+            .take(max_files)
+            .map(|(path, c)| format!("- {} (read in {} earlier requests)", path, c.traces))
+            .collect();
+        if hot.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "## Prior exploration\n\n\
+             Earlier sessions repeatedly read these files. Before reading one in \
+             full again, try file_outline or read_symbol for the part you need:\n\n{}",
+            hot.join("\n")
+        ))
+    }
+}
+
+/// Coverage summary rendered by `muninn coverage`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    /// Traces that made at least one tool call.
+    pub traces: usize,
+    /// Distinct files read.
+    pub files_read: usize,
+    /// Files the project is known to contain.
+    pub known_files: usize,
+    /// Most-read files.
+    pub hot_files: Vec<CoverageEntry>,
+    /// Most-read symbols.
+    pub hot_symbols: Vec<CoverageEntry>,
+    /// Directories none of whose files were read, with their file counts.
+    pub unexplored_dirs: Vec<(String, usize)>,
+    /// Known files never read.
+    pub unexplored: Vec<String>,
+}
+
+/// One row of a hot-spot table.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageEntry {
+    pub name: String,
+    pub reads: usize,
+    pub traces: usize,
+    pub last_read: DateTime<Utc>,
+}
+
+impl CoverageReport {
+    /// Render the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Exploration coverage\n\n");
+        if self.traces == 0 {
+            out.push_str(
+                "No RLM tool calls found in the traces under `.muninn/`. \
+                 Coverage builds up as the proxy handles requests.\n",
+            );
+            return out;
+        }
+        out.push_str(&format!(
+            "{} requests read {} files",
+            self.traces, self.files_read
+        ));
+        if self.known_files == 0 {
+            out.push_str(" (no code graph to compare against; run `muninn index`).\n");
+        } else {
+            out.push_str(&format!(
+                "; {} of {} indexed files have been explored.\n",
+                self.known_files - self.unexplored.len(),
+                self.known_files
+            ));
+        }
+
+        if !self.hot_files.is_empty() {
+            out.push_str(
+                "\n## Hot files\n\n| File | Reads | Requests | Last read |\n|---|---|---|---|\n",
+            );
+            for e in &self.hot_files {
+                out.push_str(&format!(
+                    "| `{}` | {} | {} | {} |\n",
+                    e.name,
+                    e.reads,
+                    e.traces,
+                    e.last_read.format("%Y-%m-%d")
+                ));
+            }
+        }
+        if !self.hot_symbols.is_empty() {
+            out.push_str("\n## Hot symbols\n\n| Symbol | Reads | Requests |\n|---|---|---|\n");
+            for e in &self.hot_symbols {
+                out.push_str(&format!("| `{}` | {} | {} |\n", e.name, e.reads, e.traces));
+            }
+        }
+        if !self.unexplored_dirs.is_empty() {
+            out.push_str("\n## Never-explored directories\n\n");
+            for (dir, files) in &self.unexplored_dirs {
+                out.push_str(&format!("- `{}` ({} files)\n", dir, files));
+            }
+        }
+        if !self.unexplored.is_empty() {
+            out.push_str(&format!(
+                "\n## Never-explored files\n\n{} files have never been read.\n\n",
+                self.unexplored.len()
+            ));
+            for file in &self.unexplored {
+                out.push_str(&format!("- `{}`\n", file));
+            }
+        }
+        out
+    }
+}
+
+/// Trace files under `muninn_dir`: per-session `sessions/*/traces.jsonl`
+/// and the daily `traces/*.jsonl`, sorted.
+pub(crate) fn trace_files(muninn_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(sessions) = std::fs::read_dir(muninn_dir.join("sessions")) {
+        for session in sessions.flatten() {
+            let path = session.path().join("traces.jsonl");
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    if let Ok(daily) = std::fs::read_dir(muninn_dir.join("traces")) {
+        for entry in daily.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "jsonl") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Collect `(span, tool_name, input)` for every `tool_execution` span.
+fn collect_tool_spans<'a>(
+    span: &'a Span,
+    out: &mut Vec<(&'a Span, &'a str, &'a serde_json::Value)>,
+) {
+    if span.name == "tool_execution"
+        && let Some(data) = &span.data
+        && let Some(tool) = data.get("tool_name").and_then(|v| v.as_str())
+        && let Some(input) = data.get("input")
+    {
+        out.push((span, tool, input));
+    }
+    for child in &span.children {
+        collect_tool_spans(child, out);
+    }
+}
+
+fn bump(
+    map: &mut BTreeMap<String, ReadCount>,
+    key: String,
+    at: DateTime<Utc>,
+    first_in_trace: bool,
+) {
+    let count = map.entry(key).or_insert(ReadCount {
+        reads: 0,
+        traces: 0,
+        last_read: at,
+    });
+    count.reads += 1;
+    if first_in_trace {
+        count.traces += 1;
+    }
+    count.last_read = count.last_read.max(at);
+}
+
+/// Strip `root` and a leading `./` so tool paths and graph paths compare.
+fn normalize_path(path: &str, root: &Path) -> String {
+    let relative = Path::new(path)
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    relative.trim_start_matches("./").to_string()
+}
+
+fn parent_dir(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((dir, _)) => dir.to_string(),
+        None => ".".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace(reads: &[(&str, serde_json::Value)]) -> Trace {
+        let mut cycle = Span::new("rlm_cycle");
+        let mut iteration = Span::new("rlm_iteration");
+        for (tool, input) in reads {
+            let mut span = Span::new("tool_execution")
+                .with_data(json!({"tool_name": tool, "input": input, "success": true}));
+            span.complete_ok();
+            iteration.add_child(span);
+        }
+        cycle.add_child(iteration);
+        let mut trace = Trace::new_random();
+        trace.add_span(cycle);
+        trace
+    }
+
+    #[test]
+    fn test_record_trace_counts_reads() {
+        let root = Path::new("/repo");
+        let mut coverage = ExplorationCoverage::default();
+        coverage.record_trace(
+            &trace(&[
+                ("read_file", json!({"path": "/repo/src/main.rs"})),
+                (
+                    "read_file",
+                    json!({"path": "src/main.rs", "start_line": 10}),
+                ),
+                ("read_symbol", json!({"name": "run"})),
+                ("list_directory", json!({"path": "src"})),
+            ]),
+            root,
+        );
+        coverage.record_trace(
+            &trace(&[("read_file", json!({"path": "./src/main.rs"}))]),
+            root,
+        );
+        coverage.record_trace(&trace(&[]), root);
+
+        assert_eq!(coverage.traces, 2);
+        let main = &coverage.files["src/main.rs"];
+        assert_eq!((main.reads, main.traces), (3, 2));
+        assert_eq!(coverage.files.len(), 1);
+        assert_eq!(coverage.symbols["run"].reads, 1);
+
+        let hint = coverage.engine_hint(5).unwrap();
+        assert!(hint.contains("- src/main.rs (read in 2 earlier requests)"));
+    }
+
+    #[test]
+    fn test_report_lists_unexplored() {
+        let root = Path::new("/repo");
+        let mut coverage = ExplorationCoverage::default();
+        coverage.record_trace(&trace(&[("read_file", json!({"path": "src/a.rs"}))]), root);
+        let known = ["src/a.rs", "src/b.rs", "tools/gen.rs", "build.rs"]
+            .into_iter()
+            .map(String::from);
+        let report = coverage.report(known, 10);
+
+        assert_eq!(
+            report.unexplored,
+            vec!["build.rs", "src/b.rs", "tools/gen.rs"]
+        );
+        assert_eq!(
+            report.unexplored_dirs,
+            vec![(".".to_string(), 1), ("tools".to_string(), 1)]
+        );
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("1 requests read 1 files; 1 of 4 indexed files"));
+        assert!(markdown.contains("| `src/a.rs` | 1 | 1 |"));
+        assert!(markdown.contains("- `tools` (1 files)"));
+        // A single read isn't repetition, so there's nothing to hint
+        assert!(coverage.engine_hint(5).is_none());
+    }
+
+    #[test]
+    fn test_from_muninn_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = dir.path().join("sessions/2026-01-01T00-00-00_abcd");
+        std::fs::create_dir_all(&session).unwrap();
+        let line =
+            serde_json::to_string(&trace(&[("read_file", json!({"path": "src/lib.rs"}))])).unwrap();
+        std::fs::write(
+            session.join("traces.jsonl"),
+            format!("{}\nnot json\n", line),
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("traces")).unwrap();
+        std::fs::write(dir.path().join("traces/2026-01-02.jsonl"), line + "\n").unwrap();
+
+        let coverage =
+            ExplorationCoverage::from_muninn_dir(dir.path(), Path::new("/repo")).unwrap();
+        assert_eq!(coverage.traces, 2);
+        assert_eq!(coverage.files["src/lib.rs"].traces, 2);
+    }
+}
@@ -20,7 +20,7 @@ use muninn_graph::registry::{
 };
 
 use crate::error::{Result, RlmError};
-use crate::tools::{Tool, ToolMetadata, ToolResult};
+use crate::tools::{Tool, ToolCategory, ToolMetadata, ToolResult};
 
 /// Thread-safe wrapper around DocStore.
 pub type SharedDocStore = Arc<Mutex<DocStore>>;
@@ -214,6 +214,10 @@ impl Tool for IndexCrateTool {
         "index_crate"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Write
+    }
+
     fn description(&self) -> &str {
         "Index a Rust crate from crates.io. Downloads the crate, generates rustdoc JSON, \
          and stores documentation in the search index. Requires cargo and nightly Rust. \
@@ -364,6 +368,10 @@ impl Tool for IndexPackageTool {
         "index_package"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Write
+    }
+
     fn description(&self) -> &str {
         "Index a Python package from PyPI. Downloads the package, extracts documentation using griffe, \
          and stores it in the search index. Requires Python and griffe (pip install griffe). \
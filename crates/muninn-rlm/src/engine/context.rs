@@ -3,25 +3,52 @@
 use std::time::Duration;
 
 use crate::types::{
-    BudgetConfig, CompletionRequest, CompletionResponse, ContentBlock, ExplorationMetadata,
-    Message, StopReason, ToolResultBlock, Usage,
+    BudgetConfig, CompletionRequest, CompletionResponse, Content, ContentBlock,
+    ExplorationMetadata, Message, Role, StopReason, ToolResultBlock, ToolResultContent, Usage,
 };
 
 use super::budget::BudgetTracker;
+use super::tool_executor::ToolUsage;
+
+/// What an elided tool result is replaced with.
+pub const ELIDED_RESULT: &str = "[Result elided to fit the context window]";
+
+/// How [`ExplorationContext::shrink_oldest_interaction`] made room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shrink {
+    /// The interaction's tool results were replaced with [`ELIDED_RESULT`].
+    Elided,
+    /// The interaction was removed, call and results.
+    Dropped,
+}
 
 /// Context for tracking exploration state.
 pub struct ExplorationContext {
     original_request: CompletionRequest,
     messages: Vec<Message>,
+    /// Messages that came with the request; the rest are exploration turns.
+    request_len: usize,
     budget: BudgetTracker,
+    tool_usage: ToolUsage,
+    /// Fallback model the remaining turns run on, once downshifted.
+    downshifted_to: Option<String>,
+    /// The budget ran out and the next turn is the last, whatever the depth.
+    final_turn: bool,
+    /// Continuation requests issued for an answer cut off by `max_tokens`.
+    continuations: u32,
 }
 
 impl ExplorationContext {
     pub fn new(request: CompletionRequest, budget: BudgetConfig) -> Self {
         Self {
             messages: request.messages.clone(),
+            request_len: request.messages.len(),
             original_request: request,
             budget: BudgetTracker::new(budget),
+            tool_usage: ToolUsage::default(),
+            downshifted_to: None,
+            final_turn: false,
+            continuations: 0,
         }
     }
 
@@ -63,6 +90,58 @@ impl ExplorationContext {
         self.budget.record_tool_calls(results.len() as u32);
     }
 
+    /// Make room in the exploration, oldest tool interaction first: elide
+    /// results that are still there, then drop whole interactions. The
+    /// latest interaction and the request's own messages are kept. Returns
+    /// `None` when there is nothing left to trim.
+    pub fn shrink_oldest_interaction(&mut self) -> Option<Shrink> {
+        let interactions: Vec<usize> = (self.request_len..self.messages.len().saturating_sub(1))
+            .filter(|&i| {
+                self.messages[i].role == Role::Assistant && is_tool_results(&self.messages[i + 1])
+            })
+            .collect();
+        let (_, older) = interactions.split_last()?;
+        if let Some(&i) = older.iter().find(|&&i| !is_elided(&self.messages[i + 1])) {
+            if let Content::Blocks(blocks) = &mut self.messages[i + 1].content {
+                for block in blocks {
+                    if let ContentBlock::ToolResult { content, .. } = block {
+                        *content = Some(ToolResultContent::Text(ELIDED_RESULT.to_string()));
+                    }
+                }
+            }
+            return Some(Shrink::Elided);
+        }
+        let &i = older.first()?;
+        self.messages.drain(i..i + 2);
+        Some(Shrink::Dropped)
+    }
+
+    /// Record a turn that an output guard cut short, followed by a nudge
+    /// to call a tool or answer.
+    ///
+    /// Tool calls in a cut-off turn are dropped: their input may be
+    /// truncated, and they would need matching results.
+    pub fn add_cut_off_turn(&mut self, response: CompletionResponse) {
+        let mut text = response.text();
+        if text.trim().is_empty() {
+            text = "(no output)".to_string();
+        }
+        self.messages
+            .push(Message::assistant_blocks(vec![ContentBlock::Text {
+                text,
+                cache_control: None,
+            }]));
+        self.messages.push(Message::user(
+            "[Output cut off] Your last turn ran too long. Don't narrate: call a tool \
+             for what you need next, or call `final_answer` if you have enough.",
+        ));
+    }
+
+    /// Per-tool usage so far, for enforcing tool limits.
+    pub fn tool_usage_mut(&mut self) -> &mut ToolUsage {
+        &mut self.tool_usage
+    }
+
     pub fn increment_depth(&mut self) {
         self.budget.increment_depth();
     }
@@ -80,11 +159,41 @@ impl ExplorationContext {
     }
 
     pub fn is_last_turn(&self) -> bool {
-        self.budget.is_last_turn()
+        self.final_turn || self.budget.is_last_turn()
     }
 
     pub fn would_exceed_depth(&self) -> bool {
-        self.budget.would_exceed_depth()
+        self.final_turn || self.budget.would_exceed_depth()
+    }
+
+    /// Make the next turn the last one, regardless of depth.
+    pub fn force_final_turn(&mut self) {
+        self.final_turn = true;
+    }
+
+    /// Whether [`force_final_turn`](Self::force_final_turn) was called.
+    pub fn is_final_turn_forced(&self) -> bool {
+        self.final_turn
+    }
+
+    /// Record that the remaining turns run on `model`.
+    pub fn downshift(&mut self, model: impl Into<String>) {
+        self.downshifted_to = Some(model.into());
+    }
+
+    /// Fallback model the exploration switched to, if it did.
+    pub fn downshifted_to(&self) -> Option<&str> {
+        self.downshifted_to.as_deref()
+    }
+
+    /// Record one continuation request for a cut-off answer.
+    pub fn add_continuation(&mut self) {
+        self.continuations += 1;
+    }
+
+    /// Continuation requests issued so far.
+    pub fn continuations(&self) -> u32 {
+        self.continuations
     }
 
     pub fn inject_last_turn_warning(&mut self) {
@@ -102,6 +211,17 @@ impl ExplorationContext {
         self.budget.elapsed()
     }
 
+    /// Model the exploration runs on.
+    pub fn model(&self) -> &str {
+        &self.original_request.model
+    }
+
+    /// Text of the last user message that has any, i.e. the question
+    /// being explored (see [`CompletionRequest::question`]).
+    pub fn question(&self) -> Option<String> {
+        self.original_request.question()
+    }
+
     pub fn budget_config(&self) -> &BudgetConfig {
         self.budget.config()
     }
@@ -112,6 +232,11 @@ impl ExplorationContext {
             tokens_used: self.budget.tokens_used(),
             tool_calls: self.budget.tool_calls(),
             duration_ms: self.budget.elapsed().as_millis() as u64,
+            trace_id: muninn_tracing::current_trace_id(),
+            quality_score: None,
+            downshifted_to: self.downshifted_to.clone(),
+            partial: None,
+            continuations: (self.continuations > 0).then_some(self.continuations),
         }
     }
 
@@ -149,6 +274,20 @@ impl ExplorationContext {
     }
 }
 
+fn is_tool_results(message: &Message) -> bool {
+    message.role == Role::User
+        && matches!(&message.content, Content::Blocks(blocks)
+            if blocks.iter().any(|b| matches!(b, ContentBlock::ToolResult { .. })))
+}
+
+fn is_elided(message: &Message) -> bool {
+    matches!(&message.content, Content::Blocks(blocks) if blocks.iter().all(|b| matches!(
+        b,
+        ContentBlock::ToolResult { content: Some(ToolResultContent::Text(text)), .. }
+            if text == ELIDED_RESULT
+    )))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,4 +361,36 @@ mod tests {
         assert_eq!(finalized.text(), "Final answer");
         assert_eq!(finalized.stop_reason, Some(StopReason::EndTurn));
     }
+
+    #[test]
+    fn test_shrink_oldest_interaction() {
+        let mut context = ExplorationContext::new(make_request(), BudgetConfig::default());
+        for id in ["t1", "t2", "t3"] {
+            let call = CompletionResponse::new(
+                "msg",
+                "model",
+                vec![ContentBlock::ToolUse {
+                    id: id.to_string(),
+                    name: "read_file".to_string(),
+                    input: serde_json::json!({"path": id}),
+                    cache_control: None,
+                }],
+                StopReason::ToolUse,
+                Usage::default(),
+            );
+            context.add_tool_interaction(call, vec![ToolResultBlock::success(id, "x".repeat(100))]);
+        }
+
+        assert_eq!(context.shrink_oldest_interaction(), Some(Shrink::Elided));
+        assert_eq!(context.shrink_oldest_interaction(), Some(Shrink::Elided));
+        assert!(is_elided(&context.messages[2]));
+        assert!(!is_elided(&context.messages[6]));
+        assert_eq!(context.shrink_oldest_interaction(), Some(Shrink::Dropped));
+        assert_eq!(context.shrink_oldest_interaction(), Some(Shrink::Dropped));
+        assert_eq!(context.shrink_oldest_interaction(), None);
+        // The request and the latest interaction are left
+        assert_eq!(context.messages.len(), 3);
+        assert_eq!(context.messages[0].content.to_text(), "Hello");
+        assert!(!is_elided(&context.messages[2]));
+    }
 }
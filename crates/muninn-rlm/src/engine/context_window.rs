@@ -0,0 +1,118 @@
+//! Keeping exploration turns inside the model's context window.
+//!
+//! Each turn resends the whole exploration, so a run that reads a few large
+//! files can outgrow the model's context and get a 400 from the backend,
+//! which ends the exploration. Before each turn the engine estimates the
+//! request's size ([`estimate_request_tokens`]) against the window from
+//! [`ContextWindows`] and, when over, trims the oldest tool interactions
+//! first (see [`ExplorationContext::shrink_oldest_interaction`](super::ExplorationContext::shrink_oldest_interaction)).
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::types::CompletionRequest;
+
+/// Context windows of common models, in tokens, matched by name prefix.
+const BUILTIN_WINDOWS: &[(&str, u64)] = &[
+    ("claude-", 200_000),
+    ("llama-3.1-8b-instant", 131_072),
+    ("llama-3.3-70b-versatile", 131_072),
+    ("openai/gpt-oss-", 131_072),
+    ("qwen2.5-coder", 32_768),
+];
+
+/// Context window per model: built-in defaults for common models plus
+/// configured ones. Models matching neither are never trimmed.
+#[derive(Debug, Clone)]
+pub struct ContextWindows {
+    windows: HashMap<String, u64>,
+}
+
+impl Default for ContextWindows {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ContextWindows {
+    /// The built-in windows only.
+    pub fn builtin() -> Self {
+        Self {
+            windows: BUILTIN_WINDOWS
+                .iter()
+                .map(|(model, tokens)| (model.to_string(), *tokens))
+                .collect(),
+        }
+    }
+
+    /// No windows: nothing is trimmed.
+    pub fn none() -> Self {
+        Self {
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Set the window for models named, or starting with, `model`.
+    pub fn with(mut self, model: impl Into<String>, tokens: u64) -> Self {
+        self.windows.insert(model.into(), tokens);
+        self
+    }
+
+    /// The window for `model`: an exact match, else the longest prefix.
+    pub fn get(&self, model: &str) -> Option<u64> {
+        self.windows.get(model).copied().or_else(|| {
+            self.windows
+                .iter()
+                .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, tokens)| *tokens)
+        })
+    }
+}
+
+/// Approximate tokens `request` takes from the window: system prompt,
+/// tools and messages at 4 characters per token, plus room for the answer.
+pub fn estimate_request_tokens(request: &CompletionRequest) -> u64 {
+    let system = request.system.as_ref().map_or(0, |s| s.to_text().len());
+    let tools = serde_json::to_string(&request.tools).map_or(0, |s| s.len());
+    let messages = serde_json::to_string(&request.messages).map_or(0, |s| s.len());
+    ((system + tools + messages) / 4) as u64 + u64::from(request.max_tokens)
+}
+
+/// Trace data for an `rlm_context_trimmed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextTrimTraceData {
+    pub model: String,
+    pub context_window: u64,
+    pub estimated_before: u64,
+    pub estimated_after: u64,
+    /// Tool interactions whose results were elided.
+    pub elided: u32,
+    /// Tool interactions dropped entirely.
+    pub dropped: u32,
+    pub depth: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Message;
+
+    #[test]
+    fn test_window_lookup() {
+        let windows = ContextWindows::builtin().with("qwen2.5-coder:7b", 8192);
+        assert_eq!(windows.get("claude-sonnet-4-5"), Some(200_000));
+        assert_eq!(windows.get("qwen2.5-coder:7b"), Some(8192));
+        assert_eq!(windows.get("qwen2.5-coder:14b"), Some(32_768));
+        assert_eq!(windows.get("gemma4:31b"), None);
+        assert_eq!(ContextWindows::none().get("claude-sonnet-4-5"), None);
+    }
+
+    #[test]
+    fn test_estimate_request_tokens() {
+        let request = CompletionRequest::new("m", vec![Message::user("x".repeat(4000))], 100);
+        let estimate = estimate_request_tokens(&request);
+        assert!((1100..1200).contains(&estimate), "{}", estimate);
+    }
+}
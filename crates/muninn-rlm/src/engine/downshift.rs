@@ -0,0 +1,91 @@
+//! Switching to a cheaper backend when the token budget runs low.
+//!
+//! Without a [`Downshift`], an exploration that runs out of tokens fails
+//! with `BudgetExceeded` and everything it gathered is lost. With one, the
+//! engine moves the remaining turns to the fallback model once the
+//! exploration has used a share of its token budget, and when the budget is
+//! exhausted it gives the fallback one last turn to answer from what it has.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::backend::LLMBackend;
+
+/// Share of the token budget after which the fallback takes over.
+pub const DEFAULT_DOWNSHIFT_AT: f64 = 0.8;
+
+/// A cheaper backend to finish explorations on.
+///
+/// The fallback should call tools the same way as the main backend (native
+/// or text-based): the exploration prompt is built for the main one.
+#[derive(Clone)]
+pub struct Downshift {
+    /// Backend the remaining turns go to.
+    pub backend: Arc<dyn LLMBackend>,
+    /// Model requested from the fallback backend.
+    pub model: String,
+    /// Share of `max_tokens` (0–1) used before switching.
+    pub at: f64,
+}
+
+impl Downshift {
+    pub fn new(backend: Arc<dyn LLMBackend>, model: impl Into<String>) -> Self {
+        Self {
+            backend,
+            model: model.into(),
+            at: DEFAULT_DOWNSHIFT_AT,
+        }
+    }
+
+    pub fn with_threshold(mut self, at: f64) -> Self {
+        self.at = at;
+        self
+    }
+
+    /// Whether `tokens_used` has reached the threshold. Never true without
+    /// a token limit.
+    pub fn is_due(&self, tokens_used: u64, token_limit: Option<u64>) -> bool {
+        token_limit.is_some_and(|limit| tokens_used as f64 >= limit as f64 * self.at)
+    }
+}
+
+impl std::fmt::Debug for Downshift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Downshift")
+            .field("backend", &self.backend.name())
+            .field("model", &self.model)
+            .field("at", &self.at)
+            .finish()
+    }
+}
+
+/// Trace data for the `rlm_downshift` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownshiftTraceData {
+    /// Model the exploration switched to.
+    pub model: String,
+    /// `threshold` or `budget_exceeded` (the fallback's last turn).
+    pub reason: String,
+    /// Depth at the switch.
+    pub depth: u32,
+    /// Tokens used at the switch.
+    pub tokens_used: u64,
+    /// Token budget of the exploration.
+    pub token_limit: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+
+    #[test]
+    fn test_is_due() {
+        let downshift = Downshift::new(Arc::new(MockBackend::new(vec![])), "small");
+        assert!(!downshift.is_due(79, Some(100)));
+        assert!(downshift.is_due(80, Some(100)));
+        assert!(!downshift.is_due(1_000_000, None));
+        assert!(downshift.with_threshold(0.5).is_due(50, Some(100)));
+    }
+}
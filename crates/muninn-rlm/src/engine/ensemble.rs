@@ -0,0 +1,241 @@
+//! Ensembling the final answer across two backends.
+//!
+//! With an [`Ensemble`] configured, a second backend also writes the final
+//! answer once exploration is done. It is given the same gathered context
+//! (the conversation and every tool result) plus a synthesis prompt. The
+//! exploring backend's answer is the first candidate and the second
+//! backend's is the other. A judge model picks the better one. Without a
+//! judge, or when the judge gives no usable verdict, a heuristic picks:
+//! more cited files wins, then the longer answer, then the first
+//! candidate. Both candidates and the pick go into an `rlm_ensemble` trace
+//! event for later evaluation.
+
+use std::sync::{Arc, LazyLock};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::backend::LLMBackend;
+use crate::types::{CompletionRequest, Message};
+
+use super::context::ExplorationContext;
+use super::quality::cited_paths;
+
+/// Instruction appended to the gathered context for the second backend.
+const SYNTHESIS_PROMPT: &str = "Exploration is done. Using only what was gathered above, \
+write the final answer to the original question. Cite the files and lines it rests on. \
+Do not call any tools.";
+
+const JUDGE_PROMPT: &str = "You compare two answers to a question about a codebase. \
+Pick the one that answers the question more accurately and completely, citing the code it rests on. \
+Reply with the letter A or B only.";
+
+/// Characters of question and each answer sent to the judge.
+const MAX_JUDGED_CHARS: usize = 4000;
+
+/// A verdict: `A`, `b.`, `Answer B`.
+static VERDICT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^\W*(?:answer\s+)?([AB])\b").unwrap());
+
+/// A backend and the model to request from it.
+#[derive(Clone)]
+pub struct EnsembleMember {
+    pub backend: Arc<dyn LLMBackend>,
+    pub model: String,
+}
+
+impl EnsembleMember {
+    pub fn new(backend: Arc<dyn LLMBackend>, model: impl Into<String>) -> Self {
+        Self {
+            backend,
+            model: model.into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for EnsembleMember {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnsembleMember")
+            .field("backend", &self.backend.name())
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+/// Second backend for final answers, and the judge picking between them.
+#[derive(Debug, Clone)]
+pub struct Ensemble {
+    /// Backend writing the second candidate.
+    pub synthesizer: EnsembleMember,
+    /// Model picking the better candidate; heuristics pick without one.
+    pub judge: Option<EnsembleMember>,
+}
+
+impl Ensemble {
+    pub fn new(synthesizer: EnsembleMember) -> Self {
+        Self {
+            synthesizer,
+            judge: None,
+        }
+    }
+
+    pub fn with_judge(mut self, judge: EnsembleMember) -> Self {
+        self.judge = Some(judge);
+        self
+    }
+
+    /// Have the second backend answer, pick between its answer and
+    /// `answer`, and record both in an `rlm_ensemble` trace event. Returns
+    /// the picked answer.
+    pub(crate) async fn pick(
+        &self,
+        context: &ExplorationContext,
+        model: &str,
+        answer: String,
+    ) -> String {
+        let second = self.synthesize(context).await;
+        let candidates = vec![
+            EnsembleCandidate {
+                model: model.to_string(),
+                answer,
+            },
+            EnsembleCandidate {
+                model: self.synthesizer.model.clone(),
+                answer: second.unwrap_or_default(),
+            },
+        ];
+        let (winner, judged_by) = match self.judge(context, &candidates).await {
+            Some(winner) => (winner, "judge"),
+            None => (pick_heuristically(&candidates), "heuristic"),
+        };
+        tracing::debug!(
+            winner = %candidates[winner].model,
+            judged_by,
+            "Ensemble picked final answer"
+        );
+        let data = EnsembleTraceData {
+            candidates,
+            winner,
+            judged_by: judged_by.to_string(),
+        };
+        muninn_tracing::record_event("rlm_ensemble", Some(&data));
+        data.candidates
+            .into_iter()
+            .nth(winner)
+            .map(|c| c.answer)
+            .unwrap_or_default()
+    }
+
+    async fn synthesize(&self, context: &ExplorationContext) -> Option<String> {
+        let mut request = context.build_request();
+        request.model = self.synthesizer.model.clone();
+        request.messages.push(Message::user(SYNTHESIS_PROMPT));
+        request.tools = Vec::new();
+        request.tool_choice = None;
+        match self.synthesizer.backend.complete(request).await {
+            Ok(response) => Some(response.text()).filter(|t| !t.trim().is_empty()),
+            Err(e) => {
+                tracing::warn!(error = %e, "Ensemble synthesis failed");
+                None
+            }
+        }
+    }
+
+    /// Index of the candidate the judge picks, if it gives a verdict.
+    async fn judge(
+        &self,
+        context: &ExplorationContext,
+        candidates: &[EnsembleCandidate],
+    ) -> Option<usize> {
+        let judge = self.judge.as_ref()?;
+        if candidates.iter().any(|c| c.answer.trim().is_empty()) {
+            return None;
+        }
+        let prompt = format!(
+            "Question:\n{}\n\nAnswer A:\n{}\n\nAnswer B:\n{}",
+            truncate(&context.question().unwrap_or_default()),
+            truncate(&candidates[0].answer),
+            truncate(&candidates[1].answer),
+        );
+        let mut request = CompletionRequest::new(&judge.model, vec![Message::user(prompt)], 8)
+            .with_system(JUDGE_PROMPT);
+        request.temperature = Some(0.0);
+        let reply = match judge.backend.complete(request).await {
+            Ok(response) => response.text(),
+            Err(e) => {
+                tracing::debug!(error = %e, "Ensemble judge failed");
+                return None;
+            }
+        };
+        let verdict = VERDICT_RE
+            .captures(&reply)
+            .map(|c| c[1].to_ascii_uppercase());
+        match verdict.as_deref() {
+            Some("A") => Some(0),
+            Some("B") => Some(1),
+            _ => {
+                tracing::debug!(reply = %reply, "Ensemble judge gave no verdict");
+                None
+            }
+        }
+    }
+}
+
+/// One candidate final answer.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnsembleCandidate {
+    /// Model that wrote it.
+    pub model: String,
+    /// The answer; empty when the backend failed or returned nothing.
+    pub answer: String,
+}
+
+/// Trace data for the `rlm_ensemble` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnsembleTraceData {
+    /// The exploring backend's answer, then the second backend's.
+    pub candidates: Vec<EnsembleCandidate>,
+    /// Index of the picked candidate.
+    pub winner: usize,
+    /// `judge` or `heuristic`.
+    pub judged_by: String,
+}
+
+/// More cited files wins, then the longer answer, then the first.
+fn pick_heuristically(candidates: &[EnsembleCandidate]) -> usize {
+    let rank = |c: &EnsembleCandidate| (cited_paths(&c.answer).len(), c.answer.trim().len());
+    if rank(&candidates[1]) > rank(&candidates[0]) {
+        1
+    } else {
+        0
+    }
+}
+
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(MAX_JUDGED_CHARS) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(answer: &str) -> EnsembleCandidate {
+        EnsembleCandidate {
+            model: "m".to_string(),
+            answer: answer.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pick_heuristically() {
+        let cites = candidate("Loaded in src/config.rs");
+        let longer = candidate("Config is loaded at startup from the project directory.");
+        assert_eq!(pick_heuristically(&[longer.clone(), cites.clone()]), 1);
+        assert_eq!(pick_heuristically(&[cites.clone(), longer]), 0);
+        assert_eq!(pick_heuristically(&[cites.clone(), cites]), 0);
+        assert_eq!(pick_heuristically(&[candidate("x"), candidate("")]), 0);
+    }
+}
@@ -0,0 +1,79 @@
+//! Observing and steering an exploration from outside the engine.
+//!
+//! [`EngineHooks`] are called at fixed points of the exploration loop: when
+//! an iteration starts, before each tool call runs, once the turn's tool
+//! calls have run, and with the final answer. Library users, report
+//! generators and UIs use them to watch an exploration as it happens, to
+//! refuse individual tool calls with [`ToolCallDecision::Refuse`], and to
+//! stop it early by returning [`HookDecision::Stop`], without forking the
+//! loop. Hooks are set with
+//! [`EngineConfig::with_hooks`](super::EngineConfig::with_hooks).
+
+use std::fmt;
+
+use crate::types::{CompletionResponse, ToolResultBlock, ToolUseBlock};
+
+use super::ExplorationContext;
+
+/// What a hook wants the exploration to do next.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HookDecision {
+    /// Carry on.
+    #[default]
+    Continue,
+    /// End the exploration now. The reason becomes the answer, so the
+    /// caller can tell why it stopped.
+    Stop(String),
+}
+
+/// Whether a tool call the model asked for may run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ToolCallDecision {
+    /// Run the call.
+    #[default]
+    Run,
+    /// Don't run it. The model gets an error result carrying the reason,
+    /// and the exploration goes on.
+    Refuse(String),
+}
+
+/// Callbacks into a running exploration. Every method defaults to a no-op
+/// that lets the exploration continue.
+pub trait EngineHooks: Send + Sync {
+    /// An iteration is about to call the backend. `context.depth()` is the
+    /// iteration's index.
+    fn on_iteration_start(&self, _context: &ExplorationContext) -> HookDecision {
+        HookDecision::Continue
+    }
+
+    /// The model asked for `call`, which has not run yet. This is the only
+    /// point at which a call can be vetoed: by the time
+    /// [`on_tool_result`](Self::on_tool_result) fires, it has run.
+    fn on_tool_call(
+        &self,
+        _context: &ExplorationContext,
+        _call: &ToolUseBlock,
+    ) -> ToolCallDecision {
+        ToolCallDecision::Run
+    }
+
+    /// The turn's tool calls have run. The results are added to the
+    /// context right after the hook returns.
+    fn on_tool_result(
+        &self,
+        _context: &ExplorationContext,
+        _results: &[ToolResultBlock],
+    ) -> HookDecision {
+        HookDecision::Continue
+    }
+
+    /// The exploration produced its answer, after any ensemble pick and
+    /// self-score.
+    fn on_final_answer(&self, _context: &ExplorationContext, _response: &CompletionResponse) {}
+}
+
+impl fmt::Debug for dyn EngineHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<EngineHooks>")
+    }
+}
@@ -6,8 +6,13 @@
 
 mod budget;
 mod context;
+mod context_window;
 mod dir_tree;
+mod downshift;
+mod ensemble;
+mod hooks;
 mod muninn_engine_impl;
+mod quality;
 mod tool_executor;
 mod trace;
 
@@ -15,27 +20,52 @@ mod trace;
 mod tests;
 
 pub use budget::{BudgetSummary, BudgetTracker};
-pub use context::ExplorationContext;
-pub use tool_executor::ToolExecutor;
+pub use context::{ELIDED_RESULT, ExplorationContext, Shrink};
+pub use context_window::{ContextTrimTraceData, ContextWindows, estimate_request_tokens};
+pub use dir_tree::generate_dir_tree;
+pub use downshift::{DEFAULT_DOWNSHIFT_AT, Downshift, DownshiftTraceData};
+pub use ensemble::{Ensemble, EnsembleCandidate, EnsembleMember, EnsembleTraceData};
+pub use hooks::{EngineHooks, HookDecision, ToolCallDecision};
+pub use quality::{QualityScore, cited_paths, cites_files};
+pub use tool_executor::{ToolExecutor, ToolLimits, ToolUsage};
 pub use trace::{
-    RlmCompletionTraceData, RlmCycleTraceData, RlmIterationTraceData, ToolExecutionTraceData,
+    RlmCompletionTraceData, RlmCycleTraceData, RlmIterationTraceData, TIMING_BACKEND_LATENCY,
+    TIMING_COMPACTION, TIMING_EXPLORATION, TIMING_PROMPT_BUILD, TIMING_QUEUE_WAIT, TIMING_ROUTING,
+    TIMING_SERIALIZATION, TIMING_TOOL_EXECUTION, ToolExecutionTraceData,
 };
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use muninn_core::MuninnEngine;
 
 use crate::backend::LLMBackend;
-use crate::error::Result;
+use crate::debug_log::RawRequestLog;
+use crate::error::{BudgetType, Result, RlmError};
 use crate::fs::{RealFileSystem, SharedFileSystem};
-use crate::prompts::CORE_RLM_BEHAVIOR;
+use crate::prompts::{PromptRegistry, RouteCategory};
 use crate::tools::ToolEnvironment;
 use crate::types::{
-    BudgetConfig, CompletionRequest, CompletionResponse, Message, Role, StopReason, SystemPrompt,
+    BudgetConfig, CompletionRequest, CompletionResponse, ContentBlock, Message, Role, StopReason,
+    SystemPrompt, Usage,
 };
 
+/// Time the partial-answer call gets after `max_duration_secs` runs out.
+const PARTIAL_ANSWER_TIMEOUT: Duration = Duration::from_secs(30);
+
+const PARTIAL_ANSWER_PROMPT: &str = "Time is up: the exploration was stopped. \
+Do not call any more tools. Answer the question now from what you have gathered so far, \
+citing the files you read, and say what you could not check.";
+
+/// Continuation requests made, by default, for an answer cut off by
+/// `max_tokens` before it is returned as is.
+pub const DEFAULT_MAX_CONTINUATIONS: u32 = 2;
+
+const CONTINUATION_PROMPT: &str = "Your answer was cut off by the output limit. \
+Continue from where you stopped, without repeating anything you already wrote.";
+
 /// Build a default [`MuninnEngine`] from the given backend, tools, and
 /// optional budget/work_dir overrides.
 ///
@@ -132,6 +162,43 @@ pub struct EngineConfig {
     pub work_dir: Option<PathBuf>,
     pub temperature: Option<f32>,
     pub inject_system_prompt: bool,
+    /// Per-tool limits, keyed by tool name.
+    pub tool_limits: HashMap<String, ToolLimits>,
+    /// Extra system-prompt section describing earlier exploration (see
+    /// [`ExplorationCoverage::engine_hint`](crate::coverage::ExplorationCoverage::engine_hint)).
+    pub exploration_hint: Option<String>,
+    /// Call the backend through `complete_stream` and assemble the response
+    /// from the stream, instead of a single blocking `complete`.
+    pub streaming: bool,
+    /// Stop sequences added to every exploration turn, on top of any in
+    /// the request.
+    pub stop_sequences: Vec<String>,
+    /// Cap on output tokens per exploration turn. The request's
+    /// `max_tokens` still applies when it is lower.
+    pub max_iteration_tokens: Option<u32>,
+    /// Continuation requests stitched onto an answer that stops at
+    /// `max_tokens`. 0 returns the cut-off answer as is.
+    pub max_continuations: u32,
+    /// Score each answer for quality after exploring (see
+    /// [`QualityScore`]). Costs one short extra backend call.
+    pub self_score: bool,
+    /// Category-specific prompt sections and tool subsets, applied when
+    /// the request carries a router category.
+    pub prompts: PromptRegistry,
+    /// Cheaper backend to finish explorations on when the token budget
+    /// runs low, instead of failing with `BudgetExceeded`.
+    pub downshift: Option<Downshift>,
+    /// Second backend writing a competing final answer, and the judge
+    /// picking between the two.
+    pub ensemble: Option<Ensemble>,
+    /// Context window per model. Turns estimated past the window have
+    /// their oldest tool interactions trimmed before they are sent.
+    pub context_windows: ContextWindows,
+    /// Log that receives the exact system prompt and messages of every
+    /// exploration turn (see [`PROMPT_DUMP_FILE`](crate::debug_log::PROMPT_DUMP_FILE)).
+    pub prompt_dump: Option<Arc<RawRequestLog>>,
+    /// Callbacks at each step of every exploration (see [`EngineHooks`]).
+    pub hooks: Option<Arc<dyn EngineHooks>>,
 }
 
 impl Default for EngineConfig {
@@ -141,6 +208,19 @@ impl Default for EngineConfig {
             work_dir: None,
             temperature: Some(0.1),
             inject_system_prompt: true,
+            tool_limits: HashMap::new(),
+            exploration_hint: None,
+            streaming: false,
+            stop_sequences: Vec::new(),
+            max_iteration_tokens: None,
+            max_continuations: DEFAULT_MAX_CONTINUATIONS,
+            self_score: false,
+            prompts: PromptRegistry::builtin(),
+            downshift: None,
+            ensemble: None,
+            context_windows: ContextWindows::builtin(),
+            prompt_dump: None,
+            hooks: None,
         }
     }
 }
@@ -174,6 +254,75 @@ impl EngineConfig {
         self.inject_system_prompt = inject;
         self
     }
+
+    pub fn with_tool_limits(mut self, tool: impl Into<String>, limits: ToolLimits) -> Self {
+        self.tool_limits.insert(tool.into(), limits);
+        self
+    }
+
+    pub fn with_exploration_hint(mut self, hint: impl Into<String>) -> Self {
+        self.exploration_hint = Some(hint.into());
+        self
+    }
+
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    pub fn with_stop_sequences<I, S>(mut self, sequences: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop_sequences = sequences.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_max_iteration_tokens(mut self, max: u32) -> Self {
+        self.max_iteration_tokens = Some(max);
+        self
+    }
+
+    pub fn with_max_continuations(mut self, max: u32) -> Self {
+        self.max_continuations = max;
+        self
+    }
+
+    pub fn with_self_score(mut self, self_score: bool) -> Self {
+        self.self_score = self_score;
+        self
+    }
+
+    pub fn with_prompts(mut self, prompts: PromptRegistry) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    pub fn with_downshift(mut self, downshift: Downshift) -> Self {
+        self.downshift = Some(downshift);
+        self
+    }
+
+    pub fn with_ensemble(mut self, ensemble: Ensemble) -> Self {
+        self.ensemble = Some(ensemble);
+        self
+    }
+
+    pub fn with_context_windows(mut self, windows: ContextWindows) -> Self {
+        self.context_windows = windows;
+        self
+    }
+
+    pub fn with_prompt_dump(mut self, log: Arc<RawRequestLog>) -> Self {
+        self.prompt_dump = Some(log);
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: Arc<dyn EngineHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
 }
 
 /// Recursive exploration engine.
@@ -185,6 +334,18 @@ pub struct RecursiveEngine {
     pub(crate) graph_store: Option<crate::graph_tools::SharedGraphStore>,
     default_budget: BudgetConfig,
     pub(crate) work_dir: Option<PathBuf>,
+    exploration_hint: Option<String>,
+    streaming: bool,
+    stop_sequences: Vec<String>,
+    max_iteration_tokens: Option<u32>,
+    max_continuations: u32,
+    self_score: bool,
+    prompts: PromptRegistry,
+    downshift: Option<Downshift>,
+    ensemble: Option<Ensemble>,
+    context_windows: ContextWindows,
+    prompt_dump: Option<Arc<RawRequestLog>>,
+    hooks: Option<Arc<dyn EngineHooks>>,
     #[allow(dead_code)]
     temperature: Option<f32>,
     #[allow(dead_code)]
@@ -194,7 +355,7 @@ pub struct RecursiveEngine {
 impl RecursiveEngine {
     pub fn new(deps: EngineDeps, config: EngineConfig) -> Self {
         let file_system = deps.file_system();
-        let tool_executor = ToolExecutor::new(deps.tools.clone());
+        let tool_executor = ToolExecutor::new(deps.tools.clone()).with_limits(config.tool_limits);
         Self {
             backend: deps.backend,
             tools: deps.tools,
@@ -203,6 +364,18 @@ impl RecursiveEngine {
             graph_store: deps.graph_store,
             default_budget: config.budget,
             work_dir: config.work_dir,
+            exploration_hint: config.exploration_hint,
+            streaming: config.streaming,
+            stop_sequences: config.stop_sequences,
+            max_iteration_tokens: config.max_iteration_tokens,
+            max_continuations: config.max_continuations,
+            self_score: config.self_score,
+            prompts: config.prompts,
+            downshift: config.downshift,
+            ensemble: config.ensemble,
+            context_windows: config.context_windows,
+            prompt_dump: config.prompt_dump,
+            hooks: config.hooks,
             temperature: config.temperature,
             inject_system_prompt: config.inject_system_prompt,
         }
@@ -234,21 +407,111 @@ impl RecursiveEngine {
             is_recursive: request.is_recursive(),
             initial_message_count: request.messages.len(),
             system_prompt: request.system.as_ref().map(|s| s.to_text()),
+            category: Self::category(&request),
         };
-        muninn_tracing::start_span_with_data("rlm_cycle", &cycle_data);
+        muninn_tracing::traced("rlm_cycle", Some(&cycle_data), async {
+            let request = if request.is_recursive() {
+                let prompt_start = Instant::now();
+                let request = self.prepare_recursive_request(request);
+                muninn_tracing::add_timing_segment(
+                    TIMING_PROMPT_BUILD,
+                    prompt_start.elapsed().as_millis() as u64,
+                );
+                request
+            } else {
+                request
+            };
 
-        let request = if request.is_recursive() {
-            self.prepare_recursive_request(request)
-        } else {
-            request
-        };
+            let mut context = ExplorationContext::new(request, self.default_budget.clone());
+            let mut response = self.explore(&mut context).await?;
+            if let Some(ensemble) = &self.ensemble {
+                Self::ensemble_answer(ensemble, &context, &mut response).await;
+            }
+            if self.self_score {
+                self.score_answer(&context, &mut response).await;
+            }
+            if let Some(hooks) = &self.hooks {
+                hooks.on_final_answer(&context, &response);
+            }
+            Ok(response)
+        })
+        .await
+    }
 
-        let mut context = ExplorationContext::new(request, self.default_budget.clone());
-        self.run_exploration_loop(&mut context).await
+    /// Replace the answer with the better of it and the ensemble's.
+    async fn ensemble_answer(
+        ensemble: &Ensemble,
+        context: &ExplorationContext,
+        response: &mut CompletionResponse,
+    ) {
+        let answer = response.text();
+        if answer.trim().is_empty() {
+            return;
+        }
+        let picked = ensemble.pick(context, &response.model, answer).await;
+        response.content = vec![ContentBlock::Text {
+            text: picked,
+            cache_control: None,
+        }];
+    }
+
+    /// Grade the final answer, record an `rlm_quality` event and put the
+    /// score in the response's exploration metadata.
+    async fn score_answer(&self, context: &ExplorationContext, response: &mut CompletionResponse) {
+        let quality = quality::score_answer(
+            self.backend.as_ref(),
+            context.model(),
+            context.question().as_deref(),
+            &response.text(),
+        )
+        .await;
+        tracing::debug!(score = quality.score, "Exploration self-scored");
+        muninn_tracing::record_event("rlm_quality", Some(&quality));
+        if let Some(metadata) = &mut response.muninn {
+            metadata.quality_score = Some(quality.score);
+        }
+    }
+
+    /// The router category the request carries, if it names a known one.
+    fn category(request: &CompletionRequest) -> Option<RouteCategory> {
+        request
+            .muninn
+            .as_ref()
+            .and_then(|m| m.category.as_deref())
+            .and_then(RouteCategory::parse)
+    }
+
+    /// System prompt section naming the files the client's agent just read,
+    /// relative to the work dir, so references to "this file" have a referent.
+    fn visible_files_section(&self, request: &CompletionRequest) -> Option<String> {
+        let files = &request.muninn.as_ref()?.visible_files;
+        if files.is_empty() {
+            return None;
+        }
+        let mut section = String::from(
+            "## Files the user is looking at\n\n\
+             The user's agent just read these files, most recent first. When the question \
+             says \"this file\" or \"here\" without naming one, it means the first.\n",
+        );
+        for file in files {
+            let path = Path::new(file);
+            let path = self
+                .work_dir
+                .as_ref()
+                .and_then(|dir| path.strip_prefix(dir).ok())
+                .unwrap_or(path);
+            section.push_str(&format!("- {}\n", path.display()));
+        }
+        Some(section)
     }
 
     fn prepare_recursive_request(&self, mut request: CompletionRequest) -> CompletionRequest {
-        let tools = self.tools.available_tools();
+        let category = Self::category(&request);
+        let visible_files = self.visible_files_section(&request);
+        let mut tools = self.tools.available_tools();
+        if let Some(prompt) = category.and_then(|c| self.prompts.get(c)) {
+            tools.retain(|tool| prompt.allows(&tool.name));
+        }
 
         // Truncate to the last N user messages + intervening assistant/tool messages.
         // This gives conversational context without overwhelming Qwen's smaller context window
@@ -270,7 +533,7 @@ impl RecursiveEngine {
         // Claude Code's system prompt tells the model about Bash, Read, Edit, etc.
         // which confuses the RLM. We need our specialized exploration prompt.
         if self.backend.supports_native_tools() {
-            let mut system = CORE_RLM_BEHAVIOR.to_string();
+            let mut system = self.prompts.system_prompt(category);
             if let Some(tree) = self
                 .work_dir
                 .as_ref()
@@ -279,9 +542,17 @@ impl RecursiveEngine {
                 system.push_str("\n\n");
                 system.push_str(&tree);
             }
+            if let Some(hint) = &self.exploration_hint {
+                system.push_str("\n\n");
+                system.push_str(hint);
+            }
+            if let Some(files) = &visible_files {
+                system.push_str("\n\n");
+                system.push_str(files);
+            }
             request.system = Some(SystemPrompt::Text(system));
         } else {
-            let mut rlm_prompt = CORE_RLM_BEHAVIOR.to_string();
+            let mut rlm_prompt = self.prompts.system_prompt(category);
             let tool_defs = self.backend.format_tool_definitions(&tools);
             if !tool_defs.is_empty() {
                 rlm_prompt.push_str("\n\n");
@@ -291,6 +562,14 @@ impl RecursiveEngine {
                 rlm_prompt.push('\n');
                 rlm_prompt.push_str(instructions);
             }
+            if let Some(hint) = &self.exploration_hint {
+                rlm_prompt.push_str("\n\n");
+                rlm_prompt.push_str(hint);
+            }
+            if let Some(files) = &visible_files {
+                rlm_prompt.push_str("\n\n");
+                rlm_prompt.push_str(files);
+            }
             request.system = Some(SystemPrompt::Text(rlm_prompt));
         }
 
@@ -303,57 +582,231 @@ impl RecursiveEngine {
         request
     }
 
+    /// One LLM turn, streamed or not depending on config. Goes to the
+    /// fallback backend once the exploration has downshifted.
+    async fn call_backend(
+        &self,
+        context: &ExplorationContext,
+        mut request: CompletionRequest,
+    ) -> Result<CompletionResponse> {
+        let backend = match (&self.downshift, context.downshifted_to()) {
+            (Some(downshift), Some(model)) => {
+                request.model = model.to_string();
+                &downshift.backend
+            }
+            _ => &self.backend,
+        };
+        if self.streaming {
+            let stream = backend.complete_stream(request).await?;
+            crate::backend::collect_stream(stream).await
+        } else {
+            backend.complete(request).await
+        }
+    }
+
+    /// Move the remaining turns to the fallback backend once the
+    /// exploration has used its share of the token budget.
+    fn maybe_downshift(&self, context: &mut ExplorationContext) {
+        let Some(downshift) = &self.downshift else {
+            return;
+        };
+        if context.downshifted_to().is_none()
+            && downshift.is_due(context.tokens_used(), context.budget_config().max_tokens)
+        {
+            self.downshift(context, downshift, "threshold");
+        }
+    }
+
+    /// The token budget ran out: rather than failing, give the fallback
+    /// backend one last turn to answer from what was gathered. Returns
+    /// false when there is no fallback or the last turn was already given.
+    fn downshift_for_final_turn(&self, context: &mut ExplorationContext, error: &RlmError) -> bool {
+        let Some(downshift) = &self.downshift else {
+            return false;
+        };
+        let out_of_tokens = matches!(
+            error,
+            RlmError::BudgetExceeded(e) if e.budget_type == BudgetType::Tokens
+        );
+        if !out_of_tokens || context.is_final_turn_forced() {
+            return false;
+        }
+        self.downshift(context, downshift, "budget_exceeded");
+        context.force_final_turn();
+        true
+    }
+
+    fn downshift(&self, context: &mut ExplorationContext, downshift: &Downshift, reason: &str) {
+        tracing::info!(
+            model = %downshift.model,
+            reason,
+            tokens_used = context.tokens_used(),
+            "Downshifting exploration to fallback model"
+        );
+        let data = DownshiftTraceData {
+            model: downshift.model.clone(),
+            reason: reason.to_string(),
+            depth: context.depth(),
+            tokens_used: context.tokens_used(),
+            token_limit: context.budget_config().max_tokens,
+        };
+        muninn_tracing::record_event("rlm_downshift", Some(&data));
+        context.downshift(&downshift.model);
+    }
+
+    /// Run the exploration loop under the `max_duration_secs` deadline.
+    /// When time runs out, even in the middle of a backend or tool call,
+    /// answer from what was gathered so far instead of failing.
+    async fn explore(&self, context: &mut ExplorationContext) -> Result<CompletionResponse> {
+        let result = match context.budget_config().max_duration_secs {
+            Some(secs) => {
+                let remaining = Duration::from_secs(secs).saturating_sub(context.elapsed());
+                match tokio::time::timeout(remaining, self.run_exploration_loop(context)).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        self.record_rlm_completion(context, "timeout", false);
+                        return Ok(self.partial_answer(context).await);
+                    }
+                }
+            }
+            None => self.run_exploration_loop(context).await,
+        };
+        match result {
+            Err(RlmError::BudgetExceeded(e)) if e.budget_type == BudgetType::Duration => {
+                Ok(self.partial_answer(context).await)
+            }
+            result => result,
+        }
+    }
+
+    /// Ask for an answer from what the exploration gathered before its
+    /// time ran out. The call gets [`PARTIAL_ANSWER_TIMEOUT`] of its own;
+    /// if it fails too, the answer says how far the exploration got.
+    async fn partial_answer(&self, context: &ExplorationContext) -> CompletionResponse {
+        tracing::info!(
+            tool_calls = context.tool_call_count(),
+            "Exploration timed out, writing partial answer"
+        );
+        let mut request = context.build_request();
+        request.messages.push(Message::user(PARTIAL_ANSWER_PROMPT));
+        let reply =
+            tokio::time::timeout(PARTIAL_ANSWER_TIMEOUT, self.call_backend(context, request)).await;
+        let (response, answer) = match reply {
+            Ok(Ok(response)) => {
+                let text = response.text();
+                (response, text)
+            }
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "Partial answer failed");
+                (Self::empty_response(context), String::new())
+            }
+            Err(_) => {
+                tracing::warn!("Partial answer timed out");
+                (Self::empty_response(context), String::new())
+            }
+        };
+        let answer = if answer.trim().is_empty() {
+            format!(
+                "No answer could be written in time. The exploration made {} tool calls across {} iterations.",
+                context.tool_call_count(),
+                context.depth()
+            )
+        } else {
+            answer
+        };
+        let mut response = context.finalize_with_answer(
+            response,
+            format!("[Partial answer: exploration timed out]\n\n{}", answer),
+        );
+        if let Some(metadata) = &mut response.muninn {
+            metadata.partial = Some("timeout".to_string());
+        }
+        response
+    }
+
+    fn empty_response(context: &ExplorationContext) -> CompletionResponse {
+        CompletionResponse::new(
+            "partial",
+            context.model(),
+            Vec::new(),
+            StopReason::EndTurn,
+            Usage::default(),
+        )
+    }
+
     async fn run_exploration_loop(
         &self,
         context: &mut ExplorationContext,
     ) -> Result<CompletionResponse> {
         loop {
             if let Err(e) = context.check_budget() {
-                self.end_rlm_span(context, "budget_exceeded", false);
-                return Err(e);
+                if !self.downshift_for_final_turn(context, &e) {
+                    self.record_rlm_completion(context, "budget_exceeded", false);
+                    return Err(e);
+                }
+            } else {
+                self.maybe_downshift(context);
             }
 
             if context.is_last_turn() {
                 context.inject_last_turn_warning();
             }
 
-            let iter_request = context.build_request();
+            if let Some(hooks) = &self.hooks
+                && let HookDecision::Stop(reason) = hooks.on_iteration_start(context)
+            {
+                return Ok(self.stop_for_hook(context, reason));
+            }
+
+            let prompt_start = Instant::now();
+            let (iter_request, clamped) = self.fit_context_window(context);
+            let prompt_ms = prompt_start.elapsed().as_millis() as u64;
+            self.dump_prompt(context, &iter_request);
+            muninn_tracing::add_timing_segment(TIMING_PROMPT_BUILD, prompt_ms);
             let llm_start = Instant::now();
-            let response = match self.backend.complete(iter_request.clone()).await {
+            let result = self.call_backend(context, iter_request.clone()).await;
+            let llm_ms = llm_start.elapsed().as_millis() as u64;
+            muninn_tracing::add_timing_segment(TIMING_BACKEND_LATENCY, llm_ms);
+            let mut response = match result {
                 Ok(r) => r,
                 Err(e) => {
-                    self.end_rlm_span(context, "llm_error", false);
+                    self.record_rlm_completion(context, "llm_error", false);
                     return Err(e);
                 }
             };
 
+            let repairs = self.tool_executor.repair_tool_calls(&mut response);
             let iteration_data = RlmIterationTraceData {
                 depth: context.depth(),
                 is_last_turn: context.is_last_turn(),
                 message_count: iter_request.messages.len(),
-                llm_latency_ms: llm_start.elapsed().as_millis() as u64,
+                llm_latency_ms: llm_ms,
                 input_tokens: response.usage.input_tokens,
                 output_tokens: response.usage.output_tokens,
                 stop_reason: response.stop_reason.as_ref().map(|r| format!("{:?}", r)),
+                tool_call_repairs: repairs.attempts,
+                tool_call_repair_failures: repairs.failures,
             };
-            muninn_tracing::start_span_with_data("rlm_iteration", &iteration_data);
-            muninn_tracing::end_span_ok();
+            let iteration = muninn_tracing::span_with_data("rlm_iteration", &iteration_data);
+            iteration.timing_segment(TIMING_PROMPT_BUILD, prompt_ms);
+            iteration.timing_segment(TIMING_BACKEND_LATENCY, llm_ms);
+            iteration.ok();
 
             context.add_usage(&response.usage);
 
             if let Some(answer) = Self::extract_final_pattern(&response) {
-                self.end_rlm_span(context, "final_pattern", true);
+                self.record_rlm_completion(context, "final_pattern", true);
                 return Ok(context.finalize_with_answer(response, answer));
             }
 
             match response.stop_reason {
                 Some(StopReason::EndTurn) | None => {
-                    self.end_rlm_span(context, "end_turn", false);
+                    self.record_rlm_completion(context, "end_turn", false);
                     return Ok(context.finalize(response));
                 }
                 Some(StopReason::ToolUse) => {
                     if let Some(answer) = Self::extract_final_answer_tool(&response) {
-                        self.end_rlm_span(context, "final_answer_tool", true);
+                        self.record_rlm_completion(context, "final_answer_tool", true);
                         return Ok(context.finalize_with_answer(response, answer));
                     }
                     if context.would_exceed_depth() {
@@ -362,26 +815,255 @@ impl RecursiveEngine {
                             context.tool_call_count(),
                             context.depth()
                         );
-                        self.end_rlm_span(context, "forced_termination", true);
+                        self.record_rlm_completion(context, "forced_termination", true);
                         return Ok(context.finalize_with_answer(response, msg));
                     }
-                    let results = self.tool_executor.execute_tools(&response).await?;
+                    let refused = self.refused_tool_calls(context, &response);
+                    let tools_start = Instant::now();
+                    let results = self
+                        .tool_executor
+                        .execute_tools_refusing(&response, context.tool_usage_mut(), &refused)
+                        .await;
+                    muninn_tracing::add_timing_segment(
+                        TIMING_TOOL_EXECUTION,
+                        tools_start.elapsed().as_millis() as u64,
+                    );
+                    let results = results?;
+                    let decision = match &self.hooks {
+                        Some(hooks) => hooks.on_tool_result(context, &results),
+                        None => HookDecision::Continue,
+                    };
                     context.add_tool_interaction(response, results);
                     context.increment_depth();
+                    if let HookDecision::Stop(reason) = decision {
+                        return Ok(self.stop_for_hook(context, reason));
+                    }
+                }
+                Some(StopReason::MaxTokens) if clamped => {
+                    self.cut_off_turn(context, response, "max_iteration_tokens");
                 }
                 Some(StopReason::MaxTokens) => {
-                    self.end_rlm_span(context, "max_tokens", false);
+                    let response = self.continue_answer(context, response).await;
+                    let reason = match response.stop_reason {
+                        Some(StopReason::MaxTokens) => "max_tokens",
+                        _ => "continued",
+                    };
+                    self.record_rlm_completion(context, reason, false);
                     return Ok(context.finalize(response));
                 }
+                Some(StopReason::StopSequence) if !self.stop_sequences.is_empty() => {
+                    self.cut_off_turn(context, response, "stop_sequence");
+                }
                 Some(StopReason::StopSequence) => {
-                    self.end_rlm_span(context, "stop_sequence", false);
+                    self.record_rlm_completion(context, "stop_sequence", false);
                     return Ok(context.finalize(response));
                 }
             }
         }
     }
 
-    fn end_rlm_span(&self, context: &ExplorationContext, reason: &str, has_final: bool) {
+    /// A hook stopped the exploration: answer with its reason.
+    /// Ask the hooks about each tool call in `response` before any of them
+    /// runs. Returns the refused calls' ids with the hook's reason.
+    fn refused_tool_calls(
+        &self,
+        context: &ExplorationContext,
+        response: &CompletionResponse,
+    ) -> HashMap<String, String> {
+        let Some(hooks) = &self.hooks else {
+            return HashMap::new();
+        };
+        response
+            .tool_uses()
+            .into_iter()
+            .filter_map(|call| match hooks.on_tool_call(context, &call) {
+                ToolCallDecision::Run => None,
+                ToolCallDecision::Refuse(reason) => {
+                    tracing::info!(tool = %call.name, reason = %reason, "Tool call refused by hook");
+                    Some((call.id.clone(), reason))
+                }
+            })
+            .collect()
+    }
+
+    fn stop_for_hook(&self, context: &ExplorationContext, reason: String) -> CompletionResponse {
+        tracing::info!(reason = %reason, depth = context.depth(), "Exploration stopped by hook");
+        self.record_rlm_completion(context, "hook_stop", false);
+        context.finalize_with_answer(
+            Self::empty_response(context),
+            format!("[Exploration stopped: {}]", reason),
+        )
+    }
+
+    /// Build the next turn's request, trimming the oldest tool interactions
+    /// until it fits the model's context window. Returns the request and
+    /// whether the output clamp lowered its `max_tokens`.
+    ///
+    /// A turn that still doesn't fit once nothing is left to trim is sent
+    /// as is.
+    fn fit_context_window(&self, context: &mut ExplorationContext) -> (CompletionRequest, bool) {
+        let mut request = context.build_request();
+        let mut clamped = self.apply_output_guards(&mut request);
+        let model = context
+            .downshifted_to()
+            .unwrap_or(context.model())
+            .to_string();
+        let Some(window) = self.context_windows.get(&model) else {
+            return (request, clamped);
+        };
+        let estimated_before = estimate_request_tokens(&request);
+        let mut estimate = estimated_before;
+        let (mut elided, mut dropped) = (0, 0);
+        while estimate > window {
+            match context.shrink_oldest_interaction() {
+                Some(Shrink::Elided) => elided += 1,
+                Some(Shrink::Dropped) => dropped += 1,
+                None => break,
+            }
+            request = context.build_request();
+            clamped = self.apply_output_guards(&mut request);
+            estimate = estimate_request_tokens(&request);
+        }
+        if elided + dropped > 0 {
+            tracing::info!(
+                model = %model,
+                context_window = window,
+                estimated_before,
+                estimated_after = estimate,
+                elided,
+                dropped,
+                "Trimmed exploration to fit the context window"
+            );
+            let data = ContextTrimTraceData {
+                model,
+                context_window: window,
+                estimated_before,
+                estimated_after: estimate,
+                elided,
+                dropped,
+                depth: context.depth(),
+            };
+            muninn_tracing::record_event("rlm_context_trimmed", Some(&data));
+        } else if estimate > window {
+            tracing::warn!(
+                model = %model,
+                context_window = window,
+                estimated = estimate,
+                "Exploration turn exceeds the context window with nothing left to trim"
+            );
+        }
+        (request, clamped)
+    }
+
+    /// Append the turn's prepared prompt to the prompt dump, if enabled.
+    fn dump_prompt(&self, context: &ExplorationContext, request: &CompletionRequest) {
+        let Some(dump) = &self.prompt_dump else {
+            return;
+        };
+        let tools: Vec<&str> = request.tools.iter().map(|t| t.name.as_str()).collect();
+        dump.append(serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "trace_id": muninn_tracing::current_trace_id(),
+            "depth": context.depth(),
+            "model": context.downshifted_to().unwrap_or(&request.model),
+            "max_tokens": request.max_tokens,
+            "system": request.system.as_ref().map(|s| s.to_text()),
+            "tools": tools,
+            "messages": request.messages,
+        }));
+    }
+
+    /// Add the configured stop sequences and per-turn output clamp to a
+    /// turn's request. Returns whether the clamp lowered `max_tokens`.
+    fn apply_output_guards(&self, request: &mut CompletionRequest) -> bool {
+        for stop in &self.stop_sequences {
+            if !request.stop_sequences.contains(stop) {
+                request.stop_sequences.push(stop.clone());
+            }
+        }
+        match self.max_iteration_tokens {
+            Some(max) if max < request.max_tokens => {
+                request.max_tokens = max;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// A turn was cut short by an output guard: keep what the model wrote,
+    /// tell it to act instead of narrating, and move to the next turn.
+    fn cut_off_turn(
+        &self,
+        context: &mut ExplorationContext,
+        response: CompletionResponse,
+        guard: &str,
+    ) {
+        tracing::debug!(guard, depth = context.depth(), "Exploration turn cut off");
+        muninn_tracing::record_event(
+            "rlm_turn_cut_off",
+            Some(&serde_json::json!({ "guard": guard, "depth": context.depth() })),
+        );
+        context.add_cut_off_turn(response);
+        context.increment_depth();
+    }
+
+    /// Ask for the rest of an answer that stopped at `max_tokens`, up to
+    /// `max_continuations` times, and stitch the parts together. Stops
+    /// early when a part ends on its own, calls a tool, the budget runs
+    /// out or the backend fails; whatever was written so far is kept.
+    async fn continue_answer(
+        &self,
+        context: &mut ExplorationContext,
+        mut response: CompletionResponse,
+    ) -> CompletionResponse {
+        let mut answer = response.text();
+        while response.stop_reason == Some(StopReason::MaxTokens)
+            && context.continuations() < self.max_continuations
+            && !answer.trim().is_empty()
+            && context.check_budget().is_ok()
+        {
+            let mut request = context.build_request();
+            request
+                .messages
+                .push(Message::assistant_blocks(vec![ContentBlock::Text {
+                    text: answer.clone(),
+                    cache_control: None,
+                }]));
+            request.messages.push(Message::user(CONTINUATION_PROMPT));
+            context.add_continuation();
+            muninn_tracing::record_event(
+                "rlm_continuation",
+                Some(&serde_json::json!({
+                    "continuation": context.continuations(),
+                    "answer_chars": answer.len(),
+                })),
+            );
+            let part = match self.call_backend(context, request).await {
+                Ok(part) => part,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Continuation failed, returning the cut-off answer");
+                    break;
+                }
+            };
+            context.add_usage(&part.usage);
+            if part.has_tool_use() {
+                break;
+            }
+            answer.push_str(&part.text());
+            response.usage.input_tokens += part.usage.input_tokens;
+            response.usage.output_tokens += part.usage.output_tokens;
+            response.stop_reason = part.stop_reason;
+        }
+        if context.continuations() > 0 {
+            response.content = vec![ContentBlock::Text {
+                text: answer,
+                cache_control: None,
+            }];
+        }
+        response
+    }
+
+    fn record_rlm_completion(&self, context: &ExplorationContext, reason: &str, has_final: bool) {
         let data = RlmCompletionTraceData {
             termination_reason: reason.to_string(),
             depth_reached: context.depth(),
@@ -391,7 +1073,6 @@ impl RecursiveEngine {
             has_final_answer: has_final,
         };
         muninn_tracing::record_event("rlm_completion", Some(&data));
-        muninn_tracing::end_span_ok();
     }
 
     fn extract_final_pattern(response: &CompletionResponse) -> Option<String> {
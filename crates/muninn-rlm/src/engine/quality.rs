@@ -0,0 +1,212 @@
+//! Self-scoring of finished explorations.
+//!
+//! With [`EngineConfig::self_score`](super::EngineConfig::self_score) set,
+//! each answer gets a 0–1 quality score from two checks:
+//!
+//! - **cites files**: the answer names at least one file path
+//!   (`src/lib.rs`, `main.py:42`);
+//! - **answers the question**: one short call to the exploration backend
+//!   grading how completely the answer addresses the question.
+//!
+//! The score combines the two by [`CITATION_WEIGHT`] and [`ANSWER_WEIGHT`]. When
+//! the grading call fails or doesn't reply with a number, the score rests
+//! on the citation check alone.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::backend::LLMBackend;
+use crate::types::{CompletionRequest, Message};
+
+/// Share of the score for citing files.
+pub const CITATION_WEIGHT: f64 = 0.3;
+/// Share of the score for answering the question.
+pub const ANSWER_WEIGHT: f64 = 0.7;
+
+/// Characters of question and answer sent to the grader.
+const MAX_GRADED_CHARS: usize = 4000;
+
+const GRADER_PROMPT: &str = "You grade answers to questions about a codebase. \
+Reply with a single number from 0 to 1 for how completely the answer addresses the question: \
+1 means fully answered, 0 means not answered or off-topic. Reply with the number only.";
+
+/// Extensions that make a slash-less token like `main.rs` count as a file.
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "ts", "tsx", "js", "jsx", "go", "java", "kt", "c", "h", "cc", "cpp", "hpp", "cs",
+    "rb", "php", "swift", "scala", "sh", "toml", "json", "yaml", "yml", "md", "sql", "proto",
+];
+
+static PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\w./-]*\w\.([A-Za-z][A-Za-z0-9]{0,4})\b(?::\d+(?:-\d+)?)?").unwrap()
+});
+
+static NUMBER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d*\.?\d+").unwrap());
+
+/// Quality assessment of one answer. Also the data of the `rlm_quality`
+/// trace event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QualityScore {
+    /// Weighted score from 0 to 1.
+    pub score: f64,
+    /// Whether the answer names a file.
+    pub cites_files: bool,
+    /// The grader's 0–1 verdict, if it gave one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answers_question: Option<f64>,
+}
+
+/// Score `answer` to `question`, asking `backend` (with `model`) to grade
+/// it. An empty answer scores 0 without a grading call.
+pub async fn score_answer(
+    backend: &dyn LLMBackend,
+    model: &str,
+    question: Option<&str>,
+    answer: &str,
+) -> QualityScore {
+    let cites_files = cites_files(answer);
+    let answers_question = if answer.trim().is_empty() {
+        Some(0.0)
+    } else if let Some(question) = question {
+        grade(backend, model, question, answer).await
+    } else {
+        None
+    };
+    let citation = if cites_files { 1.0 } else { 0.0 };
+    let score = match answers_question {
+        Some(answered) => CITATION_WEIGHT * citation + ANSWER_WEIGHT * answered,
+        None => citation,
+    };
+    QualityScore {
+        score,
+        cites_files,
+        answers_question,
+    }
+}
+
+/// Whether `text` names a file (see [`cited_paths`]).
+pub fn cites_files(text: &str) -> bool {
+    !cited_paths(text).is_empty()
+}
+
+/// File paths named in `text`, in order of first mention and with any
+/// `:line` suffix: paths with a directory, and file names with a
+/// [`CODE_EXTENSIONS`] extension.
+pub fn cited_paths(text: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for c in PATH_RE.captures_iter(text) {
+        let is_file =
+            c[0].contains('/') || CODE_EXTENSIONS.contains(&c[1].to_ascii_lowercase().as_str());
+        if is_file && !paths.iter().any(|p| p == &c[0]) {
+            paths.push(c[0].to_string());
+        }
+    }
+    paths
+}
+
+async fn grade(backend: &dyn LLMBackend, model: &str, question: &str, answer: &str) -> Option<f64> {
+    let prompt = format!(
+        "Question:\n{}\n\nAnswer:\n{}",
+        truncate(question),
+        truncate(answer)
+    );
+    let mut request =
+        CompletionRequest::new(model, vec![Message::user(prompt)], 16).with_system(GRADER_PROMPT);
+    request.temperature = Some(0.0);
+    let reply = match backend.complete(request).await {
+        Ok(response) => response.text(),
+        Err(e) => {
+            tracing::debug!(error = %e, "Self-score grading failed");
+            return None;
+        }
+    };
+    let verdict = NUMBER_RE
+        .find(&reply)
+        .and_then(|m| m.as_str().parse::<f64>().ok())
+        .filter(|v| (0.0..=1.0).contains(v));
+    if verdict.is_none() {
+        tracing::debug!(reply = %reply, "Self-score grader gave no score");
+    }
+    verdict
+}
+
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(MAX_GRADED_CHARS) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+    use crate::types::{CompletionResponse, ContentBlock, StopReason, Usage};
+
+    fn reply(text: &str) -> CompletionResponse {
+        CompletionResponse::new(
+            "msg",
+            "grader",
+            vec![ContentBlock::Text {
+                text: text.to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(50, 2),
+        )
+    }
+
+    #[test]
+    fn test_cites_files() {
+        assert!(cites_files("It's parsed in src/config.rs:42."));
+        assert!(cites_files("See main.py"));
+        assert!(cites_files("under docs/setup.txt"));
+        assert!(!cites_files(
+            "The config is loaded at startup, e.g. from disk."
+        ));
+        assert!(!cites_files("Version 1.2 added it."));
+        assert_eq!(
+            cited_paths("See src/lib.rs:10-20, then lib.rs and src/lib.rs:10-20 again."),
+            ["src/lib.rs:10-20", "lib.rs"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_score_combines_citation_and_grade() {
+        let backend = MockBackend::new(vec![reply("0.5")]);
+        let score = score_answer(
+            &backend,
+            "m",
+            Some("Where is config parsed?"),
+            "In src/config.rs",
+        )
+        .await;
+        assert_eq!(score.answers_question, Some(0.5));
+        assert!(score.cites_files);
+        assert!((score.score - (CITATION_WEIGHT + ANSWER_WEIGHT * 0.5)).abs() < 1e-9);
+
+        let sent = &backend.requests()[0];
+        assert_eq!(sent.max_tokens, 16);
+        assert!(
+            sent.messages[0]
+                .content
+                .to_text()
+                .contains("Where is config parsed?")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unusable_grade_falls_back_to_citation() {
+        let backend = MockBackend::new(vec![reply("Looks good to me")]);
+        let score = score_answer(&backend, "m", Some("q"), "It's in lib.rs").await;
+        assert_eq!(score.answers_question, None);
+        assert_eq!(score.score, 1.0);
+
+        // No grading call for an empty answer
+        let backend = MockBackend::new(vec![]);
+        let score = score_answer(&backend, "m", Some("q"), "  ").await;
+        assert_eq!(score.score, 0.0);
+        assert!(backend.requests().is_empty());
+    }
+}
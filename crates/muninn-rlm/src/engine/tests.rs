@@ -3,6 +3,7 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use muninn_tracing::testing::GoldenTrace;
 use serde_json::json;
 
 use crate::backend::MockBackend;
@@ -95,6 +96,53 @@ async fn test_tool_use_loop() {
     assert_eq!(tool_env.execution_count(), 1);
 }
 
+/// Pin the engine's observable trace (cycles, iterations, tool executions)
+/// for the tool-use loop against a golden snapshot.
+#[tokio::test]
+async fn test_tool_use_loop_golden_trace() {
+    let responses = vec![
+        CompletionResponse::new(
+            "msg_1",
+            "model",
+            vec![ContentBlock::ToolUse {
+                id: "tool_1".to_string(),
+                name: "read_file".to_string(),
+                input: json!({"path": "/foo.rs"}),
+                cache_control: None,
+            }],
+            StopReason::ToolUse,
+            Usage::new(20, 15),
+        ),
+        CompletionResponse::new(
+            "msg_2",
+            "model",
+            vec![ContentBlock::Text {
+                text: "The file contains: test content".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(50, 30),
+        ),
+    ];
+    let tools = vec![ToolDefinition::new(
+        "read_file",
+        "Read a file",
+        json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+    )];
+    let (engine, tool_env) = create_engine(responses, tools);
+    tool_env.set_response("read_file", "test content");
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100);
+    let (response, trace) = muninn_tracing::with_tracing(engine.complete(request)).await;
+    assert!(response.is_ok());
+
+    GoldenTrace::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/golden/engine_tool_loop.json"
+    ))
+    .assert_matches(&trace);
+}
+
 #[tokio::test]
 async fn test_multiple_tool_calls() {
     let responses = vec![
@@ -202,6 +250,325 @@ fn test_is_recursive() {
     assert!(recursive.is_recursive());
 }
 
+#[tokio::test]
+async fn test_tool_use_loop_streaming() {
+    let responses = vec![
+        CompletionResponse::new(
+            "msg_1",
+            "model",
+            vec![ContentBlock::ToolUse {
+                id: "tool_1".to_string(),
+                name: "read_file".to_string(),
+                input: json!({"path": "/foo.rs"}),
+                cache_control: None,
+            }],
+            StopReason::ToolUse,
+            Usage::new(20, 15),
+        ),
+        CompletionResponse::new(
+            "msg_2",
+            "model",
+            vec![ContentBlock::Text {
+                text: "Streamed answer".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(50, 30),
+        ),
+    ];
+    let backend = Arc::new(MockBackend::new(responses));
+    let tool_env = Arc::new(MockToolEnvironment::new(vec![ToolDefinition::new(
+        "read_file",
+        "Read a file",
+        json!({"type": "object"}),
+    )]));
+    tool_env.set_response("read_file", "test content");
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend, tool_env.clone()),
+        EngineConfig::default().with_streaming(true),
+    );
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.text(), "Streamed answer");
+    assert_eq!(tool_env.execution_count(), 1);
+}
+
+#[tokio::test]
+async fn test_malformed_tool_input_repaired() {
+    let responses = vec![
+        CompletionResponse::new(
+            "msg_1",
+            "model",
+            vec![ContentBlock::ToolUse {
+                id: "tool_1".to_string(),
+                name: "read_file".to_string(),
+                // What a backend keeps when the arguments don't parse
+                input: json!("{'path': '/foo.rs', 'limit': '20'} I'll read it now."),
+                cache_control: None,
+            }],
+            StopReason::ToolUse,
+            Usage::new(20, 15),
+        ),
+        CompletionResponse::new(
+            "msg_2",
+            "model",
+            vec![ContentBlock::Text {
+                text: "Done".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(50, 30),
+        ),
+    ];
+    let tools = vec![ToolDefinition::new(
+        "read_file",
+        "Read a file",
+        json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}, "limit": {"type": "integer"}}
+        }),
+    )];
+    let (engine, tool_env) = create_engine(responses, tools);
+    tool_env.set_response("read_file", "test content");
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100);
+    let (response, trace) = muninn_tracing::with_tracing(engine.complete(request)).await;
+    assert!(response.is_ok());
+
+    let executions = tool_env.executions();
+    assert_eq!(executions[0].input, json!({"path": "/foo.rs", "limit": 20}));
+
+    let first_iteration = trace.spans[0]
+        .children
+        .iter()
+        .find(|s| s.name == "rlm_iteration")
+        .and_then(|s| s.data.as_ref())
+        .unwrap();
+    assert_eq!(first_iteration["tool_call_repairs"], 1);
+    assert_eq!(first_iteration["tool_call_repair_failures"], 0);
+}
+
+#[tokio::test]
+async fn test_output_guards_cut_off_rambling_turn() {
+    let responses = vec![
+        CompletionResponse::new(
+            "msg_1",
+            "model",
+            vec![ContentBlock::Text {
+                text: "First, let me think about every possible approach...".to_string(),
+                cache_control: None,
+            }],
+            StopReason::MaxTokens,
+            Usage::new(20, 64),
+        ),
+        CompletionResponse::new(
+            "msg_2",
+            "model",
+            vec![ContentBlock::Text {
+                text: "Short answer".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(50, 5),
+        ),
+    ];
+    let backend = Arc::new(MockBackend::new(responses));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), Arc::new(MockToolEnvironment::new(vec![]))),
+        EngineConfig::default()
+            .with_max_iteration_tokens(64)
+            .with_stop_sequences(["\nObservation:"]),
+    );
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Explain")], 4096);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.text(), "Short answer");
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].max_tokens, 64);
+    assert_eq!(requests[0].stop_sequences, vec!["\nObservation:"]);
+    let nudge = requests[1].messages.last().unwrap().content.to_text();
+    assert!(nudge.contains("[Output cut off]"));
+}
+
+fn text_response(
+    id: &str,
+    text: &str,
+    stop_reason: StopReason,
+    usage: Usage,
+) -> CompletionResponse {
+    CompletionResponse::new(
+        id,
+        "model",
+        vec![ContentBlock::Text {
+            text: text.to_string(),
+            cache_control: None,
+        }],
+        stop_reason,
+        usage,
+    )
+}
+
+#[tokio::test]
+async fn test_cut_off_answer_is_continued_and_stitched() {
+    let responses = vec![
+        text_response(
+            "msg_1",
+            "The parser lives in ",
+            StopReason::MaxTokens,
+            Usage::new(40, 50),
+        ),
+        text_response(
+            "msg_2",
+            "src/parse.rs and ",
+            StopReason::MaxTokens,
+            Usage::new(60, 50),
+        ),
+        text_response(
+            "msg_3",
+            "handles escapes.",
+            StopReason::EndTurn,
+            Usage::new(70, 10),
+        ),
+    ];
+    let backend = Arc::new(MockBackend::new(responses));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), Arc::new(MockToolEnvironment::new(vec![]))),
+        EngineConfig::default(),
+    );
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Where?")], 50);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(
+        response.text(),
+        "The parser lives in src/parse.rs and handles escapes."
+    );
+    assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+    assert_eq!(response.usage.output_tokens, 110);
+    assert_eq!(response.muninn.unwrap().continuations, Some(2));
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 3);
+    let messages = &requests[2].messages;
+    assert_eq!(
+        messages[messages.len() - 2].content.to_text(),
+        "The parser lives in src/parse.rs and "
+    );
+    assert!(
+        messages
+            .last()
+            .unwrap()
+            .content
+            .to_text()
+            .contains("cut off")
+    );
+}
+
+#[tokio::test]
+async fn test_continuations_stop_at_the_limit() {
+    let responses = vec![
+        text_response(
+            "msg_1",
+            "Part one, ",
+            StopReason::MaxTokens,
+            Usage::new(40, 50),
+        ),
+        text_response(
+            "msg_2",
+            "part two",
+            StopReason::MaxTokens,
+            Usage::new(60, 50),
+        ),
+    ];
+    let backend = Arc::new(MockBackend::new(responses));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), Arc::new(MockToolEnvironment::new(vec![]))),
+        EngineConfig::default().with_max_continuations(1),
+    );
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Explain")], 50);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.text(), "Part one, part two");
+    assert_eq!(response.stop_reason, Some(StopReason::MaxTokens));
+    assert_eq!(response.muninn.unwrap().continuations, Some(1));
+    assert_eq!(backend.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_output_clamp_above_request_max_tokens_is_not_a_cut_off() {
+    let responses = vec![CompletionResponse::new(
+        "msg_1",
+        "model",
+        vec![ContentBlock::Text {
+            text: "Truncated by the caller's own limit".to_string(),
+            cache_control: None,
+        }],
+        StopReason::MaxTokens,
+        Usage::new(20, 50),
+    )];
+    let backend = Arc::new(MockBackend::new(responses));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), Arc::new(MockToolEnvironment::new(vec![]))),
+        EngineConfig::default().with_max_iteration_tokens(1024),
+    );
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Explain")], 50);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.stop_reason, Some(StopReason::MaxTokens));
+    assert_eq!(backend.requests()[0].max_tokens, 50);
+}
+
+#[tokio::test]
+async fn test_self_score_grades_the_answer() {
+    let text = |text: &str| {
+        CompletionResponse::new(
+            "msg",
+            "model",
+            vec![ContentBlock::Text {
+                text: text.to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        )
+    };
+    let backend = Arc::new(MockBackend::new(vec![
+        text("Config is parsed in src/config.rs"),
+        text("0.9"),
+    ]));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), Arc::new(MockToolEnvironment::new(vec![]))),
+        EngineConfig::default().with_self_score(true),
+    );
+
+    let request = CompletionRequest::new(
+        "test-model",
+        vec![Message::user("Where is the config parsed?")],
+        100,
+    );
+    let (response, trace) = muninn_tracing::with_tracing(engine.complete(request)).await;
+    let response = response.unwrap();
+    assert_eq!(response.text(), "Config is parsed in src/config.rs");
+    let score = response.muninn.unwrap().quality_score.unwrap();
+    assert!((score - 0.93).abs() < 1e-9, "{score}");
+
+    let grading = &backend.requests()[1];
+    assert!(
+        grading.messages[0]
+            .content
+            .to_text()
+            .contains("Where is the config parsed?")
+    );
+    let event = trace.spans[0]
+        .events
+        .iter()
+        .find(|e| e.name == "rlm_quality")
+        .expect("rlm_quality event");
+    assert_eq!(event.data.as_ref().unwrap()["cites_files"], json!(true));
+}
+
 #[test]
 fn test_engine_deps_creation() {
     let backend = Arc::new(MockBackend::new(vec![]));
@@ -321,3 +688,561 @@ fn test_from_components_convenience() {
     let _engine = RecursiveEngine::from_components(backend, tools);
     // If it compiles and doesn't panic, it works
 }
+
+#[tokio::test]
+async fn test_exploration_hint_in_system_prompt() {
+    let backend = Arc::new(MockBackend::with_text("done"));
+    let tools: Arc<dyn crate::tools::ToolEnvironment> =
+        Arc::new(crate::tools::EmptyToolEnvironment);
+    let config =
+        EngineConfig::default().with_exploration_hint("## Prior exploration\n\n- src/a.rs");
+    let engine = RecursiveEngine::new(EngineDeps::new(backend.clone(), tools), config);
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Hi")], 100)
+        .with_muninn(MuninnConfig::recursive());
+    engine.complete(request).await.unwrap();
+
+    let system = backend.requests()[0].system.as_ref().unwrap().to_text();
+    assert!(
+        system.ends_with("## Prior exploration\n\n- src/a.rs"),
+        "{}",
+        system
+    );
+}
+
+#[tokio::test]
+async fn test_visible_files_in_system_prompt() {
+    let backend = Arc::new(MockBackend::new(vec![
+        CompletionResponse::new(
+            "msg",
+            "model",
+            vec![ContentBlock::Text {
+                text: "done".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        );
+        2
+    ]));
+    let tools: Arc<dyn crate::tools::ToolEnvironment> =
+        Arc::new(crate::tools::EmptyToolEnvironment);
+    let config = EngineConfig::default().with_work_dir("/nonexistent/repo");
+    let engine = RecursiveEngine::new(EngineDeps::new(backend.clone(), tools), config);
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Hi")], 100).with_muninn(
+        MuninnConfig {
+            visible_files: vec![
+                "/nonexistent/repo/src/b.rs".to_string(),
+                "/elsewhere/a.rs".to_string(),
+            ],
+            ..MuninnConfig::recursive()
+        },
+    );
+    engine.complete(request).await.unwrap();
+    let request = CompletionRequest::new("test-model", vec![Message::user("Hi")], 100)
+        .with_muninn(MuninnConfig::recursive());
+    engine.complete(request).await.unwrap();
+
+    let requests = backend.requests();
+    let system = requests[0].system.as_ref().unwrap().to_text();
+    assert!(
+        system.ends_with("the first.\n- src/b.rs\n- /elsewhere/a.rs\n"),
+        "{}",
+        system
+    );
+    let system = requests[1].system.as_ref().unwrap().to_text();
+    assert!(
+        !system.contains("## Files the user is looking at"),
+        "{}",
+        system
+    );
+}
+
+#[tokio::test]
+async fn test_category_selects_prompt_and_tools() {
+    let done = CompletionResponse::new(
+        "msg",
+        "model",
+        vec![ContentBlock::Text {
+            text: "done".to_string(),
+            cache_control: None,
+        }],
+        StopReason::EndTurn,
+        Usage::new(10, 5),
+    );
+    let backend = Arc::new(MockBackend::new(vec![done; 3]));
+    let tools: Arc<dyn crate::tools::ToolEnvironment> = Arc::new(MockToolEnvironment::new(
+        ["read_file", "execute_code", "final_answer"]
+            .map(|name| ToolDefinition::new(name, name, json!({"type": "object"})))
+            .to_vec(),
+    ));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), tools),
+        EngineConfig::default(),
+    );
+
+    for category in [Some("ops"), Some("not-a-category"), None] {
+        let request = CompletionRequest::new("test-model", vec![Message::user("Hi")], 100)
+            .with_muninn(MuninnConfig {
+                category: category.map(String::from),
+                ..MuninnConfig::recursive()
+            });
+        engine.complete(request).await.unwrap();
+    }
+
+    let requests = backend.requests();
+    let ops = requests[0].system.as_ref().unwrap().to_text();
+    assert!(ops.contains("## Focus: Operations"), "{ops}");
+    assert!(ops.contains("read_file") && ops.contains("final_answer"));
+    assert!(!ops.contains("execute_code"), "{ops}");
+    for generic in &requests[1..] {
+        let system = generic.system.as_ref().unwrap().to_text();
+        assert!(!system.contains("## Focus:"), "{system}");
+        assert!(system.contains("execute_code"), "{system}");
+    }
+}
+
+fn read_file_turn(usage: Usage) -> CompletionResponse {
+    CompletionResponse::new(
+        "msg_1",
+        "model",
+        vec![ContentBlock::ToolUse {
+            id: "tool_1".to_string(),
+            name: "read_file".to_string(),
+            input: json!({"path": "/foo.rs"}),
+            cache_control: None,
+        }],
+        StopReason::ToolUse,
+        usage,
+    )
+}
+
+fn downshift_engine(
+    first_turn: Usage,
+    threshold: f64,
+) -> (RecursiveEngine, Arc<MockBackend>, Arc<MockBackend>) {
+    let main = Arc::new(MockBackend::new(vec![read_file_turn(first_turn)]));
+    let fallback = Arc::new(MockBackend::with_text("answered by the small model"));
+    let tools = Arc::new(MockToolEnvironment::new(vec![ToolDefinition::new(
+        "read_file",
+        "Read a file",
+        json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+    )]));
+    let config = EngineConfig::default()
+        .with_budget(BudgetConfig {
+            max_tokens: Some(100),
+            ..Default::default()
+        })
+        .with_downshift(super::Downshift::new(fallback.clone(), "small").with_threshold(threshold));
+    let engine = RecursiveEngine::new(EngineDeps::new(main.clone(), tools), config);
+    (engine, main, fallback)
+}
+
+#[tokio::test]
+async fn test_downshift_at_threshold() {
+    let (engine, main, fallback) = downshift_engine(Usage::new(60, 25), 0.8);
+
+    let request = CompletionRequest::new("big", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+
+    assert_eq!(response.text(), "answered by the small model");
+    assert_eq!(main.request_count(), 1);
+    assert_eq!(fallback.requests()[0].model, "small");
+    let metadata = response.muninn.unwrap();
+    assert_eq!(metadata.downshifted_to.as_deref(), Some("small"));
+}
+
+#[tokio::test]
+async fn test_downshift_finishes_exhausted_budget() {
+    let (engine, _, fallback) = downshift_engine(Usage::new(100, 50), 1.0);
+
+    let request = CompletionRequest::new("big", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+
+    assert_eq!(response.text(), "answered by the small model");
+    let last_turn = fallback.requests()[0].messages.last().unwrap().clone();
+    assert!(last_turn.content.as_text().unwrap().contains("FINAL turn"));
+    assert_eq!(
+        response.muninn.unwrap().downshifted_to.as_deref(),
+        Some("small")
+    );
+
+    // Without a fallback the same exploration fails.
+    let main = Arc::new(MockBackend::new(vec![read_file_turn(Usage::new(100, 50))]));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(main, Arc::new(MockToolEnvironment::new(vec![]))),
+        EngineConfig::default().with_budget(BudgetConfig {
+            max_tokens: Some(100),
+            ..Default::default()
+        }),
+    );
+    let request = CompletionRequest::new("big", vec![Message::user("Read /foo.rs")], 100);
+    assert!(matches!(
+        engine.complete(request).await,
+        Err(crate::error::RlmError::BudgetExceeded(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_ensemble_judge_picks_answer() {
+    let responses = vec![
+        read_file_turn(Usage::new(20, 10)),
+        CompletionResponse::new(
+            "msg_2",
+            "big",
+            vec![ContentBlock::Text {
+                text: "It reads a file.".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(50, 30),
+        ),
+    ];
+    let (_, tool_env) = create_engine(vec![], vec![]);
+    let main = Arc::new(MockBackend::new(responses));
+    let second = Arc::new(MockBackend::with_text("It reads /foo.rs via read_file."));
+    let judge = Arc::new(MockBackend::with_text("B"));
+    let config = EngineConfig::default().with_ensemble(
+        super::Ensemble::new(super::EnsembleMember::new(second.clone(), "other"))
+            .with_judge(super::EnsembleMember::new(judge.clone(), "judge")),
+    );
+    let engine = RecursiveEngine::new(EngineDeps::new(main, tool_env), config);
+
+    let request = CompletionRequest::new("big", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.text(), "It reads /foo.rs via read_file.");
+
+    // The second backend sees the gathered context, without tools.
+    let synthesis = &second.requests()[0];
+    assert_eq!(synthesis.model, "other");
+    assert!(synthesis.tools.is_empty());
+    assert_eq!(synthesis.messages.len(), 4);
+    let judged = judge.requests()[0].messages[0].content.to_text();
+    assert!(judged.contains("Answer A:\nIt reads a file."), "{judged}");
+}
+
+#[tokio::test]
+async fn test_ensemble_without_verdict_picks_heuristically() {
+    let main = Arc::new(MockBackend::with_text("Config lives in src/config.rs."));
+    let second = Arc::new(MockBackend::with_text(
+        "Config is loaded somewhere at startup.",
+    ));
+    let judge = Arc::new(MockBackend::with_text("Both are fine."));
+    let config = EngineConfig::default().with_ensemble(
+        super::Ensemble::new(super::EnsembleMember::new(second, "other"))
+            .with_judge(super::EnsembleMember::new(judge, "judge")),
+    );
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(main, Arc::new(crate::tools::EmptyToolEnvironment)),
+        config,
+    );
+
+    let request = CompletionRequest::new("big", vec![Message::user("Where is config?")], 100);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.text(), "Config lives in src/config.rs.");
+}
+
+#[tokio::test]
+async fn test_timeout_returns_partial_answer() {
+    // The first call is cut off by the deadline before it takes a
+    // response, so the queued one answers the partial-answer call.
+    let backend = Arc::new(
+        crate::testing::MockLLMBackend::new()
+            .with_response(CompletionResponse::new(
+                "msg_2",
+                "model",
+                vec![ContentBlock::Text {
+                    text: "Probably in src/config.rs, unverified.".to_string(),
+                    cache_control: None,
+                }],
+                StopReason::EndTurn,
+                Usage::new(50, 30),
+            ))
+            .with_latency(std::time::Duration::from_millis(1200)),
+    );
+    let config = EngineConfig::default().with_budget(BudgetConfig {
+        max_duration_secs: Some(1),
+        ..Default::default()
+    });
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), Arc::new(MockToolEnvironment::new(vec![]))),
+        config,
+    );
+
+    let request = CompletionRequest::new("m", vec![Message::user("Where is config?")], 100);
+    let response = engine.complete(request).await.unwrap();
+
+    assert_eq!(
+        response.text(),
+        "[Partial answer: exploration timed out]\n\nProbably in src/config.rs, unverified."
+    );
+    assert_eq!(response.muninn.unwrap().partial.as_deref(), Some("timeout"));
+    let partial_request = backend.last_request().unwrap();
+    let prompt = partial_request.messages.last().unwrap().content.to_text();
+    assert!(prompt.starts_with("Time is up"), "{prompt}");
+}
+
+#[tokio::test]
+async fn test_turns_trimmed_to_context_window() {
+    let backend = Arc::new(MockBackend::new(vec![
+        read_file_turn(Usage::new(10, 5)),
+        read_file_turn(Usage::new(10, 5)),
+        read_file_turn(Usage::new(10, 5)),
+        CompletionResponse::new(
+            "msg_4",
+            "model",
+            vec![ContentBlock::text("done")],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        ),
+    ]));
+    let tools = Arc::new(MockToolEnvironment::new(vec![]));
+    tools.set_response("read_file", "x".repeat(4000));
+    let config = EngineConfig::default()
+        .with_context_windows(super::ContextWindows::none().with("small", 2500));
+    let engine = RecursiveEngine::new(EngineDeps::new(backend.clone(), tools), config);
+
+    let request = CompletionRequest::new("small", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+    assert_eq!(response.text(), "done");
+
+    let requests = backend.requests();
+    assert_eq!(requests[2].messages.len(), 5);
+    let last = &requests[3];
+    assert!(super::estimate_request_tokens(last) <= 2500);
+    assert_eq!(last.messages.len(), 7);
+    let results: Vec<String> = last
+        .messages
+        .iter()
+        .skip(2)
+        .step_by(2)
+        .map(|m| serde_json::to_string(&m.content).unwrap())
+        .collect();
+    assert!(results[0].contains(super::ELIDED_RESULT));
+    assert!(!results[1].contains(super::ELIDED_RESULT));
+    assert!(!results[2].contains(super::ELIDED_RESULT));
+}
+
+#[tokio::test]
+async fn test_phase_timings_on_spans() {
+    let (engine, tool_env) = create_engine(
+        vec![
+            read_file_turn(Usage::new(10, 5)),
+            CompletionResponse::new(
+                "msg_2",
+                "model",
+                vec![ContentBlock::text("done")],
+                StopReason::EndTurn,
+                Usage::new(10, 5),
+            ),
+        ],
+        vec![],
+    );
+    tool_env.set_response("read_file", "test content");
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100)
+        .with_muninn(MuninnConfig::recursive());
+    let (response, trace) = muninn_tracing::with_tracing(engine.complete(request)).await;
+    assert!(response.is_ok());
+
+    let cycle = &trace.spans[0];
+    assert_eq!(cycle.name, "rlm_cycle");
+    let segments = &cycle.timing.as_ref().unwrap().segments;
+    for phase in [
+        super::TIMING_PROMPT_BUILD,
+        super::TIMING_BACKEND_LATENCY,
+        super::TIMING_TOOL_EXECUTION,
+    ] {
+        assert!(segments.contains_key(phase), "missing {}", phase);
+    }
+    let children: Vec<_> = cycle.children.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(
+        children,
+        ["rlm_iteration", "tool_execution", "rlm_iteration"]
+    );
+    for child in &cycle.children {
+        assert!(!child.timing.as_ref().unwrap().segments.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_prompt_dump_per_iteration() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = Arc::new(crate::debug_log::RawRequestLog::new(
+        crate::debug_log::RawRequestLogConfig::in_dir(dir.path())
+            .with_file_name(crate::debug_log::PROMPT_DUMP_FILE),
+    ));
+    let backend = Arc::new(MockBackend::new(vec![
+        read_file_turn(Usage::new(10, 5)),
+        CompletionResponse::new(
+            "msg_2",
+            "model",
+            vec![ContentBlock::text("done")],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        ),
+    ]));
+    let tools = Arc::new(MockToolEnvironment::new(vec![]));
+    tools.set_response("read_file", "token sk-ant-REDACTED");
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend, tools),
+        EngineConfig::default().with_prompt_dump(dump.clone()),
+    );
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100)
+        .with_system("Explore the code");
+    engine.complete(request).await.unwrap();
+
+    let content = std::fs::read_to_string(dump.path()).unwrap();
+    let entries: Vec<serde_json::Value> = content
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["depth"], 0);
+    assert_eq!(entries[0]["system"], "Explore the code");
+    assert_eq!(entries[0]["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(entries[1]["depth"], 1);
+    assert_eq!(entries[1]["messages"].as_array().unwrap().len(), 3);
+    assert!(!content.contains("sk-ant-api03"));
+}
+
+/// Hooks that record every call, refuse every tool call when `refuse` is
+/// set, and stop the exploration after `stop_after_tools` tool results
+/// when set.
+#[derive(Default)]
+struct RecordingHooks {
+    calls: std::sync::Mutex<Vec<String>>,
+    refuse: Option<String>,
+    stop_after_tools: Option<usize>,
+}
+
+impl RecordingHooks {
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl super::EngineHooks for RecordingHooks {
+    fn on_iteration_start(&self, context: &super::ExplorationContext) -> super::HookDecision {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(format!("iteration {}", context.depth()));
+        super::HookDecision::Continue
+    }
+
+    fn on_tool_call(
+        &self,
+        context: &super::ExplorationContext,
+        call: &crate::types::ToolUseBlock,
+    ) -> super::ToolCallDecision {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(format!("call {} {}", context.depth(), call.name));
+        match &self.refuse {
+            Some(reason) => super::ToolCallDecision::Refuse(reason.clone()),
+            None => super::ToolCallDecision::Run,
+        }
+    }
+
+    fn on_tool_result(
+        &self,
+        context: &super::ExplorationContext,
+        results: &[crate::types::ToolResultBlock],
+    ) -> super::HookDecision {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(format!("tools {} x{}", context.depth(), results.len()));
+        let tool_calls = calls.iter().filter(|c| c.starts_with("tools")).count();
+        match self.stop_after_tools {
+            Some(n) if tool_calls >= n => super::HookDecision::Stop("seen enough".to_string()),
+            _ => super::HookDecision::Continue,
+        }
+    }
+
+    fn on_final_answer(&self, _context: &super::ExplorationContext, response: &CompletionResponse) {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(format!("final {}", response.text()));
+    }
+}
+
+fn hooked_engine(
+    hooks: Arc<RecordingHooks>,
+) -> (RecursiveEngine, Arc<MockBackend>, Arc<MockToolEnvironment>) {
+    let backend = Arc::new(MockBackend::new(vec![
+        read_file_turn(Usage::new(10, 5)),
+        read_file_turn(Usage::new(10, 5)),
+        text_response("msg_3", "done", StopReason::EndTurn, Usage::new(10, 5)),
+    ]));
+    let tools = Arc::new(MockToolEnvironment::new(vec![]));
+    let engine = RecursiveEngine::new(
+        EngineDeps::new(backend.clone(), tools.clone()),
+        EngineConfig::default().with_hooks(hooks),
+    );
+    (engine, backend, tools)
+}
+
+#[tokio::test]
+async fn test_hooks_observe_each_step() {
+    let hooks = Arc::new(RecordingHooks::default());
+    let (engine, _, _) = hooked_engine(hooks.clone());
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+
+    assert_eq!(response.text(), "done");
+    assert_eq!(
+        hooks.calls(),
+        vec![
+            "iteration 0",
+            "call 0 read_file",
+            "tools 0 x1",
+            "iteration 1",
+            "call 1 read_file",
+            "tools 1 x1",
+            "iteration 2",
+            "final done",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_hook_stops_exploration() {
+    let hooks = Arc::new(RecordingHooks {
+        stop_after_tools: Some(1),
+        ..Default::default()
+    });
+    let (engine, backend, _) = hooked_engine(hooks.clone());
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+
+    assert_eq!(response.text(), "[Exploration stopped: seen enough]");
+    assert_eq!(backend.request_count(), 1);
+    assert_eq!(
+        hooks.calls(),
+        vec![
+            "iteration 0",
+            "call 0 read_file",
+            "tools 0 x1",
+            "final [Exploration stopped: seen enough]",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_hook_refuses_tool_call_before_it_runs() {
+    let hooks = Arc::new(RecordingHooks {
+        refuse: Some("not in this repo".to_string()),
+        ..Default::default()
+    });
+    let (engine, backend, tools) = hooked_engine(hooks.clone());
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read /foo.rs")], 100);
+    let response = engine.complete(request).await.unwrap();
+
+    assert_eq!(response.text(), "done");
+    assert_eq!(tools.execution_count(), 0);
+    let second = &backend.requests()[1];
+    let result = second.messages.last().unwrap();
+    let result = serde_json::to_string(&result.content).unwrap();
+    assert!(result.contains("Tool call refused: not in this repo"));
+}
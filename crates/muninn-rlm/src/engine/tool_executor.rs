@@ -1,16 +1,104 @@
 //! Tool call execution and result handling.
 //!
 //! This module provides the `ToolExecutor` for executing tool calls
-//! requested by the LLM during exploration.
+//! requested by the LLM during exploration, including enforcement of
+//! per-tool [`ToolLimits`].
 
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::error::Result;
+use crate::tool_repair::{RepairOutcome, repair_tool_input};
 use crate::tools::ToolEnvironment;
-use crate::types::{CompletionResponse, ToolResultBlock, ToolResultContent};
+use crate::types::{
+    CompletionResponse, ContentBlock, ToolResultBlock, ToolResultContent, ToolUseBlock,
+};
 
-use super::trace::ToolExecutionTraceData;
+use super::trace::{TIMING_TOOL_EXECUTION, ToolExecutionTraceData};
+
+/// Limits on a single tool within one exploration.
+///
+/// When a limit is hit the tool isn't run; the model gets an error result
+/// naming the limit and the configured hint, so it can change approach.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolLimits {
+    /// Maximum invocations per exploration.
+    pub max_calls: Option<u32>,
+    /// Per-call timeout.
+    pub timeout: Option<Duration>,
+    /// Maximum cumulative output tokens (estimated) per exploration.
+    pub max_output_tokens: Option<u64>,
+    /// Suggestion appended to limit messages (e.g. "use the graph instead").
+    pub hint: Option<String>,
+}
+
+impl ToolLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_calls(mut self, max: u32) -> Self {
+        self.max_calls = Some(max);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_output_tokens(mut self, max: u64) -> Self {
+        self.max_output_tokens = Some(max);
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Format a limit message for the model.
+    fn message(&self, reason: String) -> String {
+        match &self.hint {
+            Some(hint) => format!("{}, {}", reason, hint),
+            None => format!("{}, use a different tool", reason),
+        }
+    }
+}
+
+/// Per-tool usage within one exploration, checked against [`ToolLimits`].
+#[derive(Debug, Clone, Default)]
+pub struct ToolUsage {
+    calls: HashMap<String, u32>,
+    output_tokens: HashMap<String, u64>,
+}
+
+impl ToolUsage {
+    /// Number of times `tool` has run.
+    pub fn calls(&self, tool: &str) -> u32 {
+        self.calls.get(tool).copied().unwrap_or(0)
+    }
+
+    /// Estimated output tokens `tool` has produced.
+    pub fn output_tokens(&self, tool: &str) -> u64 {
+        self.output_tokens.get(tool).copied().unwrap_or(0)
+    }
+
+    fn record(&mut self, tool: &str, output_tokens: u64) {
+        *self.calls.entry(tool.to_string()).or_default() += 1;
+        *self.output_tokens.entry(tool.to_string()).or_default() += output_tokens;
+    }
+}
+
+/// Tool inputs fixed up by [`ToolExecutor::repair_tool_calls`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RepairCounts {
+    /// Inputs that needed repair, successful or not.
+    pub attempts: u32,
+    /// Inputs that could not be repaired.
+    pub failures: u32,
+}
 
 /// Executes tool calls and collects results.
 ///
@@ -19,37 +107,102 @@ use super::trace::ToolExecutionTraceData;
 #[derive(Clone)]
 pub struct ToolExecutor {
     tools: Arc<dyn ToolEnvironment>,
+    limits: HashMap<String, ToolLimits>,
 }
 
 impl ToolExecutor {
     /// Create a new tool executor with the given tool environment.
     pub fn new(tools: Arc<dyn ToolEnvironment>) -> Self {
-        Self { tools }
+        Self {
+            tools,
+            limits: HashMap::new(),
+        }
+    }
+
+    /// Set limits for the named tools.
+    pub fn with_limits(mut self, limits: HashMap<String, ToolLimits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Repair malformed tool inputs in `response` against the tools' input
+    /// schemas, so both the tools and the conversation history see the
+    /// fixed-up arguments.
+    pub(crate) fn repair_tool_calls(&self, response: &mut CompletionResponse) -> RepairCounts {
+        let mut counts = RepairCounts::default();
+        if !response.has_tool_use() {
+            return counts;
+        }
+        let definitions = self.tools.available_tools();
+        for block in &mut response.content {
+            let ContentBlock::ToolUse { name, input, .. } = block else {
+                continue;
+            };
+            let schema = definitions
+                .iter()
+                .find(|d| &d.name == name)
+                .map(|d| &d.input_schema);
+            match repair_tool_input(input, schema) {
+                RepairOutcome::Unchanged => {}
+                RepairOutcome::Repaired => {
+                    tracing::debug!(tool = %name, "Repaired tool call input");
+                    counts.attempts += 1;
+                }
+                RepairOutcome::Failed => {
+                    tracing::warn!(tool = %name, "Could not repair tool call input");
+                    counts.attempts += 1;
+                    counts.failures += 1;
+                }
+            }
+        }
+        counts
     }
 
     /// Execute all tool use requests from a response.
     ///
     /// Tool errors are returned as error results to the LLM rather than
     /// aborting the exploration - this allows the model to learn and adapt.
+    /// `usage` carries per-tool counts across the exploration's iterations.
     pub async fn execute_tools(
         &self,
         response: &CompletionResponse,
+        usage: &mut ToolUsage,
+    ) -> Result<Vec<ToolResultBlock>> {
+        self.execute_tools_refusing(response, usage, &HashMap::new())
+            .await
+    }
+
+    /// Like [`execute_tools`](Self::execute_tools), but the calls whose id
+    /// is in `refused` don't run: the model gets an error result carrying
+    /// the reason instead.
+    pub async fn execute_tools_refusing(
+        &self,
+        response: &CompletionResponse,
+        usage: &mut ToolUsage,
+        refused: &HashMap<String, String>,
     ) -> Result<Vec<ToolResultBlock>> {
         let tool_uses = response.tool_uses();
         let mut results = Vec::with_capacity(tool_uses.len());
 
         for tool_use in tool_uses {
             let tool_start = Instant::now();
-            let (result, success, output_preview) = match self.tools.execute_tool(&tool_use).await {
-                Ok(result) => {
-                    let preview = Self::extract_result_preview(&result.content, 500);
-                    (result, true, preview)
+            let limits = self.limits.get(&tool_use.name);
+            let refusal = refused
+                .get(&tool_use.id)
+                .map(|reason| format!("Tool call refused: {}", reason));
+            let (result, success, output_preview, limit) = match refusal
+                .or_else(|| limits.and_then(|l| Self::exhausted(l, &tool_use.name, usage)))
+            {
+                Some(message) => {
+                    tracing::debug!(tool = %tool_use.name, "{}", message);
+                    let error_result = ToolResultBlock::error(&tool_use.id, &message);
+                    (error_result, false, message.clone(), Some(message))
                 }
-                Err(e) => {
-                    // Return error as tool result so LLM can learn and adapt
-                    let error_result = ToolResultBlock::error(&tool_use.id, e.to_string());
-                    let preview = Self::truncate_string(&e.to_string(), 500);
-                    (error_result, false, preview)
+                None => {
+                    let (result, success, limit) = self.execute_one(&tool_use, limits).await;
+                    usage.record(&tool_use.name, Self::estimate_tokens(&result.content));
+                    let preview = Self::extract_result_preview(&result.content, 500);
+                    (result, success, preview, limit)
                 }
             };
             let execution_time_ms = tool_start.elapsed().as_millis() as u64;
@@ -62,9 +215,14 @@ impl ToolExecutor {
                 success,
                 output_preview,
                 execution_time_ms,
+                limit,
             };
-            muninn_tracing::start_span_with_data("tool_execution", &tool_data);
-            muninn_tracing::end_span_ok();
+            let span = muninn_tracing::span_with_data("tool_execution", &tool_data);
+            span.timing_segment(TIMING_TOOL_EXECUTION, execution_time_ms);
+            span.attribute("tool", tool_use.name.as_str());
+            span.attribute("latency_ms", execution_time_ms);
+            span.attribute("success", success);
+            span.ok();
 
             results.push(result);
         }
@@ -72,6 +230,73 @@ impl ToolExecutor {
         Ok(results)
     }
 
+    /// Run one tool call, applying its timeout. Returns the result, whether
+    /// the tool ran without error, and the limit message if the timeout fired.
+    async fn execute_one(
+        &self,
+        tool_use: &ToolUseBlock,
+        limits: Option<&ToolLimits>,
+    ) -> (ToolResultBlock, bool, Option<String>) {
+        let execution = self.tools.execute_tool(tool_use);
+        let outcome = match limits.and_then(|l| l.timeout.map(|t| (l, t))) {
+            Some((limits, timeout)) => match tokio::time::timeout(timeout, execution).await {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    let message = limits.message(format!(
+                        "{} timed out after {}s",
+                        tool_use.name,
+                        timeout.as_secs_f64()
+                    ));
+                    let result = ToolResultBlock::error(&tool_use.id, &message);
+                    return (result, false, Some(message));
+                }
+            },
+            None => execution.await,
+        };
+        match outcome {
+            Ok(result) => (result, true, None),
+            // Return error as tool result so LLM can learn and adapt
+            Err(e) => (
+                ToolResultBlock::error(&tool_use.id, e.to_string()),
+                false,
+                None,
+            ),
+        }
+    }
+
+    /// Message for the first limit `tool` has already used up, if any.
+    fn exhausted(limits: &ToolLimits, tool: &str, usage: &ToolUsage) -> Option<String> {
+        if let Some(max) = limits.max_calls {
+            if usage.calls(tool) >= max {
+                return Some(limits.message(format!(
+                    "{} limit reached ({} calls per exploration)",
+                    tool, max
+                )));
+            }
+        }
+        if let Some(max) = limits.max_output_tokens {
+            if usage.output_tokens(tool) >= max {
+                return Some(limits.message(format!(
+                    "{} output limit reached (~{} tokens per exploration)",
+                    tool, max
+                )));
+            }
+        }
+        None
+    }
+
+    /// Rough token count of a result (4 bytes per token).
+    fn estimate_tokens(content: &Option<ToolResultContent>) -> u64 {
+        let bytes = match content {
+            Some(ToolResultContent::Text(text)) => text.len(),
+            Some(ToolResultContent::Blocks(blocks)) => {
+                serde_json::to_string(blocks).map(|s| s.len()).unwrap_or(0)
+            }
+            None => 0,
+        };
+        bytes.div_ceil(4) as u64
+    }
+
     /// Extract a preview from tool result content.
     fn extract_result_preview(content: &Option<ToolResultContent>, max_len: usize) -> String {
         match content {
@@ -132,7 +357,10 @@ mod tests {
         let executor = ToolExecutor::new(tools.clone());
         let response = create_tool_response("test_tool", "t1");
 
-        let results = executor.execute_tools(&response).await.unwrap();
+        let results = executor
+            .execute_tools(&response, &mut ToolUsage::default())
+            .await
+            .unwrap();
         assert_eq!(results.len(), 1);
         assert!(!results[0].is_error);
         assert_eq!(tools.execution_count(), 1);
@@ -167,11 +395,112 @@ mod tests {
             Usage::new(10, 10),
         );
 
-        let results = executor.execute_tools(&response).await.unwrap();
+        let results = executor
+            .execute_tools(&response, &mut ToolUsage::default())
+            .await
+            .unwrap();
         assert_eq!(results.len(), 2);
         assert_eq!(tools.execution_count(), 2);
     }
 
+    #[tokio::test]
+    async fn test_max_calls_limit() {
+        let tools = Arc::new(MockToolEnvironment::new(vec![ToolDefinition::new(
+            "search_files",
+            "Search",
+            json!({}),
+        )]));
+        let limits = HashMap::from([(
+            "search_files".to_string(),
+            ToolLimits::new()
+                .with_max_calls(2)
+                .with_hint("use the graph instead"),
+        )]);
+        let executor = ToolExecutor::new(tools.clone()).with_limits(limits);
+        let response = create_tool_response("search_files", "t1");
+
+        let mut usage = ToolUsage::default();
+        for _ in 0..2 {
+            let results = executor.execute_tools(&response, &mut usage).await.unwrap();
+            assert!(!results[0].is_error);
+        }
+        let results = executor.execute_tools(&response, &mut usage).await.unwrap();
+        assert!(results[0].is_error);
+        assert_eq!(
+            results[0].content,
+            Some(ToolResultContent::Text(
+                "search_files limit reached (2 calls per exploration), use the graph instead"
+                    .to_string()
+            ))
+        );
+        assert_eq!(tools.execution_count(), 2);
+        assert_eq!(usage.calls("search_files"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_output_token_limit() {
+        let tools = Arc::new(MockToolEnvironment::new(vec![ToolDefinition::new(
+            "read_file",
+            "Read",
+            json!({}),
+        )]));
+        tools.set_response("read_file", "x".repeat(400));
+        let limits = HashMap::from([(
+            "read_file".to_string(),
+            ToolLimits::new().with_max_output_tokens(150),
+        )]);
+        let executor = ToolExecutor::new(tools.clone()).with_limits(limits);
+        let response = create_tool_response("read_file", "t1");
+
+        let mut usage = ToolUsage::default();
+        for _ in 0..2 {
+            executor.execute_tools(&response, &mut usage).await.unwrap();
+        }
+        assert_eq!(usage.output_tokens("read_file"), 200);
+        let results = executor.execute_tools(&response, &mut usage).await.unwrap();
+        assert!(results[0].is_error);
+        assert_eq!(tools.execution_count(), 2);
+    }
+
+    /// Tool environment whose only tool never finishes in time.
+    struct SlowTools;
+
+    #[async_trait::async_trait]
+    impl ToolEnvironment for SlowTools {
+        async fn execute_tool(&self, tool_use: &ToolUseBlock) -> Result<ToolResultBlock> {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(ToolResultBlock::success(&tool_use.id, "late"))
+        }
+
+        fn available_tools(&self) -> Vec<ToolDefinition> {
+            vec![ToolDefinition::new("slow", "Slow", json!({}))]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_limit() {
+        let limits = HashMap::from([(
+            "slow".to_string(),
+            ToolLimits::new().with_timeout(Duration::from_millis(10)),
+        )]);
+        let executor = ToolExecutor::new(Arc::new(SlowTools)).with_limits(limits);
+        let response = create_tool_response("slow", "t1");
+
+        let mut usage = ToolUsage::default();
+        let results = executor.execute_tools(&response, &mut usage).await.unwrap();
+        assert!(results[0].is_error);
+        let Some(ToolResultContent::Text(message)) = &results[0].content else {
+            panic!("expected text content");
+        };
+        assert!(
+            message.starts_with("slow timed out after 0.01s"),
+            "{}",
+            message
+        );
+        // Timed-out calls still count against max_calls
+        assert_eq!(usage.calls("slow"), 1);
+    }
+
     #[test]
     fn test_truncate_string_short() {
         let result = ToolExecutor::truncate_string("short", 100);
@@ -5,6 +5,30 @@
 
 use serde::Serialize;
 
+// Timing segment names, added to span timings with
+// `muninn_tracing::add_timing_segment`. `proxy_request` breaks the request
+// down into waiting, compaction, routing, exploration and serialization;
+// `rlm_cycle` sums prompt building, backend latency and tool execution over
+// its iterations; each `rlm_iteration`, `backend_call` and `tool_execution`
+// span carries its own share.
+
+/// Time from the request arriving until it is handled, compaction aside.
+pub const TIMING_QUEUE_WAIT: &str = "queue_wait";
+/// Conversation compaction before routing.
+pub const TIMING_COMPACTION: &str = "compaction";
+/// The router's decision.
+pub const TIMING_ROUTING: &str = "routing";
+/// The whole RLM exploration.
+pub const TIMING_EXPLORATION: &str = "exploration";
+/// Building the response body, transforms included.
+pub const TIMING_SERIALIZATION: &str = "serialization";
+/// Preparing the exploration request and each turn's prompt.
+pub const TIMING_PROMPT_BUILD: &str = "prompt_build";
+/// Waiting on the LLM backend.
+pub const TIMING_BACKEND_LATENCY: &str = "backend_latency";
+/// Running tool calls.
+pub const TIMING_TOOL_EXECUTION: &str = "tool_execution";
+
 /// Trace data captured at the start of an RLM exploration cycle.
 #[derive(Debug, Clone, Serialize)]
 pub struct RlmCycleTraceData {
@@ -16,6 +40,8 @@ pub struct RlmCycleTraceData {
     pub initial_message_count: usize,
     /// System prompt (if any).
     pub system_prompt: Option<String>,
+    /// Router category selecting the exploration prompt and tools.
+    pub category: Option<crate::prompts::RouteCategory>,
 }
 
 /// Trace data for a single LLM iteration within exploration.
@@ -35,6 +61,10 @@ pub struct RlmIterationTraceData {
     pub output_tokens: u32,
     /// Stop reason from LLM.
     pub stop_reason: Option<String>,
+    /// Tool calls whose input needed repair (see [`crate::tool_repair`]).
+    pub tool_call_repairs: u32,
+    /// Of those, how many could not be repaired.
+    pub tool_call_repair_failures: u32,
 }
 
 /// Trace data for tool execution.
@@ -52,6 +82,9 @@ pub struct ToolExecutionTraceData {
     pub output_preview: String,
     /// Execution time (ms).
     pub execution_time_ms: u64,
+    /// Per-tool limit that stopped or cut short the call, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<String>,
 }
 
 /// Trace data for exploration completion.
@@ -82,6 +115,7 @@ mod tests {
             is_recursive: true,
             initial_message_count: 3,
             system_prompt: Some("Be helpful".to_string()),
+            category: None,
         };
 
         let json = serde_json::to_string(&data).unwrap();
@@ -99,10 +133,13 @@ mod tests {
             input_tokens: 100,
             output_tokens: 50,
             stop_reason: Some("end_turn".to_string()),
+            tool_call_repairs: 1,
+            tool_call_repair_failures: 0,
         };
 
         let json = serde_json::to_string(&data).unwrap();
         assert!(json.contains("1500"));
+        assert!(json.contains("\"tool_call_repairs\":1"));
     }
 
     #[test]
@@ -114,6 +151,7 @@ mod tests {
             success: true,
             output_preview: "file contents...".to_string(),
             execution_time_ms: 50,
+            limit: None,
         };
 
         let json = serde_json::to_string(&data).unwrap();
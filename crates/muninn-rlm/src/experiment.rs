@@ -0,0 +1,422 @@
+//! A/B experiments across router prompts and RLM models.
+//!
+//! An [`Experiment`] splits proxy traffic between named [`Variant`]s by
+//! weight. A variant can bring its own router (e.g. a different router
+//! prompt) and its own engine (e.g. a different RLM model); whatever it
+//! doesn't override falls back to the proxy's defaults. Assignment hashes
+//! the trace ID, so a request keeps its variant across retries that reuse
+//! a `traceparent`.
+//!
+//! The proxy stores the assignment in each trace's `experiment` metadata.
+//! [`ExperimentReport`] reads it back from the trace files under `.muninn/`
+//! and compares variants by latency, tokens and termination reasons, for
+//! `muninn experiments report`.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+
+use muninn_core::MuninnEngine;
+use muninn_tracing::{Span, Trace};
+use serde::{Deserialize, Serialize};
+
+use crate::router::Router;
+use crate::spend::TokenUsage;
+
+/// Trace metadata key holding the [`Assignment`].
+pub const EXPERIMENT_METADATA: &str = "experiment";
+
+/// One arm of an experiment.
+pub struct Variant {
+    pub name: String,
+    /// Share of traffic relative to the other variants' weights.
+    pub weight: u32,
+    /// Router used instead of the proxy's.
+    pub router: Option<Router>,
+    /// Engine used instead of the proxy's.
+    pub engine: Option<Arc<dyn MuninnEngine>>,
+}
+
+impl Variant {
+    /// A variant that changes nothing (a control arm).
+    pub fn new(name: impl Into<String>, weight: u32) -> Self {
+        Self {
+            name: name.into(),
+            weight,
+            router: None,
+            engine: None,
+        }
+    }
+
+    /// Route this variant's requests with `router`.
+    pub fn with_router(mut self, router: Router) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// Answer this variant's RLM requests with `engine`.
+    pub fn with_engine(mut self, engine: Arc<dyn MuninnEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+}
+
+/// Named traffic split between variants.
+pub struct Experiment {
+    name: String,
+    variants: Vec<Variant>,
+}
+
+impl Experiment {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            variants: Vec::new(),
+        }
+    }
+
+    /// Add a variant.
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// Pick the variant for `key` (the trace ID). The same key always gets
+    /// the same variant. `None` when every weight is zero.
+    pub fn assign(&self, key: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut point = fnv1a(key.as_bytes()) % total;
+        for variant in &self.variants {
+            let weight = u64::from(variant.weight);
+            if point < weight {
+                return Some(variant);
+            }
+            point -= weight;
+        }
+        None
+    }
+
+    /// The metadata recorded for requests assigned to `variant`.
+    pub fn assignment(&self, variant: &Variant) -> Assignment {
+        Assignment {
+            experiment: self.name.clone(),
+            variant: variant.name.clone(),
+        }
+    }
+}
+
+/// Which experiment and variant a request ran under.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub experiment: String,
+    pub variant: String,
+}
+
+/// Stable across builds and platforms, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Per-variant results, keyed by experiment then variant name.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExperimentReport {
+    pub experiments: BTreeMap<String, BTreeMap<String, VariantStats>>,
+}
+
+/// What one variant's requests cost and how they ended.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VariantStats {
+    /// Requests assigned to the variant.
+    pub requests: usize,
+    /// Requests handled by the RLM.
+    pub rlm: usize,
+    /// Requests that failed in the RLM.
+    pub errors: usize,
+    /// Mean time the proxy spent on a request, in milliseconds.
+    pub mean_latency_ms: u64,
+    /// Median of the same.
+    pub p50_latency_ms: u64,
+    /// 95th percentile of the same.
+    pub p95_latency_ms: u64,
+    /// Input tokens (cached ones included) across the variant's
+    /// requests, RLM and passthrough.
+    pub input_tokens: u64,
+    /// Output tokens across the same.
+    pub output_tokens: u64,
+    /// How RLM explorations ended (`final_answer_tool`, `budget_exceeded`, ...).
+    pub terminations: BTreeMap<String, usize>,
+    /// Mean self-assessed quality of scored explorations (`[rlm] self_score`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_quality: Option<f64>,
+    #[serde(skip)]
+    latencies: Vec<u64>,
+    #[serde(skip)]
+    qualities: Vec<f64>,
+}
+
+impl ExperimentReport {
+    /// Aggregate every trace file under `muninn_dir`, optionally only for
+    /// the experiment called `name`. Unreadable lines are skipped.
+    pub fn from_muninn_dir(muninn_dir: &Path, name: Option<&str>) -> std::io::Result<Self> {
+        let mut report = Self::default();
+        for path in crate::coverage::trace_files(muninn_dir) {
+            let content = std::fs::read_to_string(&path)?;
+            for trace in content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Trace>(line).ok())
+            {
+                report.record_trace(&trace, name);
+            }
+        }
+        report.finish();
+        Ok(report)
+    }
+
+    /// Add one trace, if it carries an assignment (for `name`, when given).
+    pub fn record_trace(&mut self, trace: &Trace, name: Option<&str>) {
+        let Some(assignment) = trace
+            .metadata
+            .get(EXPERIMENT_METADATA)
+            .and_then(|v| serde_json::from_value::<Assignment>(v.clone()).ok())
+        else {
+            return;
+        };
+        if name.is_some_and(|name| name != assignment.experiment) {
+            return;
+        }
+        let stats = self
+            .experiments
+            .entry(assignment.experiment)
+            .or_default()
+            .entry(assignment.variant)
+            .or_default();
+
+        if let Some(usage) = trace
+            .metadata
+            .get("usage")
+            .and_then(|v| serde_json::from_value::<TokenUsage>(v.clone()).ok())
+        {
+            stats.input_tokens += usage.input_tokens
+                + usage.cache_creation_input_tokens
+                + usage.cache_read_input_tokens;
+            stats.output_tokens += usage.output_tokens;
+        }
+        // Streaming passthrough writes a second trace with only the usage
+        let Some(completion) = find_event(&trace.spans, "proxy_completion") else {
+            return;
+        };
+        stats.requests += 1;
+        if let Some(ms) = trace.duration_ms {
+            stats.latencies.push(ms);
+        }
+        if completion.get("handling").and_then(|v| v.as_str()) == Some("rlm") {
+            stats.rlm += 1;
+            if completion.get("success").and_then(|v| v.as_bool()) == Some(false) {
+                stats.errors += 1;
+            }
+        }
+        if let Some(reason) = find_event(&trace.spans, "rlm_completion")
+            .and_then(|data| data.get("termination_reason"))
+            .and_then(|v| v.as_str())
+        {
+            *stats.terminations.entry(reason.to_string()).or_default() += 1;
+        }
+        if let Some(score) = find_event(&trace.spans, "rlm_quality")
+            .and_then(|data| data.get("score"))
+            .and_then(|v| v.as_f64())
+        {
+            stats.qualities.push(score);
+        }
+    }
+
+    fn finish(&mut self) {
+        for stats in self.experiments.values_mut().flat_map(|v| v.values_mut()) {
+            stats.finish();
+        }
+    }
+
+    /// Render the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Experiments\n\n");
+        if self.experiments.is_empty() {
+            out.push_str(
+                "No traces with an experiment assignment under `.muninn/`. \
+                 Configure `[experiment]` and run the proxy to collect some.\n",
+            );
+            return out;
+        }
+        for (name, variants) in &self.experiments {
+            out.push_str(&format!(
+                "## {}\n\n\
+                 | Variant | Requests | RLM | Errors | Mean ms | p50 ms | p95 ms | Input tok | Output tok | Quality |\n\
+                 |---|---|---|---|---|---|---|---|---|---|\n",
+                name
+            ));
+            for (variant, s) in variants {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+                    variant,
+                    s.requests,
+                    s.rlm,
+                    s.errors,
+                    s.mean_latency_ms,
+                    s.p50_latency_ms,
+                    s.p95_latency_ms,
+                    s.input_tokens,
+                    s.output_tokens,
+                    s.mean_quality
+                        .map_or("-".to_string(), |q| format!("{:.2}", q))
+                ));
+            }
+            out.push_str("\n| Variant | Terminations |\n|---|---|\n");
+            for (variant, s) in variants {
+                let terminations: Vec<String> = s
+                    .terminations
+                    .iter()
+                    .map(|(reason, n)| format!("{} {}", reason, n))
+                    .collect();
+                out.push_str(&format!("| {} | {} |\n", variant, terminations.join(", ")));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl VariantStats {
+    fn finish(&mut self) {
+        if !self.qualities.is_empty() {
+            self.mean_quality =
+                Some(self.qualities.iter().sum::<f64>() / self.qualities.len() as f64);
+        }
+        if self.latencies.is_empty() {
+            return;
+        }
+        self.latencies.sort_unstable();
+        let n = self.latencies.len();
+        self.mean_latency_ms = self.latencies.iter().sum::<u64>() / n as u64;
+        self.p50_latency_ms = self.latencies[(n - 1) / 2];
+        self.p95_latency_ms = self.latencies[(n - 1) * 95 / 100];
+    }
+}
+
+/// Data of the shallowest event called `name`, so a sub-query's events
+/// don't shadow the top-level request's.
+fn find_event<'a>(spans: &'a [Span], name: &str) -> Option<&'a serde_json::Value> {
+    let mut queue: VecDeque<&Span> = spans.iter().collect();
+    while let Some(span) = queue.pop_front() {
+        if let Some(data) = span
+            .events
+            .iter()
+            .find(|e| e.name == name)
+            .and_then(|e| e.data.as_ref())
+        {
+            return Some(data);
+        }
+        queue.extend(&span.children);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn experiment() -> Experiment {
+        Experiment::new("router-prompt")
+            .with_variant(Variant::new("control", 3))
+            .with_variant(Variant::new("terse", 1))
+    }
+
+    #[test]
+    fn test_assignment_is_stable_and_weighted() {
+        let experiment = experiment();
+        let mut counts = BTreeMap::new();
+        for i in 0..4000 {
+            let key = format!("trace-{i}");
+            let first = experiment.assign(&key).unwrap().name.clone();
+            assert_eq!(experiment.assign(&key).unwrap().name, first);
+            *counts.entry(first).or_insert(0) += 1;
+        }
+        let control = counts["control"];
+        assert!((2700..3300).contains(&control), "{counts:?}");
+
+        let empty = Experiment::new("off").with_variant(Variant::new("a", 0));
+        assert!(empty.assign("trace").is_none());
+    }
+
+    fn trace(variant: &str, ms: u64, handling: &str, termination: Option<&str>) -> Trace {
+        let mut span = Span::new("proxy_request");
+        span.record_event(
+            "proxy_completion",
+            Some(json!({"handling": handling, "success": true})),
+        );
+        if let Some(reason) = termination {
+            let mut cycle = Span::new("rlm_cycle");
+            cycle.record_event(
+                "rlm_completion",
+                Some(json!({"termination_reason": reason})),
+            );
+            span.add_child(cycle);
+        }
+        let mut trace = Trace::new(format!("t-{variant}-{ms}"))
+            .with_metadata(
+                EXPERIMENT_METADATA,
+                json!({"experiment": "router-prompt", "variant": variant}),
+            )
+            .with_metadata("usage", json!({"input_tokens": 100, "output_tokens": 10}));
+        trace.add_span(span);
+        trace.duration_ms = Some(ms);
+        trace
+    }
+
+    #[test]
+    fn test_report_compares_variants() {
+        let mut report = ExperimentReport::default();
+        let mut scored = trace("control", 100, "rlm", Some("final_answer"));
+        scored.spans[0].children[0].record_event("rlm_quality", Some(json!({"score": 0.8})));
+        report.record_trace(&scored, None);
+        report.record_trace(&trace("control", 300, "rlm", Some("budget_exceeded")), None);
+        report.record_trace(&trace("terse", 50, "passthrough", None), None);
+        report.record_trace(&trace("terse", 70, "rlm", Some("final_answer")), None);
+        report.record_trace(&Trace::new("untagged"), None);
+        report.record_trace(&trace("control", 1, "rlm", None), Some("other"));
+        report.finish();
+
+        let variants = &report.experiments["router-prompt"];
+        let control = &variants["control"];
+        assert_eq!((control.requests, control.rlm), (2, 2));
+        assert_eq!(control.mean_latency_ms, 200);
+        assert_eq!(control.input_tokens, 200);
+        assert_eq!(control.terminations["budget_exceeded"], 1);
+        assert_eq!(control.mean_quality, Some(0.8));
+        let terse = &variants["terse"];
+        assert_eq!((terse.requests, terse.rlm), (2, 1));
+        assert_eq!(terse.p50_latency_ms, 50);
+        assert_eq!(terse.mean_quality, None);
+
+        let markdown = report.to_markdown();
+        assert!(
+            markdown.contains("| control | 2 | 2 | 0 | 200 |"),
+            "{markdown}"
+        );
+        assert!(
+            markdown.contains("| terse | final_answer 1 |"),
+            "{markdown}"
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! Human-readable reports of RLM explorations.
+//!
+//! Traces record everything an exploration did, but as nested JSON spans.
+//! An [`ExplorationReport`] pulls the parts worth auditing out of one
+//! request's trace: the routing decision, every tool call, the files read
+//! (with line ranges), the budget consumed and, from the proxy, the
+//! question and final answer. With a session directory the proxy writes one
+//! markdown report per RLM request to [`EXPLORATION_REPORT_DIR`], and
+//! `muninn sessions show --explorations` lists them.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use muninn_tracing::{Span, Trace};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Directory of the reports inside the session directory.
+pub const EXPLORATION_REPORT_DIR: &str = "explorations";
+
+/// Tool inputs longer than this are cut in the tool table.
+const MAX_INPUT_CHARS: usize = 80;
+
+/// One tool call made during the exploration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportToolCall {
+    pub tool: String,
+    pub input: Value,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Per-tool limit that stopped or cut short the call.
+    pub limit: Option<String>,
+}
+
+/// Budget the exploration consumed, from its `rlm_completion` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportBudget {
+    pub tokens_used: u64,
+    pub tool_calls: u64,
+    pub depth_reached: u64,
+    pub duration_ms: u64,
+    /// How the exploration ended, e.g. `final_answer` or `budget_exceeded`.
+    pub termination_reason: String,
+}
+
+/// Audit report of one RLM exploration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExplorationReport {
+    pub trace_id: String,
+    pub started_at: DateTime<Utc>,
+    /// Model the exploration ran on.
+    pub model: Option<String>,
+    pub question: Option<String>,
+    /// Router decision reason; `None` for explicit `muninn.recursive`
+    /// requests, which skip the router.
+    pub route_reason: Option<String>,
+    /// Router category that picked the exploration prompt.
+    pub category: Option<String>,
+    /// Model turns taken.
+    pub turns: usize,
+    pub tool_calls: Vec<ReportToolCall>,
+    /// Line ranges read per file; an empty list means the whole file.
+    pub files_read: BTreeMap<String, Vec<(u64, u64)>>,
+    pub budget: Option<ReportBudget>,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ExplorationReport {
+    /// Build a report from a request's trace. `None` if the trace has no
+    /// exploration (`rlm_cycle` span) in it.
+    pub fn from_trace(trace: &Trace) -> Option<Self> {
+        let cycle = find_span(&trace.spans, "rlm_cycle")?;
+        let data = cycle.data.as_ref();
+        let mut report = Self {
+            trace_id: trace.trace_id.clone(),
+            started_at: trace.started_at,
+            model: data.and_then(|d| str_field(d, "model")),
+            question: None,
+            route_reason: None,
+            category: data.and_then(|d| str_field(d, "category")),
+            turns: 0,
+            tool_calls: Vec::new(),
+            files_read: BTreeMap::new(),
+            budget: None,
+            answer: None,
+            error: None,
+        };
+        if let Some(router) = find_span(&trace.spans, "router_decision")
+            .and_then(|s| s.data.as_ref())
+            .filter(|d| d["decision"] == "rlm")
+        {
+            report.route_reason = str_field(router, "reason");
+            report.question = str_field(router, "last_user_message");
+        }
+        report.record_span(cycle);
+        report.budget = cycle
+            .events
+            .iter()
+            .rev()
+            .find(|e| e.name == "rlm_completion")
+            .and_then(|e| e.data.as_ref())
+            .map(|d| ReportBudget {
+                tokens_used: d["tokens_used"].as_u64().unwrap_or_default(),
+                tool_calls: d["tool_calls"].as_u64().unwrap_or_default(),
+                depth_reached: d["depth_reached"].as_u64().unwrap_or_default(),
+                duration_ms: d["duration_ms"].as_u64().unwrap_or_default(),
+                termination_reason: str_field(d, "termination_reason").unwrap_or_default(),
+            });
+        Some(report)
+    }
+
+    /// Set the question, replacing the router's copy.
+    pub fn with_question(mut self, question: Option<String>) -> Self {
+        if question.is_some() {
+            self.question = question;
+        }
+        self
+    }
+
+    /// Set the final answer, or the error the exploration failed with.
+    pub fn with_outcome(mut self, outcome: std::result::Result<String, String>) -> Self {
+        match outcome {
+            Ok(answer) => self.answer = Some(answer),
+            Err(error) => self.error = Some(error),
+        }
+        self
+    }
+
+    /// File name of the report: start time, then trace ID.
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}-{}.md",
+            self.started_at.format("%Y%m%dT%H%M%SZ"),
+            self.trace_id
+        )
+    }
+
+    fn record_span(&mut self, span: &Span) {
+        match (span.name.as_str(), &span.data) {
+            ("rlm_iteration", _) => self.turns += 1,
+            ("tool_execution", Some(data)) => {
+                let call = ReportToolCall {
+                    tool: str_field(data, "tool_name").unwrap_or_default(),
+                    input: data["input"].clone(),
+                    success: data["success"].as_bool().unwrap_or(false),
+                    duration_ms: data["execution_time_ms"].as_u64().unwrap_or_default(),
+                    limit: str_field(data, "limit"),
+                };
+                if call.tool == "read_file"
+                    && call.success
+                    && let Some(path) = call.input["path"].as_str()
+                {
+                    let range = call.input["start_line"]
+                        .as_u64()
+                        .zip(call.input["end_line"].as_u64());
+                    match (self.files_read.get_mut(path), range) {
+                        (None, range) => {
+                            self.files_read
+                                .insert(path.to_string(), range.into_iter().collect());
+                        }
+                        // Already read whole
+                        (Some(ranges), _) if ranges.is_empty() => {}
+                        (Some(ranges), Some(range)) => {
+                            if !ranges.contains(&range) {
+                                ranges.push(range);
+                            }
+                        }
+                        (Some(ranges), None) => ranges.clear(),
+                    }
+                }
+                self.tool_calls.push(call);
+            }
+            _ => {}
+        }
+        for child in &span.children {
+            self.record_span(child);
+        }
+    }
+
+    /// Render as markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Exploration {}\n\n",
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        out.push_str(&format!("- Trace: `{}`\n", self.trace_id));
+        if let Some(model) = &self.model {
+            out.push_str(&format!("- Model: `{}`\n", model));
+        }
+
+        out.push_str("\n## Question\n\n");
+        out.push_str(self.question.as_deref().unwrap_or("(unknown)"));
+        out.push('\n');
+
+        out.push_str("\n## Decisions\n\n");
+        match &self.route_reason {
+            Some(reason) => out.push_str(&format!("- Routed to the RLM: {}\n", reason)),
+            None => out.push_str("- Explicit RLM request (router skipped)\n"),
+        }
+        out.push_str(&format!(
+            "- Prompt category: {}\n",
+            self.category.as_deref().unwrap_or("generic")
+        ));
+        out.push_str(&format!("- Model turns: {}\n", self.turns));
+        if let Some(budget) = &self.budget {
+            out.push_str(&format!("- Stopped: {}\n", budget.termination_reason));
+        }
+        for call in self.tool_calls.iter().filter(|c| c.limit.is_some()) {
+            out.push_str(&format!(
+                "- Tool limit hit on `{}`: {}\n",
+                call.tool,
+                call.limit.as_deref().unwrap_or_default()
+            ));
+        }
+
+        out.push_str("\n## Tools used\n\n");
+        if self.tool_calls.is_empty() {
+            out.push_str("None.\n");
+        } else {
+            out.push_str("| # | Tool | Input | Result | Time |\n|---|---|---|---|---|\n");
+            for (i, call) in self.tool_calls.iter().enumerate() {
+                out.push_str(&format!(
+                    "| {} | `{}` | `{}` | {} | {} ms |\n",
+                    i + 1,
+                    call.tool,
+                    table_input(&call.input),
+                    if call.success { "ok" } else { "error" },
+                    call.duration_ms
+                ));
+            }
+        }
+
+        out.push_str("\n## Files read\n\n");
+        if self.files_read.is_empty() {
+            out.push_str("None.\n");
+        }
+        for (path, ranges) in &self.files_read {
+            if ranges.is_empty() {
+                out.push_str(&format!("- `{}` (whole file)\n", path));
+            } else {
+                let ranges: Vec<String> = ranges.iter().map(|(s, e)| format!("{s}-{e}")).collect();
+                out.push_str(&format!("- `{}` lines {}\n", path, ranges.join(", ")));
+            }
+        }
+
+        out.push_str("\n## Budget consumed\n\n");
+        match &self.budget {
+            Some(budget) => out.push_str(&format!(
+                "- Tokens: {}\n- Tool calls: {}\n- Depth: {}\n- Duration: {:.1}s\n",
+                budget.tokens_used,
+                budget.tool_calls,
+                budget.depth_reached,
+                budget.duration_ms as f64 / 1000.0
+            )),
+            None => out.push_str("Not recorded.\n"),
+        }
+
+        out.push_str("\n## Final answer\n\n");
+        match (&self.answer, &self.error) {
+            (Some(answer), _) => out.push_str(answer.trim()),
+            (None, Some(error)) => out.push_str(&format!("Exploration failed: {}", error)),
+            (None, None) => out.push_str("(none)"),
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Writes [`ExplorationReport`]s as markdown files into a directory.
+#[derive(Debug)]
+pub struct ExplorationReports {
+    dir: PathBuf,
+}
+
+impl ExplorationReports {
+    /// Write reports to [`EXPLORATION_REPORT_DIR`] inside `session_dir`.
+    pub fn in_dir(session_dir: &Path) -> Self {
+        Self {
+            dir: session_dir.join(EXPLORATION_REPORT_DIR),
+        }
+    }
+
+    /// Directory the reports go to.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write `report`. Failures are logged, never surfaced to the request
+    /// path.
+    pub fn write(&self, report: &ExplorationReport) -> Option<PathBuf> {
+        let path = self.dir.join(report.file_name());
+        let result = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&path, report.to_markdown()));
+        match result {
+            Ok(()) => Some(path),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to write exploration report");
+                None
+            }
+        }
+    }
+}
+
+/// A written report, as listed by `muninn sessions show --explorations`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExplorationReportSummary {
+    pub path: PathBuf,
+    /// The report's title line, without the `# `.
+    pub title: String,
+    /// First line of the question.
+    pub question: Option<String>,
+}
+
+/// The reports in a session directory, oldest first.
+pub fn exploration_reports(session_dir: &Path) -> Vec<ExplorationReportSummary> {
+    let Ok(entries) = std::fs::read_dir(session_dir.join(EXPLORATION_REPORT_DIR)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "md"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            let mut lines = content.lines();
+            let title = lines.next()?.trim_start_matches("# ").to_string();
+            let question = lines
+                .skip_while(|l| *l != "## Question")
+                .skip(1)
+                .find(|l| !l.trim().is_empty())
+                .map(String::from);
+            Some(ExplorationReportSummary {
+                path,
+                title,
+                question,
+            })
+        })
+        .collect()
+}
+
+fn find_span<'a>(spans: &'a [Span], name: &str) -> Option<&'a Span> {
+    spans.iter().find_map(|span| {
+        if span.name == name {
+            Some(span)
+        } else {
+            find_span(&span.children, name)
+        }
+    })
+}
+
+fn str_field(data: &Value, key: &str) -> Option<String> {
+    data.get(key).and_then(Value::as_str).map(String::from)
+}
+
+/// Tool input as one short line for a table cell.
+fn table_input(input: &Value) -> String {
+    let text = input.to_string().replace('|', "\\|").replace('`', "'");
+    match text.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace() -> Trace {
+        let mut trace = Trace::new("trace-1");
+        let mut request = Span::new("proxy_request");
+        request.add_child(Span::new("router_decision").with_data(json!({
+            "decision": "rlm",
+            "reason": "Router LLM: needs code",
+            "last_user_message": "Where is config loaded?"
+        })));
+        let mut cycle =
+            Span::new("rlm_cycle").with_data(json!({"model": "qwen", "category": "code_question"}));
+        cycle.add_child(Span::new("rlm_iteration"));
+        for input in [
+            json!({"path": "src/config.rs", "start_line": 10, "end_line": 40}),
+            json!({"path": "src/config.rs", "start_line": 80, "end_line": 90}),
+            json!({"path": "src/main.rs"}),
+        ] {
+            cycle.add_child(Span::new("tool_execution").with_data(json!({
+                "tool_name": "read_file",
+                "input": input,
+                "success": true,
+                "execution_time_ms": 3
+            })));
+        }
+        cycle.add_child(Span::new("tool_execution").with_data(json!({
+            "tool_name": "search_files",
+            "input": {"pattern": "a|b"},
+            "success": false,
+            "execution_time_ms": 1,
+            "limit": "max_calls"
+        })));
+        cycle.add_child(Span::new("rlm_iteration"));
+        cycle.record_event(
+            "rlm_completion",
+            Some(json!({
+                "termination_reason": "final_answer",
+                "depth_reached": 2,
+                "tool_calls": 4,
+                "tokens_used": 1500,
+                "duration_ms": 2500,
+                "has_final_answer": true
+            })),
+        );
+        request.add_child(cycle);
+        trace.add_span(request);
+        trace
+    }
+
+    #[test]
+    fn test_report_from_trace() {
+        let report = ExplorationReport::from_trace(&trace())
+            .unwrap()
+            .with_outcome(Ok("It is loaded in src/config.rs.".to_string()));
+        assert_eq!(report.question.as_deref(), Some("Where is config loaded?"));
+        assert_eq!(report.category.as_deref(), Some("code_question"));
+        assert_eq!(report.turns, 2);
+        assert_eq!(report.tool_calls.len(), 4);
+        assert_eq!(report.files_read["src/config.rs"], [(10, 40), (80, 90)]);
+        assert!(report.files_read["src/main.rs"].is_empty());
+        assert_eq!(report.budget.as_ref().unwrap().tokens_used, 1500);
+
+        let markdown = report.to_markdown();
+        for expected in [
+            "## Question\n\nWhere is config loaded?\n",
+            "- Routed to the RLM: Router LLM: needs code\n",
+            "- Prompt category: code_question\n",
+            "- Tool limit hit on `search_files`: max_calls\n",
+            "| 4 | `search_files` | `{\"pattern\":\"a\\|b\"}` | error | 1 ms |\n",
+            "- `src/config.rs` lines 10-40, 80-90\n",
+            "- `src/main.rs` (whole file)\n",
+            "- Tokens: 1500\n",
+            "- Duration: 2.5s\n",
+            "## Final answer\n\nIt is loaded in src/config.rs.\n",
+        ] {
+            assert!(markdown.contains(expected), "{expected}\n---\n{markdown}");
+        }
+
+        assert!(ExplorationReport::from_trace(&Trace::new("empty")).is_none());
+    }
+
+    #[test]
+    fn test_write_and_list_reports() {
+        let temp = tempfile::tempdir().unwrap();
+        assert!(exploration_reports(temp.path()).is_empty());
+
+        let report = ExplorationReport::from_trace(&trace())
+            .unwrap()
+            .with_question(Some("Where is config loaded?\nDetails follow.".to_string()))
+            .with_outcome(Err("budget exceeded".to_string()));
+        let path = ExplorationReports::in_dir(temp.path())
+            .write(&report)
+            .unwrap();
+        assert!(path.starts_with(temp.path().join(EXPLORATION_REPORT_DIR)));
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("Exploration failed: budget exceeded")
+        );
+
+        let listed = exploration_reports(temp.path());
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].title.starts_with("Exploration "));
+        assert_eq!(
+            listed[0].question.as_deref(),
+            Some("Where is config loaded?")
+        );
+    }
+}
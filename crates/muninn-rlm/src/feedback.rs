@@ -0,0 +1,250 @@
+//! User feedback on answers.
+//!
+//! A user message starting a line with `@muninn good` or `@muninn bad`
+//! (optionally followed by a comment), or a `POST /feedback`, rates the
+//! previous request. Ratings are appended to `feedback.jsonl` in the
+//! session directory with the rated trace and the question that started
+//! it; joined with the spend ledger on the trace id, they form a labeled
+//! dataset of routes and answers for tuning the router and judging answer
+//! quality.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// File name of the feedback log inside the session directory.
+pub const FEEDBACK_FILE: &str = "feedback.jsonl";
+
+/// `@muninn good` / `@muninn bad` at the start of a line, then a comment.
+static FEEDBACK_TRIGGER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?im)^@muninn\s+(good|bad)\b[ \t:,.!-]*(.*)$").expect("Invalid regex")
+});
+
+/// Thumbs up or down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Good,
+    Bad,
+}
+
+impl Rating {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rating::Good => "good",
+            Rating::Bad => "bad",
+        }
+    }
+}
+
+/// A rating with an optional comment.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Feedback {
+    pub rating: Rating,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+impl Feedback {
+    /// The feedback trigger in a user message, if there is one.
+    pub fn parse(text: &str) -> Option<Self> {
+        let captures = FEEDBACK_TRIGGER.captures(text)?;
+        let rating = if captures[1].eq_ignore_ascii_case("good") {
+            Rating::Good
+        } else {
+            Rating::Bad
+        };
+        let comment = captures[2].trim();
+        Some(Self {
+            rating,
+            comment: (!comment.is_empty()).then(|| comment.to_string()),
+        })
+    }
+}
+
+/// One line of the feedback log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackEntry {
+    /// When the feedback was given.
+    pub timestamp: DateTime<Utc>,
+    /// Trace of the rated request.
+    pub trace_id: String,
+    pub rating: Rating,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// The user message that started the rated turn, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub question: Option<String>,
+}
+
+/// The request feedback applies to by default.
+#[derive(Debug, Clone)]
+struct RatedRequest {
+    trace_id: String,
+    question: Option<String>,
+}
+
+/// Append-only JSONL feedback log, remembering the latest request so a
+/// rating without a trace id applies to it.
+#[derive(Debug)]
+pub struct FeedbackLog {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+    last: Mutex<Option<RatedRequest>>,
+}
+
+impl FeedbackLog {
+    /// Create a log writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Create a log writing [`FEEDBACK_FILE`] inside `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(FEEDBACK_FILE))
+    }
+
+    /// Path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Note a request as the one the next rating applies to. A request
+    /// without a question of its own (a tool-result turn) continues the
+    /// turn of the previous one.
+    pub fn remember(&self, trace_id: &str, question: Option<String>) {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let question = question.or_else(|| last.take().and_then(|l| l.question));
+        *last = Some(RatedRequest {
+            trace_id: trace_id.to_string(),
+            question,
+        });
+    }
+
+    /// Record `feedback` on `trace_id`, or on the remembered request.
+    /// `None` when there is nothing to rate.
+    pub fn rate(&self, feedback: Feedback, trace_id: Option<String>) -> Option<FeedbackEntry> {
+        let last = self.last.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let (trace_id, question) = match (trace_id, last) {
+            (Some(id), Some(last)) if id == last.trace_id => (id, last.question),
+            (Some(id), _) => (id, None),
+            (None, Some(last)) => (last.trace_id, last.question),
+            (None, None) => return None,
+        };
+        let entry = FeedbackEntry {
+            timestamp: Utc::now(),
+            trace_id,
+            rating: feedback.rating,
+            comment: feedback.comment,
+            question,
+        };
+        self.record(&entry);
+        Some(entry)
+    }
+
+    /// Append an entry. Failures are logged, never surfaced to the request.
+    pub fn record(&self, entry: &FeedbackEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to serialize feedback entry");
+                return;
+            }
+        };
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write feedback entry");
+        }
+    }
+
+    /// Read all entries from the log.
+    pub fn read(&self) -> std::io::Result<Vec<FeedbackEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_trigger() {
+        assert_eq!(
+            Feedback::parse("@muninn good"),
+            Some(Feedback {
+                rating: Rating::Good,
+                comment: None
+            })
+        );
+        assert_eq!(
+            Feedback::parse("thanks\n@Muninn BAD: missed the caller in ci.rs"),
+            Some(Feedback {
+                rating: Rating::Bad,
+                comment: Some("missed the caller in ci.rs".to_string())
+            })
+        );
+        assert_eq!(Feedback::parse("is this @muninn good?"), None);
+        assert_eq!(Feedback::parse("@muninn goodness"), None);
+    }
+
+    #[test]
+    fn test_rate_applies_to_remembered_request() {
+        let dir = tempdir().unwrap();
+        let log = FeedbackLog::in_dir(dir.path());
+        let good = || Feedback {
+            rating: Rating::Good,
+            comment: None,
+        };
+        assert!(log.rate(good(), None).is_none());
+
+        log.remember("t1", Some("where is the socket path computed?".to_string()));
+        log.remember("t2", None);
+        let entry = log.rate(good(), None).unwrap();
+        assert_eq!(entry.trace_id, "t2");
+        assert_eq!(
+            entry.question.as_deref(),
+            Some("where is the socket path computed?")
+        );
+
+        let entry = log
+            .rate(
+                Feedback {
+                    rating: Rating::Bad,
+                    comment: Some("wrong file".to_string()),
+                },
+                Some("t0".to_string()),
+            )
+            .unwrap();
+        assert_eq!(entry.question, None);
+
+        let entries = log.read().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].rating, Rating::Bad);
+        assert_eq!(entries[1].comment.as_deref(), Some("wrong file"));
+    }
+}
@@ -7,12 +7,14 @@
 //! with mock filesystems.
 
 use async_trait::async_trait;
+use serde::Deserialize;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::error::{Result, RlmError};
 use crate::fs::{RealFileSystem, SharedFileSystem};
-use crate::tools::{Tool, ToolMetadata, ToolResult};
+use crate::tool_params::ToolParams;
+use crate::tools::{PageInfo, Tool, ToolCategory, ToolMetadata, ToolResult};
 
 /// Map a language tag (e.g. `"rust"`) to a typical filename glob
 /// (`"*.rs"`). Returns `None` for languages we don't have a
@@ -181,6 +183,17 @@ impl ReadFileTool {
     }
 }
 
+/// Parameters for [`ReadFileTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct ReadFileParams {
+    /// Path to the file (relative to repository root or absolute)
+    path: String,
+    /// First line to read (1-indexed). Omit to start from beginning.
+    start_line: Option<usize>,
+    /// Last line to read (inclusive). Omit to read to end.
+    end_line: Option<usize>,
+}
+
 #[async_trait]
 impl Tool for ReadFileTool {
     fn name(&self) -> &str {
@@ -196,40 +209,16 @@ impl Tool for ReadFileTool {
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "path": {
-                    "type": "string",
-                    "description": "Path to the file (relative to repository root or absolute)"
-                },
-                "start_line": {
-                    "type": "integer",
-                    "description": "First line to read (1-indexed). Omit to start from beginning."
-                },
-                "end_line": {
-                    "type": "integer",
-                    "description": "Last line to read (inclusive). Omit to read to end."
-                }
-            },
-            "required": ["path"]
-        })
+        ReadFileParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let path = params.get("path").and_then(|v| v.as_str()).ok_or_else(|| {
-            RlmError::ToolExecution("Missing required parameter 'path'".to_string())
-        })?;
-
-        let start_line = params
-            .get("start_line")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as usize);
-
-        let end_line = params
-            .get("end_line")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as usize);
+        let ReadFileParams {
+            path,
+            start_line,
+            end_line,
+        } = ReadFileParams::from_params(params)?;
+        let path = path.as_str();
 
         // Resolve and validate path
         let full_path = self.resolve_path(path).await?;
@@ -331,15 +320,20 @@ impl Tool for ReadFileTool {
 // ListDirectoryTool
 // ============================================================================
 
+/// Entries a listing collects before sorting and paging. Pages past it
+/// are not reachable.
+const LIST_SCAN_LIMIT: usize = 10_000;
+
 /// Tool for listing directory contents.
 ///
-/// Supports glob patterns and respects .gitignore.
+/// Supports glob patterns and respects .gitignore. Listings are paged by
+/// `max_entries`.
 pub struct ListDirectoryTool {
     /// Filesystem abstraction for file operations.
     fs: SharedFileSystem,
     /// Root directory.
     root: PathBuf,
-    /// Maximum entries to return.
+    /// Entries per page.
     max_entries: usize,
 }
 
@@ -362,7 +356,7 @@ impl ListDirectoryTool {
         }
     }
 
-    /// Set maximum entries to return.
+    /// Set entries per page.
     pub fn with_max_entries(mut self, entries: usize) -> Self {
         self.max_entries = entries;
         self
@@ -399,6 +393,20 @@ impl ListDirectoryTool {
     }
 }
 
+/// Parameters for [`ListDirectoryTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct ListDirectoryParams {
+    /// Directory path to list (relative or absolute)
+    path: Option<String>,
+    /// Glob pattern to filter results (e.g., '*.rs', '**/*.py')
+    pattern: Option<String>,
+    /// List recursively (default: false)
+    #[serde(default)]
+    recursive: bool,
+    /// next_cursor of the previous page; omit for the first page
+    cursor: Option<String>,
+}
+
 #[async_trait]
 impl Tool for ListDirectoryTool {
     fn name(&self) -> &str {
@@ -414,35 +422,19 @@ impl Tool for ListDirectoryTool {
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "path": {
-                    "type": "string",
-                    "description": "Directory path to list (relative or absolute)"
-                },
-                "pattern": {
-                    "type": "string",
-                    "description": "Glob pattern to filter results (e.g., '*.rs', '**/*.py')"
-                },
-                "recursive": {
-                    "type": "boolean",
-                    "description": "List recursively (default: false)"
-                }
-            },
-            "required": ["path"]
-        })
+        ListDirectoryParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-
-        let pattern = params.get("pattern").and_then(|v| v.as_str());
-
-        let recursive = params
-            .get("recursive")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        let ListDirectoryParams {
+            path,
+            pattern,
+            recursive,
+            cursor,
+        } = ListDirectoryParams::from_params(params)?;
+        let offset = PageInfo::offset(cursor.as_deref())?;
+        let path = path.as_deref().unwrap_or(".");
+        let pattern = pattern.as_deref();
 
         // Resolve path
         let full_path = self.resolve_path(path).await?;
@@ -464,14 +456,9 @@ impl Tool for ListDirectoryTool {
             self.list_single(&full_path, pattern, &mut entries).await?;
         }
 
-        // Sort entries
+        // Sort the whole listing so pages don't depend on walk order
         entries.sort();
-
-        // Truncate if needed
-        let truncated = entries.len() > self.max_entries;
-        if truncated {
-            entries.truncate(self.max_entries);
-        }
+        let (entries, page) = PageInfo::paginate(entries, offset, self.max_entries);
 
         // Format output
         let display_path = full_path
@@ -486,17 +473,10 @@ impl Tool for ListDirectoryTool {
             output.push('\n');
         }
 
-        if truncated {
-            output.push_str(&format!(
-                "\n... ({} more entries truncated)",
-                self.max_entries
-            ));
-        }
-
         let mut result = ToolResult::text(output);
         result.metadata = ToolMetadata::with_source(&display_path).with_tag("directory");
 
-        Ok(result)
+        Ok(result.with_page(page))
     }
 }
 
@@ -531,7 +511,7 @@ impl ListDirectoryTool {
             let suffix = if entry.is_dir { "/" } else { "" };
             entries.push(format!("{}{}", name, suffix));
 
-            if entries.len() >= self.max_entries {
+            if entries.len() >= LIST_SCAN_LIMIT {
                 break;
             }
         }
@@ -555,7 +535,7 @@ impl ListDirectoryTool {
         pattern: Option<&str>,
         entries: &mut Vec<String>,
     ) -> Result<()> {
-        if entries.len() >= self.max_entries {
+        if entries.len() >= LIST_SCAN_LIMIT {
             return Ok(());
         }
 
@@ -603,7 +583,7 @@ impl ListDirectoryTool {
                 entries.push(relative);
             }
 
-            if entries.len() >= self.max_entries {
+            if entries.len() >= LIST_SCAN_LIMIT {
                 break;
             }
         }
@@ -901,6 +881,22 @@ struct ContextLine {
     is_match: bool,
 }
 
+/// Parameters for [`SearchFilesTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct SearchFilesParams {
+    /// Search pattern (regex supported)
+    query: String,
+    /// Directory to search in (default: repository root)
+    path: Option<String>,
+    /// Filter files by pattern (e.g., '*.rs', '*.py')
+    file_pattern: Option<String>,
+    /// Case-sensitive search (default: false)
+    #[serde(default)]
+    case_sensitive: bool,
+    /// next_cursor of the previous page; omit for the first page
+    cursor: Option<String>,
+}
+
 #[async_trait]
 impl Tool for SearchFilesTool {
     fn name(&self) -> &str {
@@ -916,46 +912,21 @@ impl Tool for SearchFilesTool {
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "query": {
-                    "type": "string",
-                    "description": "Search pattern (regex supported)"
-                },
-                "path": {
-                    "type": "string",
-                    "description": "Directory to search in (default: repository root)"
-                },
-                "file_pattern": {
-                    "type": "string",
-                    "description": "Filter files by pattern (e.g., '*.rs', '*.py')"
-                },
-                "case_sensitive": {
-                    "type": "boolean",
-                    "description": "Case-sensitive search (default: false)"
-                }
-            },
-            "required": ["query"]
-        })
+        SearchFilesParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let query = params
-            .get("query")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                RlmError::ToolExecution("Missing required parameter 'query'".to_string())
-            })?;
-
-        let path = params.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-
-        let file_pattern = params.get("file_pattern").and_then(|v| v.as_str());
-
-        let case_sensitive = params
-            .get("case_sensitive")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        let SearchFilesParams {
+            query,
+            path,
+            file_pattern,
+            case_sensitive,
+            cursor,
+        } = SearchFilesParams::from_params(params)?;
+        let offset = PageInfo::offset(cursor.as_deref())?;
+        let query = query.as_str();
+        let path = path.as_deref().unwrap_or(".");
+        let file_pattern = file_pattern.as_deref();
 
         // Build regex
         let pattern = if case_sensitive {
@@ -985,16 +956,25 @@ impl Tool for SearchFilesTool {
             return Ok(ToolResult::error(format!("Path not found: {}", path), true));
         }
 
-        // Search
+        // Search up to one match past the requested page, in walk order
+        let walker = SearchFilesTool {
+            fs: self.fs.clone(),
+            root: self.root.clone(),
+            max_results: offset + self.max_results + 1,
+            context_lines: self.context_lines,
+        };
         let mut results: Vec<SearchMatch> = Vec::new();
 
         if self.fs.is_file(&search_path).await {
-            self.search_file(&search_path, &pattern, &mut results)
+            walker
+                .search_file(&search_path, &pattern, &mut results)
                 .await?;
         } else {
-            self.search_dir(&search_path, &pattern, file_pattern, &mut results)
+            walker
+                .search_dir(&search_path, &pattern, file_pattern, &mut results)
                 .await?;
         }
+        let (results, page) = PageInfo::paginate(results, offset, self.max_results);
 
         // Format output
         if results.is_empty() {
@@ -1015,19 +995,10 @@ impl Tool for SearchFilesTool {
             output.push('\n');
         }
 
-        let truncated = results.len() >= self.max_results;
-        if truncated {
-            output.push_str(&format!("(showing first {} results)\n", self.max_results));
-        }
-
         let mut result = ToolResult::text(output);
         result.metadata = ToolMetadata::with_source(query).with_tag("search");
 
-        if truncated {
-            result.metadata.tags.push("truncated".to_string());
-        }
-
-        Ok(result)
+        Ok(result.with_page(page))
     }
 }
 
@@ -1061,6 +1032,10 @@ impl Tool for FinalAnswerTool {
         "final_answer"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Core
+    }
+
     fn description(&self) -> &str {
         "Signal completion and provide the final answer to the user's query. Call this when you have gathered sufficient context and are ready to respond. The answer should be comprehensive and directly address the user's question."
     }
@@ -1236,6 +1211,27 @@ mod tests {
         assert!(content.contains("src/"));
     }
 
+    #[tokio::test]
+    async fn test_list_directory_pages() {
+        let dir = setup_test_dir();
+        let tool = ListDirectoryTool::new(dir.path()).with_max_entries(2);
+
+        let first = tool.execute(serde_json::json!({})).await.unwrap();
+        let page = first.metadata.page.clone().unwrap();
+        assert!(page.has_more);
+        assert_eq!(page.returned, 2);
+
+        let second = tool
+            .execute(serde_json::json!({"cursor": page.next_cursor}))
+            .await
+            .unwrap();
+        let page = second.metadata.page.clone().unwrap();
+        assert!(!page.has_more);
+        assert_eq!(page.offset, 2);
+        assert!(second.to_string_content().contains("src/"));
+        assert!(!second.to_string_content().contains("More results"));
+    }
+
     #[tokio::test]
     async fn test_list_directory_with_pattern() {
         let dir = setup_test_dir();
@@ -0,0 +1,143 @@
+//! HTTP service serving a graph store to remote clients.
+//!
+//! Lets one machine hold the index of a huge monorepo while laptops read it
+//! with `[graph] url = "..."` (see [`muninn_graph::remote`] for the client
+//! and the wire format). Only read-only Cypher is accepted.
+
+use std::net::SocketAddr;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use muninn_graph::remote::{QueryRequest, QueryResponse, StatsResponse};
+use muninn_graph::{GraphStore, is_read_only_cypher};
+use tokio::net::TcpListener;
+
+use crate::graph_tools::SharedGraphStore;
+
+/// Router for the graph service endpoints.
+pub fn graph_service_router(store: SharedGraphStore) -> Router {
+    Router::new()
+        .route("/query", post(handle_query))
+        .route("/stats", get(handle_stats))
+        .with_state(store)
+}
+
+/// Serve `store` on `addr` until the process exits.
+pub async fn serve_graph(store: SharedGraphStore, addr: SocketAddr) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(addr = %addr, "Serving code graph");
+    axum::serve(listener, graph_service_router(store)).await
+}
+
+/// Status and message of a failed request.
+type Failure = (StatusCode, String);
+
+/// Run `f` on the locked store off the async runtime.
+async fn with_store<T, F>(store: SharedGraphStore, f: F) -> Result<T, Failure>
+where
+    T: Send + 'static,
+    F: FnOnce(&GraphStore) -> muninn_graph::store::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let store = store
+            .lock()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        f(&store).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+async fn handle_query(
+    State(store): State<SharedGraphStore>,
+    Json(request): Json<QueryRequest>,
+) -> Response {
+    if !is_read_only_cypher(&request.cypher) {
+        return (StatusCode::FORBIDDEN, "graph service is read-only").into_response();
+    }
+    let result = with_store(store, move |store| {
+        store
+            .query(&request.cypher)
+            .map(|result| QueryResponse::from_result(&result))
+    })
+    .await;
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(failure) => failure.into_response(),
+    }
+}
+
+async fn handle_stats(State(store): State<SharedGraphStore>) -> Response {
+    match with_store(store, |store| store.stats()).await {
+        Ok(stats) => Json(StatsResponse {
+            node_count: stats.node_count,
+            edge_count: stats.edge_count,
+        })
+        .into_response(),
+        Err(failure) => failure.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_tools::wrap_store;
+    use muninn_graph::{StoreError, Symbol, SymbolKind};
+    use serial_test::serial;
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_remote_store_reads_served_graph() {
+        let local = GraphStore::open_in_memory().unwrap();
+        local
+            .insert_node(&Symbol::new(
+                "main",
+                SymbolKind::Function,
+                "src/main.rs",
+                1,
+                3,
+            ))
+            .unwrap();
+        local
+            .insert_node(&Symbol::new(
+                "helper",
+                SymbolKind::Function,
+                "src/util.rs",
+                1,
+                3,
+            ))
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = graph_service_router(wrap_store(local));
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+
+        tokio::task::spawn_blocking(move || {
+            let remote = GraphStore::open_remote(&url).unwrap();
+            assert!(remote.is_remote());
+
+            let found = remote.find_by_name("helper").unwrap();
+            assert_eq!(found.len(), 1);
+            let paths = remote.file_paths().unwrap();
+            assert!(paths.contains("src/main.rs") && paths.contains("src/util.rs"));
+            assert_eq!(remote.stats().unwrap().node_count, 2);
+
+            let symbol = Symbol::new("new_fn", SymbolKind::Function, "src/new.rs", 1, 2);
+            assert!(matches!(
+                remote.insert_node(&symbol),
+                Err(StoreError::ReadOnly(_))
+            ));
+            let err = remote.query("MATCH (n) DETACH DELETE n").unwrap_err();
+            assert!(err.to_string().contains("read-only"), "{}", err);
+            assert_eq!(remote.stats().unwrap().node_count, 2);
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -4,13 +4,19 @@
 //! Cypher queries, finding callers/callees, and finding implementations.
 
 use async_trait::async_trait;
-use std::sync::{Arc, Mutex};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use graphqlite::Value;
-use muninn_graph::GraphStore;
+use muninn_graph::{AuditReport, CycleLevel, CycleReport, GraphStore, Sensitivity, value_to_json};
 
 use crate::error::{Result, RlmError};
-use crate::tools::{Tool, ToolMetadata, ToolResult};
+use crate::tool_params::ToolParams;
+use crate::tools::{
+    PageInfo, Tool, ToolCategory, ToolEnvironment, ToolExposure, ToolMetadata, ToolRegistry,
+    ToolResult,
+};
+use crate::types::{ToolDefinition, ToolResultBlock, ToolUseBlock};
 
 /// Thread-safe wrapper around GraphStore.
 pub type SharedGraphStore = Arc<Mutex<GraphStore>>;
@@ -57,48 +63,45 @@ impl GraphQueryTool {
     }
 }
 
+/// Parameters for [`GraphQueryTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct GraphQueryParams {
+    /// Cypher query to execute (e.g., 'MATCH (n:Function) RETURN n.name LIMIT 10')
+    query: String,
+    /// Maximum results to return (default: 100)
+    limit: Option<usize>,
+    /// next_cursor of the previous page; omit for the first page
+    cursor: Option<String>,
+}
+
 #[async_trait]
 impl Tool for GraphQueryTool {
     fn name(&self) -> &str {
         "graph_query"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Execute a Cypher query against the code graph. Returns matching nodes and relationships. \
          Available node labels: File, Module, Class, Struct, Interface, Enum, Function, Method, Variable, Type, Macro. \
-         Available relationships: CONTAINS, IMPORTS, CALLS, INHERITS, IMPLEMENTS, USES_TYPE, REFERENCES."
+         Available relationships: CONTAINS, CALLS, USES_TYPE, REFERENCES, \
+         INCLUDES (C/C++ file to included file), DEFINES (C/C++ definition to its declaration), \
+         IMPORTS (Java/Kotlin/C# file to imported type), INHERITS (type to the type it extends), \
+         IMPLEMENTS (type to an interface it implements)."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "query": {
-                    "type": "string",
-                    "description": "Cypher query to execute (e.g., 'MATCH (n:Function) RETURN n.name LIMIT 10')"
-                },
-                "limit": {
-                    "type": "integer",
-                    "description": "Maximum results to return (default: 100)"
-                }
-            },
-            "required": ["query"]
-        })
+        GraphQueryParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let query = params
-            .get("query")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                RlmError::ToolExecution("Missing required parameter 'query'".to_string())
-            })?;
-
-        let limit = params
-            .get("limit")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as usize)
-            .unwrap_or(self.max_results);
+        let params = GraphQueryParams::from_params(params)?;
+        let query = params.query.as_str();
+        let limit = params.limit.unwrap_or(self.max_results);
+        let offset = PageInfo::offset(params.cursor.as_deref())?;
 
         // Lock store and execute query
         let store = lock_store(&self.store)?;
@@ -109,7 +112,7 @@ impl Tool for GraphQueryTool {
 
         // Format results - convert each row to JSON using columns
         let mut rows: Vec<serde_json::Value> = Vec::new();
-        for row in cypher_result.iter().take(limit) {
+        for row in cypher_result.iter().skip(offset).take(limit) {
             let mut obj = serde_json::Map::new();
             for col in row.columns() {
                 if let Some(value) = row.get_value(col) {
@@ -120,23 +123,18 @@ impl Tool for GraphQueryTool {
         }
 
         let total = cypher_result.len();
-        let truncated = total > limit;
+        let page = PageInfo::new(offset, rows.len(), total > offset + rows.len());
 
         let output = serde_json::json!({
             "rows": rows,
             "count": rows.len(),
             "total": total,
-            "truncated": truncated
         });
 
         let mut result = ToolResult::json(output);
         result.metadata = ToolMetadata::with_source(query).with_tag("graph");
 
-        if truncated {
-            result.metadata.tags.push("truncated".to_string());
-        }
-
-        Ok(result)
+        Ok(result.with_page(page))
     }
 }
 
@@ -156,40 +154,38 @@ impl FindCallersTool {
     }
 }
 
+/// Parameters for [`FindCallersTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct FindCallersParams {
+    /// Name of the function to find callers for
+    function_name: Option<String>,
+    /// Full ID of the function node (if known)
+    function_id: Option<String>,
+}
+
 #[async_trait]
 impl Tool for FindCallersTool {
     fn name(&self) -> &str {
         "find_callers"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Find all functions or methods that call a given function. \
          Provide either the function name or its full ID."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "function_name": {
-                    "type": "string",
-                    "description": "Name of the function to find callers for"
-                },
-                "function_id": {
-                    "type": "string",
-                    "description": "Full ID of the function node (if known)"
-                }
-            }
-        })
+        FindCallersParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let function_id = params
-            .get("function_id")
-            .and_then(|v| v.as_str())
-            .map(String::from);
-
-        let function_name = params.get("function_name").and_then(|v| v.as_str());
+        let params = FindCallersParams::from_params(params)?;
+        let function_id = params.function_id;
+        let function_name = params.function_name.as_deref();
 
         let store = lock_store(&self.store)?;
 
@@ -263,37 +259,38 @@ impl GetSymbolTool {
     }
 }
 
+/// Parameters for [`GetSymbolTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct GetSymbolParams {
+    /// Name of the symbol to look up
+    name: Option<String>,
+    /// Full ID of the symbol node (if known)
+    id: Option<String>,
+}
+
 #[async_trait]
 impl Tool for GetSymbolTool {
     fn name(&self) -> &str {
         "get_symbol"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Get details about a symbol (function, class, etc.) by name or ID. \
          Returns symbol metadata including file location, signature, and documentation."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "name": {
-                    "type": "string",
-                    "description": "Name of the symbol to look up"
-                },
-                "id": {
-                    "type": "string",
-                    "description": "Full ID of the symbol node (if known)"
-                }
-            }
-        })
+        GetSymbolParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let symbol_id = params.get("id").and_then(|v| v.as_str());
-
-        let symbol_name = params.get("name").and_then(|v| v.as_str());
+        let params = GetSymbolParams::from_params(params)?;
+        let symbol_id = params.id.as_deref();
+        let symbol_name = params.name.as_deref();
 
         let store = lock_store(&self.store)?;
 
@@ -371,12 +368,25 @@ impl ReadSymbolTool {
     }
 }
 
+/// Parameters for [`ReadSymbolTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct ReadSymbolParams {
+    /// Name of the symbol to read
+    name: Option<String>,
+    /// Full ID of the symbol node (if known)
+    id: Option<String>,
+}
+
 #[async_trait]
 impl Tool for ReadSymbolTool {
     fn name(&self) -> &str {
         "read_symbol"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Read the full source body of a symbol (function, method, etc.) \
          by name or id. Returns the complete scope (entire function/class \
@@ -386,24 +396,13 @@ impl Tool for ReadSymbolTool {
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "name": {
-                    "type": "string",
-                    "description": "Name of the symbol to read"
-                },
-                "id": {
-                    "type": "string",
-                    "description": "Full ID of the symbol node (if known)"
-                }
-            }
-        })
+        ReadSymbolParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let symbol_id = params.get("id").and_then(|v| v.as_str());
-        let symbol_name = params.get("name").and_then(|v| v.as_str());
+        let params = ReadSymbolParams::from_params(params)?;
+        let symbol_id = params.id.as_deref();
+        let symbol_name = params.name.as_deref();
 
         let store = lock_store(&self.store)?;
 
@@ -523,12 +522,35 @@ impl FindSymbolsTool {
     }
 }
 
+/// Parameters for [`FindSymbolsTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct FindSymbolsParams {
+    /// Symbol name to search for (case-sensitive substring match, e.g., 'Trace' matches 'TraceWriter')
+    name: String,
+    /// Filter by symbol type (optional)
+    #[tool(values(
+        "function", "struct", "trait", "enum", "method", "class", "module", "macro", "type",
+        "variable"
+    ))]
+    symbol_type: Option<String>,
+    /// Filter to files whose path contains this string (e.g., 'muninn-tracing' or 'src/engine')
+    path_contains: Option<String>,
+    /// Maximum results to return (default: 50)
+    limit: Option<usize>,
+    /// next_cursor of the previous page; omit for the first page
+    cursor: Option<String>,
+}
+
 #[async_trait]
 impl Tool for FindSymbolsTool {
     fn name(&self) -> &str {
         "find_symbols"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Search for code symbols (functions, structs, traits, etc.) by name pattern and optional filters. \
          Returns matching symbols with their file location and documentation. Use this instead of graph_query \
@@ -536,45 +558,16 @@ impl Tool for FindSymbolsTool {
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "name": {
-                    "type": "string",
-                    "description": "Symbol name to search for (case-sensitive substring match, e.g., 'Trace' matches 'TraceWriter')"
-                },
-                "symbol_type": {
-                    "type": "string",
-                    "enum": ["function", "struct", "trait", "enum", "method", "class", "module", "macro", "type", "variable"],
-                    "description": "Filter by symbol type (optional)"
-                },
-                "path_contains": {
-                    "type": "string",
-                    "description": "Filter to files whose path contains this string (e.g., 'muninn-tracing' or 'src/engine')"
-                },
-                "limit": {
-                    "type": "integer",
-                    "description": "Maximum results to return (default: 50)"
-                }
-            },
-            "required": ["name"]
-        })
+        FindSymbolsParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let name = params.get("name").and_then(|v| v.as_str()).ok_or_else(|| {
-            RlmError::ToolExecution("Missing required parameter 'name'".to_string())
-        })?;
-
-        let symbol_type = params.get("symbol_type").and_then(|v| v.as_str());
-
-        let path_contains = params.get("path_contains").and_then(|v| v.as_str());
-
-        let limit = params
-            .get("limit")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as usize)
-            .unwrap_or(self.max_results);
+        let params = FindSymbolsParams::from_params(params)?;
+        let name = params.name.as_str();
+        let symbol_type = params.symbol_type.as_deref();
+        let path_contains = params.path_contains.as_deref();
+        let limit = params.limit.unwrap_or(self.max_results);
+        let offset = PageInfo::offset(params.cursor.as_deref())?;
 
         // Build Cypher query with optional filters
         let label_filter = match symbol_type {
@@ -615,7 +608,10 @@ impl Tool for FindSymbolsTool {
              n.start_line AS line, n.end_line AS end_line, n.signature AS signature, \
              n.doc_comment AS description, n.visibility AS visibility \
              ORDER BY n.file_path, n.start_line LIMIT {}",
-            match_pattern, where_clause, limit
+            match_pattern,
+            where_clause,
+            // One past the page tells whether more follow
+            offset + limit + 1
         );
 
         // Execute query
@@ -660,7 +656,7 @@ impl Tool for FindSymbolsTool {
             results.push(serde_json::Value::Object(obj));
         }
 
-        let total = results.len();
+        let (results, page) = PageInfo::paginate(results, offset, limit);
         let output = serde_json::json!({
             "query": {
                 "name": name,
@@ -668,14 +664,13 @@ impl Tool for FindSymbolsTool {
                 "path_contains": path_contains
             },
             "results": results,
-            "count": total,
-            "truncated": total >= limit
+            "count": results.len(),
         });
 
         let mut result = ToolResult::json(output);
         result.metadata = ToolMetadata::with_source(name).with_tag("symbols");
 
-        Ok(result)
+        Ok(result.with_page(page))
     }
 }
 
@@ -695,40 +690,38 @@ impl FindCalleesTool {
     }
 }
 
+/// Parameters for [`FindCalleesTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct FindCalleesParams {
+    /// Name of the function to find callees for
+    function_name: Option<String>,
+    /// Full ID of the function node (if known)
+    function_id: Option<String>,
+}
+
 #[async_trait]
 impl Tool for FindCalleesTool {
     fn name(&self) -> &str {
         "find_callees"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Find all functions or methods that are called by a given function. \
          The inverse of find_callers - shows what a function depends on."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "function_name": {
-                    "type": "string",
-                    "description": "Name of the function to find callees for"
-                },
-                "function_id": {
-                    "type": "string",
-                    "description": "Full ID of the function node (if known)"
-                }
-            }
-        })
+        FindCalleesParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let function_id = params
-            .get("function_id")
-            .and_then(|v| v.as_str())
-            .map(String::from);
-
-        let function_name = params.get("function_name").and_then(|v| v.as_str());
+        let params = FindCalleesParams::from_params(params)?;
+        let function_id = params.function_id;
+        let function_name = params.function_name.as_deref();
 
         let store = lock_store(&self.store)?;
 
@@ -801,12 +794,23 @@ impl FileOutlineTool {
     }
 }
 
+/// Parameters for [`FileOutlineTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct FileOutlineParams {
+    /// Path to the file (e.g., 'crates/muninn-rlm/src/engine.rs')
+    file_path: String,
+}
+
 #[async_trait]
 impl Tool for FileOutlineTool {
     fn name(&self) -> &str {
         "file_outline"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
     fn description(&self) -> &str {
         "Get an outline of all symbols (functions, structs, traits, etc.) defined in a file. \
          Returns symbols in source order with their line numbers. Useful for understanding \
@@ -814,25 +818,12 @@ impl Tool for FileOutlineTool {
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "file_path": {
-                    "type": "string",
-                    "description": "Path to the file (e.g., 'crates/muninn-rlm/src/engine.rs')"
-                }
-            },
-            "required": ["file_path"]
-        })
+        FileOutlineParams::parameters_schema()
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let file_path = params
-            .get("file_path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                RlmError::ToolExecution("Missing required parameter 'file_path'".to_string())
-            })?;
+        let params = FileOutlineParams::from_params(params)?;
+        let file_path = params.file_path.as_str();
 
         let store = lock_store(&self.store)?;
 
@@ -869,6 +860,195 @@ impl Tool for FileOutlineTool {
     }
 }
 
+// ============================================================================
+// FindCyclesTool
+// ============================================================================
+
+/// Tool for finding dependency cycles in the call graph.
+pub struct FindCyclesTool {
+    store: SharedGraphStore,
+    max_cycles: usize,
+}
+
+impl FindCyclesTool {
+    /// Create a new find_cycles tool.
+    pub fn new(store: SharedGraphStore) -> Self {
+        Self {
+            store,
+            max_cycles: 10,
+        }
+    }
+}
+
+/// Parameters for [`FindCyclesTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct FindCyclesParams {
+    /// Granularity: 'calls' (mutual recursion), 'files' (module cycles, default)
+    /// or 'packages' (top-level directories such as crates)
+    #[tool(values("calls", "files", "packages"))]
+    level: Option<String>,
+}
+
+#[async_trait]
+impl Tool for FindCyclesTool {
+    fn name(&self) -> &str {
+        "find_cycles"
+    }
+
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
+    fn description(&self) -> &str {
+        "Find dependency cycles in the call graph: groups of functions, files or \
+         packages that depend on each other in a loop. Each dependency comes with a \
+         call site (path:line) showing where the loop could be cut."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        FindCyclesParams::parameters_schema()
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+        let params = FindCyclesParams::from_params(params)?;
+        let level_name = params.level.as_deref().unwrap_or("files");
+        let Some(level) = CycleLevel::parse(level_name) else {
+            return Ok(ToolResult::error(
+                format!(
+                    "Unknown level '{}' (expected calls, files or packages)",
+                    level_name
+                ),
+                true,
+            ));
+        };
+
+        let store = lock_store(&self.store)?;
+        let mut report = CycleReport::from_store(&store, std::path::Path::new(""), level)
+            .map_err(|e| RlmError::ToolExecution(format!("Failed to find cycles: {}", e)))?;
+
+        if report.cycles.is_empty() {
+            return Ok(ToolResult::text(format!(
+                "No dependency cycles found at the '{}' level",
+                level
+            )));
+        }
+
+        let total = report.cycles.len();
+        report.cycles.truncate(self.max_cycles);
+        let output = serde_json::json!({
+            "level": level.to_string(),
+            "cycles": report.cycles,
+            "count": total,
+            "truncated": total > self.max_cycles,
+        });
+
+        let mut result = ToolResult::json(output);
+        result.metadata = ToolMetadata::with_source(level.to_string()).with_tag("cycles");
+
+        Ok(result)
+    }
+}
+
+// ============================================================================
+// FindSensitiveTool
+// ============================================================================
+
+/// Tool for listing symbols tagged as touching security-sensitive APIs.
+pub struct FindSensitiveTool {
+    store: SharedGraphStore,
+    max_results: usize,
+}
+
+impl FindSensitiveTool {
+    /// Create a new find_sensitive tool.
+    pub fn new(store: SharedGraphStore) -> Self {
+        Self {
+            store,
+            max_results: 50,
+        }
+    }
+}
+
+/// Parameters for [`FindSensitiveTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct FindSensitiveParams {
+    /// Only return symbols in this category (default: all categories)
+    #[tool(values("exec", "eval", "unsafe", "raw_sql", "fs_write", "network"))]
+    category: Option<String>,
+    /// Only return symbols whose file path starts with this prefix
+    path: Option<String>,
+}
+
+#[async_trait]
+impl Tool for FindSensitiveTool {
+    fn name(&self) -> &str {
+        "find_sensitive"
+    }
+
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
+    fn description(&self) -> &str {
+        "Find functions that touch security-sensitive APIs: process execution (exec), \
+         dynamic evaluation (eval), unsafe code, raw SQL, filesystem writes (fs_write) \
+         and network calls. Tags come from pattern matching at index time, so read the \
+         code before drawing conclusions."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        FindSensitiveParams::parameters_schema()
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+        let params = FindSensitiveParams::from_params(params)?;
+        let category = match params.category.as_deref() {
+            Some(name) => match Sensitivity::parse(name) {
+                Some(category) => Some(category),
+                None => {
+                    return Ok(ToolResult::error(
+                        format!(
+                            "Unknown category '{}' (expected exec, eval, unsafe, raw_sql, fs_write or network)",
+                            name
+                        ),
+                        true,
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        let store = lock_store(&self.store)?;
+        let mut report = AuditReport::from_store(&store, std::path::Path::new(""), category)
+            .map_err(|e| {
+                RlmError::ToolExecution(format!("Failed to find sensitive symbols: {}", e))
+            })?;
+        if let Some(prefix) = &params.path {
+            report
+                .symbols
+                .retain(|s| s.file_path.starts_with(prefix.as_str()));
+        }
+
+        let scope = category.map_or("sensitive".to_string(), |c| c.to_string());
+        if report.symbols.is_empty() {
+            return Ok(ToolResult::text(format!("No {} symbols found", scope)));
+        }
+
+        let total = report.symbols.len();
+        report.symbols.truncate(self.max_results);
+        let output = serde_json::json!({
+            "symbols": report.symbols,
+            "count": total,
+            "truncated": total > self.max_results,
+        });
+
+        let mut result = ToolResult::json(output);
+        result.metadata = ToolMetadata::with_source(scope).with_tag("sensitive");
+
+        Ok(result)
+    }
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
@@ -939,27 +1119,6 @@ fn extract_id_from_value(value: &Value) -> Option<String> {
     }
 }
 
-/// Convert a graphqlite Value to a serde_json Value.
-fn value_to_json(value: &Value) -> serde_json::Value {
-    match value {
-        Value::Null => serde_json::Value::Null,
-        Value::Bool(b) => serde_json::Value::Bool(*b),
-        Value::Integer(i) => serde_json::Value::Number((*i).into()),
-        Value::Float(f) => serde_json::Number::from_f64(*f)
-            .map(serde_json::Value::Number)
-            .unwrap_or(serde_json::Value::Null),
-        Value::String(s) => serde_json::Value::String(s.clone()),
-        Value::Array(arr) => serde_json::Value::Array(arr.iter().map(value_to_json).collect()),
-        Value::Object(map) => {
-            let obj: serde_json::Map<String, serde_json::Value> = map
-                .iter()
-                .map(|(k, v)| (k.clone(), value_to_json(v)))
-                .collect();
-            serde_json::Value::Object(obj)
-        }
-    }
-}
-
 /// Create all graph tools for a given store.
 pub fn create_graph_tools(store: SharedGraphStore) -> Vec<Box<dyn Tool>> {
     vec![
@@ -969,6 +1128,8 @@ pub fn create_graph_tools(store: SharedGraphStore) -> Vec<Box<dyn Tool>> {
         // Dependency/usage analysis
         Box::new(FindCallersTool::new(store.clone())),
         Box::new(FindCalleesTool::new(store.clone())),
+        Box::new(FindCyclesTool::new(store.clone())),
+        Box::new(FindSensitiveTool::new(store.clone())),
         // Detail lookup
         Box::new(GetSymbolTool::new(store.clone())),
         Box::new(ReadSymbolTool::new(store.clone())),
@@ -977,6 +1138,119 @@ pub fn create_graph_tools(store: SharedGraphStore) -> Vec<Box<dyn Tool>> {
     ]
 }
 
+// ============================================================================
+// Late-attached graph store
+// ============================================================================
+
+/// A graph store that may arrive after startup, e.g. when the first index
+/// is still being built. Clones share one slot.
+#[derive(Clone, Default)]
+pub struct LazyGraphStore {
+    slot: Arc<OnceLock<(SharedGraphStore, ToolRegistry)>>,
+}
+
+impl LazyGraphStore {
+    /// Create an empty handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach the store, making the graph tools available to every
+    /// [`LazyGraphTools`] built from this handle. Returns `false` if a
+    /// store was already attached.
+    pub fn attach(&self, store: SharedGraphStore) -> bool {
+        let mut tools = ToolRegistry::new();
+        for tool in create_graph_tools(store.clone()) {
+            tools.register_arc(Arc::from(tool));
+        }
+        let count = tools.len();
+        let attached = self.slot.set((store, tools)).is_ok();
+        if attached {
+            tracing::info!(
+                tools = count,
+                "Graph store attached; exploration upgraded with graph tools"
+            );
+        }
+        attached
+    }
+
+    /// The attached store, if any.
+    pub fn get(&self) -> Option<SharedGraphStore> {
+        self.slot.get().map(|(store, _)| store.clone())
+    }
+
+    /// Whether a store has been attached.
+    pub fn is_attached(&self) -> bool {
+        self.slot.get().is_some()
+    }
+
+    /// Layer the graph tools over `base` once the store is attached.
+    pub fn wrap(&self, base: Arc<dyn ToolEnvironment>) -> LazyGraphTools {
+        LazyGraphTools {
+            base,
+            graph: self.clone(),
+            exposure: ToolExposure::default(),
+        }
+    }
+
+    fn tools(&self) -> Option<&ToolRegistry> {
+        self.slot.get().map(|(_, tools)| tools)
+    }
+}
+
+impl std::fmt::Debug for LazyGraphStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyGraphStore")
+            .field("attached", &self.is_attached())
+            .finish()
+    }
+}
+
+/// A tool environment that gains the graph tools when its
+/// [`LazyGraphStore`] is attached; until then it is just the base tools.
+pub struct LazyGraphTools {
+    base: Arc<dyn ToolEnvironment>,
+    graph: LazyGraphStore,
+    exposure: ToolExposure,
+}
+
+impl LazyGraphTools {
+    /// Apply MCP exposure overrides to the graph tools once they arrive
+    /// (the base tools apply their own).
+    pub fn with_exposure(mut self, exposure: ToolExposure) -> Self {
+        self.exposure = exposure;
+        self
+    }
+}
+
+#[async_trait]
+impl ToolEnvironment for LazyGraphTools {
+    async fn execute_tool(&self, tool_use: &ToolUseBlock) -> Result<ToolResultBlock> {
+        match self.graph.tools() {
+            Some(tools) if tools.get(&tool_use.name).is_some() => {
+                tools.execute_tool(tool_use).await
+            }
+            _ => self.base.execute_tool(tool_use).await,
+        }
+    }
+
+    fn available_tools(&self) -> Vec<ToolDefinition> {
+        let mut tools = self.base.available_tools();
+        if let Some(graph) = self.graph.tools() {
+            tools.extend(graph.available_tools());
+        }
+        tools
+    }
+
+    fn available_tools_external(&self) -> Vec<ToolDefinition> {
+        let mut tools = self.base.available_tools_external();
+        if let Some(graph) = self.graph.tools() {
+            tools.extend(self.exposure.external_tools(graph));
+        }
+        tools
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -997,6 +1271,7 @@ mod tests {
             cyclomatic: None,
             cognitive: None,
             call_degree: None,
+            sensitivity: Vec::new(),
         }
     }
 
@@ -1033,18 +1308,44 @@ mod tests {
     fn test_create_graph_tools() {
         let store = setup_test_store();
         let tools = create_graph_tools(store);
-        assert_eq!(tools.len(), 7);
+        assert_eq!(tools.len(), 9);
 
         let names: Vec<&str> = tools.iter().map(|t| t.name()).collect();
         assert!(names.contains(&"find_symbols"));
         assert!(names.contains(&"file_outline"));
         assert!(names.contains(&"find_callers"));
         assert!(names.contains(&"find_callees"));
+        assert!(names.contains(&"find_cycles"));
+        assert!(names.contains(&"find_sensitive"));
         assert!(names.contains(&"get_symbol"));
         assert!(names.contains(&"read_symbol"));
         assert!(names.contains(&"graph_query"));
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_lazy_graph_tools_upgrade_on_attach() {
+        let lazy = LazyGraphStore::new();
+        let tools = lazy.wrap(Arc::new(ToolRegistry::new()));
+        assert!(tools.available_tools().is_empty());
+
+        let tool_use = ToolUseBlock {
+            id: "t1".to_string(),
+            name: "find_symbols".to_string(),
+            input: serde_json::json!({ "name": "main" }),
+        };
+        let result = tools.execute_tool(&tool_use).await.unwrap();
+        assert!(result.is_error);
+
+        assert!(lazy.attach(setup_test_store()));
+        assert!(!lazy.attach(setup_test_store()));
+        assert!(lazy.get().is_some());
+        assert_eq!(tools.available_tools().len(), 9);
+        assert!(tools.has_tool("graph_query"));
+        let result = tools.execute_tool(&tool_use).await.unwrap();
+        assert!(!result.is_error);
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_graph_query_tool() {
@@ -1115,6 +1416,80 @@ mod tests {
         assert!(result.to_string_content().contains("No function found"));
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_find_cycles_tool() {
+        let store = setup_test_store();
+        {
+            let guard = store.lock().unwrap();
+            let main = guard.find_by_name("main").unwrap();
+            let helper = guard.find_by_name("helper").unwrap();
+            let main_id = extract_id_from_value(&main[0]).unwrap();
+            let helper_id = extract_id_from_value(&helper[0]).unwrap();
+            guard
+                .insert_edge(&Edge::calls(&helper_id, &main_id, CallType::Direct, 8))
+                .unwrap();
+        }
+        let tool = FindCyclesTool::new(store);
+
+        let result = tool
+            .execute(serde_json::json!({"level": "calls"}))
+            .await
+            .unwrap();
+        assert!(!result.is_error());
+        let content = result.to_string_content();
+        assert!(content.contains("crate::helper"), "{}", content);
+        assert!(content.contains("test.rs:8"), "{}", content);
+
+        // Both functions live in one file, so there is no file-level cycle
+        let result = tool.execute(serde_json::json!({})).await.unwrap();
+        assert!(result.to_string_content().contains("No dependency cycles"));
+
+        let result = tool
+            .execute(serde_json::json!({"level": "modules"}))
+            .await
+            .unwrap();
+        assert!(result.is_error());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_find_sensitive_tool() {
+        let store = setup_test_store();
+        {
+            let guard = store.lock().unwrap();
+            let mut runner = create_test_symbol("run_shell", SymbolKind::Function);
+            runner.file_path = "src/exec.rs".to_string();
+            runner.sensitivity = vec![Sensitivity::Exec, Sensitivity::FsWrite];
+            guard.insert_node(&runner).unwrap();
+        }
+        let tool = FindSensitiveTool::new(store);
+
+        let result = tool.execute(serde_json::json!({})).await.unwrap();
+        assert!(!result.is_error());
+        let content = result.to_string_content();
+        assert!(content.contains("run_shell"), "{}", content);
+        assert!(content.contains("fs_write"), "{}", content);
+
+        let result = tool
+            .execute(serde_json::json!({"category": "network"}))
+            .await
+            .unwrap();
+        assert!(result.to_string_content().contains("No network symbols"));
+
+        let result = tool
+            .execute(serde_json::json!({"path": "src/other"}))
+            .await
+            .unwrap();
+        assert!(result.to_string_content().contains("No sensitive symbols"));
+
+        let result = tool
+            .execute(serde_json::json!({"category": "crypto"}))
+            .await
+            .unwrap();
+        assert!(result.is_error());
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_get_symbol_tool() {
@@ -7,14 +7,17 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
 use reqwest::{Client, Response, header};
+use std::collections::VecDeque;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::backend::{
-    ContentDelta, LLMBackend, ResponseStream, StreamEvent, pick_model, with_retry,
+    ContentDelta, LLMBackend, PayloadCapture, ResponseStream, StreamEvent, capture_call,
+    parse_tool_arguments, pick_model, with_retry, with_trace_context,
 };
 use crate::error::{Result, RlmError};
+use crate::profile::ProviderProfile;
 use crate::types::{
     CompletionRequest, CompletionResponse, ContentBlock, Message, Role, StopReason, Usage,
 };
@@ -48,6 +51,12 @@ pub struct GroqConfig {
 
     /// Initial backoff duration for retries.
     pub retry_backoff: Duration,
+
+    /// How much of each request/response to record in traces.
+    pub capture: PayloadCapture,
+
+    /// Ask for `response_format: json_object` on requests without tools.
+    pub json_mode: bool,
 }
 
 impl GroqConfig {
@@ -60,6 +69,8 @@ impl GroqConfig {
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             max_retries: 3,
             retry_backoff: Duration::from_millis(500),
+            capture: PayloadCapture::None,
+            json_mode: false,
         }
     }
 
@@ -94,6 +105,22 @@ impl GroqConfig {
         self.max_retries = retries;
         self
     }
+
+    /// Set how much of each request/response to record in traces.
+    pub fn with_capture(mut self, capture: PayloadCapture) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Force JSON object output on requests that carry no tools.
+    ///
+    /// Useful for models whose tool calling is unreliable: callers put the
+    /// expected JSON shape in the prompt instead of a tool schema. Groq
+    /// requires the prompt to mention JSON when this is on.
+    pub fn with_json_mode(mut self, enabled: bool) -> Self {
+        self.json_mode = enabled;
+        self
+    }
 }
 
 /// Groq API backend.
@@ -125,7 +152,7 @@ impl GroqBackend {
 
     /// Add authentication headers to a request.
     fn add_headers(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        builder
+        with_trace_context(builder)
             .header(
                 header::AUTHORIZATION,
                 format!("Bearer {}", self.config.api_key),
@@ -251,24 +278,21 @@ impl GroqBackend {
             }
         }
 
-        let tools: Option<Vec<GroqTool>> = if request.tools.is_empty() {
-            None
-        } else {
-            Some(
-                request
-                    .tools
-                    .iter()
-                    .map(|t| GroqTool {
-                        tool_type: "function".to_string(),
-                        function: GroqFunction {
-                            name: t.name.clone(),
-                            description: Some(t.description.clone()),
-                            parameters: t.input_schema.clone(),
-                        },
-                    })
-                    .collect(),
-            )
-        };
+        // Server tools only run at their provider
+        let tools: Vec<GroqTool> = request
+            .tools
+            .iter()
+            .filter(|t| !t.is_server_tool())
+            .map(|t| GroqTool {
+                tool_type: "function".to_string(),
+                function: GroqFunction {
+                    name: t.name.clone(),
+                    description: Some(t.description.clone()),
+                    parameters: t.input_schema.clone(),
+                },
+            })
+            .collect();
+        let tools = (!tools.is_empty()).then_some(tools);
 
         let stop = if request.stop_sequences.is_empty() {
             None
@@ -315,6 +339,10 @@ impl GroqBackend {
             None
         };
 
+        // JSON mode and tools are mutually exclusive on Groq
+        let response_format = (self.config.json_mode && tools.is_none())
+            .then(|| serde_json::json!({ "type": "json_object" }));
+
         GroqChatRequest {
             model: effective_model,
             messages,
@@ -326,6 +354,7 @@ impl GroqBackend {
             tool_choice,
             stop,
             reasoning_effort,
+            response_format,
         }
     }
 
@@ -369,6 +398,7 @@ impl LLMBackend for GroqBackend {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
         let mut request = request;
         request.stream = false;
+        ProviderProfile::GROQ.shape(&mut request);
 
         let groq_request = self.to_groq_request(&request);
 
@@ -392,7 +422,7 @@ impl LLMBackend for GroqBackend {
             );
         }
 
-        with_retry(
+        let call = with_retry(
             self.config.max_retries,
             self.config.retry_backoff,
             "groq",
@@ -405,6 +435,13 @@ impl LLMBackend for GroqBackend {
 
                 Self::handle_response(response).await
             },
+        );
+        capture_call(
+            self.config.capture,
+            "groq",
+            &groq_request.model,
+            &groq_request,
+            call,
         )
         .await
     }
@@ -412,6 +449,7 @@ impl LLMBackend for GroqBackend {
     async fn complete_stream(&self, request: CompletionRequest) -> Result<ResponseStream> {
         let mut request = request;
         request.stream = true;
+        ProviderProfile::GROQ.shape(&mut request);
 
         let groq_request = self.to_groq_request(&request);
 
@@ -494,6 +532,9 @@ struct GroqChatRequest {
     /// Controls Qwen3 reasoning/thinking mode. Set to "none" to disable thinking.
     #[serde(skip_serializing_if = "Option::is_none")]
     reasoning_effort: Option<String>,
+    /// `{"type": "json_object"}` when JSON mode is on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -564,12 +605,10 @@ impl From<GroqChatResponse> for CompletionResponse {
             // Add tool calls if present
             if let Some(tool_calls) = c.message.tool_calls {
                 for tc in tool_calls {
-                    let input: serde_json::Value =
-                        serde_json::from_str(&tc.function.arguments).unwrap_or_default();
                     blocks.push(ContentBlock::ToolUse {
                         id: tc.id,
                         name: tc.function.name,
-                        input,
+                        input: parse_tool_arguments(&tc.function.arguments),
                         cache_control: None,
                     });
                 }
@@ -650,84 +689,39 @@ fn parse_groq_sse_stream(
         GroqSseState {
             byte_stream: Box::pin(byte_stream),
             buffer: String::new(),
+            pending: VecDeque::new(),
             done: false,
-            message_id: None,
-            model: None,
-            current_index: 0,
             started: false,
+            next_index: 0,
+            text_block: None,
+            tool_blocks: Vec::new(),
+            stop_reason: None,
+            usage: Usage::new(0, 0),
         },
         |mut state| async move {
-            if state.done {
-                return None;
-            }
-
             loop {
-                // Process lines in buffer
-                while let Some(line_end) = state.buffer.find('\n') {
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((event, state));
+                }
+                if state.done {
+                    return None;
+                }
+
+                // Process lines in buffer until one produces events
+                while state.pending.is_empty() {
+                    let Some(line_end) = state.buffer.find('\n') else {
+                        break;
+                    };
                     let line = state.buffer[..line_end].trim().to_string();
                     state.buffer = state.buffer[line_end + 1..].to_string();
 
-                    if line.is_empty() {
-                        continue;
-                    }
-
-                    if let Some(data) = line.strip_prefix("data: ") {
-                        if data == "[DONE]" {
-                            return Some((Ok(StreamEvent::MessageStop), state));
-                        }
-
-                        if let Ok(chunk) = serde_json::from_str::<GroqStreamChunk>(data) {
-                            // Emit MessageStart on first chunk
-                            if !state.started {
-                                state.started = true;
-                                state.message_id = Some(chunk.id.clone());
-                                state.model = Some(chunk.model.clone());
-                                return Some((
-                                    Ok(StreamEvent::MessageStart {
-                                        id: chunk.id,
-                                        model: chunk.model,
-                                    }),
-                                    state,
-                                ));
-                            }
-
-                            // Process choices
-                            if let Some(choice) = chunk.choices.into_iter().next() {
-                                if let Some(delta) = choice.delta {
-                                    // Text content
-                                    if let Some(content) = delta.content {
-                                        if !content.is_empty() {
-                                            return Some((
-                                                Ok(StreamEvent::ContentBlockDelta {
-                                                    index: state.current_index,
-                                                    delta: ContentDelta::TextDelta(content),
-                                                }),
-                                                state,
-                                            ));
-                                        }
-                                    }
-                                }
-
-                                // Check for finish
-                                if let Some(reason) = choice.finish_reason {
-                                    let stop_reason = match reason.as_str() {
-                                        "stop" => StopReason::EndTurn,
-                                        "tool_calls" => StopReason::ToolUse,
-                                        "length" => StopReason::MaxTokens,
-                                        _ => StopReason::EndTurn,
-                                    };
-                                    return Some((
-                                        Ok(StreamEvent::MessageDelta {
-                                            stop_reason,
-                                            usage: Usage::new(0, 0),
-                                        }),
-                                        state,
-                                    ));
-                                }
-                            }
-                        }
+                    if let Some(data) = line.strip_prefix("data:") {
+                        state.handle_data(data.trim());
                     }
                 }
+                if !state.pending.is_empty() || state.done {
+                    continue;
+                }
 
                 // Need more data
                 match state.byte_stream.next().await {
@@ -736,25 +730,156 @@ fn parse_groq_sse_stream(
                         state.buffer.push_str(&text);
                     }
                     Some(Err(e)) => {
+                        state.done = true;
                         return Some((Err(RlmError::Network(e.to_string())), state));
                     }
-                    None => {
-                        return None;
-                    }
+                    None => state.finish(),
                 }
             }
         },
     ))
 }
 
+/// Translates OpenAI-style chunks into Anthropic-style block events.
+///
+/// OpenAI streams text and tool calls as flat deltas keyed by a per-call
+/// `index`; each new call (or the first text) opens a content block here,
+/// and everything still open is closed on `finish_reason` or `[DONE]`.
 struct GroqSseState {
     byte_stream: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
     buffer: String,
+    pending: VecDeque<Result<StreamEvent>>,
     done: bool,
-    message_id: Option<String>,
-    model: Option<String>,
-    current_index: usize,
     started: bool,
+    next_index: usize,
+    /// Block index of the open text block.
+    text_block: Option<usize>,
+    /// Open tool blocks as (OpenAI tool call index, block index).
+    tool_blocks: Vec<(u32, usize)>,
+    stop_reason: Option<StopReason>,
+    usage: Usage,
+}
+
+impl GroqSseState {
+    fn handle_data(&mut self, data: &str) {
+        if data == "[DONE]" {
+            self.finish();
+            return;
+        }
+        let chunk = match serde_json::from_str::<GroqStreamChunk>(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::debug!(error = %e, "Skipping unparseable Groq stream chunk");
+                return;
+            }
+        };
+
+        if !self.started {
+            self.started = true;
+            self.emit(StreamEvent::MessageStart {
+                id: chunk.id,
+                model: chunk.model,
+            });
+        }
+
+        if let Some(usage) = chunk.usage.or(chunk.x_groq.and_then(|x| x.usage)) {
+            self.usage = Usage::new(usage.prompt_tokens, usage.completion_tokens);
+        }
+
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            return;
+        };
+        if let Some(delta) = choice.delta {
+            if let Some(content) = delta.content.filter(|c| !c.is_empty()) {
+                let index = match self.text_block {
+                    Some(index) => index,
+                    None => {
+                        let index = self.open_block();
+                        self.text_block = Some(index);
+                        self.emit(StreamEvent::ContentBlockStart {
+                            index,
+                            content_type: "text".to_string(),
+                        });
+                        index
+                    }
+                };
+                self.emit(StreamEvent::ContentBlockDelta {
+                    index,
+                    delta: ContentDelta::TextDelta(content),
+                });
+            }
+
+            for call in delta.tool_calls.unwrap_or_default() {
+                let function = call.function.unwrap_or_default();
+                let index = match self.tool_blocks.iter().find(|(i, _)| *i == call.index) {
+                    Some(&(_, index)) => index,
+                    None => {
+                        if let Some(text) = self.text_block.take() {
+                            self.emit(StreamEvent::ContentBlockStop { index: text });
+                        }
+                        let index = self.open_block();
+                        self.tool_blocks.push((call.index, index));
+                        self.emit(StreamEvent::ToolUseStart {
+                            index,
+                            id: call.id.unwrap_or_else(|| format!("call_{}", call.index)),
+                            name: function.name.unwrap_or_default(),
+                        });
+                        index
+                    }
+                };
+                if let Some(args) = function.arguments.filter(|a| !a.is_empty()) {
+                    self.emit(StreamEvent::ContentBlockDelta {
+                        index,
+                        delta: ContentDelta::InputJsonDelta(args),
+                    });
+                }
+            }
+        }
+
+        if let Some(reason) = choice.finish_reason {
+            self.stop_reason = Some(match reason.as_str() {
+                "stop" => StopReason::EndTurn,
+                "tool_calls" => StopReason::ToolUse,
+                "length" => StopReason::MaxTokens,
+                _ => StopReason::EndTurn,
+            });
+            self.close_blocks();
+        }
+    }
+
+    /// Close open blocks and emit the final message events (once).
+    fn finish(&mut self) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        if !self.started {
+            return;
+        }
+        self.close_blocks();
+        let stop_reason = self.stop_reason.unwrap_or(StopReason::EndTurn);
+        let usage = self.usage.clone();
+        self.emit(StreamEvent::MessageDelta { stop_reason, usage });
+        self.emit(StreamEvent::MessageStop);
+    }
+
+    fn close_blocks(&mut self) {
+        let text = self.text_block.take();
+        let tools = std::mem::take(&mut self.tool_blocks);
+        for index in text.into_iter().chain(tools.into_iter().map(|(_, i)| i)) {
+            self.emit(StreamEvent::ContentBlockStop { index });
+        }
+    }
+
+    fn open_block(&mut self) -> usize {
+        let index = self.next_index;
+        self.next_index += 1;
+        index
+    }
+
+    fn emit(&mut self, event: StreamEvent) {
+        self.pending.push_back(Ok(event));
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -762,6 +887,18 @@ struct GroqStreamChunk {
     id: String,
     model: String,
     choices: Vec<GroqStreamChoice>,
+    /// OpenAI-style usage, sent on the final chunk when requested.
+    #[serde(default)]
+    usage: Option<GroqUsage>,
+    /// Groq reports usage for streams under `x_groq` on the final chunk.
+    #[serde(default)]
+    x_groq: Option<GroqStreamExtra>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct GroqStreamExtra {
+    #[serde(default)]
+    usage: Option<GroqUsage>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -773,6 +910,28 @@ struct GroqStreamChoice {
 #[derive(Debug, serde::Deserialize)]
 struct GroqStreamDelta {
     content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<GroqStreamToolCall>>,
+}
+
+/// Incremental tool call: `id` and `function.name` arrive on the first
+/// delta for an `index`, `function.arguments` is spread across the rest.
+#[derive(Debug, serde::Deserialize)]
+struct GroqStreamToolCall {
+    #[serde(default)]
+    index: u32,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<GroqStreamFunction>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct GroqStreamFunction {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
 }
 
 /// Create a shared Groq backend.
@@ -915,4 +1074,97 @@ mod tests {
         let groq_req = backend.to_groq_request(&request);
         assert_eq!(groq_req.model, DEFAULT_MODEL);
     }
+
+    #[test]
+    fn test_json_mode_only_without_tools() {
+        let backend = GroqBackend::new(GroqConfig::new("key").with_json_mode(true)).unwrap();
+        let request = CompletionRequest::new("m", vec![Message::user("Reply in JSON")], 100);
+        let groq_req = backend.to_groq_request(&request);
+        assert_eq!(
+            groq_req.response_format,
+            Some(serde_json::json!({ "type": "json_object" }))
+        );
+
+        let with_tools = request
+            .clone()
+            .with_tools(vec![crate::types::ToolDefinition::new(
+                "read_file",
+                "Read a file",
+                serde_json::json!({ "type": "object" }),
+            )]);
+        assert!(
+            backend
+                .to_groq_request(&with_tools)
+                .response_format
+                .is_none()
+        );
+
+        let plain = GroqBackend::new(GroqConfig::new("key")).unwrap();
+        assert!(plain.to_groq_request(&request).response_format.is_none());
+    }
+
+    fn sse_stream(chunks: &[&str]) -> ResponseStream {
+        let bytes: Vec<reqwest::Result<Bytes>> = chunks
+            .iter()
+            .map(|c| Ok(Bytes::from(c.to_string())))
+            .collect();
+        parse_groq_sse_stream(futures::stream::iter(bytes))
+    }
+
+    #[tokio::test]
+    async fn test_stream_text() {
+        // Two events in one network chunk, and one event split across chunks
+        let stream = sse_stream(&[
+            "data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"delta\":{\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n\
+             data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"delta\":{\"content\":\"lo\"},",
+            "\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}],\
+             \"x_groq\":{\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":2}}}\n\n",
+            "data: [DONE]\n\n",
+        ]);
+        let response = crate::backend::collect_stream(stream).await.unwrap();
+        assert_eq!(response.id, "c1");
+        assert_eq!(response.text(), "Hello");
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(response.usage.input_tokens, 7);
+        assert_eq!(response.usage.output_tokens, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_incremental_tool_calls() {
+        let events: Vec<StreamEvent> = sse_stream(&[
+            "data: {\"id\":\"c2\",\"model\":\"m\",\"choices\":[{\"delta\":{\"content\":\"Looking.\"}}]}\n\n",
+            "data: {\"id\":\"c2\",\"model\":\"m\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_a\",\"function\":{\"name\":\"read_file\",\"arguments\":\"{\\\"path\\\":\"}}]}}]}\n\n",
+            "data: {\"id\":\"c2\",\"model\":\"m\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"a.rs\\\"}\"}},{\"index\":1,\"id\":\"call_b\",\"function\":{\"name\":\"search\",\"arguments\":\"{}\"}}]}}]}\n\n",
+            "data: {\"id\":\"c2\",\"model\":\"m\",\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+        ])
+        .map(|e| e.unwrap())
+        .collect()
+        .await;
+
+        assert!(matches!(events[0], StreamEvent::MessageStart { .. }));
+        assert!(matches!(
+            &events[3],
+            StreamEvent::ContentBlockStop { index: 0 }
+        ));
+        assert!(matches!(
+            &events[4],
+            StreamEvent::ToolUseStart { index: 1, id, name } if id == "call_a" && name == "read_file"
+        ));
+        // Stream ended without [DONE]; the final events are still emitted
+        assert!(matches!(events.last(), Some(StreamEvent::MessageStop)));
+
+        let response = crate::backend::collect_stream(Box::pin(futures::stream::iter(
+            events.into_iter().map(Ok),
+        )))
+        .await
+        .unwrap();
+        assert_eq!(response.text(), "Looking.");
+        assert_eq!(response.stop_reason, Some(StopReason::ToolUse));
+        let tool_uses = response.tool_uses();
+        assert_eq!(tool_uses.len(), 2);
+        assert_eq!(tool_uses[0].id, "call_a");
+        assert_eq!(tool_uses[0].input, serde_json::json!({ "path": "a.rs" }));
+        assert_eq!(tool_uses[1].name, "search");
+    }
 }
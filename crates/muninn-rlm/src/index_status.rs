@@ -0,0 +1,143 @@
+//! Progress of a background graph index, shared between the indexer and
+//! the proxy's `/statusline` endpoint.
+
+use std::sync::{Arc, Mutex};
+
+use muninn_graph::IndexProgress;
+use serde::Serialize;
+
+/// Where an indexing run is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexState {
+    /// Waiting for the first file.
+    #[default]
+    Pending,
+    /// Parsing files.
+    Running,
+    /// Finished and attached.
+    Done,
+    /// Gave up; see [`IndexStatusSnapshot::error`].
+    Failed,
+}
+
+/// Point-in-time copy of an [`IndexStatus`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IndexStatusSnapshot {
+    pub state: IndexState,
+    pub files_done: usize,
+    pub files_total: usize,
+    /// Estimated seconds left while running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl IndexStatusSnapshot {
+    /// Status bar fragment, e.g. `indexing 120/800 · eta 2m`; `None` once
+    /// the index is done.
+    pub fn line(&self) -> Option<String> {
+        match self.state {
+            IndexState::Done => None,
+            IndexState::Failed => Some("index failed".to_string()),
+            IndexState::Pending => Some("indexing".to_string()),
+            IndexState::Running => {
+                let mut line = format!("indexing {}/{}", self.files_done, self.files_total);
+                if let Some(eta) = self.eta_secs {
+                    line.push_str(&format!(" · eta {}", compact_duration(eta)));
+                }
+                Some(line)
+            }
+        }
+    }
+}
+
+/// Cloneable handle the indexer updates and the proxy reads.
+#[derive(Debug, Clone, Default)]
+pub struct IndexStatus {
+    inner: Arc<Mutex<IndexStatusSnapshot>>,
+}
+
+impl IndexStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a progress report from the graph builder.
+    pub fn update(&self, progress: &IndexProgress) {
+        let mut inner = self.inner.lock().expect("index status lock poisoned");
+        inner.state = IndexState::Running;
+        inner.files_done = progress.files_done;
+        inner.files_total = progress.files_total;
+        inner.eta_secs = progress.eta().map(|d| d.as_secs());
+    }
+
+    /// Mark the index built and attached.
+    pub fn finish(&self) {
+        let mut inner = self.inner.lock().expect("index status lock poisoned");
+        inner.state = IndexState::Done;
+        inner.eta_secs = None;
+    }
+
+    /// Mark the run failed with `error`.
+    pub fn fail(&self, error: impl Into<String>) {
+        let mut inner = self.inner.lock().expect("index status lock poisoned");
+        inner.state = IndexState::Failed;
+        inner.eta_secs = None;
+        inner.error = Some(error.into());
+    }
+
+    /// Current status.
+    pub fn snapshot(&self) -> IndexStatusSnapshot {
+        self.inner
+            .lock()
+            .expect("index status lock poisoned")
+            .clone()
+    }
+}
+
+/// `45` -> `45s`, `150` -> `2m`, `7300` -> `2h`.
+fn compact_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_index_status_lifecycle() {
+        let status = IndexStatus::new();
+        assert_eq!(status.snapshot().line().as_deref(), Some("indexing"));
+
+        status.update(&IndexProgress {
+            files_done: 100,
+            files_indexed: 0,
+            files_total: 400,
+            elapsed: Duration::from_secs(50),
+        });
+        let snapshot = status.snapshot();
+        assert_eq!(snapshot.state, IndexState::Running);
+        assert_eq!(snapshot.eta_secs, Some(150));
+        assert_eq!(
+            snapshot.line().as_deref(),
+            Some("indexing 100/400 · eta 2m")
+        );
+
+        status.clone().finish();
+        assert_eq!(status.snapshot().state, IndexState::Done);
+        assert_eq!(status.snapshot().line(), None);
+
+        status.fail("disk full");
+        let json = serde_json::to_value(status.snapshot()).unwrap();
+        assert_eq!(json["state"], "failed");
+        assert_eq!(json["error"], "disk full");
+        assert!(json.get("eta_secs").is_none());
+    }
+}
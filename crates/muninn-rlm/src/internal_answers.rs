@@ -0,0 +1,270 @@
+//! Local answers for the agent's internal requests.
+//!
+//! Agents ask upstream for things the user never sees as a turn:
+//! conversation titles and next-prompt suggestions. The router recognises
+//! these ([`InternalRequest`]) and passes them through. With
+//! [`InternalAnswers`] configured, the proxy answers them itself, so they
+//! cost no upstream calls. A small local model answers when one is set.
+//! Otherwise, or when it fails, templates do: titles come from the first
+//! words of the conversation, and suggestions are left empty, which
+//! agents treat as "no suggestion".
+
+use std::sync::{Arc, LazyLock};
+
+use regex::Regex;
+use serde_json::json;
+
+use crate::backend::LLMBackend;
+use crate::router::InternalRequest;
+use crate::types::{CompletionRequest, CompletionResponse, ContentBlock, Role, StopReason, Usage};
+
+/// Model name reported on template answers.
+pub const TEMPLATE_MODEL: &str = "muninn-template";
+
+/// Title used when the conversation gives nothing to go on.
+const FALLBACK_TITLE: &str = "Coding session";
+
+/// Output cap for the local model; titles and suggestions are one line.
+const MAX_LOCAL_TOKENS: u32 = 64;
+
+/// The word range in a title request: "write a 3-5 word title".
+static TITLE_WORDS_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(\d+)-(\d+) word title").unwrap());
+
+/// Role labels in front of conversation lines.
+static ROLE_LABEL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:human|user|assistant)\s*:\s*").unwrap());
+
+/// Answers internal requests without going upstream.
+#[derive(Clone, Default)]
+pub struct InternalAnswers {
+    /// Local backend and model; templates answer without one.
+    model: Option<(Arc<dyn LLMBackend>, String)>,
+}
+
+impl InternalAnswers {
+    /// Answer from templates only.
+    pub fn template() -> Self {
+        Self::default()
+    }
+
+    /// Answer with `model` on `backend`, falling back to templates.
+    pub fn with_model(backend: Arc<dyn LLMBackend>, model: impl Into<String>) -> Self {
+        Self {
+            model: Some((backend, model.into())),
+        }
+    }
+
+    /// Answer `request`, an internal request of `kind`.
+    pub async fn answer(
+        &self,
+        kind: InternalRequest,
+        request: &CompletionRequest,
+    ) -> CompletionResponse {
+        if let Some((backend, model)) = &self.model {
+            let mut local = request.clone();
+            local.model = model.clone();
+            local.max_tokens = local.max_tokens.min(MAX_LOCAL_TOKENS);
+            local.tools = Vec::new();
+            local.tool_choice = None;
+            local.stream = false;
+            local.muninn = None;
+            match backend.complete(local).await {
+                Ok(response) if !response.text().trim().is_empty() => return response,
+                Ok(_) => tracing::debug!(?kind, "Local model gave an empty answer"),
+                Err(e) => tracing::debug!(?kind, error = %e, "Local model failed"),
+            }
+        }
+        let text = match kind {
+            InternalRequest::Title => template_title(request),
+            InternalRequest::Suggestion => String::new(),
+        };
+        text_response(text)
+    }
+}
+
+impl std::fmt::Debug for InternalAnswers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InternalAnswers")
+            .field(
+                "model",
+                &self
+                    .model
+                    .as_ref()
+                    .map(|(backend, model)| (backend.name(), model)),
+            )
+            .finish()
+    }
+}
+
+/// A title from the first conversation line after the instruction, cut to
+/// the upper end of the requested word range.
+fn template_title(request: &CompletionRequest) -> String {
+    let text = request
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == Role::User)
+        .map(|m| m.content.to_text())
+        .unwrap_or_default();
+    let max_words = TITLE_WORDS_RE
+        .captures(&text)
+        .and_then(|c| c[2].parse().ok())
+        .unwrap_or(6usize)
+        .max(1);
+    let line = text
+        .lines()
+        .skip(1)
+        .map(|line| ROLE_LABEL_RE.replace(line.trim(), "").into_owned())
+        .find(|line| !line.is_empty() && !line.to_lowercase().starts_with("respond with"))
+        .unwrap_or_default();
+    let title = line
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .take(max_words)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => FALLBACK_TITLE.to_string(),
+    }
+}
+
+/// A one-text-block response the proxy answers with itself.
+pub fn text_response(text: String) -> CompletionResponse {
+    let usage = Usage::new(0, text.split_whitespace().count() as u32);
+    CompletionResponse::new(
+        format!("msg_local_{}", uuid::Uuid::new_v4().simple()),
+        TEMPLATE_MODEL,
+        vec![ContentBlock::text(text)],
+        StopReason::EndTurn,
+        usage,
+    )
+}
+
+/// `response` as a Messages API event stream, for agents that asked to
+/// stream. Only text content is sent.
+pub fn to_sse(response: &CompletionResponse) -> String {
+    let stop_reason = response.stop_reason.unwrap_or(StopReason::EndTurn);
+    let events = [
+        (
+            "message_start",
+            json!({
+                "type": "message_start",
+                "message": {
+                    "id": response.id,
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [],
+                    "model": response.model,
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {"input_tokens": response.usage.input_tokens, "output_tokens": 0},
+                },
+            }),
+        ),
+        (
+            "content_block_start",
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        ),
+        (
+            "content_block_delta",
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": response.text()}}),
+        ),
+        (
+            "content_block_stop",
+            json!({"type": "content_block_stop", "index": 0}),
+        ),
+        (
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": stop_reason, "stop_sequence": null},
+                "usage": {"output_tokens": response.usage.output_tokens},
+            }),
+        ),
+        ("message_stop", json!({"type": "message_stop"})),
+    ];
+    events
+        .iter()
+        .map(|(event, data)| format!("event: {}\ndata: {}\n\n", event, data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+    use crate::sse::SseParser;
+    use crate::types::Message;
+
+    fn request(text: &str) -> CompletionRequest {
+        CompletionRequest::new("claude-haiku", vec![Message::user(text)], 512)
+    }
+
+    #[tokio::test]
+    async fn test_template_answers() {
+        let answers = InternalAnswers::template();
+        let title = answers
+            .answer(
+                InternalRequest::Title,
+                &request(
+                    "Please write a 3-5 word title for the following conversation:\n\n\
+                     User: why does the router keep timing out on large repos?\n\n\
+                     Respond with the title and nothing else.",
+                ),
+            )
+            .await;
+        assert_eq!(title.text(), "Why does the router keep");
+        assert_eq!(title.model, TEMPLATE_MODEL);
+
+        let empty = answers
+            .answer(
+                InternalRequest::Title,
+                &request("Please write a 3-5 word title for this conversation"),
+            )
+            .await;
+        assert_eq!(empty.text(), FALLBACK_TITLE);
+
+        let suggestion = answers
+            .answer(
+                InternalRequest::Suggestion,
+                &request("You are now a prompt suggestion generator"),
+            )
+            .await;
+        assert_eq!(suggestion.text(), "");
+    }
+
+    #[tokio::test]
+    async fn test_local_model_falls_back_to_template() {
+        let backend = Arc::new(MockBackend::with_text("Router timeouts"));
+        let answers = InternalAnswers::with_model(backend.clone(), "qwen2.5:0.5b");
+        let prompt = request("Please write a 2-3 word title\nUser: fix the proxy");
+
+        let response = answers.answer(InternalRequest::Title, &prompt).await;
+        assert_eq!(response.text(), "Router timeouts");
+        let sent = &backend.requests()[0];
+        assert_eq!(sent.model, "qwen2.5:0.5b");
+        assert_eq!(sent.max_tokens, MAX_LOCAL_TOKENS);
+
+        // The mock has no responses left, so the call fails
+        let response = answers.answer(InternalRequest::Title, &prompt).await;
+        assert_eq!(response.text(), "Fix the proxy");
+    }
+
+    #[test]
+    fn test_to_sse() {
+        let sse = to_sse(&text_response("Router timeouts".to_string()));
+        let mut parser = SseParser::new();
+        let events: Vec<_> = parser
+            .push(sse.as_bytes())
+            .iter()
+            .filter_map(|frame| crate::sse::SseEvent::parse(frame).json())
+            .collect();
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[2]["delta"]["text"], "Router timeouts");
+        assert_eq!(events[4]["delta"]["stop_reason"], "end_turn");
+    }
+}
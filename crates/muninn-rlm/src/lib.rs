@@ -7,46 +7,97 @@
 //! - Backend abstraction for multiple LLM providers
 //! - Sub-query spawning with context isolation
 
+// Lets `#[derive(ToolParams)]` output (which names `::muninn_rlm`) compile here too.
+extern crate self as muninn_rlm;
+
+pub mod answer_format;
 pub mod anthropic;
 pub mod backend;
+pub mod client_keys;
+pub mod compaction;
 pub mod context;
+pub mod coverage;
+pub mod debug_log;
 pub mod doc_tools;
 pub mod engine;
 pub mod error;
+pub mod experiment;
+pub mod exploration_report;
+pub mod feedback;
 pub mod fs;
 pub mod fs_tools;
+pub mod graph_service;
 pub mod graph_tools;
 pub mod groq;
+pub mod index_status;
+pub mod internal_answers;
 pub mod mcp;
 pub mod mcp_engine_server;
+pub mod notify;
 pub mod oauth;
 pub mod ollama;
+pub mod openai_compat;
 pub mod passthrough;
+#[cfg(feature = "wasm-plugins")]
+pub mod plugin;
+pub mod profile;
 pub mod prompts;
 pub mod proxy;
+pub mod recording;
 pub mod repl_tools;
+pub mod response_transform;
+pub mod revision_tools;
 pub mod router;
+pub mod routing_log;
+pub mod session_export;
+pub mod session_history;
+pub mod session_recording;
+pub mod session_ui;
+pub mod spend;
+pub mod sse;
 pub mod subquery;
+pub mod token_crypto;
 pub mod token_manager;
+pub mod tool_middleware;
+pub mod tool_params;
+pub mod tool_repair;
 pub mod tools;
 pub mod types;
 
-// Testing utilities - available in test builds
-#[cfg(test)]
+// Testing utilities - available in test builds and with the `testing` feature
+#[cfg(any(test, feature = "testing"))]
 pub mod testing;
 
+pub use answer_format::{AnswerFormat, AnswerFormatter, AnswerSections};
 pub use anthropic::{AnthropicBackend, AnthropicConfig};
 pub use backend::{
-    LLMBackend, LoggingBackend, MockBackend, ParsedToolCall, ResponseStream, SharedBackend,
-    StreamEvent, default_format_tool_definitions, default_format_tool_result,
+    LLMBackend, LoggingBackend, MockBackend, ParsedToolCall, PayloadCapture, ResponseStream,
+    SharedBackend, StreamEvent, collect_stream, default_format_tool_definitions,
+    default_format_tool_result, parse_tool_arguments,
 };
+pub use client_keys::{ADMIN_ROLE, ClientKey, ClientKeyFile, ClientKeys, KeyQuota};
+pub use compaction::{Compaction, Compactor};
 pub use context::{ContextAggregator, ContextBuilder, ContextItem};
+pub use coverage::{CoverageEntry, CoverageReport, ExplorationCoverage, ReadCount};
+pub use debug_log::{
+    PROMPT_DUMP_FILE, RAW_REQUEST_LOG_FILE, RawRequestLog, RawRequestLogConfig, raw_request_logs,
+};
 pub use doc_tools::{
     IndexCrateTool, IndexPackageTool, ListLibrariesTool, SearchDocsTool, SharedDocStore,
     create_doc_tools, wrap_doc_store,
 };
-pub use engine::{EngineConfig, EngineDeps, ExplorationContext, RecursiveEngine};
+pub use engine::{
+    ContextWindows, Downshift, EngineConfig, EngineDeps, EngineHooks, Ensemble, EnsembleMember,
+    ExplorationContext, HookDecision, QualityScore, RecursiveEngine, ToolCallDecision, ToolLimits,
+    ToolUsage, cited_paths,
+};
 pub use error::{BudgetExceededError, BudgetType, Result, RlmError};
+pub use experiment::{Assignment, Experiment, ExperimentReport, Variant, VariantStats};
+pub use exploration_report::{
+    EXPLORATION_REPORT_DIR, ExplorationReport, ExplorationReportSummary, ExplorationReports,
+    ReportBudget, ReportToolCall, exploration_reports,
+};
+pub use feedback::{FEEDBACK_FILE, Feedback, FeedbackEntry, FeedbackLog, Rating};
 pub use fs::{
     DirEntry, FileMetadata, FileSystem, MockFileSystem, RealFileSystem, SharedFileSystem,
 };
@@ -54,37 +105,80 @@ pub use fs_tools::{
     FinalAnswerTool, ListDirectoryTool, ReadFileTool, SearchFilesTool, create_fs_tools,
     create_fs_tools_with_fs,
 };
+pub use graph_service::{graph_service_router, serve_graph};
 pub use graph_tools::{
-    FindCallersTool, GetSymbolTool, GraphQueryTool, SharedGraphStore, create_graph_tools,
-    wrap_store,
+    FindCallersTool, FindCyclesTool, FindSensitiveTool, GetSymbolTool, GraphQueryTool,
+    LazyGraphStore, LazyGraphTools, SharedGraphStore, create_graph_tools, wrap_store,
 };
 pub use groq::{GroqBackend, GroqConfig};
-pub use mcp::{McpServerConfig, RlmServerHandler, run_mcp_server};
+pub use index_status::{IndexState, IndexStatus, IndexStatusSnapshot};
+pub use internal_answers::InternalAnswers;
+pub use mcp::{
+    GraphResource, GraphResources, McpServerConfig, RlmServerHandler, run_graph_mcp_server,
+    run_mcp_server,
+};
+pub use notify::{NotificationEvent, Notifier, NotifierConfig, WebhookFormat};
 pub use oauth::{
     OAuthConfig, OAuthTokens, PkceChallenge, build_authorization_url, exchange_code_for_tokens,
     generate_state, parse_code_state,
 };
 pub use ollama::{OllamaBackend, OllamaConfig};
+pub use openai_compat::ChatTranslation;
 pub use passthrough::{
-    ANTHROPIC_API_URL, AnthropicPassthrough, ApiProvider, OPENAI_API_URL, Passthrough,
-    PassthroughConfig,
+    ANTHROPIC_API_URL, AnthropicPassthrough, ApiProvider, ConnectionPoolConfig, ConnectionStats,
+    ConnectionStatsSnapshot, OPENAI_API_URL, Passthrough, PassthroughConfig, RetryPolicy,
+    UPSTREAM_URL_HEADER, UpstreamBodyStream, UpstreamStream,
+};
+#[cfg(feature = "wasm-plugins")]
+pub use plugin::{Capability, PluginHost, PluginTransform, WasmPlugin};
+pub use profile::ProviderProfile;
+pub use prompts::{CORE_RLM_BEHAVIOR, CategoryPrompt, PromptRegistry, RouteCategory};
+pub use proxy::{
+    ProxyConfig, ProxyServer, RouteStats, RouteStatsSnapshot, TAGS_HEADER, TRACE_ID_HEADER,
+};
+pub use recording::{
+    RecordedExchange, SharedRecorder, UPSTREAM_RECORDING_FILE, UpstreamRecorder, read_recordings,
 };
-pub use prompts::CORE_RLM_BEHAVIOR;
-pub use proxy::{ProxyConfig, ProxyServer};
 pub use repl_tools::{
     CheckLanguageTool, ExecuteCodeTool, ExecutionResult, Language, ProcessSandbox, Sandbox,
     SandboxConfig, SharedSandbox, create_default_repl_tools, create_repl_tools,
 };
-pub use router::{RouteDecision, Router, RouterConfig, RouterStrategy};
+pub use response_transform::{
+    CoalesceToolDeltas, ResponsePipeline, ResponseSource, ResponseTransform, StreamTransform,
+    StripThinking, TraceIdAnnotation, TransformContext,
+};
+pub use revision_tools::{CURRENT_REVISION, create_revision_tools};
+pub use router::{
+    InternalRequest, LlmRouterBackend, RouteDecision, Router, RouterBackend, RouterConfig,
+    RouterStrategy, RouterTraceData, RoutingTrainingRecord,
+};
+pub use routing_log::{CORRECTION_METHOD, ROUTING_LOG_FILE, RoutingLog};
+pub use session_export::{ExportQuery, session_export_router};
+pub use session_history::{HistoryEntry, SessionHistory, SessionHistoryTool};
+pub use session_recording::{
+    RecordedRequest, ReplayedRequest, SESSION_RECORDING_FILE, SessionRecorder, SessionReplayer,
+    read_session_recording,
+};
+pub use session_ui::{RequestSummary, serve_session_ui, session_ui_router};
+pub use spend::{SPEND_LEDGER_FILE, SpendEntry, SpendLedger, TokenUsage};
+pub use sse::{SseEvent, SseInspector, SseParser, StreamStats};
 pub use subquery::{SubQuery, SubQueryExecutor, SubQueryResult, spawn_subquery_tool};
+pub use token_crypto::{TOKEN_PASSPHRASE_ENV, TokenEncryption, TokenKeySource};
 pub use token_manager::{
     FileTokenManager, InMemoryTokenManager, SharedTokenManager, TOKEN_FILE, TokenInfo,
     TokenManager, create_memory_token_manager, create_memory_token_manager_with_tokens,
     create_token_manager,
 };
+pub use tool_middleware::{
+    CacheMiddleware, GuardMiddleware, IndexHintMiddleware, LoggingMiddleware, RateLimitMiddleware,
+    RewriteMiddleware, ToolCall, ToolMiddleware,
+};
+pub use tool_params::ToolParams;
+pub use tool_repair::{RepairOutcome, coerce_to_schema, repair_json, repair_tool_input};
 pub use tools::{
     CompositeToolEnvironment, EmptyToolEnvironment, MockToolEnvironment, SharedToolEnvironment,
-    Tool, ToolContent, ToolEnvironment, ToolMetadata, ToolRegistry, ToolResult,
+    Tool, ToolCategory, ToolContent, ToolEnvironment, ToolExposure, ToolFormat, ToolMetadata,
+    ToolRegistry, ToolResult, with_tool_grant,
 };
 pub use types::{
     BudgetConfig, CompletionRequest, CompletionResponse, Content, ContentBlock,
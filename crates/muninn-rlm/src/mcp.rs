@@ -3,25 +3,41 @@
 //! This module provides an MCP server that exposes tools via the Model Context Protocol,
 //! allowing external LLM clients to discover and execute tools.
 //!
+//! Tools an agent already has (like `read_file`) are internal and not
+//! listed; a [`ToolExposure`] in the [`McpServerConfig`] overrides that per
+//! tool, and tools it hides can't be called either.
+//!
+//! With a graph store attached, the indexed files and symbols are also
+//! exposed read-only as MCP resources (`muninn://file/<path>`,
+//! `muninn://symbol/<name>`), so clients can browse the index natively and
+//! subscribe to updates when the watcher sees a file change.
+//!
 //! Uses `rust-mcp-sdk` for protocol handling.
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use async_trait::async_trait;
+use muninn_graph::{FileWatcher, value_to_json};
 use rust_mcp_sdk::{
     McpServer, StdioTransport, ToMcpServerHandler, TransportOptions,
     mcp_server::{McpServerOptions, ServerHandler, server_runtime},
     schema::{
         CallToolRequestParams, CallToolResult, Implementation, InitializeResult,
-        LATEST_PROTOCOL_VERSION, ListToolsResult, PaginatedRequestParams, RpcError,
-        ServerCapabilities, ServerCapabilitiesTools, TextContent, Tool as McpTool, ToolInputSchema,
+        LATEST_PROTOCOL_VERSION, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+        PaginatedRequestParams, ReadResourceRequestParams, ReadResourceResult, Resource,
+        ResourceTemplate, ResourceUpdatedNotificationParams, RpcError, ServerCapabilities,
+        ServerCapabilitiesResources, ServerCapabilitiesTools, SubscribeRequestParams, TextContent,
+        TextResourceContents, Tool as McpTool, ToolInputSchema, UnsubscribeRequestParams,
     },
 };
-use tracing::info;
+use tracing::{debug, info, warn};
 
 use crate::error::{Result, RlmError};
-use crate::tools::ToolEnvironment;
-use crate::types::ToolUseBlock;
+use crate::graph_tools::SharedGraphStore;
+use crate::tools::{ToolEnvironment, ToolExposure};
+use crate::types::{ToolDefinition, ToolUseBlock};
 
 // ============================================================================
 // MCP Server Configuration
@@ -36,6 +52,10 @@ pub struct McpServerConfig {
     pub version: String,
     /// Optional instructions for the LLM.
     pub instructions: Option<String>,
+    /// Repository root to watch for changes to subscribed graph resources.
+    pub watch_root: Option<PathBuf>,
+    /// Which tools are listed and callable.
+    pub tool_exposure: ToolExposure,
 }
 
 impl Default for McpServerConfig {
@@ -44,6 +64,8 @@ impl Default for McpServerConfig {
             name: "muninn-rlm".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             instructions: None,
+            watch_root: None,
+            tool_exposure: ToolExposure::default(),
         }
     }
 }
@@ -68,6 +90,282 @@ impl McpServerConfig {
         self.instructions = Some(instructions.into());
         self
     }
+
+    /// Watch `root` and notify resource subscribers when files change.
+    pub fn with_watch_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.watch_root = Some(root.into());
+        self
+    }
+
+    /// Override which tools are exposed.
+    pub fn with_tool_exposure(mut self, exposure: ToolExposure) -> Self {
+        self.tool_exposure = exposure;
+        self
+    }
+}
+
+// ============================================================================
+// Graph Resources
+// ============================================================================
+
+const FILE_URI_PREFIX: &str = "muninn://file/";
+const SYMBOL_URI_PREFIX: &str = "muninn://symbol/";
+const RESOURCE_MIME_TYPE: &str = "application/json";
+
+/// A graph resource addressed by a `muninn://` URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphResource {
+    /// An indexed file, by path relative to the repository root.
+    File(String),
+    /// A symbol, by qualified name or plain name.
+    Symbol(String),
+}
+
+impl GraphResource {
+    /// Parse a `muninn://file/...` or `muninn://symbol/...` URI.
+    pub fn parse(uri: &str) -> Option<Self> {
+        if let Some(path) = uri.strip_prefix(FILE_URI_PREFIX) {
+            (!path.is_empty()).then(|| Self::File(path.to_string()))
+        } else if let Some(name) = uri.strip_prefix(SYMBOL_URI_PREFIX) {
+            (!name.is_empty()).then(|| Self::Symbol(name.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// The resource's URI.
+    pub fn uri(&self) -> String {
+        match self {
+            Self::File(path) => format!("{}{}", FILE_URI_PREFIX, path),
+            Self::Symbol(name) => format!("{}{}", SYMBOL_URI_PREFIX, name),
+        }
+    }
+}
+
+/// Read a string property from a graph node, whether nested under
+/// `properties` or not.
+fn node_str<'a>(node: &'a serde_json::Value, key: &str) -> Option<&'a str> {
+    node.get("properties")
+        .and_then(|props| props.get(key))
+        .or_else(|| node.get(key))
+        .and_then(|v| v.as_str())
+}
+
+/// Read-only view of the code graph as MCP resources, with the
+/// subscriptions clients hold on them.
+pub struct GraphResources {
+    store: SharedGraphStore,
+    /// Subscribed URIs and the files each one is read from.
+    subscriptions: Mutex<HashMap<String, HashSet<String>>>,
+    /// Server runtime to send update notifications through, captured on
+    /// the first subscription.
+    runtime: OnceLock<Arc<dyn McpServer>>,
+}
+
+impl GraphResources {
+    /// Create resources backed by the given graph store.
+    pub fn new(store: SharedGraphStore) -> Self {
+        Self {
+            store,
+            subscriptions: Mutex::new(HashMap::new()),
+            runtime: OnceLock::new(),
+        }
+    }
+
+    fn lock_store(&self) -> Result<std::sync::MutexGuard<'_, muninn_graph::GraphStore>> {
+        self.store
+            .lock()
+            .map_err(|e| RlmError::ToolExecution(format!("Failed to acquire store lock: {}", e)))
+    }
+
+    /// One resource per indexed file, sorted by path.
+    pub fn list(&self) -> Result<Vec<Resource>> {
+        let mut paths: Vec<String> = self
+            .lock_store()?
+            .file_paths()
+            .map_err(|e| RlmError::ToolExecution(format!("Failed to list files: {}", e)))?
+            .into_iter()
+            .collect();
+        paths.sort();
+
+        Ok(paths
+            .into_iter()
+            .map(|path| Resource {
+                uri: GraphResource::File(path.clone()).uri(),
+                name: path,
+                description: Some("Symbols defined in this file".to_string()),
+                mime_type: Some(RESOURCE_MIME_TYPE.to_string()),
+                annotations: None,
+                icons: vec![],
+                meta: None,
+                size: None,
+                title: None,
+            })
+            .collect())
+    }
+
+    /// URI templates for files and symbols.
+    pub fn templates() -> Vec<ResourceTemplate> {
+        let template = |uri_template: String, name: &str, description: &str| ResourceTemplate {
+            uri_template,
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            mime_type: Some(RESOURCE_MIME_TYPE.to_string()),
+            annotations: None,
+            icons: vec![],
+            meta: None,
+            title: None,
+        };
+        vec![
+            template(
+                format!("{}{{path}}", FILE_URI_PREFIX),
+                "file",
+                "Symbols defined in an indexed file, by path relative to the repository root",
+            ),
+            template(
+                format!("{}{{name}}", SYMBOL_URI_PREFIX),
+                "symbol",
+                "Symbols with the given qualified name, or else plain name",
+            ),
+        ]
+    }
+
+    /// Matching graph nodes for `resource`, as JSON.
+    fn nodes(&self, resource: &GraphResource) -> Result<Vec<serde_json::Value>> {
+        let store = self.lock_store()?;
+        let nodes = match resource {
+            GraphResource::File(path) => store.find_symbols_in_file(path),
+            GraphResource::Symbol(name) => store.find_by_qualified_name(name).and_then(|nodes| {
+                if nodes.is_empty() {
+                    store.find_by_name(name)
+                } else {
+                    Ok(nodes)
+                }
+            }),
+        }
+        .map_err(|e| RlmError::ToolExecution(format!("Failed to query graph: {}", e)))?;
+        Ok(nodes.iter().map(value_to_json).collect())
+    }
+
+    /// Files the nodes of `resource` are defined in.
+    fn files_of(&self, resource: &GraphResource) -> Result<HashSet<String>> {
+        Ok(match resource {
+            GraphResource::File(path) => HashSet::from([path.clone()]),
+            GraphResource::Symbol(_) => self
+                .nodes(resource)?
+                .iter()
+                .filter_map(|node| node_str(node, "file_path").map(str::to_string))
+                .collect(),
+        })
+    }
+
+    /// Read `resource` as a JSON document, or `None` if nothing in the
+    /// graph matches it.
+    pub fn read(&self, resource: &GraphResource) -> Result<Option<String>> {
+        let nodes = self.nodes(resource)?;
+        if nodes.is_empty() {
+            return Ok(None);
+        }
+        let symbols: Vec<serde_json::Value> = nodes
+            .into_iter()
+            .map(|mut node| {
+                let name = node_str(&node, "qualified_name")
+                    .or_else(|| node_str(&node, "name"))
+                    .map(|name| GraphResource::Symbol(name.to_string()).uri());
+                if let (Some(uri), Some(obj)) = (name, node.as_object_mut()) {
+                    obj.insert("uri".to_string(), serde_json::Value::String(uri));
+                }
+                node
+            })
+            .collect();
+        let output = serde_json::json!({
+            "uri": resource.uri(),
+            "symbols": symbols,
+            "count": symbols.len(),
+        });
+        Ok(Some(
+            serde_json::to_string_pretty(&output).unwrap_or_default(),
+        ))
+    }
+
+    /// Subscribe to updates of `resource`.
+    pub fn subscribe(&self, resource: &GraphResource) -> Result<()> {
+        let files = self.files_of(resource)?;
+        if let Ok(mut subscriptions) = self.subscriptions.lock() {
+            subscriptions.insert(resource.uri(), files);
+        }
+        Ok(())
+    }
+
+    /// Drop the subscription to `uri`, if any.
+    pub fn unsubscribe(&self, uri: &str) {
+        if let Ok(mut subscriptions) = self.subscriptions.lock() {
+            subscriptions.remove(uri);
+        }
+    }
+
+    /// Subscribed URIs affected by changes to `changed` files, sorted.
+    pub fn invalidated(&self, changed: &[String]) -> Vec<String> {
+        let Ok(subscriptions) = self.subscriptions.lock() else {
+            return Vec::new();
+        };
+        let mut uris: Vec<String> = subscriptions
+            .iter()
+            .filter(|(_, files)| changed.iter().any(|path| files.contains(path)))
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        uris.sort();
+        uris
+    }
+
+    /// Notify subscribers of resources affected by changes to `changed`
+    /// files. Symbol subscriptions are re-resolved afterwards, since a
+    /// change can move a symbol to another file.
+    pub async fn notify_changed(&self, changed: &[String]) {
+        let uris = self.invalidated(changed);
+        let Some(runtime) = self.runtime.get() else {
+            return;
+        };
+        for uri in uris {
+            if let Some(resource) = GraphResource::parse(&uri) {
+                if let Err(e) = self.subscribe(&resource) {
+                    warn!("Failed to refresh subscription {}: {}", uri, e);
+                }
+            }
+            debug!("Resource updated: {}", uri);
+            let params = ResourceUpdatedNotificationParams { uri, meta: None };
+            if let Err(e) = runtime.notify_resource_updated(params).await {
+                warn!("Failed to send resource update: {}", e);
+            }
+        }
+    }
+}
+
+/// Watch `root` on a background thread and notify subscribers of
+/// `resources` about changed files.
+pub fn spawn_resource_watcher(resources: Arc<GraphResources>, root: &Path) -> Result<()> {
+    let watcher = FileWatcher::new(root)
+        .map_err(|e| RlmError::Config(format!("Failed to watch {}: {}", root.display(), e)))?;
+    let root = root.to_path_buf();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+
+    std::thread::spawn(move || {
+        while let Some(event) = watcher.next_event() {
+            let path = event.path();
+            let relative = path.strip_prefix(&root).unwrap_or(path);
+            if tx.send(relative.to_string_lossy().into_owned()).is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Some(path) = rx.recv().await {
+            resources.notify_changed(&[path]).await;
+        }
+    });
+
+    Ok(())
 }
 
 // ============================================================================
@@ -77,16 +375,56 @@ impl McpServerConfig {
 /// MCP server handler that bridges `ToolEnvironment` to MCP protocol.
 pub struct RlmServerHandler {
     tools: Arc<dyn ToolEnvironment>,
+    exposure: ToolExposure,
+    resources: Option<Arc<GraphResources>>,
 }
 
 impl RlmServerHandler {
     /// Create a new handler with the given tool environment.
     pub fn new(tools: Arc<dyn ToolEnvironment>) -> Self {
         info!("Initializing RLM MCP Server Handler");
-        Self { tools }
+        Self {
+            tools,
+            exposure: ToolExposure::default(),
+            resources: None,
+        }
+    }
+
+    /// Override which tools are listed and callable.
+    pub fn with_tool_exposure(mut self, exposure: ToolExposure) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// The tools external clients may list and call.
+    fn exposed_tools(&self) -> Vec<ToolDefinition> {
+        self.exposure.external_tools(self.tools.as_ref())
+    }
+
+    /// Also expose the given graph resources.
+    pub fn with_graph_resources(mut self, resources: Arc<GraphResources>) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    fn graph_resources(&self) -> std::result::Result<&GraphResources, RpcError> {
+        self.resources.as_deref().ok_or_else(|| {
+            RpcError::method_not_found().with_message("No code graph is attached".to_string())
+        })
     }
 }
 
+/// Parse a resource URI, rejecting unknown ones as invalid params.
+fn parse_resource_uri(uri: &str) -> std::result::Result<GraphResource, RpcError> {
+    GraphResource::parse(uri).ok_or_else(|| {
+        RpcError::invalid_params().with_message(format!("Unknown resource URI: {}", uri))
+    })
+}
+
+fn resource_error(e: RlmError) -> RpcError {
+    RpcError::internal_error().with_message(e.to_string())
+}
+
 #[async_trait]
 impl ServerHandler for RlmServerHandler {
     async fn handle_list_tools_request(
@@ -96,8 +434,7 @@ impl ServerHandler for RlmServerHandler {
     ) -> std::result::Result<ListToolsResult, RpcError> {
         // Only expose external tools (not internal fs_tools that would collide with Claude Code)
         let tools: Vec<McpTool> = self
-            .tools
-            .available_tools_external()
+            .exposed_tools()
             .into_iter()
             .map(|t| {
                 // Convert our JSON schema to ToolInputSchema
@@ -134,6 +471,21 @@ impl ServerHandler for RlmServerHandler {
         _runtime: Arc<dyn McpServer>,
     ) -> std::result::Result<CallToolResult, rust_mcp_sdk::schema::schema_utils::CallToolError>
     {
+        if !self.exposed_tools().iter().any(|t| t.name == params.name) {
+            return Ok(CallToolResult {
+                content: vec![
+                    TextContent::new(
+                        format!("Tool '{}' is not exposed via MCP", params.name),
+                        None,
+                        None,
+                    )
+                    .into(),
+                ],
+                is_error: Some(true),
+                meta: None,
+                structured_content: None,
+            });
+        }
         let args = serde_json::Value::Object(params.arguments.unwrap_or_default());
 
         // Create a ToolUseBlock for our tool environment
@@ -169,6 +521,81 @@ impl ServerHandler for RlmServerHandler {
             }),
         }
     }
+
+    async fn handle_list_resources_request(
+        &self,
+        _params: Option<PaginatedRequestParams>,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ListResourcesResult, RpcError> {
+        let resources = self.graph_resources()?.list().map_err(resource_error)?;
+        Ok(ListResourcesResult {
+            resources,
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    async fn handle_list_resource_templates_request(
+        &self,
+        _params: Option<PaginatedRequestParams>,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ListResourceTemplatesResult, RpcError> {
+        self.graph_resources()?;
+        Ok(ListResourceTemplatesResult {
+            resource_templates: GraphResources::templates(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    async fn handle_read_resource_request(
+        &self,
+        params: ReadResourceRequestParams,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ReadResourceResult, RpcError> {
+        let resource = parse_resource_uri(&params.uri)?;
+        let text = self
+            .graph_resources()?
+            .read(&resource)
+            .map_err(resource_error)?
+            .ok_or_else(|| {
+                RpcError::invalid_params()
+                    .with_message(format!("Resource not found: {}", params.uri))
+            })?;
+        Ok(ReadResourceResult {
+            contents: vec![
+                TextResourceContents {
+                    uri: params.uri,
+                    text,
+                    mime_type: Some(RESOURCE_MIME_TYPE.to_string()),
+                    meta: None,
+                }
+                .into(),
+            ],
+            meta: None,
+        })
+    }
+
+    async fn handle_subscribe_request(
+        &self,
+        params: SubscribeRequestParams,
+        runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<rust_mcp_sdk::schema::Result, RpcError> {
+        let resource = parse_resource_uri(&params.uri)?;
+        let resources = self.graph_resources()?;
+        resources.subscribe(&resource).map_err(resource_error)?;
+        let _ = resources.runtime.set(runtime);
+        Ok(Default::default())
+    }
+
+    async fn handle_unsubscribe_request(
+        &self,
+        params: UnsubscribeRequestParams,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<rust_mcp_sdk::schema::Result, RpcError> {
+        self.graph_resources()?.unsubscribe(&params.uri);
+        Ok(Default::default())
+    }
 }
 
 // ============================================================================
@@ -180,6 +607,29 @@ pub async fn run_mcp_server(
     tools: Arc<dyn ToolEnvironment>,
     config: McpServerConfig,
 ) -> Result<()> {
+    let handler = RlmServerHandler::new(tools).with_tool_exposure(config.tool_exposure.clone());
+    serve(handler, config).await
+}
+
+/// Run an MCP server on stdio transport with the given tool environment,
+/// also exposing `store` as read-only resources. Subscribers are notified
+/// of changes under the configured watch root, if any.
+pub async fn run_graph_mcp_server(
+    tools: Arc<dyn ToolEnvironment>,
+    store: SharedGraphStore,
+    config: McpServerConfig,
+) -> Result<()> {
+    let resources = Arc::new(GraphResources::new(store));
+    if let Some(root) = &config.watch_root {
+        spawn_resource_watcher(Arc::clone(&resources), root)?;
+    }
+    let handler = RlmServerHandler::new(tools)
+        .with_tool_exposure(config.tool_exposure.clone())
+        .with_graph_resources(resources);
+    serve(handler, config).await
+}
+
+async fn serve(handler: RlmServerHandler, config: McpServerConfig) -> Result<()> {
     info!("Starting MCP Server: {}", config.name);
 
     let server_details = InitializeResult {
@@ -193,6 +643,13 @@ pub async fn run_mcp_server(
         },
         capabilities: ServerCapabilities {
             tools: Some(ServerCapabilitiesTools { list_changed: None }),
+            resources: handler
+                .resources
+                .as_ref()
+                .map(|_| ServerCapabilitiesResources {
+                    list_changed: None,
+                    subscribe: Some(true),
+                }),
             ..Default::default()
         },
         meta: None,
@@ -203,7 +660,7 @@ pub async fn run_mcp_server(
     let transport = StdioTransport::new(TransportOptions::default())
         .map_err(|e| RlmError::Protocol(format!("Failed to create transport: {}", e)))?;
 
-    let handler = RlmServerHandler::new(tools).to_mcp_server_handler();
+    let handler = handler.to_mcp_server_handler();
 
     let server = server_runtime::create_server(McpServerOptions {
         server_details,
@@ -229,9 +686,12 @@ pub async fn run_mcp_server(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::graph_tools::wrap_store;
     use crate::tools::MockToolEnvironment;
     use crate::types::ToolDefinition;
+    use muninn_graph::{GraphStore, Symbol, SymbolKind};
     use serde_json::json;
+    use serial_test::serial;
 
     fn mock_env() -> Arc<dyn ToolEnvironment> {
         Arc::new(MockToolEnvironment::new(vec![ToolDefinition::new(
@@ -270,4 +730,101 @@ mod tests {
         let _handler = RlmServerHandler::new(mock_env());
         // Handler created successfully
     }
+
+    #[test]
+    fn test_handler_honors_tool_exposure() {
+        let handler = RlmServerHandler::new(mock_env());
+        assert_eq!(handler.exposed_tools().len(), 1);
+
+        let handler = RlmServerHandler::new(mock_env())
+            .with_tool_exposure(ToolExposure::new().with("test_tool", false));
+        assert!(handler.exposed_tools().is_empty());
+    }
+
+    fn test_resources() -> GraphResources {
+        let store = GraphStore::open_in_memory().unwrap();
+        for (name, file) in [("main", "src/main.rs"), ("helper", "src/util.rs")] {
+            let mut symbol = Symbol::new(name, SymbolKind::Function, file, 1, 5);
+            symbol.qualified_name = Some(format!("crate::{}", name));
+            store.insert_node(&symbol).unwrap();
+        }
+        GraphResources::new(wrap_store(store))
+    }
+
+    #[test]
+    fn test_resource_uri_roundtrip() {
+        let file = GraphResource::parse("muninn://file/src/main.rs").unwrap();
+        assert_eq!(file, GraphResource::File("src/main.rs".to_string()));
+        assert_eq!(file.uri(), "muninn://file/src/main.rs");
+
+        let symbol = GraphResource::parse("muninn://symbol/crate::main").unwrap();
+        assert_eq!(symbol, GraphResource::Symbol("crate::main".to_string()));
+
+        assert_eq!(GraphResource::parse("muninn://file/"), None);
+        assert_eq!(GraphResource::parse("file:///src/main.rs"), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_list_and_read_resources() {
+        let resources = test_resources();
+
+        let uris: Vec<String> = resources
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.uri)
+            .collect();
+        assert_eq!(
+            uris,
+            ["muninn://file/src/main.rs", "muninn://file/src/util.rs"]
+        );
+
+        let file = resources
+            .read(&GraphResource::File("src/main.rs".to_string()))
+            .unwrap()
+            .unwrap();
+        assert!(file.contains("muninn://symbol/crate::main"), "{}", file);
+
+        // Qualified name first, then plain name.
+        for name in ["crate::helper", "helper"] {
+            let symbol = resources
+                .read(&GraphResource::Symbol(name.to_string()))
+                .unwrap()
+                .unwrap();
+            assert!(symbol.contains("src/util.rs"), "{}", symbol);
+        }
+
+        let missing = GraphResource::Symbol("nope".to_string());
+        assert!(resources.read(&missing).unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_subscriptions_invalidated_by_file_changes() {
+        let resources = test_resources();
+        resources
+            .subscribe(&GraphResource::File("src/main.rs".to_string()))
+            .unwrap();
+        resources
+            .subscribe(&GraphResource::Symbol("crate::helper".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            resources.invalidated(&["src/util.rs".to_string()]),
+            ["muninn://symbol/crate::helper"]
+        );
+        assert_eq!(
+            resources.invalidated(&["src/main.rs".to_string(), "src/util.rs".to_string()]),
+            ["muninn://file/src/main.rs", "muninn://symbol/crate::helper"]
+        );
+        assert!(resources.invalidated(&["README.md".to_string()]).is_empty());
+
+        resources.unsubscribe("muninn://file/src/main.rs");
+        assert!(
+            resources
+                .invalidated(&["src/main.rs".to_string()])
+                .is_empty()
+        );
+    }
 }
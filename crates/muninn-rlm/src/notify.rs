@@ -0,0 +1,441 @@
+//! Webhook notifications for budget, backend and spend events.
+//!
+//! The proxy feeds a [`Notifier`] from the same points it records traces
+//! and [`RouteStats`](crate::proxy::RouteStats): each RLM error, each
+//! upstream success or failure, and each request's token usage. The
+//! notifier turns those into [`NotificationEvent`]s and POSTs them to a
+//! webhook, either as a Slack incoming-webhook message or as plain JSON:
+//!
+//! - [`NotificationEvent::BudgetExceeded`] for every RLM request that ran
+//!   out of budget, rate limited by a cooldown;
+//! - [`NotificationEvent::BackendUnhealthy`] once a backend fails
+//!   `unhealthy_after` times in a row, and
+//!   [`NotificationEvent::BackendRecovered`] on its next success;
+//! - [`NotificationEvent::SpendThreshold`] the first time the day's (UTC)
+//!   token total crosses each configured threshold.
+//!
+//! Delivery is fire-and-forget on the tokio runtime; failures are logged.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{NaiveDate, Utc};
+use muninn_core::MuninnCoreError;
+use serde::Serialize;
+
+use crate::spend::TokenUsage;
+
+/// Default consecutive failures before a backend is reported unhealthy.
+pub const DEFAULT_UNHEALTHY_AFTER: u32 = 3;
+
+/// Default minimum gap between two budget notifications.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(300);
+
+/// Webhook payload shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// Slack incoming webhook: `{"text": "..."}`.
+    #[default]
+    Slack,
+    /// `{"source": "muninn", "text": "...", "event": {...}}`.
+    Json,
+}
+
+/// Configuration for the [`Notifier`].
+#[derive(Debug, Clone)]
+pub struct NotifierConfig {
+    /// URL events are POSTed to.
+    pub webhook_url: String,
+    /// Payload shape.
+    pub format: WebhookFormat,
+    /// Notify when an RLM request exceeds its budget.
+    pub on_budget: bool,
+    /// Notify when a backend goes unhealthy or recovers.
+    pub on_backend: bool,
+    /// Consecutive failures that make a backend unhealthy.
+    pub unhealthy_after: u32,
+    /// Daily token totals to notify at, ascending.
+    pub daily_token_thresholds: Vec<u64>,
+    /// Minimum gap between two budget notifications.
+    pub cooldown: Duration,
+}
+
+impl NotifierConfig {
+    /// Notify `webhook_url` in Slack format about budget and backend events.
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+            format: WebhookFormat::default(),
+            on_budget: true,
+            on_backend: true,
+            unhealthy_after: DEFAULT_UNHEALTHY_AFTER,
+            daily_token_thresholds: Vec::new(),
+            cooldown: DEFAULT_COOLDOWN,
+        }
+    }
+
+    /// Set the payload shape.
+    pub fn with_format(mut self, format: WebhookFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Enable or disable budget notifications.
+    pub fn with_budget_events(mut self, enable: bool) -> Self {
+        self.on_budget = enable;
+        self
+    }
+
+    /// Enable or disable backend health notifications.
+    pub fn with_backend_events(mut self, enable: bool) -> Self {
+        self.on_backend = enable;
+        self
+    }
+
+    /// Set how many consecutive failures make a backend unhealthy.
+    pub fn with_unhealthy_after(mut self, failures: u32) -> Self {
+        self.unhealthy_after = failures.max(1);
+        self
+    }
+
+    /// Set the daily token thresholds.
+    pub fn with_daily_token_thresholds(mut self, mut thresholds: Vec<u64>) -> Self {
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        self.daily_token_thresholds = thresholds;
+        self
+    }
+
+    /// Set the budget notification cooldown.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+}
+
+/// Something worth telling a human about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// An RLM request ran out of budget.
+    BudgetExceeded { trace_id: String, detail: String },
+    /// A backend failed repeatedly.
+    BackendUnhealthy {
+        backend: String,
+        consecutive_failures: u32,
+        error: String,
+    },
+    /// A previously unhealthy backend answered again.
+    BackendRecovered { backend: String },
+    /// The day's token total crossed a threshold.
+    SpendThreshold {
+        date: NaiveDate,
+        tokens: u64,
+        threshold: u64,
+    },
+}
+
+impl NotificationEvent {
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        match self {
+            Self::BudgetExceeded { trace_id, detail } => {
+                format!(
+                    "muninn: RLM budget exceeded ({}) [trace {}]",
+                    detail, trace_id
+                )
+            }
+            Self::BackendUnhealthy {
+                backend,
+                consecutive_failures,
+                error,
+            } => format!(
+                "muninn: {} backend unhealthy after {} consecutive failures: {}",
+                backend, consecutive_failures, error
+            ),
+            Self::BackendRecovered { backend } => {
+                format!("muninn: {} backend recovered", backend)
+            }
+            Self::SpendThreshold {
+                date,
+                tokens,
+                threshold,
+            } => format!(
+                "muninn: {} tokens used on {}, past the {} threshold",
+                tokens, date, threshold
+            ),
+        }
+    }
+
+    /// Webhook body for this event.
+    pub fn payload(&self, format: WebhookFormat) -> serde_json::Value {
+        match format {
+            WebhookFormat::Slack => serde_json::json!({ "text": self.summary() }),
+            WebhookFormat::Json => serde_json::json!({
+                "source": "muninn",
+                "text": self.summary(),
+                "event": self,
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NotifierState {
+    failures: HashMap<String, u32>,
+    day: NaiveDate,
+    day_tokens: u64,
+    /// Thresholds already reported today (a prefix of the sorted list).
+    thresholds_fired: usize,
+    last_budget: Option<Instant>,
+}
+
+/// Turns proxy events into webhook notifications.
+#[derive(Debug)]
+pub struct Notifier {
+    config: NotifierConfig,
+    client: reqwest::Client,
+    state: Mutex<NotifierState>,
+}
+
+impl Notifier {
+    /// Create a notifier from `config`.
+    pub fn new(config: NotifierConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            state: Mutex::new(NotifierState {
+                failures: HashMap::new(),
+                day: Utc::now().date_naive(),
+                day_tokens: 0,
+                thresholds_fired: 0,
+                last_budget: None,
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, NotifierState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a failed RLM request.
+    pub fn rlm_error(&self, trace_id: &str, error: &MuninnCoreError) {
+        match error {
+            MuninnCoreError::BudgetExceeded(detail) => {
+                if let Some(event) = self.budget_event(trace_id, detail, Instant::now()) {
+                    self.send(event);
+                }
+            }
+            MuninnCoreError::Backend(msg) => self.backend_failure("rlm", msg),
+            _ => {}
+        }
+    }
+
+    /// Record a failed call to `backend`.
+    pub fn backend_failure(&self, backend: &str, error: &str) {
+        if let Some(event) = self.failure_event(backend, error) {
+            self.send(event);
+        }
+    }
+
+    /// Record a successful call to `backend`.
+    pub fn backend_ok(&self, backend: &str) {
+        if let Some(event) = self.success_event(backend) {
+            self.send(event);
+        }
+    }
+
+    /// Add one request's tokens to today's total.
+    pub fn record_usage(&self, usage: &TokenUsage) {
+        let tokens = usage.input_tokens
+            + usage.output_tokens
+            + usage.cache_creation_input_tokens
+            + usage.cache_read_input_tokens;
+        for event in self.spend_events(Utc::now().date_naive(), tokens) {
+            self.send(event);
+        }
+    }
+
+    fn budget_event(
+        &self,
+        trace_id: &str,
+        detail: &str,
+        now: Instant,
+    ) -> Option<NotificationEvent> {
+        if !self.config.on_budget {
+            return None;
+        }
+        let mut state = self.state();
+        if state
+            .last_budget
+            .is_some_and(|last| now.duration_since(last) < self.config.cooldown)
+        {
+            return None;
+        }
+        state.last_budget = Some(now);
+        Some(NotificationEvent::BudgetExceeded {
+            trace_id: trace_id.to_string(),
+            detail: detail.to_string(),
+        })
+    }
+
+    fn failure_event(&self, backend: &str, error: &str) -> Option<NotificationEvent> {
+        let mut state = self.state();
+        let failures = state.failures.entry(backend.to_string()).or_insert(0);
+        *failures += 1;
+        // Only the transition into unhealthy is reported.
+        (self.config.on_backend && *failures == self.config.unhealthy_after).then(|| {
+            NotificationEvent::BackendUnhealthy {
+                backend: backend.to_string(),
+                consecutive_failures: *failures,
+                error: error.chars().take(300).collect(),
+            }
+        })
+    }
+
+    fn success_event(&self, backend: &str) -> Option<NotificationEvent> {
+        let failures = self.state().failures.remove(backend)?;
+        (self.config.on_backend && failures >= self.config.unhealthy_after).then(|| {
+            NotificationEvent::BackendRecovered {
+                backend: backend.to_string(),
+            }
+        })
+    }
+
+    fn spend_events(&self, today: NaiveDate, tokens: u64) -> Vec<NotificationEvent> {
+        let mut state = self.state();
+        if state.day != today {
+            state.day = today;
+            state.day_tokens = 0;
+            state.thresholds_fired = 0;
+        }
+        state.day_tokens += tokens;
+        let mut events = Vec::new();
+        while let Some(&threshold) = self
+            .config
+            .daily_token_thresholds
+            .get(state.thresholds_fired)
+            .filter(|&&t| state.day_tokens >= t)
+        {
+            state.thresholds_fired += 1;
+            events.push(NotificationEvent::SpendThreshold {
+                date: today,
+                tokens: state.day_tokens,
+                threshold,
+            });
+        }
+        events
+    }
+
+    fn send(&self, event: NotificationEvent) {
+        tracing::info!(event = %event.summary(), "Sending notification");
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("No async runtime; dropping notification");
+            return;
+        };
+        let request = self
+            .client
+            .post(&self.config.webhook_url)
+            .timeout(Duration::from_secs(10))
+            .json(&event.payload(self.config.format));
+        runtime.spawn(async move {
+            match request.send().await {
+                Ok(r) if r.status().is_success() => {}
+                Ok(r) => {
+                    tracing::warn!(status = %r.status(), "Notification webhook rejected event")
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to deliver notification"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifier(config: NotifierConfig) -> Notifier {
+        Notifier::new(config)
+    }
+
+    #[test]
+    fn test_backend_health_transitions() {
+        let n = notifier(NotifierConfig::new("http://unused").with_unhealthy_after(2));
+        assert_eq!(n.failure_event("upstream", "503"), None);
+        assert!(matches!(
+            n.failure_event("upstream", "503"),
+            Some(NotificationEvent::BackendUnhealthy {
+                consecutive_failures: 2,
+                ..
+            })
+        ));
+        // Still unhealthy: no repeat.
+        assert_eq!(n.failure_event("upstream", "503"), None);
+        assert_eq!(
+            n.success_event("upstream"),
+            Some(NotificationEvent::BackendRecovered {
+                backend: "upstream".to_string()
+            })
+        );
+        assert_eq!(n.success_event("upstream"), None);
+
+        // A single blip never reports recovery.
+        n.failure_event("rlm", "timeout");
+        assert_eq!(n.success_event("rlm"), None);
+    }
+
+    #[test]
+    fn test_budget_cooldown() {
+        let n =
+            notifier(NotifierConfig::new("http://unused").with_cooldown(Duration::from_secs(60)));
+        let now = Instant::now();
+        assert!(n.budget_event("t1", "tokens", now).is_some());
+        assert!(
+            n.budget_event("t2", "tokens", now + Duration::from_secs(10))
+                .is_none()
+        );
+        assert!(
+            n.budget_event("t3", "tokens", now + Duration::from_secs(61))
+                .is_some()
+        );
+
+        let off = notifier(NotifierConfig::new("http://unused").with_budget_events(false));
+        assert!(off.budget_event("t", "tokens", now).is_none());
+    }
+
+    #[test]
+    fn test_spend_thresholds_fire_once_per_day() {
+        let n = notifier(
+            NotifierConfig::new("http://unused").with_daily_token_thresholds(vec![1000, 100]),
+        );
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert!(n.spend_events(day, 50).is_empty());
+        let events = n.spend_events(day, 2000);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1],
+            NotificationEvent::SpendThreshold {
+                tokens: 2050,
+                threshold: 1000,
+                ..
+            }
+        ));
+        assert!(n.spend_events(day, 5000).is_empty());
+        assert_eq!(n.spend_events(day.succ_opt().unwrap(), 150).len(), 1);
+    }
+
+    #[test]
+    fn test_payload_formats() {
+        let event = NotificationEvent::BackendRecovered {
+            backend: "rlm".to_string(),
+        };
+        assert_eq!(
+            event.payload(WebhookFormat::Slack),
+            serde_json::json!({"text": "muninn: rlm backend recovered"})
+        );
+        let json = event.payload(WebhookFormat::Json);
+        assert_eq!(json["event"]["kind"], "backend_recovered");
+        assert_eq!(json["source"], "muninn");
+    }
+}
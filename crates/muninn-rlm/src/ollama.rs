@@ -7,8 +7,12 @@ use async_trait::async_trait;
 use reqwest::{Client, header};
 use std::time::Duration;
 
-use crate::backend::{LLMBackend, ResponseStream, StreamEvent, pick_model, with_retry};
+use crate::backend::{
+    LLMBackend, PayloadCapture, ResponseStream, StreamEvent, capture_call, parse_tool_arguments,
+    pick_model, with_retry, with_trace_context,
+};
 use crate::error::{Result, RlmError};
+use crate::profile::ProviderProfile;
 use crate::types::{
     CompletionRequest, CompletionResponse, ContentBlock, Role, StopReason, ToolResultContent, Usage,
 };
@@ -47,6 +51,9 @@ pub struct OllamaConfig {
 
     /// Initial backoff duration for retries.
     pub retry_backoff: Duration,
+
+    /// How much of each request/response to record in traces.
+    pub capture: PayloadCapture,
 }
 
 impl Default for OllamaConfig {
@@ -58,6 +65,7 @@ impl Default for OllamaConfig {
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             max_retries: 3,
             retry_backoff: Duration::from_millis(500),
+            capture: PayloadCapture::None,
         }
     }
 }
@@ -92,6 +100,12 @@ impl OllamaConfig {
         self
     }
 
+    /// Set how much of each request/response to record in traces.
+    pub fn with_capture(mut self, capture: PayloadCapture) -> Self {
+        self.capture = capture;
+        self
+    }
+
     /// Set the API key (required for Ollama Cloud).
     pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
         self.api_key = Some(api_key.into());
@@ -135,7 +149,7 @@ impl OllamaBackend {
 
     /// Add headers to a request.
     fn add_headers(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        let builder = builder.header(header::CONTENT_TYPE, "application/json");
+        let builder = with_trace_context(builder).header(header::CONTENT_TYPE, "application/json");
         if let Some(key) = self.config.api_key.as_deref() {
             builder.header(header::AUTHORIZATION, format!("Bearer {}", key))
         } else {
@@ -254,24 +268,21 @@ impl OllamaBackend {
         }
 
         // Convert tools to OpenAI format
-        let tools: Option<Vec<OllamaTool>> = if request.tools.is_empty() {
-            None
-        } else {
-            Some(
-                request
-                    .tools
-                    .iter()
-                    .map(|t| OllamaTool {
-                        tool_type: "function".to_string(),
-                        function: OllamaFunction {
-                            name: t.name.clone(),
-                            description: Some(t.description.clone()),
-                            parameters: t.input_schema.clone(),
-                        },
-                    })
-                    .collect(),
-            )
-        };
+        // Server tools only run at their provider
+        let tools: Vec<OllamaTool> = request
+            .tools
+            .iter()
+            .filter(|t| !t.is_server_tool())
+            .map(|t| OllamaTool {
+                tool_type: "function".to_string(),
+                function: OllamaFunction {
+                    name: t.name.clone(),
+                    description: Some(t.description.clone()),
+                    parameters: t.input_schema.clone(),
+                },
+            })
+            .collect();
+        let tools = (!tools.is_empty()).then_some(tools);
 
         OllamaChatRequest {
             model: pick_model(&request.model, &self.config.model),
@@ -304,12 +315,10 @@ impl OllamaBackend {
                 // Add tool calls if present
                 if let Some(tool_calls) = c.message.tool_calls {
                     for tc in tool_calls {
-                        let input: serde_json::Value =
-                            serde_json::from_str(&tc.function.arguments).unwrap_or_default();
                         blocks.push(ContentBlock::ToolUse {
                             id: tc.id,
                             name: tc.function.name,
-                            input,
+                            input: parse_tool_arguments(&tc.function.arguments),
                             cache_control: None,
                         });
                     }
@@ -386,11 +395,21 @@ impl OllamaBackend {
 #[async_trait]
 impl LLMBackend for OllamaBackend {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
-        with_retry(
+        let mut request = request;
+        ProviderProfile::OLLAMA.shape(&mut request);
+        let call = with_retry(
             self.config.max_retries,
             self.config.retry_backoff,
             "ollama",
             || self.send_request(&request),
+        );
+        let ollama_request = self.to_ollama_request(&request);
+        capture_call(
+            self.config.capture,
+            "ollama",
+            &ollama_request.model,
+            &ollama_request,
+            call,
         )
         .await
     }
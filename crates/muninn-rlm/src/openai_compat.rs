@@ -0,0 +1,454 @@
+//! OpenAI Chat Completions <-> Anthropic Messages translation.
+//!
+//! OpenAI-speaking agents (aider, anything built on litellm or the OpenAI
+//! SDK) post to `/v1/chat/completions`. With
+//! [`ChatTranslation::Anthropic`] the proxy converts those requests into
+//! Messages requests, runs them through the same router/RLM path as
+//! `/v1/messages`, and converts the reply back.
+//!
+//! Streaming requests are answered from a single upstream completion: the
+//! whole reply is sent as one `chat.completion.chunk` followed by a
+//! finish chunk and `[DONE]`, which OpenAI clients accept as a stream.
+
+use serde_json::{Map, Value, json};
+
+use crate::error::{Result, RlmError};
+
+/// Output cap used when the request sets neither `max_tokens` nor
+/// `max_completion_tokens` (Messages requires one).
+pub const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+/// How the proxy treats `/v1/chat/completions` bodies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ChatTranslation {
+    /// Forward the body upstream unchanged.
+    #[default]
+    Raw,
+    /// Translate to and from Anthropic Messages. Requests naming a model
+    /// that isn't a Claude model (`gpt-4o`, aider's defaults) are sent as
+    /// `default_model`.
+    Anthropic { default_model: String },
+}
+
+/// Convert a Chat Completions request body into a Messages request body.
+/// The result is always non-streaming; see the module docs.
+pub fn chat_request_to_messages(request: &Value, default_model: &str) -> Result<Value> {
+    let chat_messages = request
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| RlmError::InvalidRequest("`messages` must be an array".to_string()))?;
+
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+    for message in chat_messages {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        match role {
+            "system" | "developer" => system.push(content_text(message.get("content"))),
+            "assistant" => {
+                let mut blocks = content_blocks(message.get("content"));
+                for call in message
+                    .get("tool_calls")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let function = call.get("function").cloned().unwrap_or_default();
+                    let arguments = function
+                        .get("arguments")
+                        .and_then(Value::as_str)
+                        .unwrap_or("{}");
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.get("id").cloned().unwrap_or_default(),
+                        "name": function.get("name").cloned().unwrap_or_default(),
+                        "input": serde_json::from_str::<Value>(arguments)
+                            .unwrap_or_else(|_| json!({})),
+                    }));
+                }
+                messages.push(json!({"role": "assistant", "content": blocks}));
+            }
+            "tool" => {
+                let result = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.get("tool_call_id").cloned().unwrap_or_default(),
+                    "content": content_text(message.get("content")),
+                });
+                // Results for one assistant turn belong in a single user message
+                match messages.last_mut() {
+                    Some(last) if is_tool_result_message(last) => {
+                        if let Some(blocks) = last["content"].as_array_mut() {
+                            blocks.push(result);
+                        }
+                    }
+                    _ => messages.push(json!({"role": "user", "content": [result]})),
+                }
+            }
+            _ => messages.push(json!({
+                "role": "user",
+                "content": content_blocks(message.get("content")),
+            })),
+        }
+    }
+
+    let mut out = Map::new();
+    out.insert(
+        "model".to_string(),
+        json!(translate_model(
+            request.get("model").and_then(Value::as_str).unwrap_or(""),
+            default_model
+        )),
+    );
+    out.insert("messages".to_string(), Value::Array(messages));
+    out.insert(
+        "max_tokens".to_string(),
+        request
+            .get("max_completion_tokens")
+            .or_else(|| request.get("max_tokens"))
+            .filter(|v| v.is_u64())
+            .cloned()
+            .unwrap_or(json!(DEFAULT_MAX_TOKENS)),
+    );
+    if !system.is_empty() {
+        out.insert("system".to_string(), json!(system.join("\n\n")));
+    }
+    for key in ["temperature", "top_p"] {
+        if let Some(value) = request.get(key).filter(|v| v.is_number()) {
+            out.insert(key.to_string(), value.clone());
+        }
+    }
+    match request.get("stop") {
+        Some(Value::String(stop)) => {
+            out.insert("stop_sequences".to_string(), json!([stop]));
+        }
+        Some(Value::Array(stops)) if !stops.is_empty() => {
+            out.insert("stop_sequences".to_string(), Value::Array(stops.clone()));
+        }
+        _ => {}
+    }
+    if let Some(tools) = request.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| tool.get("function"))
+            .map(|function| {
+                let mut tool = json!({
+                    "name": function.get("name").cloned().unwrap_or_default(),
+                    "input_schema": function
+                        .get("parameters")
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "object"})),
+                });
+                if let Some(description) = function.get("description") {
+                    tool["description"] = description.clone();
+                }
+                tool
+            })
+            .collect();
+        if !tools.is_empty() {
+            out.insert("tools".to_string(), Value::Array(tools));
+        }
+    }
+    let tool_choice = match request.get("tool_choice") {
+        Some(Value::String(choice)) => match choice.as_str() {
+            "required" => Some(json!({"type": "any"})),
+            "none" => Some(json!({"type": "none"})),
+            _ => None,
+        },
+        Some(choice) => choice
+            .pointer("/function/name")
+            .map(|name| json!({"type": "tool", "name": name})),
+        None => None,
+    };
+    if let Some(choice) = tool_choice
+        && out.contains_key("tools")
+    {
+        out.insert("tool_choice".to_string(), choice);
+    }
+    Ok(Value::Object(out))
+}
+
+/// Convert a Messages response body into a `chat.completion` body.
+pub fn messages_response_to_chat(response: &Value) -> Value {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in response
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        match block.get("type").and_then(Value::as_str) {
+            Some("text") => text.push_str(block.get("text").and_then(Value::as_str).unwrap_or("")),
+            Some("tool_use") => tool_calls.push(json!({
+                "id": block.get("id").cloned().unwrap_or_default(),
+                "type": "function",
+                "function": {
+                    "name": block.get("name").cloned().unwrap_or_default(),
+                    "arguments": block.get("input").cloned().unwrap_or_else(|| json!({})).to_string(),
+                },
+            })),
+            _ => {}
+        }
+    }
+
+    let mut message = json!({
+        "role": "assistant",
+        "content": if text.is_empty() && !tool_calls.is_empty() { Value::Null } else { json!(text) },
+    });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    let usage = response.get("usage").cloned().unwrap_or_default();
+    let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+    let prompt_tokens = count("input_tokens")
+        + count("cache_creation_input_tokens")
+        + count("cache_read_input_tokens");
+    let completion_tokens = count("output_tokens");
+
+    json!({
+        "id": format!("chatcmpl-{}", response.get("id").and_then(Value::as_str).unwrap_or("muninn")),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": response.get("model").cloned().unwrap_or_default(),
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason(response.get("stop_reason").and_then(Value::as_str)),
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+        },
+    })
+}
+
+/// Render a `chat.completion` body as an SSE stream of
+/// `chat.completion.chunk` events ending in `[DONE]`.
+pub fn chat_completion_to_sse(completion: &Value) -> String {
+    let choice = &completion["choices"][0];
+    let mut delta = choice["message"].clone();
+    if let Some(calls) = delta.get_mut("tool_calls").and_then(Value::as_array_mut) {
+        for (index, call) in calls.iter_mut().enumerate() {
+            call["index"] = json!(index);
+        }
+    }
+    let chunk = |delta: Value, finish_reason: Value, usage: Option<&Value>| {
+        let mut chunk = json!({
+            "id": completion["id"],
+            "object": "chat.completion.chunk",
+            "created": completion["created"],
+            "model": completion["model"],
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = usage.clone();
+        }
+        format!("data: {}\n\n", chunk)
+    };
+    let mut out = chunk(delta, Value::Null, None);
+    out.push_str(&chunk(
+        json!({}),
+        choice["finish_reason"].clone(),
+        Some(&completion["usage"]),
+    ));
+    out.push_str("data: [DONE]\n\n");
+    out
+}
+
+/// Convert an Anthropic-style error body into OpenAI's error shape.
+pub fn error_to_chat(error: &Value) -> Value {
+    let detail = error.get("error").unwrap_or(error);
+    json!({
+        "error": {
+            "message": detail.get("message").and_then(Value::as_str).unwrap_or("upstream error"),
+            "type": detail.get("type").and_then(Value::as_str).unwrap_or("api_error"),
+        }
+    })
+}
+
+/// Keep Claude model names (dropping a litellm-style `provider/` prefix);
+/// anything else becomes `default_model`.
+fn translate_model(model: &str, default_model: &str) -> String {
+    let bare = model.rsplit('/').next().unwrap_or(model);
+    if bare.starts_with("claude") {
+        bare.to_string()
+    } else {
+        default_model.to_string()
+    }
+}
+
+fn finish_reason(stop_reason: Option<&str>) -> &'static str {
+    match stop_reason {
+        Some("max_tokens") => "length",
+        Some("tool_use") => "tool_calls",
+        _ => "stop",
+    }
+}
+
+fn is_tool_result_message(message: &Value) -> bool {
+    message["role"] == "user"
+        && message["content"].as_array().is_some_and(|blocks| {
+            blocks
+                .iter()
+                .all(|b| b.get("type").and_then(Value::as_str) == Some("tool_result"))
+        })
+}
+
+/// Plain text of a Chat Completions `content` (string or parts array).
+fn content_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Messages content blocks for a Chat Completions `content`.
+fn content_blocks(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(text)) if !text.is_empty() => {
+            vec![json!({"type": "text", "text": text})]
+        }
+        Some(Value::Array(parts)) => parts.iter().filter_map(content_part).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn content_part(part: &Value) -> Option<Value> {
+    match part.get("type").and_then(Value::as_str)? {
+        "text" => Some(json!({"type": "text", "text": part.get("text")?})),
+        "image_url" => {
+            let url = part.pointer("/image_url/url").and_then(Value::as_str)?;
+            let source = match url
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(";base64,"))
+            {
+                Some((media_type, data)) => {
+                    json!({"type": "base64", "media_type": media_type, "data": data})
+                }
+                None => json!({"type": "url", "url": url}),
+            };
+            Some(json!({"type": "image", "source": source}))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_to_messages() {
+        let request = json!({
+            "model": "openai/gpt-4o",
+            "stream": true,
+            "stop": "END",
+            "temperature": 0.2,
+            "messages": [
+                {"role": "system", "content": "Be terse."},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "read", "arguments": "{\"path\":\"a.rs\"}"}},
+                    {"id": "call_2", "type": "function",
+                     "function": {"name": "read", "arguments": "{\"path\":\"b.rs\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "fn a()"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "fn b()"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "read", "description": "Read a file",
+                "parameters": {"type": "object", "properties": {"path": {"type": "string"}}}
+            }}],
+            "tool_choice": {"type": "function", "function": {"name": "read"}}
+        });
+        let out = chat_request_to_messages(&request, "claude-default").unwrap();
+
+        assert_eq!(out["model"], "claude-default");
+        assert_eq!(out["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(out["system"], "Be terse.");
+        assert_eq!(out["stop_sequences"], json!(["END"]));
+        assert_eq!(out["temperature"], 0.2);
+        assert!(out.get("stream").is_none());
+
+        let messages = out["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][1]["source"]["media_type"],
+            "image/png"
+        );
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][1]["input"]["path"], "b.rs");
+        let results = messages[2]["content"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1]["tool_use_id"], "call_2");
+
+        assert_eq!(out["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(out["tool_choice"], json!({"type": "tool", "name": "read"}));
+
+        // Claude model names survive, minus the provider prefix
+        let request = json!({"model": "anthropic/claude-haiku", "max_tokens": 10, "messages": []});
+        let out = chat_request_to_messages(&request, "claude-default").unwrap();
+        assert_eq!(out["model"], "claude-haiku");
+        assert_eq!(out["max_tokens"], 10);
+    }
+
+    #[test]
+    fn test_messages_response_to_chat_and_sse() {
+        let response = json!({
+            "id": "msg_1",
+            "model": "claude-x",
+            "content": [
+                {"type": "text", "text": "Reading."},
+                {"type": "tool_use", "id": "toolu_1", "name": "read", "input": {"path": "a.rs"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "cache_read_input_tokens": 5, "output_tokens": 3}
+        });
+        let chat = messages_response_to_chat(&response);
+        assert_eq!(chat["id"], "chatcmpl-msg_1");
+        let choice = &chat["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Reading.");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"path\":\"a.rs\"}"
+        );
+        assert_eq!(chat["usage"]["prompt_tokens"], 15);
+        assert_eq!(chat["usage"]["total_tokens"], 18);
+
+        let sse = chat_completion_to_sse(&chat);
+        let events: Vec<&str> = sse
+            .split("\n\n")
+            .filter_map(|e| e.strip_prefix("data: "))
+            .collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2], "[DONE]");
+        let first: Value = serde_json::from_str(events[0]).unwrap();
+        assert_eq!(first["choices"][0]["delta"]["tool_calls"][0]["index"], 0);
+        let last: Value = serde_json::from_str(events[1]).unwrap();
+        assert_eq!(last["choices"][0]["finish_reason"], "tool_calls");
+    }
+
+    #[test]
+    fn test_error_to_chat() {
+        let error =
+            json!({"type": "error", "error": {"type": "rate_limit_error", "message": "slow down"}});
+        assert_eq!(
+            error_to_chat(&error),
+            json!({"error": {"message": "slow down", "type": "rate_limit_error"}})
+        );
+    }
+}
@@ -10,11 +10,23 @@
 //! - Adds required anthropic-beta headers
 //! - Uses Bearer token authentication
 
+use bytes::Bytes;
+use futures::StreamExt;
+use futures::stream::BoxStream;
 use reqwest::{Client, header};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
+use crate::backend::with_trace_context;
 use crate::error::{Result, RlmError};
+use crate::profile::ProviderProfile;
+use crate::recording::{
+    PendingExchange, REDACTED, RecordedExchange, RecordingStream, SharedRecorder,
+};
 use crate::token_manager::SharedTokenManager;
 use crate::types::{CompletionRequest, CompletionResponse};
 
@@ -29,6 +41,18 @@ pub enum ApiProvider {
     Custom,
 }
 
+impl ApiProvider {
+    /// The request shaping profile for this provider.
+    ///
+    /// Custom upstreams are assumed to speak the Anthropic API.
+    pub fn profile(&self) -> ProviderProfile {
+        match self {
+            Self::Anthropic | Self::Custom => ProviderProfile::ANTHROPIC,
+            Self::OpenAI => ProviderProfile::OPENAI,
+        }
+    }
+}
+
 /// Default Anthropic API base URL.
 pub const ANTHROPIC_API_URL: &str = "https://api.anthropic.com";
 /// Default OpenAI API base URL.
@@ -72,6 +96,126 @@ pub struct PassthroughConfig {
     pub auth_mode: AuthMode,
     /// Whether to inject the required Claude Code system prompt (for OAuth/MAX).
     pub inject_system_prompt: bool,
+    /// PEM files with extra root certificates to trust, for gateways
+    /// behind a private CA.
+    pub ca_certs: Vec<PathBuf>,
+    /// Outbound proxy URL. When unset, `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY`
+    /// from the environment apply.
+    pub proxy: Option<String>,
+    /// Base URLs a request may select with [`UPSTREAM_URL_HEADER`]. Empty
+    /// means per-request overrides are refused, since credentials (OAuth
+    /// tokens included) go wherever the request is sent.
+    pub allowed_upstreams: Vec<String>,
+    /// Connection pool and keep-alive settings.
+    pub pool: ConnectionPoolConfig,
+    /// Retries for overloaded or briefly unavailable upstreams.
+    pub retry: RetryPolicy,
+}
+
+/// Retry policy for transient upstream failures: 429, 529 (Anthropic
+/// "overloaded"), other 5xx gateway errors, and failed connections.
+///
+/// Delays grow exponentially with random jitter so agents sharing a
+/// brownout don't retry in lockstep. A `Retry-After` from the upstream is
+/// honoured as-is, unless it exceeds `max_retry_after`, in which case the
+/// error is returned straight away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying).
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after.
+    pub initial_backoff: Duration,
+    /// Upper bound on the computed backoff.
+    pub max_backoff: Duration,
+    /// Longest `Retry-After` worth waiting for.
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            max_retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Whether an upstream status is worth retrying.
+    pub fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+        matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+    }
+
+    /// Delay before retry number `retry` (0-based), or `None` when the
+    /// upstream asked for a longer wait than the policy allows.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if let Some(wait) = retry_after {
+            return (wait <= self.max_retry_after).then_some(wait);
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        // Jitter into [backoff/2, backoff]
+        Some(backoff.mul_f64(0.5 + rand::random::<f64>() / 2.0))
+    }
+}
+
+/// The `Retry-After` delay in a response, in its delta-seconds form.
+fn retry_after(headers: &header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?;
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// Connection pool and keep-alive settings for the upstream client.
+///
+/// Agent sessions send a request every few seconds for hours; keeping
+/// connections warm saves a TCP and TLS handshake on each one. There is
+/// no overall request timeout because streamed responses can run for
+/// minutes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionPoolConfig {
+    /// Idle connections kept per host.
+    pub max_idle_per_host: usize,
+    /// How long an idle connection stays pooled (`None` keeps it forever).
+    pub idle_timeout: Option<Duration>,
+    /// TCP keep-alive probe interval.
+    pub tcp_keepalive: Option<Duration>,
+    /// HTTP/2 PING interval, which stops gateways and NATs from dropping
+    /// idle connections.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Time allowed to establish a connection, TLS included.
+    pub connect_timeout: Option<Duration>,
+    /// Offer HTTP/2 during TLS negotiation; HTTP/1.1 only when false.
+    pub http2: bool,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 16,
+            idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            http2_keep_alive_interval: Some(Duration::from_secs(30)),
+            connect_timeout: Some(Duration::from_secs(10)),
+            http2: true,
+        }
+    }
 }
 
 impl PassthroughConfig {
@@ -91,6 +235,11 @@ impl PassthroughConfig {
             extra_headers,
             auth_mode: AuthMode::ApiKey,
             inject_system_prompt: false,
+            ca_certs: Vec::new(),
+            proxy: None,
+            allowed_upstreams: Vec::new(),
+            pool: ConnectionPoolConfig::default(),
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -111,6 +260,11 @@ impl PassthroughConfig {
             extra_headers,
             auth_mode: AuthMode::OAuthWithFallback,
             inject_system_prompt: true,
+            ca_certs: Vec::new(),
+            proxy: None,
+            allowed_upstreams: Vec::new(),
+            pool: ConnectionPoolConfig::default(),
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -124,6 +278,11 @@ impl PassthroughConfig {
             extra_headers: HashMap::new(),
             auth_mode: AuthMode::ApiKey,
             inject_system_prompt: false,
+            ca_certs: Vec::new(),
+            proxy: None,
+            allowed_upstreams: Vec::new(),
+            pool: ConnectionPoolConfig::default(),
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -137,6 +296,11 @@ impl PassthroughConfig {
             extra_headers: HashMap::new(),
             auth_mode: AuthMode::ApiKey,
             inject_system_prompt: false,
+            ca_certs: Vec::new(),
+            proxy: None,
+            allowed_upstreams: Vec::new(),
+            pool: ConnectionPoolConfig::default(),
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -175,6 +339,167 @@ impl PassthroughConfig {
         self.inject_system_prompt = inject;
         self
     }
+
+    /// Trust the root certificates in a PEM file.
+    pub fn with_ca_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_certs.push(path.into());
+        self
+    }
+
+    /// Send upstream traffic through an HTTP(S) proxy.
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Allow requests to select this base URL with [`UPSTREAM_URL_HEADER`].
+    pub fn with_allowed_upstream(mut self, url: impl Into<String>) -> Self {
+        self.allowed_upstreams.push(normalize_base_url(&url.into()));
+        self
+    }
+
+    /// Set the retry policy for transient upstream failures.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set the connection pool and keep-alive settings.
+    pub fn with_pool(mut self, pool: ConnectionPoolConfig) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Build the HTTP client for these settings.
+    pub fn build_client(&self) -> Result<Client> {
+        self.build_client_counting(Arc::default())
+    }
+
+    /// Build the HTTP client, counting new connections in `stats`.
+    fn build_client_counting(&self, stats: Arc<ConnectionStats>) -> Result<Client> {
+        let pool = &self.pool;
+        let mut builder = Client::builder()
+            .pool_max_idle_per_host(pool.max_idle_per_host)
+            .pool_idle_timeout(pool.idle_timeout)
+            .tcp_keepalive(pool.tcp_keepalive)
+            .connector_layer(CountConnections(stats));
+        if let Some(timeout) = pool.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        builder = if pool.http2 {
+            builder
+                .http2_keep_alive_interval(pool.http2_keep_alive_interval)
+                .http2_keep_alive_while_idle(true)
+        } else {
+            builder.http1_only()
+        };
+        for path in &self.ca_certs {
+            let pem = std::fs::read(path).map_err(|e| {
+                RlmError::Config(format!(
+                    "Failed to read CA certificate {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+                RlmError::Config(format!("Invalid CA certificate {}: {}", path.display(), e))
+            })?;
+            if certs.is_empty() {
+                return Err(RlmError::Config(format!(
+                    "No certificates found in {}",
+                    path.display()
+                )));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(url) = &self.proxy {
+            let proxy = reqwest::Proxy::all(url)
+                .map_err(|e| RlmError::Config(format!("Invalid proxy URL {}: {}", url, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        builder
+            .build()
+            .map_err(|e| RlmError::Config(format!("Failed to build HTTP client: {}", e)))
+    }
+}
+
+/// Request header selecting a different upstream base URL for one request.
+/// Only URLs in [`PassthroughConfig::allowed_upstreams`] are accepted.
+pub const UPSTREAM_URL_HEADER: &str = "x-muninn-upstream-url";
+
+/// Upstream request and connection counters for a [`Passthrough`].
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    requests: AtomicU64,
+    opened: AtomicU64,
+}
+
+impl ConnectionStats {
+    fn snapshot(&self) -> ConnectionStatsSnapshot {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let connections_opened = self.opened.load(Ordering::Relaxed);
+        ConnectionStatsSnapshot {
+            requests,
+            connections_opened,
+            connections_reused: requests.saturating_sub(connections_opened),
+        }
+    }
+}
+
+/// Point-in-time copy of [`ConnectionStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ConnectionStatsSnapshot {
+    /// Requests sent upstream.
+    pub requests: u64,
+    /// New connections (TCP + TLS handshakes), including failed attempts.
+    pub connections_opened: u64,
+    /// Requests served over a pooled or multiplexed connection.
+    pub connections_reused: u64,
+}
+
+/// Connector layer counting every new connection the client opens.
+#[derive(Clone)]
+struct CountConnections(Arc<ConnectionStats>);
+
+impl<S> tower::Layer<S> for CountConnections {
+    type Service = CountedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountedConnector {
+            inner,
+            stats: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CountedConnector<S> {
+    inner: S,
+    stats: Arc<ConnectionStats>,
+}
+
+impl<S: tower::Service<R>, R> tower::Service<R> for CountedConnector<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.stats.opened.fetch_add(1, Ordering::Relaxed);
+        self.inner.call(request)
+    }
+}
+
+fn normalize_base_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
 }
 
 impl Default for PassthroughConfig {
@@ -191,6 +516,10 @@ pub struct Passthrough {
     config: PassthroughConfig,
     /// Token manager for OAuth authentication.
     token_manager: Option<SharedTokenManager>,
+    /// Recorder for upstream request/response pairs (`--record`).
+    recorder: Option<SharedRecorder>,
+    /// Connection reuse counters, shared by clones.
+    connections: Arc<ConnectionStats>,
 }
 
 impl Clone for Passthrough {
@@ -199,10 +528,34 @@ impl Clone for Passthrough {
             client: self.client.clone(),
             config: self.config.clone(),
             token_manager: self.token_manager.clone(),
+            recorder: self.recorder.clone(),
+            connections: self.connections.clone(),
         }
     }
 }
 
+/// Body stream type returned by [`Passthrough::forward_raw_stream`].
+pub type UpstreamBodyStream = BoxStream<'static, std::result::Result<Bytes, std::io::Error>>;
+
+/// A successful streaming response from upstream.
+pub struct UpstreamStream {
+    /// Upstream content type (normally `text/event-stream`).
+    pub content_type: String,
+    /// Response body, chunk by chunk.
+    pub body: UpstreamBodyStream,
+    /// Retries it took to get the response.
+    pub retries: u32,
+}
+
+impl std::fmt::Debug for UpstreamStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpstreamStream")
+            .field("content_type", &self.content_type)
+            .field("retries", &self.retries)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Passthrough {
     /// Create a new passthrough client with default config (OAuth mode).
     pub fn new() -> Self {
@@ -220,11 +573,40 @@ impl Passthrough {
     }
 
     /// Create a new passthrough client with custom config.
+    ///
+    /// If the client can't be built (a bad CA file or proxy URL), this logs
+    /// the error and falls back to default client settings; use
+    /// [`try_with_config`](Self::try_with_config) to fail instead.
     pub fn with_config(config: PassthroughConfig) -> Self {
+        let connections = Arc::new(ConnectionStats::default());
+        let client = config
+            .build_client_counting(connections.clone())
+            .unwrap_or_else(|e| {
+                tracing::error!(error = %e, "Using default HTTP client for passthrough");
+                Client::new()
+            });
+        Self::with_client(client, config, connections)
+    }
+
+    /// Create a new passthrough client, failing if the configured CA
+    /// certificates or proxy can't be used.
+    pub fn try_with_config(config: PassthroughConfig) -> Result<Self> {
+        let connections = Arc::new(ConnectionStats::default());
+        let client = config.build_client_counting(connections.clone())?;
+        Ok(Self::with_client(client, config, connections))
+    }
+
+    fn with_client(
+        client: Client,
+        config: PassthroughConfig,
+        connections: Arc<ConnectionStats>,
+    ) -> Self {
         Self {
-            client: Client::new(),
+            client,
             config,
             token_manager: None,
+            recorder: None,
+            connections,
         }
     }
 
@@ -234,6 +616,12 @@ impl Passthrough {
         self
     }
 
+    /// Record every upstream request/response pair.
+    pub fn with_recorder(mut self, recorder: SharedRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
     /// Create with a custom base URL (convenience method).
     pub fn with_base_url(base_url: impl Into<String>) -> Self {
         Self::with_config(PassthroughConfig::anthropic().with_base_url(base_url))
@@ -249,6 +637,85 @@ impl Passthrough {
         self.token_manager.as_ref()
     }
 
+    /// Upstream requests sent and connections opened so far.
+    pub fn connection_stats(&self) -> ConnectionStatsSnapshot {
+        self.connections.snapshot()
+    }
+
+    /// Send a request, retrying transient failures per the retry policy.
+    ///
+    /// Returns the final response (which may still be an error status once
+    /// retries run out) and how many retries it took. The count is also
+    /// added to the current trace's metadata as `upstream_retries`.
+    async fn send_with_retry(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> std::result::Result<(reqwest::Response, u32), reqwest::Error> {
+        let policy = &self.config.retry;
+        let mut retries = 0;
+        loop {
+            // JSON bodies are buffered, so the builder always clones
+            let Some(attempt) = req.try_clone() else {
+                return req.send().await.map(|r| (r, retries));
+            };
+            let result = attempt.send().await;
+            if retries < policy.max_retries {
+                let delay = match &result {
+                    Ok(response) if RetryPolicy::is_retryable_status(response.status()) => {
+                        policy.delay(retries, retry_after(response.headers()))
+                    }
+                    Err(e) if e.is_connect() || e.is_timeout() => policy.delay(retries, None),
+                    _ => None,
+                };
+                if let Some(delay) = delay {
+                    tracing::warn!(
+                        status = result.as_ref().ok().map(|r| r.status().as_u16()),
+                        error = result.as_ref().err().map(|e| e.to_string()),
+                        retry = retries + 1,
+                        max_retries = policy.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        "Upstream request failed, retrying"
+                    );
+                    drop(result);
+                    self.connections.requests.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                    continue;
+                }
+            }
+            if retries > 0 {
+                muninn_tracing::add_metadata("upstream_retries", retries);
+            }
+            return result.map(|r| (r, retries));
+        }
+    }
+
+    /// Start a JSON POST to `url`, counting it for connection reuse stats.
+    fn post_json(&self, url: &str) -> reqwest::RequestBuilder {
+        self.connections.requests.fetch_add(1, Ordering::Relaxed);
+        self.client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+    }
+
+    /// This client pointed at another base URL, for a request that asked
+    /// for it with [`UPSTREAM_URL_HEADER`]. The URL must be the configured
+    /// one or listed in [`PassthroughConfig::allowed_upstreams`].
+    pub fn for_upstream(&self, base_url: &str) -> Result<Self> {
+        let base_url = normalize_base_url(base_url);
+        if base_url != normalize_base_url(&self.config.base_url)
+            && !self.config.allowed_upstreams.contains(&base_url)
+        {
+            return Err(RlmError::InvalidRequest(format!(
+                "Upstream {} is not in the allowed upstreams",
+                base_url
+            )));
+        }
+        let mut passthrough = self.clone();
+        passthrough.config.base_url = base_url;
+        Ok(passthrough)
+    }
+
     /// Forward a completion request to the upstream API.
     ///
     /// # Arguments
@@ -265,10 +732,7 @@ impl Passthrough {
         let forward_request = self.prepare_request(request);
 
         // Build the request
-        let mut req = self
-            .client
-            .post(&url)
-            .header(header::CONTENT_TYPE, "application/json");
+        let mut req = self.post_json(&url);
 
         // Get auth token based on mode
         let auth_value = self.get_auth_value(api_key).await?;
@@ -278,6 +742,7 @@ impl Passthrough {
         for (key, value) in &self.config.extra_headers {
             req = req.header(key, value);
         }
+        req = with_trace_context(req);
 
         let response = req
             .json(&forward_request)
@@ -349,10 +814,7 @@ impl Passthrough {
         let forward_request = self.prepare_raw_request(request);
 
         // Build the request
-        let mut req = self
-            .client
-            .post(&url)
-            .header(header::CONTENT_TYPE, "application/json");
+        let mut req = self.post_json(&url);
 
         // Get auth token based on mode
         let auth_value = match self.get_auth_value(api_key).await {
@@ -368,11 +830,17 @@ impl Passthrough {
         for (key, value) in &self.config.extra_headers {
             req = req.header(key, value);
         }
+        req = with_trace_context(req);
 
-        let response = match req.json(&forward_request).send().await {
+        let mut pending = self.begin_recording(&url, &forward_request);
+
+        let (response, retries) = match self.send_with_retry(req.json(&forward_request)).await {
             Ok(r) => r,
             Err(e) => {
                 tracing::error!(error = %e, url = %url, "Failed to send request to upstream");
+                if let Some(p) = pending.as_mut() {
+                    p.set_error(e.to_string());
+                }
                 return Err(RlmError::Backend(format!(
                     "Failed to forward request: {}",
                     e
@@ -381,10 +849,17 @@ impl Passthrough {
         };
 
         let status = response.status();
+        if let Some(p) = pending.as_mut() {
+            p.set_response_head(status.as_u16(), response.headers());
+        }
         let body = response
             .text()
             .await
             .map_err(|e| RlmError::Backend(format!("Failed to read response: {}", e)))?;
+        if let Some(mut p) = pending {
+            p.push_body(body.as_bytes());
+            p.mark_complete();
+        }
 
         if !status.is_success() {
             tracing::error!(
@@ -403,19 +878,21 @@ impl Passthrough {
         let response_json: serde_json::Value = serde_json::from_str(&body)
             .map_err(|e| RlmError::Backend(format!("Failed to parse response: {}", e)))?;
 
-        tracing::debug!(model = %model, "Successfully forwarded request");
+        tracing::debug!(model = %model, retries, "Successfully forwarded request");
 
         Ok(response_json)
     }
 
     /// Forward a raw JSON streaming request to the upstream API.
     ///
-    /// Returns the raw reqwest::Response so the caller can stream it back.
+    /// Returns the upstream body as a byte stream so the caller can stream
+    /// it back. When recording is enabled the stream is tapped and the
+    /// exchange is written once the body ends or is dropped.
     pub async fn forward_raw_stream(
         &self,
         request: serde_json::Value,
         api_key: Option<&str>,
-    ) -> Result<reqwest::Response> {
+    ) -> Result<UpstreamStream> {
         let url = format!("{}{}", self.config.base_url, self.config.messages_path);
 
         // Extract model for logging
@@ -436,10 +913,7 @@ impl Passthrough {
         let forward_request = self.prepare_raw_request(request);
 
         // Build the request
-        let mut req = self
-            .client
-            .post(&url)
-            .header(header::CONTENT_TYPE, "application/json");
+        let mut req = self.post_json(&url);
 
         // Get auth token based on mode
         let auth_value = match self.get_auth_value(api_key).await {
@@ -455,11 +929,17 @@ impl Passthrough {
         for (key, value) in &self.config.extra_headers {
             req = req.header(key, value);
         }
+        req = with_trace_context(req);
+
+        let mut pending = self.begin_recording(&url, &forward_request);
 
-        let response = match req.json(&forward_request).send().await {
+        let (response, retries) = match self.send_with_retry(req.json(&forward_request)).await {
             Ok(r) => r,
             Err(e) => {
                 tracing::error!(error = %e, url = %url, "Failed to send request to upstream");
+                if let Some(p) = pending.as_mut() {
+                    p.set_error(e.to_string());
+                }
                 return Err(RlmError::Backend(format!(
                     "Failed to forward request: {}",
                     e
@@ -468,11 +948,18 @@ impl Passthrough {
         };
 
         let status = response.status();
+        if let Some(p) = pending.as_mut() {
+            p.set_response_head(status.as_u16(), response.headers());
+        }
         if !status.is_success() {
             let body = response
                 .text()
                 .await
                 .unwrap_or_else(|_| "Failed to read error body".to_string());
+            if let Some(mut p) = pending {
+                p.push_body(body.as_bytes());
+                p.mark_complete();
+            }
             tracing::error!(
                 status = %status,
                 body = %body,
@@ -486,9 +973,124 @@ impl Passthrough {
             )));
         }
 
-        tracing::debug!(model = %model, "Streaming request started");
+        tracing::debug!(model = %model, retries, "Streaming request started");
+
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("text/event-stream")
+            .to_string();
+        let stream = response
+            .bytes_stream()
+            .map(|r| r.map_err(std::io::Error::other));
+        let body = match pending {
+            Some(p) => RecordingStream::new(stream, p).boxed(),
+            None => stream.boxed(),
+        };
+
+        Ok(UpstreamStream {
+            content_type,
+            body,
+            retries,
+        })
+    }
+
+    /// Resend a recorded exchange upstream, verbatim.
+    ///
+    /// The recorded body and non-sensitive headers are sent unchanged to the
+    /// recorded URL; credentials are re-resolved from the configured auth mode
+    /// since recordings never contain them. The URL must be under the
+    /// configured base URL or one of the allowed upstreams, so an edited
+    /// recording can't send those credentials elsewhere. Streaming responses
+    /// are read to completion and reassembled. The returned exchange is not
+    /// redacted.
+    pub async fn replay(
+        &self,
+        recorded: &RecordedExchange,
+        api_key: Option<&str>,
+    ) -> Result<RecordedExchange> {
+        if !self.is_trusted_url(&recorded.url) {
+            return Err(RlmError::InvalidRequest(format!(
+                "Recorded URL {} is not under the configured upstream {} or an allowed upstream",
+                recorded.url, self.config.base_url
+            )));
+        }
+        let auth_value = self.get_auth_value(api_key).await?;
+
+        let mut headers = recorded.request_headers.clone();
+        headers.retain(|name, value| {
+            value != REDACTED
+                && !name.eq_ignore_ascii_case("content-length")
+                && !name.eq_ignore_ascii_case("host")
+        });
+        headers.insert(self.config.auth_header.clone(), auth_value);
+
+        self.connections.requests.fetch_add(1, Ordering::Relaxed);
+        let mut req = self.client.post(&recorded.url);
+        for (key, value) in &headers {
+            req = req.header(key, value);
+        }
+
+        let mut replayed = RecordedExchange::new(&recorded.url, headers, recorded.request.clone());
+        let started = Instant::now();
+        let response = req
+            .body(recorded.request.to_string())
+            .send()
+            .await
+            .map_err(|e| RlmError::Backend(format!("Failed to replay request: {}", e)))?;
+        replayed.set_response_head(response.status().as_u16(), response.headers());
+        let body = response
+            .text()
+            .await
+            .map_err(|e| RlmError::Backend(format!("Failed to read response: {}", e)))?;
+        replayed.set_response_body(&body);
+        replayed.complete = true;
+        replayed.duration_ms = started.elapsed().as_millis() as u64;
+        Ok(replayed)
+    }
+
+    /// Whether `url` points under the configured base URL or one of the
+    /// allowed upstreams: same scheme, host and port, within its path.
+    fn is_trusted_url(&self, url: &str) -> bool {
+        let Ok(url) = reqwest::Url::parse(url) else {
+            return false;
+        };
+        if !url.username().is_empty() || url.password().is_some() {
+            return false;
+        }
+        std::iter::once(&self.config.base_url)
+            .chain(&self.config.allowed_upstreams)
+            .filter_map(|base| reqwest::Url::parse(&normalize_base_url(base)).ok())
+            .any(|base| {
+                let prefix = base.path().trim_end_matches('/');
+                base.scheme() == url.scheme()
+                    && base.host_str() == url.host_str()
+                    && base.port_or_known_default() == url.port_or_known_default()
+                    && url
+                        .path()
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+    }
 
-        Ok(response)
+    /// Start recording an upstream exchange, if recording is enabled.
+    fn begin_recording(&self, url: &str, request: &serde_json::Value) -> Option<PendingExchange> {
+        let recorder = self.recorder.as_ref()?;
+        let mut headers = BTreeMap::new();
+        headers.insert(
+            header::CONTENT_TYPE.as_str().to_string(),
+            "application/json".to_string(),
+        );
+        // Auth value is redacted on write; only record that it was present.
+        headers.insert(self.config.auth_header.clone(), REDACTED.to_string());
+        for (key, value) in &self.config.extra_headers {
+            headers.insert(key.clone(), value.clone());
+        }
+        Some(PendingExchange::new(
+            recorder.clone(),
+            RecordedExchange::new(url, headers, request.clone()),
+        ))
     }
 
     /// Get the authentication value based on the configured mode.
@@ -559,12 +1161,11 @@ impl Passthrough {
 
     /// Prepare a raw JSON request for forwarding.
     ///
-    /// This strips unknown fields and optionally injects the required system prompt.
+    /// This shapes the request for the provider (stripping unknown fields
+    /// and unsupported features) and optionally injects the required system
+    /// prompt.
     fn prepare_raw_request(&self, request: serde_json::Value) -> serde_json::Value {
-        // Strip unknown top-level fields
-        let sanitized = strip_unknown_fields_raw(&request);
-
-        let mut result = sanitized;
+        let mut result = self.config.provider.profile().shape_raw(&request);
 
         // Inject required system prompt for OAuth/MAX if enabled
         if self.config.inject_system_prompt {
@@ -642,41 +1243,6 @@ fn inject_claude_code_system_prompt(system: Option<Vec<SystemMessage>>) -> Vec<S
     }
 }
 
-/// Valid top-level fields for Anthropic API requests.
-/// Fields like 'context_management' from the Agent SDK are not supported.
-const VALID_REQUEST_FIELDS: &[&str] = &[
-    "model",
-    "max_tokens",
-    "system",
-    "messages",
-    "tools",
-    "tool_choice",
-    "stream",
-    "temperature",
-    "top_p",
-    "top_k",
-    "stop_sequences",
-    "metadata",
-    "thinking",
-];
-
-/// Strip unknown fields from a raw JSON request.
-fn strip_unknown_fields_raw(request: &serde_json::Value) -> serde_json::Value {
-    match request {
-        serde_json::Value::Object(map) => {
-            let mut sanitized = serde_json::Map::new();
-            for (key, value) in map {
-                if VALID_REQUEST_FIELDS.contains(&key.as_str()) {
-                    sanitized.insert(key.clone(), value.clone());
-                }
-            }
-            serde_json::Value::Object(sanitized)
-        }
-        // If not an object, return as-is (shouldn't happen for valid requests)
-        _ => request.clone(),
-    }
-}
-
 /// Inject the required system prompt into a raw JSON request.
 fn inject_system_prompt_raw(request: &mut serde_json::Value) {
     let required_prompt = serde_json::json!({
@@ -689,17 +1255,11 @@ fn inject_system_prompt_raw(request: &mut serde_json::Value) {
             .entry("system")
             .or_insert(serde_json::Value::Array(vec![]));
 
-        // Normalize system to array format
-        let system_array = match system {
-            serde_json::Value::String(s) => {
-                // Convert string to array format
-                let text_msg = serde_json::json!({
-                    "type": "text",
-                    "text": s.clone()
-                });
-                vec![text_msg]
-            }
-            serde_json::Value::Array(arr) => arr.clone(),
+        // Normalize system to an array of blocks
+        let system_array = match &*system {
+            serde_json::Value::String(s) => vec![text_block(s)],
+            serde_json::Value::Array(arr) => arr.iter().filter_map(system_block).collect(),
+            block @ serde_json::Value::Object(_) => vec![block.clone()],
             _ => vec![],
         };
 
@@ -721,6 +1281,24 @@ fn inject_system_prompt_raw(request: &mut serde_json::Value) {
     }
 }
 
+/// A `{"type": "text"}` system block.
+fn text_block(text: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "text",
+        "text": text
+    })
+}
+
+/// Coerce one `system` array item into a block: strings become text blocks,
+/// and anything else that isn't an object is dropped.
+fn system_block(item: &serde_json::Value) -> Option<serde_json::Value> {
+    match item {
+        serde_json::Value::String(s) => Some(text_block(s)),
+        serde_json::Value::Object(_) => Some(item.clone()),
+        _ => None,
+    }
+}
+
 /// System message structure for Anthropic API.
 #[derive(Debug, Clone, Serialize)]
 pub struct SystemMessage {
@@ -852,6 +1430,147 @@ mod tests {
         assert_eq!(config.auth_mode, AuthMode::OAuth);
     }
 
+    #[test]
+    fn test_for_upstream_requires_allowlist() {
+        let pt = Passthrough::with_config(
+            PassthroughConfig::anthropic().with_allowed_upstream("https://gateway.example/"),
+        );
+        let routed = pt.for_upstream("https://gateway.example").unwrap();
+        assert_eq!(routed.config.base_url, "https://gateway.example");
+        assert!(pt.for_upstream(&format!("{}/", ANTHROPIC_API_URL)).is_ok());
+        assert!(matches!(
+            pt.for_upstream("https://evil.example"),
+            Err(RlmError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_build_client_rejects_bad_settings() {
+        assert!(PassthroughConfig::anthropic().build_client().is_ok());
+        assert!(
+            PassthroughConfig::anthropic()
+                .with_proxy("http://proxy.example:3128")
+                .build_client()
+                .is_ok()
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let not_pem = temp.path().join("ca.pem");
+        std::fs::write(&not_pem, "not a certificate").unwrap();
+        for config in [
+            PassthroughConfig::anthropic().with_ca_cert(temp.path().join("missing.pem")),
+            PassthroughConfig::anthropic().with_ca_cert(&not_pem),
+            PassthroughConfig::anthropic().with_proxy("::not a url::"),
+        ] {
+            assert!(matches!(config.build_client(), Err(RlmError::Config(_))));
+            assert!(Passthrough::try_with_config(config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::default();
+        for retry in 0..6 {
+            let delay = policy.delay(retry, None).unwrap();
+            let full = (policy.initial_backoff * 2u32.pow(retry)).min(policy.max_backoff);
+            assert!(delay >= full / 2 && delay <= full, "{retry}: {delay:?}");
+        }
+        let wait = Duration::from_secs(5);
+        assert_eq!(policy.delay(0, Some(wait)), Some(wait));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(120))), None);
+
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, "2".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert(
+            header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    /// Upstream answering with each status in turn (then 200), returning
+    /// its base URL and a request counter.
+    async fn spawn_flaky_upstream(
+        statuses: Vec<(u16, Option<&'static str>)>,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use axum::response::IntoResponse;
+        use std::sync::atomic::AtomicUsize;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/v1/messages",
+            axum::routing::post(move || {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let reply = statuses.get(n).copied();
+                async move {
+                    match reply {
+                        Some((status, retry_after)) => {
+                            let mut response = (
+                                axum::http::StatusCode::from_u16(status).unwrap(),
+                                r#"{"type":"error","error":{"type":"overloaded_error"}}"#,
+                            )
+                                .into_response();
+                            if let Some(wait) = retry_after {
+                                response
+                                    .headers_mut()
+                                    .insert("retry-after", wait.parse().unwrap());
+                            }
+                            response
+                        }
+                        None => axum::Json(serde_json::json!({"id": "msg_ok"})).into_response(),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        (url, hits)
+    }
+
+    #[tokio::test]
+    async fn test_forward_raw_retries_overload() {
+        let fast = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let request = serde_json::json!({"model": "m", "max_tokens": 10, "messages": []});
+
+        let (url, hits) = spawn_flaky_upstream(vec![(529, None), (429, Some("0"))]).await;
+        let pt = Passthrough::with_config(PassthroughConfig::custom(url).with_retry(fast.clone()));
+        let (response, trace) =
+            muninn_tracing::with_tracing(pt.forward_raw(request.clone(), Some("key"))).await;
+        assert_eq!(response.unwrap()["id"], "msg_ok");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        assert_eq!(trace.metadata["upstream_retries"], 2);
+
+        // Retries stop at the cap
+        let (url, hits) = spawn_flaky_upstream(vec![(503, None); 10]).await;
+        let pt = Passthrough::with_config(PassthroughConfig::custom(url).with_retry(fast.clone()));
+        let stream =
+            serde_json::json!({"model": "m", "max_tokens": 10, "messages": [], "stream": true});
+        let err = pt
+            .forward_raw_stream(stream, Some("key"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("503"), "{err}");
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+
+        // A Retry-After beyond the policy's limit is not waited out
+        let (url, hits) = spawn_flaky_upstream(vec![(429, Some("3600"))]).await;
+        let pt = Passthrough::with_config(PassthroughConfig::custom(url).with_retry(fast));
+        assert!(pt.forward_raw(request.clone(), Some("key")).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Client errors are never retried
+        let (url, hits) = spawn_flaky_upstream(vec![(400, None)]).await;
+        let pt = Passthrough::with_config(PassthroughConfig::custom(url));
+        assert!(pt.forward_raw(request, Some("key")).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_openai_config() {
         let config = PassthroughConfig::openai();
@@ -885,6 +1604,93 @@ mod tests {
         assert_eq!(result[1].text, "Custom prompt");
     }
 
+    #[tokio::test]
+    async fn test_forward_raw_records_and_replays() {
+        use crate::recording::{UpstreamRecorder, read_recordings};
+        use crate::testing::{MockLLMServer, fixtures};
+        use std::sync::Arc;
+
+        let server = MockLLMServer::start().await;
+        server.queue_response(fixtures::text_response("recorded"));
+        server.queue_response(fixtures::text_response("replayed"));
+
+        let temp = tempfile::tempdir().unwrap();
+        let recorder = Arc::new(UpstreamRecorder::in_dir(temp.path()));
+        let pt = Passthrough::with_base_url(server.url()).with_recorder(recorder.clone());
+
+        let request = serde_json::json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Hi"}],
+            "context_management": {"dropped": true}
+        });
+        pt.forward_raw(request, Some("sk-ant-api03-secretsecret"))
+            .await
+            .unwrap();
+
+        let recorded = read_recordings(recorder.path()).unwrap();
+        assert_eq!(recorded.len(), 1);
+        let ex = &recorded[0];
+        assert_eq!(ex.status, Some(200));
+        assert!(ex.complete);
+        assert_eq!(ex.request_headers["x-api-key"], crate::recording::REDACTED);
+        // The recording holds the sanitized body actually sent upstream
+        assert!(ex.request.get("context_management").is_none());
+        assert_eq!(
+            ex.response.as_ref().unwrap()["content"][0]["text"],
+            "recorded"
+        );
+
+        let replayed = pt.replay(ex, Some("test-key")).await.unwrap();
+        assert_eq!(replayed.status, Some(200));
+        assert_eq!(
+            replayed.response.as_ref().unwrap()["content"][0]["text"],
+            "replayed"
+        );
+        server.assert_request_count(2);
+        // The replay reuses the pooled connection
+        assert_eq!(
+            pt.connection_stats(),
+            ConnectionStatsSnapshot {
+                requests: 2,
+                connections_opened: 1,
+                connections_reused: 1,
+            }
+        );
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_refuses_foreign_hosts() {
+        use crate::testing::MockLLMServer;
+
+        let server = MockLLMServer::start().await;
+        let pt = Passthrough::with_base_url(format!("{}/anthropic", server.url()));
+        let request = serde_json::json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+
+        let foreign = [
+            "https://attacker.example/v1/messages".to_string(),
+            format!("{}/v1/messages", server.url()),
+            format!("{}/anthropic-evil/v1/messages", server.url()),
+            format!("{}/anthropic/v1/messages", server.url()).replace("://", "://user@"),
+        ];
+        for url in foreign {
+            let recorded = RecordedExchange::new(&url, Default::default(), request.clone());
+            let err = pt.replay(&recorded, Some("test-key")).await.unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("not under the configured upstream"),
+                "{url}"
+            );
+        }
+        server.assert_request_count(0);
+        server.shutdown().await;
+    }
+
     #[test]
     fn test_inject_system_prompt_already_present() {
         let existing = vec![
@@ -895,4 +1701,123 @@ mod tests {
         assert_eq!(result.len(), 2);
         assert_eq!(result[0].text, CLAUDE_CODE_SYSTEM_PROMPT);
     }
+
+    #[test]
+    fn test_inject_system_prompt_raw_coerces_blocks() {
+        let mut request = serde_json::json!({
+            "system": ["plain", 42, null, {"type": "text", "text": "block"}]
+        });
+        inject_system_prompt_raw(&mut request);
+        let texts: Vec<&str> = request["system"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, vec![CLAUDE_CODE_SYSTEM_PROMPT, "plain", "block"]);
+
+        let mut request = serde_json::json!({"system": {"type": "text", "text": "solo"}});
+        inject_system_prompt_raw(&mut request);
+        assert_eq!(request["system"][1]["text"], "solo");
+    }
+
+    /// Property tests: arbitrary agent payloads must never panic the
+    /// sanitizers or yield a malformed upstream request.
+    mod props {
+        use super::*;
+        use crate::profile::MESSAGES_FIELDS;
+        use proptest::prelude::*;
+        use serde_json::{Map, Value};
+
+        /// Arbitrary JSON, a few levels deep.
+        fn json_value() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                any::<i64>().prop_map(Value::from),
+                any::<f64>().prop_map(Value::from),
+                ".{0,12}".prop_map(Value::String),
+                Just(Value::String(CLAUDE_CODE_SYSTEM_PROMPT.to_string())),
+            ];
+            leaf.prop_recursive(4, 48, 6, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                    prop::collection::btree_map("[a-z_]{1,10}", inner, 0..6)
+                        .prop_map(|m| Value::Object(m.into_iter().collect())),
+                ]
+            })
+        }
+
+        /// Request-shaped payloads: known fields (with arbitrary values)
+        /// mixed with unknown agent-SDK fields.
+        fn request_payload() -> impl Strategy<Value = Value> {
+            let key = prop_oneof![
+                prop::sample::select(MESSAGES_FIELDS).prop_map(str::to_string),
+                Just("context_management".to_string()),
+                Just("muninn".to_string()),
+                "[a-z_]{1,12}",
+            ];
+            prop::collection::vec((key, json_value()), 0..10)
+                .prop_map(|fields| Value::Object(fields.into_iter().collect::<Map<_, _>>()))
+        }
+
+        fn is_required_prompt(block: &Value) -> bool {
+            block.get("type").and_then(Value::as_str) == Some("text")
+                && block.get("text").and_then(Value::as_str) == Some(CLAUDE_CODE_SYSTEM_PROMPT)
+        }
+
+        proptest! {
+            #[test]
+            fn strip_keeps_only_known_fields(request in request_payload()) {
+                let stripped = ProviderProfile::ANTHROPIC.shape_raw(&request);
+                let map = stripped.as_object().unwrap();
+                for (key, value) in map {
+                    prop_assert!(MESSAGES_FIELDS.contains(&key.as_str()));
+                    prop_assert_eq!(Some(value), request.get(key));
+                }
+                prop_assert_eq!(ProviderProfile::ANTHROPIC.shape_raw(&stripped), stripped.clone());
+            }
+
+            #[test]
+            fn strip_passes_non_objects_through(value in json_value()) {
+                if !value.is_object() {
+                    prop_assert_eq!(ProviderProfile::ANTHROPIC.shape_raw(&value), value);
+                }
+            }
+
+            #[test]
+            fn inject_yields_block_array_with_prompt_first(request in request_payload()) {
+                let mut injected = ProviderProfile::ANTHROPIC.shape_raw(&request);
+                inject_system_prompt_raw(&mut injected);
+
+                let system = injected["system"].as_array().unwrap();
+                prop_assert!(is_required_prompt(&system[0]));
+                prop_assert!(system.iter().all(Value::is_object));
+
+                // Only `system` is touched
+                let before = ProviderProfile::ANTHROPIC.shape_raw(&request);
+                for (key, value) in injected.as_object().unwrap() {
+                    if key != "system" {
+                        prop_assert_eq!(Some(value), before.get(key));
+                    }
+                }
+
+                // Idempotent: the prompt is never stacked twice
+                let mut twice = injected.clone();
+                inject_system_prompt_raw(&mut twice);
+                prop_assert_eq!(twice, injected);
+            }
+
+            #[test]
+            fn prepared_raw_request_serializes(request in request_payload()) {
+                let passthrough = Passthrough::with_config(
+                    PassthroughConfig::anthropic_oauth(),
+                );
+                let prepared = passthrough.prepare_raw_request(request);
+                let body = serde_json::to_vec(&prepared).unwrap();
+                let reparsed: Value = serde_json::from_slice(&body).unwrap();
+                prop_assert!(reparsed.is_object());
+            }
+        }
+    }
 }
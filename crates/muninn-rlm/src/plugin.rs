@@ -0,0 +1,761 @@
+//! WASM plugins.
+//!
+//! Third parties can ship tools, routers and response transforms as
+//! `.wasm` modules (usually from `.muninn/plugins/`) without rebuilding
+//! muninn. A [`PluginHost`] loads them and [`WasmPlugin`] adapts each one
+//! to the matching extension point: [`Tool`], [`RouterBackend`] and
+//! [`ResponseTransform`].
+//!
+//! # ABI
+//!
+//! Every value crossing the boundary is UTF-8 JSON in the guest's linear
+//! memory, passed as a pointer/length pair and returned packed into an
+//! `i64` as `(ptr << 32) | len`. A plugin exports:
+//!
+//! - `memory`
+//! - `muninn_alloc(len: i32) -> i32`: room for the host to write input
+//! - `muninn_manifest() -> i64`: `{"name", "tools": [..], "transform", "router"}`,
+//!   with tools as `{"name", "description", "input_schema", "internal"}`
+//! - `muninn_tool_<name>(ptr, len) -> i64` for each tool: takes the tool
+//!   input, returns its result (`{"error": ".."}` marks a failure)
+//! - `muninn_transform(ptr, len) -> i64` if `transform`: takes and returns
+//!   a complete Messages response
+//! - `muninn_route(ptr, len) -> i64` if `router`: takes `{"message", "model"}`,
+//!   returns `{"route": "rlm" | "passthrough", "reason"}`
+//!
+//! # Host API
+//!
+//! Plugins may import these from the `muninn` module. Each takes a JSON
+//! request and returns `{"ok": ..}` or `{"error": ".."}`; capabilities not
+//! granted to the plugin always return an error.
+//!
+//! - `fs_read(ptr, len) -> i64`: `{"path"}` relative to the project root
+//!   ([`Capability::FsRead`])
+//! - `graph_query(ptr, len) -> i64`: `{"query", "limit"}` read-only Cypher
+//!   against the code graph ([`Capability::GraphQuery`])
+//! - `log(ptr, len) -> i64`: `{"message"}`, always allowed
+//!
+//! Each call runs in a fresh instance with bounded fuel and memory, so a
+//! plugin keeps no state between calls and can't hang the proxy.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use wasmtime::{
+    AsContextMut, Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::error::{Result, RlmError};
+use crate::graph_tools::SharedGraphStore;
+use crate::response_transform::{ResponseTransform, TransformContext};
+use crate::router::{RouteDecision, RouterBackend};
+use crate::tools::{Tool, ToolMetadata, ToolResult};
+use crate::types::CompletionRequest;
+
+/// Host functions a plugin may be granted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Read files under the project root.
+    FsRead,
+    /// Run read-only Cypher queries against the code graph.
+    GraphQuery,
+}
+
+/// Loads plugins and decides what each may do.
+#[derive(Clone)]
+pub struct PluginHost {
+    root: PathBuf,
+    graph: Option<SharedGraphStore>,
+    grants: HashMap<String, Vec<Capability>>,
+    fuel: u64,
+    max_memory: usize,
+}
+
+impl std::fmt::Debug for PluginHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginHost")
+            .field("root", &self.root)
+            .field("graph", &self.graph.is_some())
+            .field("grants", &self.grants)
+            .field("fuel", &self.fuel)
+            .field("max_memory", &self.max_memory)
+            .finish()
+    }
+}
+
+impl PluginHost {
+    /// Host with `fs_read` rooted at `root` and no capabilities granted.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            graph: None,
+            grants: HashMap::new(),
+            fuel: 1_000_000_000,
+            max_memory: 64 * 1024 * 1024,
+        }
+    }
+
+    /// Serve `graph_query` from this store.
+    pub fn with_graph_store(mut self, store: SharedGraphStore) -> Self {
+        self.graph = Some(store);
+        self
+    }
+
+    /// Grant capabilities to the plugin with this manifest name.
+    pub fn with_grant(mut self, plugin: impl Into<String>, capabilities: Vec<Capability>) -> Self {
+        self.grants.insert(plugin.into(), capabilities);
+        self
+    }
+
+    /// Fuel (roughly, wasm instructions) each call may burn.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Linear memory limit per call, in bytes.
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = bytes;
+        self
+    }
+
+    /// Load one plugin from a `.wasm` (or `.wat`) file.
+    pub fn load(&self, path: &Path) -> Result<WasmPlugin> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(config_error(path))?;
+        let module = Module::from_file(&engine, path).map_err(config_error(path))?;
+        let mut linker = Linker::new(&engine);
+        link_host_api(&mut linker).map_err(config_error(path))?;
+
+        let mut plugin = WasmPlugin {
+            inner: Arc::new(PluginInner {
+                manifest: PluginManifest::default(),
+                engine,
+                module,
+                linker,
+                host: self.clone(),
+                capabilities: Vec::new(),
+            }),
+        };
+        let manifest: PluginManifest =
+            serde_json::from_value(plugin.call("muninn_manifest", None)?).map_err(|e| {
+                RlmError::Config(format!("Plugin {}: bad manifest: {}", path.display(), e))
+            })?;
+        let capabilities = self.grants.get(&manifest.name).cloned().unwrap_or_default();
+        let inner = Arc::get_mut(&mut plugin.inner).expect("plugin not shared yet");
+        inner.manifest = manifest;
+        inner.capabilities = capabilities;
+        Ok(plugin)
+    }
+
+    /// Load every `.wasm` file in `dir`, in name order.
+    ///
+    /// A missing directory yields no plugins; plugins that fail to load are
+    /// logged and skipped so one bad module doesn't take the proxy down.
+    pub fn load_dir(&self, dir: &Path) -> Vec<WasmPlugin> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "wasm"))
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .filter_map(|path| match self.load(path) {
+                Ok(plugin) => {
+                    tracing::info!(
+                        plugin = plugin.name(),
+                        path = %path.display(),
+                        capabilities = ?plugin.capabilities(),
+                        "Loaded plugin"
+                    );
+                    Some(plugin)
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping plugin");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn config_error(path: &Path) -> impl Fn(wasmtime::Error) -> RlmError + '_ {
+    move |e| RlmError::Config(format!("Plugin {}: {:#}", path.display(), e))
+}
+
+/// What a plugin declares about itself.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PluginManifest {
+    name: String,
+    #[serde(default)]
+    tools: Vec<PluginToolSpec>,
+    #[serde(default)]
+    transform: bool,
+    #[serde(default)]
+    router: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PluginToolSpec {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "empty_schema")]
+    input_schema: Value,
+    #[serde(default)]
+    internal: bool,
+}
+
+fn empty_schema() -> Value {
+    json!({"type": "object"})
+}
+
+/// A loaded plugin. Cheap to clone.
+#[derive(Clone)]
+pub struct WasmPlugin {
+    inner: Arc<PluginInner>,
+}
+
+struct PluginInner {
+    manifest: PluginManifest,
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+    host: PluginHost,
+    capabilities: Vec<Capability>,
+}
+
+impl std::fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("name", &self.name())
+            .field("capabilities", &self.capabilities())
+            .finish()
+    }
+}
+
+impl WasmPlugin {
+    /// The plugin's manifest name.
+    pub fn name(&self) -> &str {
+        &self.inner.manifest.name
+    }
+
+    /// Capabilities the host granted this plugin.
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.inner.capabilities
+    }
+
+    /// The plugin's tools, ready to register.
+    pub fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        self.inner
+            .manifest
+            .tools
+            .iter()
+            .map(|spec| {
+                Arc::new(PluginTool {
+                    plugin: self.clone(),
+                    spec: spec.clone(),
+                }) as Arc<dyn Tool>
+            })
+            .collect()
+    }
+
+    /// The plugin's response transform, if it declares one.
+    pub fn transform(&self) -> Option<PluginTransform> {
+        self.inner
+            .manifest
+            .transform
+            .then(|| PluginTransform(self.clone()))
+    }
+
+    /// The plugin's router, if it declares one.
+    pub fn router(&self) -> Option<Arc<dyn RouterBackend>> {
+        self.inner
+            .manifest
+            .router
+            .then(|| Arc::new(PluginRouter(self.clone())) as Arc<dyn RouterBackend>)
+    }
+
+    /// Call an export in a fresh instance, blocking the thread.
+    fn call(&self, export: &str, input: Option<&Value>) -> Result<Value> {
+        let inner = &self.inner;
+        let state = HostState {
+            plugin: inner.manifest.name.clone(),
+            capabilities: inner.capabilities.clone(),
+            root: inner.host.root.clone(),
+            graph: inner.host.graph.clone(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(inner.host.max_memory)
+                .build(),
+        };
+        let mut store = Store::new(&inner.engine, state);
+        store.limiter(|state| &mut state.limits);
+        let failed = |e: wasmtime::Error| {
+            RlmError::Internal(format!("Plugin {} {}: {:#}", self.name(), export, e))
+        };
+        store.set_fuel(inner.host.fuel).map_err(failed)?;
+        let instance = inner
+            .linker
+            .instantiate(&mut store, &inner.module)
+            .map_err(failed)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| failed(wasmtime::Error::msg("no memory export")))?;
+
+        let packed = match input {
+            Some(input) => {
+                let alloc = instance
+                    .get_typed_func::<i32, i32>(&mut store, "muninn_alloc")
+                    .map_err(failed)?;
+                let (ptr, len) =
+                    write_guest(&mut store, &alloc, &memory, input.to_string().as_bytes())
+                        .map_err(failed)?;
+                instance
+                    .get_typed_func::<(i32, i32), i64>(&mut store, export)
+                    .and_then(|f| f.call(&mut store, (ptr, len)))
+                    .map_err(failed)?
+            }
+            None => instance
+                .get_typed_func::<(), i64>(&mut store, export)
+                .and_then(|f| f.call(&mut store, ()))
+                .map_err(failed)?,
+        };
+        let output = read_guest(&store, &memory, packed).map_err(failed)?;
+        serde_json::from_slice(&output).map_err(|e| {
+            RlmError::Serialization(format!("Plugin {} {}: {}", self.name(), export, e))
+        })
+    }
+
+    /// [`call`](Self::call) off the async runtime.
+    async fn call_blocking(&self, export: String, input: Value) -> Result<Value> {
+        let plugin = self.clone();
+        tokio::task::spawn_blocking(move || plugin.call(&export, Some(&input)))
+            .await
+            .map_err(|e| RlmError::Internal(format!("Plugin task failed: {}", e)))?
+    }
+}
+
+// ============================================================================
+// Extension Point Adapters
+// ============================================================================
+
+/// A tool exported by a plugin.
+struct PluginTool {
+    plugin: WasmPlugin,
+    spec: PluginToolSpec,
+}
+
+#[async_trait]
+impl Tool for PluginTool {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.spec.input_schema.clone()
+    }
+
+    fn is_internal(&self) -> bool {
+        self.spec.internal
+    }
+
+    async fn execute(&self, params: Value) -> Result<ToolResult> {
+        let export = format!("muninn_tool_{}", self.spec.name);
+        let output = match self.plugin.call_blocking(export, params).await {
+            Ok(output) => output,
+            Err(e) => return Ok(ToolResult::error(e.to_string(), false)),
+        };
+        let result = match output.get("error").and_then(Value::as_str) {
+            Some(message) => ToolResult::error(message, true),
+            None => ToolResult::json(output),
+        };
+        Ok(result.with_metadata(ToolMetadata::with_source(self.plugin.name()).with_tag("plugin")))
+    }
+}
+
+/// A plugin's `muninn_transform` export as a [`ResponseTransform`].
+///
+/// Only complete responses are rewritten; streams pass through untouched.
+pub struct PluginTransform(WasmPlugin);
+
+impl ResponseTransform for PluginTransform {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn transform(&self, response: &mut Value, _ctx: &TransformContext) {
+        match self.0.call("muninn_transform", Some(response)) {
+            Ok(rewritten) => *response = rewritten,
+            Err(e) => tracing::warn!(plugin = self.0.name(), error = %e, "Plugin transform failed"),
+        }
+    }
+}
+
+/// A plugin's `muninn_route` export as a [`RouterBackend`].
+struct PluginRouter(WasmPlugin);
+
+#[derive(Deserialize)]
+struct PluginRoute {
+    route: String,
+    #[serde(default)]
+    reason: String,
+}
+
+#[async_trait]
+impl RouterBackend for PluginRouter {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    async fn route(
+        &self,
+        user_message: &str,
+        request: &CompletionRequest,
+    ) -> Result<RouteDecision> {
+        let input = json!({"message": user_message, "model": request.model});
+        let output = self
+            .0
+            .call_blocking("muninn_route".to_string(), input)
+            .await?;
+        let route: PluginRoute = serde_json::from_value(output)?;
+        Ok(match route.route.to_lowercase().as_str() {
+            "rlm" | "explore" => {
+                RouteDecision::rlm(format!("Plugin {}: {}", self.0.name(), route.reason))
+            }
+            _ => RouteDecision::passthrough(),
+        })
+    }
+}
+
+// ============================================================================
+// Host API
+// ============================================================================
+
+struct HostState {
+    plugin: String,
+    capabilities: Vec<Capability>,
+    root: PathBuf,
+    graph: Option<SharedGraphStore>,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    fn require(&self, capability: Capability) -> std::result::Result<(), String> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(format!(
+                "capability {:?} not granted to {}",
+                capability, self.plugin
+            ))
+        }
+    }
+
+    fn fs_read(&self, request: Value) -> std::result::Result<Value, String> {
+        self.require(Capability::FsRead)?;
+        let path = request
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or("missing path")?;
+        let root = self.root.canonicalize().map_err(|e| e.to_string())?;
+        let full = root.join(path).canonicalize().map_err(|e| e.to_string())?;
+        if !full.starts_with(&root) {
+            return Err(format!("path '{}' is outside the project", path));
+        }
+        std::fs::read_to_string(full)
+            .map(Value::String)
+            .map_err(|e| e.to_string())
+    }
+
+    fn graph_query(&self, request: Value) -> std::result::Result<Value, String> {
+        self.require(Capability::GraphQuery)?;
+        let query = request
+            .get("query")
+            .and_then(Value::as_str)
+            .ok_or("missing query")?;
+        if !muninn_graph::is_read_only_cypher(query) {
+            return Err("graph_query is read-only".to_string());
+        }
+        let limit = request.get("limit").and_then(Value::as_u64).unwrap_or(100) as usize;
+        let store = self.graph.as_ref().ok_or("no code graph indexed")?;
+        let store = store.lock().map_err(|e| e.to_string())?;
+        let result = store.query(query).map_err(|e| e.to_string())?;
+        let rows: Vec<Value> = result
+            .iter()
+            .take(limit)
+            .map(|row| {
+                row.columns()
+                    .iter()
+                    .filter_map(|col| {
+                        row.get_value(col)
+                            .map(|v| (col.clone(), muninn_graph::value_to_json(v)))
+                    })
+                    .collect::<serde_json::Map<_, _>>()
+                    .into()
+            })
+            .collect();
+        Ok(json!({"rows": rows, "total": result.len()}))
+    }
+
+    fn log(&self, request: Value) -> std::result::Result<Value, String> {
+        let message = request.get("message").and_then(Value::as_str).unwrap_or("");
+        tracing::info!(plugin = %self.plugin, "{}", message);
+        Ok(Value::Null)
+    }
+}
+
+type HostFn = fn(&HostState, Value) -> std::result::Result<Value, String>;
+
+fn link_host_api(linker: &mut Linker<HostState>) -> wasmtime::Result<()> {
+    let functions: [(&str, HostFn); 3] = [
+        ("fs_read", HostState::fs_read),
+        ("graph_query", HostState::graph_query),
+        ("log", HostState::log),
+    ];
+    for (name, function) in functions {
+        linker.func_wrap(
+            "muninn",
+            name,
+            move |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let memory = caller_memory(&mut caller)?;
+                let request = read_guest(&caller, &memory, pack(ptr, len))?;
+                let reply = match serde_json::from_slice(&request) {
+                    Ok(request) => match function(caller.data(), request) {
+                        Ok(value) => json!({"ok": value}),
+                        Err(error) => json!({"error": error}),
+                    },
+                    Err(e) => json!({"error": format!("bad request: {}", e)}),
+                };
+                let alloc = caller
+                    .get_export("muninn_alloc")
+                    .and_then(Extern::into_func)
+                    .ok_or_else(|| wasmtime::Error::msg("no muninn_alloc export"))?
+                    .typed::<i32, i32>(&caller)?;
+                let (ptr, len) =
+                    write_guest(&mut caller, &alloc, &memory, reply.to_string().as_bytes())?;
+                Ok(pack(ptr, len))
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn caller_memory(caller: &mut Caller<'_, HostState>) -> wasmtime::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("no memory export"))
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    (((ptr as u32 as u64) << 32) | len as u32 as u64) as i64
+}
+
+fn read_guest(
+    store: impl wasmtime::AsContext,
+    memory: &Memory,
+    packed: i64,
+) -> wasmtime::Result<Vec<u8>> {
+    let ptr = (packed as u64 >> 32) as usize;
+    let len = (packed as u64 & 0xffff_ffff) as usize;
+    // The length comes from the guest: check it before allocating
+    if ptr
+        .checked_add(len)
+        .is_none_or(|end| end > memory.data_size(&store))
+    {
+        return Err(wasmtime::Error::msg(format!(
+            "guest buffer {}+{} is out of bounds",
+            ptr, len
+        )));
+    }
+    let mut buf = vec![0; len];
+    memory.read(&store, ptr, &mut buf)?;
+    Ok(buf)
+}
+
+fn write_guest(
+    mut store: impl AsContextMut,
+    alloc: &TypedFunc<i32, i32>,
+    memory: &Memory,
+    bytes: &[u8],
+) -> wasmtime::Result<(i32, i32)> {
+    let len = i32::try_from(bytes.len())?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, bytes)?;
+    Ok((ptr, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plugin with a bump allocator, a `cat` tool that forwards its input
+    /// to `fs_read`, a constant router and an identity transform.
+    const PLUGIN: &str = r#"
+(module
+  (import "muninn" "fs_read" (func $fs_read (param i32 i32) (result i64)))
+  (memory (export "memory") 2)
+  (global $heap (mut i32) (i32.const 4096))
+  (data (i32.const 0) "{\"name\":\"demo\",\"tools\":[{\"name\":\"cat\",\"description\":\"Read a file\"}],\"transform\":true,\"router\":true}")
+  (data (i32.const 1024) "{\"route\":\"rlm\",\"reason\":\"always\"}")
+  (func (export "muninn_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (local.get $ptr))
+  (func (export "muninn_manifest") (result i64)
+    (i64.const 99))
+  (func (export "muninn_tool_cat") (param i32 i32) (result i64)
+    (call $fs_read (local.get 0) (local.get 1)))
+  (func (export "muninn_route") (param i32 i32) (result i64)
+    (i64.or (i64.shl (i64.const 1024) (i64.const 32)) (i64.const 33)))
+  (func (export "muninn_transform") (param i32 i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+      (i64.extend_i32_u (local.get 1))))
+)
+"#;
+
+    /// Write [`PLUGIN`] and a `hello.txt` for it to read into a temp dir.
+    fn write_plugin() -> (tempfile::TempDir, PathBuf) {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("hello.txt"), "hello").unwrap();
+        let path = temp.path().join("demo.wat");
+        std::fs::write(&path, PLUGIN).unwrap();
+        (temp, path)
+    }
+
+    #[tokio::test]
+    async fn test_plugin_tool_respects_capabilities() {
+        let (temp, path) = write_plugin();
+        let granted = PluginHost::new(temp.path())
+            .with_grant("demo", vec![Capability::FsRead])
+            .load(&path)
+            .unwrap();
+        assert_eq!(granted.name(), "demo");
+        let tool = granted.tools().remove(0);
+        assert_eq!(tool.name(), "cat");
+        let result = tool.execute(json!({"path": "hello.txt"})).await.unwrap();
+        assert!(
+            matches!(result.content, crate::tools::ToolContent::Json(ref v) if v["ok"] == "hello")
+        );
+        let escaped = tool
+            .execute(json!({"path": "../etc/passwd"}))
+            .await
+            .unwrap();
+        assert!(matches!(
+            escaped.content,
+            crate::tools::ToolContent::Error { .. }
+        ));
+
+        let denied = PluginHost::new(temp.path()).load(&path).unwrap();
+        let result = denied.tools()[0]
+            .execute(json!({"path": "hello.txt"}))
+            .await
+            .unwrap();
+        match result.content {
+            crate::tools::ToolContent::Error { message, .. } => {
+                assert!(message.contains("FsRead"), "{}", message)
+            }
+            other => panic!("expected error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugin_router_and_transform() {
+        let (_temp, path) = write_plugin();
+        let plugin = PluginHost::new(".").load(&path).unwrap();
+        let request = CompletionRequest::new("m", vec![crate::types::Message::user("hi")], 10);
+        let decision = plugin
+            .router()
+            .unwrap()
+            .route("hi", &request)
+            .await
+            .unwrap();
+        assert!(decision.is_rlm());
+
+        let mut response = json!({"type": "message", "content": []});
+        let ctx = TransformContext {
+            trace_id: "t".to_string(),
+            source: crate::response_transform::ResponseSource::Rlm,
+            streaming: false,
+            tags: Vec::new(),
+        };
+        plugin.transform().unwrap().transform(&mut response, &ctx);
+        assert_eq!(response, json!({"type": "message", "content": []}));
+    }
+
+    #[test]
+    fn test_plugin_fuel_is_bounded() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("spin.wat");
+        std::fs::write(
+            &path,
+            r#"(module
+                 (memory (export "memory") 1)
+                 (func (export "muninn_manifest") (result i64)
+                   (loop $spin (br $spin))
+                   (i64.const 0)))"#,
+        )
+        .unwrap();
+        let err = PluginHost::new(temp.path())
+            .with_fuel(10_000)
+            .load(&path)
+            .unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{}", err);
+    }
+
+    #[test]
+    fn test_plugin_output_out_of_bounds() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("huge.wat");
+        std::fs::write(
+            &path,
+            r#"(module
+                 (memory (export "memory") 1)
+                 (func (export "muninn_manifest") (result i64)
+                   (i64.const 0xffffffff)))"#,
+        )
+        .unwrap();
+        let err = PluginHost::new(temp.path()).load(&path).unwrap_err();
+        assert!(err.to_string().contains("out of bounds"), "{}", err);
+    }
+
+    #[test]
+    fn test_graph_query_is_read_only() {
+        let graph =
+            crate::graph_tools::wrap_store(muninn_graph::GraphStore::open_in_memory().unwrap());
+        let state = HostState {
+            plugin: "demo".to_string(),
+            capabilities: vec![Capability::GraphQuery],
+            root: PathBuf::from("."),
+            graph: Some(graph.clone()),
+            limits: StoreLimitsBuilder::new().build(),
+        };
+
+        let err = state
+            .graph_query(json!({"query": "CREATE (n:Symbol {id: 'x'}) RETURN n"}))
+            .unwrap_err();
+        assert!(err.contains("read-only"), "{}", err);
+        assert_eq!(graph.lock().unwrap().stats().unwrap().node_count, 0);
+
+        let reply = state
+            .graph_query(json!({"query": "MATCH (n:Symbol) RETURN n.id"}))
+            .unwrap();
+        assert_eq!(reply["total"], 0);
+    }
+}
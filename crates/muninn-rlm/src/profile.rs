@@ -0,0 +1,336 @@
+//! Per-provider request shaping.
+//!
+//! Upstreams disagree on which request features they take: OpenAI-style
+//! APIs have no `thinking`, `top_k` or `cache_control`, and some ignore or
+//! reject `tool_choice` variants. A [`ProviderProfile`] declares what a
+//! provider supports and strips or converts the rest, both for typed
+//! [`CompletionRequest`]s (in the backends) and for raw Anthropic-format
+//! bodies (in the passthrough).
+
+use serde_json::Value;
+
+use crate::types::{
+    CompletionRequest, Content, ContentBlock, SystemPrompt, ToolChoice, ToolDefinition,
+    ToolResultContent,
+};
+
+/// Top-level fields of an Anthropic Messages request.
+///
+/// Anything else is dropped from raw bodies; fields like
+/// `context_management` from the Agent SDK are not part of the API.
+pub(crate) const MESSAGES_FIELDS: &[&str] = &[
+    "model",
+    "max_tokens",
+    "system",
+    "messages",
+    "tools",
+    "tool_choice",
+    "stream",
+    "temperature",
+    "top_p",
+    "top_k",
+    "stop_sequences",
+    "metadata",
+    "thinking",
+];
+
+/// What a provider accepts, and how unsupported features are shaped away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProviderProfile {
+    /// Profile name, for logs.
+    pub name: &'static str,
+    /// Extended thinking (`thinking`).
+    pub thinking: bool,
+    /// Prompt caching markers (`cache_control` on blocks).
+    pub cache_control: bool,
+    /// `top_k` sampling.
+    pub top_k: bool,
+    /// `tool_choice` at all. Without it, a named tool is approximated by
+    /// sending only that tool.
+    pub tool_choice: bool,
+    /// `tool_choice: any` (OpenAI `required`); otherwise sent as `auto`.
+    pub tool_choice_any: bool,
+    /// `tool_choice: tool` with a name; otherwise sent as `any`.
+    pub tool_choice_named: bool,
+    /// `tool_choice: none`; otherwise the tools are dropped instead.
+    pub tool_choice_none: bool,
+}
+
+impl ProviderProfile {
+    /// Anthropic Messages API: everything is supported.
+    pub const ANTHROPIC: Self = Self {
+        name: "anthropic",
+        thinking: true,
+        cache_control: true,
+        top_k: true,
+        tool_choice: true,
+        tool_choice_any: true,
+        tool_choice_named: true,
+        tool_choice_none: true,
+    };
+
+    /// OpenAI Chat Completions.
+    pub const OPENAI: Self = Self {
+        name: "openai",
+        thinking: false,
+        cache_control: false,
+        top_k: false,
+        ..Self::ANTHROPIC
+    };
+
+    /// Groq's OpenAI-compatible API.
+    pub const GROQ: Self = Self {
+        name: "groq",
+        ..Self::OPENAI
+    };
+
+    /// Ollama's chat API, which ignores `tool_choice`.
+    pub const OLLAMA: Self = Self {
+        name: "ollama",
+        top_k: true,
+        tool_choice: false,
+        tool_choice_any: false,
+        tool_choice_named: false,
+        tool_choice_none: false,
+        ..Self::OPENAI
+    };
+
+    /// Strip or convert what this provider doesn't support.
+    pub fn shape(&self, request: &mut CompletionRequest) {
+        if !self.thinking {
+            request.thinking = None;
+        }
+        if !self.top_k {
+            request.top_k = None;
+        }
+        if !self.cache_control {
+            strip_cache_control(request);
+        }
+        if let Some(choice) = request.tool_choice.take() {
+            request.tool_choice = self.shape_tool_choice(choice, &mut request.tools);
+        }
+    }
+
+    /// [`shape`](Self::shape) for a raw Anthropic-format JSON body, also
+    /// dropping top-level fields that aren't part of the API. Non-object
+    /// values are returned unchanged.
+    pub fn shape_raw(&self, request: &Value) -> Value {
+        let Value::Object(map) = request else {
+            return request.clone();
+        };
+        let mut shaped = serde_json::Map::new();
+        for (key, value) in map {
+            let supported = match key.as_str() {
+                "thinking" => self.thinking,
+                "top_k" => self.top_k,
+                key => MESSAGES_FIELDS.contains(&key),
+            };
+            if supported {
+                shaped.insert(key.clone(), value.clone());
+            }
+        }
+        if !self.cache_control {
+            for key in ["system", "messages", "tools"] {
+                if let Some(value) = shaped.get_mut(key) {
+                    strip_cache_control_raw(value);
+                }
+            }
+        }
+        if let Some(choice) = shaped.remove("tool_choice") {
+            let parsed = serde_json::from_value::<ToolChoice>(choice.clone()).ok();
+            let tools = shaped
+                .get("tools")
+                .and_then(|t| serde_json::from_value::<Vec<ToolDefinition>>(t.clone()).ok());
+            match (parsed, tools) {
+                (Some(parsed), Some(mut tools)) => {
+                    let before = tools.len();
+                    if let Some(shaped_choice) = self.shape_tool_choice(parsed, &mut tools) {
+                        // Unconverted choices keep extra keys like `disable_parallel_tool_use`
+                        let value = match serde_json::to_value(&shaped_choice) {
+                            Ok(value) if value["type"] != choice["type"] => value,
+                            _ => choice,
+                        };
+                        shaped.insert("tool_choice".to_string(), value);
+                    }
+                    if tools.len() != before {
+                        retain_raw_tools(&mut shaped, &tools);
+                    }
+                }
+                // Unparseable or tool-less choices are left for the upstream to judge
+                _ if self.tool_choice => {
+                    shaped.insert("tool_choice".to_string(), choice);
+                }
+                _ => {}
+            }
+        }
+        Value::Object(shaped)
+    }
+
+    /// The `tool_choice` to send, possibly narrowing `tools`.
+    fn shape_tool_choice(
+        &self,
+        choice: ToolChoice,
+        tools: &mut Vec<ToolDefinition>,
+    ) -> Option<ToolChoice> {
+        let choice = match choice {
+            ToolChoice::Tool { name } if !self.tool_choice_named => {
+                // Sending only the named tool keeps the intent
+                tools.retain(|t| t.name == name);
+                ToolChoice::Any
+            }
+            ToolChoice::None if !self.tool_choice_none => {
+                tools.clear();
+                return None;
+            }
+            choice => choice,
+        };
+        match choice {
+            _ if !self.tool_choice => None,
+            ToolChoice::Any if !self.tool_choice_any => Some(ToolChoice::Auto),
+            choice => Some(choice),
+        }
+    }
+}
+
+/// Keep only the raw tool definitions whose names survived shaping,
+/// preserving their original JSON.
+fn retain_raw_tools(shaped: &mut serde_json::Map<String, Value>, kept: &[ToolDefinition]) {
+    if kept.is_empty() {
+        shaped.remove("tools");
+        return;
+    }
+    if let Some(Value::Array(tools)) = shaped.get_mut("tools") {
+        tools.retain(|t| {
+            t.get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|name| kept.iter().any(|k| k.name == name))
+        });
+    }
+}
+
+fn strip_cache_control(request: &mut CompletionRequest) {
+    if let Some(SystemPrompt::Blocks(blocks)) = &mut request.system {
+        for block in blocks {
+            block.cache_control = None;
+        }
+    }
+    for message in &mut request.messages {
+        if let Content::Blocks(blocks) = &mut message.content {
+            for block in blocks {
+                match block {
+                    ContentBlock::Text { cache_control, .. }
+                    | ContentBlock::ToolUse { cache_control, .. } => *cache_control = None,
+                    ContentBlock::ToolResult {
+                        content,
+                        cache_control,
+                        ..
+                    } => {
+                        *cache_control = None;
+                        if let Some(ToolResultContent::Blocks(blocks)) = content {
+                            blocks.iter_mut().for_each(strip_cache_control_raw);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Remove `cache_control` keys from blocks (and nested tool-result
+/// content) in a raw `system`, `messages` or `tools` value.
+fn strip_cache_control_raw(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(strip_cache_control_raw),
+        Value::Object(map) => {
+            map.remove("cache_control");
+            if let Some(content) = map.get_mut("content") {
+                strip_cache_control_raw(content);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Message;
+    use serde_json::json;
+
+    fn tools() -> Vec<ToolDefinition> {
+        ["read_file", "search"]
+            .into_iter()
+            .map(|name| ToolDefinition::new(name, "", json!({"type": "object"})))
+            .collect()
+    }
+
+    #[test]
+    fn test_shape_tool_choice_conversions() {
+        let mut request =
+            CompletionRequest::new("m", vec![Message::user("hi")], 10).with_tools(tools());
+        request.tool_choice = Some(ToolChoice::Tool {
+            name: "search".to_string(),
+        });
+        ProviderProfile::OLLAMA.shape(&mut request);
+        assert!(request.tool_choice.is_none());
+        assert_eq!(request.tools.len(), 1);
+        assert_eq!(request.tools[0].name, "search");
+
+        let mut request =
+            CompletionRequest::new("m", vec![Message::user("hi")], 10).with_tools(tools());
+        request.tool_choice = Some(ToolChoice::None);
+        ProviderProfile::OLLAMA.shape(&mut request);
+        assert!(request.tools.is_empty());
+
+        let mut request =
+            CompletionRequest::new("m", vec![Message::user("hi")], 10).with_tools(tools());
+        request.tool_choice = Some(ToolChoice::Any);
+        request.top_k = Some(5);
+        ProviderProfile::GROQ.shape(&mut request);
+        assert!(matches!(request.tool_choice, Some(ToolChoice::Any)));
+        assert_eq!(request.top_k, None);
+        assert_eq!(request.tools.len(), 2);
+    }
+
+    #[test]
+    fn test_shape_raw_strips_unsupported() {
+        let body = json!({
+            "model": "m",
+            "max_tokens": 10,
+            "context_management": {"edits": []},
+            "thinking": {"type": "enabled", "budget_tokens": 1024},
+            "system": [{"type": "text", "text": "sys", "cache_control": {"type": "ephemeral"}}],
+            "messages": [{
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": "t1",
+                    "content": [{"type": "text", "text": "x", "cache_control": {"type": "ephemeral"}}],
+                    "cache_control": {"type": "ephemeral"}
+                }]
+            }],
+            "tools": [
+                {"name": "read_file", "description": "", "input_schema": {"type": "object"}},
+                {"name": "search", "description": "", "input_schema": {"type": "object"}}
+            ],
+            "tool_choice": {"type": "tool", "name": "search"}
+        });
+
+        let anthropic = ProviderProfile::ANTHROPIC.shape_raw(&body);
+        assert!(anthropic.get("context_management").is_none());
+        assert_eq!(anthropic["thinking"], body["thinking"]);
+        assert_eq!(anthropic["tool_choice"], body["tool_choice"]);
+        assert_eq!(anthropic["messages"], body["messages"]);
+
+        let openai = ProviderProfile::OPENAI.shape_raw(&body);
+        assert!(openai.get("thinking").is_none());
+        assert!(!openai.to_string().contains("cache_control"));
+        assert_eq!(openai["tool_choice"], body["tool_choice"]);
+
+        let ollama = ProviderProfile::OLLAMA.shape_raw(&body);
+        assert!(ollama.get("tool_choice").is_none());
+        assert_eq!(ollama["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(ollama["tools"][0]["name"], "search");
+    }
+}
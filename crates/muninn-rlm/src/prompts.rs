@@ -4,6 +4,15 @@
 //! 1. `CORE_RLM_BEHAVIOR` - Core strategy and guidelines (backend-agnostic)
 //! 2. Backend-specific tool definitions (via `LLMBackend::format_tool_definitions`)
 //! 3. Backend-specific tool calling instructions (via `LLMBackend::tool_calling_instructions`)
+//!
+//! When the router puts a request in a [`RouteCategory`], the
+//! [`PromptRegistry`] adds a focus section for that category to
+//! `CORE_RLM_BEHAVIOR` and narrows the tools offered to the model.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 
 /// Core RLM behavior prompt - describes strategy and guidelines.
 ///
@@ -36,6 +45,7 @@ Focus on the **last user message** as your primary query. Use the conversation h
 - **Stop when sufficient**: Once you have enough context to answer, stop exploring
 - **Use tools actively**: Don't just describe what you would do - actually call the tools
 - **Graph then read**: When graph tools return file locations, consider following up with read_file to get the actual code - metadata alone is often not enough
+- **Check earlier answers**: For follow-up questions, `session_history` (when available) shows what earlier requests in this session already concluded
 
 ## Library Documentation
 
@@ -79,6 +89,211 @@ pub fn authenticate(token: &str) -> Result<User> {
 
 Keep responses COMPACT but HIGH-SIGNAL. Include code, skip unnecessary commentary."#;
 
+/// Kind of request, picked by the router during triage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteCategory {
+    /// How something in the codebase works or where it lives.
+    CodeQuestion,
+    /// A failure, error or unexpected behaviour to track down.
+    Debugging,
+    /// Restructuring or renaming existing code.
+    Refactor,
+    /// Library APIs and project documentation.
+    Docs,
+    /// Build, CI, deployment and configuration files.
+    Ops,
+}
+
+impl RouteCategory {
+    /// Every category, in the order the router is shown them.
+    pub const ALL: [RouteCategory; 5] = [
+        Self::CodeQuestion,
+        Self::Debugging,
+        Self::Refactor,
+        Self::Docs,
+        Self::Ops,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CodeQuestion => "code_question",
+            Self::Debugging => "debugging",
+            Self::Refactor => "refactor",
+            Self::Docs => "docs",
+            Self::Ops => "ops",
+        }
+    }
+
+    /// Parse a category name, accepting `-` or spaces for `_` and any case
+    /// (routers are small models and not always exact).
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+        Self::ALL.into_iter().find(|c| c.as_str() == name)
+    }
+}
+
+impl fmt::Display for RouteCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Category-specific additions to the exploration prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategoryPrompt {
+    /// Section appended to `CORE_RLM_BEHAVIOR`.
+    pub focus: String,
+    /// Tools offered to the model; empty offers all of them.
+    /// `final_answer` and `session_history` are always offered.
+    pub tools: Vec<String>,
+}
+
+impl CategoryPrompt {
+    pub fn new(focus: impl Into<String>) -> Self {
+        Self {
+            focus: focus.into(),
+            tools: Vec::new(),
+        }
+    }
+
+    pub fn with_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether `tool` is offered for this category.
+    pub fn allows(&self, tool: &str) -> bool {
+        self.tools.is_empty()
+            || ALWAYS_OFFERED.contains(&tool)
+            || self.tools.iter().any(|t| t == tool)
+    }
+}
+
+/// Tools every category keeps.
+const ALWAYS_OFFERED: [&str; 2] = ["final_answer", "session_history"];
+
+const FS_TOOLS: [&str; 3] = ["read_file", "list_directory", "search_files"];
+
+const GRAPH_TOOLS: [&str; 8] = [
+    "graph_query",
+    "get_symbol",
+    "read_symbol",
+    "find_symbols",
+    "find_callers",
+    "find_callees",
+    "file_outline",
+    "find_cycles",
+];
+
+const DOC_TOOLS: [&str; 4] = [
+    "list_libraries",
+    "search_docs",
+    "index_crate",
+    "index_package",
+];
+
+/// The filesystem tools plus `extra`.
+fn fs_and(extra: &[&'static str]) -> Vec<&'static str> {
+    [&FS_TOOLS[..], extra].concat()
+}
+
+const CODE_QUESTION_FOCUS: &str = "## Focus: Code Question
+
+The request asks how something works or where it lives. Find the defining code first, then the one or two call sites that show it in use. Answer the question directly before adding detail.";
+
+const DEBUGGING_FOCUS: &str = "## Focus: Debugging
+
+The request is about a failure. Start from the error text, failing test or symptom: search for the message, then trace the code path that produces it through its callers. Report the likely cause with the exact lines involved, and mention anything you ruled out. Use `execute_code` to check an assumption when reading alone can't settle it.";
+
+const REFACTOR_FOCUS: &str = "## Focus: Refactor
+
+The request changes existing code. Establish the full blast radius before answering: the definition, every caller and implementor (`find_callers`, `find_symbols`), and the tests that cover it. List each place that would need to change, with file paths and line numbers.";
+
+const DOCS_FOCUS: &str = "## Focus: Documentation
+
+The request is about a library API or project documentation. Prefer indexed docs (`search_docs`, indexing the library first if needed) and the project's own README and doc comments, then show how the project already uses the API.";
+
+const OPS_FOCUS: &str = "## Focus: Operations
+
+The request is about building, testing, deploying or configuring the project. Look at manifests, CI workflows, Dockerfiles, scripts and config files rather than application source, and quote the relevant settings verbatim.";
+
+/// Category prompts, keyed by [`RouteCategory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptRegistry {
+    prompts: HashMap<RouteCategory, CategoryPrompt>,
+}
+
+impl Default for PromptRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PromptRegistry {
+    /// A registry with no category prompts: every request gets
+    /// `CORE_RLM_BEHAVIOR` and all tools.
+    pub fn empty() -> Self {
+        Self {
+            prompts: HashMap::new(),
+        }
+    }
+
+    /// The built-in prompt and tool subset for each category.
+    pub fn builtin() -> Self {
+        Self::empty()
+            .with(
+                RouteCategory::CodeQuestion,
+                CategoryPrompt::new(CODE_QUESTION_FOCUS),
+            )
+            .with(
+                RouteCategory::Debugging,
+                CategoryPrompt::new(DEBUGGING_FOCUS)
+                    .with_tools(fs_and(&[&GRAPH_TOOLS[..], &["execute_code"]].concat())),
+            )
+            .with(
+                RouteCategory::Refactor,
+                CategoryPrompt::new(REFACTOR_FOCUS).with_tools(fs_and(&GRAPH_TOOLS)),
+            )
+            .with(
+                RouteCategory::Docs,
+                CategoryPrompt::new(DOCS_FOCUS).with_tools(fs_and(&DOC_TOOLS)),
+            )
+            .with(
+                RouteCategory::Ops,
+                CategoryPrompt::new(OPS_FOCUS).with_tools(FS_TOOLS),
+            )
+    }
+
+    /// Set the prompt for `category`, replacing any existing one.
+    pub fn with(mut self, category: RouteCategory, prompt: CategoryPrompt) -> Self {
+        self.prompts.insert(category, prompt);
+        self
+    }
+
+    pub fn get(&self, category: RouteCategory) -> Option<&CategoryPrompt> {
+        self.prompts.get(&category)
+    }
+
+    /// `CORE_RLM_BEHAVIOR` plus the focus section for `category`, if any.
+    pub fn system_prompt(&self, category: Option<RouteCategory>) -> String {
+        let mut prompt = CORE_RLM_BEHAVIOR.to_string();
+        if let Some(focus) = category
+            .and_then(|c| self.get(c))
+            .map(|p| p.focus.as_str())
+            .filter(|f| !f.is_empty())
+        {
+            prompt.push_str("\n\n");
+            prompt.push_str(focus);
+        }
+        prompt
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +306,55 @@ mod tests {
         assert!(CORE_RLM_BEHAVIOR.contains("## Termination"));
         assert!(CORE_RLM_BEHAVIOR.contains("final_answer"));
     }
+
+    #[test]
+    fn test_route_category_parse() {
+        assert_eq!(
+            RouteCategory::parse("code_question"),
+            Some(RouteCategory::CodeQuestion)
+        );
+        assert_eq!(
+            RouteCategory::parse(" Code-Question "),
+            Some(RouteCategory::CodeQuestion)
+        );
+        assert_eq!(RouteCategory::parse("OPS"), Some(RouteCategory::Ops));
+        assert_eq!(RouteCategory::parse("chat"), None);
+        for category in RouteCategory::ALL {
+            assert_eq!(RouteCategory::parse(category.as_str()), Some(category));
+        }
+    }
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = PromptRegistry::builtin();
+        for category in RouteCategory::ALL {
+            assert!(registry.get(category).is_some(), "{category}");
+        }
+
+        let ops = registry.get(RouteCategory::Ops).unwrap();
+        assert!(ops.allows("read_file"));
+        assert!(ops.allows("final_answer"));
+        assert!(!ops.allows("execute_code"));
+        assert!(
+            registry
+                .get(RouteCategory::Debugging)
+                .unwrap()
+                .allows("execute_code")
+        );
+        assert!(
+            registry
+                .get(RouteCategory::CodeQuestion)
+                .unwrap()
+                .allows("anything")
+        );
+
+        let prompt = registry.system_prompt(Some(RouteCategory::Refactor));
+        assert!(prompt.starts_with(CORE_RLM_BEHAVIOR));
+        assert!(prompt.contains("## Focus: Refactor"));
+        assert_eq!(registry.system_prompt(None), CORE_RLM_BEHAVIOR);
+        assert_eq!(
+            PromptRegistry::empty().system_prompt(Some(RouteCategory::Ops)),
+            CORE_RLM_BEHAVIOR
+        );
+    }
 }
@@ -6,15 +6,18 @@
 //! - RLM mode: Use configured backend for recursive exploration
 
 use axum::{
-    Json, Router as AxumRouter,
+    Extension, Json, Router as AxumRouter,
     extract::State,
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
     response::IntoResponse,
     routing::post,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 use tokio::net::TcpListener;
 use tower_http::cors::{Any, CorsLayer};
@@ -22,14 +25,42 @@ use tower_http::trace::TraceLayer;
 
 use crate::backend::LLMBackend;
 use muninn_core::MuninnEngine;
-
-use crate::engine::default_engine;
+use muninn_tracing::TraceParent;
+
+use crate::client_keys::{ClientIdentity, ClientKeys};
+use crate::compaction::{Compaction, Compactor};
+use crate::debug_log::{RawRequestLog, RawRequestLogConfig};
+use crate::engine::{
+    Downshift, EngineConfig, EngineDeps, Ensemble, RecursiveEngine, TIMING_COMPACTION,
+    TIMING_EXPLORATION, TIMING_QUEUE_WAIT, TIMING_ROUTING, TIMING_SERIALIZATION, ToolLimits,
+};
 use crate::error::RlmError;
-use crate::passthrough::{Passthrough, PassthroughConfig};
-use crate::router::{RouteDecision, Router as RlmRouter, RouterConfig};
+use crate::experiment::{Assignment, EXPERIMENT_METADATA, Experiment};
+use crate::exploration_report::{ExplorationReport, ExplorationReports};
+use crate::feedback::{Feedback, FeedbackLog};
+use crate::index_status::{IndexStatus, IndexStatusSnapshot};
+use crate::internal_answers::{self, InternalAnswers};
+use crate::notify::{Notifier, NotifierConfig};
+use crate::openai_compat::{self, ChatTranslation};
+use crate::passthrough::{
+    ConnectionStatsSnapshot, Passthrough, PassthroughConfig, UPSTREAM_URL_HEADER,
+    UpstreamBodyStream,
+};
+use crate::prompts::PromptRegistry;
+use crate::recording::UpstreamRecorder;
+use crate::response_transform::{
+    ResponsePipeline, ResponseSource, ResponseTransform, TransformContext, transform_stream,
+};
+use crate::router::{self, RouteDecision, Router as RlmRouter, RouterBackend, RouterConfig};
+use crate::routing_log::RoutingLog;
+use crate::session_export::session_export_router;
+use crate::session_recording::{ROUTE_PASSTHROUGH, ROUTE_RLM, RecordedRequest, SessionRecorder};
+use crate::spend::{SpendEntry, SpendLedger, TokenUsage};
+use crate::sse::{SseInspector, StreamStats};
 use crate::token_manager::SharedTokenManager;
-use crate::tools::ToolEnvironment;
+use crate::tools::{ToolCategory, ToolEnvironment, with_tool_grant};
 use crate::types::{CompletionRequest, MuninnConfig};
+use muninn_graph::IndexQueue;
 
 // ============================================================================
 // Proxy Trace Data
@@ -82,6 +113,49 @@ pub struct ProxyConfig {
     pub trace_writer: Option<muninn_tracing::WriterConfig>,
     /// Session directory for logging (when set, uses session-based logging).
     pub session_dir: Option<std::path::PathBuf>,
+    /// Record upstream request/response pairs into the session directory.
+    pub record_upstream: bool,
+    /// Per-tool limits for recursive exploration, keyed by tool name.
+    pub tool_limits: std::collections::HashMap<String, ToolLimits>,
+    /// System-prompt hint about earlier exploration, passed to the engine.
+    pub exploration_hint: Option<String>,
+    /// Have the engine call its backend through the streaming API.
+    pub engine_streaming: bool,
+    /// Stop sequences added to every exploration turn.
+    pub stop_sequences: Vec<String>,
+    /// Cap on output tokens per exploration turn.
+    pub max_iteration_tokens: Option<u32>,
+    /// Continuation requests for an answer cut off by `max_tokens`.
+    pub max_continuations: u32,
+    /// Category prompts and tool subsets, passed to the engine.
+    pub prompts: PromptRegistry,
+    /// Fallback backend the engine downshifts to when the token budget
+    /// runs low.
+    pub downshift: Option<Downshift>,
+    /// Second backend and judge for final answers, passed to the engine.
+    pub ensemble: Option<Ensemble>,
+    /// Raw request debug log; `None` (the default) disables it.
+    pub raw_request_log: Option<RawRequestLogConfig>,
+    /// How `/v1/chat/completions` bodies are handled.
+    pub chat_translation: ChatTranslation,
+    /// Webhook notifications; `None` (the default) disables them.
+    pub notifications: Option<NotifierConfig>,
+    /// Answer title and suggestion requests locally instead of passing
+    /// them through; `None` (the default) passes them through.
+    pub internal_answers: Option<InternalAnswers>,
+    /// Summarizes the older turns of long conversations; `None` (the
+    /// default) sends conversations on whole.
+    pub compactor: Option<Compactor>,
+    /// Client keys required on `/v1` requests; `None` (the default)
+    /// accepts any request.
+    pub client_keys: Option<Arc<ClientKeys>>,
+    /// Background graph index progress, reported at `/statusline`.
+    pub index_status: Option<IndexStatus>,
+    /// Priority index queue fed with the paths of the agent's tool calls.
+    pub index_queue: Option<IndexQueue>,
+    /// Route the rest of the session toward RLM once the user corrects a
+    /// passthrough decision with `{at}muninn explore`.
+    pub promote_on_correction: bool,
 }
 
 impl Clone for ProxyConfig {
@@ -96,6 +170,25 @@ impl Clone for ProxyConfig {
             work_dir: self.work_dir.clone(),
             trace_writer: self.trace_writer.clone(),
             session_dir: self.session_dir.clone(),
+            record_upstream: self.record_upstream,
+            tool_limits: self.tool_limits.clone(),
+            exploration_hint: self.exploration_hint.clone(),
+            engine_streaming: self.engine_streaming,
+            stop_sequences: self.stop_sequences.clone(),
+            max_iteration_tokens: self.max_iteration_tokens,
+            max_continuations: self.max_continuations,
+            prompts: self.prompts.clone(),
+            downshift: self.downshift.clone(),
+            ensemble: self.ensemble.clone(),
+            raw_request_log: self.raw_request_log.clone(),
+            chat_translation: self.chat_translation.clone(),
+            notifications: self.notifications.clone(),
+            internal_answers: self.internal_answers.clone(),
+            compactor: self.compactor.clone(),
+            client_keys: self.client_keys.clone(),
+            index_status: self.index_status.clone(),
+            index_queue: self.index_queue.clone(),
+            promote_on_correction: self.promote_on_correction,
         }
     }
 }
@@ -112,6 +205,25 @@ impl Default for ProxyConfig {
             work_dir: None,
             trace_writer: Some(muninn_tracing::WriterConfig::default()),
             session_dir: None,
+            record_upstream: false,
+            tool_limits: std::collections::HashMap::new(),
+            exploration_hint: None,
+            engine_streaming: false,
+            stop_sequences: Vec::new(),
+            max_iteration_tokens: None,
+            max_continuations: crate::engine::DEFAULT_MAX_CONTINUATIONS,
+            prompts: PromptRegistry::builtin(),
+            downshift: None,
+            ensemble: None,
+            raw_request_log: None,
+            chat_translation: ChatTranslation::Raw,
+            notifications: None,
+            internal_answers: None,
+            compactor: None,
+            client_keys: None,
+            index_status: None,
+            index_queue: None,
+            promote_on_correction: false,
         }
     }
 }
@@ -178,6 +290,89 @@ impl ProxyConfig {
         self.session_dir = Some(path.into());
         self
     }
+
+    /// Enable or disable recording of upstream request/response pairs.
+    ///
+    /// Recordings go to `upstream.jsonl` in the session directory (or
+    /// `.muninn/debug/` without one), next to `requests.jsonl` with each
+    /// agent request and how it was routed.
+    pub fn with_upstream_recording(mut self, enable: bool) -> Self {
+        self.record_upstream = enable;
+        self
+    }
+
+    /// Set limits for one tool during recursive exploration.
+    pub fn with_tool_limits(mut self, tool: impl Into<String>, limits: ToolLimits) -> Self {
+        self.tool_limits.insert(tool.into(), limits);
+        self
+    }
+
+    /// Log every raw `/v1/messages` request body (see [`crate::debug_log`]).
+    pub fn with_raw_request_log(mut self, config: RawRequestLogConfig) -> Self {
+        self.raw_request_log = Some(config);
+        self
+    }
+
+    /// Set how `/v1/chat/completions` bodies are handled.
+    pub fn with_chat_translation(mut self, translation: ChatTranslation) -> Self {
+        self.chat_translation = translation;
+        self
+    }
+
+    /// Send budget, backend health and spend notifications (see [`crate::notify`]).
+    pub fn with_notifications(mut self, config: NotifierConfig) -> Self {
+        self.notifications = Some(config);
+        self
+    }
+
+    /// Answer the agent's title and suggestion requests locally.
+    pub fn with_internal_answers(mut self, answers: InternalAnswers) -> Self {
+        self.internal_answers = Some(answers);
+        self
+    }
+
+    /// Compact long conversations before handling them.
+    pub fn with_compactor(mut self, compactor: Compactor) -> Self {
+        self.compactor = Some(compactor);
+        self
+    }
+
+    /// Require one of `keys` on every `/v1` request.
+    pub fn with_client_keys(mut self, keys: ClientKeys) -> Self {
+        self.client_keys = Some(Arc::new(keys));
+        self
+    }
+
+    /// Set the system-prompt hint about earlier exploration.
+    pub fn with_exploration_hint(mut self, hint: impl Into<String>) -> Self {
+        self.exploration_hint = Some(hint.into());
+        self
+    }
+
+    /// Stream backend responses during recursive exploration.
+    pub fn with_engine_streaming(mut self, streaming: bool) -> Self {
+        self.engine_streaming = streaming;
+        self
+    }
+
+    /// Report background indexing progress at `/statusline`.
+    pub fn with_index_status(mut self, status: IndexStatus) -> Self {
+        self.index_status = Some(status);
+        self
+    }
+
+    /// Hint the paths in the agent's tool calls to a background index.
+    pub fn with_index_queue(mut self, queue: IndexQueue) -> Self {
+        self.index_queue = Some(queue);
+        self
+    }
+
+    /// Bias routing toward RLM for the rest of the session once the user
+    /// corrects a passthrough decision (see [`RlmRouter::promote`]).
+    pub fn with_correction_promotion(mut self, enable: bool) -> Self {
+        self.promote_on_correction = enable;
+        self
+    }
 }
 
 /// Shared state for the proxy server.
@@ -190,10 +385,193 @@ struct ProxyState {
     router: Option<RlmRouter>,
     /// Passthrough client for forwarding to upstream API.
     passthrough: Passthrough,
-    /// Trace writer for agentic traces (optional).
-    trace_writer: Option<muninn_tracing::TraceWriter>,
-    /// Session directory for logging (optional).
-    session_dir: Option<std::path::PathBuf>,
+    /// Trace writer for agentic traces (optional). Shared so streaming
+    /// responses can write their stats after the handler returns.
+    trace_writer: Option<Arc<muninn_tracing::TraceWriter>>,
+    /// Token spend ledger (only with a session directory).
+    spend_ledger: Option<Arc<SpendLedger>>,
+    /// Markdown report per RLM exploration (only with a session directory).
+    exploration_reports: Option<Arc<ExplorationReports>>,
+    /// User ratings of previous answers (only with a session directory).
+    feedback: Option<Arc<FeedbackLog>>,
+    /// Routing decisions and user corrections (only with a session directory).
+    routing_log: Option<Arc<RoutingLog>>,
+    /// Raw request debug log (opt-in).
+    raw_request_log: Option<Arc<RawRequestLog>>,
+    /// Agent requests and their routes, for replay (with `record_upstream`).
+    session_recorder: Option<Arc<SessionRecorder>>,
+    /// Live routing counters served at `/statusline`.
+    stats: Arc<RouteStats>,
+    /// Webhook notifier (opt-in).
+    notifier: Option<Arc<Notifier>>,
+    /// How `/v1/chat/completions` bodies are handled.
+    chat_translation: ChatTranslation,
+    /// Local answers for title and suggestion requests (opt-in).
+    internal_answers: Option<InternalAnswers>,
+    /// Conversation compaction (opt-in).
+    compactor: Option<Compactor>,
+    /// Client keys and their usage (opt-in).
+    client_keys: Option<Arc<ClientKeys>>,
+    /// Rewrites applied to Messages responses on the way out.
+    transforms: ResponsePipeline,
+    /// Background graph index progress (only while one was started).
+    index_status: Option<IndexStatus>,
+    /// Priority queue of a background index (only while one was started).
+    index_queue: Option<IndexQueue>,
+    /// A/B experiment splitting routed requests between variants.
+    experiment: Option<Experiment>,
+}
+
+/// Routing and token counters since the proxy started.
+#[derive(Debug)]
+pub struct RouteStats {
+    started: Instant,
+    passthrough: AtomicU64,
+    rlm: AtomicU64,
+    rlm_errors: AtomicU64,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+}
+
+/// Point-in-time copy of [`RouteStats`], as returned by `/statusline`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteStatsSnapshot {
+    pub uptime_secs: u64,
+    pub requests: u64,
+    pub passthrough: u64,
+    pub rlm: u64,
+    pub rlm_errors: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Passthrough connection reuse.
+    pub upstream: ConnectionStatsSnapshot,
+    /// Background graph indexing, when one was started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexing: Option<IndexStatusSnapshot>,
+    /// One-line summary for an agent status bar.
+    pub line: String,
+}
+
+impl RouteStats {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            passthrough: AtomicU64::new(0),
+            rlm: AtomicU64::new(0),
+            rlm_errors: AtomicU64::new(0),
+            input_tokens: AtomicU64::new(0),
+            output_tokens: AtomicU64::new(0),
+        }
+    }
+
+    fn record_route(&self, handling: &str) {
+        let counter = if handling == "rlm" {
+            &self.rlm
+        } else {
+            &self.passthrough
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_usage(&self, usage: &TokenUsage) {
+        self.input_tokens.fetch_add(
+            usage.input_tokens + usage.cache_creation_input_tokens + usage.cache_read_input_tokens,
+            Ordering::Relaxed,
+        );
+        self.output_tokens
+            .fetch_add(usage.output_tokens, Ordering::Relaxed);
+    }
+
+    /// Current counters.
+    pub fn snapshot(&self) -> RouteStatsSnapshot {
+        let passthrough = self.passthrough.load(Ordering::Relaxed);
+        let rlm = self.rlm.load(Ordering::Relaxed);
+        let rlm_errors = self.rlm_errors.load(Ordering::Relaxed);
+        let input_tokens = self.input_tokens.load(Ordering::Relaxed);
+        let output_tokens = self.output_tokens.load(Ordering::Relaxed);
+        let mut line = format!(
+            "muninn · {} req · {} rlm · {} tok",
+            passthrough + rlm,
+            rlm,
+            compact_count(input_tokens + output_tokens)
+        );
+        if rlm_errors > 0 {
+            line.push_str(&format!(" · {} rlm err", rlm_errors));
+        }
+        RouteStatsSnapshot {
+            uptime_secs: self.started.elapsed().as_secs(),
+            requests: passthrough + rlm,
+            passthrough,
+            rlm,
+            rlm_errors,
+            input_tokens,
+            output_tokens,
+            upstream: ConnectionStatsSnapshot::default(),
+            indexing: None,
+            line,
+        }
+    }
+}
+
+/// `1234567` -> `1.2M`, `45210` -> `45.2k`.
+fn compact_count(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", n as f64 / 1_000.0),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+impl ProxyState {
+    /// Report an upstream call's outcome to the notifier, passing it through.
+    fn upstream_result<T>(&self, result: crate::error::Result<T>) -> crate::error::Result<T> {
+        if let Some(notifier) = &self.notifier {
+            match &result {
+                Ok(_) => notifier.backend_ok("upstream"),
+                Err(RlmError::Backend(msg) | RlmError::Network(msg)) => {
+                    notifier.backend_failure("upstream", msg)
+                }
+                Err(_) => {}
+            }
+        }
+        result
+    }
+
+    /// Append a request's token usage (and, for scored explorations, its
+    /// answer quality) to the spend ledger, if enabled.
+    fn record_spend(
+        &self,
+        ctx: &RequestContext,
+        model: &str,
+        handling: &str,
+        streaming: bool,
+        usage: TokenUsage,
+        quality: Option<f64>,
+    ) {
+        self.stats.record_usage(&usage);
+        if let Some(notifier) = &self.notifier {
+            notifier.record_usage(&usage);
+        }
+        if let (Some(keys), Some(client)) = (&self.client_keys, &ctx.client) {
+            keys.record_usage(client, usage.total());
+        }
+        if let Some(ledger) = &self.spend_ledger {
+            ledger.record(
+                &SpendEntry::new(&ctx.trace_id, model, handling, streaming, usage)
+                    .with_tags(ctx.tags.clone())
+                    .with_client(ctx.client.clone())
+                    .with_quality(quality),
+            );
+        }
+    }
+
+    /// Append a request to the session recording, if enabled. `recorded`
+    /// is only called when recording, so callers can clone freely.
+    fn record_request(&self, recorded: impl FnOnce() -> RecordedRequest) {
+        if let Some(recorder) = &self.session_recorder {
+            recorder.record(recorded());
+        }
+    }
 }
 
 /// The RLM proxy server.
@@ -204,10 +582,10 @@ pub struct ProxyServer {
 
 impl ProxyServer {
     /// Create a trace writer from config.
-    fn create_trace_writer(config: &ProxyConfig) -> Option<muninn_tracing::TraceWriter> {
+    fn create_trace_writer(config: &ProxyConfig) -> Option<Arc<muninn_tracing::TraceWriter>> {
         config.trace_writer.as_ref().and_then(
             |writer_config| match muninn_tracing::TraceWriter::new(writer_config.clone()) {
-                Ok(writer) => Some(writer),
+                Ok(writer) => Some(Arc::new(writer)),
                 Err(e) => {
                     tracing::warn!(error = %e, "Failed to create trace writer");
                     None
@@ -216,23 +594,96 @@ impl ProxyServer {
         )
     }
 
-    /// Create a new proxy server with RLM backend.
-    pub fn new(
-        config: ProxyConfig,
+    /// Create the raw request log from config, if enabled.
+    fn create_raw_request_log(config: &ProxyConfig) -> Option<Arc<RawRequestLog>> {
+        config.raw_request_log.clone().map(|log_config| {
+            let log = RawRequestLog::new(log_config);
+            tracing::info!(path = %log.path().display(), "Logging raw requests");
+            Arc::new(log)
+        })
+    }
+
+    /// Create the session recorder when upstream recording is on. It shares
+    /// the directory of the upstream recording.
+    fn create_session_recorder(config: &ProxyConfig) -> Option<Arc<SessionRecorder>> {
+        if !config.record_upstream {
+            return None;
+        }
+        let dir = config
+            .session_dir
+            .clone()
+            .unwrap_or_else(|| std::path::PathBuf::from(".muninn/debug"));
+        let recorder = SessionRecorder::in_dir(&dir);
+        tracing::info!(path = %recorder.path().display(), "Recording agent requests");
+        Some(Arc::new(recorder))
+    }
+
+    /// Create the RLM engine from config.
+    fn create_engine(
+        config: &ProxyConfig,
         backend: Arc<dyn LLMBackend>,
         tools: Arc<dyn ToolEnvironment>,
-    ) -> Self {
-        let engine = default_engine(
-            backend,
-            tools,
-            config.budget.clone(),
-            config.work_dir.clone(),
-        );
-        let router = RlmRouter::new();
+    ) -> Arc<dyn MuninnEngine> {
+        let mut engine_config = EngineConfig::default();
+        if let Some(budget) = &config.budget {
+            engine_config = engine_config.with_budget(budget.clone());
+        }
+        if let Some(work_dir) = &config.work_dir {
+            engine_config = engine_config.with_work_dir(work_dir);
+        }
+        for (tool, limits) in &config.tool_limits {
+            engine_config = engine_config.with_tool_limits(tool, limits.clone());
+        }
+        if let Some(hint) = &config.exploration_hint {
+            engine_config = engine_config.with_exploration_hint(hint);
+        }
+        engine_config = engine_config
+            .with_streaming(config.engine_streaming)
+            .with_stop_sequences(config.stop_sequences.iter().cloned())
+            .with_max_continuations(config.max_continuations)
+            .with_prompts(config.prompts.clone());
+        if let Some(max) = config.max_iteration_tokens {
+            engine_config = engine_config.with_max_iteration_tokens(max);
+        }
+        if let Some(downshift) = &config.downshift {
+            engine_config = engine_config.with_downshift(downshift.clone());
+        }
+        if let Some(ensemble) = &config.ensemble {
+            engine_config = engine_config.with_ensemble(ensemble.clone());
+        }
+        Arc::new(RecursiveEngine::new(
+            EngineDeps::new(backend, tools),
+            engine_config,
+        ))
+    }
+
+    /// Create the passthrough client from config.
+    fn create_passthrough(config: &ProxyConfig) -> Passthrough {
         let mut passthrough = Passthrough::with_config(config.passthrough.clone());
         if let Some(tm) = &config.token_manager {
             passthrough = passthrough.with_token_manager(tm.clone());
         }
+        if config.record_upstream {
+            let dir = config
+                .session_dir
+                .clone()
+                .unwrap_or_else(|| std::path::PathBuf::from(".muninn/debug"));
+            let recorder = UpstreamRecorder::in_dir(&dir);
+            tracing::info!(path = %recorder.path().display(), "Recording upstream requests");
+            passthrough = passthrough.with_recorder(Arc::new(recorder));
+        }
+        passthrough
+    }
+
+    /// Create a new proxy server with RLM backend.
+    pub fn new(
+        config: ProxyConfig,
+        backend: Arc<dyn LLMBackend>,
+        tools: Arc<dyn ToolEnvironment>,
+    ) -> Self {
+        let engine = Self::create_engine(&config, backend, tools);
+        let router = RlmRouter::new().with_correction_promotion(config.promote_on_correction);
+        let passthrough = Self::create_passthrough(&config);
         let trace_writer = Self::create_trace_writer(&config);
         Self {
             state: Arc::new(ProxyState {
@@ -240,7 +691,37 @@ impl ProxyServer {
                 router: Some(router),
                 passthrough,
                 trace_writer,
-                session_dir: config.session_dir.clone(),
+                spend_ledger: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(SpendLedger::in_dir(dir))),
+                exploration_reports: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(ExplorationReports::in_dir(dir))),
+                feedback: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(FeedbackLog::in_dir(dir))),
+                routing_log: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(RoutingLog::in_dir(dir))),
+                raw_request_log: Self::create_raw_request_log(&config),
+                session_recorder: Self::create_session_recorder(&config),
+                stats: Arc::new(RouteStats::new()),
+                notifier: config
+                    .notifications
+                    .clone()
+                    .map(|c| Arc::new(Notifier::new(c))),
+                chat_translation: config.chat_translation.clone(),
+                internal_answers: config.internal_answers.clone(),
+                compactor: config.compactor.clone(),
+                client_keys: config.client_keys.clone(),
+                transforms: ResponsePipeline::default(),
+                index_status: config.index_status.clone(),
+                index_queue: config.index_queue.clone(),
+                experiment: None,
             }),
             config,
         }
@@ -248,10 +729,7 @@ impl ProxyServer {
 
     /// Create a passthrough-only proxy (no RLM backend required).
     pub fn passthrough_only(config: ProxyConfig) -> Self {
-        let mut passthrough = Passthrough::with_config(config.passthrough.clone());
-        if let Some(tm) = &config.token_manager {
-            passthrough = passthrough.with_token_manager(tm.clone());
-        }
+        let passthrough = Self::create_passthrough(&config);
         let trace_writer = Self::create_trace_writer(&config);
         Self {
             state: Arc::new(ProxyState {
@@ -259,7 +737,37 @@ impl ProxyServer {
                 router: None,
                 passthrough,
                 trace_writer,
-                session_dir: config.session_dir.clone(),
+                spend_ledger: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(SpendLedger::in_dir(dir))),
+                exploration_reports: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(ExplorationReports::in_dir(dir))),
+                feedback: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(FeedbackLog::in_dir(dir))),
+                routing_log: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(RoutingLog::in_dir(dir))),
+                raw_request_log: Self::create_raw_request_log(&config),
+                session_recorder: Self::create_session_recorder(&config),
+                stats: Arc::new(RouteStats::new()),
+                notifier: config
+                    .notifications
+                    .clone()
+                    .map(|c| Arc::new(Notifier::new(c))),
+                chat_translation: config.chat_translation.clone(),
+                internal_answers: config.internal_answers.clone(),
+                compactor: config.compactor.clone(),
+                client_keys: config.client_keys.clone(),
+                transforms: ResponsePipeline::default(),
+                index_status: config.index_status.clone(),
+                index_queue: config.index_queue.clone(),
+                experiment: None,
             }),
             config,
         }
@@ -272,17 +780,11 @@ impl ProxyServer {
         tools: Arc<dyn ToolEnvironment>,
         router_config: RouterConfig,
     ) -> Self {
-        let engine = default_engine(
-            backend.clone(),
-            tools,
-            config.budget.clone(),
-            config.work_dir.clone(),
-        );
-        let router = RlmRouter::with_config(router_config).with_llm(backend);
-        let mut passthrough = Passthrough::with_config(config.passthrough.clone());
-        if let Some(tm) = &config.token_manager {
-            passthrough = passthrough.with_token_manager(tm.clone());
-        }
+        let engine = Self::create_engine(&config, backend.clone(), tools);
+        let router = RlmRouter::with_config(router_config)
+            .with_llm(backend)
+            .with_correction_promotion(config.promote_on_correction);
+        let passthrough = Self::create_passthrough(&config);
         let trace_writer = Self::create_trace_writer(&config);
         Self {
             state: Arc::new(ProxyState {
@@ -290,7 +792,37 @@ impl ProxyServer {
                 router: Some(router),
                 passthrough,
                 trace_writer,
-                session_dir: config.session_dir.clone(),
+                spend_ledger: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(SpendLedger::in_dir(dir))),
+                exploration_reports: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(ExplorationReports::in_dir(dir))),
+                feedback: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(FeedbackLog::in_dir(dir))),
+                routing_log: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(RoutingLog::in_dir(dir))),
+                raw_request_log: Self::create_raw_request_log(&config),
+                session_recorder: Self::create_session_recorder(&config),
+                stats: Arc::new(RouteStats::new()),
+                notifier: config
+                    .notifications
+                    .clone()
+                    .map(|c| Arc::new(Notifier::new(c))),
+                chat_translation: config.chat_translation.clone(),
+                internal_answers: config.internal_answers.clone(),
+                compactor: config.compactor.clone(),
+                client_keys: config.client_keys.clone(),
+                transforms: ResponsePipeline::default(),
+                index_status: config.index_status.clone(),
+                index_queue: config.index_queue.clone(),
+                experiment: None,
             }),
             config,
         }
@@ -308,19 +840,13 @@ impl ProxyServer {
         router_config: RouterConfig,
     ) -> Self {
         // Use the RLM backend for the engine.
-        let engine = default_engine(
-            rlm_backend,
-            tools,
-            config.budget.clone(),
-            config.work_dir.clone(),
-        );
+        let engine = Self::create_engine(&config, rlm_backend, tools);
 
         // Use the router backend for routing decisions.
-        let router = RlmRouter::with_config(router_config).with_llm(router_backend);
-        let mut passthrough = Passthrough::with_config(config.passthrough.clone());
-        if let Some(tm) = &config.token_manager {
-            passthrough = passthrough.with_token_manager(tm.clone());
-        }
+        let router = RlmRouter::with_config(router_config)
+            .with_llm(router_backend)
+            .with_correction_promotion(config.promote_on_correction);
+        let passthrough = Self::create_passthrough(&config);
         let trace_writer = Self::create_trace_writer(&config);
         Self {
             state: Arc::new(ProxyState {
@@ -328,7 +854,37 @@ impl ProxyServer {
                 router: Some(router),
                 passthrough,
                 trace_writer,
-                session_dir: config.session_dir.clone(),
+                spend_ledger: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(SpendLedger::in_dir(dir))),
+                exploration_reports: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(ExplorationReports::in_dir(dir))),
+                feedback: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(FeedbackLog::in_dir(dir))),
+                routing_log: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(RoutingLog::in_dir(dir))),
+                raw_request_log: Self::create_raw_request_log(&config),
+                session_recorder: Self::create_session_recorder(&config),
+                stats: Arc::new(RouteStats::new()),
+                notifier: config
+                    .notifications
+                    .clone()
+                    .map(|c| Arc::new(Notifier::new(c))),
+                chat_translation: config.chat_translation.clone(),
+                internal_answers: config.internal_answers.clone(),
+                compactor: config.compactor.clone(),
+                client_keys: config.client_keys.clone(),
+                transforms: ResponsePipeline::default(),
+                index_status: config.index_status.clone(),
+                index_queue: config.index_queue.clone(),
+                experiment: None,
             }),
             config,
         }
@@ -336,11 +892,8 @@ impl ProxyServer {
 
     /// Create a proxy with an existing engine (any [`MuninnEngine`] impl).
     pub fn with_engine(config: ProxyConfig, engine: Arc<dyn MuninnEngine>) -> Self {
-        let router = RlmRouter::new();
-        let mut passthrough = Passthrough::with_config(config.passthrough.clone());
-        if let Some(tm) = &config.token_manager {
-            passthrough = passthrough.with_token_manager(tm.clone());
-        }
+        let router = RlmRouter::new().with_correction_promotion(config.promote_on_correction);
+        let passthrough = Self::create_passthrough(&config);
         let trace_writer = Self::create_trace_writer(&config);
         Self {
             state: Arc::new(ProxyState {
@@ -348,18 +901,106 @@ impl ProxyServer {
                 router: Some(router),
                 passthrough,
                 trace_writer,
-                session_dir: config.session_dir.clone(),
+                spend_ledger: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(SpendLedger::in_dir(dir))),
+                exploration_reports: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(ExplorationReports::in_dir(dir))),
+                feedback: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(FeedbackLog::in_dir(dir))),
+                routing_log: config
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| Arc::new(RoutingLog::in_dir(dir))),
+                raw_request_log: Self::create_raw_request_log(&config),
+                session_recorder: Self::create_session_recorder(&config),
+                stats: Arc::new(RouteStats::new()),
+                notifier: config
+                    .notifications
+                    .clone()
+                    .map(|c| Arc::new(Notifier::new(c))),
+                chat_translation: config.chat_translation.clone(),
+                internal_answers: config.internal_answers.clone(),
+                compactor: config.compactor.clone(),
+                client_keys: config.client_keys.clone(),
+                transforms: ResponsePipeline::default(),
+                index_status: config.index_status.clone(),
+                index_queue: config.index_queue.clone(),
+                experiment: None,
             }),
             config,
         }
     }
 
+    /// Rewrite responses with `transform` before they reach the agent, on
+    /// both the RLM and passthrough paths (see [`crate::response_transform`]).
+    pub fn with_response_transform(self, transform: impl ResponseTransform + 'static) -> Self {
+        self.state.transforms.push(Arc::new(transform));
+        self
+    }
+
+    /// Make `Llm`-strategy routing decisions with `backend` instead of the
+    /// router LLM. Has no effect in passthrough-only mode or after
+    /// [`router`](Self::router) has been called.
+    pub fn with_router_backend(mut self, backend: Arc<dyn RouterBackend>) -> Self {
+        if let Some(state) = Arc::get_mut(&mut self.state)
+            && let Some(router) = state.router.take()
+        {
+            state.router = Some(router.with_backend(backend));
+        }
+        self
+    }
+
+    /// Split routed requests between the variants of `experiment`, each
+    /// using its own router and engine where it has one. Has no effect in
+    /// passthrough-only mode or after [`router`](Self::router) has been
+    /// called.
+    pub fn with_experiment(mut self, experiment: Experiment) -> Self {
+        if let Some(state) = Arc::get_mut(&mut self.state)
+            && state.engine.is_some()
+        {
+            tracing::info!(
+                experiment = experiment.name(),
+                variants = experiment.variants().len(),
+                "Running experiment"
+            );
+            state.experiment = Some(experiment);
+        }
+        self
+    }
+
     /// Build the axum router for the proxy.
     pub fn router(&self) -> AxumRouter {
         let mut router = AxumRouter::new()
             .route("/v1/messages", post(handle_messages))
             .route("/v1/chat/completions", post(handle_openai_chat))
+            .route("/feedback", post(handle_feedback));
+        // Export the sessions next to this one (`.muninn/sessions/`). They
+        // are merged after the CORS layer so other origins can't read them,
+        // and with client keys only admin keys may read them.
+        let mut exports = self
+            .config
+            .session_dir
+            .as_deref()
+            .and_then(|d| d.parent())
+            .map(session_export_router::<Arc<ProxyState>>);
+        if self.state.client_keys.is_some() {
+            let auth = middleware::from_fn_with_state(self.state.clone(), require_client_key);
+            router = router.route_layer(auth.clone());
+            exports = exports.map(|exports| {
+                exports
+                    .route_layer(middleware::from_fn(require_admin_key))
+                    .route_layer(auth)
+            });
+        }
+        let mut router = router
             .route("/health", axum::routing::get(handle_health))
+            .route("/statusline", axum::routing::get(handle_statusline))
             .with_state(self.state.clone());
 
         if self.config.enable_cors {
@@ -370,6 +1011,9 @@ impl ProxyServer {
                     .allow_headers(Any),
             );
         }
+        if let Some(exports) = exports {
+            router = router.merge(exports.with_state(self.state.clone()));
+        }
 
         if self.config.enable_tracing {
             router = router.layer(TraceLayer::new_for_http());
@@ -409,13 +1053,28 @@ impl ProxyServer {
 /// This endpoint bypasses the router entirely and forwards requests directly
 /// to Claude using OAuth tokens. Use this to "raw dog" the proxy with your
 /// Claude MAX subscription credits as a simple OpenAI-compatible API.
+///
+/// With [`ChatTranslation::Anthropic`] the body is instead translated to a
+/// Messages request and goes through the router like `/v1/messages`.
 async fn handle_openai_chat(
     State(state): State<Arc<ProxyState>>,
+    client: Option<Extension<ClientIdentity>>,
     headers: HeaderMap,
     body: String,
+) -> axum::response::Response {
+    let ctx = RequestContext::from_headers(&headers).with_client(client);
+    let result = openai_chat(&state, &headers, &body, &ctx).await;
+    with_trace_id_header(result, &ctx.trace_id)
+}
+
+async fn openai_chat(
+    state: &ProxyState,
+    headers: &HeaderMap,
+    body: &str,
+    ctx: &RequestContext,
 ) -> Result<axum::response::Response, ProxyError> {
     // Parse body as raw JSON
-    let raw_request: serde_json::Value = serde_json::from_str(&body)
+    let raw_request: serde_json::Value = serde_json::from_str(body)
         .map_err(|e| RlmError::InvalidRequest(format!("Invalid JSON: {}", e)))?;
 
     // Extract streaming flag
@@ -424,8 +1083,12 @@ async fn handle_openai_chat(
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    if let ChatTranslation::Anthropic { default_model } = &state.chat_translation {
+        return translated_chat(state, headers, &raw_request, default_model, ctx).await;
+    }
+
     // Extract API key from headers for fallback
-    let api_key = extract_api_key(&headers, state.passthrough.config());
+    let api_key = extract_api_key(headers, state.passthrough.config());
 
     tracing::debug!(
         streaming = is_streaming,
@@ -433,32 +1096,96 @@ async fn handle_openai_chat(
         "OpenAI-compatible direct passthrough"
     );
 
-    // Forward directly via passthrough (bypass router entirely)
+    // Forward directly via passthrough (bypass router entirely). The
+    // bodies are Chat Completions, so response transforms don't apply.
     forward_passthrough(
-        &state.passthrough,
+        state,
         raw_request,
         api_key.as_deref(),
         is_streaming,
+        false,
+        ctx,
     )
     .await
 }
 
+/// Serve a Chat Completions request through the `/v1/messages` path,
+/// translating the request and the reply.
+async fn translated_chat(
+    state: &ProxyState,
+    headers: &HeaderMap,
+    chat_request: &serde_json::Value,
+    default_model: &str,
+    ctx: &RequestContext,
+) -> Result<axum::response::Response, ProxyError> {
+    let wants_stream = chat_request
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let request = openai_compat::chat_request_to_messages(chat_request, default_model)?;
+    tracing::debug!(
+        model = %request["model"],
+        streaming = wants_stream,
+        "Translated OpenAI chat request"
+    );
+
+    let response = match messages(state, headers, &request.to_string(), ctx).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    };
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| RlmError::Backend(format!("Failed to read response: {}", e)))?;
+    let body: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| RlmError::Backend(format!("Upstream returned invalid JSON: {}", e)))?;
+    if !status.is_success() {
+        return Ok((status, Json(openai_compat::error_to_chat(&body))).into_response());
+    }
+
+    let completion = openai_compat::messages_response_to_chat(&body);
+    if !wants_stream {
+        return Ok(Json(completion).into_response());
+    }
+    axum::response::Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(axum::body::Body::from(
+            openai_compat::chat_completion_to_sse(&completion),
+        ))
+        .map_err(|e| RlmError::Backend(format!("Failed to build response: {}", e)).into())
+}
+
 /// Handle POST /v1/messages
 ///
 /// This handler accepts raw JSON to support passthrough of all content types
 /// (including thinking blocks, images, etc.) that may not be in our type definitions.
 async fn handle_messages(
     State(state): State<Arc<ProxyState>>,
+    client: Option<Extension<ClientIdentity>>,
     headers: HeaderMap,
     body: String,
+) -> axum::response::Response {
+    let ctx = RequestContext::from_headers(&headers).with_client(client);
+    let result = messages(&state, &headers, &body, &ctx).await;
+    with_trace_id_header(result, &ctx.trace_id)
+}
+
+async fn messages(
+    state: &ProxyState,
+    headers: &HeaderMap,
+    body: &str,
+    ctx: &RequestContext,
 ) -> Result<axum::response::Response, ProxyError> {
     let request_start = Instant::now();
+    let trace_id = ctx.trace_id.as_str();
 
     // Extract API key from request headers for passthrough
-    let api_key = extract_api_key(&headers, state.passthrough.config());
+    let api_key = extract_api_key(headers, state.passthrough.config());
 
     // Parse body as raw JSON first
-    let raw_request: serde_json::Value = serde_json::from_str(&body)
+    let mut raw_request: serde_json::Value = serde_json::from_str(body)
         .map_err(|e| RlmError::InvalidRequest(format!("Invalid JSON: {}", e)))?;
 
     // Extract model and streaming flag for logging/routing
@@ -479,45 +1206,99 @@ async fn handle_messages(
         .map(|a| a.len())
         .unwrap_or(0);
 
-    // Log raw request to file for debugging
-    {
-        // Use session directory if available, otherwise fall back to legacy path
-        let log_path = if let Some(ref session_dir) = state.session_dir {
-            session_dir.join("raw_requests.jsonl")
-        } else {
-            let log_dir = std::path::Path::new(".muninn/debug");
-            std::fs::create_dir_all(log_dir).ok();
-            log_dir.join("raw_requests.jsonl")
-        };
+    if let Some(log) = &state.raw_request_log {
+        log.record(&model, message_count, &raw_request);
+    }
+    if let Some(queue) = &state.index_queue {
+        queue_tool_paths(queue, &raw_request);
+    }
 
-        if let Ok(mut file) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)
-        {
-            use std::io::Write;
-            let timestamp = chrono::Utc::now().to_rfc3339();
-            let log_entry = serde_json::json!({
-                "timestamp": timestamp,
-                "model": model,
-                "message_count": message_count,
-                "request": raw_request
-            });
-            writeln!(file, "{}", serde_json::to_string(&log_entry).unwrap()).ok();
+    // `@muninn good` / `@muninn bad` rates the previous request and is
+    // answered locally; any other user request is the one rated next
+    if let Some(log) = &state.feedback
+        && let Ok(request) = serde_json::from_str::<CompletionRequest>(body)
+        && !request.is_recursive()
+    {
+        if let Some(feedback) = router::feedback_request(&request) {
+            let text = match log.rate(feedback, None) {
+                Some(entry) => format!(
+                    "Recorded: the previous answer was {} (trace {}).",
+                    entry.rating.as_str(),
+                    entry.trace_id
+                ),
+                None => "Nothing to rate yet: no earlier request in this session.".to_string(),
+            };
+            return local_response(internal_answers::text_response(text), is_streaming);
         }
+        if router::internal_request(&request).is_none() {
+            log.remember(trace_id, router::user_question(&request));
+        }
+    }
+
+    // Title and suggestion requests are answered locally when configured
+    if let Some(answers) = &state.internal_answers
+        && let Ok(request) = serde_json::from_str::<CompletionRequest>(body)
+        && !request.is_recursive()
+        && let Some(kind) = router::internal_request(&request)
+    {
+        let response = answers.answer(kind, &request).await;
+        tracing::debug!(?kind, model = %response.model, "Answered internal request locally");
+        state.record_request(|| {
+            let mut recorded = RecordedRequest::new(
+                raw_request.clone(),
+                ROUTE_PASSTHROUGH,
+                Some("internal_local".into()),
+            );
+            recorded.answer = Some(response.text());
+            recorded
+        });
+        return local_response(response, is_streaming);
     }
 
+    // Long conversations are compacted before routing; the response
+    // reports the edit
+    let mut compacted = None;
+    let compaction_start = Instant::now();
+    if let Some(compactor) = &state.compactor
+        && let Some(compaction) = compactor.compact(&mut raw_request).await
+    {
+        compacted = Some((
+            raw_request.to_string(),
+            RequestContext {
+                compaction: Some(Arc::new(compaction)),
+                ..ctx.clone()
+            },
+        ));
+    }
+    let compaction_ms = state
+        .compactor
+        .as_ref()
+        .map(|_| compaction_start.elapsed().as_millis() as u64);
+    let (body, ctx) = match &compacted {
+        Some((body, ctx)) => (body.as_str(), ctx),
+        None => (body, ctx),
+    };
+
     // If no RLM engine available, always passthrough using raw JSON
     let (engine, router) = match (&state.engine, &state.router) {
         (Some(e), Some(r)) => (e, r),
         _ => {
             // Passthrough-only mode - use raw JSON forwarding
             tracing::debug!("Passthrough (no RLM backend)");
+            state.record_request(|| {
+                RecordedRequest::new(
+                    raw_request.clone(),
+                    ROUTE_PASSTHROUGH,
+                    Some("no_engine".into()),
+                )
+            });
             return forward_passthrough(
-                &state.passthrough,
+                state,
                 raw_request,
                 api_key.as_deref(),
                 is_streaming,
+                true,
+                ctx,
             )
             .await;
         }
@@ -525,7 +1306,7 @@ async fn handle_messages(
 
     // For RLM routing, try to parse into CompletionRequest
     // If parsing fails (unknown content types), fall back to passthrough
-    let typed_request = match serde_json::from_str::<CompletionRequest>(&body) {
+    let typed_request = match serde_json::from_str::<CompletionRequest>(body) {
         Ok(r) => {
             // Log the parsed message content for debugging
             if let Some(last_msg) = r
@@ -545,21 +1326,54 @@ async fn handle_messages(
         Err(e) => {
             // Can't parse into our types - use passthrough
             tracing::debug!(error = %e, "Request parse failed, using passthrough");
+            state.record_request(|| {
+                RecordedRequest::new(
+                    raw_request.clone(),
+                    ROUTE_PASSTHROUGH,
+                    Some("unparsed".into()),
+                )
+            });
             return forward_passthrough(
-                &state.passthrough,
+                state,
                 raw_request,
                 api_key.as_deref(),
                 is_streaming,
+                true,
+                ctx,
             )
             .await;
         }
     };
 
+    // Experiment variants swap in their own router and engine
+    let mut assigned_ctx = None;
+    let (engine, router) = match &state.experiment {
+        Some(experiment) => match experiment.assign(trace_id) {
+            Some(variant) => {
+                let mut variant_ctx = ctx.clone();
+                variant_ctx.experiment = Some(experiment.assignment(variant));
+                assigned_ctx = Some(variant_ctx);
+                (
+                    variant.engine.as_ref().unwrap_or(engine),
+                    variant.router.as_ref().unwrap_or(router),
+                )
+            }
+            None => (engine, router),
+        },
+        None => (engine, router),
+    };
+    let ctx = assigned_ctx.as_ref().unwrap_or(ctx);
+
     // First check for explicit muninn.recursive flag
     let explicit_recursive = typed_request.is_recursive();
 
-    // Use with_tracing to collect trace data for RLM requests
-    let (result, trace) = muninn_tracing::with_tracing(async {
+    // Filled in on the RLM path, for the exploration report
+    let question = typed_request.question();
+    let mut exploration_outcome = None;
+
+    // Collect trace data for RLM requests; errors and panics are recorded in
+    // the trace, which is written either way.
+    let (result, trace) = muninn_tracing::with_tracing_result(Some(trace_id.to_string()), async {
         // Record request metadata
         let request_data = ProxyRequestTraceData {
             model: model.clone(),
@@ -567,57 +1381,156 @@ async fn handle_messages(
             explicit_recursive,
             message_count,
         };
-        muninn_tracing::start_span_with_data("proxy_request", &request_data);
+        if let Some(parent) = &ctx.traceparent {
+            muninn_tracing::set_trace_parent(parent);
+        }
+        let span = muninn_tracing::span_with_data("proxy_request", &request_data);
+        // Time before handling started, compaction aside
+        let waited_ms = request_start.elapsed().as_millis() as u64;
+        span.timing_segment(
+            TIMING_QUEUE_WAIT,
+            waited_ms.saturating_sub(compaction_ms.unwrap_or(0)),
+        );
+        if let Some(ms) = compaction_ms {
+            span.timing_segment(TIMING_COMPACTION, ms);
+        }
+        if !ctx.tags.is_empty() {
+            muninn_tracing::add_metadata("tags", &ctx.tags);
+        }
+        if let Some(client) = &ctx.client {
+            muninn_tracing::add_metadata(CLIENT_METADATA, client);
+        }
+        if let Some(assignment) = &ctx.experiment {
+            muninn_tracing::add_metadata(EXPERIMENT_METADATA, assignment);
+        }
+        let server_tools = typed_request.server_tools();
+        if !server_tools.is_empty() {
+            muninn_tracing::add_metadata(SERVER_TOOLS_METADATA, &server_tools);
+        }
 
         // If not explicitly set, use router to decide
-        let trace_id = muninn_tracing::current_trace_id().unwrap_or_default();
-        let should_use_rlm = if explicit_recursive {
+        let rlm_route = if explicit_recursive {
             tracing::debug!(trace_id = %trace_id, "RLM request (explicit)");
-            true
+            Some(("explicit".to_string(), None))
         } else {
-            let decision = router.route(&typed_request).await;
-            match &decision {
+            let routing_start = Instant::now();
+            let (decision, router_trace) = router.route_traced(&typed_request).await;
+            span.timing_segment(TIMING_ROUTING, routing_start.elapsed().as_millis() as u64);
+            // A trigger overruling the previous decision relabels it in
+            // the routing log, and may bias the session toward RLM
+            if let Some(log) = &state.routing_log
+                && let Some(correction) = log.log(trace_id, &router_trace)
+            {
+                tracing::info!(
+                    corrected = %correction.trace_id,
+                    decision = %correction.decision,
+                    "User corrected the previous routing decision"
+                );
+                if correction.decision == "rlm" {
+                    router.promote();
+                }
+            }
+            match decision {
                 RouteDecision::Passthrough => {
                     tracing::debug!(trace_id = %trace_id, "Passthrough request");
-                    false
+                    None
                 }
-                RouteDecision::Rlm { .. } => {
-                    tracing::debug!(trace_id = %trace_id, "RLM request (routed)");
-                    true
+                RouteDecision::Rlm { reason, category } => {
+                    tracing::debug!(trace_id = %trace_id, ?category, "RLM request (routed)");
+                    Some((reason, category))
                 }
             }
         };
 
-        if should_use_rlm {
+        if let Some((reason, category)) = rlm_route {
             // Use configured backend (Groq/local) for recursive exploration
             let mut request = typed_request;
             let muninn = request.muninn.get_or_insert_with(MuninnConfig::default);
             muninn.recursive = true;
-            match engine.complete(request).await {
+            if let Some(category) = category {
+                muninn.category = Some(category.to_string());
+            }
+            muninn.visible_files = visible_files(&raw_request);
+            state.stats.record_route("rlm");
+            let exploration_start = Instant::now();
+            let result = match &ctx.tool_grant {
+                Some(granted) => with_tool_grant(granted.clone(), engine.complete(request)).await,
+                None => engine.complete(request).await,
+            };
+            span.timing_segment(
+                TIMING_EXPLORATION,
+                exploration_start.elapsed().as_millis() as u64,
+            );
+            exploration_outcome = Some(match &result {
+                Ok(response) => Ok(response.text()),
+                Err(e) => Err(e.to_string()),
+            });
+            state.record_request(|| {
+                let mut recorded =
+                    RecordedRequest::new(raw_request.clone(), ROUTE_RLM, Some(reason));
+                match &result {
+                    Ok(response) => recorded.answer = Some(response.text()),
+                    Err(e) => recorded.error = Some(e.to_string()),
+                }
+                recorded
+            });
+            match result {
                 Ok(response) => {
+                    if let Some(notifier) = &state.notifier {
+                        notifier.backend_ok("rlm");
+                    }
+                    let usage = TokenUsage::from(&response.usage);
+                    muninn_tracing::add_metadata("usage", usage);
+                    let quality = response.muninn.as_ref().and_then(|m| m.quality_score);
+                    state.record_spend(ctx, &response.model, "rlm", false, usage, quality);
                     let completion_data = ProxyCompletionTraceData {
                         handling: "rlm".to_string(),
                         success: true,
                         error: None,
                         total_time_ms: request_start.elapsed().as_millis() as u64,
                     };
-                    muninn_tracing::record_event("proxy_completion", Some(&completion_data));
-                    muninn_tracing::end_span_ok();
-                    Ok(Json(response).into_response())
+                    span.record_event("proxy_completion", Some(&completion_data));
+                    let serialization_start = Instant::now();
+                    let response = if state.transforms.is_empty() && ctx.compaction.is_none() {
+                        Json(response).into_response()
+                    } else {
+                        let body = serde_json::to_value(&response).map_err(RlmError::from)?;
+                        let mut body = state
+                            .transforms
+                            .apply(body, ctx.transform_context(ResponseSource::Rlm, false))
+                            .await?;
+                        if let Some(compaction) = &ctx.compaction {
+                            compaction.annotate(&mut body);
+                        }
+                        Json(body).into_response()
+                    };
+                    span.timing_segment(
+                        TIMING_SERIALIZATION,
+                        serialization_start.elapsed().as_millis() as u64,
+                    );
+                    span.ok();
+                    Ok(response)
                 }
                 Err(e) => {
+                    state.stats.rlm_errors.fetch_add(1, Ordering::Relaxed);
+                    if let Some(notifier) = &state.notifier {
+                        notifier.rlm_error(ctx.trace_id.as_str(), &e);
+                    }
                     let completion_data = ProxyCompletionTraceData {
                         handling: "rlm".to_string(),
                         success: false,
                         error: Some(e.to_string()),
                         total_time_ms: request_start.elapsed().as_millis() as u64,
                     };
-                    muninn_tracing::record_event("proxy_completion", Some(&completion_data));
-                    muninn_tracing::end_span_error(e.to_string());
+                    span.record_event("proxy_completion", Some(&completion_data));
+                    span.error(e.to_string());
                     Err(ProxyError::from(e))
                 }
             }
         } else {
+            state.record_request(|| {
+                RecordedRequest::new(raw_request.clone(), ROUTE_PASSTHROUGH, None)
+            });
             // Passthrough - don't trace the actual passthrough request
             let completion_data = ProxyCompletionTraceData {
                 handling: "passthrough".to_string(),
@@ -625,13 +1538,15 @@ async fn handle_messages(
                 error: None,
                 total_time_ms: request_start.elapsed().as_millis() as u64,
             };
-            muninn_tracing::record_event("proxy_completion", Some(&completion_data));
-            muninn_tracing::end_span_ok();
+            span.record_event("proxy_completion", Some(&completion_data));
+            span.ok();
             forward_passthrough(
-                &state.passthrough,
+                state,
                 raw_request,
                 api_key.as_deref(),
                 is_streaming,
+                true,
+                ctx,
             )
             .await
         }
@@ -644,42 +1559,109 @@ async fn handle_messages(
             tracing::warn!(trace_id = %trace.trace_id, error = %e, "Failed to write trace");
         }
     }
+    if let Some(reports) = &state.exploration_reports
+        && let Some(outcome) = exploration_outcome
+        && let Some(report) = ExplorationReport::from_trace(&trace)
+    {
+        reports.write(&report.with_question(question).with_outcome(outcome));
+    }
 
     result
 }
 
 /// Forward a request through passthrough, handling both streaming and non-streaming.
+///
+/// `transform` applies the response transforms, which expect Messages API
+/// responses.
 async fn forward_passthrough(
-    passthrough: &Passthrough,
+    state: &ProxyState,
     request: serde_json::Value,
     api_key: Option<&str>,
     is_streaming: bool,
+    transform: bool,
+    ctx: &RequestContext,
 ) -> Result<axum::response::Response, ProxyError> {
-    use axum::body::Body;
-    use futures::StreamExt;
+    let forward = forward_upstream(state, request, api_key, is_streaming, transform, ctx);
+    if muninn_tracing::is_tracing_active() {
+        forward.await
+    } else {
+        // Untraced requests still carry the trace context upstream
+        muninn_tracing::with_trace_parent(ctx.trace_parent(), forward).await
+    }
+}
 
-    if is_streaming {
-        // For streaming requests, get the raw response and stream it back
-        let upstream_response = passthrough.forward_raw_stream(request, api_key).await?;
+async fn forward_upstream(
+    state: &ProxyState,
+    request: serde_json::Value,
+    api_key: Option<&str>,
+    is_streaming: bool,
+    transform: bool,
+    ctx: &RequestContext,
+) -> Result<axum::response::Response, ProxyError> {
+    use axum::body::Body;
 
-        // Get headers from upstream response
-        let content_type = upstream_response
-            .headers()
-            .get(reqwest::header::CONTENT_TYPE)
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("text/event-stream")
-            .to_string();
+    let overridden;
+    let passthrough = match &ctx.upstream {
+        Some(url) => {
+            overridden = state.passthrough.for_upstream(url)?;
+            tracing::debug!(upstream = %url, "Using per-request upstream");
+            &overridden
+        }
+        None => &state.passthrough,
+    };
+    let model = request
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    state.stats.record_route("passthrough");
 
-        // Convert reqwest body stream to axum body
-        let stream = upstream_response
-            .bytes_stream()
-            .map(|result| result.map_err(std::io::Error::other));
+    if is_streaming {
+        // For streaming requests, get the upstream body and stream it back
+        // through the inspector, which validates framing, repairs truncated
+        // streams, and picks usage out of the events for accounting.
+        let upstream =
+            state.upstream_result(passthrough.forward_raw_stream(request, api_key).await)?;
+        let retries = upstream.retries;
+        let writer = state.trace_writer.clone();
+        let ledger = state.spend_ledger.clone();
+        let route_stats = state.stats.clone();
+        let notifier = state.notifier.clone();
+        let client_keys = state.client_keys.clone();
+        let transform_ctx = ctx.transform_context(ResponseSource::Passthrough, true);
+        let compaction = ctx.compaction.clone();
+        let ctx = ctx.clone();
+        let stream = SseInspector::new(upstream.body).on_finish(move |stats| {
+            route_stats.record_usage(&stats.usage);
+            if let Some(notifier) = notifier {
+                notifier.record_usage(&stats.usage);
+            }
+            if let (Some(keys), Some(client)) = (client_keys, &ctx.client) {
+                keys.record_usage(client, stats.usage.total());
+            }
+            if let Some(ledger) = ledger {
+                let model = stats.model.clone().unwrap_or(model);
+                ledger.record(
+                    &SpendEntry::new(&ctx.trace_id, model, "passthrough", true, stats.usage)
+                        .with_tags(ctx.tags.clone())
+                        .with_client(ctx.client.clone()),
+                );
+            }
+            write_stream_trace(writer.as_deref(), ctx, stats, retries);
+        });
+        let mut stream: UpstreamBodyStream = Box::pin(stream);
+        if transform {
+            stream = state.transforms.apply_stream(stream, &transform_ctx);
+        }
+        if let Some(compaction) = &compaction {
+            stream = transform_stream(stream, compaction.stream());
+        }
         let body = Body::from_stream(stream);
 
         // Build response with SSE content type
         let response = axum::response::Response::builder()
             .status(StatusCode::OK)
-            .header("content-type", content_type)
+            .header("content-type", upstream.content_type)
             .header("cache-control", "no-cache")
             .body(body)
             .map_err(|e| RlmError::Backend(format!("Failed to build response: {}", e)))?;
@@ -687,19 +1669,200 @@ async fn forward_passthrough(
         Ok(response)
     } else {
         // Non-streaming: parse as JSON
-        let response = passthrough.forward_raw(request, api_key).await?;
+        let response = state.upstream_result(passthrough.forward_raw(request, api_key).await)?;
+        if let Some(usage) = response.get("usage") {
+            let usage = TokenUsage::from_json(usage);
+            muninn_tracing::add_metadata("usage", usage);
+            let model = response
+                .get("model")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&model);
+            state.record_spend(ctx, model, "passthrough", false, usage, None);
+        }
+        let server_tool_uses = server_tool_uses(&response);
+        if !server_tool_uses.is_empty() {
+            muninn_tracing::add_metadata(SERVER_TOOL_USE_METADATA, server_tool_uses);
+        }
+        let mut response = response;
+        if transform {
+            response = state
+                .transforms
+                .apply(
+                    response,
+                    ctx.transform_context(ResponseSource::Passthrough, false),
+                )
+                .await?;
+        }
+        if let Some(compaction) = &ctx.compaction {
+            compaction.annotate(&mut response);
+        }
         Ok(Json(response).into_response())
     }
 }
 
-/// Extract API key from request headers based on passthrough config.
-fn extract_api_key(headers: &HeaderMap, config: &PassthroughConfig) -> Option<String> {
-    // Try the configured auth header first
-    if let Some(value) = headers.get(&config.auth_header) {
-        if let Ok(s) = value.to_str() {
-            // Strip "Bearer " prefix if present (for OpenAI-style auth)
-            let key = s.strip_prefix("Bearer ").unwrap_or(s);
-            return Some(key.to_string());
+/// Response header carrying the request's trace ID.
+pub const TRACE_ID_HEADER: &str = "x-muninn-trace-id";
+
+/// Request header with comma-separated tags (e.g. `refactor,experiment-b`)
+/// recorded in trace metadata and the spend ledger.
+pub const TAGS_HEADER: &str = "x-muninn-tags";
+
+/// Per-request identity shared by the RLM and passthrough paths.
+#[derive(Debug, Clone)]
+struct RequestContext {
+    trace_id: String,
+    tags: Vec<String>,
+    /// Incoming W3C trace context, if the caller sent a valid one.
+    traceparent: Option<TraceParent>,
+    /// Upstream base URL requested with [`UPSTREAM_URL_HEADER`].
+    upstream: Option<String>,
+    /// Experiment variant the request was assigned to.
+    experiment: Option<Assignment>,
+    /// Compaction applied to the request's conversation.
+    compaction: Option<Arc<Compaction>>,
+    /// Name of the client key the request was admitted with.
+    client: Option<String>,
+    /// Tool categories the client key is granted; `None` grants all.
+    tool_grant: Option<Vec<ToolCategory>>,
+}
+
+impl RequestContext {
+    /// Pick up the trace context and tags from the request headers. The
+    /// trace ID comes from `traceparent` when present, else a fresh UUID.
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let traceparent = headers
+            .get(muninn_tracing::TRACEPARENT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(TraceParent::parse);
+        Self {
+            trace_id: traceparent
+                .as_ref()
+                .map(|tp| tp.trace_id.clone())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            tags: parse_tags(headers),
+            traceparent,
+            upstream: headers
+                .get(UPSTREAM_URL_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            experiment: None,
+            compaction: None,
+            client: None,
+            tool_grant: None,
+        }
+    }
+
+    /// Attribute the request to the client key it was admitted with.
+    fn with_client(mut self, client: Option<Extension<ClientIdentity>>) -> Self {
+        if let Some(Extension(identity)) = client {
+            self.client = Some(identity.name);
+            self.tool_grant = identity.tools;
+        }
+        self
+    }
+
+    /// What response transforms see of this request.
+    fn transform_context(&self, source: ResponseSource, streaming: bool) -> TransformContext {
+        TransformContext {
+            trace_id: self.trace_id.clone(),
+            source,
+            streaming,
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Trace context to forward on requests made outside a trace scope: the
+    /// caller's unchanged, or a new one rooted at this request's trace ID.
+    fn trace_parent(&self) -> TraceParent {
+        self.traceparent.clone().unwrap_or_else(|| {
+            TraceParent::new(&self.trace_id, &uuid::Uuid::new_v4().to_string(), true)
+        })
+    }
+}
+
+/// Collect tags from every `x-muninn-tags` header, trimmed and deduplicated
+/// in first-seen order.
+fn parse_tags(headers: &HeaderMap) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for value in headers.get_all(TAGS_HEADER) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for tag in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+    }
+    tags
+}
+
+/// Render a handler result and tag it with the trace ID header, so odd
+/// answers (and errors) can be matched to the trace/session files on disk.
+fn with_trace_id_header(
+    result: Result<axum::response::Response, ProxyError>,
+    trace_id: &str,
+) -> axum::response::Response {
+    let mut response = result.unwrap_or_else(IntoResponse::into_response);
+    if let Ok(value) = axum::http::HeaderValue::from_str(trace_id) {
+        response.headers_mut().insert(TRACE_ID_HEADER, value);
+    }
+    response
+}
+
+/// Write the stats of a finished passthrough stream as its own trace.
+///
+/// Streams outlive the request handler (and its `with_tracing` scope), so
+/// the stats are written separately under the same trace ID.
+fn write_stream_trace(
+    writer: Option<&muninn_tracing::TraceWriter>,
+    ctx: RequestContext,
+    stats: StreamStats,
+    retries: u32,
+) {
+    let Some(writer) = writer else {
+        return;
+    };
+    let mut trace = muninn_tracing::Trace::new(ctx.trace_id)
+        .with_metadata("kind", "passthrough_stream")
+        .with_metadata("usage", stats.usage);
+    if !ctx.tags.is_empty() {
+        trace = trace.with_metadata("tags", ctx.tags);
+    }
+    if let Some(client) = ctx.client {
+        trace = trace.with_metadata(CLIENT_METADATA, client);
+    }
+    if let Some(assignment) = ctx.experiment {
+        trace = trace.with_metadata(EXPERIMENT_METADATA, assignment);
+    }
+    if retries > 0 {
+        trace = trace.with_metadata("upstream_retries", retries);
+    }
+    if !stats.server_tool_uses.is_empty() {
+        trace = trace.with_metadata(SERVER_TOOL_USE_METADATA, &stats.server_tool_uses);
+    }
+    let repaired = stats.repaired;
+    let mut span = muninn_tracing::Span::new("passthrough_stream").with_data(&stats);
+    if repaired {
+        span.complete_error("upstream stream truncated; repaired");
+    } else {
+        span.complete_ok();
+    }
+    trace.add_span(span);
+    trace.complete();
+    if let Err(e) = writer.write(&trace) {
+        tracing::warn!(trace_id = %trace.trace_id, error = %e, "Failed to write stream trace");
+    }
+}
+
+/// Extract API key from request headers based on passthrough config.
+fn extract_api_key(headers: &HeaderMap, config: &PassthroughConfig) -> Option<String> {
+    // Try the configured auth header first
+    if let Some(value) = headers.get(&config.auth_header) {
+        if let Ok(s) = value.to_str() {
+            // Strip "Bearer " prefix if present (for OpenAI-style auth)
+            let key = s.strip_prefix("Bearer ").unwrap_or(s);
+            return Some(key.to_string());
         }
     }
 
@@ -725,6 +1888,146 @@ fn extract_api_key(headers: &HeaderMap, config: &PassthroughConfig) -> Option<St
     None
 }
 
+/// Trace metadata key naming the client key a request was made with.
+pub const CLIENT_METADATA: &str = "client";
+
+/// Trace metadata key listing the server tools a request offered.
+pub const SERVER_TOOLS_METADATA: &str = "server_tools";
+
+/// Trace metadata key counting server tool calls by tool name.
+pub const SERVER_TOOL_USE_METADATA: &str = "server_tool_use";
+
+/// Server tool calls in a Messages response, by tool name.
+fn server_tool_uses(response: &serde_json::Value) -> BTreeMap<String, u64> {
+    let mut uses = BTreeMap::new();
+    for block in response["content"].as_array().into_iter().flatten() {
+        if block["type"] == "server_tool_use" {
+            let name = block["name"].as_str().unwrap_or("unknown").to_string();
+            *uses.entry(name).or_default() += 1;
+        }
+    }
+    uses
+}
+
+/// Admit `/v1` requests carrying a valid client key. The key is swapped
+/// for the upstream key (or dropped, leaving OAuth) before the request is
+/// handled, and the key holder is passed on for attribution.
+async fn require_client_key(
+    State(state): State<Arc<ProxyState>>,
+    mut request: axum::extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    let Some(keys) = &state.client_keys else {
+        return next.run(request).await;
+    };
+    let presented = extract_api_key(request.headers(), state.passthrough.config());
+    match keys.admit(presented.as_deref()) {
+        Ok(identity) => {
+            let headers = request.headers_mut();
+            headers.remove(state.passthrough.config().auth_header.as_str());
+            headers.remove("x-api-key");
+            headers.remove(axum::http::header::AUTHORIZATION);
+            if let Some(key) = keys.upstream_key()
+                && let Ok(value) = HeaderValue::from_str(key)
+            {
+                headers.insert("x-api-key", value);
+            }
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        Err(rejection) => {
+            tracing::debug!(?rejection, "Refused request without a usable client key");
+            let (status, error_type) = if rejection.is_quota() {
+                (StatusCode::TOO_MANY_REQUESTS, "rate_limit_error")
+            } else {
+                (StatusCode::UNAUTHORIZED, "authentication_error")
+            };
+            let body = serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": error_type,
+                    "message": rejection.message()
+                }
+            });
+            (status, Json(body)).into_response()
+        }
+    }
+}
+
+/// Admit requests from keys with the [`ADMIN_ROLE`](crate::client_keys::ADMIN_ROLE).
+/// Runs after [`require_client_key`], which attaches the key holder.
+async fn require_admin_key(
+    request: axum::extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    let admin = request
+        .extensions()
+        .get::<ClientIdentity>()
+        .is_some_and(ClientIdentity::is_admin);
+    if admin {
+        return next.run(request).await;
+    }
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "permission_error",
+            "message": format!(
+                "Exporting sessions needs a client key with the {} role",
+                crate::client_keys::ADMIN_ROLE
+            )
+        }
+    });
+    (StatusCode::FORBIDDEN, Json(body)).into_response()
+}
+
+/// A response the proxy made itself, as JSON or as an event stream.
+fn local_response(
+    response: crate::types::CompletionResponse,
+    is_streaming: bool,
+) -> Result<axum::response::Response, ProxyError> {
+    if !is_streaming {
+        return Ok(Json(response).into_response());
+    }
+    axum::response::Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(axum::body::Body::from(internal_answers::to_sse(&response)))
+        .map_err(|e| RlmError::Backend(format!("Failed to build response: {}", e)).into())
+}
+
+/// Body of `POST /feedback`.
+#[derive(Debug, Deserialize)]
+struct FeedbackBody {
+    #[serde(flatten)]
+    feedback: Feedback,
+    /// Trace to rate; the latest request when omitted.
+    #[serde(default)]
+    trace_id: Option<String>,
+}
+
+/// Handle POST /feedback: rate a request, the latest one by default.
+async fn handle_feedback(
+    State(state): State<Arc<ProxyState>>,
+    Json(body): Json<FeedbackBody>,
+) -> impl IntoResponse {
+    let Some(log) = &state.feedback else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(
+                serde_json::json!({"error": "feedback is only recorded with a session directory"}),
+            ),
+        );
+    };
+    match log.rate(body.feedback, body.trace_id) {
+        Some(entry) => (StatusCode::OK, Json(serde_json::json!(entry))),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "no earlier request to rate; pass a trace_id"})),
+        ),
+    }
+}
+
 /// Handle GET /health
 async fn handle_health() -> impl IntoResponse {
     Json(serde_json::json!({
@@ -733,10 +2036,114 @@ async fn handle_health() -> impl IntoResponse {
     }))
 }
 
+/// Feed the `file_path`/`path` inputs of the agent's recent tool calls to
+/// `queue`, oldest first so the latest call is indexed first.
+fn queue_tool_paths(queue: &IndexQueue, request: &serde_json::Value) {
+    /// Messages from the end of the conversation to scan.
+    const RECENT_MESSAGES: usize = 4;
+    let Some(messages) = request.get("messages").and_then(|m| m.as_array()) else {
+        return;
+    };
+    let blocks = messages
+        .iter()
+        .skip(messages.len().saturating_sub(RECENT_MESSAGES))
+        .filter_map(|m| m.get("content").and_then(|c| c.as_array()))
+        .flatten()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"));
+    for block in blocks {
+        let input = block.get("input");
+        let path = ["file_path", "path"]
+            .iter()
+            .find_map(|key| input.and_then(|i| i.get(key)).and_then(|p| p.as_str()));
+        if let Some(path) = path {
+            queue.push(path);
+        }
+    }
+}
+
+/// Files the agent has just seen: the `file_path` of recent tool calls
+/// whose result came back without an error, most recent first.
+fn visible_files(request: &serde_json::Value) -> Vec<String> {
+    /// Messages from the end of the conversation to scan.
+    const RECENT_MESSAGES: usize = 6;
+    /// Most files to seed an exploration with.
+    const MAX_FILES: usize = 5;
+    let Some(messages) = request.get("messages").and_then(|m| m.as_array()) else {
+        return Vec::new();
+    };
+    let blocks: Vec<&serde_json::Value> = messages
+        .iter()
+        .skip(messages.len().saturating_sub(RECENT_MESSAGES))
+        .filter_map(|m| m.get("content").and_then(|c| c.as_array()))
+        .flatten()
+        .collect();
+    let is_type =
+        |b: &serde_json::Value, ty: &str| b.get("type").and_then(|t| t.as_str()) == Some(ty);
+    let seen: HashSet<&str> = blocks
+        .iter()
+        .filter(|b| is_type(b, "tool_result"))
+        .filter(|b| b.get("is_error").and_then(|e| e.as_bool()) != Some(true))
+        .filter_map(|b| b.get("tool_use_id").and_then(|id| id.as_str()))
+        .collect();
+
+    let mut files: Vec<String> = Vec::new();
+    for block in blocks.iter().rev() {
+        if !is_type(block, "tool_use")
+            || !block
+                .get("id")
+                .and_then(|id| id.as_str())
+                .is_some_and(|id| seen.contains(id))
+        {
+            continue;
+        }
+        let Some(path) = block
+            .get("input")
+            .and_then(|i| i.get("file_path"))
+            .and_then(|p| p.as_str())
+        else {
+            continue;
+        };
+        if !files.iter().any(|f| f == path) {
+            files.push(path.to_string());
+        }
+        if files.len() == MAX_FILES {
+            break;
+        }
+    }
+    files
+}
+
+/// Handle GET /statusline: routing counters plus a one-line summary.
+async fn handle_statusline(State(state): State<Arc<ProxyState>>) -> impl IntoResponse {
+    let mut snapshot = state.stats.snapshot();
+    snapshot.upstream = state.passthrough.connection_stats();
+    if let Some(status) = &state.index_status {
+        let indexing = status.snapshot();
+        if let Some(line) = indexing.line() {
+            snapshot.line.push_str(" · ");
+            snapshot.line.push_str(&line);
+        }
+        snapshot.indexing = Some(indexing);
+    }
+    Json(snapshot)
+}
+
 /// Error type for proxy responses.
 #[derive(Debug)]
 pub struct ProxyError(RlmError);
 
+impl std::fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<muninn_tracing::TracedPanic> for ProxyError {
+    fn from(panic: muninn_tracing::TracedPanic) -> Self {
+        Self(RlmError::Internal(panic.to_string()))
+    }
+}
+
 impl From<RlmError> for ProxyError {
     fn from(err: RlmError) -> Self {
         Self(err)
@@ -823,6 +2230,7 @@ impl IntoResponse for ProxyError {
 mod tests {
     use super::*;
     use crate::backend::MockBackend;
+    use crate::response_transform::{CoalesceToolDeltas, StripThinking, TraceIdAnnotation};
     use crate::router::RouterStrategy;
     use crate::tools::EmptyToolEnvironment;
     use crate::types::{CompletionResponse, ContentBlock, StopReason, Usage};
@@ -861,6 +2269,81 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_session_export_is_not_cross_origin() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = ProxyConfig::default()
+            .with_session_dir(temp.path().join("sessions").join("current"))
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            Arc::new(MockBackend::new(vec![])),
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig::default(),
+        )
+        .router();
+
+        let get = |uri: &str| {
+            router.clone().oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("origin", "https://example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let health = get("/health").await.unwrap();
+        assert!(health.headers().contains_key("access-control-allow-origin"));
+        let sessions = get("/v1/sessions").await.unwrap();
+        assert_eq!(sessions.status(), StatusCode::OK);
+        assert!(
+            !sessions
+                .headers()
+                .contains_key("access-control-allow-origin")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_export_needs_an_admin_key() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = crate::client_keys::ClientKeyFile::in_dir(temp.path());
+        let (member, _) = file
+            .create("alice", Default::default(), Some("reader"))
+            .unwrap();
+        let (admin, _) = file
+            .create(
+                "bob",
+                Default::default(),
+                Some(crate::client_keys::ADMIN_ROLE),
+            )
+            .unwrap();
+        let config = ProxyConfig::default()
+            .with_session_dir(temp.path().join("sessions").join("current"))
+            .with_client_keys(ClientKeys::load(&file).unwrap())
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            Arc::new(MockBackend::new(vec![])),
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig::default(),
+        )
+        .router();
+
+        let get = |key: Option<&str>| {
+            let mut request = Request::builder().uri("/v1/sessions");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+        assert_eq!(get(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            get(Some(&member)).await.unwrap().status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(get(Some(&admin)).await.unwrap().status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn test_messages_endpoint_simple() {
         let responses = vec![CompletionResponse::new(
@@ -946,14 +2429,19 @@ mod tests {
             .unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
+        let trace_id = response.headers()[TRACE_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
 
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
         let parsed: CompletionResponse = serde_json::from_slice(&body).unwrap();
         assert_eq!(parsed.text(), "Explored!");
-        // Should have exploration metadata
-        assert!(parsed.muninn.is_some());
+        // Should have exploration metadata carrying the same trace ID
+        let metadata = parsed.muninn.expect("exploration metadata");
+        assert_eq!(metadata.trace_id.as_deref(), Some(trace_id.as_str()));
     }
 
     #[tokio::test]
@@ -975,6 +2463,179 @@ mod tests {
 
         // Axum returns 400 Bad Request for JSON parsing errors
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // Errors are still tagged so they can be found on disk
+        assert!(response.headers().contains_key(TRACE_ID_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_internal_request_answered_locally() {
+        // No upstream is reachable; a passthrough would fail
+        let mut config = ProxyConfig::default().with_internal_answers(InternalAnswers::template());
+        config.passthrough.base_url = "http://127.0.0.1:9".to_string();
+        let router = ProxyServer::passthrough_only(config).router();
+
+        let request_body = json!({
+            "model": "claude-haiku",
+            "max_tokens": 32,
+            "messages": [{
+                "role": "user",
+                "content": "Please write a 2-3 word title for the following conversation:\nUser: graph queries are slow"
+            }]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let parsed: CompletionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(parsed.text(), "Graph queries are");
+    }
+
+    #[tokio::test]
+    async fn test_feedback_rates_previous_request() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::with_text("It's in daemon.rs"));
+        let config = ProxyConfig::default()
+            .with_session_dir(temp.path())
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            backend,
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysRlm,
+                ..Default::default()
+            },
+        )
+        .router();
+        let send = |uri: &str, body: serde_json::Value| {
+            router.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+        };
+        let message = |text: &str| {
+            json!({
+                "model": "test-model",
+                "max_tokens": 100,
+                "messages": [{"role": "user", "content": text}]
+            })
+        };
+
+        let response = send("/feedback", json!({"rating": "good"})).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let response = send("/v1/messages", message("Where is the socket path built?"))
+            .await
+            .unwrap();
+        let trace_id = response.headers()[TRACE_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let response = send("/v1/messages", message("@muninn bad: wrong file"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let parsed: CompletionResponse = serde_json::from_slice(&body).unwrap();
+        assert!(parsed.text().contains(&trace_id), "{}", parsed.text());
+
+        let response = send(
+            "/feedback",
+            json!({"rating": "good", "trace_id": "t-other"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let entries = FeedbackLog::in_dir(temp.path()).read().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].trace_id, trace_id);
+        assert_eq!(entries[0].rating, crate::feedback::Rating::Bad);
+        assert_eq!(entries[0].comment.as_deref(), Some("wrong file"));
+        assert_eq!(
+            entries[0].question.as_deref(),
+            Some("Where is the socket path built?")
+        );
+        assert_eq!(entries[1].trace_id, "t-other");
+    }
+
+    #[tokio::test]
+    async fn test_openai_chat_translation() {
+        let backend = Arc::new(MockBackend::new(vec![CompletionResponse::new(
+            "msg_1",
+            "claude-test",
+            vec![ContentBlock::Text {
+                text: "Hello!".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        )]));
+        let router_config = RouterConfig {
+            strategy: RouterStrategy::AlwaysRlm,
+            ..Default::default()
+        };
+        let config = ProxyConfig::default().with_chat_translation(ChatTranslation::Anthropic {
+            default_model: "claude-test".to_string(),
+        });
+        let router = ProxyServer::with_router(
+            config,
+            backend.clone(),
+            Arc::new(EmptyToolEnvironment),
+            router_config,
+        )
+        .router();
+
+        let request_body = json!({
+            "model": "gpt-4o",
+            "stream": true,
+            "messages": [
+                {"role": "system", "content": "Be terse."},
+                {"role": "user", "content": "Hi"}
+            ]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .header("authorization", "Bearer test-key")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("\"content\":\"Hello!\""), "{}", body);
+        assert!(body.ends_with("data: [DONE]\n\n"));
+
+        let requests = backend.requests();
+        assert_eq!(requests[0].model, "claude-test");
+        assert!(format!("{:?}", requests[0].messages).contains("Hi"));
     }
 
     #[tokio::test]
@@ -1012,6 +2673,1040 @@ mod tests {
         assert_eq!(parsed["error"]["type"], "backend_error");
     }
 
+    #[tokio::test]
+    async fn test_experiment_variant_answers_and_is_traced() {
+        let answer = |text: &str| {
+            Arc::new(MockBackend::new(vec![CompletionResponse::new(
+                "msg_1",
+                "claude-test",
+                vec![ContentBlock::Text {
+                    text: text.to_string(),
+                    cache_control: None,
+                }],
+                StopReason::EndTurn,
+                Usage::new(10, 5),
+            )]))
+        };
+        let temp = tempfile::tempdir().unwrap();
+        let traces = temp.path().join("traces.jsonl");
+        let config = ProxyConfig::default()
+            .with_trace_writer(muninn_tracing::WriterConfig::session(&traces));
+        let variant_engine = ProxyServer::create_engine(
+            &config,
+            answer("from variant"),
+            Arc::new(EmptyToolEnvironment),
+        );
+        let experiment = Experiment::new("rlm-model")
+            .with_variant(crate::experiment::Variant::new("control", 0))
+            .with_variant(crate::experiment::Variant::new("b", 1).with_engine(variant_engine));
+        let router = ProxyServer::with_router(
+            config,
+            answer("from control"),
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysRlm,
+                ..Default::default()
+            },
+        )
+        .with_experiment(experiment)
+        .router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Where is main?"}]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["content"][0]["text"], "from variant");
+
+        let written = muninn_tracing::TraceWriter::read_traces(&traces).unwrap();
+        assert_eq!(
+            written[0].metadata[EXPERIMENT_METADATA],
+            json!({"experiment": "rlm-model", "variant": "b"})
+        );
+        let mut report = crate::experiment::ExperimentReport::default();
+        report.record_trace(&written[0], Some("rlm-model"));
+        let stats = &report.experiments["rlm-model"]["b"];
+        assert_eq!((stats.requests, stats.rlm), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_client_key_tool_grant() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("secret.txt"), "hunter2").unwrap();
+        let file = crate::client_keys::ClientKeyFile::in_dir(temp.path());
+        let (key, _) = file
+            .create("alice", Default::default(), Some("graph-only"))
+            .unwrap();
+        let keys = ClientKeys::load(&file)
+            .unwrap()
+            .with_roles([("graph-only".to_string(), vec![ToolCategory::Graph])].into());
+
+        let backend = Arc::new(MockBackend::new(vec![
+            CompletionResponse::new(
+                "msg_1",
+                "test-model",
+                vec![ContentBlock::ToolUse {
+                    id: "tool_1".to_string(),
+                    name: "read_file".to_string(),
+                    input: json!({"path": "secret.txt"}),
+                    cache_control: None,
+                }],
+                StopReason::ToolUse,
+                Usage::new(10, 5),
+            ),
+            CompletionResponse::new(
+                "msg_2",
+                "test-model",
+                vec![ContentBlock::text("No access")],
+                StopReason::EndTurn,
+                Usage::new(10, 5),
+            ),
+        ]));
+        let mut tools = crate::tools::ToolRegistry::new();
+        tools.register(crate::fs_tools::ReadFileTool::new(temp.path()));
+        let config = ProxyConfig::default()
+            .with_client_keys(keys)
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            backend.clone(),
+            Arc::new(tools),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysRlm,
+                ..Default::default()
+            },
+        )
+        .router();
+
+        let body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Read secret.txt"}]
+        });
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .header("x-api-key", &key)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // read_file is neither offered nor run for a graph-only key
+        let sent = backend.requests();
+        assert!(sent[0].tools.iter().all(|t| t.name != "read_file"));
+        let followup = serde_json::to_string(&sent[1].messages).unwrap();
+        assert!(followup.contains("not permitted"));
+        assert!(!followup.contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_client_keys_required_and_attributed() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = crate::client_keys::ClientKeyFile::in_dir(temp.path());
+        let quota = crate::client_keys::KeyQuota {
+            daily_tokens: Some(10),
+            ..Default::default()
+        };
+        let (key, _) = file.create("alice", quota, None).unwrap();
+        let backend = Arc::new(MockBackend::with_text("Hi Alice"));
+        let config = ProxyConfig::default()
+            .with_session_dir(temp.path())
+            .with_client_keys(ClientKeys::load(&file).unwrap())
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            backend,
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysRlm,
+                ..Default::default()
+            },
+        )
+        .router();
+
+        let send = |key: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let body = json!({
+                "model": "test-model",
+                "max_tokens": 100,
+                "messages": [{"role": "user", "content": "Hi"}]
+            });
+            router
+                .clone()
+                .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        };
+
+        let response = send(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(Some("mk_wrong")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(Some(&key)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let spend = SpendLedger::in_dir(temp.path()).read().unwrap();
+        assert_eq!(spend.len(), 1);
+        assert_eq!(spend[0].client.as_deref(), Some("alice"));
+
+        // The answer used up the key's 10 daily tokens
+        let response = send(Some(&key)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Health checks need no key
+        let response = router
+            .clone()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_recording_writes_session_requests() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::new(vec![CompletionResponse::new(
+            "msg_1",
+            "claude-test",
+            vec![ContentBlock::Text {
+                text: "It's in src/main.rs".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        )]));
+        let config = ProxyConfig::default()
+            .with_session_dir(temp.path())
+            .with_upstream_recording(true)
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            backend,
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysRlm,
+                ..Default::default()
+            },
+        )
+        .router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Where is main?"}]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let recorded = crate::session_recording::read_session_recording(
+            &temp
+                .path()
+                .join(crate::session_recording::SESSION_RECORDING_FILE),
+        )
+        .unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].route, ROUTE_RLM);
+        assert_eq!(recorded[0].request, request_body);
+        assert_eq!(recorded[0].answer.as_deref(), Some("It's in src/main.rs"));
+
+        let reports = crate::exploration_report::exploration_reports(temp.path());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].question.as_deref(), Some("Where is main?"));
+        let report = std::fs::read_to_string(&reports[0].path).unwrap();
+        assert!(
+            report.contains("## Final answer\n\nIt's in src/main.rs\n"),
+            "{report}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rlm_failure_notifies_webhook() {
+        let received = Arc::new(std::sync::Mutex::new(Vec::<serde_json::Value>::new()));
+        let sink = received.clone();
+        let app = AxumRouter::new().route(
+            "/hook",
+            post(move |Json(body): Json<serde_json::Value>| {
+                let sink = sink.clone();
+                async move {
+                    sink.lock().unwrap().push(body);
+                    StatusCode::OK
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+
+        let config = ProxyConfig::default()
+            .with_notifications(NotifierConfig::new(url).with_unhealthy_after(1));
+        let server = ProxyServer::with_router(
+            config,
+            Arc::new(MockBackend::new(vec![])),
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysRlm,
+                ..Default::default()
+            },
+        );
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+        let response = server
+            .router()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        for _ in 0..50 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let text = received[0]["text"].as_str().unwrap();
+        assert!(
+            text.starts_with("muninn: rlm backend unhealthy"),
+            "{}",
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_conversation_compacted() {
+        // Upstream replies with how many messages it was sent
+        let app = AxumRouter::new().route(
+            "/v1/messages",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                let count = body["messages"].as_array().map_or(0, |m| m.len());
+                Json(json!({
+                    "id": "msg_1",
+                    "type": "message",
+                    "role": "assistant",
+                    "model": "test-model",
+                    "content": [{"type": "text", "text": count.to_string()}],
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 1, "output_tokens": 1}
+                }))
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+
+        let compactor = Compactor::new(Arc::new(MockBackend::with_text("Summary.")), "qwen")
+            .with_threshold(1)
+            .with_keep_recent(1);
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .with_compactor(compactor)
+            .without_agentic_tracing();
+        let router = ProxyServer::passthrough_only(config).router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [
+                {"role": "user", "content": "First question"},
+                {"role": "assistant", "content": "First answer"},
+                {"role": "user", "content": "Second question"}
+            ]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("x-api-key", "test-key")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["content"][0]["text"], "1");
+        let edit = &body["context_management"]["applied_edits"][0];
+        assert_eq!(edit["compacted_messages"], 2);
+        assert_eq!(edit["messages"][0]["content"][1]["text"], "Second question");
+    }
+
+    /// Serve a fixed SSE body from a local upstream and return its base URL.
+    async fn spawn_sse_upstream(body: &'static str) -> String {
+        let app =
+            AxumRouter::new().route(
+                "/v1/messages",
+                post(move || async move {
+                    ([("content-type", "text/event-stream")], body).into_response()
+                }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_response_transforms_rewrite_passthrough_stream() {
+        let upstream = spawn_sse_upstream(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\"}}\n\n\
+             event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n\n\
+             event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n\
+             event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+             event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":1}\n\n\
+             event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        )
+        .await;
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .without_agentic_tracing();
+        let router = ProxyServer::passthrough_only(config)
+            .with_response_transform(StripThinking)
+            .with_response_transform(TraceIdAnnotation)
+            .router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("x-api-key", "test-key")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let trace_id = response.headers()[TRACE_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let mut parser = crate::sse::SseParser::new();
+        let events: Vec<serde_json::Value> = parser
+            .push(&body)
+            .iter()
+            .filter_map(|f| crate::sse::SseEvent::parse(f).json())
+            .collect();
+        assert_eq!(
+            events[0]["message"]["muninn"]["trace_id"],
+            trace_id.as_str()
+        );
+        assert!(!serde_json::to_string(&events).unwrap().contains("thinking"));
+        assert_eq!(events[1]["index"], 0);
+        assert_eq!(events[1]["content_block"]["type"], "text");
+    }
+
+    #[tokio::test]
+    async fn test_upstream_header_selects_allowed_upstream() {
+        async fn spawn_json_upstream(id: &'static str) -> String {
+            let app = AxumRouter::new().route(
+                "/v1/messages",
+                post(move || async move { Json(json!({"id": id})) }),
+            );
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+            format!("http://{}", addr)
+        }
+        let default = spawn_json_upstream("msg_default").await;
+        let gateway = spawn_json_upstream("msg_gateway").await;
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(default).with_allowed_upstream(&gateway))
+            .without_agentic_tracing();
+        let router = ProxyServer::passthrough_only(config).router();
+
+        let send = |upstream: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .header("x-api-key", "test-key");
+            if let Some(upstream) = upstream {
+                request = request.header(UPSTREAM_URL_HEADER, upstream);
+            }
+            let body = json!({"model": "m", "max_tokens": 10, "messages": []});
+            router
+                .clone()
+                .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        };
+        let id = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"].clone()
+        };
+
+        assert_eq!(id(send(None).await.unwrap()).await, "msg_default");
+        assert_eq!(id(send(Some(&gateway)).await.unwrap()).await, "msg_gateway");
+        let refused = send(Some("http://127.0.0.1:1")).await.unwrap();
+        assert_eq!(refused.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_server_tools_forwarded_and_traced() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::<serde_json::Value>::new()));
+        let sink = seen.clone();
+        let app = AxumRouter::new().route(
+            "/v1/messages",
+            post(move |Json(body): Json<serde_json::Value>| {
+                let sink = sink.clone();
+                async move {
+                    sink.lock().unwrap().push(body);
+                    Json(json!({
+                        "id": "msg_1",
+                        "type": "message",
+                        "role": "assistant",
+                        "content": [
+                            {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search",
+                             "input": {"query": "tokio 1.40"}},
+                            {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1", "content": []},
+                            {"type": "text", "text": "Found it"}
+                        ],
+                        "usage": {"input_tokens": 10, "output_tokens": 5,
+                                  "server_tool_use": {"web_search_requests": 1}}
+                    }))
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        let temp = tempfile::tempdir().unwrap();
+        let traces = temp.path().join("traces.jsonl");
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .with_trace_writer(muninn_tracing::WriterConfig::session(&traces));
+        let router = ProxyServer::with_router(
+            config,
+            Arc::new(MockBackend::new(vec![])),
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysPassthrough,
+                ..Default::default()
+            },
+        )
+        .router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "tools": [{"type": "web_search_20250305", "name": "web_search", "max_uses": 3}],
+            "messages": [
+                {"role": "user", "content": "What's new?"},
+                {"role": "assistant", "content": [
+                    {"type": "server_tool_use", "id": "srvtoolu_0", "name": "web_search", "input": {}},
+                    {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_0", "content": []}
+                ]},
+                {"role": "user", "content": "And in tokio?"}
+            ]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("x-api-key", "test-key")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0]["tools"], request_body["tools"]);
+        assert_eq!(seen[0]["messages"], request_body["messages"]);
+
+        let written = muninn_tracing::TraceWriter::read_traces(&traces).unwrap();
+        assert_eq!(
+            written[0].metadata[SERVER_TOOLS_METADATA],
+            json!(["web_search"])
+        );
+        assert_eq!(
+            written[0].metadata[SERVER_TOOL_USE_METADATA],
+            json!({"web_search": 1})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_propagates_to_upstream() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let sink = seen.clone();
+        let app = AxumRouter::new().route(
+            "/v1/messages",
+            post(move |headers: HeaderMap| {
+                let sink = sink.clone();
+                async move {
+                    if let Some(tp) = headers.get(muninn_tracing::TRACEPARENT_HEADER) {
+                        sink.lock().unwrap().push(tp.to_str().unwrap().to_string());
+                    }
+                    Json(json!({"id": "msg_1", "usage": {"input_tokens": 1, "output_tokens": 1}}))
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .without_agentic_tracing();
+        let router = ProxyServer::with_router(
+            config,
+            Arc::new(MockBackend::new(vec![])),
+            Arc::new(EmptyToolEnvironment),
+            RouterConfig {
+                strategy: RouterStrategy::AlwaysPassthrough,
+                ..Default::default()
+            },
+        )
+        .router();
+
+        let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+        for uri in ["/v1/messages", "/v1/chat/completions"] {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .header("x-api-key", "test-key")
+                        .header(muninn_tracing::TRACEPARENT_HEADER, incoming)
+                        .body(Body::from(request_body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response.headers()[TRACE_ID_HEADER],
+                "4bf92f3577b34da6a3ce929d0e0e4736"
+            );
+        }
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        // Traced requests forward from the proxy's own span...
+        let traced = TraceParent::parse(&seen[0]).unwrap();
+        assert_eq!(traced.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(traced.parent_id, "00f067aa0ba902b7");
+        // ...untraced direct passthrough forwards the caller's context as-is
+        assert_eq!(seen[1], incoming);
+    }
+
+    #[tokio::test]
+    async fn test_fine_grained_tool_stream_fidelity() {
+        const TOOL_STREAM: &str = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\"}}\n\n\
+             event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"Read\",\"input\":{}}}\n\n\
+             event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"file_pa\"}}\n\n\
+             event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"th\\\": \\\"a.rs\\\"}\"}}\n\n\
+             event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n\
+             event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "stream": true,
+            "messages": [{"role": "user", "content": "Read a.rs"}]
+        });
+        let body = request_body.to_string();
+        let send = |router: AxumRouter| {
+            let body = body.clone();
+            async move {
+                let response = router
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri("/v1/messages")
+                            .header("content-type", "application/json")
+                            .header("x-api-key", "test-key")
+                            .body(Body::from(body))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+        let upstream = spawn_sse_upstream(TOOL_STREAM).await;
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .without_agentic_tracing();
+
+        // Untransformed, every delta reaches the agent byte for byte
+        let relayed = send(ProxyServer::passthrough_only(config.clone()).router()).await;
+        assert_eq!(relayed, TOOL_STREAM);
+
+        // Coalesced, the input arrives as one delta
+        let router = ProxyServer::passthrough_only(config)
+            .with_response_transform(CoalesceToolDeltas)
+            .router();
+        let coalesced = send(router).await;
+        let mut parser = crate::sse::SseParser::new();
+        let events: Vec<serde_json::Value> = parser
+            .push(coalesced.as_bytes())
+            .iter()
+            .filter_map(|f| crate::sse::SseEvent::parse(f).json())
+            .collect();
+        let deltas: Vec<_> = events
+            .iter()
+            .filter(|e| e["type"] == "content_block_delta")
+            .collect();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(
+            deltas[0]["delta"]["partial_json"],
+            "{\"file_path\": \"a.rs\"}"
+        );
+        assert_eq!(events.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_passthrough_repairs_truncated_stream() {
+        let upstream = spawn_sse_upstream(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n\
+             event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        )
+        .await;
+        let temp = tempfile::tempdir().unwrap();
+        let traces = temp.path().join("traces.jsonl");
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .with_trace_writer(muninn_tracing::WriterConfig::session(&traces));
+        let router = ProxyServer::passthrough_only(config).router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("x-api-key", "test-key")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("content_block_stop"));
+        assert!(!body.contains("event: message_stop"));
+        assert!(body.ends_with("}}\n\n") && body.contains("event: error\n"));
+
+        let written = muninn_tracing::TraceWriter::read_traces(&traces).unwrap();
+        let stream_trace = written
+            .iter()
+            .find(|t| t.metadata.get("kind") == Some(&json!("passthrough_stream")))
+            .expect("stream trace written");
+        let stats = stream_trace.spans[0].data.as_ref().unwrap();
+        assert_eq!(stats["repaired"], true);
+        assert_eq!(stats["events"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_passthrough_records_spend() {
+        let upstream = spawn_sse_upstream(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-up\",\"usage\":{\"input_tokens\":30,\"output_tokens\":1}}}\n\n\
+             event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":12}}\n\n\
+             event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        )
+        .await;
+        let temp = tempfile::tempdir().unwrap();
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .with_session_dir(temp.path())
+            .without_agentic_tracing();
+        let router = ProxyServer::passthrough_only(config).router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("x-api-key", "test-key")
+                    .header(TAGS_HEADER, "refactor, experiment-b,refactor")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        // Raw request logging is opt-in
+        assert!(crate::debug_log::raw_request_logs(temp.path()).is_empty());
+
+        let entries = SpendLedger::in_dir(temp.path()).read().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].model, "claude-up");
+        assert_eq!(entries[0].handling, "passthrough");
+        assert!(entries[0].streaming);
+        assert_eq!(entries[0].usage.input_tokens, 30);
+        assert_eq!(entries[0].usage.output_tokens, 12);
+        assert_eq!(entries[0].tags, vec!["refactor", "experiment-b"]);
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/statusline")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["requests"], 1);
+        assert_eq!(stats["passthrough"], 1);
+        assert_eq!(stats["rlm"], 0);
+        assert_eq!(stats["input_tokens"], 30);
+        assert_eq!(stats["output_tokens"], 12);
+        assert_eq!(stats["line"], "muninn · 1 req · 0 rlm · 42 tok");
+        assert_eq!(stats["upstream"]["requests"], 1);
+        assert_eq!(stats["upstream"]["connections_opened"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_raw_request_log_opt_in() {
+        let upstream =
+            spawn_sse_upstream("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n").await;
+        let temp = tempfile::tempdir().unwrap();
+        let config = ProxyConfig::default()
+            .with_passthrough(PassthroughConfig::custom(upstream))
+            .with_raw_request_log(RawRequestLogConfig::in_dir(temp.path()))
+            .without_agentic_tracing();
+        let router = ProxyServer::passthrough_only(config).router();
+
+        let request_body = json!({
+            "model": "test-model",
+            "max_tokens": 100,
+            "stream": true,
+            "messages": [{"role": "user", "content": "my key is sk-ant-api03-abcdefghijkl"}]
+        });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("x-api-key", "test-key")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let log = std::fs::read_to_string(temp.path().join("raw_requests.jsonl")).unwrap();
+        assert!(log.contains("\"model\":\"test-model\""));
+        assert!(log.contains("my key is [REDACTED]"));
+        assert!(!log.contains("sk-ant-api03"));
+    }
+
+    #[tokio::test]
+    async fn test_statusline_reports_indexing() {
+        let status = IndexStatus::new();
+        let config = ProxyConfig::default()
+            .with_index_status(status.clone())
+            .without_agentic_tracing();
+        let router = ProxyServer::passthrough_only(config).router();
+        status.update(&muninn_graph::IndexProgress {
+            files_done: 3,
+            files_indexed: 0,
+            files_total: 12,
+            elapsed: std::time::Duration::from_secs(3),
+        });
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/statusline")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["indexing"]["state"], "running");
+        assert_eq!(stats["indexing"]["files_done"], 3);
+        assert_eq!(
+            stats["line"],
+            "muninn · 0 req · 0 rlm · 0 tok · indexing 3/12 · eta 9s"
+        );
+    }
+
+    #[test]
+    fn test_queue_tool_paths() {
+        let queue = IndexQueue::new();
+        let request = json!({"messages": [
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "1", "name": "Read",
+                 "input": {"file_path": "/repo/src/old.rs"}}
+            ]},
+            {"role": "user", "content": "next"},
+            {"role": "user", "content": "next"},
+            {"role": "user", "content": "next"},
+            {"role": "assistant", "content": [
+                {"type": "text", "text": "Looking"},
+                {"type": "tool_use", "id": "2", "name": "Grep",
+                 "input": {"pattern": "fn", "path": "/repo/crates/a"}},
+                {"type": "tool_use", "id": "3", "name": "Read",
+                 "input": {"file_path": "/repo/crates/b/lib.rs"}}
+            ]}
+        ]});
+        queue_tool_paths(&queue, &request);
+        assert_eq!(queue.pop(), Some("/repo/crates/b/lib.rs".into()));
+        assert_eq!(queue.pop(), Some("/repo/crates/a".into()));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_visible_files() {
+        let request = json!({"messages": [
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "1", "name": "Read",
+                 "input": {"file_path": "/repo/src/a.rs"}},
+                {"type": "tool_use", "id": "2", "name": "Read",
+                 "input": {"file_path": "/repo/src/missing.rs"}},
+                {"type": "tool_use", "id": "3", "name": "Grep",
+                 "input": {"pattern": "fn", "path": "/repo/src"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "1", "content": "fn a() {}"},
+                {"type": "tool_result", "tool_use_id": "2", "content": "not found",
+                 "is_error": true},
+                {"type": "tool_result", "tool_use_id": "3", "content": "src/a.rs"}
+            ]},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "4", "name": "Read",
+                 "input": {"file_path": "/repo/src/b.rs"}},
+                {"type": "tool_use", "id": "5", "name": "Read",
+                 "input": {"file_path": "/repo/src/pending.rs"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "4", "content": "fn b() {}"},
+                {"type": "text", "text": "what does this file do?"}
+            ]}
+        ]});
+        assert_eq!(
+            visible_files(&request),
+            ["/repo/src/b.rs", "/repo/src/a.rs"]
+        );
+        assert!(visible_files(&json!({"messages": []})).is_empty());
+    }
+
+    #[test]
+    fn test_compact_count() {
+        assert_eq!(compact_count(999), "999");
+        assert_eq!(compact_count(45_210), "45.2k");
+        assert_eq!(compact_count(1_234_567), "1.2M");
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let mut headers = HeaderMap::new();
+        assert!(parse_tags(&headers).is_empty());
+
+        headers.append(TAGS_HEADER, " refactor,,experiment-b ".parse().unwrap());
+        headers.append(TAGS_HEADER, "experiment-b,nightly".parse().unwrap());
+        assert_eq!(
+            parse_tags(&headers),
+            vec!["refactor", "experiment-b", "nightly"]
+        );
+    }
+
     #[test]
     fn test_proxy_config_default() {
         let config = ProxyConfig::default();
@@ -0,0 +1,561 @@
+//! Upstream request/response recording.
+//!
+//! When enabled (`muninn --record`), every request the passthrough client
+//! sends upstream is captured together with the upstream response into a
+//! JSONL file in the session directory. Streaming responses are recorded
+//! both as raw SSE text and reassembled into a single Messages API
+//! response object, so the final message can be inspected without
+//! replaying the event stream by hand.
+//!
+//! Credentials never reach disk: auth headers are replaced with
+//! [`REDACTED`] and any API-key or OAuth-token shaped string inside the
+//! request body is masked. Recordings can be resent with
+//! `muninn replay-upstream <file>` (see [`crate::Passthrough::replay`]).
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, RlmError};
+
+/// File name of the recording inside the session directory.
+pub const UPSTREAM_RECORDING_FILE: &str = "upstream.jsonl";
+
+/// Placeholder written in place of redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers whose values are always redacted.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "cookie",
+    "set-cookie",
+];
+
+/// Anthropic API keys and OAuth tokens (`sk-ant-api03-…`, `sk-ant-oat01-…`).
+static SECRET_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"sk-ant-[A-Za-z0-9_\-]{8,}").unwrap());
+
+/// A single recorded upstream exchange.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    /// When the request was sent.
+    pub recorded_at: DateTime<Utc>,
+    /// HTTP method.
+    pub method: String,
+    /// Full upstream URL.
+    pub url: String,
+    /// Request headers as sent (sensitive values redacted).
+    pub request_headers: BTreeMap<String, String>,
+    /// Request body as sent upstream (after sanitization and injection).
+    pub request: serde_json::Value,
+    /// Whether the request asked for a streaming response.
+    pub streaming: bool,
+    /// Upstream HTTP status, if a response was received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Upstream response headers.
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    /// Parsed response body, or the reassembled message for streams.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+    /// Raw response body when it is not plain JSON (SSE text, error pages).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+    /// Transport or stream error, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// False when the response body was cut short (client disconnect,
+    /// upstream error mid-stream).
+    #[serde(default)]
+    pub complete: bool,
+    /// Time from send to the end of the response body (ms).
+    #[serde(default)]
+    pub duration_ms: u64,
+}
+
+impl RecordedExchange {
+    /// Start a new exchange for a POST to `url`.
+    pub fn new(
+        url: impl Into<String>,
+        request_headers: BTreeMap<String, String>,
+        request: serde_json::Value,
+    ) -> Self {
+        let streaming = request
+            .get("stream")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        Self {
+            recorded_at: Utc::now(),
+            method: "POST".to_string(),
+            url: url.into(),
+            request_headers,
+            request,
+            streaming,
+            status: None,
+            response_headers: BTreeMap::new(),
+            response: None,
+            response_body: None,
+            error: None,
+            complete: false,
+            duration_ms: 0,
+        }
+    }
+
+    /// Record the response status and headers.
+    pub fn set_response_head(&mut self, status: u16, headers: &reqwest::header::HeaderMap) {
+        self.status = Some(status);
+        self.response_headers = header_map(headers);
+    }
+
+    /// Record the full response body.
+    ///
+    /// JSON bodies are stored parsed; SSE bodies are stored raw and
+    /// reassembled into a single message.
+    pub fn set_response_body(&mut self, body: &str) {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
+            self.response = Some(json);
+            self.response_body = None;
+        } else {
+            if self.streaming {
+                self.response = reassemble_sse(body);
+            }
+            self.response_body = Some(body.to_string());
+        }
+    }
+
+    /// Redact credentials from headers and the request body.
+    pub fn redact(&mut self) {
+        for (name, value) in self
+            .request_headers
+            .iter_mut()
+            .chain(self.response_headers.iter_mut())
+        {
+            if SENSITIVE_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                *value = REDACTED.to_string();
+            }
+        }
+        redact_value(&mut self.request);
+    }
+}
+
+/// Appends [`RecordedExchange`]s to a JSONL file.
+#[derive(Debug)]
+pub struct UpstreamRecorder {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl UpstreamRecorder {
+    /// Create a recorder writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Create a recorder writing [`UPSTREAM_RECORDING_FILE`] inside `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(UPSTREAM_RECORDING_FILE))
+    }
+
+    /// Path of the recording file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Redact and append an exchange. Failures are logged, never surfaced
+    /// to the request path.
+    pub fn record(&self, mut exchange: RecordedExchange) {
+        exchange.redact();
+        let line = match serde_json::to_string(&exchange) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to serialize upstream recording");
+                return;
+            }
+        };
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write upstream recording");
+        }
+    }
+}
+
+/// Shared handle to an [`UpstreamRecorder`].
+pub type SharedRecorder = Arc<UpstreamRecorder>;
+
+/// An exchange in flight. Written to the recorder when dropped, so
+/// requests abandoned mid-stream are still captured (with
+/// `complete: false`).
+#[derive(Debug)]
+pub struct PendingExchange {
+    recorder: SharedRecorder,
+    exchange: Option<RecordedExchange>,
+    body: Vec<u8>,
+    started: Instant,
+}
+
+impl PendingExchange {
+    /// Begin recording an exchange.
+    pub fn new(recorder: SharedRecorder, exchange: RecordedExchange) -> Self {
+        Self {
+            recorder,
+            exchange: Some(exchange),
+            body: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Record the response status and headers.
+    pub fn set_response_head(&mut self, status: u16, headers: &reqwest::header::HeaderMap) {
+        if let Some(exchange) = &mut self.exchange {
+            exchange.set_response_head(status, headers);
+        }
+    }
+
+    /// Append a chunk of response body.
+    pub fn push_body(&mut self, chunk: &[u8]) {
+        self.body.extend_from_slice(chunk);
+    }
+
+    /// Mark the exchange as failed.
+    pub fn set_error(&mut self, error: impl Into<String>) {
+        if let Some(exchange) = &mut self.exchange {
+            exchange.error = Some(error.into());
+        }
+    }
+
+    /// Mark the response body as fully received.
+    pub fn mark_complete(&mut self) {
+        if let Some(exchange) = &mut self.exchange {
+            exchange.complete = true;
+        }
+    }
+}
+
+impl Drop for PendingExchange {
+    fn drop(&mut self) {
+        let Some(mut exchange) = self.exchange.take() else {
+            return;
+        };
+        if !self.body.is_empty() {
+            exchange.set_response_body(&String::from_utf8_lossy(&self.body));
+        }
+        exchange.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.recorder.record(exchange);
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Byte stream adapter that copies every chunk into a
+    /// [`PendingExchange`] as it is forwarded.
+    pub struct RecordingStream<S> {
+        #[pin]
+        inner: S,
+        pending: Option<PendingExchange>,
+    }
+}
+
+impl<S> RecordingStream<S> {
+    /// Wrap `inner`, recording into `pending`.
+    pub fn new(inner: S, pending: PendingExchange) -> Self {
+        Self {
+            inner,
+            pending: Some(pending),
+        }
+    }
+}
+
+impl<S, E> Stream for RecordingStream<S>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>>,
+    E: std::fmt::Display,
+{
+    type Item = std::result::Result<Bytes, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = futures::ready!(this.inner.poll_next(cx));
+        match &item {
+            Some(Ok(chunk)) => {
+                if let Some(pending) = this.pending.as_mut() {
+                    pending.push_body(chunk);
+                }
+            }
+            Some(Err(e)) => {
+                if let Some(mut pending) = this.pending.take() {
+                    pending.set_error(e.to_string());
+                }
+            }
+            None => {
+                if let Some(mut pending) = this.pending.take() {
+                    pending.mark_complete();
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Read all exchanges from a recording file.
+pub fn read_recordings(path: &Path) -> Result<Vec<RecordedExchange>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        RlmError::Config(format!(
+            "Failed to read recording {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                RlmError::Serialization(format!(
+                    "{}:{}: invalid recording: {}",
+                    path.display(),
+                    i + 1,
+                    e
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Reassemble an Anthropic SSE stream into a single Messages API response.
+///
+/// Returns `None` if the stream never sent `message_start`.
+pub fn reassemble_sse(body: &str) -> Option<serde_json::Value> {
+    let mut message: Option<serde_json::Value> = None;
+    let mut blocks: BTreeMap<u64, serde_json::Value> = BTreeMap::new();
+    let mut partial_json: BTreeMap<u64, String> = BTreeMap::new();
+
+    for data in body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+    {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else {
+            continue;
+        };
+        let index = event.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+        match event.get("type").and_then(|v| v.as_str()) {
+            Some("message_start") => message = event.get("message").cloned(),
+            Some("content_block_start") => {
+                if let Some(block) = event.get("content_block") {
+                    blocks.insert(index, block.clone());
+                }
+            }
+            Some("content_block_delta") => {
+                let (Some(block), Some(delta)) = (blocks.get_mut(&index), event.get("delta"))
+                else {
+                    continue;
+                };
+                match delta.get("type").and_then(|v| v.as_str()) {
+                    Some("text_delta") => append_str(block, "text", delta.get("text")),
+                    Some("thinking_delta") => append_str(block, "thinking", delta.get("thinking")),
+                    Some("signature_delta") => {
+                        if let Some(sig) = delta.get("signature") {
+                            block["signature"] = sig.clone();
+                        }
+                    }
+                    Some("input_json_delta") => {
+                        if let Some(s) = delta.get("partial_json").and_then(|v| v.as_str()) {
+                            partial_json.entry(index).or_default().push_str(s);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some("message_delta") => {
+                let Some(msg) = message.as_mut() else {
+                    continue;
+                };
+                if let Some(delta) = event.get("delta").and_then(|v| v.as_object()) {
+                    for (k, v) in delta {
+                        msg[k] = v.clone();
+                    }
+                }
+                if let Some(usage) = event.get("usage").and_then(|v| v.as_object()) {
+                    for (k, v) in usage {
+                        msg["usage"][k] = v.clone();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (index, json) in partial_json {
+        if let Some(block) = blocks.get_mut(&index) {
+            block["input"] = serde_json::from_str(&json).unwrap_or(serde_json::Value::String(json));
+        }
+    }
+
+    let mut message = message?;
+    message["content"] = serde_json::Value::Array(blocks.into_values().collect());
+    Some(message)
+}
+
+fn append_str(block: &mut serde_json::Value, field: &str, piece: Option<&serde_json::Value>) {
+    let Some(piece) = piece.and_then(|v| v.as_str()) else {
+        return;
+    };
+    let existing = block.get(field).and_then(|v| v.as_str()).unwrap_or("");
+    block[field] = serde_json::Value::String(format!("{}{}", existing, piece));
+}
+
+fn header_map(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.as_str().to_string(),
+                v.to_str().unwrap_or("<non-utf8>").to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Mask credential-shaped substrings in every string of a JSON value.
+pub(crate) fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) if SECRET_PATTERN.is_match(s) => {
+            *s = SECRET_PATTERN.replace_all(s, REDACTED).into_owned();
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_value),
+        serde_json::Value::Object(map) => map.values_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn exchange() -> RecordedExchange {
+        let mut headers = BTreeMap::new();
+        headers.insert(
+            "Authorization".to_string(),
+            "Bearer sk-ant-oat01-secret".to_string(),
+        );
+        headers.insert("anthropic-version".to_string(), "2023-06-01".to_string());
+        RecordedExchange::new(
+            "https://api.anthropic.com/v1/messages",
+            headers,
+            json!({
+                "model": "claude-sonnet",
+                "stream": true,
+                "messages": [{"role": "user", "content": "key is sk-ant-api03-abcdefghijkl"}]
+            }),
+        )
+    }
+
+    const SSE: &str = "event: message_start\n\
+data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"role\":\"assistant\",\"content\":[],\"usage\":{\"input_tokens\":10,\"output_tokens\":1}}}\n\n\
+event: content_block_start\n\
+data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n\
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n\n\
+data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"tu_1\",\"name\":\"read\",\"input\":{}}}\n\n\
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"path\\\":\"}}\n\n\
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"\\\"a.rs\\\"}\"}}\n\n\
+data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":42}}\n\n\
+data: {\"type\":\"message_stop\"}\n\n";
+
+    #[test]
+    fn test_redact_headers_and_body() {
+        let mut ex = exchange();
+        ex.redact();
+        assert_eq!(ex.request_headers["Authorization"], REDACTED);
+        assert_eq!(ex.request_headers["anthropic-version"], "2023-06-01");
+        let content = ex.request["messages"][0]["content"].as_str().unwrap();
+        assert_eq!(content, format!("key is {}", REDACTED));
+    }
+
+    #[test]
+    fn test_reassemble_sse() {
+        let message = reassemble_sse(SSE).unwrap();
+        assert_eq!(message["id"], "msg_1");
+        assert_eq!(message["stop_reason"], "tool_use");
+        assert_eq!(message["usage"]["input_tokens"], 10);
+        assert_eq!(message["usage"]["output_tokens"], 42);
+        assert_eq!(message["content"][0]["text"], "Hello");
+        assert_eq!(message["content"][1]["input"], json!({"path": "a.rs"}));
+    }
+
+    #[test]
+    fn test_reassemble_without_message_start() {
+        assert!(reassemble_sse("data: {\"type\":\"ping\"}\n\n").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recording_stream_writes_on_completion() {
+        let temp = tempdir().unwrap();
+        let recorder = Arc::new(UpstreamRecorder::in_dir(temp.path()));
+        let pending = PendingExchange::new(recorder.clone(), exchange());
+
+        let chunks: Vec<std::result::Result<Bytes, std::io::Error>> = SSE
+            .as_bytes()
+            .chunks(37)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let forwarded: Vec<_> = RecordingStream::new(futures::stream::iter(chunks), pending)
+            .collect()
+            .await;
+        assert!(forwarded.iter().all(|c| c.is_ok()));
+
+        let recorded = read_recordings(recorder.path()).unwrap();
+        assert_eq!(recorded.len(), 1);
+        let ex = &recorded[0];
+        assert!(ex.complete);
+        assert!(ex.streaming);
+        assert_eq!(ex.response_body.as_deref(), Some(SSE));
+        assert_eq!(ex.response.as_ref().unwrap()["content"][0]["text"], "Hello");
+        assert_eq!(ex.request_headers["Authorization"], REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_is_recorded_incomplete() {
+        let temp = tempdir().unwrap();
+        let recorder = Arc::new(UpstreamRecorder::in_dir(temp.path()));
+        let pending = PendingExchange::new(recorder.clone(), exchange());
+
+        let chunks: Vec<std::result::Result<Bytes, std::io::Error>> = (0..3)
+            .map(|_| Ok(Bytes::from_static(b"data: {\"type\":\"ping\"}\n\n")))
+            .collect();
+        let mut stream = RecordingStream::new(futures::stream::iter(chunks), pending);
+        stream.next().await;
+        drop(stream);
+
+        let recorded = read_recordings(recorder.path()).unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert!(!recorded[0].complete);
+    }
+}
@@ -16,7 +16,7 @@ use tokio::process::Command;
 use tokio::time::timeout;
 
 use crate::error::{Result, RlmError};
-use crate::tools::{Tool, ToolMetadata, ToolResult};
+use crate::tools::{Tool, ToolCategory, ToolMetadata, ToolResult};
 
 // ============================================================================
 // Configuration
@@ -335,6 +335,10 @@ impl Tool for ExecuteCodeTool {
         "execute_code"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Repl
+    }
+
     fn description(&self) -> &str {
         "Execute code in a sandboxed environment. Supports Python and Shell (bash). \
          Use for testing hypotheses, running calculations, or validating code snippets. \
@@ -432,6 +436,10 @@ impl Tool for CheckLanguageTool {
         "check_language"
     }
 
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Repl
+    }
+
     fn description(&self) -> &str {
         "Check which programming languages are available for code execution."
     }
@@ -0,0 +1,553 @@
+//! Response transformation hooks for the proxy.
+//!
+//! A [`ResponseTransform`] registered on a
+//! [`ProxyServer`](crate::proxy::ProxyServer) rewrites Messages API
+//! responses before they reach the agent, whether they came from the RLM
+//! engine or were passed through from upstream. Complete responses go
+//! through [`ResponseTransform::transform`]; streamed ones are rewritten
+//! event by event by the per-stream state [`ResponseTransform::stream`]
+//! returns.
+//!
+//! Transforms run in registration order.
+//!
+//! ```rust,ignore
+//! let server = ProxyServer::passthrough_only(config)
+//!     .with_response_transform(StripThinking)
+//!     .with_response_transform(TraceIdAnnotation);
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::Stream;
+use serde_json::Value;
+
+use crate::error::{Result, RlmError};
+use crate::passthrough::UpstreamBodyStream;
+use crate::sse::{SseEvent, SseParser};
+
+/// Which path produced a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseSource {
+    /// The recursive engine.
+    Rlm,
+    /// Forwarded from the upstream API.
+    Passthrough,
+}
+
+/// What a transform knows about the response it is rewriting.
+#[derive(Debug, Clone)]
+pub struct TransformContext {
+    /// The request's trace ID (also sent as `x-muninn-trace-id`).
+    pub trace_id: String,
+    /// Which path produced the response.
+    pub source: ResponseSource,
+    /// Whether the response is streamed.
+    pub streaming: bool,
+    /// Tags from the request's `x-muninn-tags` header.
+    pub tags: Vec<String>,
+}
+
+/// Rewrites responses on their way to the agent.
+pub trait ResponseTransform: Send + Sync {
+    /// Name used in logs.
+    fn name(&self) -> &str;
+
+    /// Rewrite a complete Messages response.
+    fn transform(&self, response: &mut Value, ctx: &TransformContext) {
+        let _ = (response, ctx);
+    }
+
+    /// State for rewriting one streamed response; `None` leaves streams
+    /// untouched.
+    fn stream(&self, ctx: &TransformContext) -> Option<Box<dyn StreamTransform>> {
+        let _ = ctx;
+        None
+    }
+}
+
+/// Rewrites the events of one streamed response.
+pub trait StreamTransform: Send {
+    /// Rewrite an event's JSON data, returning the events to send in its
+    /// place: none to drop it, several to insert new ones.
+    fn event(&mut self, event: Value) -> Vec<Value>;
+}
+
+/// The transforms registered on a proxy.
+#[derive(Default)]
+pub struct ResponsePipeline {
+    transforms: RwLock<Vec<Arc<dyn ResponseTransform>>>,
+}
+
+impl std::fmt::Debug for ResponsePipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self
+            .snapshot()
+            .iter()
+            .map(|t| t.name().to_string())
+            .collect();
+        f.debug_struct("ResponsePipeline")
+            .field("transforms", &names)
+            .finish()
+    }
+}
+
+impl ResponsePipeline {
+    /// Add a transform after those already registered.
+    pub fn push(&self, transform: Arc<dyn ResponseTransform>) {
+        self.transforms
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(transform);
+    }
+
+    /// Whether no transforms are registered.
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().is_empty()
+    }
+
+    fn snapshot(&self) -> Vec<Arc<dyn ResponseTransform>> {
+        self.transforms
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Run every transform over a complete response, on the blocking
+    /// thread pool: transforms are synchronous and some do real work
+    /// (plugin transforms run a wasm instance). A transform that panics, or
+    /// a task cancelled by runtime shutdown, fails the response rather than
+    /// the proxy.
+    pub async fn apply(&self, response: Value, ctx: TransformContext) -> Result<Value> {
+        let transforms = self.snapshot();
+        if transforms.is_empty() {
+            return Ok(response);
+        }
+        tokio::task::spawn_blocking(move || {
+            let mut response = response;
+            for transform in &transforms {
+                transform.transform(&mut response, &ctx);
+            }
+            response
+        })
+        .await
+        .map_err(|e| {
+            if e.is_cancelled() {
+                RlmError::Internal("Response transform was cancelled".to_string())
+            } else {
+                tracing::error!(error = %e, "Response transform panicked");
+                RlmError::Internal("Response transform panicked".to_string())
+            }
+        })
+    }
+
+    /// Wrap an SSE body so every event passes through the transforms.
+    pub fn apply_stream(
+        &self,
+        body: UpstreamBodyStream,
+        ctx: &TransformContext,
+    ) -> UpstreamBodyStream {
+        let stages: Vec<_> = self
+            .snapshot()
+            .iter()
+            .filter_map(|t| t.stream(ctx))
+            .collect();
+        if stages.is_empty() {
+            return body;
+        }
+        Box::pin(TransformedStream {
+            inner: body,
+            parser: SseParser::new(),
+            stages,
+        })
+    }
+}
+
+/// Wrap an SSE body so every event passes through one stream stage.
+pub fn transform_stream(
+    body: UpstreamBodyStream,
+    stage: Box<dyn StreamTransform>,
+) -> UpstreamBodyStream {
+    Box::pin(TransformedStream {
+        inner: body,
+        parser: SseParser::new(),
+        stages: vec![stage],
+    })
+}
+
+/// SSE body rewritten by a chain of [`StreamTransform`]s.
+///
+/// Events whose data isn't JSON are forwarded unchanged.
+struct TransformedStream {
+    inner: UpstreamBodyStream,
+    parser: SseParser,
+    stages: Vec<Box<dyn StreamTransform>>,
+}
+
+impl TransformedStream {
+    fn rewrite(&mut self, frame: &[u8], out: &mut Vec<u8>) {
+        let Some(json) = SseEvent::parse(frame).json() else {
+            out.extend_from_slice(frame);
+            return;
+        };
+        let mut events = vec![json];
+        for stage in &mut self.stages {
+            events = events.into_iter().flat_map(|e| stage.event(e)).collect();
+        }
+        for event in events {
+            if let Some(name) = event.get("type").and_then(Value::as_str) {
+                out.extend_from_slice(format!("event: {}\n", name).as_bytes());
+            }
+            out.extend_from_slice(format!("data: {}\n\n", event).as_bytes());
+        }
+    }
+}
+
+impl Stream for TransformedStream {
+    type Item = std::result::Result<Bytes, std::io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match futures::ready!(self.inner.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    let frames = self.parser.push(&chunk);
+                    let mut out = Vec::new();
+                    for frame in frames {
+                        self.rewrite(&frame, &mut out);
+                    }
+                    if !out.is_empty() {
+                        return Poll::Ready(Some(Ok(Bytes::from(out))));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    // Anything short of a full frame goes out as it came
+                    let rest = self.parser.take_remainder();
+                    if rest.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    let mut out = Vec::new();
+                    self.rewrite(&rest, &mut out);
+                    return Poll::Ready(Some(Ok(Bytes::from(out))));
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Built-in Transforms
+// ============================================================================
+
+/// Adds `muninn.trace_id` to each response (on `message_start` when
+/// streaming), so agents can quote the trace for a reply.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceIdAnnotation;
+
+impl TraceIdAnnotation {
+    fn annotate(message: &mut Value, trace_id: &str) {
+        if let Some(message) = message.as_object_mut() {
+            let muninn = message
+                .entry("muninn")
+                .or_insert_with(|| Value::Object(Default::default()));
+            if let Some(muninn) = muninn.as_object_mut() {
+                muninn.insert("trace_id".to_string(), Value::String(trace_id.to_string()));
+            }
+        }
+    }
+}
+
+impl ResponseTransform for TraceIdAnnotation {
+    fn name(&self) -> &str {
+        "trace_id"
+    }
+
+    fn transform(&self, response: &mut Value, ctx: &TransformContext) {
+        Self::annotate(response, &ctx.trace_id);
+    }
+
+    fn stream(&self, ctx: &TransformContext) -> Option<Box<dyn StreamTransform>> {
+        struct Annotate(String);
+        impl StreamTransform for Annotate {
+            fn event(&mut self, mut event: Value) -> Vec<Value> {
+                if event["type"] == "message_start"
+                    && let Some(message) = event.get_mut("message")
+                {
+                    TraceIdAnnotation::annotate(message, &self.0);
+                }
+                vec![event]
+            }
+        }
+        Some(Box::new(Annotate(ctx.trace_id.clone())))
+    }
+}
+
+/// Removes `thinking` and `redacted_thinking` blocks, renumbering the
+/// remaining blocks of a stream so indices stay contiguous.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripThinking;
+
+fn is_thinking(block: &Value) -> bool {
+    matches!(
+        block.get("type").and_then(Value::as_str),
+        Some("thinking" | "redacted_thinking")
+    )
+}
+
+impl ResponseTransform for StripThinking {
+    fn name(&self) -> &str {
+        "strip_thinking"
+    }
+
+    fn transform(&self, response: &mut Value, _ctx: &TransformContext) {
+        if let Some(content) = response.get_mut("content").and_then(Value::as_array_mut) {
+            content.retain(|block| !is_thinking(block));
+        }
+    }
+
+    fn stream(&self, _ctx: &TransformContext) -> Option<Box<dyn StreamTransform>> {
+        #[derive(Default)]
+        struct Strip {
+            thinking: BTreeSet<u64>,
+        }
+        impl StreamTransform for Strip {
+            fn event(&mut self, mut event: Value) -> Vec<Value> {
+                let Some(index) = event.get("index").and_then(Value::as_u64) else {
+                    return vec![event];
+                };
+                if event["type"] == "content_block_start" && is_thinking(&event["content_block"]) {
+                    self.thinking.insert(index);
+                }
+                if self.thinking.contains(&index) {
+                    return Vec::new();
+                }
+                let shift = self.thinking.range(..index).count() as u64;
+                event["index"] = Value::from(index - shift);
+                vec![event]
+            }
+        }
+        Some(Box::new(Strip::default()))
+    }
+}
+
+/// Buffers the `input_json_delta` events of each streamed tool call and
+/// sends the whole input as one delta when the block closes.
+///
+/// With fine-grained tool streaming, inputs arrive in small pieces that
+/// need not split on JSON boundaries, and a call cut off by `max_tokens`
+/// leaves invalid JSON. Some clients mis-handle either. Coalesced inputs
+/// that still don't parse are sent as `{"INVALID_JSON": "<raw>"}`, so the
+/// client always receives valid JSON. Text and thinking deltas stream
+/// unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CoalesceToolDeltas;
+
+impl CoalesceToolDeltas {
+    /// The buffered input as one `input_json_delta`, made valid JSON.
+    fn delta(index: u64, partial: String) -> Value {
+        let partial = if serde_json::from_str::<Value>(&partial).is_ok() {
+            partial
+        } else {
+            serde_json::json!({ "INVALID_JSON": partial }).to_string()
+        };
+        serde_json::json!({
+            "type": "content_block_delta",
+            "index": index,
+            "delta": {"type": "input_json_delta", "partial_json": partial},
+        })
+    }
+}
+
+impl ResponseTransform for CoalesceToolDeltas {
+    fn name(&self) -> &str {
+        "coalesce_tool_deltas"
+    }
+
+    fn stream(&self, _ctx: &TransformContext) -> Option<Box<dyn StreamTransform>> {
+        #[derive(Default)]
+        struct Coalesce {
+            /// Input received so far, by index of open tool blocks.
+            inputs: BTreeMap<u64, String>,
+        }
+        impl StreamTransform for Coalesce {
+            fn event(&mut self, event: Value) -> Vec<Value> {
+                let Some(index) = event.get("index").and_then(Value::as_u64) else {
+                    return vec![event];
+                };
+                match event["type"].as_str() {
+                    Some("content_block_start")
+                        if matches!(
+                            event["content_block"]["type"].as_str(),
+                            Some("tool_use" | "server_tool_use")
+                        ) =>
+                    {
+                        self.inputs.insert(index, String::new());
+                        vec![event]
+                    }
+                    Some("content_block_delta")
+                        if event["delta"]["type"] == "input_json_delta"
+                            && self.inputs.contains_key(&index) =>
+                    {
+                        if let (Some(input), Some(part)) = (
+                            self.inputs.get_mut(&index),
+                            event["delta"]["partial_json"].as_str(),
+                        ) {
+                            input.push_str(part);
+                        }
+                        Vec::new()
+                    }
+                    Some("content_block_stop") => match self.inputs.remove(&index) {
+                        Some(input) if !input.is_empty() => {
+                            vec![CoalesceToolDeltas::delta(index, input), event]
+                        }
+                        _ => vec![event],
+                    },
+                    _ => vec![event],
+                }
+            }
+        }
+        Some(Box::new(Coalesce::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    fn ctx() -> TransformContext {
+        TransformContext {
+            trace_id: "trace-1".to_string(),
+            source: ResponseSource::Passthrough,
+            streaming: true,
+            tags: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_strips_thinking_and_annotates() {
+        let pipeline = ResponsePipeline::default();
+        pipeline.push(Arc::new(StripThinking));
+        pipeline.push(Arc::new(TraceIdAnnotation));
+        let response = json!({
+            "type": "message",
+            "content": [
+                {"type": "thinking", "thinking": "hmm", "signature": "s"},
+                {"type": "text", "text": "answer"}
+            ]
+        });
+        let response = pipeline.apply(response, ctx()).await.unwrap();
+        assert_eq!(
+            response["content"],
+            json!([{"type": "text", "text": "answer"}])
+        );
+        assert_eq!(response["muninn"]["trace_id"], "trace-1");
+    }
+
+    struct Panics;
+
+    impl ResponseTransform for Panics {
+        fn name(&self) -> &str {
+            "panics"
+        }
+
+        fn transform(&self, _response: &mut Value, _ctx: &TransformContext) {
+            panic!("transform bug");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_returns_error_when_transform_panics() {
+        let pipeline = ResponsePipeline::default();
+        pipeline.push(Arc::new(Panics));
+        let result = pipeline.apply(json!({}), ctx()).await;
+        assert!(matches!(result, Err(RlmError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_apply_stream_renumbers_blocks() {
+        let pipeline = ResponsePipeline::default();
+        pipeline.push(Arc::new(StripThinking));
+        pipeline.push(Arc::new(TraceIdAnnotation));
+        let events = [
+            json!({"type": "message_start", "message": {"id": "m"}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "hmm"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_stop"}),
+        ];
+        let body: String = events
+            .iter()
+            .map(|e| format!("event: {}\ndata: {}\n\n", e["type"].as_str().unwrap(), e))
+            .collect();
+        let chunks: Vec<std::result::Result<Bytes, std::io::Error>> = body
+            .as_bytes()
+            .chunks(9)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+
+        let out: Vec<_> = pipeline
+            .apply_stream(Box::pin(futures::stream::iter(chunks)), &ctx())
+            .collect()
+            .await;
+        let out: Vec<u8> = out.into_iter().flat_map(|c| c.unwrap().to_vec()).collect();
+        let mut parser = SseParser::new();
+        let events: Vec<Value> = parser
+            .push(&out)
+            .iter()
+            .map(|f| SseEvent::parse(f).json().unwrap())
+            .collect();
+
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0]["message"]["muninn"]["trace_id"], "trace-1");
+        assert_eq!(events[1]["index"], 0);
+        assert_eq!(events[1]["content_block"]["type"], "text");
+        assert_eq!(events[2], json!({"type": "content_block_stop", "index": 0}));
+    }
+
+    fn run_stage(transform: &dyn ResponseTransform, events: Vec<Value>) -> Vec<Value> {
+        let mut stage = transform.stream(&ctx()).unwrap();
+        events.into_iter().flat_map(|e| stage.event(e)).collect()
+    }
+
+    #[test]
+    fn test_coalesce_tool_deltas() {
+        let delta = |index: u64, part: &str| {
+            json!({"type": "content_block_delta", "index": index,
+                   "delta": {"type": "input_json_delta", "partial_json": part}})
+        };
+        let events = vec![
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Reading"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1,
+                   "content_block": {"type": "tool_use", "id": "t1", "name": "Read", "input": {}}}),
+            delta(1, "{\"file_pa"),
+            delta(1, "th\": \"src/ma"),
+            delta(1, "in.rs\"}"),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2,
+                   "content_block": {"type": "tool_use", "id": "t2", "name": "Write", "input": {}}}),
+            delta(2, "{\"content\": \"fn ma"),
+            json!({"type": "content_block_stop", "index": 2}),
+        ];
+        let out = run_stage(&CoalesceToolDeltas, events);
+
+        // Text streams as before; each tool input arrives whole
+        assert_eq!(out.len(), 9);
+        assert_eq!(out[1]["delta"]["text"], "Reading");
+        assert_eq!(out[4], delta(1, "{\"file_path\": \"src/main.rs\"}"));
+        assert_eq!(out[5], json!({"type": "content_block_stop", "index": 1}));
+
+        // A call cut off mid-input still arrives as valid JSON
+        let repaired: Value =
+            serde_json::from_str(out[7]["delta"]["partial_json"].as_str().unwrap()).unwrap();
+        assert_eq!(repaired, json!({"INVALID_JSON": "{\"content\": \"fn ma"}));
+    }
+}
@@ -0,0 +1,374 @@
+//! Tools for querying commit-pinned graph partitions.
+//!
+//! Partitions are indexed with `muninn index --rev <rev>` into
+//! `.muninn/revisions/` (see [`muninn_graph::revision`]). These tools let
+//! the exploration list them, diff symbol sets between two of them (or one
+//! and the live graph), and run Cypher "as of" a revision.
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use muninn_graph::{GraphPartition, GraphStore, RevisionDiff, is_read_only_cypher, value_to_json};
+
+use crate::error::{Result, RlmError};
+use crate::graph_tools::SharedGraphStore;
+use crate::tool_params::ToolParams;
+use crate::tools::{Tool, ToolCategory, ToolMetadata, ToolResult};
+
+/// Name that selects the live graph instead of a partition.
+pub const CURRENT_REVISION: &str = "current";
+
+/// Where the partitions live, plus the live graph for `current`.
+#[derive(Clone)]
+struct Revisions {
+    dir: PathBuf,
+    current: Option<(SharedGraphStore, PathBuf)>,
+}
+
+impl Revisions {
+    fn partition(&self, name: &str) -> Result<GraphPartition> {
+        GraphPartition::new(&self.dir, name).map_err(|e| RlmError::ToolExecution(e.to_string()))
+    }
+
+    /// Run `f` on the graph for `name` and the root its file paths start with.
+    fn with_graph<T>(
+        &self,
+        name: &str,
+        f: impl FnOnce(&GraphStore, &Path) -> Result<T>,
+    ) -> Result<T> {
+        if name == CURRENT_REVISION {
+            let (store, root) = self.current.as_ref().ok_or_else(|| {
+                RlmError::ToolExecution("no live graph to compare against".to_string())
+            })?;
+            let store = store.lock().map_err(|e| {
+                RlmError::ToolExecution(format!("Failed to acquire store lock: {}", e))
+            })?;
+            return f(&store, root);
+        }
+        let partition = self.partition(name)?;
+        let manifest = partition
+            .manifest()
+            .map_err(|_| RlmError::ToolExecution(format!("revision '{}' is not indexed", name)))?;
+        let store = partition
+            .open()
+            .map_err(|e| RlmError::ToolExecution(e.to_string()))?;
+        f(&store, &manifest.root)
+    }
+}
+
+// ============================================================================
+// ListRevisionsTool
+// ============================================================================
+
+/// Tool listing the indexed revisions.
+pub struct ListRevisionsTool {
+    revisions: Revisions,
+}
+
+#[async_trait]
+impl Tool for ListRevisionsTool {
+    fn name(&self) -> &str {
+        "list_revisions"
+    }
+
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
+    fn description(&self) -> &str {
+        "List git revisions indexed as separate graphs, with the commit each was indexed at. \
+         Use their names with compare_revisions and query_revision."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {}
+        })
+    }
+
+    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult> {
+        let revisions: Vec<serde_json::Value> = GraphPartition::list(&self.revisions.dir)
+            .into_iter()
+            .map(|m| {
+                serde_json::json!({
+                    "name": m.name,
+                    "revision": m.revision,
+                    "commit": m.commit,
+                    "indexed_at": m.indexed_at,
+                })
+            })
+            .collect();
+        Ok(ToolResult::json(serde_json::json!({
+            "revisions": revisions,
+            "current_available": self.revisions.current.is_some(),
+        })))
+    }
+}
+
+// ============================================================================
+// CompareRevisionsTool
+// ============================================================================
+
+/// Tool diffing the symbol sets of two revisions.
+pub struct CompareRevisionsTool {
+    revisions: Revisions,
+    max_results: usize,
+}
+
+/// Parameters for [`CompareRevisionsTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct CompareRevisionsParams {
+    /// Revision to compare from (e.g. 'main')
+    base: String,
+    /// Revision to compare to; 'current' (the default) is the working tree's graph
+    head: Option<String>,
+}
+
+#[async_trait]
+impl Tool for CompareRevisionsTool {
+    fn name(&self) -> &str {
+        "compare_revisions"
+    }
+
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
+    fn description(&self) -> &str {
+        "Compare the symbols of two indexed revisions: which functions, types and methods were \
+         added, removed, or changed signature or file between them."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        CompareRevisionsParams::parameters_schema()
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+        let params = CompareRevisionsParams::from_params(params)?;
+        let head = params.head.as_deref().unwrap_or(CURRENT_REVISION);
+        let base = params.base.as_str();
+
+        let diff = self.revisions.with_graph(base, |base_store, base_root| {
+            self.revisions.with_graph(head, |head_store, head_root| {
+                RevisionDiff::between((base, base_store, base_root), (head, head_store, head_root))
+                    .map_err(|e| RlmError::ToolExecution(format!("Failed to compare: {}", e)))
+            })
+        })?;
+
+        let truncated = diff
+            .added
+            .len()
+            .max(diff.removed.len())
+            .max(diff.changed.len())
+            > self.max_results;
+        let output = serde_json::json!({
+            "base": diff.base,
+            "head": diff.head,
+            "added_count": diff.added.len(),
+            "removed_count": diff.removed.len(),
+            "changed_count": diff.changed.len(),
+            "added": &diff.added[..diff.added.len().min(self.max_results)],
+            "removed": &diff.removed[..diff.removed.len().min(self.max_results)],
+            "changed": &diff.changed[..diff.changed.len().min(self.max_results)],
+            "truncated": truncated,
+        });
+
+        let mut result = ToolResult::json(output);
+        result.metadata =
+            ToolMetadata::with_source(format!("{}..{}", base, head)).with_tag("revisions");
+        Ok(result)
+    }
+}
+
+// ============================================================================
+// QueryRevisionTool
+// ============================================================================
+
+/// Tool running a read-only Cypher query against one revision's graph.
+pub struct QueryRevisionTool {
+    revisions: Revisions,
+    max_results: usize,
+}
+
+/// Parameters for [`QueryRevisionTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct QueryRevisionParams {
+    /// Indexed revision to query (see list_revisions)
+    revision: String,
+    /// Read-only Cypher query, as for graph_query
+    query: String,
+}
+
+#[async_trait]
+impl Tool for QueryRevisionTool {
+    fn name(&self) -> &str {
+        "query_revision"
+    }
+
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Graph
+    }
+
+    fn description(&self) -> &str {
+        "Run a read-only Cypher query against the code graph as of an indexed revision, \
+         with the same labels and relationships as graph_query."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        QueryRevisionParams::parameters_schema()
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+        let params = QueryRevisionParams::from_params(params)?;
+        if !is_read_only_cypher(&params.query) {
+            return Ok(ToolResult::error(
+                "Only read-only queries are allowed",
+                true,
+            ));
+        }
+
+        let rows = self.revisions.with_graph(&params.revision, |store, _| {
+            let result = store
+                .query(&params.query)
+                .map_err(|e| RlmError::ToolExecution(format!("Graph query failed: {}", e)))?;
+            Ok(result
+                .iter()
+                .take(self.max_results)
+                .map(|row| {
+                    let obj: serde_json::Map<String, serde_json::Value> = row
+                        .columns()
+                        .iter()
+                        .filter_map(|col| {
+                            row.get_value(col).map(|v| (col.clone(), value_to_json(v)))
+                        })
+                        .collect();
+                    serde_json::Value::Object(obj)
+                })
+                .collect::<Vec<_>>())
+        })?;
+
+        let mut result = ToolResult::json(serde_json::json!({
+            "revision": params.revision,
+            "rows": rows,
+            "count": rows.len(),
+        }));
+        result.metadata = ToolMetadata::with_source(&params.query).with_tag("revisions");
+        Ok(result)
+    }
+}
+
+/// Create the revision tools for partitions under `revisions_dir`.
+/// `current` is the live graph and the root its file paths start with,
+/// used when a tool is asked for the `current` revision.
+pub fn create_revision_tools(
+    revisions_dir: impl Into<PathBuf>,
+    current: Option<(SharedGraphStore, PathBuf)>,
+) -> Vec<Box<dyn Tool>> {
+    let revisions = Revisions {
+        dir: revisions_dir.into(),
+        current,
+    };
+    vec![
+        Box::new(ListRevisionsTool {
+            revisions: revisions.clone(),
+        }),
+        Box::new(CompareRevisionsTool {
+            revisions: revisions.clone(),
+            max_results: 100,
+        }),
+        Box::new(QueryRevisionTool {
+            revisions,
+            max_results: 100,
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_tools::wrap_store;
+    use muninn_graph::{RevisionManifest, Symbol, SymbolKind};
+    use serial_test::serial;
+
+    fn tool<'a>(tools: &'a [Box<dyn Tool>], name: &str) -> &'a dyn Tool {
+        tools.iter().find(|t| t.name() == name).unwrap().as_ref()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_compare_and_query_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let partition = GraphPartition::new(dir.path(), "main").unwrap();
+        partition
+            .write_manifest(&RevisionManifest {
+                name: "main".to_string(),
+                revision: "main".to_string(),
+                commit: "abc123".to_string(),
+                root: PathBuf::from("/tmp/main"),
+                indexed_at: 0,
+            })
+            .unwrap();
+        GraphStore::open(partition.graph_path())
+            .unwrap()
+            .insert_node(&Symbol::new(
+                "old_fn",
+                SymbolKind::Function,
+                "/tmp/main/src/lib.rs",
+                1,
+                2,
+            ))
+            .unwrap();
+
+        let live = GraphStore::open_in_memory().unwrap();
+        live.insert_node(&Symbol::new(
+            "new_fn",
+            SymbolKind::Function,
+            "/repo/src/lib.rs",
+            1,
+            2,
+        ))
+        .unwrap();
+
+        let tools =
+            create_revision_tools(dir.path(), Some((wrap_store(live), PathBuf::from("/repo"))));
+
+        let listed = tool(&tools, "list_revisions")
+            .execute(serde_json::json!({}))
+            .await
+            .unwrap();
+        assert!(listed.to_string_content().contains("abc123"));
+
+        let diff = tool(&tools, "compare_revisions")
+            .execute(serde_json::json!({ "base": "main" }))
+            .await
+            .unwrap();
+        let text = diff.to_string_content();
+        assert!(
+            text.contains("src/lib.rs::new_fn") && text.contains("src/lib.rs::old_fn"),
+            "{}",
+            text
+        );
+
+        let rows = tool(&tools, "query_revision")
+            .execute(serde_json::json!({
+                "revision": "main",
+                "query": "MATCH (n {name: 'old_fn'}) RETURN n.name AS name"
+            }))
+            .await
+            .unwrap();
+        assert!(
+            rows.to_string_content().contains("\"count\": 1"),
+            "{}",
+            rows.to_string_content()
+        );
+
+        let write = tool(&tools, "query_revision")
+            .execute(serde_json::json!({ "revision": "main", "query": "MATCH (n) DELETE n" }))
+            .await
+            .unwrap();
+        assert!(write.is_error());
+    }
+}
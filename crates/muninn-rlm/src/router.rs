@@ -25,19 +25,30 @@
 //! strategy-based routing
 //!   ├─ AlwaysPassthrough ──────────▶ passthrough
 //!   ├─ AlwaysRlm ──────────────────▶ rlm
-//!   └─ Llm ─▶ route_via_llm() ────▶ decision
+//!   └─ Llm ─▶ RouterBackend::route() ─▶ decision
+//!                promoted? passthrough ─▶ rlm
 //! ```
 //!
+//! The `Llm` strategy delegates to a [`RouterBackend`]. LLM backends are
+//! adapted via [`LlmRouterBackend`]; classifiers, remote services, or rules
+//! engines can implement the trait directly.
+//!
 //! Note: The JSON flag (`request.muninn.recursive`) is checked in proxy before routing.
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
+use async_trait::async_trait;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use crate::backend::LLMBackend;
+use crate::engine::TIMING_ROUTING;
+use crate::error::Result;
+use crate::feedback::Feedback;
+use crate::prompts::RouteCategory;
 use crate::types::{
     CompletionRequest, CompletionResponse, Message, Role, SystemPrompt, ToolChoice, ToolDefinition,
 };
@@ -52,7 +63,9 @@ pub struct RouterTraceData {
     /// The routing strategy used.
     pub strategy: String,
     /// How the decision was made: "disabled", "no_message", "internal_bypass",
-    /// "passthrough_trigger", "rlm_trigger", "forced_passthrough", "forced_rlm", "llm".
+    /// "passthrough_trigger", "rlm_trigger", "forced_passthrough", "forced_rlm",
+    /// "promoted" (see [`Router::promote`]), or the router backend's name
+    /// ("llm" for [`LlmRouterBackend`]).
     pub method: String,
     /// Model requested in the original request.
     pub model: String,
@@ -74,14 +87,19 @@ pub struct RouterTraceData {
     pub decision: String,
     /// Reason for the decision (if RLM).
     pub reason: Option<String>,
+    /// Request category the router picked (if RLM and it picked one).
+    pub category: Option<RouteCategory>,
     /// Time taken to make the decision (ms).
     pub decision_time_ms: u64,
 }
 
 /// Training data record for routing decisions.
 /// This format is designed for fine-tuning a routing SLM.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingTrainingRecord {
+    /// Trace of the routed request.
+    #[serde(default)]
+    pub trace_id: String,
     /// Timestamp of the decision.
     pub timestamp: String,
     /// The user's request (last message).
@@ -92,6 +110,10 @@ pub struct RoutingTrainingRecord {
     pub reason: String,
     /// How the decision was made.
     pub method: String,
+    /// For a `user_correction` record: the request whose trigger
+    /// overruled this decision.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_by: Option<String>,
 }
 
 // ============================================================================
@@ -107,6 +129,9 @@ pub enum RouteDecision {
     Rlm {
         /// Reason for RLM routing (for logging/debugging).
         reason: String,
+        /// Kind of request, used to pick the exploration prompt and tools.
+        /// `None` explores with the generic prompt.
+        category: Option<RouteCategory>,
     },
 }
 
@@ -118,6 +143,23 @@ impl RouteDecision {
     pub fn rlm(reason: impl Into<String>) -> Self {
         Self::Rlm {
             reason: reason.into(),
+            category: None,
+        }
+    }
+
+    /// Attach a category to an RLM decision; passthrough is left as is.
+    pub fn with_category(mut self, category: Option<RouteCategory>) -> Self {
+        if let Self::Rlm { category: c, .. } = &mut self {
+            *c = category;
+        }
+        self
+    }
+
+    /// The category of an RLM decision.
+    pub fn category(&self) -> Option<RouteCategory> {
+        match self {
+            Self::Rlm { category, .. } => *category,
+            Self::Passthrough => None,
         }
     }
 
@@ -137,7 +179,7 @@ impl RouteDecision {
 /// Strategy for making routing decisions.
 #[derive(Debug, Clone, Default)]
 pub enum RouterStrategy {
-    /// Use LLM to classify requests (default).
+    /// Delegate to the configured [`RouterBackend`], an LLM by default.
     #[default]
     Llm,
     /// Always use RLM (for testing/development).
@@ -155,6 +197,12 @@ pub struct RouterConfig {
     pub enabled: bool,
     /// Model to use for LLM-based routing (if different from default).
     pub router_model: Option<String>,
+    /// Ask the router LLM for a JSON object instead of a `route_decision`
+    /// tool call, for models with unreliable tool calling.
+    pub json_mode: bool,
+    /// Replaces the built-in router system prompt (e.g. for an experiment
+    /// variant).
+    pub system_prompt: Option<String>,
 }
 
 impl Default for RouterConfig {
@@ -163,6 +211,8 @@ impl Default for RouterConfig {
             strategy: RouterStrategy::Llm,
             enabled: true,
             router_model: None,
+            json_mode: false,
+            system_prompt: None,
         }
     }
 }
@@ -249,26 +299,67 @@ fn extract_routing_input(request: &CompletionRequest) -> Option<RoutingInput> {
 // Fast Path Checks (no LLM needed)
 // ============================================================================
 
+/// Internal requests the agent makes on its own behalf, not the user's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InternalRequest {
+    /// Conversation title generation.
+    Title,
+    /// Autocomplete suggestions for the user's next prompt.
+    Suggestion,
+}
+
 /// Patterns that should always passthrough - internal Claude Code requests.
-const PASSTHROUGH_BYPASS_PATTERNS: &[&str] = &[
-    r"(?i)^please write a \d+-\d+ word title", // Title generation
-    r"(?i)^you are now a prompt suggestion generator", // Autocomplete suggestions
+const PASSTHROUGH_BYPASS_PATTERNS: &[(&str, InternalRequest)] = &[
+    (
+        r"(?i)^please write a \d+-\d+ word title",
+        InternalRequest::Title,
+    ),
+    (
+        r"(?i)^you are now a prompt suggestion generator",
+        InternalRequest::Suggestion,
+    ),
 ];
 
+/// Which internal request `text` is, if any.
+fn internal_kind(text: &str) -> Option<InternalRequest> {
+    PASSTHROUGH_BYPASS_PATTERNS
+        .iter()
+        .find(|(pattern, _)| Regex::new(pattern).is_ok_and(|re| re.is_match(text)))
+        .map(|(_, kind)| *kind)
+}
+
 /// Check if text matches internal bypass patterns (title gen, autocomplete).
 fn should_bypass(text: &str) -> bool {
-    for pattern in PASSTHROUGH_BYPASS_PATTERNS {
-        if let Ok(re) = Regex::new(pattern) {
-            if re.is_match(text) {
-                tracing::debug!(
-                    pattern,
-                    "Bypassing to passthrough: matches internal request pattern"
-                );
-                return true;
-            }
+    match internal_kind(text) {
+        Some(kind) => {
+            tracing::debug!(
+                ?kind,
+                "Bypassing to passthrough: matches internal request pattern"
+            );
+            true
         }
+        None => false,
     }
-    false
+}
+
+/// The internal request (title gen, autocomplete) `request` is, if any.
+/// The proxy can answer these locally instead of passing them through.
+pub fn internal_request(request: &CompletionRequest) -> Option<InternalRequest> {
+    internal_kind(&extract_routing_input(request)?.text)
+}
+
+/// The feedback (`{at}muninn good` / `{at}muninn bad`) `request` gives on
+/// the previous answer, if it is one. The proxy records it instead of
+/// routing the request.
+pub fn feedback_request(request: &CompletionRequest) -> Option<Feedback> {
+    Feedback::parse(&extract_routing_input(request)?.text)
+}
+
+/// The user's latest message with control tags stripped, if it has any
+/// text (tool-result turns don't).
+pub fn user_question(request: &CompletionRequest) -> Option<String> {
+    extract_routing_input(request).map(|input| input.text)
 }
 
 /// Regex pattern for explicit RLM trigger ({at}muninn explore).
@@ -302,6 +393,21 @@ fn has_passthrough_trigger(text: &str) -> bool {
 struct RouteDecisionInput {
     route: String,
     reason: String,
+    /// Unknown category names are ignored rather than failing the parse.
+    #[serde(default)]
+    category: Option<String>,
+}
+
+impl RouteDecisionInput {
+    fn into_route(self) -> RouteDecision {
+        let route = self.route.to_lowercase();
+        if route == "rlm" || route == "explore" {
+            let category = self.category.as_deref().and_then(RouteCategory::parse);
+            RouteDecision::rlm(format!("Router LLM: {}", self.reason)).with_category(category)
+        } else {
+            RouteDecision::passthrough()
+        }
+    }
 }
 
 /// System prompt for the router LLM.
@@ -330,6 +436,13 @@ refactors, diagnostic questions — they all benefit from project \
 context, even when the user didn't ask to 'explore'. If you're \
 unsure, pick 'rlm'.";
 
+/// Appended to the system prompt in JSON mode, replacing the tool schema.
+const ROUTER_JSON_INSTRUCTIONS: &str = "\n\n\
+Respond with only a JSON object of the form \
+{\"route\": \"rlm\" | \"passthrough\", \"reason\": \"<1-2 sentences>\", \
+\"category\": \"code_question\" | \"debugging\" | \"refactor\" | \"docs\" | \"ops\"}. \
+Include \"category\" only when the route is \"rlm\".";
+
 /// Build the user message for the router LLM.
 fn build_router_user_message(user_request: &str) -> String {
     format!(
@@ -373,7 +486,15 @@ the codebase. Floor cases:
 If there's any plausible benefit to code context, pick "rlm". The
 upstream model still has its own grep / read tools as a fallback if
 muninn over-routes — over-routing wastes a bit of compute, but
-under-routing loses the context muninn was built to provide."#,
+under-routing loses the context muninn was built to provide.
+
+CATEGORY (rlm only) — pick the one that fits best:
+
+- "code_question": how something works or where it lives
+- "debugging": errors, failing tests, unexpected behaviour
+- "refactor": restructuring, renaming or extending existing code
+- "docs": library APIs, READMEs, doc comments
+- "ops": builds, CI, deployment, configuration files"#,
         user_request
     )
 }
@@ -394,6 +515,11 @@ fn route_decision_tool() -> ToolDefinition {
                 "reason": {
                     "type": "string",
                     "description": "Brief explanation (1-2 sentences)."
+                },
+                "category": {
+                    "type": "string",
+                    "enum": RouteCategory::ALL.map(|c| c.as_str()),
+                    "description": "For 'rlm' only: the kind of request, which selects the exploration prompt and tools."
                 }
             },
             "required": ["route", "reason"]
@@ -402,19 +528,41 @@ fn route_decision_tool() -> ToolDefinition {
 }
 
 /// Build the CompletionRequest for the router LLM.
-fn build_router_request(user_message: &str, router_model: &Option<String>) -> CompletionRequest {
+///
+/// In JSON mode the decision is requested as a bare JSON object instead of
+/// a forced `route_decision` tool call.
+fn build_router_request(
+    user_message: &str,
+    router_model: &Option<String>,
+    json_mode: bool,
+    system_prompt: Option<&str>,
+) -> CompletionRequest {
     let model = router_model.clone().unwrap_or_else(|| "router".to_string());
+    let prompt = system_prompt.unwrap_or(ROUTER_SYSTEM_PROMPT);
+    let (system, tools, tool_choice) = if json_mode {
+        (
+            format!("{prompt}{ROUTER_JSON_INSTRUCTIONS}"),
+            Vec::new(),
+            None,
+        )
+    } else {
+        (
+            prompt.to_string(),
+            vec![route_decision_tool()],
+            Some(ToolChoice::Tool {
+                name: "route_decision".to_string(),
+            }),
+        )
+    };
 
     CompletionRequest {
         model,
         messages: vec![Message::user(build_router_user_message(user_message))],
-        system: Some(SystemPrompt::Text(ROUTER_SYSTEM_PROMPT.to_string())),
+        system: Some(SystemPrompt::Text(system)),
         max_tokens: 256,
         temperature: Some(0.0),
-        tools: vec![route_decision_tool()],
-        tool_choice: Some(ToolChoice::Tool {
-            name: "route_decision".to_string(),
-        }),
+        tools,
+        tool_choice,
         stream: false,
         stop_sequences: Vec::new(),
         top_p: None,
@@ -431,14 +579,7 @@ fn parse_route_response(response: &CompletionResponse) -> RouteDecision {
     if let Some(tool_use) = response.tool_uses().first() {
         if tool_use.name == "route_decision" {
             match serde_json::from_value::<RouteDecisionInput>(tool_use.input.clone()) {
-                Ok(decision) => {
-                    let route = decision.route.to_lowercase();
-                    if route == "rlm" || route == "explore" {
-                        return RouteDecision::rlm(format!("Router LLM: {}", decision.reason));
-                    } else {
-                        return RouteDecision::passthrough();
-                    }
-                }
+                Ok(decision) => return decision.into_route(),
                 Err(e) => {
                     tracing::warn!(error = %e, "Failed to parse route_decision tool input");
                 }
@@ -446,8 +587,17 @@ fn parse_route_response(response: &CompletionResponse) -> RouteDecision {
         }
     }
 
+    // JSON mode: the decision is the text itself (possibly fenced)
+    let text = response.text();
+    if let (Some(start), Some(end)) = (text.find('{'), text.rfind('}'))
+        && start < end
+        && let Ok(decision) = serde_json::from_str::<RouteDecisionInput>(&text[start..=end])
+    {
+        return decision.into_route();
+    }
+
     // Fallback: check text response for keywords
-    let text = response.text().to_lowercase();
+    let text = text.to_lowercase();
     if text.contains("rlm") || text.contains("explore") {
         RouteDecision::rlm("Router LLM fallback: text contained rlm/explore")
     } else {
@@ -455,6 +605,89 @@ fn parse_route_response(response: &CompletionResponse) -> RouteDecision {
     }
 }
 
+// ============================================================================
+// Router Backends
+// ============================================================================
+
+/// Decision-maker behind [`RouterStrategy::Llm`].
+///
+/// Much smaller than [`LLMBackend`]: a router backend only turns a cleaned
+/// user message into a [`RouteDecision`], so non-LLM routers don't have to
+/// pretend to be chat models.
+#[async_trait]
+pub trait RouterBackend: Send + Sync {
+    /// Name recorded as the routing method in trace data.
+    fn name(&self) -> &str;
+
+    /// Decide how to handle a request.
+    ///
+    /// `user_message` is the last user message with control tags stripped;
+    /// `request` is the original request. Errors fall back to passthrough.
+    async fn route(&self, user_message: &str, request: &CompletionRequest)
+    -> Result<RouteDecision>;
+}
+
+/// Adapter that routes by asking an [`LLMBackend`] to call the
+/// `route_decision` tool.
+pub struct LlmRouterBackend {
+    llm: Arc<dyn LLMBackend>,
+    model: Option<String>,
+    json_mode: bool,
+    system_prompt: Option<String>,
+}
+
+impl LlmRouterBackend {
+    /// Wrap an LLM backend.
+    pub fn new(llm: Arc<dyn LLMBackend>) -> Self {
+        Self {
+            llm,
+            model: None,
+            json_mode: false,
+            system_prompt: None,
+        }
+    }
+
+    /// Set the model requested from the backend (defaults to "router").
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Ask for a JSON object in the reply instead of a tool call.
+    pub fn with_json_mode(mut self, json_mode: bool) -> Self {
+        self.json_mode = json_mode;
+        self
+    }
+
+    /// Use `prompt` instead of the built-in router system prompt.
+    pub fn with_system_prompt(mut self, prompt: Option<String>) -> Self {
+        self.system_prompt = prompt;
+        self
+    }
+}
+
+#[async_trait]
+impl RouterBackend for LlmRouterBackend {
+    fn name(&self) -> &str {
+        "llm"
+    }
+
+    async fn route(
+        &self,
+        user_message: &str,
+        _request: &CompletionRequest,
+    ) -> Result<RouteDecision> {
+        let request = build_router_request(
+            user_message,
+            &self.model,
+            self.json_mode,
+            self.system_prompt.as_deref(),
+        );
+        let response = self.llm.complete(request).await?;
+        Ok(parse_route_response(&response))
+    }
+}
+
 // ============================================================================
 // Router
 // ============================================================================
@@ -462,29 +695,71 @@ fn parse_route_response(response: &CompletionResponse) -> RouteDecision {
 /// Request router that decides between RLM and passthrough.
 pub struct Router {
     config: RouterConfig,
-    llm: Option<Arc<dyn LLMBackend>>,
+    backend: Option<Arc<dyn RouterBackend>>,
+    /// Whether [`Router::promote`] takes effect.
+    promote_on_correction: bool,
+    /// Set once the user corrected a passthrough decision; `Llm`-strategy
+    /// passthrough decisions then explore instead.
+    promoted: AtomicBool,
 }
 
 impl Router {
     /// Create a new router with default configuration.
     pub fn new() -> Self {
-        Self {
-            config: RouterConfig::default(),
-            llm: None,
-        }
+        Self::with_config(RouterConfig::default())
     }
 
     /// Create with custom configuration.
     pub fn with_config(config: RouterConfig) -> Self {
-        Self { config, llm: None }
+        Self {
+            config,
+            backend: None,
+            promote_on_correction: false,
+            promoted: AtomicBool::new(false),
+        }
     }
 
     /// Set the LLM backend for LLM-based routing.
-    pub fn with_llm(mut self, llm: Arc<dyn LLMBackend>) -> Self {
-        self.llm = Some(llm);
+    ///
+    /// Shorthand for [`Router::with_backend`] with an [`LlmRouterBackend`]
+    /// using the configured `router_model`, `json_mode` and `system_prompt`.
+    pub fn with_llm(self, llm: Arc<dyn LLMBackend>) -> Self {
+        let model = self.config.router_model.clone();
+        let backend = LlmRouterBackend::new(llm)
+            .with_model(model)
+            .with_json_mode(self.config.json_mode)
+            .with_system_prompt(self.config.system_prompt.clone());
+        self.with_backend(Arc::new(backend))
+    }
+
+    /// Set the backend used by the `Llm` strategy.
+    pub fn with_backend(mut self, backend: Arc<dyn RouterBackend>) -> Self {
+        self.backend = Some(backend);
         self
     }
 
+    /// Let [`Router::promote`] bias this router toward RLM.
+    pub fn with_correction_promotion(mut self, enable: bool) -> Self {
+        self.promote_on_correction = enable;
+        self
+    }
+
+    /// The user overruled a passthrough decision with `{at}muninn explore`:
+    /// from now on, route the backend's passthrough decisions to RLM too.
+    /// Explicit triggers still win. Returns whether the router is promoted
+    /// (always `false` without [`Router::with_correction_promotion`]).
+    pub fn promote(&self) -> bool {
+        if self.promote_on_correction && !self.promoted.swap(true, Ordering::Relaxed) {
+            tracing::info!("Routing promoted toward RLM after a user correction");
+        }
+        self.is_promoted()
+    }
+
+    /// Whether [`Router::promote`] took effect.
+    pub fn is_promoted(&self) -> bool {
+        self.promoted.load(Ordering::Relaxed)
+    }
+
     /// Route a request to either passthrough or RLM.
     ///
     /// # Routing Phases
@@ -495,8 +770,18 @@ impl Router {
     /// 4. **Text triggers** - Check for explicit triggers:
     ///    - `{at}muninn passthrough` - Force passthrough to upstream
     ///    - `{at}muninn explore` - Force RLM processing
-    /// 5. **Strategy** - Use configured strategy (LLM, AlwaysRlm, AlwaysPassthrough)
+    /// 5. **Strategy** - Use configured strategy (LLM, AlwaysRlm, AlwaysPassthrough);
+    ///    a [promoted](Self::promote) router explores instead of passing through
     pub async fn route(&self, request: &CompletionRequest) -> RouteDecision {
+        self.route_traced(request).await.0
+    }
+
+    /// Route a request like [`Router::route`], also returning the trace
+    /// data recorded for the decision.
+    pub async fn route_traced(
+        &self,
+        request: &CompletionRequest,
+    ) -> (RouteDecision, RouterTraceData) {
         let start = Instant::now();
 
         // Phase 1: Quick exit if disabled
@@ -561,36 +846,53 @@ impl Router {
                 (RouteDecision::passthrough(), "forced_passthrough")
             }
             RouterStrategy::AlwaysRlm => (RouteDecision::rlm("Strategy: AlwaysRlm"), "forced_rlm"),
-            RouterStrategy::Llm => (self.route_via_llm(&input.text).await, "llm"),
+            RouterStrategy::Llm => {
+                let decision = self.route_via_backend(&input.text, request).await;
+                let method = self.backend.as_ref().map_or("llm", |b| b.name());
+                if decision.is_passthrough() && self.is_promoted() {
+                    (
+                        RouteDecision::rlm("Promoted: the user corrected a passthrough answer"),
+                        "promoted",
+                    )
+                } else {
+                    (decision, method)
+                }
+            }
         };
 
         self.finish(decision, method, Some(&input.text), request, start)
     }
 
-    /// Call the router LLM to make a routing decision.
-    async fn route_via_llm(&self, user_message: &str) -> RouteDecision {
-        let Some(llm) = &self.llm else {
-            tracing::error!("Router LLM not configured");
+    /// Ask the router backend to make a routing decision.
+    async fn route_via_backend(
+        &self,
+        user_message: &str,
+        request: &CompletionRequest,
+    ) -> RouteDecision {
+        let Some(backend) = &self.backend else {
+            tracing::error!("Router backend not configured");
             return RouteDecision::passthrough();
         };
 
-        let request = build_router_request(user_message, &self.config.router_model);
-
-        match llm.complete(request).await {
-            Ok(response) => parse_route_response(&response),
+        match backend.route(user_message, request).await {
+            Ok(decision) => decision,
             Err(e) => {
-                // Error, not warn: a router LLM failure produces the
+                // Error, not warn: a router backend failure produces the
                 // same RouteDecision as a real passthrough decision,
                 // so this log line is the only signal that distinguishes
                 // "router said the prompt didn't need exploration" from
                 // "router couldn't reach its backend." Make it greppable.
-                tracing::error!(error = %e, "Router LLM failed — falling back to passthrough");
+                tracing::error!(
+                    backend = backend.name(),
+                    error = %e,
+                    "Router backend failed — falling back to passthrough"
+                );
                 RouteDecision::passthrough()
             }
         }
     }
 
-    /// Emit trace data and return the decision.
+    /// Emit trace data and return it with the decision.
     fn finish(
         &self,
         decision: RouteDecision,
@@ -598,7 +900,7 @@ impl Router {
         cleaned_message: Option<&str>,
         request: &CompletionRequest,
         start: Instant,
-    ) -> RouteDecision {
+    ) -> (RouteDecision, RouterTraceData) {
         let trace_data = RouterTraceData {
             strategy: format!("{:?}", self.config.strategy),
             method: method.to_string(),
@@ -613,16 +915,18 @@ impl Router {
                 "passthrough".to_string()
             },
             reason: match &decision {
-                RouteDecision::Rlm { reason } => Some(reason.clone()),
+                RouteDecision::Rlm { reason, .. } => Some(reason.clone()),
                 RouteDecision::Passthrough => None,
             },
+            category: decision.category(),
             decision_time_ms: start.elapsed().as_millis() as u64,
         };
 
-        muninn_tracing::start_span_with_data("router_decision", &trace_data);
-        muninn_tracing::end_span_ok();
+        let span = muninn_tracing::span_with_data("router_decision", &trace_data);
+        span.timing_segment(TIMING_ROUTING, trace_data.decision_time_ms);
+        span.ok();
 
-        decision
+        (decision, trace_data)
     }
 }
 
@@ -717,12 +1021,166 @@ mod tests {
         assert!(decision.is_rlm());
     }
 
+    #[tokio::test]
+    async fn test_llm_routes_with_category() {
+        let mut with_category = mock_route_response("rlm", "Failing test");
+        let mut unknown = mock_route_response("rlm", "Chatty");
+        for (response, category) in [(&mut with_category, "Debugging"), (&mut unknown, "chat")] {
+            if let ContentBlock::ToolUse { input, .. } = &mut response.content[0] {
+                input["category"] = category.into();
+            }
+        }
+        let backend = Arc::new(MockBackend::new(vec![with_category, unknown]));
+        let router = Router::new().with_llm(backend.clone());
+        let request = make_request(vec![("user", "Why does test_parse fail?")]);
+
+        let decision = router.route(&request).await;
+        assert_eq!(decision.category(), Some(RouteCategory::Debugging));
+        let decision = router.route(&request).await;
+        assert!(decision.is_rlm());
+        assert_eq!(decision.category(), None);
+
+        let schema = &backend.requests()[0].tools[0].input_schema;
+        assert_eq!(schema["properties"]["category"]["enum"][1], "debugging");
+    }
+
+    /// Keyword rules standing in for a non-LLM router.
+    struct KeywordRouter;
+
+    #[async_trait]
+    impl RouterBackend for KeywordRouter {
+        fn name(&self) -> &str {
+            "keywords"
+        }
+
+        async fn route(
+            &self,
+            user_message: &str,
+            _request: &CompletionRequest,
+        ) -> Result<RouteDecision> {
+            if user_message.contains("parse()") {
+                Ok(RouteDecision::rlm("keyword: parse()"))
+            } else if user_message.contains("boom") {
+                Err(crate::error::RlmError::Backend("rules unavailable".into()))
+            } else {
+                Ok(RouteDecision::passthrough())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_mode_routes_from_text() {
+        let backend = Arc::new(MockBackend::new(vec![CompletionResponse::new(
+            "test-id",
+            "test-model",
+            vec![ContentBlock::Text {
+                text: "```json\n{\"route\": \"rlm\", \"reason\": \"Touches the config\"}\n```"
+                    .to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        )]));
+        let router = Router::with_config(RouterConfig {
+            json_mode: true,
+            ..Default::default()
+        })
+        .with_llm(backend.clone());
+        let request = make_request(vec![("user", "Wire the new field into the config")]);
+
+        let decision = router.route(&request).await;
+        assert!(decision.is_rlm());
+
+        let sent = &backend.requests()[0];
+        assert!(sent.tools.is_empty());
+        assert!(sent.tool_choice.is_none());
+        assert!(
+            sent.system
+                .as_ref()
+                .unwrap()
+                .to_text()
+                .contains("JSON object")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_override() {
+        let backend = Arc::new(MockBackend::new(vec![CompletionResponse::new(
+            "test-id",
+            "test-model",
+            vec![ContentBlock::Text {
+                text: "{\"route\": \"passthrough\", \"reason\": \"Chat\"}".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        )]));
+        let router = Router::with_config(RouterConfig {
+            json_mode: true,
+            system_prompt: Some("Route terse questions to passthrough.".to_string()),
+            ..Default::default()
+        })
+        .with_llm(backend.clone());
+
+        router
+            .route(&make_request(vec![("user", "Where is the config loaded?")]))
+            .await;
+        let system = backend.requests()[0].system.as_ref().unwrap().to_text();
+        assert!(system.starts_with("Route terse questions to passthrough."));
+        assert!(system.contains("JSON object"));
+        assert!(!system.contains("THIS IS THE DEFAULT"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_router_backend() {
+        let router = Router::new().with_backend(Arc::new(KeywordRouter));
+
+        let request = make_request(vec![("user", "Find all functions that call parse()")]);
+        assert_eq!(
+            router.route(&request).await,
+            RouteDecision::rlm("keyword: parse()")
+        );
+
+        let request = make_request(vec![("user", "What is 2 + 2?")]);
+        assert!(router.route(&request).await.is_passthrough());
+    }
+
+    #[tokio::test]
+    async fn test_promoted_router_explores_backend_passthrough() {
+        let passthrough = make_request(vec![("user", "What is 2 + 2?")]);
+
+        let router = Router::new().with_backend(Arc::new(KeywordRouter));
+        assert!(!router.promote());
+        assert!(router.route(&passthrough).await.is_passthrough());
+
+        let router = Router::new()
+            .with_backend(Arc::new(KeywordRouter))
+            .with_correction_promotion(true);
+        assert!(router.promote());
+        let (decision, trace) = router.route_traced(&passthrough).await;
+        assert!(decision.is_rlm());
+        assert_eq!(trace.method, "promoted");
+
+        // Explicit triggers still win
+        let request = make_request(vec![("user", "@muninn passthrough\nWhat is 2 + 2?")]);
+        assert!(router.route(&request).await.is_passthrough());
+    }
+
+    #[tokio::test]
+    async fn test_router_backend_error_falls_back_to_passthrough() {
+        let router = Router::new().with_backend(Arc::new(KeywordRouter));
+        let request = make_request(vec![("user", "boom")]);
+        assert!(router.route(&request).await.is_passthrough());
+    }
+
     #[tokio::test]
     async fn test_strategy_always_passthrough() {
         let config = RouterConfig {
             strategy: RouterStrategy::AlwaysPassthrough,
             enabled: true,
             router_model: None,
+            json_mode: false,
+            system_prompt: None,
         };
         let router = Router::with_config(config);
         let request = make_request(vec![("user", "Explain the entire codebase")]);
@@ -737,6 +1195,8 @@ mod tests {
             strategy: RouterStrategy::AlwaysRlm,
             enabled: true,
             router_model: None,
+            json_mode: false,
+            system_prompt: None,
         };
         let router = Router::with_config(config);
         let request = make_request(vec![("user", "Hello")]);
@@ -751,6 +1211,8 @@ mod tests {
             strategy: RouterStrategy::Llm,
             enabled: false,
             router_model: None,
+            json_mode: false,
+            system_prompt: None,
         };
         let router = Router::with_config(config);
         let request = make_request(vec![("user", "Explain the entire codebase architecture")]);
@@ -804,6 +1266,8 @@ mod tests {
             strategy: RouterStrategy::AlwaysRlm,
             enabled: true,
             router_model: None,
+            json_mode: false,
+            system_prompt: None,
         };
         let router = Router::with_config(config);
         let request = make_request(vec![("user", "@muninn passthrough explain the codebase")]);
@@ -838,6 +1302,8 @@ mod tests {
             strategy: RouterStrategy::AlwaysRlm,
             enabled: true,
             router_model: None,
+            json_mode: false,
+            system_prompt: None,
         };
         let router = Router::with_config(config);
         let request = make_request(vec![("user", "context\n@muninn passthrough")]);
@@ -898,6 +1364,22 @@ mod tests {
         assert!(decision.is_passthrough());
     }
 
+    #[test]
+    fn test_internal_request() {
+        let request = |text: &str| CompletionRequest::new("m", vec![Message::user(text)], 16);
+        assert_eq!(
+            internal_request(&request("Please write a 5-10 word title\nUser: hi")),
+            Some(InternalRequest::Title)
+        );
+        assert_eq!(
+            internal_request(&request(
+                "<system-reminder>x</system-reminder>You are now a prompt suggestion generator"
+            )),
+            Some(InternalRequest::Suggestion)
+        );
+        assert_eq!(internal_request(&request("how does routing work?")), None);
+    }
+
     #[test]
     fn test_has_rlm_trigger() {
         // Valid triggers (at start of line)
@@ -0,0 +1,232 @@
+//! Router training log.
+//!
+//! Every routed request with a user message is appended to `routing.jsonl`
+//! in the session directory as a [`RoutingTrainingRecord`]. When the user
+//! overrules the router on the very next request (`@muninn explore` right
+//! after a passthrough answer, or `@muninn passthrough` right after an
+//! exploration), a `user_correction` record relabels the misrouted request
+//! and links it to the correcting one, so the latest record per trace id is
+//! the label to train on.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Utc;
+
+use crate::router::{RouterTraceData, RoutingTrainingRecord};
+
+/// File name of the routing log inside the session directory.
+pub const ROUTING_LOG_FILE: &str = "routing.jsonl";
+
+/// Method of the records written for a correction.
+pub const CORRECTION_METHOD: &str = "user_correction";
+
+/// Methods that are the user's own choice rather than the router's.
+const TRIGGER_METHODS: &[&str] = &["rlm_trigger", "passthrough_trigger"];
+
+/// Append-only JSONL log of routing decisions, remembering the latest one
+/// so a trigger right after it can correct it.
+#[derive(Debug)]
+pub struct RoutingLog {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+    last: Mutex<Option<RoutingTrainingRecord>>,
+}
+
+impl RoutingLog {
+    /// Create a log writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Create a log writing [`ROUTING_LOG_FILE`] inside `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(ROUTING_LOG_FILE))
+    }
+
+    /// Path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Log the decision for `trace_id`. Requests without a user message
+    /// (tool-result turns) and internal requests are skipped. Returns the
+    /// correction record when this decision overrules the previous one.
+    pub fn log(&self, trace_id: &str, trace: &RouterTraceData) -> Option<RoutingTrainingRecord> {
+        let request = trace.last_user_message.as_ref()?;
+        if trace.method == "internal_bypass" {
+            return None;
+        }
+        let record = RoutingTrainingRecord {
+            trace_id: trace_id.to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            request: request.clone(),
+            decision: trace.decision.clone(),
+            reason: trace.reason.clone().unwrap_or_default(),
+            method: trace.method.clone(),
+            corrected_by: None,
+        };
+        self.record(&record);
+
+        let previous = self
+            .last
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(record.clone())?;
+        let corrects = TRIGGER_METHODS.contains(&record.method.as_str())
+            && !TRIGGER_METHODS.contains(&previous.method.as_str())
+            && previous.decision != record.decision;
+        if !corrects {
+            return None;
+        }
+        let correction = RoutingTrainingRecord {
+            timestamp: record.timestamp.clone(),
+            decision: record.decision.clone(),
+            reason: format!(
+                "User corrected a {} decision with the {} trigger",
+                previous.decision, record.method
+            ),
+            method: CORRECTION_METHOD.to_string(),
+            corrected_by: Some(record.trace_id.clone()),
+            ..previous
+        };
+        self.record(&correction);
+        Some(correction)
+    }
+
+    /// Append a record. Failures are logged, never surfaced to the request.
+    pub fn record(&self, record: &RoutingTrainingRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to serialize routing record");
+                return;
+            }
+        };
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write routing record");
+        }
+    }
+
+    /// Read all records from the log.
+    pub fn read(&self) -> std::io::Result<Vec<RoutingTrainingRecord>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn trace(message: Option<&str>, method: &str, decision: &str) -> RouterTraceData {
+        RouterTraceData {
+            strategy: "Llm".to_string(),
+            method: method.to_string(),
+            model: "m".to_string(),
+            system_prompt: None,
+            last_user_message: message.map(String::from),
+            message_count: 1,
+            max_tokens: 1024,
+            decision: decision.to_string(),
+            reason: None,
+            category: None,
+            decision_time_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_explore_trigger_corrects_passthrough() {
+        let dir = tempdir().unwrap();
+        let log = RoutingLog::in_dir(dir.path());
+
+        assert!(
+            log.log(
+                "t1",
+                &trace(Some("why is the cache cold?"), "llm", "passthrough")
+            )
+            .is_none()
+        );
+        // Tool-result turns and internal requests don't count as the previous decision
+        assert!(
+            log.log("t2", &trace(None, "no_message", "passthrough"))
+                .is_none()
+        );
+        assert!(
+            log.log(
+                "t3",
+                &trace(Some("title"), "internal_bypass", "passthrough")
+            )
+            .is_none()
+        );
+        let correction = log
+            .log("t4", &trace(Some("@muninn explore"), "rlm_trigger", "rlm"))
+            .unwrap();
+        assert_eq!(correction.trace_id, "t1");
+        assert_eq!(correction.request, "why is the cache cold?");
+        assert_eq!(correction.decision, "rlm");
+        assert_eq!(correction.corrected_by.as_deref(), Some("t4"));
+
+        // A second trigger in a row corrects nothing
+        assert!(
+            log.log(
+                "t5",
+                &trace(
+                    Some("@muninn passthrough"),
+                    "passthrough_trigger",
+                    "passthrough"
+                )
+            )
+            .is_none()
+        );
+
+        let records = log.read().unwrap();
+        let methods: Vec<_> = records.iter().map(|r| r.method.as_str()).collect();
+        assert_eq!(
+            methods,
+            [
+                "llm",
+                "rlm_trigger",
+                CORRECTION_METHOD,
+                "passthrough_trigger"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_agreeing_trigger_is_not_a_correction() {
+        let dir = tempdir().unwrap();
+        let log = RoutingLog::in_dir(dir.path());
+        log.log("t1", &trace(Some("trace the socket setup"), "llm", "rlm"));
+        assert!(
+            log.log(
+                "t2",
+                &trace(Some("@muninn explore more"), "rlm_trigger", "rlm")
+            )
+            .is_none()
+        );
+        assert_eq!(log.read().unwrap().len(), 2);
+    }
+}
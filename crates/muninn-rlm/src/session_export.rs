@@ -0,0 +1,307 @@
+//! Read-only JSON Lines export of sessions and their traces.
+//!
+//! The proxy serves `GET /v1/sessions` and `GET /v1/sessions/{id}/traces`
+//! next to its session directory so dashboards can pull session data
+//! without reading `.muninn/sessions/` themselves. Both return
+//! `application/x-ndjson`, one object per line, oldest first so offsets stay
+//! stable while new sessions and traces are appended. `since` and `until`
+//! (RFC 3339) keep the items whose time span overlaps the range; `offset`
+//! and `limit` page through them. `x-total-count` carries the number of
+//! matching items and `x-next-offset` the offset of the next page, when
+//! there is one.
+//!
+//! The proxy keeps these routes out of its CORS layer, so a web page on
+//! another origin can't read session data. Sessions and traces hold every
+//! key holder's requests, so with client keys configured only keys with
+//! the [`ADMIN_ROLE`](crate::client_keys::ADMIN_ROLE) may export them.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::session_ui::{read_metadata, read_traces, session_path};
+
+/// Page size when the request doesn't give one.
+pub const DEFAULT_EXPORT_LIMIT: usize = 100;
+
+/// Largest page a request can ask for.
+pub const MAX_EXPORT_LIMIT: usize = 1000;
+
+/// Header carrying the number of items matching the filters.
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Header carrying the offset of the next page, when there is one.
+pub const NEXT_OFFSET_HEADER: &str = "x-next-offset";
+
+/// Pagination and time-range filters of an export request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportQuery {
+    /// Items to skip.
+    #[serde(default)]
+    pub offset: usize,
+    /// Items to return, from 1 to [`MAX_EXPORT_LIMIT`].
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Keep items still active at or after this time.
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Keep items started before this time.
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ExportQuery {
+    /// Whether an item running from `start` to `end` overlaps the range.
+    pub fn in_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| end >= since) && self.until.is_none_or(|until| start < until)
+    }
+
+    /// The requested page of `items`, with the pagination headers. All the
+    /// matching items are in memory, as they are sorted and counted; only
+    /// the page's are serialized into the body.
+    fn page<T: Serialize>(&self, items: Vec<T>) -> Response {
+        let total = items.len();
+        let limit = self
+            .limit
+            .unwrap_or(DEFAULT_EXPORT_LIMIT)
+            .clamp(1, MAX_EXPORT_LIMIT);
+        let mut body = String::new();
+        let mut returned = 0;
+        for item in items.iter().skip(self.offset).take(limit) {
+            if let Ok(line) = serde_json::to_string(item) {
+                body.push_str(&line);
+                body.push('\n');
+            }
+            returned += 1;
+        }
+        let next = self.offset.saturating_add(returned);
+
+        let mut response = Body::from(body).into_response();
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+        if returned > 0 && next < total {
+            headers.insert(NEXT_OFFSET_HEADER, HeaderValue::from(next));
+        }
+        response
+    }
+}
+
+/// Routes exporting the sessions in `sessions_dir`, to merge into a
+/// router with any state.
+pub fn session_export_router<S>(sessions_dir: impl Into<PathBuf>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/v1/sessions", get(handle_sessions))
+        .route("/v1/sessions/{id}/traces", get(handle_traces))
+        .with_state(Arc::new(sessions_dir.into()))
+}
+
+type SessionsDir = State<Arc<PathBuf>>;
+
+/// A timestamp field of session metadata.
+fn metadata_time(metadata: &serde_json::Value, field: &str) -> Option<DateTime<Utc>> {
+    metadata[field].as_str()?.parse().ok()
+}
+
+/// Session metadata, oldest first.
+async fn handle_sessions(State(dir): SessionsDir, Query(query): Query<ExportQuery>) -> Response {
+    let filter = query.clone();
+    match tokio::task::spawn_blocking(move || list_sessions(&dir, &filter)).await {
+        Ok(sessions) => query.page(sessions),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Metadata of the sessions in `dir` that `query` keeps, oldest first. A
+/// session is active from its start to its end, or to its last activity
+/// while it is still open.
+fn list_sessions(dir: &Path, query: &ExportQuery) -> Vec<serde_json::Value> {
+    let mut sessions: Vec<(DateTime<Utc>, serde_json::Value)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| read_metadata(&e.path()))
+                .filter_map(|metadata| {
+                    let start = metadata_time(&metadata, "started_at")?;
+                    let end = ["ended_at", "last_activity", "updated_at"]
+                        .iter()
+                        .find_map(|field| metadata_time(&metadata, field))
+                        .unwrap_or(start);
+                    query.in_range(start, end).then_some((start, metadata))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    sessions.sort_by(|(a_start, a), (b_start, b)| {
+        a_start
+            .cmp(b_start)
+            .then_with(|| a["session_id"].as_str().cmp(&b["session_id"].as_str()))
+    });
+    sessions.into_iter().map(|(_, m)| m).collect()
+}
+
+/// A session's traces with their span trees, oldest first.
+async fn handle_traces(
+    State(dir): SessionsDir,
+    UrlPath(id): UrlPath<String>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let filter = query.clone();
+    let traces = tokio::task::spawn_blocking(move || {
+        let session_dir = session_path(&dir, &id)?;
+        let mut traces: Vec<_> = read_traces(&session_dir)
+            .into_iter()
+            .filter(|t| filter.in_range(t.started_at, t.ended_at.unwrap_or(t.started_at)))
+            .collect();
+        traces.sort_by_key(|t| t.started_at);
+        Ok::<_, crate::session_ui::Rejection>(traces)
+    })
+    .await;
+    match traces {
+        Ok(Ok(traces)) => query.page(traces),
+        Ok(Err(rejection)) => rejection.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use muninn_tracing::Trace;
+    use tower::ServiceExt;
+
+    fn write_session(sessions: &std::path::Path, id: &str, started: &str, ended: Option<&str>) {
+        let dir = sessions.join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut metadata = serde_json::json!({
+            "session_id": id,
+            "started_at": started,
+            "work_dir": "/repo",
+        });
+        if let Some(ended) = ended {
+            metadata["ended_at"] = serde_json::json!(ended);
+        }
+        std::fs::write(dir.join("session.json"), metadata.to_string()).unwrap();
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, axum::http::HeaderMap, Vec<String>) {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (status, headers) = (response.status(), response.headers().clone());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let lines = String::from_utf8_lossy(&body)
+            .lines()
+            .map(String::from)
+            .collect();
+        (status, headers, lines)
+    }
+
+    fn ids(lines: &[String], field: &str) -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()[field].to_string())
+            .map(|id| id.trim_matches('"').to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_sessions_filtered_and_paged() {
+        let temp = tempfile::tempdir().unwrap();
+        write_session(
+            temp.path(),
+            "s2",
+            "2026-10-02T09:00:00Z",
+            Some("2026-10-02T18:00:00Z"),
+        );
+        write_session(
+            temp.path(),
+            "s1",
+            "2026-10-01T09:00:00Z",
+            Some("2026-10-01T10:00:00Z"),
+        );
+        write_session(temp.path(), "s3", "2026-10-03T09:00:00Z", None);
+        let app: Router = session_export_router(temp.path());
+
+        let (status, headers, lines) = get(&app, "/v1/sessions").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/x-ndjson");
+        assert_eq!(ids(&lines, "session_id"), ["s1", "s2", "s3"]);
+        assert!(headers.get(NEXT_OFFSET_HEADER).is_none());
+
+        let (_, headers, lines) = get(&app, "/v1/sessions?limit=2").await;
+        assert_eq!(ids(&lines, "session_id"), ["s1", "s2"]);
+        assert_eq!(headers[TOTAL_COUNT_HEADER], "3");
+        assert_eq!(headers[NEXT_OFFSET_HEADER], "2");
+        let (_, _, lines) = get(&app, "/v1/sessions?limit=2&offset=2").await;
+        assert_eq!(ids(&lines, "session_id"), ["s3"]);
+
+        // A zero limit still returns one item and moves on; past the end
+        // there is no next page to follow
+        let (_, headers, lines) = get(&app, "/v1/sessions?limit=0").await;
+        assert_eq!(ids(&lines, "session_id"), ["s1"]);
+        assert_eq!(headers[NEXT_OFFSET_HEADER], "1");
+        let (_, headers, lines) = get(&app, "/v1/sessions?offset=5").await;
+        assert!(lines.is_empty());
+        assert!(headers.get(NEXT_OFFSET_HEADER).is_none());
+
+        // s2 is still running at noon on the 2nd; s1 had ended
+        let (_, headers, lines) = get(
+            &app,
+            "/v1/sessions?since=2026-10-02T12:00:00Z&until=2026-10-03T00:00:00Z",
+        )
+        .await;
+        assert_eq!(ids(&lines, "session_id"), ["s2"]);
+        assert_eq!(headers[TOTAL_COUNT_HEADER], "1");
+
+        let (status, _, _) = get(&app, "/v1/sessions?since=yesterday").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_traces_of_a_session() {
+        let temp = tempfile::tempdir().unwrap();
+        write_session(temp.path(), "s1", "2026-10-01T09:00:00Z", None);
+        let mut content = String::new();
+        for (id, started) in [
+            ("t2", "2026-10-01T09:30:00Z"),
+            ("t1", "2026-10-01T09:10:00Z"),
+        ] {
+            let mut trace = Trace::new(id);
+            trace.started_at = started.parse().unwrap();
+            content.push_str(&serde_json::to_string(&trace).unwrap());
+            content.push('\n');
+        }
+        std::fs::write(temp.path().join("s1").join("traces.jsonl"), content).unwrap();
+        let app: Router = session_export_router(temp.path());
+
+        let (_, _, lines) = get(&app, "/v1/sessions/s1/traces").await;
+        assert_eq!(ids(&lines, "trace_id"), ["t1", "t2"]);
+        let (_, _, lines) = get(&app, "/v1/sessions/s1/traces?since=2026-10-01T09:20:00Z").await;
+        assert_eq!(ids(&lines, "trace_id"), ["t2"]);
+
+        let (status, _, _) = get(&app, "/v1/sessions/nope/traces").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
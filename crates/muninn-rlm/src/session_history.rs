@@ -0,0 +1,488 @@
+//! Searching the current session's earlier requests.
+//!
+//! The engine only sees the last few user turns of a conversation, so a
+//! follow-up exploration can't tell what was already concluded further
+//! back. [`SessionHistoryTool`] (`session_history`) lets the RLM look it
+//! up. It reads three files in the session directory, each optional:
+//!
+//! - `traces.jsonl`: the question behind every routed request and its route
+//! - the raw request log: the agent's full conversation, with the answers it
+//!   was given
+//! - the session recording: RLM answers as muninn produced them
+//!
+//! Entries with the same question are merged. The files are re-read on
+//! every call, so the history includes requests finished since startup.
+//! Answers that cite files modified after the answer was given are flagged
+//! stale, so the RLM re-checks them instead of repeating outdated facts.
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use muninn_tracing::{Span, Trace};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::debug_log::raw_request_logs;
+use crate::engine::cited_paths;
+use crate::error::Result;
+use crate::session_recording::SESSION_RECORDING_FILE;
+use crate::tool_params::ToolParams;
+use crate::tools::{Tool, ToolCategory, ToolMetadata, ToolResult};
+
+/// File name of the session's trace log.
+const SESSION_TRACE_FILE: &str = "traces.jsonl";
+
+/// Entries returned when the model doesn't ask for a number.
+const DEFAULT_LIMIT: usize = 5;
+
+/// Most entries one call returns.
+const MAX_LIMIT: usize = 20;
+
+/// Answers are cut to this many characters in tool output.
+const MAX_ANSWER_CHARS: usize = 2000;
+
+/// One earlier request in the session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    /// When the request arrived, if known.
+    pub at: Option<DateTime<Utc>>,
+    /// The user's message.
+    pub question: String,
+    /// How muninn routed it: `"rlm"` or `"passthrough"`.
+    pub route: Option<String>,
+    /// The answer the agent got.
+    pub answer: Option<String>,
+}
+
+impl HistoryEntry {
+    fn new(question: String) -> Self {
+        Self {
+            at: None,
+            question,
+            route: None,
+            answer: None,
+        }
+    }
+
+    /// Files the answer cites (resolved against `root`) that were modified
+    /// after the request. Empty when the request time is unknown.
+    pub fn stale_citations(&self, root: &Path) -> Vec<String> {
+        let (Some(at), Some(answer)) = (self.at, &self.answer) else {
+            return Vec::new();
+        };
+        let mut stale: Vec<String> = Vec::new();
+        for cited in cited_paths(answer) {
+            // Drop any `:line` suffix
+            let path = cited.split(':').next().unwrap_or_default().to_string();
+            let modified = std::fs::metadata(root.join(&path))
+                .and_then(|m| m.modified())
+                .map(DateTime::<Utc>::from);
+            if modified.is_ok_and(|modified| modified > at) && !stale.contains(&path) {
+                stale.push(path);
+            }
+        }
+        stale
+    }
+}
+
+/// The earlier requests of one session, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct SessionHistory {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl SessionHistory {
+    /// Load the history of the session in `dir`. Missing files and
+    /// unreadable lines are skipped.
+    pub fn load(dir: &Path) -> Self {
+        let mut history = Self::default();
+        for line in read_lines(&dir.join(SESSION_TRACE_FILE)) {
+            if let Ok(trace) = serde_json::from_str::<Trace>(&line) {
+                for span in &trace.spans {
+                    history.add_router_decisions(span);
+                }
+            }
+        }
+        if let Some(latest) = raw_request_logs(dir)
+            .last()
+            .and_then(|path| read_lines(path).pop())
+            .and_then(|line| serde_json::from_str::<Value>(&line).ok())
+        {
+            history.add_conversation(&latest["request"]);
+        }
+        for line in read_lines(&dir.join(SESSION_RECORDING_FILE)) {
+            if let Ok(recorded) =
+                serde_json::from_str::<crate::session_recording::RecordedRequest>(&line)
+                && let Some(question) = last_user_text(&recorded.request)
+            {
+                let mut entry = HistoryEntry::new(question);
+                entry.at = Some(recorded.recorded_at);
+                entry.route = Some(recorded.route);
+                entry.answer = recorded.answer;
+                history.merge(entry);
+            }
+        }
+        history
+    }
+
+    /// Entries matching the most words of `query`, best first; ties go to
+    /// the more recent entry. An empty query returns the most recent.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&HistoryEntry> {
+        let words: Vec<String> = query
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|w| w.len() > 1)
+            .map(str::to_lowercase)
+            .collect();
+        let mut scored: Vec<(usize, usize, &HistoryEntry)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let text = format!(
+                    "{}\n{}",
+                    entry.question,
+                    entry.answer.as_deref().unwrap_or_default()
+                )
+                .to_lowercase();
+                let score = words.iter().filter(|w| text.contains(w.as_str())).count();
+                (score, i, entry)
+            })
+            .filter(|(score, _, _)| words.is_empty() || *score > 0)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, entry)| entry)
+            .collect()
+    }
+
+    /// Add `entry`, or fill in the gaps of an entry with the same question.
+    fn merge(&mut self, entry: HistoryEntry) {
+        let key = entry.question.trim();
+        match self.entries.iter_mut().find(|e| e.question.trim() == key) {
+            Some(existing) => {
+                existing.at = existing.at.or(entry.at);
+                existing.route = existing.route.take().or(entry.route);
+                if entry.answer.is_some() {
+                    existing.answer = entry.answer;
+                }
+            }
+            None => self.entries.push(entry),
+        }
+    }
+
+    fn add_router_decisions(&mut self, span: &Span) {
+        if span.name == "router_decision"
+            && let Some(data) = &span.data
+            && let Some(question) = data.get("last_user_message").and_then(Value::as_str)
+        {
+            let mut entry = HistoryEntry::new(question.to_string());
+            entry.at = Some(span.started_at);
+            entry.route = data
+                .get("decision")
+                .and_then(Value::as_str)
+                .map(String::from);
+            self.merge(entry);
+        }
+        for child in &span.children {
+            self.add_router_decisions(child);
+        }
+    }
+
+    /// Pair each user question in a request's conversation with the
+    /// assistant text that followed it. The last question is the request
+    /// itself, still unanswered, and is left out.
+    fn add_conversation(&mut self, request: &Value) {
+        let mut turns: Vec<HistoryEntry> = Vec::new();
+        for message in request["messages"].as_array().into_iter().flatten() {
+            let text = message_text(message);
+            if text.is_empty() {
+                continue;
+            }
+            match message["role"].as_str() {
+                Some("user") => turns.push(HistoryEntry::new(text)),
+                Some("assistant") => {
+                    if let Some(turn) = turns.last_mut() {
+                        let answer = turn.answer.get_or_insert_with(String::new);
+                        if !answer.is_empty() {
+                            answer.push_str("\n\n");
+                        }
+                        answer.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        turns.pop();
+        for turn in turns {
+            self.merge(turn);
+        }
+    }
+}
+
+fn read_lines(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Text of a message: a string `content`, or its text blocks joined.
+fn message_text(message: &Value) -> String {
+    match &message["content"] {
+        Value::String(text) => text.trim().to_string(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b["type"] == "text")
+            .filter_map(|b| b["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+fn last_user_text(request: &Value) -> Option<String> {
+    request["messages"]
+        .as_array()?
+        .iter()
+        .rev()
+        .filter(|m| m["role"] == "user")
+        .map(message_text)
+        .find(|text| !text.is_empty())
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_ANSWER_CHARS) {
+        Some((i, _)) => format!("{}\n[... answer truncated]", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/// Tool searching the session's earlier requests and answers.
+pub struct SessionHistoryTool {
+    session_dir: PathBuf,
+    /// Project root cited paths are resolved against, for staleness checks.
+    root: Option<PathBuf>,
+}
+
+impl SessionHistoryTool {
+    /// Create a tool reading the session in `session_dir`.
+    pub fn new(session_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            session_dir: session_dir.into(),
+            root: None,
+        }
+    }
+
+    /// Flag answers citing files under `root` that changed since.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+}
+
+/// Parameters for [`SessionHistoryTool`].
+#[derive(Debug, Deserialize, ToolParams)]
+struct SessionHistoryParams {
+    /// Keywords to look for in earlier questions and answers; omit for the most recent requests
+    query: Option<String>,
+    /// Maximum entries to return (default: 5, max: 20)
+    limit: Option<usize>,
+}
+
+#[async_trait]
+impl Tool for SessionHistoryTool {
+    fn name(&self) -> &str {
+        "session_history"
+    }
+
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Core
+    }
+
+    fn description(&self) -> &str {
+        "Search earlier requests in this session and the answers they got. \
+         Use it for follow-up questions to find what was already concluded instead of exploring again. \
+         Answers citing files that have changed since are flagged stale: re-read those files before relying on them."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        SessionHistoryParams::parameters_schema()
+    }
+
+    fn is_internal(&self) -> bool {
+        true // The agent has its own conversation; this is for the RLM
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+        let params = SessionHistoryParams::from_params(params)?;
+        let query = params.query.unwrap_or_default();
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let history = SessionHistory::load(&self.session_dir);
+        let matches = history.search(&query, limit);
+        if matches.is_empty() {
+            return Ok(ToolResult::text(if history.entries.is_empty() {
+                "No earlier requests in this session."
+            } else {
+                "No earlier requests match the query."
+            }));
+        }
+
+        let mut output = String::new();
+        for entry in matches {
+            let at = entry
+                .at
+                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "earlier".to_string());
+            let route = entry.route.as_deref().unwrap_or("unknown route");
+            output.push_str(&format!(
+                "## {} ({})\n\nQ: {}\n\n",
+                at, route, entry.question
+            ));
+            match &entry.answer {
+                Some(answer) => output.push_str(&format!("A: {}\n\n", truncate(answer))),
+                None => output.push_str("A: (not recorded)\n\n"),
+            }
+            let stale = self
+                .root
+                .as_deref()
+                .map(|root| entry.stale_citations(root))
+                .unwrap_or_default();
+            if !stale.is_empty() {
+                output.push_str(&format!(
+                    "STALE: {} changed after this answer; re-read before relying on it.\n\n",
+                    stale.join(", ")
+                ));
+            }
+        }
+
+        let mut result = ToolResult::text(output.trim_end());
+        result.metadata = ToolMetadata::with_source("session_history").with_tag("history");
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_session(dir: &Path) {
+        let trace = json!({
+            "trace_id": "t1",
+            "started_at": "2026-01-01T10:00:00Z",
+            "ended_at": null,
+            "duration_ms": null,
+            "spans": [{
+                "span_id": "s1",
+                "name": "proxy_request",
+                "started_at": "2026-01-01T10:00:00Z",
+                "ended_at": null,
+                "children": [{
+                    "span_id": "s2",
+                    "name": "router_decision",
+                    "started_at": "2026-01-01T10:00:01Z",
+                    "ended_at": null,
+                    "data": {"last_user_message": "Where is the config loaded?", "decision": "rlm"}
+                }]
+            }]
+        });
+        std::fs::write(dir.join(SESSION_TRACE_FILE), format!("{trace}\n")).unwrap();
+
+        let raw = json!({
+            "timestamp": "2026-01-01T10:05:00Z",
+            "request": {"messages": [
+                {"role": "user", "content": "Where is the config loaded?"},
+                {"role": "assistant", "content": [{"type": "text", "text": "In src/config.rs by load()."}]},
+                {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "x", "content": "ok"}]},
+                {"role": "user", "content": "What is 2+2?"},
+                {"role": "assistant", "content": "4"},
+                {"role": "user", "content": "And who calls load()?"}
+            ]}
+        });
+        std::fs::write(dir.join("raw_requests.jsonl"), format!("{raw}\n")).unwrap();
+    }
+
+    #[test]
+    fn test_load_merges_sources() {
+        let temp = tempfile::tempdir().unwrap();
+        write_session(temp.path());
+
+        let history = SessionHistory::load(temp.path());
+        assert_eq!(history.entries.len(), 2, "{:?}", history.entries);
+        let config = &history.entries[0];
+        assert_eq!(config.route.as_deref(), Some("rlm"));
+        assert!(config.at.is_some());
+        assert_eq!(
+            config.answer.as_deref(),
+            Some("In src/config.rs by load().")
+        );
+        assert_eq!(history.entries[1].question, "What is 2+2?");
+        assert!(
+            history
+                .entries
+                .iter()
+                .all(|e| e.question != "And who calls load()?")
+        );
+    }
+
+    #[test]
+    fn test_search_ranks_by_matches_then_recency() {
+        let mut history = SessionHistory::default();
+        for question in ["config loading", "router config prompts", "unrelated"] {
+            history.merge(HistoryEntry::new(question.to_string()));
+        }
+        let found: Vec<_> = history
+            .search("router config", 5)
+            .into_iter()
+            .map(|e| e.question.as_str())
+            .collect();
+        assert_eq!(found, ["router config prompts", "config loading"]);
+        assert_eq!(history.search("", 1)[0].question, "unrelated");
+        assert!(history.search("missing", 5).is_empty());
+    }
+
+    #[test]
+    fn test_stale_citations() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp.path().join("src")).unwrap();
+        std::fs::write(temp.path().join("src/config.rs"), "fn load() {}").unwrap();
+
+        let mut entry = HistoryEntry::new("Where is config loaded?".to_string());
+        entry.answer = Some("In src/config.rs:1 and src/missing.rs.".to_string());
+        assert!(entry.stale_citations(temp.path()).is_empty());
+
+        entry.at = Some(Utc::now() - chrono::Duration::hours(1));
+        assert_eq!(entry.stale_citations(temp.path()), ["src/config.rs"]);
+        entry.at = Some(Utc::now() + chrono::Duration::hours(1));
+        assert!(entry.stale_citations(temp.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_output() {
+        let temp = tempfile::tempdir().unwrap();
+        let tool = SessionHistoryTool::new(temp.path());
+        let result = tool.execute(json!({})).await.unwrap();
+        assert_eq!(
+            result.to_string_content(),
+            "No earlier requests in this session."
+        );
+
+        write_session(temp.path());
+        let result = tool.execute(json!({"query": "config"})).await.unwrap();
+        let text = result.to_string_content();
+        assert!(text.contains("Q: Where is the config loaded?"), "{text}");
+        assert!(text.contains("A: In src/config.rs by load()."), "{text}");
+        assert!(!text.contains("2+2"), "{text}");
+    }
+}
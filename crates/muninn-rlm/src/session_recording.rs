@@ -0,0 +1,360 @@
+//! Agent session recording and replay.
+//!
+//! With `muninn --record`, every `/v1/messages` request the agent sends is
+//! appended to [`SESSION_RECORDING_FILE`] in the session directory,
+//! together with how muninn handled it: the route taken and, for RLM
+//! requests, the final answer. Request bodies get the same credential
+//! masking as [`crate::recording`].
+//!
+//! A [`SessionReplayer`] feeds the recorded requests through a router (and
+//! optionally an engine) built from the current version and config, so
+//! `muninn replay-session <file>` can report which route decisions and
+//! answers changed. Requests routed to passthrough are never sent upstream.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use muninn_core::MuninnEngine;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, RlmError};
+use crate::recording::redact_value;
+use crate::router::{RouteDecision, Router};
+use crate::types::{CompletionRequest, MuninnConfig};
+
+/// File name of the recording inside the session directory.
+pub const SESSION_RECORDING_FILE: &str = "requests.jsonl";
+
+/// Route names used in recordings.
+pub const ROUTE_RLM: &str = "rlm";
+pub const ROUTE_PASSTHROUGH: &str = "passthrough";
+
+/// One agent request and how it was handled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// When the request arrived.
+    pub recorded_at: DateTime<Utc>,
+    /// Request body as the agent sent it (credentials masked).
+    pub request: serde_json::Value,
+    /// [`ROUTE_RLM`] or [`ROUTE_PASSTHROUGH`].
+    pub route: String,
+    /// Why that route was taken: the router's reason, `"explicit"` for
+    /// `muninn.recursive` requests, `"unparsed"` when the body didn't parse.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Text of the RLM answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    /// RLM error, if exploration failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RecordedRequest {
+    /// A request handled by `route`, with nothing else known yet.
+    pub fn new(request: serde_json::Value, route: &str, reason: Option<String>) -> Self {
+        Self {
+            recorded_at: Utc::now(),
+            request,
+            route: route.to_string(),
+            reason,
+            answer: None,
+            error: None,
+        }
+    }
+}
+
+/// Appends [`RecordedRequest`]s to a JSONL file.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl SessionRecorder {
+    /// Create a recorder writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Create a recorder writing [`SESSION_RECORDING_FILE`] inside `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(SESSION_RECORDING_FILE))
+    }
+
+    /// Path of the recording file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Redact and append a request. Failures are logged, never surfaced
+    /// to the request path.
+    pub fn record(&self, mut recorded: RecordedRequest) {
+        redact_value(&mut recorded.request);
+        let line = match serde_json::to_string(&recorded) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to serialize session recording");
+                return;
+            }
+        };
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write session recording");
+        }
+    }
+}
+
+/// Read all requests from a session recording.
+pub fn read_session_recording(path: &Path) -> Result<Vec<RecordedRequest>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        RlmError::Config(format!(
+            "Failed to read session recording {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                RlmError::Serialization(format!(
+                    "{}:{}: invalid session recording: {}",
+                    path.display(),
+                    i + 1,
+                    e
+                ))
+            })
+        })
+        .collect()
+}
+
+/// A recorded request next to what the current version made of it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayedRequest {
+    pub recorded: RecordedRequest,
+    /// The replay, in the same shape as the recording.
+    pub replayed: RecordedRequest,
+}
+
+impl ReplayedRequest {
+    pub fn route_changed(&self) -> bool {
+        self.recorded.route != self.replayed.route
+    }
+
+    /// `None` unless both runs produced an answer.
+    pub fn answer_similarity(&self) -> Option<f64> {
+        Some(word_similarity(
+            self.recorded.answer.as_deref()?,
+            self.replayed.answer.as_deref()?,
+        ))
+    }
+
+    pub fn answer_changed(&self) -> bool {
+        self.recorded.answer != self.replayed.answer
+    }
+}
+
+/// Jaccard similarity of the word sets of `a` and `b` (1.0 when both are
+/// empty). Crude, but enough to tell a reworded answer from a different one.
+pub fn word_similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        s.split_whitespace()
+            .map(|w| w.to_lowercase())
+            .collect::<HashSet<_>>()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Replays recorded requests through the current router and engine.
+pub struct SessionReplayer {
+    router: Router,
+    engine: Option<Arc<dyn MuninnEngine>>,
+}
+
+impl SessionReplayer {
+    /// Replay route decisions only.
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            engine: None,
+        }
+    }
+
+    /// Also run RLM-routed requests through `engine` to compare answers.
+    pub fn with_engine(mut self, engine: Arc<dyn MuninnEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Route `recorded` the way the proxy would and, with an engine, answer
+    /// it.
+    pub async fn replay(&self, recorded: &RecordedRequest) -> ReplayedRequest {
+        let request = recorded.request.clone();
+        let mut replayed = match serde_json::from_value::<CompletionRequest>(request.clone()) {
+            Err(_) => RecordedRequest::new(request, ROUTE_PASSTHROUGH, Some("unparsed".into())),
+            Ok(typed) => self.route_and_answer(request, typed).await,
+        };
+        replayed.recorded_at = Utc::now();
+        ReplayedRequest {
+            recorded: recorded.clone(),
+            replayed,
+        }
+    }
+
+    async fn route_and_answer(
+        &self,
+        request: serde_json::Value,
+        mut typed: CompletionRequest,
+    ) -> RecordedRequest {
+        let (reason, category) = if typed.is_recursive() {
+            ("explicit".to_string(), None)
+        } else {
+            match self.router.route(&typed).await {
+                RouteDecision::Passthrough => {
+                    return RecordedRequest::new(request, ROUTE_PASSTHROUGH, None);
+                }
+                RouteDecision::Rlm { reason, category } => (reason, category),
+            }
+        };
+        let mut replayed = RecordedRequest::new(request, ROUTE_RLM, Some(reason));
+        let Some(engine) = &self.engine else {
+            return replayed;
+        };
+        let muninn = typed.muninn.get_or_insert_with(MuninnConfig::default);
+        muninn.recursive = true;
+        if let Some(category) = category {
+            muninn.category = Some(category.to_string());
+        }
+        match engine.complete(typed).await {
+            Ok(response) => replayed.answer = Some(response.text()),
+            Err(e) => replayed.error = Some(e.to_string()),
+        }
+        replayed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+    use crate::engine::{EngineConfig, EngineDeps, RecursiveEngine};
+    use crate::router::{RouterConfig, RouterStrategy};
+    use crate::tools::EmptyToolEnvironment;
+    use crate::types::{CompletionResponse, ContentBlock, StopReason, Usage};
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn request(text: &str) -> serde_json::Value {
+        json!({
+            "model": "claude-test",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": text}]
+        })
+    }
+
+    fn router(strategy: RouterStrategy) -> Router {
+        Router::with_config(RouterConfig {
+            strategy,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_recorder_round_trip_redacts() {
+        let temp = tempdir().unwrap();
+        let recorder = SessionRecorder::in_dir(temp.path());
+        let mut recorded = RecordedRequest::new(
+            request("key sk-ant-api03-abcdefghijkl"),
+            ROUTE_RLM,
+            Some("needs exploration".into()),
+        );
+        recorded.answer = Some("It lives in src/lib.rs".into());
+        recorder.record(recorded);
+        recorder.record(RecordedRequest::new(request("hi"), ROUTE_PASSTHROUGH, None));
+
+        let read = read_session_recording(recorder.path()).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].route, ROUTE_RLM);
+        assert_eq!(read[0].answer.as_deref(), Some("It lives in src/lib.rs"));
+        let content = read[0].request["messages"][0]["content"].as_str().unwrap();
+        assert!(!content.contains("sk-ant-api03"), "{content}");
+        assert_eq!(read[1].reason, None);
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_route_changes() {
+        let recorded = RecordedRequest::new(request("where is main?"), ROUTE_RLM, None);
+        let replayer = SessionReplayer::new(router(RouterStrategy::AlwaysPassthrough));
+        let result = replayer.replay(&recorded).await;
+        assert!(result.route_changed());
+        assert_eq!(result.replayed.route, ROUTE_PASSTHROUGH);
+        assert_eq!(result.answer_similarity(), None);
+
+        let unparsed = RecordedRequest::new(json!({"bogus": true}), ROUTE_PASSTHROUGH, None);
+        let result = replayer.replay(&unparsed).await;
+        assert!(!result.route_changed());
+        assert_eq!(result.replayed.reason.as_deref(), Some("unparsed"));
+    }
+
+    #[tokio::test]
+    async fn test_replay_answers_with_engine() {
+        let backend = Arc::new(MockBackend::new(vec![CompletionResponse::new(
+            "msg_1",
+            "claude-test",
+            vec![ContentBlock::Text {
+                text: "main is in src/main.rs".to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 5),
+        )]));
+        let engine = RecursiveEngine::new(
+            EngineDeps::new(backend, Arc::new(EmptyToolEnvironment)),
+            EngineConfig::default(),
+        );
+        let replayer =
+            SessionReplayer::new(router(RouterStrategy::AlwaysRlm)).with_engine(Arc::new(engine));
+
+        let mut recorded = RecordedRequest::new(request("where is main?"), ROUTE_RLM, None);
+        recorded.answer = Some("main is in src/bin/main.rs".to_string());
+        let result = replayer.replay(&recorded).await;
+
+        assert!(!result.route_changed());
+        assert_eq!(
+            result.replayed.answer.as_deref(),
+            Some("main is in src/main.rs")
+        );
+        assert!(result.answer_changed());
+        let similarity = result.answer_similarity().unwrap();
+        assert!(similarity > 0.5 && similarity < 1.0, "{similarity}");
+    }
+
+    #[test]
+    fn test_word_similarity() {
+        assert_eq!(word_similarity("", ""), 1.0);
+        assert_eq!(word_similarity("a b", "B a"), 1.0);
+        assert_eq!(word_similarity("a b", "c d"), 0.0);
+    }
+}
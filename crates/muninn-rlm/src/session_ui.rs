@@ -0,0 +1,355 @@
+//! Small web UI over the session directories (`muninn ui`).
+//!
+//! Serves one embedded page plus a read-only JSON API over
+//! `.muninn/sessions/`: the sessions, each session's requests with their
+//! router decisions, the span timeline of a trace, and the exploration
+//! reports. Everything is read from the files the proxy already writes, so
+//! the UI works for live and finished sessions alike.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path as UrlPath, State},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+};
+use muninn_tracing::{Span, Trace, TraceWriter};
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+use crate::exploration_report::{EXPLORATION_REPORT_DIR, exploration_reports};
+use crate::feedback::FeedbackLog;
+use crate::routing_log::RoutingLog;
+use crate::spend::SpendLedger;
+
+/// The page, with its script and styles inline.
+const UI_HTML: &str = include_str!("session_ui.html");
+
+/// Session metadata file written by the CLI.
+const METADATA_FILE: &str = "session.json";
+
+/// Trace file inside a session directory.
+const TRACES_FILE: &str = "traces.jsonl";
+
+/// Router for the UI page and its API, over the sessions in `sessions_dir`.
+pub fn session_ui_router(sessions_dir: impl Into<PathBuf>) -> Router {
+    Router::new()
+        .route("/", get(|| async { Html(UI_HTML) }))
+        .route("/api/sessions", get(handle_sessions))
+        .route("/api/sessions/{id}", get(handle_session))
+        .route("/api/sessions/{id}/traces/{trace_id}", get(handle_trace))
+        .route(
+            "/api/sessions/{id}/explorations/{file}",
+            get(handle_exploration),
+        )
+        .with_state(Arc::new(sessions_dir.into()))
+}
+
+/// Serve the UI for `sessions_dir` on `addr` until the process exits.
+pub async fn serve_session_ui(
+    sessions_dir: impl Into<PathBuf>,
+    addr: SocketAddr,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(addr = %addr, "Serving session UI");
+    axum::serve(listener, session_ui_router(sessions_dir)).await
+}
+
+type SessionsDir = State<Arc<PathBuf>>;
+
+/// Status and message for a request the API can't serve.
+pub(crate) type Rejection = (StatusCode, String);
+
+/// One request of a session, as listed by the UI.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RequestSummary {
+    pub trace_id: String,
+    pub started_at: String,
+    pub duration_ms: Option<u64>,
+    /// "rlm" or "passthrough", when the router decided.
+    pub decision: Option<String>,
+    /// How the router decided (see [`crate::router::RouterTraceData`]).
+    pub method: Option<String>,
+    pub reason: Option<String>,
+    pub category: Option<String>,
+    /// The user message the router saw, cut to one line.
+    pub question: Option<String>,
+    /// Error of the first failed span, if any.
+    pub error: Option<String>,
+}
+
+impl RequestSummary {
+    /// Summarize `trace` from its `router_decision` span.
+    pub fn from_trace(trace: &Trace) -> Self {
+        let router = find_span(&trace.spans, "router_decision").and_then(|s| s.data.as_ref());
+        let field = |name: &str| {
+            router
+                .and_then(|data| data.get(name))
+                .and_then(|v| v.as_str())
+                .map(String::from)
+        };
+        Self {
+            trace_id: trace.trace_id.clone(),
+            started_at: trace.started_at.to_rfc3339(),
+            duration_ms: trace.duration_ms,
+            decision: field("decision"),
+            method: field("method"),
+            reason: field("reason"),
+            category: field("category"),
+            question: field("last_user_message").map(|q| {
+                q.lines()
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(120)
+                    .collect()
+            }),
+            error: first_error(&trace.spans),
+        }
+    }
+}
+
+/// Depth-first search for the first span called `name`.
+fn find_span<'a>(spans: &'a [Span], name: &str) -> Option<&'a Span> {
+    spans.iter().find_map(|span| {
+        if span.name == name {
+            Some(span)
+        } else {
+            find_span(&span.children, name)
+        }
+    })
+}
+
+fn first_error(spans: &[Span]) -> Option<String> {
+    spans.iter().find_map(|span| match &span.outcome {
+        Some(muninn_tracing::SpanOutcome::Error { message }) => Some(message.clone()),
+        _ => first_error(&span.children),
+    })
+}
+
+/// A session directory name or file name from the URL, refusing anything
+/// that could leave the directory.
+fn plain_name(name: &str) -> Result<&str, Rejection> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err((StatusCode::BAD_REQUEST, "invalid name".to_string()));
+    }
+    Ok(name)
+}
+
+/// The directory of session `id`, if it exists.
+pub(crate) fn session_path(sessions_dir: &Path, id: &str) -> Result<PathBuf, Rejection> {
+    let dir = sessions_dir.join(plain_name(id)?);
+    if dir.is_dir() {
+        Ok(dir)
+    } else {
+        Err((StatusCode::NOT_FOUND, format!("no session {}", id)))
+    }
+}
+
+pub(crate) fn read_traces(session_dir: &Path) -> Vec<Trace> {
+    TraceWriter::read_traces(&session_dir.join(TRACES_FILE)).unwrap_or_default()
+}
+
+pub(crate) fn read_metadata(session_dir: &Path) -> Option<serde_json::Value> {
+    let content = std::fs::read_to_string(session_dir.join(METADATA_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Sessions with readable metadata, newest first.
+async fn handle_sessions(State(dir): SessionsDir) -> Response {
+    let mut sessions: Vec<serde_json::Value> = std::fs::read_dir(dir.as_path())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| read_metadata(&e.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    sessions.sort_by(|a, b| {
+        let started = |v: &serde_json::Value| v["started_at"].as_str().unwrap_or("").to_string();
+        started(b).cmp(&started(a))
+    });
+    Json(sessions).into_response()
+}
+
+/// A session's metadata, requests, spend, routing log, feedback and
+/// exploration reports.
+async fn handle_session(State(dir): SessionsDir, UrlPath(id): UrlPath<String>) -> Response {
+    let session_dir = match session_path(&dir, &id) {
+        Ok(d) => d,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let requests: Vec<RequestSummary> = read_traces(&session_dir)
+        .iter()
+        .map(RequestSummary::from_trace)
+        .collect();
+    let explorations: Vec<serde_json::Value> = exploration_reports(&session_dir)
+        .into_iter()
+        .map(|report| {
+            serde_json::json!({
+                "file": report.path.file_name().map(|f| f.to_string_lossy()),
+                "title": report.title,
+                "question": report.question,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "metadata": read_metadata(&session_dir),
+        "requests": requests,
+        "spend": SpendLedger::in_dir(&session_dir).read().unwrap_or_default(),
+        "routing": RoutingLog::in_dir(&session_dir).read().unwrap_or_default(),
+        "feedback": FeedbackLog::in_dir(&session_dir).read().unwrap_or_default(),
+        "explorations": explorations,
+    }))
+    .into_response()
+}
+
+/// One trace with its full span tree.
+async fn handle_trace(
+    State(dir): SessionsDir,
+    UrlPath((id, trace_id)): UrlPath<(String, String)>,
+) -> Response {
+    let session_dir = match session_path(&dir, &id) {
+        Ok(d) => d,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match read_traces(&session_dir)
+        .into_iter()
+        .find(|t| t.trace_id == trace_id)
+    {
+        Some(trace) => Json(trace).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("no trace {}", trace_id)).into_response(),
+    }
+}
+
+/// An exploration report's markdown.
+async fn handle_exploration(
+    State(dir): SessionsDir,
+    UrlPath((id, file)): UrlPath<(String, String)>,
+) -> Response {
+    let session_dir = match session_path(&dir, &id) {
+        Ok(d) => d,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let file = match plain_name(&file) {
+        Ok(f) => f,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match std::fs::read_to_string(session_dir.join(EXPLORATION_REPORT_DIR).join(file)) {
+        Ok(markdown) => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            markdown,
+        )
+            .into_response(),
+        Err(_) => (StatusCode::NOT_FOUND, format!("no report {}", file)).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use muninn_tracing::SpanOutcome;
+    use tower::ServiceExt;
+
+    fn write_session(sessions: &Path) -> Trace {
+        let dir = sessions.join("20261015-abc");
+        std::fs::create_dir_all(dir.join(EXPLORATION_REPORT_DIR)).unwrap();
+        std::fs::write(
+            dir.join(METADATA_FILE),
+            r#"{"session_id":"20261015-abc","started_at":"2026-10-15T10:00:00Z","work_dir":"/repo"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(EXPLORATION_REPORT_DIR).join("001-t1.md"),
+            "# Exploration t1\n\n## Question\n\nWhere is the socket built?\n",
+        )
+        .unwrap();
+
+        let mut router = Span::new("router_decision");
+        router.data = Some(serde_json::json!({
+            "decision": "rlm",
+            "method": "llm",
+            "reason": "Router LLM: needs the code",
+            "last_user_message": "Where is the socket built?\nmore context",
+        }));
+        let mut tool = Span::new("tool_execution");
+        tool.outcome = Some(SpanOutcome::Error {
+            message: "file not found".to_string(),
+        });
+        let mut request = Span::new("proxy_request");
+        request.children = vec![router, tool];
+        let mut trace = Trace::new("t1");
+        trace.spans = vec![request];
+        std::fs::write(
+            dir.join(TRACES_FILE),
+            format!("{}\n", serde_json::to_string(&trace).unwrap()),
+        )
+        .unwrap();
+        trace
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[test]
+    fn test_request_summary_from_trace() {
+        let temp = tempfile::tempdir().unwrap();
+        let summary = RequestSummary::from_trace(&write_session(temp.path()));
+        assert_eq!(summary.decision.as_deref(), Some("rlm"));
+        assert_eq!(summary.method.as_deref(), Some("llm"));
+        assert_eq!(
+            summary.question.as_deref(),
+            Some("Where is the socket built?")
+        );
+        assert_eq!(summary.error.as_deref(), Some("file not found"));
+    }
+
+    #[tokio::test]
+    async fn test_api_serves_sessions_traces_and_reports() {
+        let temp = tempfile::tempdir().unwrap();
+        write_session(temp.path());
+        let app = session_ui_router(temp.path());
+
+        let (status, page) = get(&app, "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("<html"));
+
+        let (_, sessions) = get(&app, "/api/sessions").await;
+        let sessions: serde_json::Value = serde_json::from_str(&sessions).unwrap();
+        assert_eq!(sessions[0]["session_id"], "20261015-abc");
+
+        let (_, session) = get(&app, "/api/sessions/20261015-abc").await;
+        let session: serde_json::Value = serde_json::from_str(&session).unwrap();
+        assert_eq!(session["requests"][0]["trace_id"], "t1");
+        assert_eq!(session["explorations"][0]["file"], "001-t1.md");
+
+        let (status, trace) = get(&app, "/api/sessions/20261015-abc/traces/t1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(trace.contains("tool_execution"));
+
+        let (status, report) = get(&app, "/api/sessions/20261015-abc/explorations/001-t1.md").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(report.starts_with("# Exploration t1"));
+
+        let (status, _) = get(&app, "/api/sessions/nope").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&app, "/api/sessions/20261015-abc/explorations/..").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,270 @@
+//! Token spend ledger.
+//!
+//! Every completed proxy request appends one [`SpendEntry`] to
+//! `spend.jsonl` in the session directory, covering passthrough
+//! (streaming and non-streaming) and RLM handling alike. Streaming usage is
+//! picked out of the SSE events as they are forwarded, so accounting never
+//! buffers the response.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// File name of the ledger inside the session directory.
+pub const SPEND_LEDGER_FILE: &str = "spend.jsonl";
+
+/// Token counts for a single request, in Anthropic usage terms.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Uncached input tokens.
+    pub input_tokens: u64,
+    /// Generated output tokens.
+    pub output_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    /// Input tokens served from the prompt cache.
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Overwrite counts present in an Anthropic `usage` object.
+    ///
+    /// `message_delta` usage is cumulative, so later values replace
+    /// earlier ones rather than adding to them.
+    pub fn merge_json(&mut self, usage: &serde_json::Value) {
+        let field = |name: &str| usage.get(name).and_then(|v| v.as_u64());
+        if let Some(v) = field("input_tokens") {
+            self.input_tokens = v;
+        }
+        if let Some(v) = field("output_tokens") {
+            self.output_tokens = v;
+        }
+        if let Some(v) = field("cache_creation_input_tokens") {
+            self.cache_creation_input_tokens = v;
+        }
+        if let Some(v) = field("cache_read_input_tokens") {
+            self.cache_read_input_tokens = v;
+        }
+    }
+
+    /// Parse an Anthropic `usage` object.
+    pub fn from_json(usage: &serde_json::Value) -> Self {
+        let mut parsed = Self::default();
+        parsed.merge_json(usage);
+        parsed
+    }
+
+    /// All input tokens (cached or not) plus output tokens.
+    pub fn total(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+
+    /// Whether no tokens were counted.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl From<&crate::types::Usage> for TokenUsage {
+    fn from(usage: &crate::types::Usage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens as u64,
+            output_tokens: usage.output_tokens as u64,
+            cache_creation_input_tokens: usage.cache_creation_input_tokens as u64,
+            cache_read_input_tokens: usage.cache_read_input_tokens as u64,
+        }
+    }
+}
+
+/// One ledger line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpendEntry {
+    /// When the request finished.
+    pub timestamp: DateTime<Utc>,
+    /// Trace the request was recorded under.
+    pub trace_id: String,
+    /// Model that served the request.
+    pub model: String,
+    /// How the request was handled ("passthrough" or "rlm").
+    pub handling: String,
+    /// Whether the response was streamed.
+    pub streaming: bool,
+    /// Tags from the request's `x-muninn-tags` header.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Name of the client key the request was made with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Self-assessed answer quality (0–1) of a scored RLM exploration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<f64>,
+    /// Token counts.
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+}
+
+impl SpendEntry {
+    /// Create an entry timestamped now.
+    pub fn new(
+        trace_id: impl Into<String>,
+        model: impl Into<String>,
+        handling: impl Into<String>,
+        streaming: bool,
+        usage: TokenUsage,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            trace_id: trace_id.into(),
+            model: model.into(),
+            handling: handling.into(),
+            streaming,
+            tags: Vec::new(),
+            client: None,
+            quality: None,
+            usage,
+        }
+    }
+
+    /// Attach request tags.
+    pub fn with_tags(mut self, tags: impl Into<Vec<String>>) -> Self {
+        self.tags = tags.into();
+        self
+    }
+
+    /// Attribute the entry to a client key.
+    pub fn with_client(mut self, client: Option<String>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Attach an answer quality score.
+    pub fn with_quality(mut self, quality: Option<f64>) -> Self {
+        self.quality = quality;
+        self
+    }
+}
+
+/// Append-only JSONL spend ledger.
+#[derive(Debug)]
+pub struct SpendLedger {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl SpendLedger {
+    /// Create a ledger writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Create a ledger writing [`SPEND_LEDGER_FILE`] inside `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(SPEND_LEDGER_FILE))
+    }
+
+    /// Path of the ledger file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry. Failures are logged, never surfaced to the request.
+    pub fn record(&self, entry: &SpendEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to serialize spend entry");
+                return;
+            }
+        };
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write spend entry");
+        }
+    }
+
+    /// Read all entries from the ledger.
+    pub fn read(&self) -> std::io::Result<Vec<SpendEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_json_overwrites_cumulative_counts() {
+        let mut usage = TokenUsage::from_json(&json!({
+            "input_tokens": 12,
+            "output_tokens": 1,
+            "cache_read_input_tokens": 300
+        }));
+        usage.merge_json(&json!({"output_tokens": 40}));
+        usage.merge_json(&json!({"output_tokens": 95}));
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 95);
+        assert_eq!(usage.cache_read_input_tokens, 300);
+        assert_eq!(usage.cache_creation_input_tokens, 0);
+    }
+
+    #[test]
+    fn test_ledger_roundtrip() {
+        let temp = tempdir().unwrap();
+        let ledger = SpendLedger::in_dir(temp.path());
+        assert!(ledger.read().unwrap().is_empty());
+
+        let usage = TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            ..Default::default()
+        };
+        ledger.record(&SpendEntry::new("t1", "claude", "passthrough", true, usage));
+        ledger.record(
+            &SpendEntry::new("t2", "claude", "rlm", false, usage)
+                .with_tags(vec!["refactor".to_string()]),
+        );
+
+        let entries = ledger.read().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].trace_id, "t1");
+        assert!(entries[0].streaming);
+        assert_eq!(entries[1].usage, usage);
+        assert!(entries[0].tags.is_empty());
+        assert_eq!(entries[1].tags, vec!["refactor"]);
+
+        // Usage fields are flattened into the line; empty tags are omitted
+        let raw = std::fs::read_to_string(ledger.path()).unwrap();
+        let first = raw.lines().next().unwrap();
+        assert!(first.contains("\"input_tokens\":10"));
+        assert!(!first.contains("tags"));
+    }
+}
@@ -0,0 +1,535 @@
+//! Server-sent event inspection and repair for passthrough streams.
+//!
+//! [`SseInspector`] sits between the upstream byte stream and the client.
+//! It parses the Anthropic SSE framing as bytes flow through, forwarding
+//! only complete events, and collects [`StreamStats`] for the trace.
+//!
+//! If upstream disconnects mid-message (connection reset, truncated body)
+//! the inspector repairs the framing and tells the agent the message was
+//! cut short, rather than letting truncated output pass for complete:
+//! - a dangling event whose data is valid JSON is terminated; otherwise it
+//!   is dropped rather than forwarded half-written
+//! - open content blocks get a synthetic `content_block_stop`
+//! - a synthetic `error` event (`api_error`) ends the stream in place of
+//!   `message_stop`
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use bytes::Bytes;
+use futures::Stream;
+use serde::Serialize;
+
+use crate::spend::TokenUsage;
+
+/// A parsed server-sent event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    /// The `event:` field, if present.
+    pub event: Option<String>,
+    /// The `data:` payload (multiple data lines joined with `\n`).
+    pub data: String,
+}
+
+impl SseEvent {
+    /// Parse a single frame (without requiring the trailing blank line).
+    pub fn parse(frame: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(frame);
+        let mut event = Self::default();
+        let mut data_lines: Vec<&str> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if let Some(v) = line.strip_prefix("event:") {
+                event.event = Some(v.trim().to_string());
+            } else if let Some(v) = line.strip_prefix("data:") {
+                data_lines.push(v.strip_prefix(' ').unwrap_or(v));
+            }
+            // Comments (":") and unknown fields are ignored.
+        }
+        event.data = data_lines.join("\n");
+        event
+    }
+
+    /// The data payload parsed as JSON, if valid.
+    pub fn json(&self) -> Option<serde_json::Value> {
+        serde_json::from_str(&self.data).ok()
+    }
+}
+
+/// Incremental SSE frame splitter.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buf: Vec<u8>,
+    /// Bytes at the start of `buf` already searched for a terminator, so
+    /// each chunk only scans what it added.
+    scanned: usize,
+}
+
+impl SseParser {
+    /// Create an empty parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk; returns the complete frames it finished (raw bytes
+    /// including their terminating blank line).
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Bytes> {
+        self.buf.extend_from_slice(chunk);
+        let mut frames = Vec::new();
+        let mut start = 0;
+        // A terminator can straddle the old end of the buffer: back up by
+        // one byte less than the longest one.
+        let mut from = self.scanned.saturating_sub(3);
+        while let Some(end) = find_frame_end(&self.buf[from..]) {
+            let end = from + end;
+            frames.push(Bytes::copy_from_slice(&self.buf[start..end]));
+            start = end;
+            from = end;
+        }
+        self.buf.drain(..start);
+        self.scanned = self.buf.len();
+        frames
+    }
+
+    /// Take any buffered bytes that did not form a complete frame.
+    pub fn take_remainder(&mut self) -> Vec<u8> {
+        self.scanned = 0;
+        std::mem::take(&mut self.buf)
+    }
+}
+
+/// Offset just past the first frame terminator (`\n\n` or `\r\n\r\n`).
+fn find_frame_end(buf: &[u8]) -> Option<usize> {
+    (0..buf.len()).find_map(|i| {
+        if buf[i..].starts_with(b"\n\n") {
+            Some(i + 2)
+        } else if buf[i..].starts_with(b"\r\n\r\n") {
+            Some(i + 4)
+        } else {
+            None
+        }
+    })
+}
+
+/// Statistics about a forwarded SSE stream, recorded in the trace.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamStats {
+    /// Bytes received from upstream.
+    pub bytes: u64,
+    /// Complete events received from upstream.
+    pub events: u64,
+    /// Count of events by type.
+    pub event_counts: BTreeMap<String, u64>,
+    /// Events whose data was not a JSON object with a `type`.
+    pub malformed_events: u64,
+    /// Whether `message_start` was seen.
+    pub message_started: bool,
+    /// Whether upstream sent `message_stop` itself.
+    pub message_stopped: bool,
+    /// Model reported in `message_start`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Token usage from `message_start` and `message_delta` events.
+    pub usage: TokenUsage,
+    /// Server tool calls the provider made, by tool name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub server_tool_uses: BTreeMap<String, u64>,
+    /// Upstream `error` event payload, if one was sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_event: Option<serde_json::Value>,
+    /// Transport error that ended the upstream stream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_error: Option<String>,
+    /// Whether synthetic events were emitted to close the stream.
+    pub repaired: bool,
+    /// Names of the synthetic events emitted, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub synthetic_events: Vec<String>,
+    /// Bytes of a half-written trailing event that were discarded.
+    pub dropped_partial_bytes: u64,
+    /// Whether the client went away before the stream ended.
+    pub client_disconnected: bool,
+    /// Time until the first complete event (ms).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_to_first_event_ms: Option<u64>,
+    /// Total stream duration (ms).
+    pub duration_ms: u64,
+}
+
+/// Callback invoked once with the final stats when the stream ends or is
+/// dropped.
+pub type StreamStatsCallback = Box<dyn FnOnce(StreamStats) + Send>;
+
+/// Owns the stats and fires the callback on drop, so a client disconnect
+/// still reports.
+struct StatsGuard {
+    stats: StreamStats,
+    started: Instant,
+    finished: bool,
+    on_finish: Option<StreamStatsCallback>,
+}
+
+impl Drop for StatsGuard {
+    fn drop(&mut self) {
+        self.stats.client_disconnected = !self.finished;
+        self.stats.duration_ms = self.started.elapsed().as_millis() as u64;
+        if let Some(cb) = self.on_finish.take() {
+            cb(std::mem::take(&mut self.stats));
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Stream adapter that validates, repairs, and measures an SSE stream.
+    pub struct SseInspector<S> {
+        #[pin]
+        inner: S,
+        parser: SseParser,
+        open_blocks: BTreeSet<u64>,
+        guard: StatsGuard,
+        done: bool,
+    }
+}
+
+impl<S> SseInspector<S> {
+    /// Wrap an upstream byte stream.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            parser: SseParser::new(),
+            open_blocks: BTreeSet::new(),
+            guard: StatsGuard {
+                stats: StreamStats::default(),
+                started: Instant::now(),
+                finished: false,
+                on_finish: None,
+            },
+            done: false,
+        }
+    }
+
+    /// Report final stats to `callback`.
+    pub fn on_finish(mut self, callback: impl FnOnce(StreamStats) + Send + 'static) -> Self {
+        self.guard.on_finish = Some(Box::new(callback));
+        self
+    }
+}
+
+/// Record an event in the stats and track open content blocks.
+fn observe(
+    event: &SseEvent,
+    stats: &mut StreamStats,
+    open_blocks: &mut BTreeSet<u64>,
+    started: Instant,
+) {
+    stats.events += 1;
+    if stats.time_to_first_event_ms.is_none() {
+        stats.time_to_first_event_ms = Some(started.elapsed().as_millis() as u64);
+    }
+    let json = event.json();
+    let kind = json
+        .as_ref()
+        .and_then(|j| j.get("type"))
+        .and_then(|t| t.as_str())
+        .map(str::to_string);
+    let Some(kind) = kind else {
+        stats.malformed_events += 1;
+        let name = event.event.clone().unwrap_or_else(|| "unknown".to_string());
+        *stats.event_counts.entry(name).or_default() += 1;
+        return;
+    };
+    if event.event.as_deref().is_some_and(|e| e != kind) {
+        stats.malformed_events += 1;
+    }
+    let index = json
+        .as_ref()
+        .and_then(|j| j.get("index"))
+        .and_then(|i| i.as_u64());
+    match kind.as_str() {
+        "message_start" => {
+            stats.message_started = true;
+            let message = json.as_ref().and_then(|j| j.get("message"));
+            if let Some(model) = message
+                .and_then(|m| m.get("model"))
+                .and_then(|m| m.as_str())
+            {
+                stats.model = Some(model.to_string());
+            }
+            if let Some(usage) = message.and_then(|m| m.get("usage")) {
+                stats.usage.merge_json(usage);
+            }
+        }
+        "message_delta" => {
+            if let Some(usage) = json.as_ref().and_then(|j| j.get("usage")) {
+                stats.usage.merge_json(usage);
+            }
+        }
+        "message_stop" => stats.message_stopped = true,
+        "content_block_start" => {
+            if let Some(i) = index {
+                open_blocks.insert(i);
+            }
+            let block = json.as_ref().and_then(|j| j.get("content_block"));
+            if let Some(block) = block.filter(|b| b["type"] == "server_tool_use") {
+                let name = block["name"].as_str().unwrap_or("unknown").to_string();
+                *stats.server_tool_uses.entry(name).or_default() += 1;
+            }
+        }
+        "content_block_stop" => {
+            if let Some(i) = index {
+                open_blocks.remove(&i);
+            }
+        }
+        "error" => stats.error_event = json.as_ref().and_then(|j| j.get("error")).cloned(),
+        _ => {}
+    }
+    *stats.event_counts.entry(kind).or_default() += 1;
+}
+
+/// Build the bytes needed to close a truncated stream.
+fn repair(
+    remainder: Vec<u8>,
+    stats: &mut StreamStats,
+    open_blocks: &mut BTreeSet<u64>,
+    started: Instant,
+) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    if !remainder.iter().all(u8::is_ascii_whitespace) {
+        let event = SseEvent::parse(&remainder);
+        if event.json().is_some() {
+            // Complete event, only the terminator was lost.
+            observe(&event, stats, open_blocks, started);
+            let trimmed = remainder.trim_ascii_end();
+            out.extend_from_slice(trimmed);
+            out.extend_from_slice(b"\n\n");
+        } else {
+            stats.malformed_events += 1;
+            stats.dropped_partial_bytes = remainder.len() as u64;
+        }
+    }
+
+    if stats.message_started && !stats.message_stopped && stats.error_event.is_none() {
+        for index in std::mem::take(open_blocks) {
+            push_event(
+                &mut out,
+                "content_block_stop",
+                &format!("{{\"type\":\"content_block_stop\",\"index\":{}}}", index),
+            );
+            stats
+                .synthetic_events
+                .push("content_block_stop".to_string());
+        }
+        push_event(
+            &mut out,
+            "error",
+            "{\"type\":\"error\",\"error\":{\"type\":\"api_error\",\"message\":\"Upstream stream ended before message_stop\"}}",
+        );
+        stats.synthetic_events.push("error".to_string());
+        stats.repaired = true;
+    }
+    out
+}
+
+fn push_event(out: &mut Vec<u8>, name: &str, data: &str) {
+    out.extend_from_slice(format!("event: {}\ndata: {}\n\n", name, data).as_bytes());
+}
+
+impl<S> Stream for SseInspector<S>
+where
+    S: Stream<Item = std::result::Result<Bytes, std::io::Error>>,
+{
+    type Item = std::result::Result<Bytes, std::io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            let started = this.guard.started;
+            match futures::ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    this.guard.stats.bytes += chunk.len() as u64;
+                    let frames = this.parser.push(&chunk);
+                    if frames.is_empty() {
+                        continue;
+                    }
+                    let mut out = Vec::new();
+                    for frame in frames {
+                        let event = SseEvent::parse(&frame);
+                        observe(&event, &mut this.guard.stats, this.open_blocks, started);
+                        out.extend_from_slice(&frame);
+                    }
+                    return Poll::Ready(Some(Ok(Bytes::from(out))));
+                }
+                end => {
+                    if let Some(Err(e)) = end {
+                        tracing::warn!(error = %e, "Upstream stream ended with error");
+                        this.guard.stats.upstream_error = Some(e.to_string());
+                    }
+                    let remainder = this.parser.take_remainder();
+                    let out = repair(remainder, &mut this.guard.stats, this.open_blocks, started);
+                    if this.guard.stats.repaired {
+                        tracing::warn!(
+                            synthetic = ?this.guard.stats.synthetic_events,
+                            "Repaired truncated upstream SSE stream"
+                        );
+                    }
+                    *this.done = true;
+                    this.guard.finished = true;
+                    if out.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Ok(Bytes::from(out))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+
+    const START: &str =
+        "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"m\"}}\n\n";
+    const BLOCK: &str = "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n";
+    const DELTA: &str = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"hi\"}}\n\n";
+    const BLOCK_STOP: &str =
+        "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n";
+    const STOP: &str = "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+    const TRUNCATED: &str = "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"api_error\",\"message\":\"Upstream stream ended before message_stop\"}}\n\n";
+
+    type Chunk = std::result::Result<Bytes, std::io::Error>;
+
+    async fn run(chunks: Vec<Chunk>) -> (String, StreamStats) {
+        let stats = Arc::new(Mutex::new(None));
+        let sink = stats.clone();
+        let out: Vec<Chunk> = SseInspector::new(futures::stream::iter(chunks))
+            .on_finish(move |s| *sink.lock().unwrap() = Some(s))
+            .collect()
+            .await;
+        let body: Vec<u8> = out.into_iter().flat_map(|c| c.unwrap().to_vec()).collect();
+        let stats = stats.lock().unwrap().take().expect("stats reported");
+        (String::from_utf8(body).unwrap(), stats)
+    }
+
+    fn chunked(s: &str, size: usize) -> Vec<Chunk> {
+        s.as_bytes()
+            .chunks(size)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parser_splits_frames_across_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"event: ping\ndata: {\"type\"").is_empty());
+        let frames = parser.push(b":\"ping\"}\n\ndata: {}\r\n\r\nda");
+        assert_eq!(frames.len(), 2);
+        let event = SseEvent::parse(&frames[0]);
+        assert_eq!(event.event.as_deref(), Some("ping"));
+        assert_eq!(event.data, "{\"type\":\"ping\"}");
+        assert_eq!(parser.take_remainder(), b"da");
+    }
+
+    #[test]
+    fn test_parser_finds_terminators_split_across_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"data: {}\r\n").is_empty());
+        assert!(parser.push(b"\r").is_empty());
+        assert_eq!(parser.push(b"\ndata: 1\n").len(), 1);
+        let frames = parser.push(b"\ndata: 2");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(SseEvent::parse(&frames[0]).data, "1");
+        // Only what follows the last frame is kept for the next chunk
+        assert_eq!(parser.scanned, b"data: 2".len());
+        assert_eq!(parser.take_remainder(), b"data: 2");
+        assert_eq!(parser.scanned, 0);
+    }
+
+    #[tokio::test]
+    async fn test_well_formed_stream_passes_through() {
+        let input = format!("{START}{BLOCK}{DELTA}{BLOCK_STOP}{STOP}");
+        let (body, stats) = run(chunked(&input, 7)).await;
+        assert_eq!(body, input);
+        assert_eq!(stats.events, 5);
+        assert!(stats.message_stopped);
+        assert!(!stats.repaired);
+        assert_eq!(stats.malformed_events, 0);
+        assert_eq!(stats.event_counts["content_block_delta"], 1);
+        assert!(!stats.client_disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_repaired() {
+        let input = format!("{START}{BLOCK}{DELTA}event: content_block_delta\ndata: {{\"ty");
+        let mut chunks = chunked(&input, 50);
+        chunks.push(Err(std::io::Error::other("connection reset")));
+        let (body, stats) = run(chunks).await;
+
+        assert!(body.starts_with(&format!("{START}{BLOCK}{DELTA}")));
+        assert!(!body.contains("{\"ty\n"));
+        assert!(body.ends_with(&format!("{BLOCK_STOP}{TRUNCATED}")));
+        assert!(!body.contains("event: message_stop"));
+        assert!(stats.repaired);
+        assert!(!stats.message_stopped);
+        assert_eq!(stats.synthetic_events, vec!["content_block_stop", "error"]);
+        assert_eq!(stats.upstream_error.as_deref(), Some("connection reset"));
+        assert!(stats.dropped_partial_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_missing_terminator_is_completed() {
+        let input = format!("{START}{}", STOP.trim_end());
+        let (body, stats) = run(chunked(&input, 1000)).await;
+        assert_eq!(body, format!("{START}{STOP}"));
+        assert!(stats.message_stopped);
+        assert!(!stats.repaired);
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_accounted() {
+        let input = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-x\",\"usage\":{\"input_tokens\":25,\"cache_read_input_tokens\":100,\"output_tokens\":1}}}\n\n\
+            event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":57}}\n\n"
+            .to_string()
+            + STOP;
+        let (_, stats) = run(chunked(&input, 9)).await;
+        assert_eq!(stats.model.as_deref(), Some("claude-x"));
+        assert_eq!(stats.usage.input_tokens, 25);
+        assert_eq!(stats.usage.cache_read_input_tokens, 100);
+        assert_eq!(stats.usage.output_tokens, 57);
+    }
+
+    #[tokio::test]
+    async fn test_server_tool_uses_are_counted() {
+        let search = "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"server_tool_use\",\"id\":\"srvtoolu_1\",\"name\":\"web_search\",\"input\":{}}}\n\n";
+        let input = format!("{START}{search}{BLOCK_STOP}{STOP}");
+        let (body, stats) = run(chunked(&input, 40)).await;
+        assert_eq!(body, input);
+        assert_eq!(stats.server_tool_uses["web_search"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_error_event_is_not_repaired() {
+        let input = format!(
+            "{START}event: error\ndata: {{\"type\":\"error\",\"error\":{{\"type\":\"overloaded_error\"}}}}\n\n"
+        );
+        let (body, stats) = run(chunked(&input, 1000)).await;
+        assert_eq!(body, input);
+        assert!(!stats.repaired);
+        assert_eq!(stats.error_event.unwrap()["type"], "overloaded_error");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_event_is_counted() {
+        let input = format!("{START}data: not json\n\n{STOP}");
+        let (_, stats) = run(chunked(&input, 1000)).await;
+        assert_eq!(stats.malformed_events, 1);
+    }
+}
@@ -0,0 +1,350 @@
+//! Fault-injecting backend wrapper for resilience testing.
+//!
+//! [`ChaosBackend`] wraps any [`LLMBackend`] and injects failures on chosen
+//! calls, so engine error handling and proxy fallback paths can be driven
+//! from tests without a misbehaving provider.
+//!
+//! Faults come from a script (consumed one per call, in order) and then from
+//! seeded random chances, so runs are reproducible.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::backend::{ContentDelta, LLMBackend, ResponseStream, StreamEvent};
+use crate::error::{Result, RlmError};
+use crate::types::{CompletionRequest, CompletionResponse, ContentBlock, StopReason, Usage};
+
+/// Truncated tool input used by [`Fault::MalformedToolCall`].
+pub const MALFORMED_TOOL_INPUT: &str = "{\"path\": \"src/";
+
+/// A failure to inject into a single backend call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Delay the call, then delegate normally.
+    Latency(Duration),
+    /// Wait, then fail with a network timeout (retryable).
+    Timeout(Duration),
+    /// Fail with the provider rate-limit error (429).
+    RateLimit,
+    /// Return a `tool_use` block whose input is truncated JSON, naming the
+    /// request's first tool (or `unknown_tool` when it has none).
+    MalformedToolCall,
+    /// Drop the connection after this many stream events. Non-streaming
+    /// calls fail outright with a network error.
+    Disconnect {
+        /// Events delivered before the stream errors.
+        after_events: usize,
+    },
+}
+
+/// Backend wrapper that injects [`Fault`]s into calls to an inner backend.
+///
+/// # Example
+///
+/// ```ignore
+/// use muninn_rlm::testing::{ChaosBackend, Fault};
+///
+/// let backend = ChaosBackend::new(inner)
+///     .then(Fault::RateLimit) // first call
+///     .then_pass() // second call
+///     .with_chance(Fault::Timeout(Duration::from_millis(5)), 0.1)
+///     .with_seed(7);
+/// ```
+pub struct ChaosBackend {
+    inner: Arc<dyn LLMBackend>,
+    script: Mutex<VecDeque<Option<Fault>>>,
+    chances: Vec<(Fault, f64)>,
+    rng: Mutex<StdRng>,
+    injected: Mutex<Vec<Fault>>,
+    name: String,
+}
+
+impl ChaosBackend {
+    /// Wrap a backend. With no faults configured, calls pass straight through.
+    pub fn new(inner: Arc<dyn LLMBackend>) -> Self {
+        let name = format!("chaos({})", inner.name());
+        Self {
+            inner,
+            script: Mutex::new(VecDeque::new()),
+            chances: Vec::new(),
+            rng: Mutex::new(StdRng::seed_from_u64(0)),
+            injected: Mutex::new(Vec::new()),
+            name,
+        }
+    }
+
+    /// Inject `fault` into the next unscripted call.
+    pub fn then(self, fault: Fault) -> Self {
+        self.script.lock().unwrap().push_back(Some(fault));
+        self
+    }
+
+    /// Let the next unscripted call through untouched.
+    pub fn then_pass(self) -> Self {
+        self.script.lock().unwrap().push_back(None);
+        self
+    }
+
+    /// Once the script runs out, inject `fault` with the given probability.
+    ///
+    /// Chances are rolled in the order they were added; the first hit wins.
+    pub fn with_chance(mut self, fault: Fault, probability: f64) -> Self {
+        self.chances.push((fault, probability.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Seed the random source used by [`ChaosBackend::with_chance`].
+    pub fn with_seed(self, seed: u64) -> Self {
+        *self.rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Faults injected so far, in call order.
+    pub fn injected(&self) -> Vec<Fault> {
+        self.injected.lock().unwrap().clone()
+    }
+
+    /// Pick the fault for the next call, if any.
+    fn next_fault(&self) -> Option<Fault> {
+        let fault = match self.script.lock().unwrap().pop_front() {
+            Some(scripted) => scripted,
+            None => {
+                let mut rng = self.rng.lock().unwrap();
+                self.chances
+                    .iter()
+                    .find(|(_, p)| rng.random_bool(*p))
+                    .map(|(fault, _)| fault.clone())
+            }
+        };
+        if let Some(fault) = &fault {
+            tracing::debug!(backend = %self.name, fault = ?fault, "Injecting fault");
+            self.injected.lock().unwrap().push(fault.clone());
+        }
+        fault
+    }
+}
+
+#[async_trait]
+impl LLMBackend for ChaosBackend {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        match self.next_fault() {
+            None => self.inner.complete(request).await,
+            Some(Fault::Latency(delay)) => {
+                tokio::time::sleep(delay).await;
+                self.inner.complete(request).await
+            }
+            Some(Fault::Timeout(after)) => {
+                tokio::time::sleep(after).await;
+                Err(timeout_error(after))
+            }
+            Some(Fault::RateLimit) => Err(rate_limit_error()),
+            Some(Fault::MalformedToolCall) => Ok(malformed_tool_response(&request)),
+            Some(Fault::Disconnect { .. }) => Err(disconnect_error()),
+        }
+    }
+
+    async fn complete_stream(&self, request: CompletionRequest) -> Result<ResponseStream> {
+        match self.next_fault() {
+            None => self.inner.complete_stream(request).await,
+            Some(Fault::Latency(delay)) => {
+                tokio::time::sleep(delay).await;
+                self.inner.complete_stream(request).await
+            }
+            Some(Fault::Timeout(after)) => {
+                tokio::time::sleep(after).await;
+                Err(timeout_error(after))
+            }
+            Some(Fault::RateLimit) => Err(rate_limit_error()),
+            Some(Fault::MalformedToolCall) => {
+                let events = malformed_tool_events(&request);
+                Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+            }
+            Some(Fault::Disconnect { after_events }) => {
+                let stream = self.inner.complete_stream(request).await?;
+                let cut = stream
+                    .take(after_events)
+                    .chain(futures::stream::once(async { Err(disconnect_error()) }));
+                Ok(Box::pin(cut))
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        self.inner.supports_native_tools()
+    }
+}
+
+fn timeout_error(after: Duration) -> RlmError {
+    RlmError::Network(format!(
+        "Request timed out after {}ms (injected)",
+        after.as_millis()
+    ))
+}
+
+fn rate_limit_error() -> RlmError {
+    // Same shape as the Anthropic/Groq 429 mapping.
+    RlmError::Backend("Rate limit exceeded: injected by ChaosBackend".to_string())
+}
+
+fn disconnect_error() -> RlmError {
+    RlmError::Network("Connection closed mid-stream (injected)".to_string())
+}
+
+/// Name of the tool a malformed call targets.
+fn malformed_tool_name(request: &CompletionRequest) -> String {
+    request
+        .tools
+        .first()
+        .map(|t| t.name.clone())
+        .unwrap_or_else(|| "unknown_tool".to_string())
+}
+
+fn malformed_tool_response(request: &CompletionRequest) -> CompletionResponse {
+    CompletionResponse::new(
+        "msg_chaos",
+        request.model.clone(),
+        vec![ContentBlock::ToolUse {
+            id: "toolu_chaos".to_string(),
+            name: malformed_tool_name(request),
+            input: serde_json::Value::String(MALFORMED_TOOL_INPUT.to_string()),
+            cache_control: None,
+        }],
+        StopReason::ToolUse,
+        Usage::new(0, 0),
+    )
+}
+
+fn malformed_tool_events(request: &CompletionRequest) -> Vec<StreamEvent> {
+    vec![
+        StreamEvent::MessageStart {
+            id: "msg_chaos".to_string(),
+            model: request.model.clone(),
+        },
+        StreamEvent::ContentBlockStart {
+            index: 0,
+            content_type: "tool_use".to_string(),
+        },
+        StreamEvent::ContentBlockDelta {
+            index: 0,
+            delta: ContentDelta::InputJsonDelta(MALFORMED_TOOL_INPUT.to_string()),
+        },
+        StreamEvent::ContentBlockStop { index: 0 },
+        StreamEvent::MessageDelta {
+            stop_reason: StopReason::ToolUse,
+            usage: Usage::new(0, 0),
+        },
+        StreamEvent::MessageStop,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::is_retryable;
+    use crate::testing::{MockLLMBackend, fixtures};
+
+    fn inner(responses: usize) -> Arc<MockLLMBackend> {
+        let backend = MockLLMBackend::new();
+        for i in 0..responses {
+            backend.queue_response(fixtures::text_response(&format!("ok {}", i)));
+        }
+        Arc::new(backend)
+    }
+
+    #[tokio::test]
+    async fn test_script_then_pass_through() {
+        let mock = inner(1);
+        let chaos = ChaosBackend::new(mock.clone())
+            .then(Fault::RateLimit)
+            .then(Fault::Timeout(Duration::from_millis(1)));
+
+        let err = chaos
+            .complete(fixtures::simple_request())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Rate limit"));
+        assert!(!is_retryable(&err));
+
+        let err = chaos
+            .complete(fixtures::simple_request())
+            .await
+            .unwrap_err();
+        assert!(is_retryable(&err));
+
+        // Script exhausted: the inner backend answers
+        let ok = chaos.complete(fixtures::simple_request()).await.unwrap();
+        assert_eq!(ok.text(), "ok 0");
+        mock.assert_request_count(1);
+        assert_eq!(
+            chaos.injected(),
+            vec![Fault::RateLimit, Fault::Timeout(Duration::from_millis(1))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_latency_delegates() {
+        let chaos = ChaosBackend::new(inner(1)).then(Fault::Latency(Duration::from_millis(20)));
+        let start = std::time::Instant::now();
+        let ok = chaos.complete(fixtures::simple_request()).await.unwrap();
+        assert_eq!(ok.text(), "ok 0");
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_tool_call() {
+        let chaos = ChaosBackend::new(inner(0)).then(Fault::MalformedToolCall);
+        let response = chaos.complete(fixtures::simple_request()).await.unwrap();
+        let tool_uses = response.tool_uses();
+        assert_eq!(tool_uses.len(), 1);
+        assert_eq!(tool_uses[0].name, "unknown_tool");
+        assert!(!tool_uses[0].input.is_object());
+    }
+
+    #[tokio::test]
+    async fn test_mid_stream_disconnect() {
+        let chaos = ChaosBackend::new(inner(1)).then(Fault::Disconnect { after_events: 2 });
+        let mut stream = chaos
+            .complete_stream(fixtures::simple_request())
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 3);
+        assert!(events[..2].iter().all(|e| e.is_ok()));
+        assert!(matches!(events[2], Err(RlmError::Network(_))));
+    }
+
+    #[tokio::test]
+    async fn test_seeded_chances_are_reproducible() {
+        let run = || async {
+            let chaos = ChaosBackend::new(inner(20))
+                .with_chance(Fault::RateLimit, 0.5)
+                .with_seed(42);
+            let mut outcomes = Vec::new();
+            for _ in 0..20 {
+                outcomes.push(chaos.complete(fixtures::simple_request()).await.is_ok());
+            }
+            outcomes
+        };
+        let first = run().await;
+        assert_eq!(first, run().await);
+        assert!(first.contains(&true) && first.contains(&false));
+    }
+}
@@ -1,18 +1,22 @@
 //! Testing utilities for muninn-rlm.
 //!
 //! This module provides mock implementations and test fixtures for testing
-//! LLM-based functionality without making real API calls.
+//! LLM-based functionality without making real API calls. Other crates can
+//! use them by enabling the `testing` feature.
 //!
 //! # Components
 //!
+//! - [`chaos_backend`]: Fault-injecting wrapper for resilience tests
 //! - [`fixtures`]: Common test data and request/response builders
 //! - [`mock_backend`]: Enhanced mock LLM backend with request capture
 //! - [`mock_server`]: HTTP mock server for integration tests
 
+pub mod chaos_backend;
 pub mod fixtures;
 pub mod mock_backend;
 pub mod mock_server;
 
+pub use chaos_backend::{ChaosBackend, Fault};
 pub use fixtures::*;
 pub use mock_backend::MockLLMBackend;
 pub use mock_server::MockLLMServer;
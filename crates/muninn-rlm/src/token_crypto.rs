@@ -0,0 +1,399 @@
+//! Encryption at rest for the OAuth token file.
+//!
+//! When enabled (`[auth] encrypt_tokens = true`), [`FileTokenManager`]
+//! stores tokens as a small JSON envelope around a ChaCha20-Poly1305
+//! ciphertext instead of plaintext JSON. The key comes from one of:
+//!
+//! - **Keychain** — a random 256-bit key kept in the OS credential store
+//!   (macOS `security`, Linux Secret Service via `secret-tool`), created
+//!   on first use.
+//! - **Passphrase** — a user passphrase stretched with PBKDF2-HMAC-SHA256
+//!   and a per-file random salt.
+//!
+//! Plaintext token files are still read transparently, so turning
+//! encryption on migrates the file on the next save.
+//!
+//! [`FileTokenManager`]: crate::token_manager::FileTokenManager
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, RlmError};
+
+/// Envelope format version written to disk.
+const ENVELOPE_VERSION: u32 = 1;
+
+/// PBKDF2 rounds for passphrase-derived keys.
+const PBKDF2_ROUNDS: u32 = 210_000;
+
+/// Environment variable consulted for the passphrase key source.
+pub const TOKEN_PASSPHRASE_ENV: &str = "MUNINN_TOKEN_PASSPHRASE";
+
+/// Credential-store service name for the keychain key source.
+const KEYCHAIN_SERVICE: &str = "muninn";
+
+/// Where the token encryption key comes from.
+#[derive(Clone)]
+pub enum TokenKeySource {
+    /// Random key held in the OS keychain, created on first use.
+    Keychain,
+    /// Key derived from a user passphrase.
+    Passphrase(String),
+}
+
+impl std::fmt::Debug for TokenKeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKeySource::Keychain => write!(f, "Keychain"),
+            TokenKeySource::Passphrase(_) => write!(f, "Passphrase(<redacted>)"),
+        }
+    }
+}
+
+impl TokenKeySource {
+    /// Parse a config value (`"keychain"` or `"passphrase"`).
+    ///
+    /// The passphrase itself is read from `MUNINN_TOKEN_PASSPHRASE` so it
+    /// never has to live in `config.toml`.
+    pub fn from_config(name: &str) -> Result<Self> {
+        match name {
+            "keychain" => Ok(TokenKeySource::Keychain),
+            "passphrase" => std::env::var(TOKEN_PASSPHRASE_ENV)
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(TokenKeySource::Passphrase)
+                .ok_or_else(|| {
+                    RlmError::Config(format!(
+                        "[auth] key_source = \"passphrase\" requires {} to be set",
+                        TOKEN_PASSPHRASE_ENV
+                    ))
+                }),
+            other => Err(RlmError::Config(format!(
+                "Unknown [auth] key_source '{}'. Expected \"keychain\" or \"passphrase\".",
+                other
+            ))),
+        }
+    }
+}
+
+/// On-disk envelope for an encrypted token file.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedEnvelope {
+    /// Envelope format version.
+    encrypted: u32,
+    /// Key derivation: `"keychain"` or `"pbkdf2-sha256"`.
+    kdf: String,
+    /// Base64 salt (passphrase KDF only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    /// Base64 96-bit nonce.
+    nonce: String,
+    /// Base64 ciphertext including the Poly1305 tag.
+    ciphertext: String,
+}
+
+/// Token file encryption settings for a [`FileTokenManager`].
+///
+/// [`FileTokenManager`]: crate::token_manager::FileTokenManager
+#[derive(Debug, Clone)]
+pub struct TokenEncryption {
+    source: TokenKeySource,
+}
+
+impl TokenEncryption {
+    /// Encrypt with a key from the given source.
+    pub fn new(source: TokenKeySource) -> Self {
+        Self { source }
+    }
+
+    /// Check whether file contents look like an encrypted envelope.
+    pub fn is_encrypted(content: &str) -> bool {
+        serde_json::from_str::<EncryptedEnvelope>(content).is_ok()
+    }
+
+    /// Encrypt `plaintext` for storage at `token_path`.
+    pub fn encrypt(&self, token_path: &Path, plaintext: &[u8]) -> Result<String> {
+        let (key, kdf, salt) = match &self.source {
+            TokenKeySource::Keychain => (keychain_key(token_path)?, "keychain", None),
+            TokenKeySource::Passphrase(p) => {
+                let mut salt = [0u8; 16];
+                rand::rng().fill_bytes(&mut salt);
+                (derive_key(p, &salt), "pbkdf2-sha256", Some(salt))
+            }
+        };
+
+        let mut nonce = [0u8; 12];
+        rand::rng().fill_bytes(&mut nonce);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| RlmError::Internal("Token encryption failed".to_string()))?;
+
+        let envelope = EncryptedEnvelope {
+            encrypted: ENVELOPE_VERSION,
+            kdf: kdf.to_string(),
+            salt: salt.map(|s| STANDARD.encode(s)),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        Ok(serde_json::to_string_pretty(&envelope)?)
+    }
+
+    /// Decrypt an envelope previously produced by [`TokenEncryption::encrypt`].
+    ///
+    /// A wrong key surfaces as [`RlmError::Config`], not as a parse error,
+    /// so it is never mistaken for file corruption.
+    pub fn decrypt(&self, token_path: &Path, content: &str) -> Result<Vec<u8>> {
+        let envelope: EncryptedEnvelope = serde_json::from_str(content)?;
+        if envelope.encrypted != ENVELOPE_VERSION {
+            return Err(RlmError::Config(format!(
+                "Unsupported token envelope version {}",
+                envelope.encrypted
+            )));
+        }
+
+        let key = match (&self.source, envelope.kdf.as_str()) {
+            (TokenKeySource::Keychain, "keychain") => keychain_key(token_path)?,
+            (TokenKeySource::Passphrase(p), "pbkdf2-sha256") => {
+                let salt = decode_field(envelope.salt.as_deref().unwrap_or_default(), "salt")?;
+                derive_key(p, &salt)
+            }
+            (source, kdf) => {
+                return Err(RlmError::Config(format!(
+                    "Token file was encrypted with '{}' but [auth] key_source is {:?}",
+                    kdf, source
+                )));
+            }
+        };
+
+        let nonce = decode_field(&envelope.nonce, "nonce")?;
+        if nonce.len() != 12 {
+            return Err(RlmError::Serialization("Invalid token nonce".to_string()));
+        }
+        let ciphertext = decode_field(&envelope.ciphertext, "ciphertext")?;
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| {
+                RlmError::Config(
+                    "Failed to decrypt token file (wrong key or passphrase?)".to_string(),
+                )
+            })
+    }
+}
+
+fn decode_field(value: &str, name: &str) -> Result<Vec<u8>> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| RlmError::Serialization(format!("Invalid token {}: {}", name, e)))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+/// Keychain account name, scoped to the token file so separate projects
+/// get separate keys.
+fn keychain_account(token_path: &Path) -> String {
+    let abs = std::path::absolute(token_path).unwrap_or_else(|_| token_path.to_path_buf());
+    let digest = Sha256::digest(abs.to_string_lossy().as_bytes());
+    let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("oauth-token-key-{}", hex)
+}
+
+/// Fetch the token key from the OS keychain, creating it on first use.
+fn keychain_key(token_path: &Path) -> Result<[u8; 32]> {
+    let account = keychain_account(token_path);
+    let encoded = match keychain_lookup(&account)? {
+        Some(k) => k,
+        None => {
+            let mut key = [0u8; 32];
+            rand::rng().fill_bytes(&mut key);
+            let encoded = STANDARD.encode(key);
+            keychain_store(&account, &encoded)?;
+            encoded
+        }
+    };
+    let bytes = decode_field(encoded.trim(), "keychain key")?;
+    bytes
+        .try_into()
+        .map_err(|_| RlmError::Config("Keychain token key has the wrong length".to_string()))
+}
+
+fn keychain_unavailable(e: std::io::Error) -> RlmError {
+    RlmError::Config(format!(
+        "OS keychain unavailable ({}). Set [auth] key_source = \"passphrase\" and {} instead.",
+        e, TOKEN_PASSPHRASE_ENV
+    ))
+}
+
+#[cfg(target_os = "macos")]
+fn keychain_lookup(account: &str) -> Result<Option<String>> {
+    let out = Command::new("security")
+        .args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account,
+            "-w",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .map_err(keychain_unavailable)?;
+    Ok(out
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string()))
+}
+
+/// Store the key with `security -i`, which reads the whole command from
+/// stdin, so the secret never appears in argv where other processes could
+/// see it.
+#[cfg(target_os = "macos")]
+fn keychain_store(account: &str, secret: &str) -> Result<()> {
+    use std::io::Write;
+    let mut child = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(keychain_unavailable)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(keychain_store_command(account, secret).as_bytes())?;
+    }
+    child.wait()?;
+    // The exit status of an interactive session doesn't reliably report a
+    // failed command, so read the key back.
+    if keychain_lookup(account)?.as_deref() == Some(secret) {
+        Ok(())
+    } else {
+        Err(RlmError::Config(
+            "Failed to store token key in the macOS keychain".to_string(),
+        ))
+    }
+}
+
+/// The `security -i` command line storing `secret`. The service, account
+/// and base64 secret never contain whitespace, so they need no quoting.
+#[cfg(any(target_os = "macos", test))]
+fn keychain_store_command(account: &str, secret: &str) -> String {
+    format!(
+        "add-generic-password -U -s {} -a {} -w {}\n",
+        KEYCHAIN_SERVICE, account, secret
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+fn keychain_lookup(account: &str) -> Result<Option<String>> {
+    let out = Command::new("secret-tool")
+        .args(["lookup", "service", KEYCHAIN_SERVICE, "account", account])
+        .stderr(Stdio::null())
+        .output()
+        .map_err(keychain_unavailable)?;
+    let value = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    Ok((out.status.success() && !value.is_empty()).then_some(value))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn keychain_store(account: &str, secret: &str) -> Result<()> {
+    use std::io::Write;
+    let mut child = Command::new("secret-tool")
+        .args([
+            "store",
+            "--label",
+            "muninn OAuth token key",
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            account,
+        ])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(keychain_unavailable)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(secret.as_bytes())?;
+    }
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(RlmError::Config(
+            "Failed to store token key via secret-tool".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passphrase(p: &str) -> TokenEncryption {
+        TokenEncryption::new(TokenKeySource::Passphrase(p.to_string()))
+    }
+
+    #[test]
+    fn test_passphrase_roundtrip() {
+        let enc = passphrase("correct horse");
+        let path = Path::new("/tmp/oauth-tokens.json");
+        let sealed = enc.encrypt(path, b"{\"access_token\":\"secret\"}").unwrap();
+
+        assert!(TokenEncryption::is_encrypted(&sealed));
+        assert!(!sealed.contains("secret"));
+        let opened = enc.decrypt(path, &sealed).unwrap();
+        assert_eq!(opened, b"{\"access_token\":\"secret\"}");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_config_error() {
+        let path = Path::new("/tmp/oauth-tokens.json");
+        let sealed = passphrase("right").encrypt(path, b"tokens").unwrap();
+        let err = passphrase("wrong").decrypt(path, &sealed).unwrap_err();
+        assert!(matches!(err, RlmError::Config(_)));
+    }
+
+    #[test]
+    fn test_plaintext_is_not_envelope() {
+        assert!(!TokenEncryption::is_encrypted(
+            r#"{"access_token":"a","refresh_token":"b"}"#
+        ));
+    }
+
+    #[test]
+    fn test_key_source_debug_redacts_passphrase() {
+        let s = format!("{:?}", TokenKeySource::Passphrase("hunter2".to_string()));
+        assert!(!s.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_keychain_store_command_is_one_line() {
+        let secret = STANDARD.encode([7u8; 32]);
+        let account = keychain_account(Path::new("/tmp/tokens.json"));
+        let command = keychain_store_command(&account, &secret);
+
+        assert_eq!(
+            command,
+            format!("add-generic-password -U -s muninn -a {account} -w {secret}\n")
+        );
+        assert_eq!(command.lines().count(), 1);
+        assert!(!secret.contains(char::is_whitespace));
+        assert!(!account.contains(char::is_whitespace));
+    }
+
+    #[test]
+    fn test_unknown_key_source_rejected() {
+        assert!(TokenKeySource::from_config("tpm").is_err());
+        assert!(matches!(
+            TokenKeySource::from_config("keychain"),
+            Ok(TokenKeySource::Keychain)
+        ));
+    }
+}
@@ -13,10 +13,12 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
 
 use async_trait::async_trait;
+use muninn_graph::FileLock;
 use tokio::sync::RwLock;
 
 use crate::error::{Result, RlmError};
 use crate::oauth::{OAuthConfig, OAuthTokens, refresh_access_token};
+use crate::token_crypto::TokenEncryption;
 
 /// Default token file name within the .muninn directory.
 pub const TOKEN_FILE: &str = "oauth-tokens.json";
@@ -24,6 +26,9 @@ pub const TOKEN_FILE: &str = "oauth-tokens.json";
 /// Buffer time before expiry to trigger refresh (5 minutes in milliseconds).
 const REFRESH_BUFFER_MS: u64 = 5 * 60 * 1000;
 
+/// How long to wait for another process to finish writing the token file.
+const TOKEN_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 // ============================================================================
 // TokenManager Trait
 // ============================================================================
@@ -62,7 +67,8 @@ pub trait TokenManager: Send + Sync + std::fmt::Debug {
 
 /// File-based token manager for production use.
 ///
-/// Persists tokens to a JSON file on disk.
+/// Persists tokens to a JSON file on disk, optionally encrypted at rest
+/// (see [`FileTokenManager::with_encryption`]).
 #[derive(Debug)]
 pub struct FileTokenManager {
     /// Path to the token file.
@@ -71,6 +77,8 @@ pub struct FileTokenManager {
     config: OAuthConfig,
     /// Cached tokens (with RwLock for concurrent access).
     cached_tokens: Arc<RwLock<Option<OAuthTokens>>>,
+    /// Encryption applied on save; `None` writes plaintext JSON.
+    encryption: Option<TokenEncryption>,
 }
 
 impl FileTokenManager {
@@ -83,6 +91,7 @@ impl FileTokenManager {
             token_path: muninn_dir.join(TOKEN_FILE),
             config: OAuthConfig::default(),
             cached_tokens: Arc::new(RwLock::new(None)),
+            encryption: None,
         }
     }
 
@@ -92,9 +101,19 @@ impl FileTokenManager {
             token_path,
             config: OAuthConfig::default(),
             cached_tokens: Arc::new(RwLock::new(None)),
+            encryption: None,
         }
     }
 
+    /// Encrypt the token file at rest.
+    ///
+    /// Existing plaintext files are still read and get encrypted on the
+    /// next save.
+    pub fn with_encryption(mut self, encryption: TokenEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
     /// Get the token file path.
     pub fn token_path(&self) -> &Path {
         &self.token_path
@@ -113,26 +132,107 @@ impl FileTokenManager {
 
         now >= tokens.expires_at.saturating_sub(REFRESH_BUFFER_MS)
     }
-}
 
-#[async_trait]
-impl TokenManager for FileTokenManager {
-    fn has_tokens(&self) -> bool {
-        self.token_path.exists()
+    /// Path of the previous-generation token file kept by [`TokenManager::save_tokens`].
+    pub fn backup_path(&self) -> PathBuf {
+        sibling_path(&self.token_path, ".bak")
     }
 
-    async fn save_tokens(&self, tokens: &OAuthTokens) -> Result<()> {
-        // Ensure parent directory exists
-        if let Some(parent) = self.token_path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                RlmError::Config(format!("Failed to create token directory: {}", e))
+    /// Read and parse a token file (the live file or its backup),
+    /// decrypting it if it holds an encrypted envelope.
+    fn read_token_file(&self, path: &Path) -> Result<OAuthTokens> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| RlmError::Config(format!("Failed to read token file: {}", e)))?;
+
+        let plaintext = if TokenEncryption::is_encrypted(&content) {
+            let encryption = self.encryption.as_ref().ok_or_else(|| {
+                RlmError::Config(
+                    "Token file is encrypted; set [auth] encrypt_tokens = true to read it"
+                        .to_string(),
+                )
             })?;
+            // Keys are scoped to the live token path, backups included.
+            String::from_utf8(encryption.decrypt(&self.token_path, &content)?).map_err(|e| {
+                RlmError::Serialization(format!("Failed to parse token file: {}", e))
+            })?
+        } else {
+            content
+        };
+
+        serde_json::from_str(&plaintext)
+            .map_err(|e| RlmError::Serialization(format!("Failed to parse token file: {}", e)))
+    }
+
+    /// Recover from an unparseable token file.
+    ///
+    /// Restores the backup if it parses; otherwise moves the corrupt file
+    /// aside (`<file>.corrupt`) so `has_tokens()` reports false and callers
+    /// fall through to re-authentication instead of failing forever.
+    fn recover_corrupt_tokens(&self, parse_err: &RlmError) -> Option<OAuthTokens> {
+        tracing::warn!(
+            "Token file {} is corrupted ({}); attempting recovery",
+            self.token_path.display(),
+            parse_err
+        );
+
+        let backup = self.backup_path();
+        if let Ok(tokens) = self.read_token_file(&backup) {
+            match std::fs::copy(&backup, &self.token_path) {
+                Ok(_) => tracing::info!("Restored tokens from {}", backup.display()),
+                Err(e) => tracing::warn!("Failed to restore token backup: {}", e),
+            }
+            return Some(tokens);
         }
 
-        let json = serde_json::to_string_pretty(tokens)
+        let quarantine = sibling_path(&self.token_path, ".corrupt");
+        if let Err(e) = std::fs::rename(&self.token_path, &quarantine) {
+            tracing::warn!("Failed to move corrupted token file aside: {}", e);
+            let _ = std::fs::remove_file(&self.token_path);
+        }
+        tracing::warn!("No usable token backup; run 'muninn oauth' to re-authenticate");
+        None
+    }
+
+    /// Take the advisory lock guarding the token file.
+    ///
+    /// Several proxies can share one `.muninn/` directory and all of them
+    /// refresh tokens on expiry; the lock keeps their read-modify-write
+    /// cycles (load, refresh or recover, save) from interleaving. Waits
+    /// briefly for a live holder before giving up. Not reentrant: callers
+    /// holding it use the `_locked` helpers.
+    async fn lock_token_file(&self) -> Result<FileLock> {
+        let path = self.token_path.clone();
+        tokio::task::spawn_blocking(move || {
+            FileLock::acquire_with_timeout(&path, TOKEN_LOCK_TIMEOUT)
+        })
+        .await
+        .map_err(|e| RlmError::Internal(format!("Token lock task failed: {}", e)))?
+        .map_err(|e| RlmError::Config(format!("Failed to lock token file: {}", e)))
+    }
+
+    /// Write `tokens` (write-then-rename, keeping a backup) and cache them.
+    /// The caller holds the token lock.
+    async fn save_tokens_locked(&self, tokens: &OAuthTokens) -> Result<()> {
+        let mut json = serde_json::to_string_pretty(tokens)
             .map_err(|e| RlmError::Serialization(format!("Failed to serialize tokens: {}", e)))?;
+        if let Some(encryption) = &self.encryption {
+            json = encryption.encrypt(&self.token_path, json.as_bytes())?;
+        }
+
+        // Write-then-rename so a crash mid-write never leaves a truncated
+        // token file behind; readers see either the old set or the new one.
+        let tmp_path = sibling_path(&self.token_path, ".tmp");
+        write_synced(&tmp_path, json.as_bytes())
+            .map_err(|e| RlmError::Config(format!("Failed to write token file: {}", e)))?;
 
-        std::fs::write(&self.token_path, json)
+        // Keep the previous generation around for corruption recovery.
+        if self.read_token_file(&self.token_path).is_ok()
+            && let Err(e) = std::fs::copy(&self.token_path, self.backup_path())
+        {
+            tracing::warn!("Failed to back up previous tokens: {}", e);
+        }
+
+        std::fs::rename(&tmp_path, &self.token_path)
             .map_err(|e| RlmError::Config(format!("Failed to write token file: {}", e)))?;
 
         // Update cache
@@ -143,6 +243,47 @@ impl TokenManager for FileTokenManager {
         Ok(())
     }
 
+    /// Read the token file, recovering a corrupt one, and cache the
+    /// result. The caller holds the token lock.
+    async fn load_tokens_locked(&self) -> Result<Option<OAuthTokens>> {
+        if !self.token_path.exists() {
+            return Ok(None);
+        }
+
+        let tokens = match self.read_token_file(&self.token_path) {
+            Ok(tokens) => tokens,
+            Err(e @ RlmError::Serialization(_)) => match self.recover_corrupt_tokens(&e) {
+                Some(tokens) => tokens,
+                None => return Ok(None),
+            },
+            Err(e) => return Err(e),
+        };
+
+        // Update cache
+        let mut cache = self.cached_tokens.write().await;
+        *cache = Some(tokens.clone());
+
+        Ok(Some(tokens))
+    }
+}
+
+#[async_trait]
+impl TokenManager for FileTokenManager {
+    fn has_tokens(&self) -> bool {
+        self.token_path.exists()
+    }
+
+    async fn save_tokens(&self, tokens: &OAuthTokens) -> Result<()> {
+        // Ensure parent directory exists
+        if let Some(parent) = self.token_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                RlmError::Config(format!("Failed to create token directory: {}", e))
+            })?;
+        }
+        let _lock = self.lock_token_file().await?;
+        self.save_tokens_locked(tokens).await
+    }
+
     async fn load_tokens(&self) -> Result<Option<OAuthTokens>> {
         // Check cache first
         {
@@ -156,52 +297,54 @@ impl TokenManager for FileTokenManager {
         if !self.token_path.exists() {
             return Ok(None);
         }
-
-        let content = std::fs::read_to_string(&self.token_path)
-            .map_err(|e| RlmError::Config(format!("Failed to read token file: {}", e)))?;
-
-        let tokens: OAuthTokens = serde_json::from_str(&content)
-            .map_err(|e| RlmError::Serialization(format!("Failed to parse token file: {}", e)))?;
-
-        // Update cache
-        let mut cache = self.cached_tokens.write().await;
-        *cache = Some(tokens.clone());
-
-        Ok(Some(tokens))
+        let _lock = self.lock_token_file().await?;
+        self.load_tokens_locked().await
     }
 
     async fn get_valid_access_token(&self) -> Result<String> {
-        let tokens = self.load_tokens().await?.ok_or_else(|| {
-            RlmError::Config("No OAuth tokens found. Run 'muninn oauth' first.".to_string())
-        })?;
+        let no_tokens =
+            || RlmError::Config("No OAuth tokens found. Run 'muninn oauth' first.".to_string());
+        let tokens = self.load_tokens().await?.ok_or_else(no_tokens)?;
+        if !Self::is_token_expired(&tokens) {
+            return Ok(tokens.access_token);
+        }
 
-        if Self::is_token_expired(&tokens) {
-            tracing::info!("Token expired, refreshing...");
-            let mut new_tokens = refresh_access_token(&self.config, &tokens.refresh_token).await?;
+        // Hold the lock from reading the expired tokens to saving the
+        // refreshed ones, so two processes never refresh (and rotate) the
+        // same refresh token at once.
+        let _lock = self.lock_token_file().await?;
+        self.clear_cache().await;
+        let tokens = self.load_tokens_locked().await?.ok_or_else(no_tokens)?;
+        if !Self::is_token_expired(&tokens) {
+            // Another process refreshed them while we waited
+            return Ok(tokens.access_token);
+        }
 
-            // Preserve refresh token if not returned in response
-            if new_tokens.refresh_token.is_empty() {
-                new_tokens.refresh_token = tokens.refresh_token;
-            }
+        tracing::info!("Token expired, refreshing...");
+        let mut new_tokens = refresh_access_token(&self.config, &tokens.refresh_token).await?;
 
-            self.save_tokens(&new_tokens).await?;
-            tracing::info!("Token refreshed successfully");
-            return Ok(new_tokens.access_token);
+        // Preserve refresh token if not returned in response
+        if new_tokens.refresh_token.is_empty() {
+            new_tokens.refresh_token = tokens.refresh_token;
         }
 
-        Ok(tokens.access_token)
+        self.save_tokens_locked(&new_tokens).await?;
+        tracing::info!("Token refreshed successfully");
+        Ok(new_tokens.access_token)
     }
-
     async fn clear_cache(&self) {
         let mut cache = self.cached_tokens.write().await;
         *cache = None;
     }
 
     async fn delete_tokens(&self) -> Result<()> {
+        let _lock = self.lock_token_file().await?;
         if self.token_path.exists() {
             std::fs::remove_file(&self.token_path)
                 .map_err(|e| RlmError::Config(format!("Failed to delete token file: {}", e)))?;
         }
+        // Logging out must not leave a backup for recovery to resurrect.
+        let _ = std::fs::remove_file(self.backup_path());
         self.clear_cache().await;
         Ok(())
     }
@@ -234,6 +377,21 @@ impl TokenManager for FileTokenManager {
     }
 }
 
+/// `oauth-tokens.json` + `.bak` → `oauth-tokens.json.bak`.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(suffix);
+    PathBuf::from(s)
+}
+
+/// Write `bytes` to `path` and fsync before returning.
+fn write_synced(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
 // ============================================================================
 // InMemoryTokenManager
 // ============================================================================
@@ -499,6 +657,198 @@ mod tests {
         assert!(FileTokenManager::is_token_expired(&expired_tokens));
     }
 
+    fn file_test_tokens(access: &str) -> OAuthTokens {
+        OAuthTokens {
+            access_token: access.to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_in: 3600,
+            token_type: "Bearer".to_string(),
+            scope: "test".to_string(),
+            expires_at: 9999999999999,
+            created_at: "".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_save_is_atomic_and_keeps_backup() {
+        let temp = tempdir().unwrap();
+        let manager = FileTokenManager::new(temp.path());
+
+        manager
+            .save_tokens(&file_test_tokens("first"))
+            .await
+            .unwrap();
+        assert!(!manager.backup_path().exists());
+
+        manager
+            .save_tokens(&file_test_tokens("second"))
+            .await
+            .unwrap();
+        let backup = manager.read_token_file(&manager.backup_path()).unwrap();
+        assert_eq!(backup.access_token, "first");
+
+        // No temp files left behind, and the lock is released.
+        let leftovers: Vec<_> = std::fs::read_dir(temp.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "leftover files: {leftovers:?}");
+        FileLock::try_acquire(manager.token_path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_corrupt_tokens_recover_from_backup() {
+        let temp = tempdir().unwrap();
+        let manager = FileTokenManager::new(temp.path());
+        manager
+            .save_tokens(&file_test_tokens("first"))
+            .await
+            .unwrap();
+        manager
+            .save_tokens(&file_test_tokens("second"))
+            .await
+            .unwrap();
+
+        // Simulate a torn write from an older muninn.
+        std::fs::write(manager.token_path(), "{\"access_token\": \"sec").unwrap();
+        manager.clear_cache().await;
+
+        let loaded = manager.load_tokens().await.unwrap().unwrap();
+        assert_eq!(loaded.access_token, "first");
+        // The backup was copied back into place.
+        assert!(manager.read_token_file(manager.token_path()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_file_corrupt_tokens_without_backup_prompts_reauth() {
+        let temp = tempdir().unwrap();
+        let manager = FileTokenManager::new(temp.path());
+        std::fs::write(manager.token_path(), "not json").unwrap();
+
+        assert!(manager.load_tokens().await.unwrap().is_none());
+        assert!(!manager.has_tokens());
+        assert!(temp.path().join("oauth-tokens.json.corrupt").exists());
+
+        let err = manager.get_valid_access_token().await.unwrap_err();
+        assert!(err.to_string().contains("muninn oauth"));
+    }
+
+    #[tokio::test]
+    async fn test_file_delete_removes_backup() {
+        let temp = tempdir().unwrap();
+        let manager = FileTokenManager::new(temp.path());
+        manager
+            .save_tokens(&file_test_tokens("first"))
+            .await
+            .unwrap();
+        manager
+            .save_tokens(&file_test_tokens("second"))
+            .await
+            .unwrap();
+        assert!(manager.backup_path().exists());
+
+        manager.delete_tokens().await.unwrap();
+        assert!(!manager.has_tokens());
+        assert!(!manager.backup_path().exists());
+    }
+
+    #[tokio::test]
+    async fn test_file_encrypted_tokens_roundtrip() {
+        use crate::token_crypto::TokenKeySource;
+
+        let temp = tempdir().unwrap();
+        let encryption = TokenEncryption::new(TokenKeySource::Passphrase("pw".to_string()));
+        let manager = FileTokenManager::new(temp.path()).with_encryption(encryption.clone());
+
+        manager
+            .save_tokens(&file_test_tokens("secret"))
+            .await
+            .unwrap();
+        let on_disk = std::fs::read_to_string(manager.token_path()).unwrap();
+        assert!(!on_disk.contains("secret"));
+
+        // A fresh manager (no cache) decrypts transparently.
+        let reader = FileTokenManager::new(temp.path()).with_encryption(encryption);
+        let loaded = reader.load_tokens().await.unwrap().unwrap();
+        assert_eq!(loaded.access_token, "secret");
+
+        // Without encryption configured, the file is reported, not quarantined.
+        let plain = FileTokenManager::new(temp.path());
+        assert!(plain.load_tokens().await.is_err());
+        assert!(plain.has_tokens());
+    }
+
+    #[tokio::test]
+    async fn test_file_encryption_migrates_plaintext() {
+        use crate::token_crypto::TokenKeySource;
+
+        let temp = tempdir().unwrap();
+        FileTokenManager::new(temp.path())
+            .save_tokens(&file_test_tokens("legacy"))
+            .await
+            .unwrap();
+
+        let encryption = TokenEncryption::new(TokenKeySource::Passphrase("pw".to_string()));
+        let manager = FileTokenManager::new(temp.path()).with_encryption(encryption);
+        let loaded = manager.load_tokens().await.unwrap().unwrap();
+        assert_eq!(loaded.access_token, "legacy");
+
+        manager.save_tokens(&loaded).await.unwrap();
+        let on_disk = std::fs::read_to_string(manager.token_path()).unwrap();
+        assert!(TokenEncryption::is_encrypted(&on_disk));
+    }
+
+    #[tokio::test]
+    async fn test_file_save_fails_while_locked_elsewhere() {
+        let temp = tempdir().unwrap();
+        let manager = FileTokenManager::new(temp.path());
+
+        // Simulate another live process holding the token lock.
+        let _held = FileLock::try_acquire(manager.token_path()).unwrap();
+
+        let tokens = OAuthTokens {
+            access_token: "test".to_string(),
+            refresh_token: "test".to_string(),
+            expires_in: 3600,
+            token_type: "Bearer".to_string(),
+            scope: "test".to_string(),
+            expires_at: 9999999999999,
+            created_at: "".to_string(),
+        };
+        let err = manager.save_tokens(&tokens).await.unwrap_err();
+        assert!(err.to_string().contains("locked"));
+        assert!(!manager.has_tokens());
+    }
+
+    #[tokio::test]
+    async fn test_file_refresh_rereads_under_lock() {
+        let temp = tempdir().unwrap();
+        let tokens = |access: &str, expires_at: u64| OAuthTokens {
+            access_token: access.to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_in: 3600,
+            token_type: "Bearer".to_string(),
+            scope: "test".to_string(),
+            expires_at,
+            created_at: "".to_string(),
+        };
+        let manager = FileTokenManager::new(temp.path());
+        manager.save_tokens(&tokens("stale", 1)).await.unwrap();
+
+        // Another process refreshes the tokens after this one cached them
+        let other = FileTokenManager::new(temp.path());
+        other
+            .save_tokens(&tokens("fresh", 9999999999999))
+            .await
+            .unwrap();
+
+        // The expired cache is re-read under the lock instead of refreshing
+        assert_eq!(manager.get_valid_access_token().await.unwrap(), "fresh");
+        FileLock::try_acquire(manager.token_path()).unwrap();
+    }
+
     #[tokio::test]
     async fn test_file_delete_tokens() {
         let temp = tempdir().unwrap();
@@ -0,0 +1,561 @@
+//! Middleware around tool execution.
+//!
+//! A [`ToolMiddleware`] registered on a [`ToolRegistry`](crate::tools::ToolRegistry)
+//! sees every tool call: `before` runs ahead of the tool and may rewrite its
+//! input or answer in its place, `after` runs on the result. Cross-cutting
+//! concerns (logging, caching, rate limiting, policy checks) live here instead
+//! of inside each tool.
+//!
+//! Middleware runs in registration order on the way in and in reverse order
+//! on the way out. When a `before` hook returns a result, the tool and any
+//! later middleware are skipped; middleware registered earlier still get
+//! their `after` hook.
+//!
+//! ```rust,ignore
+//! let mut registry = ToolRegistry::new();
+//! registry.add_middleware(LoggingMiddleware);
+//! registry.add_middleware(GuardMiddleware::new(|call| {
+//!     match call.input.get("path").and_then(|p| p.as_str()) {
+//!         Some(path) if path.contains(".env") => Err("secrets are off limits".into()),
+//!         _ => Ok(()),
+//!     }
+//! }));
+//! registry.add_middleware(CacheMiddleware::new().only(["graph_query", "find_symbols"]));
+//! ```
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use muninn_graph::IndexQueue;
+
+use crate::error::Result;
+use crate::tools::{Tool, ToolResult};
+
+/// A tool invocation as seen by middleware.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    tool: String,
+    /// Tool input. Middleware may rewrite it before the tool runs.
+    pub input: serde_json::Value,
+}
+
+impl ToolCall {
+    /// Create a call to `tool` with the given input.
+    pub fn new(tool: impl Into<String>, input: serde_json::Value) -> Self {
+        Self {
+            tool: tool.into(),
+            input,
+        }
+    }
+
+    /// Name of the tool being called.
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+}
+
+/// Hooks that run around every tool execution.
+#[async_trait]
+pub trait ToolMiddleware: Send + Sync {
+    /// Name used in logs and debug output.
+    fn name(&self) -> &str;
+
+    /// Runs before the tool. Return `Some(result)` to answer without running it.
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        let _ = call;
+        Ok(None)
+    }
+
+    /// Runs after the tool (or a later middleware) produced a result.
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) -> Result<()> {
+        let _ = (call, result);
+        Ok(())
+    }
+}
+
+/// Execute `tool` through a middleware chain.
+pub(crate) async fn execute_with_middleware(
+    middleware: &[Arc<dyn ToolMiddleware>],
+    tool: &dyn Tool,
+    input: serde_json::Value,
+) -> Result<ToolResult> {
+    let mut call = ToolCall::new(tool.name(), input);
+
+    let mut entered = 0;
+    let mut short_circuit = None;
+    for layer in middleware {
+        entered += 1;
+        if let Some(result) = layer.before(&mut call).await? {
+            tracing::debug!(tool = %call.tool, middleware = layer.name(), "Tool call answered by middleware");
+            short_circuit = Some(result);
+            break;
+        }
+    }
+
+    let (mut result, unwind) = match short_circuit {
+        // The answering layer doesn't see its own result
+        Some(result) => (result, entered - 1),
+        None => (tool.execute(call.input.clone()).await?, entered),
+    };
+
+    for layer in middleware[..unwind].iter().rev() {
+        layer.after(&call, &mut result).await?;
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// Built-in Middleware
+// ============================================================================
+
+/// Logs each tool call and whether it failed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingMiddleware;
+
+#[async_trait]
+impl ToolMiddleware for LoggingMiddleware {
+    fn name(&self) -> &str {
+        "logging"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        tracing::debug!(tool = %call.tool, input = %call.input, "Tool call");
+        Ok(None)
+    }
+
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) -> Result<()> {
+        if result.is_error() {
+            tracing::debug!(tool = %call.tool, error = %result.to_string_content(), "Tool call failed");
+        } else {
+            tracing::debug!(tool = %call.tool, "Tool call succeeded");
+        }
+        Ok(())
+    }
+}
+
+/// Remembers successful results and replays them for identical calls.
+///
+/// Use [`CacheMiddleware::only`] to limit caching to tools whose output
+/// doesn't change during a session (graph queries, symbol lookups).
+#[derive(Debug, Default)]
+pub struct CacheMiddleware {
+    tools: Option<HashSet<String>>,
+    entries: Mutex<HashMap<String, ToolResult>>,
+}
+
+impl CacheMiddleware {
+    /// Cache every tool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache only the named tools.
+    pub fn only<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn key(&self, call: &ToolCall) -> Option<String> {
+        let cached = self.tools.as_ref().is_none_or(|t| t.contains(&call.tool));
+        cached.then(|| format!("{}:{}", call.tool, call.input))
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for CacheMiddleware {
+    fn name(&self) -> &str {
+        "cache"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        let Some(key) = self.key(call) else {
+            return Ok(None);
+        };
+        Ok(self.entries.lock().unwrap().get(&key).cloned())
+    }
+
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) -> Result<()> {
+        if result.is_error() {
+            return Ok(());
+        }
+        if let Some(key) = self.key(call) {
+            self.entries.lock().unwrap().insert(key, result.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Limits how often each tool may run within a sliding window.
+///
+/// Calls over the limit get a recoverable error telling the model to slow
+/// down or pick another tool; the tool itself isn't run.
+#[derive(Debug)]
+pub struct RateLimitMiddleware {
+    max_calls: usize,
+    window: Duration,
+    calls: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimitMiddleware {
+    /// Allow at most `max_calls` calls per tool in any `window`.
+    pub fn new(max_calls: usize, window: Duration) -> Self {
+        Self {
+            max_calls,
+            window,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for RateLimitMiddleware {
+    fn name(&self) -> &str {
+        "rate_limit"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        let now = Instant::now();
+        let mut calls = self.calls.lock().unwrap();
+        let recent = calls.entry(call.tool.clone()).or_default();
+        while recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= self.window)
+        {
+            recent.pop_front();
+        }
+
+        if recent.len() >= self.max_calls {
+            return Ok(Some(ToolResult::error(
+                format!(
+                    "Rate limit for '{}' reached ({} calls per {}s). Use a different tool or wait.",
+                    call.tool,
+                    self.max_calls,
+                    self.window.as_secs_f64()
+                ),
+                true,
+            )));
+        }
+        recent.push_back(now);
+        Ok(None)
+    }
+}
+
+type RewriteFn = dyn Fn(&mut serde_json::Value) + Send + Sync;
+
+/// Rewrites the input of one tool before it runs.
+///
+/// Useful for pinning defaults or fixing up arguments models commonly get
+/// wrong (e.g. clamping a `limit`).
+pub struct RewriteMiddleware {
+    tool: String,
+    rewrite: Box<RewriteFn>,
+}
+
+impl RewriteMiddleware {
+    /// Apply `rewrite` to every input passed to `tool`.
+    pub fn new(
+        tool: impl Into<String>,
+        rewrite: impl Fn(&mut serde_json::Value) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            tool: tool.into(),
+            rewrite: Box::new(rewrite),
+        }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for RewriteMiddleware {
+    fn name(&self) -> &str {
+        "rewrite"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        if call.tool == self.tool {
+            (self.rewrite)(&mut call.input);
+        }
+        Ok(None)
+    }
+}
+
+type GuardFn = dyn Fn(&ToolCall) -> std::result::Result<(), String> + Send + Sync;
+
+/// Rejects calls that fail a policy check.
+///
+/// The check returns `Err(reason)` to block a call; the model receives a
+/// non-recoverable error naming the reason.
+pub struct GuardMiddleware {
+    check: Box<GuardFn>,
+}
+
+impl GuardMiddleware {
+    /// Block calls for which `check` returns an error.
+    pub fn new(
+        check: impl Fn(&ToolCall) -> std::result::Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            check: Box::new(check),
+        }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for GuardMiddleware {
+    fn name(&self) -> &str {
+        "guard"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        match (self.check)(call) {
+            Ok(()) => Ok(None),
+            Err(reason) => {
+                tracing::warn!(tool = %call.tool, reason = %reason, "Tool call blocked");
+                Ok(Some(ToolResult::error(
+                    format!("Tool call blocked: {}", reason),
+                    false,
+                )))
+            }
+        }
+    }
+}
+
+/// Feeds the `path` of every tool call to an [`IndexQueue`], so a running
+/// background index reaches the code the model is reading first.
+pub struct IndexHintMiddleware {
+    queue: IndexQueue,
+}
+
+impl IndexHintMiddleware {
+    pub fn new(queue: IndexQueue) -> Self {
+        Self { queue }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for IndexHintMiddleware {
+    fn name(&self) -> &str {
+        "index_hint"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Result<Option<ToolResult>> {
+        if let Some(path) = call.input.get("path").and_then(|p| p.as_str()) {
+            self.queue.push(path);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ToolEnvironment, ToolRegistry};
+    use crate::types::{ToolResultBlock, ToolResultContent, ToolUseBlock};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Echoes its input and counts executions.
+    struct CountingTool {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for CountingTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echoes its input"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            json!({"type": "object", "properties": {}})
+        }
+
+        async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(ToolResult::json(params))
+        }
+    }
+
+    /// Records hook order into a shared log.
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl ToolMiddleware for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn before(&self, _call: &mut ToolCall) -> Result<Option<ToolResult>> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("before {}", self.name));
+            Ok(None)
+        }
+
+        async fn after(&self, _call: &ToolCall, _result: &mut ToolResult) -> Result<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("after {}", self.name));
+            Ok(())
+        }
+    }
+
+    fn registry() -> (ToolRegistry, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = ToolRegistry::new();
+        registry.register(CountingTool {
+            calls: calls.clone(),
+        });
+        (registry, calls)
+    }
+
+    async fn call(registry: &ToolRegistry, input: serde_json::Value) -> ToolResultBlock {
+        let tool_use = ToolUseBlock {
+            id: "toolu_1".to_string(),
+            name: "echo".to_string(),
+            input,
+        };
+        registry.execute_tool(&tool_use).await.unwrap()
+    }
+
+    fn text(block: &ToolResultBlock) -> String {
+        match &block.content {
+            Some(ToolResultContent::Text(text)) => text.clone(),
+            other => panic!("unexpected content: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_as_onion() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (mut registry, calls) = registry();
+        for name in ["outer", "inner"] {
+            registry.add_middleware(Recorder {
+                name,
+                log: log.clone(),
+            });
+        }
+
+        call(&registry, json!({})).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["before outer", "before inner", "after inner", "after outer"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_changes_input() {
+        let (mut registry, _) = registry();
+        registry.add_middleware(RewriteMiddleware::new("echo", |input| {
+            input["limit"] = json!(10);
+        }));
+        registry.add_middleware(RewriteMiddleware::new("other", |input| {
+            input["touched"] = json!(true);
+        }));
+
+        let result = call(&registry, json!({"limit": 5000})).await;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&text(&result)).unwrap(),
+            json!({"limit": 10})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache_skips_repeat_calls() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (mut registry, calls) = registry();
+        registry.add_middleware(Recorder {
+            name: "outer",
+            log: log.clone(),
+        });
+        registry.add_middleware(CacheMiddleware::new());
+
+        let first = call(&registry, json!({"q": 1})).await;
+        let second = call(&registry, json!({"q": 1})).await;
+        call(&registry, json!({"q": 2})).await;
+
+        assert_eq!(text(&first), text(&second));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        // Layers outside the cache still see cached answers
+        assert_eq!(log.lock().unwrap().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_cache_only_listed_tools() {
+        let (mut registry, calls) = registry();
+        registry.add_middleware(CacheMiddleware::new().only(["graph_query"]));
+
+        call(&registry, json!({})).await;
+        call(&registry, json!({})).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let (mut registry, calls) = registry();
+        registry.add_middleware(RateLimitMiddleware::new(2, Duration::from_secs(60)));
+
+        for _ in 0..3 {
+            call(&registry, json!({})).await;
+        }
+        let limited = call(&registry, json!({})).await;
+        assert!(limited.is_error);
+        assert!(text(&limited).contains("Rate limit for 'echo' reached"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_guard_blocks_call() {
+        let (mut registry, calls) = registry();
+        registry.add_middleware(GuardMiddleware::new(|call| {
+            match call.input.get("path").and_then(|p| p.as_str()) {
+                Some(path) if path.ends_with(".env") => Err("secrets are off limits".into()),
+                _ => Ok(()),
+            }
+        }));
+
+        let blocked = call(&registry, json!({"path": "app/.env"})).await;
+        assert!(blocked.is_error);
+        assert!(text(&blocked).contains("secrets are off limits"));
+
+        call(&registry, json!({"path": "src/main.rs"})).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_index_hint_queues_paths() {
+        let (mut registry, calls) = registry();
+        let queue = IndexQueue::new();
+        registry.add_middleware(IndexHintMiddleware::new(queue.clone()));
+
+        call(&registry, json!({"path": "src/main.rs"})).await;
+        call(&registry, json!({"query": "no path"})).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(queue.pop(), Some(std::path::PathBuf::from("src/main.rs")));
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! Typed tool parameters.
+//!
+//! Tools describe their input as a struct deriving [`ToolParams`] (plus
+//! serde's `Deserialize`). The derive generates the JSON Schema the LLM sees,
+//! and [`ToolParams::from_params`] turns the raw input into the struct, so the
+//! advertised schema and the parsing code can't drift apart.
+//!
+//! ```rust,ignore
+//! #[derive(Deserialize, ToolParams)]
+//! struct FileOutlineParams {
+//!     /// Path to the file
+//!     file_path: String,
+//! }
+//!
+//! fn parameters_schema(&self) -> serde_json::Value {
+//!     FileOutlineParams::parameters_schema()
+//! }
+//!
+//! async fn execute(&self, params: serde_json::Value) -> Result<ToolResult> {
+//!     let params = FileOutlineParams::from_params(params)?;
+//!     // ...
+//! }
+//! ```
+
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::error::{Result, RlmError};
+
+pub use muninn_rlm_macros::ToolParams;
+
+/// A JSON Schema fragment.
+pub type Schema = serde_json::Value;
+
+/// A tool's parameter struct. Usually derived.
+pub trait ToolParams: DeserializeOwned {
+    /// JSON Schema (`"type": "object"`) describing the parameters.
+    fn parameters_schema() -> Schema;
+
+    /// Deserialize tool input, reporting mismatches as tool errors.
+    fn from_params(params: serde_json::Value) -> Result<Self> {
+        serde_json::from_value(params)
+            .map_err(|e| RlmError::ToolExecution(format!("Invalid parameters: {}", e)))
+    }
+}
+
+/// Field types usable in a [`ToolParams`] struct.
+pub trait ToolParamType {
+    /// Whether the field must be present (false for `Option`).
+    const REQUIRED: bool = true;
+
+    /// Schema for a value of this type.
+    fn schema() -> Schema;
+}
+
+macro_rules! param_type {
+    ($json_type:literal: $($ty:ty),+) => {
+        $(
+            impl ToolParamType for $ty {
+                fn schema() -> Schema {
+                    json!({"type": $json_type})
+                }
+            }
+        )+
+    };
+}
+
+param_type!("string": String, PathBuf);
+param_type!("boolean": bool);
+param_type!("integer": u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+param_type!("number": f32, f64);
+
+impl<T: ToolParamType> ToolParamType for Option<T> {
+    const REQUIRED: bool = false;
+
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: ToolParamType> ToolParamType for Vec<T> {
+    fn schema() -> Schema {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl ToolParamType for serde_json::Value {
+    fn schema() -> Schema {
+        json!({})
+    }
+}
+
+/// One property of a generated schema. Built by the derive macro.
+#[derive(Debug, Clone)]
+pub struct ParamSpec {
+    /// Property name, after serde renames.
+    pub name: &'static str,
+    /// Description shown to the LLM.
+    pub description: Option<&'static str>,
+    /// Allowed values (`enum`), if restricted.
+    pub values: &'static [&'static str],
+    /// Schema of the field's type.
+    pub schema: Schema,
+    /// Whether the property is listed under `required`.
+    pub required: bool,
+}
+
+/// Assemble an object schema from property specs.
+pub fn object_schema(params: Vec<ParamSpec>) -> Schema {
+    let mut properties = serde_json::Map::new();
+    let mut required = Vec::new();
+    for param in params {
+        let mut schema = param.schema;
+        if let Some(object) = schema.as_object_mut() {
+            if let Some(description) = param.description {
+                object.insert("description".into(), description.into());
+            }
+            if !param.values.is_empty() {
+                object.insert("enum".into(), param.values.into());
+            }
+        }
+        if param.required {
+            required.push(param.name);
+        }
+        properties.insert(param.name.to_string(), schema);
+    }
+
+    let mut schema = json!({"type": "object", "properties": properties});
+    if !required.is_empty() {
+        schema["required"] = required.into();
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize, ToolParams)]
+    struct SearchParams {
+        /// Search pattern
+        /// (regex supported)
+        query: String,
+        #[tool(description = "Maximum results", values("10", "50"))]
+        limit: Option<usize>,
+        #[serde(default, rename = "case")]
+        case_sensitive: bool,
+        tags: Vec<String>,
+        #[serde(skip)]
+        internal: u8,
+    }
+
+    #[test]
+    fn test_derived_schema() {
+        assert_eq!(
+            SearchParams::parameters_schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Search pattern (regex supported)"},
+                    "limit": {"type": "integer", "description": "Maximum results", "enum": ["10", "50"]},
+                    "case": {"type": "boolean"},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["query", "tags"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_params() {
+        let params =
+            SearchParams::from_params(json!({"query": "fn main", "case": true, "tags": []}))
+                .unwrap();
+        assert_eq!(params.query, "fn main");
+        assert!(params.case_sensitive);
+        assert_eq!(params.limit, None);
+
+        let err = SearchParams::from_params(json!({"tags": []})).unwrap_err();
+        assert!(err.to_string().contains("missing field `query`"), "{}", err);
+    }
+}
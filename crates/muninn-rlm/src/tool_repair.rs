@@ -0,0 +1,434 @@
+//! Repair of malformed tool-call arguments.
+//!
+//! Smaller models often emit tool input that is almost JSON: single-quoted
+//! strings, unquoted keys, trailing commas, Python literals, a sentence after
+//! the closing brace, or numbers sent as strings. Backends keep arguments
+//! they can't parse as a raw JSON string; [`repair_tool_input`] then runs a
+//! lenient JSON5-style parse and coerces fields to the tool's input schema
+//! before the tool sees them.
+
+use serde_json::{Map, Number, Value};
+
+/// What [`repair_tool_input`] did to a tool input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairOutcome {
+    /// The input was already valid for the schema.
+    Unchanged,
+    /// The input was fixed up; it is now a JSON object.
+    Repaired,
+    /// The input is still unusable (left as the raw string).
+    Failed,
+}
+
+/// Repair a tool input in place.
+///
+/// A string input is treated as raw, possibly malformed argument text and
+/// parsed leniently; it must yield an object. With a `schema`, fields are
+/// then coerced to their declared types (see [`coerce_to_schema`]).
+pub fn repair_tool_input(input: &mut Value, schema: Option<&Value>) -> RepairOutcome {
+    let mut repaired = false;
+    if let Value::String(raw) = input {
+        match repair_json(raw) {
+            Some(value @ Value::Object(_)) => {
+                *input = value;
+                repaired = true;
+            }
+            _ => return RepairOutcome::Failed,
+        }
+    }
+    if let Some(schema) = schema {
+        repaired |= coerce_to_schema(input, schema) > 0;
+    }
+    if repaired {
+        RepairOutcome::Repaired
+    } else {
+        RepairOutcome::Unchanged
+    }
+}
+
+/// Parse almost-JSON text.
+///
+/// Strict JSON is tried first. Otherwise parsing starts at the first `{` or
+/// `[` (skipping prose and code fences), ignores anything after the value,
+/// and accepts single quotes, unquoted keys, trailing or missing commas,
+/// `//` comments, `True`/`False`/`None`, and unclosed brackets at the end.
+/// A JSON string holding JSON (double-encoded arguments) is unwrapped.
+pub fn repair_json(raw: &str) -> Option<Value> {
+    match serde_json::from_str::<Value>(raw) {
+        Ok(Value::String(inner)) if inner.trim_start().starts_with(['{', '[']) => {
+            repair_json(&inner)
+        }
+        Ok(value) => Some(value),
+        Err(_) => {
+            let start = raw.find(['{', '['])?;
+            let mut parser = Lenient {
+                chars: raw[start..].chars().collect(),
+                pos: 0,
+            };
+            parser.value()
+        }
+    }
+}
+
+/// Coerce `value` towards a JSON schema, returning the number of fields
+/// changed.
+///
+/// Handles the usual small-model slips: numbers and booleans sent as
+/// strings (and vice versa), a single value where an array is expected, and
+/// nested objects sent as JSON text. Values that can't be coerced are left
+/// for the tool to reject.
+pub fn coerce_to_schema(value: &mut Value, schema: &Value) -> usize {
+    let Some(ty) = schema_type(schema) else {
+        return 0;
+    };
+    let coerced = match (ty, &*value) {
+        ("integer", Value::String(s)) => {
+            let s = s.trim();
+            s.parse::<i64>()
+                .ok()
+                .or_else(|| {
+                    s.parse::<f64>()
+                        .ok()
+                        .filter(|f| f.fract() == 0.0)
+                        .map(|f| f as i64)
+                })
+                .map(Value::from)
+        }
+        ("integer", Value::Number(n)) if n.as_i64().is_none() && n.as_u64().is_none() => n
+            .as_f64()
+            .filter(|f| f.fract() == 0.0)
+            .map(|f| Value::from(f as i64)),
+        ("number", Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number),
+        ("boolean", Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(Value::Bool(true)),
+            "false" | "no" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("boolean", Value::Number(n)) => match n.as_i64() {
+            Some(0) => Some(Value::Bool(false)),
+            Some(1) => Some(Value::Bool(true)),
+            _ => None,
+        },
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+        ("array", Value::String(s)) => match repair_json(s) {
+            Some(array @ Value::Array(_)) => Some(array),
+            _ => Some(Value::Array(vec![value.clone()])),
+        },
+        ("array", Value::Array(_) | Value::Null) => None,
+        ("array", other) => Some(Value::Array(vec![other.clone()])),
+        ("object", Value::String(s)) => repair_json(s).filter(Value::is_object),
+        _ => None,
+    };
+    let mut changes = 0;
+    if let Some(coerced) = coerced {
+        *value = coerced;
+        changes += 1;
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (key, field) in map.iter_mut() {
+                    if let Some(field_schema) = properties.get(key) {
+                        changes += coerce_to_schema(field, field_schema);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items {
+                    changes += coerce_to_schema(item, item_schema);
+                }
+            }
+        }
+        _ => {}
+    }
+    changes
+}
+
+/// The schema's `type`, taking the first non-null entry of a type list.
+fn schema_type(schema: &Value) -> Option<&str> {
+    match schema.get("type")? {
+        Value::String(ty) => Some(ty),
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|ty| *ty != "null"),
+        _ => None,
+    }
+}
+
+/// Recursive-descent parser for the JSON dialect small models write.
+struct Lenient {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lenient {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_ws();
+        match self.peek()? {
+            '{' => self.object(),
+            '[' => self.array(),
+            q @ ('"' | '\'') => self.string(q).map(Value::String),
+            c if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => self.number(),
+            _ => {
+                let word = self.bare_word();
+                Some(match word.as_str() {
+                    "true" | "True" => Value::Bool(true),
+                    "false" | "False" => Value::Bool(false),
+                    "null" | "None" | "undefined" => Value::Null,
+                    "" => return None,
+                    _ => Value::String(word),
+                })
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut map = Map::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => break,
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let key = match self.peek()? {
+                q @ ('"' | '\'') => self.string(q)?,
+                _ => self.bare_word(),
+            };
+            if key.is_empty() {
+                return None;
+            }
+            self.skip_ws();
+            if self.peek() != Some(':') {
+                return None;
+            }
+            self.pos += 1;
+            let value = self.value()?;
+            map.insert(key, value);
+        }
+        Some(Value::Object(map))
+    }
+
+    fn array(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => break,
+                Some(']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => items.push(self.value()?),
+            }
+        }
+        Some(Value::Array(items))
+    }
+
+    /// A quoted string; an unterminated one runs to the end of input.
+    fn string(&mut self, quote: char) -> Option<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                c if c == quote => return Some(out),
+                '\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'u' => {
+                            let hex: String =
+                                self.chars.get(self.pos..self.pos + 4)?.iter().collect();
+                            self.pos += 4;
+                            out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                        }
+                        other => out.push(other),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        Some(out)
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        let text = text.trim_start_matches('+');
+        if let Ok(i) = text.parse::<i64>() {
+            return Some(Value::from(i));
+        }
+        text.parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+    }
+
+    /// An unquoted key or value: runs up to the next delimiter.
+    fn bare_word(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !matches!(c, ',' | ':' | '}' | ']' | '\n'))
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_repair_json_fixes() {
+        for (raw, expected) in [
+            (r#"{"path": "a.rs"}"#, json!({"path": "a.rs"})),
+            (r#"{'path': 'a.rs'}"#, json!({"path": "a.rs"})),
+            (
+                r#"{path: "a.rs", limit: 5,}"#,
+                json!({"path": "a.rs", "limit": 5}),
+            ),
+            (
+                "Sure! ```json\n{\"path\": \"a.rs\"}\n``` Let me know.",
+                json!({"path": "a.rs"}),
+            ),
+            (
+                r#"{"recursive": True, "filter": None}"#,
+                json!({"recursive": true, "filter": null}),
+            ),
+            (
+                r#"{"paths": ["a.rs", "b.rs""#,
+                json!({"paths": ["a.rs", "b.rs"]}),
+            ),
+            (r#""{\"path\": \"a.rs\"}""#, json!({"path": "a.rs"})),
+            ("{'msg': 'it\\'s here'}", json!({"msg": "it's here"})),
+        ] {
+            assert_eq!(repair_json(raw), Some(expected), "{raw}");
+        }
+        assert_eq!(repair_json("no json here"), None);
+        assert_eq!(repair_json("{: 1}"), None);
+    }
+
+    #[test]
+    fn test_coerce_to_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": {"type": "integer"},
+                "recursive": {"type": "boolean"},
+                "paths": {"type": "array", "items": {"type": "string"}},
+                "name": {"type": ["string", "null"]},
+                "options": {
+                    "type": "object",
+                    "properties": {"depth": {"type": "integer"}}
+                }
+            }
+        });
+        let mut input = json!({
+            "limit": "10",
+            "recursive": "false",
+            "paths": "src/lib.rs",
+            "name": 42,
+            "options": "{\"depth\": \"2\"}",
+            "extra": "kept"
+        });
+        assert_eq!(coerce_to_schema(&mut input, &schema), 6);
+        assert_eq!(
+            input,
+            json!({
+                "limit": 10,
+                "recursive": false,
+                "paths": ["src/lib.rs"],
+                "name": "42",
+                "options": {"depth": 2},
+                "extra": "kept"
+            })
+        );
+        assert_eq!(coerce_to_schema(&mut input, &schema), 0);
+    }
+
+    #[test]
+    fn test_repair_tool_input_outcomes() {
+        let schema = json!({"type": "object", "properties": {"limit": {"type": "integer"}}});
+
+        let mut valid = json!({"limit": 3});
+        assert_eq!(
+            repair_tool_input(&mut valid, Some(&schema)),
+            RepairOutcome::Unchanged
+        );
+
+        let mut raw = Value::String("{'limit': '3'} and then".to_string());
+        assert_eq!(
+            repair_tool_input(&mut raw, Some(&schema)),
+            RepairOutcome::Repaired
+        );
+        assert_eq!(raw, json!({"limit": 3}));
+
+        let mut hopeless = Value::String("call read_file please".to_string());
+        assert_eq!(
+            repair_tool_input(&mut hopeless, Some(&schema)),
+            RepairOutcome::Failed
+        );
+        assert!(hopeless.is_string());
+    }
+}
@@ -9,12 +9,25 @@
 //! - `ToolRegistry`: Collection of tools, implements `ToolEnvironment`
 //! - `ToolEnvironment`: Abstraction for executing tools (used by RLM engine)
 //! - `ToolResult`: Structured result from tool execution with metadata
+//! - `ToolFormat`: How a result is rendered for the model. Every tool call
+//!   through a `ToolRegistry` may pass `format` to pick one.
+//! - `PageInfo`: Pagination envelope for tools returning long lists. They
+//!   take a `cursor` parameter and report `has_more` and `next_cursor`.
+//! - `ToolMiddleware`: Hooks around every tool call, registered on `ToolRegistry`
+//!   (see [`crate::tool_middleware`])
+//! - `ToolCategory`: What kind of access a tool needs. Within
+//!   [`with_tool_grant`], a `ToolRegistry` neither offers nor runs tools
+//!   outside the granted categories.
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 
-use crate::error::Result;
+use crate::error::{Result, RlmError};
+use crate::tool_middleware::{ToolMiddleware, execute_with_middleware};
 use crate::types::{ToolDefinition, ToolResultBlock, ToolUseBlock};
 
 // ============================================================================
@@ -45,18 +58,83 @@ pub trait Tool: Send + Sync {
     /// Internal tools are used by the RLM engine for exploration but are
     /// not exposed to external agents (like Claude Code) via MCP. This
     /// prevents collisions with tools the agent already has (e.g., read_file).
+    /// A [`ToolExposure`] override takes precedence.
     ///
     /// Default: false (tools are exposed by default)
     fn is_internal(&self) -> bool {
         false
     }
 
+    /// The access this tool needs, for per-client tool grants.
+    ///
+    /// Default: [`ToolCategory::Fs`] (reads the project, changes nothing)
+    fn category(&self) -> ToolCategory {
+        ToolCategory::Fs
+    }
+
     /// Convert this tool to an Anthropic-compatible tool definition.
     fn to_definition(&self) -> ToolDefinition {
         ToolDefinition::new(self.name(), self.description(), self.parameters_schema())
     }
 }
 
+/// What kind of access a tool needs.
+///
+/// Shared proxies grant categories per client key, so that, for example,
+/// only trusted users get code execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCategory {
+    /// Exploration bookkeeping (`final_answer`, `session_history`); always
+    /// granted.
+    Core,
+    /// Read-only access to project files and docs.
+    Fs,
+    /// Queries against the code graph.
+    Graph,
+    /// Running code.
+    Repl,
+    /// Changes to stored state, such as indexing docs.
+    Write,
+}
+
+impl ToolCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Core => "core",
+            Self::Fs => "fs",
+            Self::Graph => "graph",
+            Self::Repl => "repl",
+            Self::Write => "write",
+        }
+    }
+
+    /// Whether tools in this category may run in the current task: always
+    /// outside [`with_tool_grant`], else if granted.
+    pub fn is_granted(&self) -> bool {
+        *self == Self::Core
+            || TOOL_GRANT
+                .try_with(|granted| granted.contains(self))
+                .unwrap_or(true)
+    }
+}
+
+impl std::fmt::Display for ToolCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+tokio::task_local! {
+    static TOOL_GRANT: Vec<ToolCategory>;
+}
+
+/// Run `f` with tools limited to the `granted` categories (plus
+/// [`ToolCategory::Core`]).
+pub async fn with_tool_grant<F: Future>(granted: Vec<ToolCategory>, f: F) -> F::Output {
+    TOOL_GRANT.scope(granted, f).await
+}
+
 /// Result from executing a tool.
 #[derive(Debug, Clone)]
 pub struct ToolResult {
@@ -116,11 +194,84 @@ impl ToolResult {
         self
     }
 
+    /// Mark this result as one page of a longer list. JSON objects get a
+    /// `page` field; text gets a closing line saying how to fetch the next
+    /// page, when there is one. Call after setting the metadata.
+    pub fn with_page(mut self, page: PageInfo) -> Self {
+        match &mut self.content {
+            ToolContent::Json(Value::Object(map)) => {
+                map.insert(
+                    "page".to_string(),
+                    serde_json::to_value(&page).unwrap_or_default(),
+                );
+            }
+            ToolContent::Text(text) => {
+                if let Some(cursor) = &page.next_cursor {
+                    text.push_str(&format!(
+                        "\n[Showing {}-{}. More results: call again with cursor \"{}\".]",
+                        page.offset + 1,
+                        page.offset + page.returned,
+                        cursor
+                    ));
+                }
+            }
+            _ => {}
+        }
+        if page.has_more {
+            self.metadata.tags.push("truncated".to_string());
+        }
+        self.metadata.page = Some(page);
+        self
+    }
+
     /// Check if this result is an error.
     pub fn is_error(&self) -> bool {
         matches!(self.content, ToolContent::Error { .. })
     }
 
+    /// Render for the LLM in `format`. Errors read the same in every
+    /// format.
+    pub fn render(&self, format: ToolFormat) -> String {
+        match (format, &self.content) {
+            (ToolFormat::Text, _) | (_, ToolContent::Error { .. }) => self.to_string_content(),
+            (ToolFormat::Json, ToolContent::Json(v)) => {
+                serde_json::to_string_pretty(v).unwrap_or_default()
+            }
+            (ToolFormat::Json, ToolContent::Text(s)) => {
+                serde_json::to_string_pretty(&serde_json::json!({ "text": s })).unwrap_or_default()
+            }
+            (
+                ToolFormat::Json,
+                ToolContent::FileContent {
+                    path,
+                    content,
+                    language,
+                },
+            ) => serde_json::to_string_pretty(&serde_json::json!({
+                "path": path,
+                "language": language,
+                "content": content,
+            }))
+            .unwrap_or_default(),
+            (ToolFormat::Markdown, ToolContent::Json(v)) => {
+                let mut out = String::new();
+                markdown_value(v, 0, &mut out);
+                out.trim_end().to_string()
+            }
+            (ToolFormat::Markdown, ToolContent::FileContent { path, .. }) => {
+                format!("**{}**\n\n{}", path, self.to_string_content())
+            }
+            (ToolFormat::Compact, ToolContent::Json(v)) => {
+                serde_json::to_string(v).unwrap_or_default()
+            }
+            (ToolFormat::Compact, ToolContent::FileContent { path, content, .. }) => {
+                format!("{}\n{}", path, compact_text(content))
+            }
+            (ToolFormat::Compact, ToolContent::Text(s)) => compact_text(s),
+            (ToolFormat::Markdown, ToolContent::Text(_)) => self.to_string_content(),
+        }
+    }
+
     /// Convert to a string representation for the LLM.
     pub fn to_string_content(&self) -> String {
         match &self.content {
@@ -140,13 +291,146 @@ impl ToolResult {
 
     /// Convert to a ToolResultBlock for the API.
     pub fn to_result_block(&self, tool_use_id: &str) -> ToolResultBlock {
+        self.to_result_block_as(tool_use_id, ToolFormat::Text)
+    }
+
+    /// Convert to a ToolResultBlock rendered in `format`.
+    pub fn to_result_block_as(&self, tool_use_id: &str, format: ToolFormat) -> ToolResultBlock {
         match &self.content {
             ToolContent::Error { message, .. } => ToolResultBlock::error(tool_use_id, message),
-            _ => ToolResultBlock::success(tool_use_id, self.to_string_content()),
+            _ => ToolResultBlock::success(tool_use_id, self.render(format)),
         }
     }
 }
 
+/// Output format a tool call can ask for with its `format` parameter.
+/// Smaller models parse some formats more reliably than others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolFormat {
+    /// The tool's own rendering.
+    #[default]
+    Text,
+    /// Pretty-printed JSON; text and file results are wrapped in an object.
+    Json,
+    /// Structured results as nested bullet lists, files under a heading.
+    Markdown,
+    /// Single-line JSON, text without blank lines or trailing whitespace.
+    Compact,
+}
+
+impl ToolFormat {
+    /// Name of the parameter every registry tool accepts.
+    pub const PARAM: &'static str = "format";
+
+    /// Every format, in schema order.
+    pub const ALL: [ToolFormat; 4] = [Self::Text, Self::Json, Self::Markdown, Self::Compact];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Markdown => "markdown",
+            Self::Compact => "compact",
+        }
+    }
+
+    /// Take the `format` parameter out of a tool's input. Missing means
+    /// [`ToolFormat::Text`]; an unknown name is an error naming the options.
+    pub fn take_from(input: &mut Value) -> std::result::Result<Self, String> {
+        let Some(value) = input.as_object_mut().and_then(|o| o.remove(Self::PARAM)) else {
+            return Ok(Self::Text);
+        };
+        let name = value.as_str().unwrap_or_default();
+        Self::ALL
+            .into_iter()
+            .find(|f| f.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown format {}: use one of {}",
+                    value,
+                    Self::ALL.map(|f| f.as_str()).join(", ")
+                )
+            })
+    }
+
+    /// Add the `format` parameter to a tool's input schema, unless the
+    /// tool defines its own.
+    pub fn add_to_schema(schema: &mut Value) {
+        let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) else {
+            return;
+        };
+        properties
+            .entry(Self::PARAM)
+            .or_insert_with(|| {
+                serde_json::json!({
+                    "type": "string",
+                    "enum": Self::ALL.map(|f| f.as_str()),
+                    "description": "Output format (default text). Use json or compact if you parse the output."
+                })
+            });
+    }
+}
+
+/// Append `value` to `out` as nested bullet lists.
+fn markdown_value(value: &Value, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match scalar(value) {
+                    Some(s) => out.push_str(&format!("{}- **{}**: {}\n", indent, key, s)),
+                    None => {
+                        out.push_str(&format!("{}- **{}**:\n", indent, key));
+                        markdown_value(value, depth + 1, out);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                let flat = item.as_object().and_then(|map| {
+                    map.iter()
+                        .map(|(k, v)| scalar(v).map(|s| format!("{}: {}", k, s)))
+                        .collect::<Option<Vec<_>>>()
+                });
+                match (scalar(item), flat) {
+                    (Some(s), _) => out.push_str(&format!("{}- {}\n", indent, s)),
+                    (None, Some(fields)) => {
+                        out.push_str(&format!("{}- {}\n", indent, fields.join(", ")))
+                    }
+                    (None, None) => {
+                        out.push_str(&format!("{}-\n", indent));
+                        markdown_value(item, depth + 1, out);
+                    }
+                }
+            }
+        }
+        other => {
+            out.push_str(&indent);
+            out.push_str(&scalar(other).unwrap_or_default());
+            out.push('\n');
+        }
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(_) | Value::Array(_) => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Text without trailing whitespace or blank lines.
+fn compact_text(text: &str) -> String {
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Content types that tools can return.
 #[derive(Debug, Clone)]
 pub enum ToolContent {
@@ -175,6 +459,61 @@ pub struct ToolMetadata {
     pub token_estimate: Option<usize>,
     /// Tags for categorization.
     pub tags: Vec<String>,
+    /// Where this result sits in a longer list, for paginated tools.
+    pub page: Option<PageInfo>,
+}
+
+/// Pagination envelope shared by every tool that returns a list.
+///
+/// A paginated tool takes an optional `cursor` parameter: omitted for the
+/// first page, then the `next_cursor` of the previous page. The cursor is
+/// opaque to the model; it holds the offset of the page's first item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageInfo {
+    /// Index of the page's first item in the full list.
+    pub offset: usize,
+    /// Number of items in this page.
+    pub returned: usize,
+    /// Whether more items follow.
+    pub has_more: bool,
+    /// Cursor for the next page, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl PageInfo {
+    /// Page of `returned` items from `offset`.
+    pub fn new(offset: usize, returned: usize, has_more: bool) -> Self {
+        Self {
+            offset,
+            returned,
+            has_more,
+            next_cursor: has_more.then(|| (offset + returned).to_string()),
+        }
+    }
+
+    /// Offset a `cursor` parameter points at; the first page without one.
+    pub fn offset(cursor: Option<&str>) -> Result<usize> {
+        match cursor.map(str::trim) {
+            None | Some("") => Ok(0),
+            Some(cursor) => cursor.parse().map_err(|_| {
+                RlmError::ToolExecution(format!(
+                    "Invalid cursor '{}': pass the next_cursor of the previous page",
+                    cursor
+                ))
+            }),
+        }
+    }
+
+    /// The `limit` items from `offset` on. `items` starts at the
+    /// beginning of the list; one item past the page is enough to know
+    /// whether more follow.
+    pub fn paginate<T>(items: Vec<T>, offset: usize, limit: usize) -> (Vec<T>, PageInfo) {
+        let has_more = items.len() > offset.saturating_add(limit);
+        let page: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+        let info = PageInfo::new(offset, page.len(), has_more);
+        (page, info)
+    }
 }
 
 impl ToolMetadata {
@@ -205,6 +544,60 @@ impl ToolMetadata {
     }
 }
 
+// ============================================================================
+// Tool Exposure
+// ============================================================================
+
+/// Per-tool overrides of which tools external agents see via MCP.
+///
+/// Without an override a tool is exposed unless it
+/// [is internal](Tool::is_internal); an override exposes an internal tool
+/// (e.g. `read_file` for an agent without one) or hides an external one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolExposure {
+    overrides: HashMap<String, bool>,
+}
+
+impl ToolExposure {
+    /// No overrides: every tool keeps its default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expose (`true`) or hide (`false`) the tool `name`.
+    pub fn with(mut self, name: impl Into<String>, exposed: bool) -> Self {
+        self.overrides.insert(name.into(), exposed);
+        self
+    }
+
+    /// Whether there are no overrides.
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Whether the tool `name` is exposed, given whether it is internal.
+    pub fn is_exposed(&self, name: &str, internal: bool) -> bool {
+        self.overrides.get(name).copied().unwrap_or(!internal)
+    }
+
+    /// The tools of `tools` to expose externally, with the overrides
+    /// applied over [`ToolEnvironment::available_tools_external`].
+    pub fn external_tools(&self, tools: &dyn ToolEnvironment) -> Vec<ToolDefinition> {
+        let external = tools.available_tools_external();
+        if self.is_empty() {
+            return external;
+        }
+        tools
+            .available_tools()
+            .into_iter()
+            .filter(|t| {
+                let internal = !external.iter().any(|e| e.name == t.name);
+                self.is_exposed(&t.name, internal)
+            })
+            .collect()
+    }
+}
+
 // ============================================================================
 // Tool Registry
 // ============================================================================
@@ -212,9 +605,12 @@ impl ToolMetadata {
 /// Registry of tools that implements `ToolEnvironment`.
 ///
 /// The registry holds a collection of tools and routes execution requests
-/// to the appropriate tool based on the tool name.
+/// to the appropriate tool based on the tool name. Registered middleware
+/// wraps every execution.
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
+    middleware: Vec<Arc<dyn ToolMiddleware>>,
+    exposure: ToolExposure,
 }
 
 impl ToolRegistry {
@@ -222,6 +618,8 @@ impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            middleware: Vec::new(),
+            exposure: ToolExposure::default(),
         }
     }
 
@@ -237,6 +635,30 @@ impl ToolRegistry {
         self.tools.insert(name, tool);
     }
 
+    /// Add middleware around all tool executions.
+    ///
+    /// Middleware runs in the order it was added.
+    pub fn add_middleware(&mut self, middleware: impl ToolMiddleware + 'static) {
+        self.middleware.push(Arc::new(middleware));
+    }
+
+    /// Add middleware (Arc version).
+    pub fn add_middleware_arc(&mut self, middleware: Arc<dyn ToolMiddleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Override which tools [`available_tools_external`] returns.
+    ///
+    /// [`available_tools_external`]: ToolEnvironment::available_tools_external
+    pub fn set_exposure(&mut self, exposure: ToolExposure) {
+        self.exposure = exposure;
+    }
+
+    /// Names of registered middleware, in execution order.
+    pub fn middleware_names(&self) -> Vec<&str> {
+        self.middleware.iter().map(|m| m.name()).collect()
+    }
+
     /// Get a tool by name.
     pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
         self.tools.get(name)
@@ -256,6 +678,22 @@ impl ToolRegistry {
     pub fn tool_names(&self) -> Vec<&str> {
         self.tools.keys().map(|s| s.as_str()).collect()
     }
+
+    /// The tool's definition, with the [`ToolFormat`] parameter added.
+    fn definition(tool: &dyn Tool) -> ToolDefinition {
+        let mut definition = tool.to_definition();
+        ToolFormat::add_to_schema(&mut definition.input_schema);
+        definition
+    }
+
+    /// Whether the tool has a `format` parameter of its own, which then
+    /// reaches it untouched.
+    fn defines_format(tool: &dyn Tool) -> bool {
+        tool.parameters_schema()
+            .get("properties")
+            .and_then(|p| p.get(ToolFormat::PARAM))
+            .is_some()
+    }
 }
 
 impl Default for ToolRegistry {
@@ -268,8 +706,27 @@ impl Default for ToolRegistry {
 impl ToolEnvironment for ToolRegistry {
     async fn execute_tool(&self, tool_use: &ToolUseBlock) -> Result<ToolResultBlock> {
         if let Some(tool) = self.tools.get(&tool_use.name) {
-            let result = tool.execute(tool_use.input.clone()).await?;
-            Ok(result.to_result_block(&tool_use.id))
+            let category = tool.category();
+            if !category.is_granted() {
+                return Ok(ToolResultBlock::error(
+                    &tool_use.id,
+                    format!(
+                        "Tool '{}' is not permitted: this client is not granted {} tools",
+                        tool_use.name, category
+                    ),
+                ));
+            }
+            let mut input = tool_use.input.clone();
+            let format = if Self::defines_format(tool.as_ref()) {
+                ToolFormat::Text
+            } else {
+                match ToolFormat::take_from(&mut input) {
+                    Ok(format) => format,
+                    Err(message) => return Ok(ToolResultBlock::error(&tool_use.id, message)),
+                }
+            };
+            let result = execute_with_middleware(&self.middleware, tool.as_ref(), input).await?;
+            Ok(result.to_result_block_as(&tool_use.id, format))
         } else {
             Ok(ToolResultBlock::error(
                 &tool_use.id,
@@ -279,14 +736,20 @@ impl ToolEnvironment for ToolRegistry {
     }
 
     fn available_tools(&self) -> Vec<ToolDefinition> {
-        self.tools.values().map(|t| t.to_definition()).collect()
+        self.tools
+            .values()
+            .filter(|t| t.category().is_granted())
+            .map(|t| Self::definition(t.as_ref()))
+            .collect()
     }
 
     fn available_tools_external(&self) -> Vec<ToolDefinition> {
         self.tools
             .values()
-            .filter(|t| !t.is_internal())
-            .map(|t| t.to_definition())
+            .filter(|t| {
+                self.exposure.is_exposed(t.name(), t.is_internal()) && t.category().is_granted()
+            })
+            .map(|t| Self::definition(t.as_ref()))
             .collect()
     }
 }
@@ -295,6 +758,7 @@ impl std::fmt::Debug for ToolRegistry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ToolRegistry")
             .field("tools", &self.tool_names())
+            .field("middleware", &self.middleware_names())
             .finish()
     }
 }
@@ -952,4 +1416,156 @@ mod tests {
         // By default, tools are external (not internal)
         assert!(!EchoTool.is_internal());
     }
+
+    #[test]
+    fn test_exposure_overrides() {
+        let exposure = ToolExposure::new()
+            .with("internal_tool", true)
+            .with("echo", false);
+        let names = |tools: Vec<ToolDefinition>| -> Vec<String> {
+            tools.into_iter().map(|t| t.name).collect()
+        };
+
+        let mut registry = ToolRegistry::new();
+        registry.register(EchoTool);
+        registry.register(InternalTool);
+        // Applied over an environment that only knows its defaults
+        assert_eq!(names(exposure.external_tools(&registry)), ["internal_tool"]);
+        assert_eq!(
+            names(ToolExposure::new().external_tools(&registry)),
+            ["echo"]
+        );
+
+        registry.set_exposure(exposure);
+        assert_eq!(
+            names(registry.available_tools_external()),
+            ["internal_tool"]
+        );
+        assert_eq!(registry.available_tools().len(), 2);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let result = ToolResult::json(json!({
+            "symbol": "load",
+            "callers": [{"file": "src/main.rs", "line": 3}]
+        }));
+        assert_eq!(
+            result.render(ToolFormat::Compact),
+            r#"{"callers":[{"file":"src/main.rs","line":3}],"symbol":"load"}"#
+        );
+        assert_eq!(
+            result.render(ToolFormat::Markdown),
+            "- **callers**:\n  - file: src/main.rs, line: 3\n- **symbol**: load"
+        );
+
+        let text = ToolResult::text("a  \n\n\nb");
+        assert_eq!(text.render(ToolFormat::Compact), "a\nb");
+        assert_eq!(
+            text.render(ToolFormat::Json),
+            "{\n  \"text\": \"a  \\n\\n\\nb\"\n}"
+        );
+
+        let error = ToolResult::error("nope", true);
+        assert_eq!(error.render(ToolFormat::Json), "Error: nope");
+    }
+
+    #[test]
+    fn test_paginate() {
+        let (page, info) = PageInfo::paginate((0..5).collect(), 0, 2);
+        assert_eq!(page, vec![0, 1]);
+        assert!(info.has_more);
+        assert_eq!(info.next_cursor.as_deref(), Some("2"));
+
+        let offset = PageInfo::offset(info.next_cursor.as_deref()).unwrap();
+        let (page, info) = PageInfo::paginate((0..5).collect(), offset, 3);
+        assert_eq!(page, vec![2, 3, 4]);
+        assert!(!info.has_more);
+        assert_eq!(info.next_cursor, None);
+
+        assert_eq!(PageInfo::offset(None).unwrap(), 0);
+        assert!(PageInfo::offset(Some("page2")).is_err());
+    }
+
+    #[test]
+    fn test_with_page() {
+        let text = ToolResult::text("a\nb").with_page(PageInfo::new(0, 2, true));
+        assert!(
+            text.to_string_content()
+                .ends_with("[Showing 1-2. More results: call again with cursor \"2\".]")
+        );
+        assert!(text.metadata.tags.contains(&"truncated".to_string()));
+
+        let json = ToolResult::json(json!({"rows": []})).with_page(PageInfo::new(4, 0, false));
+        match &json.content {
+            ToolContent::Json(v) => {
+                assert_eq!(
+                    v["page"],
+                    json!({"offset": 4, "returned": 0, "has_more": false})
+                )
+            }
+            other => panic!("expected json, got {:?}", other),
+        }
+        assert_eq!(json.metadata.page.unwrap().offset, 4);
+    }
+
+    #[tokio::test]
+    async fn test_registry_applies_format_param() {
+        let mut registry = ToolRegistry::new();
+        registry.register(EchoTool);
+
+        let schema = &registry.available_tools()[0].input_schema;
+        assert_eq!(schema["properties"]["format"]["enum"][1], "json");
+
+        let call = |input| ToolUseBlock {
+            id: "t".to_string(),
+            name: "echo".to_string(),
+            input,
+        };
+        let block = registry
+            .execute_tool(&call(json!({"message": "hi", "format": "json"})))
+            .await
+            .unwrap();
+        assert_eq!(
+            block.content,
+            Some(crate::types::ToolResultContent::Text(
+                "{\n  \"text\": \"Echo: hi\"\n}".to_string()
+            ))
+        );
+
+        let block = registry
+            .execute_tool(&call(json!({"message": "hi", "format": "yaml"})))
+            .await
+            .unwrap();
+        assert!(block.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_registry_enforces_tool_grant() {
+        let mut registry = ToolRegistry::new();
+        registry.register(EchoTool);
+        let call = ToolUseBlock {
+            id: "t".to_string(),
+            name: "echo".to_string(),
+            input: json!({"message": "hi"}),
+        };
+
+        with_tool_grant(vec![ToolCategory::Graph], async {
+            assert!(registry.available_tools().is_empty());
+            let block = registry.execute_tool(&call).await.unwrap();
+            assert!(block.is_error);
+            assert!(ToolCategory::Core.is_granted());
+        })
+        .await;
+
+        with_tool_grant(vec![ToolCategory::Fs], async {
+            assert_eq!(registry.available_tools().len(), 1);
+            assert!(!registry.execute_tool(&call).await.unwrap().is_error);
+        })
+        .await;
+
+        // Outside a grant every tool is available
+        assert!(ToolCategory::Repl.is_granted());
+        assert!(!registry.execute_tool(&call).await.unwrap().is_error);
+    }
 }
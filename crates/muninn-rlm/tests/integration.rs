@@ -453,6 +453,7 @@ async fn test_graph_tools_integration() {
         cyclomatic: None,
         cognitive: None,
         call_degree: None,
+        sensitivity: Vec::new(),
     };
     store.insert_node(&test_fn).unwrap();
 
@@ -0,0 +1,170 @@
+//! LLM backends from the `[default]`, `[router]` and `[rlm]` tiers.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use muninn_rlm::{
+    AnthropicBackend, AnthropicConfig, GroqBackend, GroqConfig, LLMBackend, OllamaBackend,
+    OllamaConfig, RouterStrategy,
+};
+
+use crate::config::Config;
+
+/// Create a backend from provider and model configuration.
+///
+/// Returns None if required credentials are missing.
+pub fn create_backend_from_config(
+    provider: &str,
+    model: &str,
+    config: &Config,
+    config_dir: Option<&Path>,
+) -> Result<Option<Arc<dyn LLMBackend>>> {
+    create_backend(provider, model, config, config_dir, false)
+}
+
+/// Create the backend for the resolved `[router]` tier, honoring
+/// `router.json_mode`.
+pub fn create_router_backend(
+    config: &Config,
+    config_dir: Option<&Path>,
+) -> Result<Option<Arc<dyn LLMBackend>>> {
+    let resolved = config.resolved_router();
+    create_backend(
+        &resolved.provider,
+        &resolved.model,
+        config,
+        config_dir,
+        config.router.json_mode,
+    )
+}
+
+fn create_backend(
+    provider: &str,
+    model: &str,
+    config: &Config,
+    _config_dir: Option<&Path>,
+    json_mode: bool,
+) -> Result<Option<Arc<dyn LLMBackend>>> {
+    match provider {
+        "groq" => {
+            let key = config
+                .groq
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("GROQ_API_KEY").ok());
+            match key {
+                Some(k) => {
+                    let groq_config = GroqConfig::new(k)
+                        .with_model(model)
+                        .with_capture(config.groq.capture.payload_capture())
+                        .with_json_mode(json_mode);
+                    Ok(Some(Arc::new(GroqBackend::new(groq_config)?)))
+                }
+                None => Ok(None),
+            }
+        }
+        "anthropic" => {
+            let key = config
+                .anthropic
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
+            match key {
+                Some(k) => Ok(Some(Arc::new(AnthropicBackend::new(
+                    AnthropicConfig::new(k)
+                        .with_capture(config.anthropic.capture.payload_capture()),
+                )?))),
+                None => Ok(None),
+            }
+        }
+        "ollama" => {
+            // Resolve base_url + api_key from [ollama] (with env var fallback
+            // for the key). Local Ollama works keyless; Ollama Cloud requires
+            // OLLAMA_API_KEY and is the new default base_url.
+            let base_url = config.ollama.resolved_base_url().to_string();
+            let api_key = config.ollama.resolved_api_key();
+            if config.ollama.needs_api_key() && api_key.is_none() {
+                // The validator already surfaces this, but guard the factory
+                // too so we never silently hit cloud without credentials.
+                return Ok(None);
+            }
+            let mut ollama_config = OllamaConfig::new()
+                .with_base_url(base_url)
+                .with_model(model)
+                .with_capture(config.ollama.capture.payload_capture());
+            if let Some(k) = api_key {
+                ollama_config = ollama_config.with_api_key(k);
+            }
+            if let Some(r) = config.ollama.max_retries {
+                ollama_config = ollama_config.with_max_retries(r);
+            }
+            Ok(Some(Arc::new(OllamaBackend::new(ollama_config)?)))
+        }
+        other => {
+            anyhow::bail!("Unknown provider: {}", other)
+        }
+    }
+}
+
+/// Router and RLM backends that share one Groq key, as with `--groq-key`.
+pub fn groq_backends(
+    key: &str,
+    config: &Config,
+) -> Result<(Arc<dyn LLMBackend>, Arc<dyn LLMBackend>)> {
+    let capture = config.groq.capture.payload_capture();
+    let router_groq = GroqConfig::new(key)
+        .with_model(&config.resolved_router().model)
+        .with_capture(capture)
+        .with_json_mode(config.router.json_mode);
+    let rlm_groq = GroqConfig::new(key)
+        .with_model(&config.resolved_rlm().model)
+        .with_capture(capture);
+    Ok((
+        Arc::new(GroqBackend::new(router_groq)?),
+        Arc::new(GroqBackend::new(rlm_groq)?),
+    ))
+}
+
+/// Parse a `[router] strategy` or `--router` value, falling back to `llm`.
+pub fn parse_router_strategy(s: &str) -> RouterStrategy {
+    match s.to_lowercase().as_str() {
+        "llm" => RouterStrategy::Llm,
+        "always-rlm" | "rlm" => RouterStrategy::AlwaysRlm,
+        "always-passthrough" | "passthrough" => RouterStrategy::AlwaysPassthrough,
+        _ => {
+            tracing::warn!("Unknown router strategy '{}', using llm", s);
+            RouterStrategy::Llm
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_router_strategy() {
+        assert!(matches!(parse_router_strategy("LLM"), RouterStrategy::Llm));
+        assert!(matches!(
+            parse_router_strategy("rlm"),
+            RouterStrategy::AlwaysRlm
+        ));
+        assert!(matches!(
+            parse_router_strategy("always-passthrough"),
+            RouterStrategy::AlwaysPassthrough
+        ));
+        assert!(matches!(
+            parse_router_strategy("bogus"),
+            RouterStrategy::Llm
+        ));
+    }
+
+    #[test]
+    fn test_unknown_provider_is_an_error() {
+        let Err(err) = create_backend_from_config("nope", "m", &Config::default(), None) else {
+            panic!("expected an unknown provider error");
+        };
+        assert!(err.to_string().contains("nope"));
+    }
+}
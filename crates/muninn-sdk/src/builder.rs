@@ -0,0 +1,792 @@
+//! [`MuninnBuilder`]: one construction path from a [`Config`] to a
+//! running proxy or engine.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use muninn_graph::{GraphPartition, IndexQueue, REVISIONS_DIR};
+use muninn_rlm::{
+    AnswerFormat, AnswerFormatter, ChatTranslation, ClientKeyFile, ClientKeys, CoalesceToolDeltas,
+    Compactor, ContextWindows, Downshift, EngineConfig, EngineDeps, EngineHooks, Ensemble,
+    EnsembleMember, Experiment, IndexHintMiddleware, IndexStatus, InternalAnswers, LLMBackend,
+    LazyGraphStore, ProxyConfig, ProxyServer, RawRequestLog, RecursiveEngine, Router, RouterConfig,
+    RouterStrategy, SessionHistoryTool, SharedDocStore, SharedGraphStore, SharedTokenManager,
+    ToolEnvironment, Variant, create_revision_tools,
+};
+use tracing::{info, warn};
+
+use crate::backend::{
+    create_backend_from_config, create_router_backend, groq_backends, parse_router_strategy,
+};
+use crate::config::{self, Config};
+use crate::plugins;
+use crate::setup::{
+    config_to_prompts, config_to_rlm_budget, config_to_tool_exposure, config_to_tool_limits,
+    create_tools, exploration_hint, file_token_manager, notifier_config,
+    open_configured_graph_store, open_doc_store, project_root, prompt_dump_config,
+    raw_request_log_config, trace_sinks, upstream_passthrough_config,
+};
+
+/// Builds a [`ProxyServer`] or [`RecursiveEngine`] from a muninn [`Config`],
+/// wiring backends, tools, stores, plugins, budgets and tracing the same
+/// way the `muninn` CLI does.
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use muninn_sdk::{Config, MuninnBuilder};
+///
+/// if let Some((config, muninn_dir)) = Config::find_and_load()? {
+///     let server = MuninnBuilder::new(config)
+///         .with_muninn_dir(muninn_dir)
+///         .build_proxy("127.0.0.1:8080".parse()?)?;
+///     # let _ = server;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MuninnBuilder {
+    config: Config,
+    muninn_dir: Option<PathBuf>,
+    work_dir: Option<PathBuf>,
+    groq_key: Option<String>,
+    router_strategy: Option<RouterStrategy>,
+    graph_store: Option<SharedGraphStore>,
+    open_graph: bool,
+    lazy_graph: Option<LazyGraphStore>,
+    index_status: Option<IndexStatus>,
+    index_queue: Option<IndexQueue>,
+    doc_store: Option<SharedDocStore>,
+    token_manager: Option<SharedTokenManager>,
+    session_dir: Option<PathBuf>,
+    trace_writer: Option<muninn_tracing::WriterConfig>,
+    record_upstream: bool,
+    chat_translation: ChatTranslation,
+    answer_format: AnswerFormat,
+    coalesce_tool_deltas: bool,
+    engine_hooks: Option<Arc<dyn EngineHooks>>,
+}
+
+impl MuninnBuilder {
+    /// Start from a loaded config, with `.muninn` as the data directory.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            muninn_dir: None,
+            work_dir: None,
+            groq_key: None,
+            router_strategy: None,
+            graph_store: None,
+            open_graph: true,
+            lazy_graph: None,
+            index_status: None,
+            index_queue: None,
+            doc_store: None,
+            token_manager: None,
+            session_dir: None,
+            trace_writer: None,
+            record_upstream: false,
+            chat_translation: ChatTranslation::Raw,
+            answer_format: AnswerFormat::Plain,
+            coalesce_tool_deltas: false,
+            engine_hooks: None,
+        }
+    }
+
+    /// Set the `.muninn` directory the config was loaded from. Relative
+    /// config paths, tokens, traces and plugins resolve against it.
+    pub fn with_muninn_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.muninn_dir = Some(dir.into());
+        self
+    }
+
+    /// Override the project root (default: `[project] root`).
+    pub fn with_work_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        self.work_dir = Some(dir.canonicalize().unwrap_or(dir));
+        self
+    }
+
+    /// Use one Groq key for both the router and the RLM, ignoring the
+    /// configured providers.
+    pub fn with_groq_key(mut self, key: impl Into<String>) -> Self {
+        self.groq_key = Some(key.into());
+        self
+    }
+
+    /// Override `[router] strategy`.
+    pub fn with_router_strategy(mut self, strategy: RouterStrategy) -> Self {
+        self.router_strategy = Some(strategy);
+        self
+    }
+
+    /// Use this graph store, or none at all, instead of opening
+    /// `[graph] path`.
+    pub fn with_graph_store(mut self, store: Option<SharedGraphStore>) -> Self {
+        self.graph_store = store;
+        self.open_graph = false;
+        self
+    }
+
+    /// When no graph store is open at build time, add the graph tools once
+    /// `lazy` is attached (e.g. after a background index finishes).
+    pub fn with_lazy_graph(mut self, lazy: LazyGraphStore) -> Self {
+        self.lazy_graph = Some(lazy);
+        self
+    }
+
+    /// Report a background index's progress at the proxy's `/statusline`.
+    pub fn with_index_status(mut self, status: IndexStatus) -> Self {
+        self.index_status = Some(status);
+        self
+    }
+
+    /// Hint the paths the agent and the fs tools touch to a background
+    /// index, so it reaches them first.
+    pub fn with_index_queue(mut self, queue: IndexQueue) -> Self {
+        self.index_queue = Some(queue);
+        self
+    }
+
+    /// Use this doc store instead of opening `docs.db`.
+    pub fn with_doc_store(mut self, store: SharedDocStore) -> Self {
+        self.doc_store = Some(store);
+        self
+    }
+
+    /// Use this token manager instead of the file-backed one in the
+    /// muninn directory.
+    pub fn with_token_manager(mut self, manager: SharedTokenManager) -> Self {
+        self.token_manager = Some(manager);
+        self
+    }
+
+    /// Log into a session directory (spend ledger, recordings, raw
+    /// request logs), and give the RLM the `session_history` tool over it.
+    pub fn with_session_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.session_dir = Some(dir.into());
+        self
+    }
+
+    /// Set the trace writer; `[tracing]` sinks are added to it, and a
+    /// `[tracing.filter]` replaces its span filter.
+    pub fn with_trace_writer(mut self, config: muninn_tracing::WriterConfig) -> Self {
+        self.trace_writer = Some(config);
+        self
+    }
+
+    /// Record upstream request/response pairs.
+    pub fn with_upstream_recording(mut self, enable: bool) -> Self {
+        self.record_upstream = enable;
+        self
+    }
+
+    /// Set how `/v1/chat/completions` bodies are handled.
+    pub fn with_chat_translation(mut self, translation: ChatTranslation) -> Self {
+        self.chat_translation = translation;
+        self
+    }
+
+    /// Present RLM answers to the agent in `format` (default: as written).
+    pub fn with_answer_format(mut self, format: AnswerFormat) -> Self {
+        self.answer_format = format;
+        self
+    }
+
+    /// Send each streamed tool call's input to the agent as one delta
+    /// instead of fine-grained pieces.
+    pub fn with_tool_delta_coalescing(mut self, coalesce: bool) -> Self {
+        self.coalesce_tool_deltas = coalesce;
+        self
+    }
+
+    /// Call `hooks` at each step of every RLM exploration, in the proxy
+    /// and in [`build_engine`](Self::build_engine).
+    pub fn with_engine_hooks(mut self, hooks: Arc<dyn EngineHooks>) -> Self {
+        self.engine_hooks = Some(hooks);
+        self
+    }
+
+    /// The config being built from.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The `.muninn` directory (default: `.muninn` in the current
+    /// directory).
+    pub fn muninn_dir(&self) -> &Path {
+        self.muninn_dir
+            .as_deref()
+            .unwrap_or(Path::new(config::MUNINN_DIR))
+    }
+
+    /// The project root the tools are rooted at. Without a muninn
+    /// directory, `[project] root` is relative to the current directory.
+    pub fn work_dir(&self) -> PathBuf {
+        self.work_dir
+            .clone()
+            .unwrap_or_else(|| project_root(&self.config, self.muninn_dir.as_deref()))
+    }
+
+    /// The routing strategy the proxy will use.
+    pub fn router_strategy(&self) -> RouterStrategy {
+        self.router_strategy
+            .clone()
+            .unwrap_or_else(|| parse_router_strategy(&self.config.router.strategy))
+    }
+
+    /// Build the proxy.
+    ///
+    /// Without a router backend the RLM backend routes too; without an
+    /// RLM backend the proxy only passes requests through.
+    pub fn build_proxy(self, addr: SocketAddr) -> Result<ProxyServer> {
+        let work_dir = self.work_dir();
+        let resolved_router = self.config.resolved_router();
+        let resolved_rlm = self.config.resolved_rlm();
+        let router_config = self.router_config();
+
+        let (router_backend, rlm_backend) = match &self.groq_key {
+            Some(key) => {
+                info!("Using Groq backend from CLI for both router and RLM");
+                let (router, rlm) = groq_backends(key, &self.config)?;
+                (Some(router), Some(rlm))
+            }
+            None => (
+                create_router_backend(&self.config, Some(self.muninn_dir()))?,
+                self.rlm_backend()?,
+            ),
+        };
+        info!(
+            "Router: {} via {}",
+            resolved_router.model, resolved_router.provider
+        );
+        info!("RLM: {} via {}", resolved_rlm.model, resolved_rlm.provider);
+
+        let (graph_store, doc_store) = self.stores()?;
+        let plugins = plugins::load(
+            &self.config.plugins,
+            self.muninn_dir(),
+            &work_dir,
+            graph_store.clone(),
+        )?;
+        let tools = self.tools(&work_dir, graph_store, doc_store, &plugins);
+
+        let token_manager = match self.token_manager.clone() {
+            Some(manager) => manager,
+            None => Arc::new(file_token_manager(self.muninn_dir(), &self.config)?),
+        };
+        info!(
+            "Budget config: max_depth={}, max_tool_calls={}, max_tokens={}",
+            self.config.budget.max_depth,
+            self.config.budget.max_tool_calls,
+            self.config.budget.max_tokens
+        );
+
+        let mut trace_writer = self
+            .trace_writer
+            .clone()
+            .unwrap_or_default()
+            .with_sinks(trace_sinks(&self.config, self.muninn_dir()));
+        if !self.config.tracing.filter.is_empty() {
+            trace_writer = trace_writer.with_filter(self.config.tracing.filter.clone());
+        }
+        let mut proxy_config = ProxyConfig::new(addr)
+            .with_token_manager(token_manager)
+            .with_budget(config_to_rlm_budget(&self.config.budget))
+            .with_work_dir(&work_dir)
+            .with_trace_writer(trace_writer)
+            .with_upstream_recording(self.record_upstream)
+            .with_chat_translation(self.chat_translation.clone());
+        if let Some(dir) = &self.session_dir {
+            proxy_config = proxy_config.with_session_dir(dir);
+        }
+        if let Some(status) = &self.index_status {
+            proxy_config = proxy_config.with_index_status(status.clone());
+        }
+        if let Some(queue) = &self.index_queue {
+            proxy_config = proxy_config.with_index_queue(queue.clone());
+        }
+        proxy_config.tool_limits = config_to_tool_limits(&self.config.tools);
+        proxy_config.prompts = config_to_prompts(&self.config.prompts);
+        proxy_config.exploration_hint =
+            exploration_hint(&self.config, self.muninn_dir(), &work_dir);
+        proxy_config.engine_streaming = self.config.rlm.streaming;
+        proxy_config.stop_sequences = self.config.rlm.stop_sequences.clone();
+        proxy_config.max_iteration_tokens = self.config.rlm.max_iteration_tokens;
+        proxy_config.max_continuations = self.config.rlm.max_continuations;
+        proxy_config.downshift = self.downshift()?;
+        proxy_config.ensemble = self.ensemble()?;
+        proxy_config.internal_answers = self.internal_answers()?;
+        proxy_config.compactor = self.compactor(rlm_backend.clone())?;
+        proxy_config.promote_on_correction = self.config.router.promote_on_correction;
+        proxy_config.client_keys = self.client_keys()?.map(Arc::new);
+        proxy_config.raw_request_log =
+            raw_request_log_config(&self.config, self.muninn_dir(), self.session_dir.as_deref());
+        proxy_config.notifications = notifier_config(&self.config);
+        proxy_config.passthrough = upstream_passthrough_config(&self.config, self.muninn_dir())?;
+
+        let experiment = self.experiment(
+            router_backend.clone().or_else(|| rlm_backend.clone()),
+            &tools,
+            &work_dir,
+        )?;
+        let server = match (router_backend, rlm_backend) {
+            (Some(router_be), Some(rlm_be)) => ProxyServer::with_separate_backends(
+                proxy_config,
+                router_be,
+                rlm_be,
+                tools,
+                router_config,
+            ),
+            (_, Some(rlm_be)) => {
+                info!("Router backend not available, using RLM backend for routing");
+                ProxyServer::with_router(proxy_config, rlm_be, tools, router_config)
+            }
+            _ => {
+                info!("No RLM backend configured, running in passthrough-only mode");
+                ProxyServer::passthrough_only(proxy_config)
+            }
+        };
+        let mut server = match experiment {
+            Some(experiment) => server.with_experiment(experiment),
+            None => server,
+        };
+        if self.answer_format != AnswerFormat::Plain {
+            server = server.with_response_transform(AnswerFormatter::new(self.answer_format));
+        }
+        if self.coalesce_tool_deltas {
+            server = server.with_response_transform(CoalesceToolDeltas);
+        }
+        Ok(plugins.install(server))
+    }
+
+    /// Build the router the proxy would use, on its own. Replays use it to
+    /// re-decide recorded requests without starting a proxy.
+    pub fn build_router(&self) -> Result<Router> {
+        let router_backend = match &self.groq_key {
+            Some(key) => Some(groq_backends(key, &self.config)?.0),
+            None => match create_router_backend(&self.config, Some(self.muninn_dir()))? {
+                Some(backend) => Some(backend),
+                None => self.rlm_backend()?,
+            },
+        };
+        let router = Router::with_config(self.router_config());
+        Ok(match router_backend {
+            Some(backend) => router.with_llm(backend),
+            None => router,
+        })
+    }
+
+    /// Build an in-process engine from the resolved `[rlm]` tier, with the
+    /// same tools and stores as the proxy.
+    ///
+    /// Returns `None` when no backend is available (missing credentials).
+    pub fn build_engine(self) -> Result<Option<RecursiveEngine>> {
+        let rlm_backend = match &self.groq_key {
+            Some(key) => Some(groq_backends(key, &self.config)?.1),
+            None => self.rlm_backend()?,
+        };
+        let Some(rlm_backend) = rlm_backend else {
+            return Ok(None);
+        };
+
+        let work_dir = self.work_dir();
+        let (graph_store, doc_store) = self.stores()?;
+        let plugins = plugins::load(
+            &self.config.plugins,
+            self.muninn_dir(),
+            &work_dir,
+            graph_store.clone(),
+        )?;
+        let tools = self.tools(&work_dir, graph_store.clone(), doc_store, &plugins);
+
+        let mut engine_deps = EngineDeps::new(rlm_backend, tools);
+        if let Some(store) = graph_store {
+            engine_deps = engine_deps.with_graph_store(store);
+        }
+        let engine_config = self.engine_config(&work_dir)?;
+        Ok(Some(RecursiveEngine::new(engine_deps, engine_config)))
+    }
+
+    fn engine_config(&self, work_dir: &Path) -> Result<EngineConfig> {
+        let mut engine_config = EngineConfig::default()
+            .with_budget(config_to_rlm_budget(&self.config.budget))
+            .with_work_dir(work_dir);
+        engine_config.tool_limits = config_to_tool_limits(&self.config.tools);
+        engine_config.exploration_hint =
+            exploration_hint(&self.config, self.muninn_dir(), work_dir);
+        engine_config.streaming = self.config.rlm.streaming;
+        engine_config.stop_sequences = self.config.rlm.stop_sequences.clone();
+        engine_config.max_iteration_tokens = self.config.rlm.max_iteration_tokens;
+        engine_config.max_continuations = self.config.rlm.max_continuations;
+        engine_config.self_score = self.config.rlm.self_score;
+        engine_config.prompts = config_to_prompts(&self.config.prompts);
+        engine_config.downshift = self.downshift()?;
+        engine_config.ensemble = self.ensemble()?;
+        engine_config.prompt_dump =
+            prompt_dump_config(&self.config, self.muninn_dir(), self.session_dir.as_deref())
+                .map(|config| Arc::new(RawRequestLog::new(config)));
+        engine_config.context_windows = self
+            .config
+            .context_windows
+            .iter()
+            .fold(ContextWindows::builtin(), |windows, (model, tokens)| {
+                windows.with(model, *tokens)
+            });
+        engine_config.hooks = self.engine_hooks.clone();
+        Ok(engine_config)
+    }
+
+    /// The `[rlm] fallback_model` to downshift to, if one is set.
+    fn downshift(&self) -> Result<Option<Downshift>> {
+        let rlm = &self.config.rlm;
+        let Some(model) = &rlm.fallback_model else {
+            return Ok(None);
+        };
+        let backend = self.extra_rlm_backend(
+            "[rlm] fallback_model",
+            rlm.fallback_provider.as_deref(),
+            model,
+        )?;
+        Ok(Some(
+            Downshift::new(backend, model).with_threshold(rlm.downshift_at),
+        ))
+    }
+
+    /// The `[rlm] ensemble_model` (and judge) for final answers, if set.
+    fn ensemble(&self) -> Result<Option<Ensemble>> {
+        let rlm = &self.config.rlm;
+        let Some(model) = &rlm.ensemble_model else {
+            return Ok(None);
+        };
+        let backend = self.extra_rlm_backend(
+            "[rlm] ensemble_model",
+            rlm.ensemble_provider.as_deref(),
+            model,
+        )?;
+        let mut ensemble = Ensemble::new(EnsembleMember::new(backend, model));
+        if let Some(judge) = &rlm.ensemble_judge_model {
+            let backend = self.extra_rlm_backend("[rlm] ensemble_judge_model", None, judge)?;
+            ensemble = ensemble.with_judge(EnsembleMember::new(backend, judge));
+        }
+        Ok(Some(ensemble))
+    }
+
+    /// Local answers for title and suggestion requests, if
+    /// `[router] answer_internal` is set.
+    fn internal_answers(&self) -> Result<Option<InternalAnswers>> {
+        let router = &self.config.router;
+        if !router.answer_internal {
+            return Ok(None);
+        }
+        let Some(model) = &router.internal_model else {
+            info!("Internal requests: answered from templates");
+            return Ok(Some(InternalAnswers::template()));
+        };
+        let provider = router
+            .internal_provider
+            .clone()
+            .unwrap_or_else(|| self.config.resolved_router().provider);
+        let Some(backend) =
+            create_backend_from_config(&provider, model, &self.config, Some(self.muninn_dir()))?
+        else {
+            bail!(
+                "[router] internal_model: no credentials for {} via {}",
+                model,
+                provider
+            );
+        };
+        info!("Internal requests: {} via {}", model, provider);
+        Ok(Some(InternalAnswers::with_model(backend, model)))
+    }
+
+    /// The `[compaction]` summarizer, if enabled. It runs on the RLM
+    /// backend unless it names a model of its own.
+    fn compactor(&self, rlm_backend: Option<Arc<dyn LLMBackend>>) -> Result<Option<Compactor>> {
+        let config = &self.config.compaction;
+        if !config.enabled {
+            return Ok(None);
+        }
+        let (backend, model) = match &config.model {
+            Some(model) => (
+                Some(self.extra_rlm_backend(
+                    "[compaction] model",
+                    config.provider.as_deref(),
+                    model,
+                )?),
+                model.clone(),
+            ),
+            None => (rlm_backend, self.config.resolved_rlm().model),
+        };
+        let Some(backend) = backend else {
+            warn!("[compaction] enabled but no RLM backend is available; not compacting");
+            return Ok(None);
+        };
+        Ok(Some(
+            Compactor::new(backend, model)
+                .with_threshold(config.threshold_tokens)
+                .with_keep_recent(config.keep_recent),
+        ))
+    }
+
+    /// Client keys from `muninn keys create`, if `[keys]` is enabled.
+    fn client_keys(&self) -> Result<Option<ClientKeys>> {
+        let config = &self.config.keys;
+        if !config.enabled {
+            return Ok(None);
+        }
+        let mut keys = ClientKeys::load(&ClientKeyFile::in_dir(self.muninn_dir()))?
+            .with_roles(config.roles.clone());
+        if keys.is_empty() {
+            bail!(
+                "[keys] enabled but no active keys; create one with `muninn keys create --name <name>`"
+            );
+        }
+        if let Some(var) = &config.upstream_api_key_env {
+            let Ok(key) = std::env::var(var) else {
+                bail!("[keys] upstream_api_key_env: {} is not set", var);
+            };
+            keys = keys.with_upstream_key(key);
+        }
+        for role in keys.unmapped_roles() {
+            warn!(
+                "[keys.roles] has no entry for role '{}'; its keys get no tools",
+                role
+            );
+        }
+        let spent = keys.seed_usage(&self.muninn_dir().join("sessions"));
+        info!(
+            "Client keys: {} active, {} tokens already spent today",
+            keys.len(),
+            spent
+        );
+        Ok(Some(keys))
+    }
+
+    /// Backend for an extra model, such as `[rlm] fallback_model`, named by
+    /// `field` in messages; `provider` defaults to the RLM provider.
+    fn extra_rlm_backend(
+        &self,
+        field: &str,
+        provider: Option<&str>,
+        model: &str,
+    ) -> Result<Arc<dyn LLMBackend>> {
+        let provider = provider
+            .map(String::from)
+            .unwrap_or_else(|| self.config.resolved_rlm().provider);
+        let Some(backend) =
+            create_backend_from_config(&provider, model, &self.config, Some(self.muninn_dir()))?
+        else {
+            bail!("{}: no credentials for {} via {}", field, model, provider);
+        };
+        info!("{}: {} via {}", field, model, provider);
+        Ok(backend)
+    }
+
+    /// The `[experiment]` to run, if it has variants. Variants with a
+    /// router prompt route with `router_llm`; variants with an RLM model
+    /// get an engine of their own over the same tools.
+    fn experiment(
+        &self,
+        router_llm: Option<Arc<dyn LLMBackend>>,
+        tools: &Arc<dyn ToolEnvironment>,
+        work_dir: &Path,
+    ) -> Result<Option<Experiment>> {
+        let config = &self.config.experiment;
+        if config.variants.is_empty() {
+            return Ok(None);
+        }
+        if config.name.is_empty() {
+            bail!("[experiment] needs a name");
+        }
+        let mut experiment = Experiment::new(&config.name);
+        for variant_config in &config.variants {
+            if experiment
+                .variants()
+                .iter()
+                .any(|v| v.name == variant_config.name)
+            {
+                bail!(
+                    "[experiment] has two variants named '{}'",
+                    variant_config.name
+                );
+            }
+            let mut variant = Variant::new(&variant_config.name, variant_config.weight);
+            if let Some(prompt) = &variant_config.router_prompt {
+                let mut router_config = self.router_config();
+                router_config.system_prompt = Some(prompt.clone());
+                let router = Router::with_config(router_config);
+                variant = variant.with_router(match &router_llm {
+                    Some(llm) => router.with_llm(llm.clone()),
+                    None => router,
+                });
+            }
+            if let Some(model) = &variant_config.rlm_model {
+                let provider = variant_config
+                    .rlm_provider
+                    .clone()
+                    .unwrap_or_else(|| self.config.resolved_rlm().provider);
+                let Some(backend) = create_backend_from_config(
+                    &provider,
+                    model,
+                    &self.config,
+                    Some(self.muninn_dir()),
+                )?
+                else {
+                    bail!(
+                        "experiment variant '{}': no credentials for {} via {}",
+                        variant_config.name,
+                        model,
+                        provider
+                    );
+                };
+                let engine = RecursiveEngine::new(
+                    EngineDeps::new(backend, tools.clone()),
+                    self.engine_config(work_dir)?,
+                );
+                variant = variant.with_engine(Arc::new(engine));
+            }
+            info!(
+                "Experiment {}: variant {} (weight {})",
+                config.name, variant.name, variant.weight
+            );
+            experiment = experiment.with_variant(variant);
+        }
+        Ok(Some(experiment))
+    }
+
+    fn router_config(&self) -> RouterConfig {
+        RouterConfig {
+            strategy: self.router_strategy(),
+            enabled: self.config.router.enabled,
+            router_model: Some(self.config.resolved_router().model),
+            json_mode: self.config.router.json_mode,
+            system_prompt: None,
+        }
+    }
+
+    fn rlm_backend(&self) -> Result<Option<Arc<dyn LLMBackend>>> {
+        let resolved = self.config.resolved_rlm();
+        create_backend_from_config(
+            &resolved.provider,
+            &resolved.model,
+            &self.config,
+            Some(self.muninn_dir()),
+        )
+    }
+
+    fn tools(
+        &self,
+        work_dir: &Path,
+        graph_store: Option<SharedGraphStore>,
+        doc_store: Option<SharedDocStore>,
+        plugins: &plugins::LoadedPlugins,
+    ) -> Arc<dyn ToolEnvironment> {
+        let late = graph_store.is_none();
+        let current = graph_store
+            .clone()
+            .map(|store| (store, work_dir.to_path_buf()));
+        let exposure = config_to_tool_exposure(&self.config.tools);
+        let mut registry = create_tools(work_dir, graph_store, doc_store, plugins);
+        registry.set_exposure(exposure.clone());
+        let revisions_dir = self.muninn_dir().join(REVISIONS_DIR);
+        if !GraphPartition::list(&revisions_dir).is_empty() {
+            for tool in create_revision_tools(revisions_dir, current) {
+                registry.register_arc(Arc::from(tool));
+            }
+        }
+        if let Some(dir) = &self.session_dir {
+            registry.register(SessionHistoryTool::new(dir).with_root(work_dir));
+        }
+        if let Some(queue) = &self.index_queue {
+            registry.add_middleware(IndexHintMiddleware::new(queue.clone()));
+        }
+        match &self.lazy_graph {
+            Some(lazy) if late => Arc::new(lazy.wrap(Arc::new(registry)).with_exposure(exposure)),
+            _ => Arc::new(registry),
+        }
+    }
+
+    fn stores(&self) -> Result<(Option<SharedGraphStore>, Option<SharedDocStore>)> {
+        let graph_store = if self.open_graph {
+            open_configured_graph_store(&self.config, Some(self.muninn_dir()))?
+        } else {
+            self.graph_store.clone()
+        };
+        let doc_store = match &self.doc_store {
+            Some(store) => Some(store.clone()),
+            None => open_doc_store(&self.muninn_dir().join("docs.db"))?,
+        };
+        Ok((graph_store, doc_store))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(temp: &tempfile::TempDir) -> MuninnBuilder {
+        let mut config = Config::default();
+        // Point every tier at a provider with no credentials in the test
+        // environment so no real backend is built.
+        config.default.provider = "groq".to_string();
+        config.groq.api_key = None;
+        MuninnBuilder::new(config)
+            .with_muninn_dir(temp.path())
+            .with_work_dir(temp.path())
+    }
+
+    #[test]
+    fn test_work_dir_defaults_to_project_root() {
+        let temp = tempfile::tempdir().unwrap();
+        let builder = MuninnBuilder::new(Config::default()).with_muninn_dir(temp.path());
+        assert_eq!(builder.work_dir(), temp.path().canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_build_engine_without_credentials() {
+        if std::env::var("GROQ_API_KEY").is_ok() {
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        assert!(builder(&temp).build_engine().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_build_engine_with_groq_key() {
+        let temp = tempfile::tempdir().unwrap();
+        let engine = builder(&temp)
+            .with_groq_key("gsk-test")
+            .with_graph_store(None)
+            .build_engine()
+            .unwrap();
+        assert!(engine.is_some());
+    }
+
+    #[test]
+    fn test_build_engine_with_lazy_graph() {
+        let temp = tempfile::tempdir().unwrap();
+        let lazy = LazyGraphStore::new();
+        let engine = builder(&temp)
+            .with_groq_key("gsk-test")
+            .with_lazy_graph(lazy.clone())
+            .build_engine()
+            .unwrap();
+        assert!(engine.is_some());
+        assert!(!lazy.is_attached());
+    }
+
+    #[tokio::test]
+    async fn test_build_passthrough_proxy() {
+        if std::env::var("GROQ_API_KEY").is_ok() {
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let session_dir = temp.path().join("sessions/s1");
+        let server = builder(&temp)
+            .with_session_dir(&session_dir)
+            .build_proxy("127.0.0.1:0".parse().unwrap());
+        assert!(server.is_ok());
+    }
+}
@@ -0,0 +1,2114 @@
+//! Configuration file support for Muninn.
+//!
+//! All muninn data is stored in a `.muninn/` directory:
+//! - `.muninn/config.toml` - Configuration file
+//! - `.muninn/graph.db` - Code graph database
+//! - `.muninn/logs/` - Log files (future)
+//!
+//! Config discovery searches for `.muninn/config.toml` starting from the current
+//! directory and walking up to parent directories.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// The muninn data directory name.
+pub const MUNINN_DIR: &str = ".muninn";
+/// The config file name within the muninn directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Main configuration structure.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+#[derive(Default)]
+pub struct Config {
+    /// Project settings.
+    pub project: ProjectConfig,
+    /// Graph/index settings.
+    pub graph: GraphConfig,
+    /// LLM backend settings (deprecated - use router/rlm sections).
+    #[serde(default)]
+    pub backend: BackendConfig,
+    /// Default LLM provider/model. Router and RLM inherit from this when not
+    /// explicitly overridden, so the out-of-the-box config is a single-model
+    /// setup that works on free-tier Ollama Cloud.
+    #[serde(default)]
+    pub default: DefaultLlmConfig,
+    /// Groq-specific settings.
+    pub groq: GroqProviderConfig,
+    /// Anthropic-specific settings.
+    pub anthropic: AnthropicProviderConfig,
+    /// Ollama-specific settings (covers both local and Ollama Cloud).
+    #[serde(default)]
+    pub ollama: OllamaProviderConfig,
+    /// Router settings.
+    pub router: RouterConfig,
+    /// RLM (Recursive Language Model) settings.
+    pub rlm: RlmConfig,
+    /// Budget settings for recursive exploration.
+    pub budget: BudgetConfig,
+    /// Conversation compaction for long agent sessions.
+    #[serde(default)]
+    pub compaction: CompactionConfig,
+    /// Managed client keys for a shared proxy (`muninn keys`).
+    #[serde(default)]
+    pub keys: KeysConfig,
+    /// Per-tool limits for recursive exploration and MCP exposure, keyed
+    /// by tool name (`[tools.search_files]`).
+    #[serde(default)]
+    pub tools: HashMap<String, ToolLimitsConfig>,
+    /// OAuth token storage settings.
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Debug logging settings.
+    #[serde(default)]
+    pub debug: DebugConfig,
+    /// Launch profiles for `muninn <agent>`, keyed by agent command
+    /// (`[agents.aider]`). Merged over the built-in profiles.
+    #[serde(default)]
+    pub agents: HashMap<String, AgentProfileConfig>,
+    /// GitHub integration for `muninn github` (answering PR mentions).
+    #[serde(default)]
+    pub github: GithubConfig,
+    /// Webhook notifications for budget, backend and spend events.
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// Trace export settings.
+    #[serde(default)]
+    pub tracing: TracingConfig,
+    /// Upstream API settings for passthrough (enterprise gateways).
+    #[serde(default)]
+    pub upstream: UpstreamConfig,
+    /// WASM plugin settings.
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    /// A/B experiment between router prompts or RLM models.
+    #[serde(default)]
+    pub experiment: ExperimentConfig,
+    /// Token prices per model (`[pricing."llama-3.3-70b-versatile"]`), for
+    /// cost estimates in `muninn bench backends`.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPricing>,
+    /// Context window per model in tokens (`[context_windows]`), added to
+    /// the built-in ones. A key matches models named or starting with it.
+    /// RLM turns estimated past the window have their oldest tool
+    /// interactions trimmed before they are sent.
+    #[serde(default)]
+    pub context_windows: HashMap<String, u64>,
+    /// Overrides of the exploration prompt and tool subset for a router
+    /// category (`[prompts.debugging]`).
+    #[serde(default)]
+    pub prompts: HashMap<muninn_rlm::RouteCategory, CategoryPromptConfig>,
+}
+
+/// Project configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Root directory of the project.
+    pub root: PathBuf,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("."),
+        }
+    }
+}
+
+/// Graph/index configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GraphConfig {
+    /// Path to the graph database.
+    pub path: PathBuf,
+    /// File extensions to index.
+    pub extensions: Vec<String>,
+    /// Gitignore-style patterns, relative to the project root, that
+    /// indexing leaves out on top of `.gitignore` and `.ignore` files.
+    pub exclude: Vec<String>,
+    /// `[graph.languages]`: file extension -> grammar overrides on top of
+    /// the built-in table (`mjs = "javascript"`), or `"none"` to stop
+    /// indexing an extension.
+    pub languages: BTreeMap<String, String>,
+    /// Patterns `muninn graph orphans` never reports.
+    pub orphan_allow: Vec<String>,
+    /// Print a one-line background indexing status before the agent starts.
+    pub index_status_line: bool,
+    /// Read the graph from a `muninn graph serve` service at this URL
+    /// instead of the local database.
+    pub url: Option<String>,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        Self {
+            // Relative to .muninn/ directory
+            path: PathBuf::from("graph.db"),
+            extensions: vec![
+                "rs".to_string(),
+                "py".to_string(),
+                "ts".to_string(),
+                "js".to_string(),
+                "go".to_string(),
+                "c".to_string(),
+                "cpp".to_string(),
+                "h".to_string(),
+            ],
+            exclude: Vec::new(),
+            languages: BTreeMap::new(),
+            orphan_allow: Vec::new(),
+            index_status_line: true,
+            url: None,
+        }
+    }
+}
+
+impl GraphConfig {
+    /// The built-in extension table with `[graph.languages]` applied.
+    pub fn language_map(
+        &self,
+    ) -> std::result::Result<muninn_graph::LanguageMap, muninn_graph::UnknownLanguage> {
+        muninn_graph::LanguageMap::with_overrides(
+            self.languages
+                .iter()
+                .map(|(ext, language)| (ext.as_str(), language.as_str())),
+        )
+    }
+}
+
+/// Backend configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    /// Backend type: "groq", "anthropic", or "local".
+    #[serde(rename = "type")]
+    pub backend_type: String,
+    /// Model to use (optional, backend-specific default).
+    pub model: Option<String>,
+    /// API base URL override.
+    pub base_url: Option<String>,
+    /// Path to local model file (for "local" backend).
+    pub model_path: Option<PathBuf>,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            backend_type: "groq".to_string(),
+            model: None,
+            base_url: None,
+            model_path: None,
+        }
+    }
+}
+
+/// Router configuration.
+///
+/// `provider` and `model` are optional overrides. When unset, they inherit
+/// from `[default]`. Downstream callers should consume the post-inheritance
+/// view via [`Config::resolved_router`] rather than reading these fields
+/// directly.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RouterConfig {
+    /// Routing strategy: "llm", "always-rlm", "always-passthrough".
+    pub strategy: String,
+    /// Enable/disable routing.
+    pub enabled: bool,
+    /// Provider override for LLM-based routing. If `None`, inherits from `[default]`.
+    pub provider: Option<String>,
+    /// Model override for LLM-based routing. If `None`, inherits from `[default]`.
+    pub model: Option<String>,
+    /// Ask the router model for a JSON reply instead of a tool call, for
+    /// models with flaky tool support. On Groq this also sets
+    /// `response_format: json_object`.
+    pub json_mode: bool,
+    /// Answer the agent's title and prompt-suggestion requests in the
+    /// proxy instead of passing them upstream.
+    pub answer_internal: bool,
+    /// Small model for those answers. Without one, templates answer.
+    pub internal_model: Option<String>,
+    /// Provider for `internal_model`. Defaults to the router provider.
+    pub internal_provider: Option<String>,
+    /// Once the user answers a passthrough reply with `@muninn explore`,
+    /// route the rest of the session's requests the router would pass
+    /// through to RLM instead.
+    pub promote_on_correction: bool,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            strategy: "llm".to_string(),
+            enabled: true,
+            provider: None,
+            model: None,
+            json_mode: false,
+            answer_internal: false,
+            internal_model: None,
+            internal_provider: None,
+            promote_on_correction: false,
+        }
+    }
+}
+
+/// RLM (Recursive Language Model) configuration.
+///
+/// `provider` and `model` are optional overrides. When unset, they inherit
+/// from `[default]`. Consume via [`Config::resolved_rlm`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RlmConfig {
+    /// Provider override for RLM exploration. If `None`, inherits from `[default]`.
+    pub provider: Option<String>,
+    /// Model override for recursive exploration. If `None`, inherits from `[default]`.
+    pub model: Option<String>,
+    /// Tell the RLM which files earlier sessions kept re-reading (from the
+    /// traces under `.muninn/`), so it can reach for outlines instead.
+    pub coverage_hint: bool,
+    /// Stream each exploration turn from the backend instead of waiting
+    /// for the full response.
+    pub streaming: bool,
+    /// Stop sequences added to every exploration turn.
+    pub stop_sequences: Vec<String>,
+    /// Cap on output tokens per exploration turn, so small models can't
+    /// write long monologues between tool calls. A turn that hits it is
+    /// cut off and the model is told to call a tool or answer.
+    pub max_iteration_tokens: Option<u32>,
+    /// Continuation requests made when the final answer stops at the
+    /// request's `max_tokens`; the parts are stitched into one answer.
+    /// 0 returns the cut-off answer as is.
+    pub max_continuations: u32,
+    /// Score every answer for quality (cites files? answers the question?)
+    /// with one short extra call to the RLM model. The 0–1 score lands in
+    /// the exploration metadata, traces and spend ledger.
+    pub self_score: bool,
+    /// Cheaper model to finish explorations on when the token budget runs
+    /// low. Once `downshift_at` of `[budget] max_tokens` is used, the
+    /// remaining turns go to it; when the budget runs out, it gets one last
+    /// turn to answer instead of the request failing.
+    pub fallback_model: Option<String>,
+    /// Provider for `fallback_model`. Defaults to the RLM provider.
+    pub fallback_provider: Option<String>,
+    /// Share of the token budget (0–1) used before downshifting.
+    pub downshift_at: f64,
+    /// Second model writing a competing final answer from the same
+    /// gathered context. Both answers are kept in the trace.
+    pub ensemble_model: Option<String>,
+    /// Provider for `ensemble_model`. Defaults to the RLM provider.
+    pub ensemble_provider: Option<String>,
+    /// Model (on the RLM provider) picking the better of the two answers.
+    /// Without one, the answer citing more files wins.
+    pub ensemble_judge_model: Option<String>,
+}
+
+impl Default for RlmConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            model: None,
+            coverage_hint: true,
+            streaming: false,
+            stop_sequences: Vec::new(),
+            max_iteration_tokens: None,
+            max_continuations: muninn_rlm::engine::DEFAULT_MAX_CONTINUATIONS,
+            self_score: false,
+            fallback_model: None,
+            fallback_provider: None,
+            downshift_at: muninn_rlm::engine::DEFAULT_DOWNSHIFT_AT,
+            ensemble_model: None,
+            ensemble_provider: None,
+            ensemble_judge_model: None,
+        }
+    }
+}
+
+/// Default LLM provider/model baseline.
+///
+/// Router and RLM inherit from this when they don't set their own
+/// `provider`/`model`. The out-of-the-box default is a single Ollama Cloud
+/// model that serves both surfaces — works on the free tier (concurrent
+/// model cap = 1) and maximises prompt-cache reuse across calls.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DefaultLlmConfig {
+    /// Provider name (e.g. "ollama", "groq", "anthropic").
+    pub provider: String,
+    /// Model identifier.
+    pub model: String,
+}
+
+impl Default for DefaultLlmConfig {
+    fn default() -> Self {
+        Self {
+            provider: "ollama".to_string(),
+            model: "gemma4:31b".to_string(),
+        }
+    }
+}
+
+/// Fully-resolved LLM provider/model after applying inheritance from `[default]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedLlmConfig {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Ollama provider configuration. Covers both local Ollama (no key required)
+/// and Ollama Cloud (`https://ollama.com/v1`, requires `OLLAMA_API_KEY`).
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct OllamaProviderConfig {
+    /// API key for Ollama Cloud. Falls back to the `OLLAMA_API_KEY` env var
+    /// if unset here.
+    pub api_key: Option<String>,
+    /// Base URL override. If unset, defaults to Ollama Cloud
+    /// (`https://ollama.com/v1`). Set to `http://localhost:11434/v1` for
+    /// local Ollama.
+    pub base_url: Option<String>,
+    /// Maximum retries on transient network failures (default: 3).
+    /// Each retry adds ~500ms × attempt of backoff, so on a flapping
+    /// provider this directly consumes the hook's wall-clock budget.
+    /// Set to `0` to fail fast (useful for local dev / UAT).
+    pub max_retries: Option<u32>,
+    /// Request/response capture in traces (`[ollama.capture]`).
+    pub capture: CaptureConfig,
+}
+
+/// Default Ollama Cloud base URL.
+pub const OLLAMA_CLOUD_BASE_URL: &str = "https://ollama.com/v1";
+/// Default local Ollama base URL. Exposed for docs and install tooling.
+pub const OLLAMA_LOCAL_BASE_URL: &str = "http://localhost:11434/v1";
+
+impl OllamaProviderConfig {
+    /// Resolve the effective base URL, defaulting to Ollama Cloud.
+    pub fn resolved_base_url(&self) -> &str {
+        self.base_url.as_deref().unwrap_or(OLLAMA_CLOUD_BASE_URL)
+    }
+
+    /// Resolve the effective API key, consulting `OLLAMA_API_KEY` if the
+    /// config value is unset.
+    pub fn resolved_api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .or_else(|| std::env::var("OLLAMA_API_KEY").ok())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// True when the resolved base URL points at Ollama Cloud (or any
+    /// non-localhost host), which means an API key is required.
+    pub fn needs_api_key(&self) -> bool {
+        let url = self.resolved_base_url();
+        !(url.contains("localhost") || url.contains("127.0.0.1"))
+    }
+}
+
+/// Budget configuration for recursive exploration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Maximum tokens across all recursive calls.
+    pub max_tokens: u32,
+    /// Maximum recursion depth.
+    pub max_depth: u32,
+    /// Maximum tool calls per exploration.
+    pub max_tool_calls: u32,
+    /// Maximum duration in seconds.
+    pub max_duration_secs: u64,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: 100_000,
+            max_depth: 5,
+            max_tool_calls: 50,
+            max_duration_secs: 300,
+        }
+    }
+}
+
+/// Limits on a single tool within one exploration, and whether MCP
+/// clients see it.
+///
+/// When a limit is hit the model is told so (plus `hint`) instead of the
+/// tool running.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ToolLimitsConfig {
+    /// Maximum invocations per exploration.
+    pub max_calls: Option<u32>,
+    /// Per-call timeout in seconds.
+    pub timeout_secs: Option<u64>,
+    /// Maximum cumulative output tokens (estimated) per exploration.
+    pub max_output_tokens: Option<u64>,
+    /// Suggestion shown with the limit message, e.g. "use the graph instead".
+    pub hint: Option<String>,
+    /// List the tool to MCP clients (`true`) or hide it (`false`). Unset
+    /// keeps the default: tools agents already have, like `read_file`, are
+    /// hidden.
+    pub expose: Option<bool>,
+}
+
+/// Override of one category's exploration prompt; unset fields keep the
+/// built-in value.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct CategoryPromptConfig {
+    /// Section appended to the core exploration prompt.
+    pub focus: Option<String>,
+    /// Tools offered for the category; an empty list offers all of them.
+    pub tools: Option<Vec<String>>,
+}
+
+/// OAuth token storage configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Encrypt `.muninn/oauth-tokens.json` at rest.
+    pub encrypt_tokens: bool,
+    /// Where the encryption key comes from: "keychain" (OS credential
+    /// store) or "passphrase" (read from `MUNINN_TOKEN_PASSPHRASE`).
+    pub key_source: String,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            encrypt_tokens: false,
+            key_source: "keychain".to_string(),
+        }
+    }
+}
+
+/// Conversation compaction configuration (`[compaction]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CompactionConfig {
+    /// Summarize the older turns of long conversations before they go on.
+    pub enabled: bool,
+    /// Estimated conversation tokens past which a request is compacted.
+    pub threshold_tokens: u64,
+    /// Messages at the end of the conversation kept verbatim.
+    pub keep_recent: usize,
+    /// Model writing the summary. Defaults to the RLM model.
+    pub model: Option<String>,
+    /// Provider for `model`. Defaults to the RLM provider.
+    pub provider: Option<String>,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_tokens: muninn_rlm::compaction::DEFAULT_COMPACT_THRESHOLD,
+            keep_recent: muninn_rlm::compaction::DEFAULT_KEEP_RECENT,
+            model: None,
+            provider: None,
+        }
+    }
+}
+
+/// Client key configuration (`[keys]`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// Require a key from `muninn keys create` on every `/v1` request.
+    pub enabled: bool,
+    /// Environment variable holding the API key sent upstream in place of
+    /// the client's key. Without it, passthrough uses stored credentials.
+    pub upstream_api_key_env: Option<String>,
+    /// Tool categories granted per role, or per key name
+    /// (`reader = ["fs", "graph"]`). Keys matching no entry get only the
+    /// core tools. Empty: every key gets every tool.
+    pub roles: HashMap<String, Vec<muninn_rlm::ToolCategory>>,
+}
+
+/// Debug logging configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    /// Append every raw `/v1/messages` request body to `raw_requests.jsonl`.
+    /// Bodies carry whole conversations, so this is off by default.
+    pub log_raw_requests: bool,
+    /// Directory for the log. Defaults to the session directory, or
+    /// `.muninn/debug` when running without one.
+    pub dir: Option<PathBuf>,
+    /// Rotate the log once it reaches this many megabytes.
+    pub max_file_mb: u64,
+    /// Rotated log files to keep.
+    pub max_files: usize,
+    /// Mask API keys and tokens in logged request bodies.
+    pub redact: bool,
+    /// Write the exact system prompt and messages of every RLM iteration
+    /// to `rlm_prompts.jsonl`, with the same directory, rotation and
+    /// redaction as the raw request log.
+    pub dump_prompts: bool,
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        Self {
+            log_raw_requests: false,
+            dir: None,
+            max_file_mb: 10,
+            max_files: 3,
+            redact: true,
+            dump_prompts: false,
+        }
+    }
+}
+
+/// Trace export configuration (`[tracing]`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    /// Extra trace sinks alongside the session's `traces.jsonl`, e.g.
+    /// `[[tracing.sinks]]` with `type = "otlp"` and
+    /// `endpoint = "http://localhost:4318"`. Relative paths resolve
+    /// against `.muninn/`.
+    pub sinks: Vec<muninn_tracing::SinkConfig>,
+    /// Spans to keep or drop before any trace is written
+    /// (`[tracing.filter]`), e.g. `exclude = [{ name = "rlm_iteration" }]`
+    /// or `include = [{ name = "router_decision" }, { name = "proxy_request" }]`
+    /// for low-volume traces. Selectors can also match on span data with
+    /// `attribute` and `equals`.
+    pub filter: muninn_tracing::SpanFilter,
+}
+
+/// Upstream API configuration (`[upstream]`).
+///
+/// Points passthrough traffic at an internal gateway instead of
+/// api.anthropic.com. This is the config-file counterpart of the
+/// `ANTHROPIC_BASE_URL` an agent would use; muninn can't read that
+/// variable itself because agents set it to reach the proxy.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UpstreamConfig {
+    /// Base URL replacing `https://api.anthropic.com`.
+    pub base_url: Option<String>,
+    /// PEM files with extra root certificates to trust. Relative paths
+    /// resolve against `.muninn/`.
+    pub ca_certs: Vec<PathBuf>,
+    /// Outbound proxy URL. When unset, `HTTP_PROXY`/`HTTPS_PROXY` from the
+    /// environment apply.
+    pub proxy: Option<String>,
+    /// Base URLs a request may pick with the `x-muninn-upstream-url` header.
+    pub allowed_upstreams: Vec<String>,
+    /// Connection pool and keep-alive tuning.
+    pub pool: UpstreamPoolConfig,
+    /// Retries for 429/529 and other transient upstream failures.
+    pub retry: UpstreamRetryConfig,
+}
+
+/// Upstream retry settings (`[upstream.retry]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UpstreamRetryConfig {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled (with jitter) for each one after.
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the computed backoff.
+    pub max_backoff_ms: u64,
+    /// Longest upstream `Retry-After` worth waiting for; longer ones fail
+    /// straight away.
+    pub max_retry_after_secs: u64,
+}
+
+impl Default for UpstreamRetryConfig {
+    fn default() -> Self {
+        let policy = muninn_rlm::RetryPolicy::default();
+        Self {
+            max_retries: policy.max_retries,
+            initial_backoff_ms: policy.initial_backoff.as_millis() as u64,
+            max_backoff_ms: policy.max_backoff.as_millis() as u64,
+            max_retry_after_secs: policy.max_retry_after.as_secs(),
+        }
+    }
+}
+
+impl UpstreamRetryConfig {
+    /// The passthrough retry policy these describe.
+    pub fn to_policy(&self) -> muninn_rlm::RetryPolicy {
+        muninn_rlm::RetryPolicy {
+            max_retries: self.max_retries,
+            initial_backoff: std::time::Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: std::time::Duration::from_millis(self.max_backoff_ms),
+            max_retry_after: std::time::Duration::from_secs(self.max_retry_after_secs),
+        }
+    }
+}
+
+/// Upstream connection pool settings (`[upstream.pool]`). A value of 0
+/// turns the corresponding timeout or keep-alive off.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UpstreamPoolConfig {
+    /// Idle connections kept per host.
+    pub max_idle_per_host: usize,
+    /// Seconds an idle connection stays pooled.
+    pub idle_timeout_secs: u64,
+    /// TCP keep-alive interval in seconds.
+    pub tcp_keepalive_secs: u64,
+    /// HTTP/2 PING interval in seconds.
+    pub http2_keepalive_secs: u64,
+    /// Seconds allowed to connect (TLS included).
+    pub connect_timeout_secs: u64,
+    /// Offer HTTP/2; set false for gateways that mishandle it.
+    pub http2: bool,
+}
+
+impl Default for UpstreamPoolConfig {
+    fn default() -> Self {
+        Self::from(&muninn_rlm::ConnectionPoolConfig::default())
+    }
+}
+
+impl From<&muninn_rlm::ConnectionPoolConfig> for UpstreamPoolConfig {
+    fn from(pool: &muninn_rlm::ConnectionPoolConfig) -> Self {
+        let secs = |d: Option<std::time::Duration>| d.map_or(0, |d| d.as_secs());
+        Self {
+            max_idle_per_host: pool.max_idle_per_host,
+            idle_timeout_secs: secs(pool.idle_timeout),
+            tcp_keepalive_secs: secs(pool.tcp_keepalive),
+            http2_keepalive_secs: secs(pool.http2_keep_alive_interval),
+            connect_timeout_secs: secs(pool.connect_timeout),
+            http2: pool.http2,
+        }
+    }
+}
+
+impl UpstreamPoolConfig {
+    /// The passthrough pool settings these describe.
+    pub fn to_pool_config(&self) -> muninn_rlm::ConnectionPoolConfig {
+        let secs = |s: u64| (s > 0).then(|| std::time::Duration::from_secs(s));
+        muninn_rlm::ConnectionPoolConfig {
+            max_idle_per_host: self.max_idle_per_host,
+            idle_timeout: secs(self.idle_timeout_secs),
+            tcp_keepalive: secs(self.tcp_keepalive_secs),
+            http2_keep_alive_interval: secs(self.http2_keepalive_secs),
+            connect_timeout: secs(self.connect_timeout_secs),
+            http2: self.http2,
+        }
+    }
+}
+
+impl UpstreamConfig {
+    /// Apply these settings to a passthrough config.
+    pub fn apply(
+        &self,
+        mut passthrough: muninn_rlm::PassthroughConfig,
+        muninn_dir: &Path,
+    ) -> muninn_rlm::PassthroughConfig {
+        if let Some(url) = &self.base_url {
+            passthrough.base_url = url.trim_end_matches('/').to_string();
+        }
+        for path in &self.ca_certs {
+            passthrough = passthrough.with_ca_cert(muninn_dir.join(path));
+        }
+        if let Some(proxy) = &self.proxy {
+            passthrough = passthrough.with_proxy(proxy);
+        }
+        for url in &self.allowed_upstreams {
+            passthrough = passthrough.with_allowed_upstream(url);
+        }
+        passthrough
+            .with_pool(self.pool.to_pool_config())
+            .with_retry(self.retry.to_policy())
+    }
+}
+
+/// WASM plugin settings (`[plugins]`).
+///
+/// Plugins get no host capabilities unless granted here by manifest name:
+///
+/// ```toml
+/// [plugins.grants]
+/// license-check = ["fs_read", "graph_query"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Load plugins at all.
+    pub enabled: bool,
+    /// Directory scanned for `.wasm` files. Relative paths resolve
+    /// against `.muninn/`.
+    pub dir: PathBuf,
+    /// Host capabilities (`fs_read`, `graph_query`) granted per plugin.
+    pub grants: HashMap<String, Vec<String>>,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: PathBuf::from("plugins"),
+            grants: HashMap::new(),
+        }
+    }
+}
+
+/// A/B experiment (`[experiment]`), off until it has variants:
+///
+/// ```toml
+/// [experiment]
+/// name = "router-prompt-v2"
+///
+/// [[experiment.variants]]
+/// name = "control"
+///
+/// [[experiment.variants]]
+/// name = "terse"
+/// router_prompt = "Route to rlm only when the request names project code."
+/// rlm_model = "llama-3.3-70b-versatile"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    /// Recorded in trace metadata; `muninn experiments report` groups by it.
+    pub name: String,
+    /// The variants traffic is split between.
+    pub variants: Vec<ExperimentVariantConfig>,
+}
+
+/// One variant of `[experiment]`. Unset fields use the normal config.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExperimentVariantConfig {
+    pub name: String,
+    /// Share of traffic relative to the other variants.
+    pub weight: u32,
+    /// Router system prompt replacing the built-in one.
+    pub router_prompt: Option<String>,
+    /// RLM provider; defaults to the resolved `[rlm]` provider.
+    pub rlm_provider: Option<String>,
+    /// RLM model.
+    pub rlm_model: Option<String>,
+}
+
+impl Default for ExperimentVariantConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            weight: 1,
+            router_prompt: None,
+            rlm_provider: None,
+            rlm_model: None,
+        }
+    }
+}
+
+/// Price of one model, in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ModelPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPricing {
+    /// USD cost of `input` and `output` tokens.
+    pub fn cost(&self, input: u64, output: u64) -> f64 {
+        (input as f64 * self.input_per_mtok + output as f64 * self.output_per_mtok) / 1_000_000.0
+    }
+}
+
+/// Webhook payload shape for `[notifications]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationFormat {
+    /// Slack incoming webhook (`{"text": ...}`).
+    #[default]
+    Slack,
+    /// The event as JSON alongside its summary text.
+    Json,
+}
+
+/// Webhook notification configuration (`[notifications]`).
+///
+/// Notifications are off until a webhook URL is set, either inline or
+/// through `webhook_url_env` (Slack webhook URLs are secrets).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// URL to POST events to.
+    pub webhook_url: Option<String>,
+    /// Environment variable holding the webhook URL.
+    pub webhook_url_env: Option<String>,
+    /// Payload shape.
+    pub format: NotificationFormat,
+    /// Notify when an RLM request exceeds its budget.
+    pub budget: bool,
+    /// Notify when a backend goes unhealthy or recovers.
+    pub backend: bool,
+    /// Consecutive failures that make a backend unhealthy.
+    pub unhealthy_after: u32,
+    /// Daily (UTC) token totals to notify at.
+    pub daily_token_thresholds: Vec<u64>,
+    /// Minimum seconds between two budget notifications.
+    pub cooldown_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            webhook_url_env: None,
+            format: NotificationFormat::Slack,
+            budget: true,
+            backend: true,
+            unhealthy_after: 3,
+            daily_token_thresholds: Vec::new(),
+            cooldown_secs: 300,
+        }
+    }
+}
+
+impl NotificationsConfig {
+    /// Resolve the webhook URL, consulting `webhook_url_env` if unset.
+    pub fn resolved_webhook_url(&self) -> Option<String> {
+        self.webhook_url
+            .clone()
+            .or_else(|| {
+                self.webhook_url_env
+                    .as_deref()
+                    .and_then(|var| std::env::var(var).ok())
+            })
+            .filter(|s| !s.is_empty())
+    }
+}
+
+/// Default GitHub REST API base URL.
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// GitHub integration configuration (`[github]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GithubConfig {
+    /// Repository to watch, as `owner/name`. Falls back to
+    /// `GITHUB_REPOSITORY` (set in GitHub Actions).
+    pub repo: Option<String>,
+    /// Environment variable holding the API token.
+    pub token_env: String,
+    /// Mention that triggers an answer, e.g. `@muninn what breaks if...`.
+    pub mention: String,
+    /// REST API base URL; override for GitHub Enterprise.
+    pub api_url: String,
+    /// Seconds between polls in `muninn github watch`.
+    pub poll_interval_secs: u64,
+    /// `author_association` values whose mentions are answered. Each
+    /// answer is a paid RLM run, so by default only people with write
+    /// access to the repository get one.
+    pub allowed_associations: Vec<String>,
+    /// Logins whose mentions are answered whatever their association.
+    pub allowed_users: Vec<String>,
+}
+
+impl Default for GithubConfig {
+    fn default() -> Self {
+        Self {
+            repo: None,
+            token_env: "GITHUB_TOKEN".to_string(),
+            mention: "@muninn".to_string(),
+            api_url: GITHUB_API_URL.to_string(),
+            poll_interval_secs: 60,
+            allowed_associations: ["OWNER", "MEMBER", "COLLABORATOR"]
+                .map(String::from)
+                .to_vec(),
+            allowed_users: Vec::new(),
+        }
+    }
+}
+
+impl GithubConfig {
+    /// Resolve the repository, consulting `GITHUB_REPOSITORY` if unset.
+    pub fn resolved_repo(&self) -> Option<String> {
+        self.repo
+            .clone()
+            .or_else(|| std::env::var("GITHUB_REPOSITORY").ok())
+            .filter(|s| s.contains('/'))
+    }
+
+    /// Whether a mention from `login`, with the comment's
+    /// `author_association`, should be answered.
+    pub fn allows(&self, login: &str, association: &str) -> bool {
+        self.allowed_users
+            .iter()
+            .any(|user| user.eq_ignore_ascii_case(login))
+            || self
+                .allowed_associations
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(association))
+    }
+
+    /// Resolve the API token from `token_env`.
+    pub fn resolved_token(&self) -> Option<String> {
+        std::env::var(&self.token_env)
+            .ok()
+            .filter(|s| !s.is_empty())
+    }
+}
+
+/// Model translated OpenAI requests use when they don't name a Claude model.
+pub const DEFAULT_TRANSLATED_MODEL: &str = "claude-sonnet-4-5";
+
+/// Wire format an agent speaks to the proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentApi {
+    /// Anthropic Messages (`/v1/messages`).
+    Anthropic,
+    /// OpenAI Chat Completions (`/v1/chat/completions`), translated to
+    /// Messages by the proxy.
+    Openai,
+}
+
+impl AgentApi {
+    /// Answer format for agents speaking this API: XML sections for
+    /// Claude-based agents, Markdown for the rest.
+    pub fn default_answer_format(self) -> muninn_rlm::AnswerFormat {
+        match self {
+            AgentApi::Anthropic => muninn_rlm::AnswerFormat::Xml,
+            AgentApi::Openai => muninn_rlm::AnswerFormat::Markdown,
+        }
+    }
+}
+
+/// `[agents.<name>]` overrides for an agent launch profile.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AgentProfileConfig {
+    /// Environment variables for the agent, added to (or replacing) the
+    /// built-in ones. `{proxy_url}` and `{api_key}` are substituted.
+    pub env: BTreeMap<String, String>,
+    /// Wire format the agent speaks.
+    pub api: Option<AgentApi>,
+    /// Model for translated requests that name a non-Claude model.
+    pub model: Option<String>,
+    /// How RLM answers are presented to the agent.
+    pub answer_format: Option<muninn_rlm::AnswerFormat>,
+    /// Send each streamed tool call's input as one delta, for agents that
+    /// mis-handle fine-grained tool streaming.
+    pub coalesce_tool_deltas: Option<bool>,
+}
+
+/// Resolved launch profile for an agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentProfile {
+    /// Environment variable templates.
+    pub env: BTreeMap<String, String>,
+    /// Wire format the agent speaks.
+    pub api: AgentApi,
+    /// Model for translated requests that name a non-Claude model.
+    pub model: String,
+    /// How RLM answers are presented to the agent.
+    pub answer_format: muninn_rlm::AnswerFormat,
+    /// Send each streamed tool call's input as one delta.
+    pub coalesce_tool_deltas: bool,
+}
+
+impl AgentProfile {
+    /// Built-in profile: Anthropic variables for everything except aider,
+    /// which goes through litellm and reads the OpenAI ones.
+    fn builtin(agent: &str) -> Self {
+        let (api, vars): (AgentApi, &[(&str, &str)]) = match agent {
+            "aider" => (
+                AgentApi::Openai,
+                &[
+                    ("OPENAI_API_BASE", "{proxy_url}/v1"),
+                    ("OPENAI_BASE_URL", "{proxy_url}/v1"),
+                    ("OPENAI_API_KEY", "{api_key}"),
+                ],
+            ),
+            // Claude Code uses ANTHROPIC_AUTH_TOKEN (not API_KEY) for custom endpoints
+            _ => (
+                AgentApi::Anthropic,
+                &[
+                    ("ANTHROPIC_BASE_URL", "{proxy_url}"),
+                    ("ANTHROPIC_AUTH_TOKEN", "{api_key}"),
+                ],
+            ),
+        };
+        let mut env: BTreeMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        // Prevent proxy interference
+        env.insert("NO_PROXY".to_string(), "127.0.0.1".to_string());
+        Self {
+            env,
+            api,
+            model: DEFAULT_TRANSLATED_MODEL.to_string(),
+            answer_format: api.default_answer_format(),
+            coalesce_tool_deltas: false,
+        }
+    }
+
+    /// Environment for the agent with the templates filled in.
+    pub fn render_env(&self, proxy_url: &str, api_key: &str) -> BTreeMap<String, String> {
+        self.env
+            .iter()
+            .map(|(k, v)| {
+                let value = v
+                    .replace("{proxy_url}", proxy_url)
+                    .replace("{api_key}", api_key);
+                (k.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Groq provider configuration.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct GroqProviderConfig {
+    /// Groq API key.
+    pub api_key: Option<String>,
+    /// API base URL override.
+    pub base_url: Option<String>,
+    /// Request/response capture in traces (`[groq.capture]`).
+    pub capture: CaptureConfig,
+}
+
+/// Anthropic provider configuration.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct AnthropicProviderConfig {
+    /// Anthropic API key.
+    pub api_key: Option<String>,
+    /// API base URL override.
+    pub base_url: Option<String>,
+    /// Request/response capture in traces (`[anthropic.capture]`).
+    pub capture: CaptureConfig,
+}
+
+/// What each backend call records in traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureMode {
+    /// No `backend_call` spans.
+    #[default]
+    None,
+    /// Model, timing, usage and payload sizes.
+    Metadata,
+    /// Metadata plus payloads cut to `max_bytes`.
+    Truncated,
+    /// Metadata plus complete payloads.
+    Full,
+}
+
+/// Per-backend request/response capture settings.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// Capture mode (default: none).
+    pub mode: CaptureMode,
+    /// Payload limit for `truncated` mode, in bytes (default: 4096).
+    pub max_bytes: usize,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            mode: CaptureMode::None,
+            max_bytes: 4096,
+        }
+    }
+}
+
+impl CaptureConfig {
+    /// The backend-level capture setting.
+    pub fn payload_capture(&self) -> muninn_rlm::PayloadCapture {
+        match self.mode {
+            CaptureMode::None => muninn_rlm::PayloadCapture::None,
+            CaptureMode::Metadata => muninn_rlm::PayloadCapture::Metadata,
+            CaptureMode::Truncated => muninn_rlm::PayloadCapture::Truncated(self.max_bytes),
+            CaptureMode::Full => muninn_rlm::PayloadCapture::Full,
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from a file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        let config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Find and load configuration from current or parent directories.
+    ///
+    /// Searches for `.muninn/config.toml` starting from the current directory
+    /// and walking up to parent directories.
+    pub fn find_and_load() -> Result<Option<(Self, PathBuf)>> {
+        let current = std::env::current_dir()?;
+        Self::find_and_load_from(&current)
+    }
+
+    /// Find and load configuration starting from a specific directory.
+    ///
+    /// Looks for `.muninn/config.toml` in the directory and its parents.
+    pub fn find_and_load_from(start: &Path) -> Result<Option<(Self, PathBuf)>> {
+        let mut dir = start.to_path_buf();
+
+        loop {
+            // Look for .muninn/config.toml
+            let muninn_dir = dir.join(MUNINN_DIR);
+            let config_path = muninn_dir.join(CONFIG_FILE);
+            if config_path.exists() {
+                let config = Self::from_file(&config_path)?;
+                // Return the .muninn directory, not the config file
+                return Ok(Some((config, muninn_dir)));
+            }
+
+            if !dir.pop() {
+                break;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Load configuration or use defaults.
+    pub fn load_or_default() -> Self {
+        match Self::find_and_load() {
+            Ok(Some((config, path))) => {
+                tracing::info!("Loaded config from {}", path.display());
+                config
+            }
+            Ok(None) => {
+                tracing::debug!("No .muninn/config.toml found, using defaults");
+                Self::default()
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load config: {}, using defaults", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Resolve the graph path relative to the .muninn directory.
+    pub fn resolve_graph_path(&self, muninn_dir: Option<&Path>) -> PathBuf {
+        if self.graph.path.is_absolute() {
+            self.graph.path.clone()
+        } else if let Some(dir) = muninn_dir {
+            dir.join(&self.graph.path)
+        } else {
+            // Fall back to .muninn in current directory
+            PathBuf::from(MUNINN_DIR).join(&self.graph.path)
+        }
+    }
+
+    /// Get the path to the .muninn directory for a given base path.
+    pub fn muninn_dir(base: &Path) -> PathBuf {
+        base.join(MUNINN_DIR)
+    }
+
+    /// Get the config file path for a given .muninn directory.
+    pub fn config_path(muninn_dir: &Path) -> PathBuf {
+        muninn_dir.join(CONFIG_FILE)
+    }
+
+    /// Resolve the router's effective provider+model after applying
+    /// inheritance from `[default]`. Use this everywhere downstream — never
+    /// read `Config.router.provider/model` directly.
+    pub fn resolved_router(&self) -> ResolvedLlmConfig {
+        ResolvedLlmConfig {
+            provider: self
+                .router
+                .provider
+                .clone()
+                .unwrap_or_else(|| self.default.provider.clone()),
+            model: self
+                .router
+                .model
+                .clone()
+                .unwrap_or_else(|| self.default.model.clone()),
+        }
+    }
+
+    /// Launch profile for `agent`: the built-in one with any
+    /// `[agents.<agent>]` overrides applied.
+    pub fn agent_profile(&self, agent: &str) -> AgentProfile {
+        let mut profile = AgentProfile::builtin(agent);
+        if let Some(overrides) = self.agents.get(agent) {
+            profile.env.extend(overrides.env.clone());
+            if let Some(api) = overrides.api {
+                profile.api = api;
+                profile.answer_format = api.default_answer_format();
+            }
+            if let Some(model) = &overrides.model {
+                profile.model = model.clone();
+            }
+            if let Some(format) = overrides.answer_format {
+                profile.answer_format = format;
+            }
+            if let Some(coalesce) = overrides.coalesce_tool_deltas {
+                profile.coalesce_tool_deltas = coalesce;
+            }
+        }
+        profile
+    }
+
+    /// Resolve the RLM's effective provider+model after applying inheritance
+    /// from `[default]`. Use this everywhere downstream.
+    pub fn resolved_rlm(&self) -> ResolvedLlmConfig {
+        ResolvedLlmConfig {
+            provider: self
+                .rlm
+                .provider
+                .clone()
+                .unwrap_or_else(|| self.default.provider.clone()),
+            model: self
+                .rlm
+                .model
+                .clone()
+                .unwrap_or_else(|| self.default.model.clone()),
+        }
+    }
+}
+
+/// Configuration validation error.
+#[derive(Debug, Clone)]
+pub struct ConfigValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ConfigValidationError {}
+
+impl Config {
+    /// Validate the configuration.
+    ///
+    /// Returns a list of validation errors if any are found.
+    pub fn validate(&self) -> Vec<ConfigValidationError> {
+        let mut errors = Vec::new();
+
+        let router = self.resolved_router();
+        let rlm = self.resolved_rlm();
+        let valid_providers = ["groq", "anthropic", "ollama", "local"];
+
+        // Validate router provider
+        if !valid_providers.contains(&router.provider.as_str()) {
+            errors.push(ConfigValidationError {
+                field: "router.provider".to_string(),
+                message: format!(
+                    "Invalid provider '{}'. Expected one of: {}.",
+                    router.provider,
+                    valid_providers.join(", ")
+                ),
+            });
+        }
+
+        if router.model.is_empty() {
+            errors.push(ConfigValidationError {
+                field: "router.model".to_string(),
+                message: "Router model cannot be empty (set [router] model or [default] model)."
+                    .to_string(),
+            });
+        }
+
+        // Validate RLM provider
+        if !valid_providers.contains(&rlm.provider.as_str()) {
+            errors.push(ConfigValidationError {
+                field: "rlm.provider".to_string(),
+                message: format!(
+                    "Invalid provider '{}'. Expected one of: {}.",
+                    rlm.provider,
+                    valid_providers.join(", ")
+                ),
+            });
+        }
+
+        if rlm.model.is_empty() {
+            errors.push(ConfigValidationError {
+                field: "rlm.model".to_string(),
+                message: "RLM model cannot be empty (set [rlm] model or [default] model)."
+                    .to_string(),
+            });
+        }
+
+        if self.compaction.keep_recent == 0 {
+            errors.push(ConfigValidationError {
+                field: "compaction.keep_recent".to_string(),
+                message: "keep_recent must be at least 1.".to_string(),
+            });
+        }
+
+        if !(self.rlm.downshift_at > 0.0 && self.rlm.downshift_at <= 1.0) {
+            errors.push(ConfigValidationError {
+                field: "rlm.downshift_at".to_string(),
+                message: format!(
+                    "downshift_at must be between 0 (exclusive) and 1, got {}.",
+                    self.rlm.downshift_at
+                ),
+            });
+        }
+
+        if let Err(e) = self.graph.language_map() {
+            errors.push(ConfigValidationError {
+                field: format!("graph.languages.{}", e.extension),
+                message: format!("{}.", e),
+            });
+        }
+
+        // Validate router strategy
+        let valid_strategies = [
+            "llm",
+            "always-rlm",
+            "always-passthrough",
+            "heuristic",
+            "hybrid",
+        ];
+        if !valid_strategies.contains(&self.router.strategy.as_str()) {
+            errors.push(ConfigValidationError {
+                field: "router.strategy".to_string(),
+                message: format!(
+                    "Invalid strategy '{}'. Expected one of: {}.",
+                    self.router.strategy,
+                    valid_strategies.join(", ")
+                ),
+            });
+        }
+
+        // Check for provider-specific configuration
+        if (router.provider == "groq" || rlm.provider == "groq")
+            && self.groq.api_key.is_none()
+            && std::env::var("GROQ_API_KEY").is_err()
+        {
+            errors.push(ConfigValidationError {
+                    field: "groq.api_key".to_string(),
+                    message: "Groq API key required for router/RLM. Set [groq] api_key or GROQ_API_KEY env var.".to_string(),
+                });
+        }
+
+        if (router.provider == "anthropic" || rlm.provider == "anthropic")
+            && self.anthropic.api_key.is_none()
+            && std::env::var("ANTHROPIC_API_KEY").is_err()
+        {
+            errors.push(ConfigValidationError {
+                    field: "anthropic.api_key".to_string(),
+                    message: "Anthropic API key required for router/RLM. Set [anthropic] api_key or ANTHROPIC_API_KEY env var.".to_string(),
+                });
+        }
+
+        if (router.provider == "ollama" || rlm.provider == "ollama")
+            && self.ollama.needs_api_key()
+            && self.ollama.resolved_api_key().is_none()
+        {
+            errors.push(ConfigValidationError {
+                field: "ollama.api_key".to_string(),
+                message: format!(
+                    "Ollama Cloud API key required (resolved base_url = {}). \
+                     Set [ollama] api_key, OLLAMA_API_KEY env var, or override [ollama] base_url \
+                     to a local Ollama endpoint (e.g. http://localhost:11434/v1).",
+                    self.ollama.resolved_base_url()
+                ),
+            });
+        }
+
+        errors
+    }
+
+    /// Check if the deprecated [backend] section is being used.
+    pub fn has_deprecated_backend_config(&self) -> bool {
+        // Check if backend has non-default values
+        let default = BackendConfig::default();
+        self.backend.backend_type != default.backend_type
+            || self.backend.model != default.model
+            || self.backend.base_url != default.base_url
+            || self.backend.model_path != default.model_path
+    }
+
+    /// Emit a deprecation warning if using old [backend] section.
+    pub fn warn_deprecated_backend(&self) {
+        if self.has_deprecated_backend_config() {
+            tracing::warn!(
+                "The [backend] config section is deprecated. \
+                 Use [router] and [rlm] sections instead:\n  \
+                 [router]\n  \
+                 provider = \"groq\"\n  \
+                 model = \"llama-3.1-8b-instant\"\n\n  \
+                 [rlm]\n  \
+                 provider = \"groq\"\n  \
+                 model = \"qwen/qwen3-32b\""
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert_eq!(config.backend.backend_type, "groq");
+        assert_eq!(config.router.strategy, "llm");
+        // Router and RLM start with no explicit provider/model — they inherit
+        // from [default], which lands them on Ollama Cloud + gemma4:31b.
+        assert_eq!(config.router.provider, None);
+        assert_eq!(config.router.model, None);
+        assert_eq!(config.rlm.provider, None);
+        assert_eq!(config.rlm.model, None);
+        assert!(config.rlm.coverage_hint);
+        assert_eq!(config.default.provider, "ollama");
+        assert_eq!(config.default.model, "gemma4:31b");
+        assert_eq!(config.resolved_router().provider, "ollama");
+        assert_eq!(config.resolved_router().model, "gemma4:31b");
+        assert_eq!(config.resolved_rlm().provider, "ollama");
+        assert_eq!(config.resolved_rlm().model, "gemma4:31b");
+        assert_eq!(config.budget.max_depth, 5);
+    }
+
+    #[test]
+    fn test_parse_minimal_config() {
+        let toml = r#"
+[backend]
+type = "anthropic"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.backend.backend_type, "anthropic");
+        // Defaults should still apply
+        assert_eq!(config.router.strategy, "llm");
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let toml = r#"
+[project]
+root = "/home/user/myproject"
+
+[graph]
+path = "code.db"
+extensions = ["rs", "py"]
+exclude = ["vendor/", "*.pb.rs"]
+orphan_allow = ["*::ffi::*"]
+index_status_line = false
+url = "http://indexer:7700"
+
+[graph.languages]
+mjs = "javascript"
+java = "none"
+
+[default]
+provider = "groq"
+model = "llama-3.1-8b-instant"
+
+[router]
+strategy = "llm"
+enabled = true
+json_mode = true
+answer_internal = true
+internal_model = "qwen2.5:0.5b"
+
+[rlm]
+model = "qwen/qwen3-32b"
+coverage_hint = false
+streaming = true
+stop_sequences = ["\nObservation:"]
+max_iteration_tokens = 1024
+max_continuations = 1
+self_score = true
+fallback_model = "llama-3.1-8b-instant"
+downshift_at = 0.7
+ensemble_model = "openai/gpt-oss-120b"
+
+[budget]
+max_tokens = 50000
+max_depth = 3
+max_tool_calls = 20
+max_duration_secs = 120
+
+[compaction]
+enabled = true
+threshold_tokens = 80000
+
+[keys]
+enabled = true
+upstream_api_key_env = "TEAM_ANTHROPIC_KEY"
+
+[keys.roles]
+reader = ["fs", "graph"]
+trusted = ["fs", "graph", "repl", "write"]
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.project.root, PathBuf::from("/home/user/myproject"));
+        assert!(config.compaction.enabled);
+        assert_eq!(config.compaction.threshold_tokens, 80000);
+        assert_eq!(
+            config.compaction.keep_recent,
+            muninn_rlm::compaction::DEFAULT_KEEP_RECENT
+        );
+        assert!(config.keys.enabled);
+        assert_eq!(
+            config.keys.upstream_api_key_env.as_deref(),
+            Some("TEAM_ANTHROPIC_KEY")
+        );
+        assert_eq!(
+            config.keys.roles["reader"],
+            vec![
+                muninn_rlm::ToolCategory::Fs,
+                muninn_rlm::ToolCategory::Graph
+            ]
+        );
+        assert_eq!(config.graph.path, PathBuf::from("code.db"));
+        assert_eq!(config.graph.extensions, vec!["rs", "py"]);
+        assert_eq!(config.graph.exclude, vec!["vendor/", "*.pb.rs"]);
+        let languages = config.graph.language_map().unwrap();
+        assert_eq!(languages.language_for_extension("mjs"), Some("javascript"));
+        assert_eq!(languages.language_for_extension("java"), None);
+        assert_eq!(config.graph.orphan_allow, vec!["*::ffi::*"]);
+        assert!(!config.graph.index_status_line);
+        assert_eq!(config.graph.url.as_deref(), Some("http://indexer:7700"));
+        assert_eq!(config.router.strategy, "llm");
+        // Router inherits both fields from [default].
+        assert_eq!(config.resolved_router().provider, "groq");
+        assert_eq!(config.resolved_router().model, "llama-3.1-8b-instant");
+        // RLM overrides only the model; provider inherits.
+        assert_eq!(config.resolved_rlm().provider, "groq");
+        assert_eq!(config.resolved_rlm().model, "qwen/qwen3-32b");
+        assert!(!config.rlm.coverage_hint);
+        assert!(config.router.json_mode);
+        assert!(config.router.answer_internal);
+        assert_eq!(
+            config.router.internal_model.as_deref(),
+            Some("qwen2.5:0.5b")
+        );
+        assert!(config.rlm.streaming);
+        assert_eq!(config.rlm.stop_sequences, vec!["\nObservation:"]);
+        assert_eq!(config.rlm.max_iteration_tokens, Some(1024));
+        assert_eq!(config.rlm.max_continuations, 1);
+        assert!(config.rlm.self_score);
+        assert_eq!(
+            config.rlm.fallback_model.as_deref(),
+            Some("llama-3.1-8b-instant")
+        );
+        assert_eq!(config.rlm.downshift_at, 0.7);
+        assert_eq!(
+            config.rlm.ensemble_model.as_deref(),
+            Some("openai/gpt-oss-120b")
+        );
+        assert_eq!(config.rlm.ensemble_judge_model, None);
+        assert_eq!(config.budget.max_tokens, 50000);
+        assert_eq!(config.budget.max_depth, 3);
+    }
+
+    #[test]
+    fn test_tool_limits() {
+        let toml = r#"
+[tools.search_files]
+max_calls = 10
+hint = "use the graph instead"
+
+[tools.read_file]
+timeout_secs = 30
+max_output_tokens = 20000
+expose = true
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.tools.len(), 2);
+        assert_eq!(config.tools["search_files"].max_calls, Some(10));
+        assert_eq!(
+            config.tools["search_files"].hint.as_deref(),
+            Some("use the graph instead")
+        );
+        assert_eq!(config.tools["read_file"].timeout_secs, Some(30));
+        assert_eq!(config.tools["read_file"].max_output_tokens, Some(20000));
+        assert_eq!(config.tools["read_file"].expose, Some(true));
+        assert_eq!(config.tools["search_files"].expose, None);
+        assert!(Config::default().tools.is_empty());
+    }
+
+    #[test]
+    fn test_prompts_config() {
+        let toml = r#"
+[prompts.debugging]
+focus = "Check the logs first."
+
+[prompts.ops]
+tools = []
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let debugging = &config.prompts[&muninn_rlm::RouteCategory::Debugging];
+        assert!(debugging.focus.as_deref().unwrap().contains("logs first"));
+        assert_eq!(debugging.tools, None);
+        assert_eq!(
+            config.prompts[&muninn_rlm::RouteCategory::Ops].tools,
+            Some(Vec::new())
+        );
+        assert!(toml::from_str::<Config>("[prompts.chat]\nfocus = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_inheritance_default_only() {
+        // With no router/rlm overrides, both inherit from [default].
+        let toml = r#"
+[default]
+provider = "anthropic"
+model = "claude-haiku-4-5-20251001"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let r = config.resolved_router();
+        let m = config.resolved_rlm();
+        assert_eq!(r.provider, "anthropic");
+        assert_eq!(r.model, "claude-haiku-4-5-20251001");
+        assert_eq!(m.provider, "anthropic");
+        assert_eq!(m.model, "claude-haiku-4-5-20251001");
+    }
+
+    #[test]
+    fn test_inheritance_router_override_beats_default() {
+        let toml = r#"
+[default]
+provider = "ollama"
+model = "gemma4:31b"
+
+[router]
+provider = "groq"
+model = "llama-3.1-8b-instant"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.resolved_router().provider, "groq");
+        assert_eq!(config.resolved_router().model, "llama-3.1-8b-instant");
+        // RLM still inherits.
+        assert_eq!(config.resolved_rlm().provider, "ollama");
+        assert_eq!(config.resolved_rlm().model, "gemma4:31b");
+    }
+
+    #[test]
+    fn test_inheritance_backwards_compat_no_default_section() {
+        // Pre-tiered configs that only set [router]/[rlm] should still resolve
+        // via the built-in DefaultLlmConfig fallback.
+        let toml = r#"
+[router]
+provider = "groq"
+model = "llama-3.1-8b-instant"
+
+[rlm]
+provider = "groq"
+model = "qwen/qwen3-32b"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.resolved_router().provider, "groq");
+        assert_eq!(config.resolved_rlm().model, "qwen/qwen3-32b");
+        // The built-in DefaultLlmConfig is still present underneath.
+        assert_eq!(config.default.provider, "ollama");
+    }
+
+    #[test]
+    fn test_validate_requires_ollama_api_key_for_cloud() {
+        // SAFETY: we don't actually want OLLAMA_API_KEY leaking in from the
+        // host env into this assertion. Remove it for the duration of the test.
+        let prev = std::env::var("OLLAMA_API_KEY").ok();
+        // SAFETY: tests run in the same process; remove + restore the env var.
+        unsafe {
+            std::env::remove_var("OLLAMA_API_KEY");
+        }
+        let config = Config::default();
+        let errors = config.validate();
+        assert!(
+            errors.iter().any(|e| e.field == "ollama.api_key"),
+            "expected ollama.api_key error, got {:?}",
+            errors
+        );
+        if let Some(v) = prev {
+            // SAFETY: restoring the prior env var.
+            unsafe {
+                std::env::set_var("OLLAMA_API_KEY", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_local_ollama_keyless_ok() {
+        let prev = std::env::var("OLLAMA_API_KEY").ok();
+        // SAFETY: see test above.
+        unsafe {
+            std::env::remove_var("OLLAMA_API_KEY");
+        }
+        let mut config = Config::default();
+        config.ollama.base_url = Some("http://localhost:11434/v1".to_string());
+        let errors = config.validate();
+        assert!(
+            !errors.iter().any(|e| e.field == "ollama.api_key"),
+            "local ollama should not require an api key; got {:?}",
+            errors
+        );
+        if let Some(v) = prev {
+            // SAFETY: restoring.
+            unsafe {
+                std::env::set_var("OLLAMA_API_KEY", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_graph_path() {
+        let config = Config::default();
+        // Default is relative, resolved within .muninn/
+        assert_eq!(config.graph.path, PathBuf::from("graph.db"));
+    }
+
+    #[test]
+    fn test_resolve_graph_path() {
+        let config = Config::default();
+        let muninn_dir = PathBuf::from("/project/.muninn");
+        let resolved = config.resolve_graph_path(Some(&muninn_dir));
+        assert_eq!(resolved, PathBuf::from("/project/.muninn/graph.db"));
+    }
+
+    #[test]
+    fn test_validate_invalid_provider() {
+        let mut config = Config::default();
+        config.router.provider = Some("invalid".to_string());
+
+        let errors = config.validate();
+        assert!(errors.iter().any(|e| e.field == "router.provider"));
+    }
+
+    #[test]
+    fn test_validate_empty_model() {
+        let mut config = Config::default();
+        // An empty override propagates through inheritance as the resolved
+        // RLM model, so the validator should catch it.
+        config.rlm.model = Some("".to_string());
+
+        let errors = config.validate();
+        assert!(errors.iter().any(|e| e.field == "rlm.model"));
+    }
+
+    #[test]
+    fn test_validate_unknown_graph_language() {
+        let mut config = Config::default();
+        config
+            .graph
+            .languages
+            .insert("swift".to_string(), "swift".to_string());
+
+        let errors = config.validate();
+        assert!(errors.iter().any(|e| e.field == "graph.languages.swift"));
+    }
+
+    #[test]
+    fn test_parse_auth_config() {
+        let config = Config::default();
+        assert!(!config.auth.encrypt_tokens);
+        assert_eq!(config.auth.key_source, "keychain");
+
+        let toml = r#"
+[auth]
+encrypt_tokens = true
+key_source = "passphrase"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.auth.encrypt_tokens);
+        assert_eq!(config.auth.key_source, "passphrase");
+    }
+
+    #[test]
+    fn test_parse_debug_config() {
+        let config = Config::default();
+        assert!(!config.debug.log_raw_requests);
+        assert!(!config.debug.dump_prompts);
+        assert!(config.debug.redact);
+        assert_eq!(config.debug.max_file_mb, 10);
+
+        let toml = r#"
+[debug]
+log_raw_requests = true
+dump_prompts = true
+dir = "/tmp/muninn-debug"
+max_file_mb = 1
+max_files = 5
+redact = false
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.debug.log_raw_requests);
+        assert!(config.debug.dump_prompts);
+        assert_eq!(config.debug.dir, Some(PathBuf::from("/tmp/muninn-debug")));
+        assert_eq!(config.debug.max_file_mb, 1);
+        assert_eq!(config.debug.max_files, 5);
+        assert!(!config.debug.redact);
+    }
+
+    #[test]
+    fn test_notifications_config() {
+        assert_eq!(Config::default().notifications.resolved_webhook_url(), None);
+
+        let toml = r#"
+[notifications]
+webhook_url = "https://hooks.example.com/x"
+format = "json"
+daily_token_thresholds = [1000000]
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let notifications = &config.notifications;
+        assert_eq!(
+            notifications.resolved_webhook_url().as_deref(),
+            Some("https://hooks.example.com/x")
+        );
+        assert_eq!(notifications.format, NotificationFormat::Json);
+        assert_eq!(notifications.daily_token_thresholds, vec![1_000_000]);
+        assert!(notifications.budget);
+        assert_eq!(notifications.unhealthy_after, 3);
+    }
+
+    #[test]
+    fn test_capture_config() {
+        let config = Config::default();
+        assert_eq!(
+            config.groq.capture.payload_capture(),
+            muninn_rlm::PayloadCapture::None
+        );
+
+        let toml = r#"
+[groq.capture]
+mode = "truncated"
+max_bytes = 1024
+
+[ollama.capture]
+mode = "metadata"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.groq.capture.payload_capture(),
+            muninn_rlm::PayloadCapture::Truncated(1024)
+        );
+        assert_eq!(
+            config.ollama.capture.payload_capture(),
+            muninn_rlm::PayloadCapture::Metadata
+        );
+        assert_eq!(config.anthropic.capture.mode, CaptureMode::None);
+    }
+
+    #[test]
+    fn test_tracing_sinks_config() {
+        let toml = r#"
+[[tracing.sinks]]
+type = "otlp"
+endpoint = "http://localhost:4318"
+
+[[tracing.sinks]]
+type = "sqlite"
+path = "traces.db"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.tracing.sinks.len(), 2);
+        assert!(matches!(
+            &config.tracing.sinks[0],
+            muninn_tracing::SinkConfig::Otlp { service_name, .. } if service_name == "muninn"
+        ));
+        assert!(Config::default().tracing.sinks.is_empty());
+    }
+
+    #[test]
+    fn test_tracing_filter_config() {
+        let toml = r#"
+[tracing.filter]
+exclude = [{ name = "rlm_iteration" }]
+include = [{ name = "proxy_request" }, { attribute = "route", equals = "rlm" }]
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let filter = &config.tracing.filter;
+        assert_eq!(
+            filter.exclude,
+            [muninn_tracing::SpanMatch::name("rlm_iteration")]
+        );
+        assert_eq!(
+            filter.include[1],
+            muninn_tracing::SpanMatch::attribute("route", "rlm")
+        );
+        assert!(Config::default().tracing.filter.is_empty());
+    }
+
+    #[test]
+    fn test_experiment_config() {
+        let toml = r#"
+[experiment]
+name = "rlm-model"
+
+[[experiment.variants]]
+name = "control"
+weight = 3
+
+[[experiment.variants]]
+name = "big"
+rlm_model = "llama-3.3-70b-versatile"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let variants = &config.experiment.variants;
+        assert_eq!(config.experiment.name, "rlm-model");
+        assert_eq!((variants[0].weight, variants[1].weight), (3, 1));
+        assert_eq!(
+            variants[1].rlm_model.as_deref(),
+            Some("llama-3.3-70b-versatile")
+        );
+        assert!(variants[1].router_prompt.is_none());
+        assert!(Config::default().experiment.variants.is_empty());
+    }
+
+    #[test]
+    fn test_pricing_config() {
+        let toml = r#"
+[pricing."llama-3.3-70b-versatile"]
+input_per_mtok = 0.59
+output_per_mtok = 0.79
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let price = config.pricing["llama-3.3-70b-versatile"];
+        assert_eq!(price.input_per_mtok, 0.59);
+        assert!((price.cost(1_000_000, 500_000) - 0.985).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_context_windows_config() {
+        let toml = r#"
+[context_windows]
+"qwen2.5-coder:7b" = 16384
+"gemma4" = 131072
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.context_windows["qwen2.5-coder:7b"], 16384);
+        assert_eq!(config.context_windows["gemma4"], 131_072);
+        assert!(Config::default().context_windows.is_empty());
+    }
+
+    #[test]
+    fn test_upstream_config() {
+        let toml = r#"
+[upstream]
+base_url = "https://llm-gateway.corp.example/"
+ca_certs = ["corp-root.pem", "/etc/ssl/extra.pem"]
+proxy = "http://proxy.corp.example:3128"
+allowed_upstreams = ["https://llm-gateway-eu.corp.example"]
+
+[upstream.pool]
+max_idle_per_host = 4
+idle_timeout_secs = 0
+http2 = false
+
+[upstream.retry]
+max_retries = 5
+max_retry_after_secs = 10
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let passthrough = config.upstream.apply(
+            muninn_rlm::PassthroughConfig::default(),
+            Path::new("/repo/.muninn"),
+        );
+        assert_eq!(passthrough.base_url, "https://llm-gateway.corp.example");
+        assert_eq!(
+            passthrough.ca_certs,
+            vec![
+                PathBuf::from("/repo/.muninn/corp-root.pem"),
+                PathBuf::from("/etc/ssl/extra.pem")
+            ]
+        );
+        assert_eq!(
+            passthrough.proxy.as_deref(),
+            Some("http://proxy.corp.example:3128")
+        );
+        assert_eq!(
+            passthrough.allowed_upstreams,
+            vec!["https://llm-gateway-eu.corp.example"]
+        );
+        assert_eq!(passthrough.pool.max_idle_per_host, 4);
+        assert_eq!(passthrough.pool.idle_timeout, None);
+        assert!(!passthrough.pool.http2);
+        assert_eq!(passthrough.retry.max_retries, 5);
+        assert_eq!(
+            passthrough.retry.max_retry_after,
+            std::time::Duration::from_secs(10)
+        );
+        assert_eq!(
+            passthrough.retry.initial_backoff,
+            muninn_rlm::RetryPolicy::default().initial_backoff
+        );
+        assert_eq!(
+            passthrough.pool.connect_timeout,
+            muninn_rlm::ConnectionPoolConfig::default().connect_timeout
+        );
+
+        let untouched = Config::default().upstream.apply(
+            muninn_rlm::PassthroughConfig::default(),
+            Path::new(".muninn"),
+        );
+        assert_eq!(untouched.base_url, muninn_rlm::ANTHROPIC_API_URL);
+        assert!(untouched.ca_certs.is_empty());
+        assert_eq!(untouched.pool, muninn_rlm::ConnectionPoolConfig::default());
+        assert_eq!(untouched.retry, muninn_rlm::RetryPolicy::default());
+    }
+
+    #[test]
+    fn test_agent_profiles() {
+        let config = Config::default();
+        let claude = config.agent_profile("claude");
+        assert_eq!(claude.api, AgentApi::Anthropic);
+        assert_eq!(claude.answer_format, muninn_rlm::AnswerFormat::Xml);
+        let env = claude.render_env("http://127.0.0.1:9000", "key");
+        assert_eq!(env["ANTHROPIC_BASE_URL"], "http://127.0.0.1:9000");
+        assert_eq!(env["ANTHROPIC_AUTH_TOKEN"], "key");
+
+        let aider = config.agent_profile("aider");
+        assert_eq!(aider.api, AgentApi::Openai);
+        let env = aider.render_env("http://127.0.0.1:9000", "key");
+        assert_eq!(env["OPENAI_API_BASE"], "http://127.0.0.1:9000/v1");
+        assert_eq!(env["OPENAI_API_KEY"], "key");
+        assert!(!env.contains_key("ANTHROPIC_BASE_URL"));
+
+        let toml = r#"
+[agents.aider]
+model = "claude-haiku-4-5"
+env = { AIDER_DARK_MODE = "true", OPENAI_API_KEY = "custom" }
+
+[agents.cursor]
+api = "openai"
+
+[agents.claude]
+answer_format = "plain"
+coalesce_tool_deltas = true
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let aider = config.agent_profile("aider");
+        assert_eq!(aider.model, "claude-haiku-4-5");
+        let env = aider.render_env("http://p", "key");
+        assert_eq!(env["AIDER_DARK_MODE"], "true");
+        assert_eq!(env["OPENAI_API_KEY"], "custom");
+        assert_eq!(env["OPENAI_API_BASE"], "http://p/v1");
+        assert_eq!(config.agent_profile("cursor").api, AgentApi::Openai);
+        assert_eq!(
+            config.agent_profile("claude").answer_format,
+            muninn_rlm::AnswerFormat::Plain
+        );
+        assert_eq!(
+            config.agent_profile("cursor").answer_format,
+            muninn_rlm::AnswerFormat::Markdown
+        );
+        assert!(config.agent_profile("claude").coalesce_tool_deltas);
+        assert!(!config.agent_profile("cursor").coalesce_tool_deltas);
+    }
+
+    #[test]
+    fn test_deprecated_backend_detection() {
+        let mut config = Config::default();
+        assert!(!config.has_deprecated_backend_config());
+
+        config.backend.model = Some("custom-model".to_string());
+        assert!(config.has_deprecated_backend_config());
+    }
+}
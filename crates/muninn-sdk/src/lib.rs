@@ -0,0 +1,26 @@
+//! muninn-sdk: embed muninn the way the CLI runs it
+//!
+//! Loads `.muninn/config.toml` and turns it into a ready proxy or engine:
+//! - [`config`]: the config file format and discovery
+//! - [`MuninnBuilder`]: backends, tools, stores, plugins, budgets and
+//!   tracing wired from a [`Config`]
+//! - Lower-level helpers for callers that assemble pieces themselves
+
+mod backend;
+mod builder;
+pub mod config;
+pub mod plugins;
+mod setup;
+
+pub use backend::{
+    create_backend_from_config, create_router_backend, groq_backends, parse_router_strategy,
+};
+pub use builder::MuninnBuilder;
+pub use config::Config;
+pub use plugins::LoadedPlugins;
+pub use setup::{
+    config_to_prompts, config_to_rlm_budget, config_to_tool_exposure, config_to_tool_limits,
+    create_tools, exploration_hint, file_token_manager, notifier_config,
+    open_configured_graph_store, open_doc_store, open_graph_store, project_root,
+    prompt_dump_config, raw_request_log_config, trace_sinks, upstream_passthrough_config,
+};
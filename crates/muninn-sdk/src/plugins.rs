@@ -0,0 +1,142 @@
+//! Loading `[plugins]` from `.muninn/plugins/`.
+//!
+//! Plugin support is behind the `plugins` feature (on by default). Without
+//! it, configured plugins are reported and ignored.
+
+use std::path::Path;
+
+use anyhow::Result;
+use muninn_rlm::{ProxyServer, SharedGraphStore, ToolRegistry};
+
+use crate::config::PluginsConfig;
+
+/// Plugins loaded for one proxy or engine.
+#[derive(Debug, Default)]
+pub struct LoadedPlugins {
+    #[cfg(feature = "plugins")]
+    plugins: Vec<muninn_rlm::WasmPlugin>,
+}
+
+/// Load the plugins in `config.dir` (relative to `muninn_dir`), with
+/// `fs_read` rooted at `work_dir`.
+///
+/// Unknown capability names are an error; plugins that fail to load are
+/// logged and skipped.
+#[cfg(feature = "plugins")]
+pub fn load(
+    config: &PluginsConfig,
+    muninn_dir: &Path,
+    work_dir: &Path,
+    graph_store: Option<SharedGraphStore>,
+) -> Result<LoadedPlugins> {
+    if !config.enabled {
+        return Ok(LoadedPlugins::default());
+    }
+    let mut host = muninn_rlm::PluginHost::new(work_dir);
+    if let Some(store) = graph_store {
+        host = host.with_graph_store(store);
+    }
+    for (plugin, names) in &config.grants {
+        host = host.with_grant(plugin, parse_capabilities(plugin, names)?);
+    }
+    Ok(LoadedPlugins {
+        plugins: host.load_dir(&muninn_dir.join(&config.dir)),
+    })
+}
+
+#[cfg(feature = "plugins")]
+fn parse_capabilities(plugin: &str, names: &[String]) -> Result<Vec<muninn_rlm::Capability>> {
+    names
+        .iter()
+        .map(|name| {
+            serde_json::from_value(serde_json::Value::String(name.clone())).map_err(|_| {
+                anyhow::anyhow!(
+                    "Unknown capability '{}' granted to plugin '{}' (expected fs_read or graph_query)",
+                    name,
+                    plugin
+                )
+            })
+        })
+        .collect()
+}
+
+/// Without the `plugins` feature: warn if any plugins would have loaded.
+#[cfg(not(feature = "plugins"))]
+pub fn load(
+    config: &PluginsConfig,
+    muninn_dir: &Path,
+    _work_dir: &Path,
+    _graph_store: Option<SharedGraphStore>,
+) -> Result<LoadedPlugins> {
+    let dir = muninn_dir.join(&config.dir);
+    let has_plugins = std::fs::read_dir(&dir).is_ok_and(|entries| {
+        entries
+            .filter_map(|e| e.ok())
+            .any(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
+    });
+    if config.enabled && has_plugins {
+        tracing::warn!(
+            "Ignoring plugins in {}: muninn was built without the `plugins` feature",
+            dir.display()
+        );
+    }
+    Ok(LoadedPlugins::default())
+}
+
+impl LoadedPlugins {
+    /// Register every plugin tool.
+    pub fn register_tools(&self, registry: &mut ToolRegistry) {
+        #[cfg(feature = "plugins")]
+        for plugin in &self.plugins {
+            for tool in plugin.tools() {
+                registry.register_arc(tool);
+            }
+        }
+        #[cfg(not(feature = "plugins"))]
+        let _ = registry;
+    }
+
+    /// Install plugin response transforms and, if any plugin provides one,
+    /// the first plugin router.
+    pub fn install(&self, server: ProxyServer) -> ProxyServer {
+        #[cfg(feature = "plugins")]
+        {
+            let mut server = server;
+            for transform in self.plugins.iter().filter_map(|p| p.transform()) {
+                server = server.with_response_transform(transform);
+            }
+            if let Some(router) = self.plugins.iter().find_map(|p| p.router()) {
+                server = server.with_router_backend(router);
+            }
+            server
+        }
+        #[cfg(not(feature = "plugins"))]
+        server
+    }
+}
+
+#[cfg(all(test, feature = "plugins"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capabilities() {
+        let names = vec!["fs_read".to_string(), "graph_query".to_string()];
+        assert_eq!(
+            parse_capabilities("demo", &names).unwrap(),
+            vec![
+                muninn_rlm::Capability::FsRead,
+                muninn_rlm::Capability::GraphQuery
+            ]
+        );
+        let err = parse_capabilities("demo", &["network".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("network"));
+    }
+
+    #[test]
+    fn test_load_missing_dir_is_empty() {
+        let temp = tempfile::tempdir().unwrap();
+        let plugins = load(&PluginsConfig::default(), temp.path(), temp.path(), None).unwrap();
+        assert!(plugins.plugins.is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+//! Translating muninn config sections into `muninn-rlm` settings, and
+//! opening the stores and tools a proxy or engine runs with.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use muninn_graph::GraphStore;
+use muninn_graph::doc_store::DocStore;
+use muninn_rlm::{
+    BudgetConfig as RlmBudgetConfig, FileTokenManager, PromptRegistry, SharedDocStore,
+    SharedGraphStore, TokenEncryption, TokenKeySource, ToolExposure, ToolLimits, ToolRegistry,
+    create_doc_tools, create_fs_tools, create_graph_tools, wrap_doc_store, wrap_store,
+};
+use tracing::{debug, info};
+
+use crate::config::{self, Config};
+use crate::plugins::LoadedPlugins;
+
+/// Convert config budget to RLM budget type.
+pub fn config_to_rlm_budget(config: &config::BudgetConfig) -> RlmBudgetConfig {
+    RlmBudgetConfig {
+        max_tokens: Some(config.max_tokens as u64),
+        max_depth: Some(config.max_depth),
+        max_tool_calls: Some(config.max_tool_calls),
+        max_duration_secs: Some(config.max_duration_secs),
+    }
+}
+
+/// Convert config tool limits to RLM tool limits.
+pub fn config_to_tool_limits(
+    tools: &HashMap<String, config::ToolLimitsConfig>,
+) -> HashMap<String, ToolLimits> {
+    tools
+        .iter()
+        .map(|(name, config)| {
+            let limits = ToolLimits {
+                max_calls: config.max_calls,
+                timeout: config.timeout_secs.map(std::time::Duration::from_secs),
+                max_output_tokens: config.max_output_tokens,
+                hint: config.hint.clone(),
+            };
+            (name.clone(), limits)
+        })
+        .collect()
+}
+
+/// MCP exposure overrides from the `expose` settings under `[tools.<name>]`.
+pub fn config_to_tool_exposure(tools: &HashMap<String, config::ToolLimitsConfig>) -> ToolExposure {
+    tools
+        .iter()
+        .filter_map(|(name, config)| config.expose.map(|expose| (name, expose)))
+        .fold(ToolExposure::new(), |exposure, (name, expose)| {
+            exposure.with(name.clone(), expose)
+        })
+}
+
+/// The built-in category prompts with `[prompts.<category>]` overrides
+/// applied.
+pub fn config_to_prompts(
+    prompts: &HashMap<muninn_rlm::RouteCategory, config::CategoryPromptConfig>,
+) -> PromptRegistry {
+    prompts
+        .iter()
+        .fold(PromptRegistry::builtin(), |registry, (category, config)| {
+            let mut prompt = registry.get(*category).cloned().unwrap_or_default();
+            if let Some(focus) = &config.focus {
+                prompt.focus = focus.clone();
+            }
+            if let Some(tools) = &config.tools {
+                prompt.tools = tools.clone();
+            }
+            registry.with(*category, prompt)
+        })
+}
+
+/// Files named in the engine's prior-exploration hint.
+const COVERAGE_HINT_FILES: usize = 10;
+
+/// Build the engine's prior-exploration hint from the traces under
+/// `muninn_dir`. `None` when `rlm.coverage_hint` is off or no file was
+/// re-read across requests.
+pub fn exploration_hint(config: &Config, muninn_dir: &Path, work_path: &Path) -> Option<String> {
+    if !config.rlm.coverage_hint {
+        return None;
+    }
+    match muninn_rlm::ExplorationCoverage::from_muninn_dir(muninn_dir, work_path) {
+        Ok(coverage) => coverage.engine_hint(COVERAGE_HINT_FILES),
+        Err(e) => {
+            debug!("Skipping exploration hint: {}", e);
+            None
+        }
+    }
+}
+
+/// Raw request log settings from `[debug]`, or `None` when disabled.
+///
+/// Logs go to `[debug] dir`, else the session directory, else
+/// `.muninn/debug`.
+pub fn raw_request_log_config(
+    config: &Config,
+    muninn_dir: &Path,
+    session_dir: Option<&Path>,
+) -> Option<muninn_rlm::RawRequestLogConfig> {
+    config
+        .debug
+        .log_raw_requests
+        .then(|| debug_log_config(config, muninn_dir, session_dir))
+}
+
+/// `[debug]` prepared RLM prompt dump settings, if enabled. Same directory,
+/// rotation and redaction as the raw request log.
+pub fn prompt_dump_config(
+    config: &Config,
+    muninn_dir: &Path,
+    session_dir: Option<&Path>,
+) -> Option<muninn_rlm::RawRequestLogConfig> {
+    config.debug.dump_prompts.then(|| {
+        debug_log_config(config, muninn_dir, session_dir)
+            .with_file_name(muninn_rlm::PROMPT_DUMP_FILE)
+    })
+}
+
+fn debug_log_config(
+    config: &Config,
+    muninn_dir: &Path,
+    session_dir: Option<&Path>,
+) -> muninn_rlm::RawRequestLogConfig {
+    let dir = config
+        .debug
+        .dir
+        .clone()
+        .or_else(|| session_dir.map(Path::to_path_buf))
+        .unwrap_or_else(|| muninn_dir.join("debug"));
+    muninn_rlm::RawRequestLogConfig::in_dir(&dir)
+        .with_max_bytes(config.debug.max_file_mb.saturating_mul(1024 * 1024))
+        .with_max_files(config.debug.max_files)
+        .with_redaction(config.debug.redact)
+}
+
+/// Extra trace sinks from `[tracing]`, with relative paths resolved
+/// against `muninn_dir`.
+pub fn trace_sinks(config: &Config, muninn_dir: &Path) -> Vec<muninn_tracing::SinkConfig> {
+    use muninn_tracing::SinkConfig;
+    config
+        .tracing
+        .sinks
+        .iter()
+        .cloned()
+        .map(|sink| match sink {
+            SinkConfig::Jsonl { path } if path.is_relative() => SinkConfig::Jsonl {
+                path: muninn_dir.join(path),
+            },
+            SinkConfig::Sqlite { path } if path.is_relative() => SinkConfig::Sqlite {
+                path: muninn_dir.join(path),
+            },
+            other => other,
+        })
+        .collect()
+}
+
+/// Webhook notifier settings from `[notifications]`, or `None` without a URL.
+pub fn notifier_config(config: &Config) -> Option<muninn_rlm::NotifierConfig> {
+    let notifications = &config.notifications;
+    let url = notifications.resolved_webhook_url()?;
+    let format = match notifications.format {
+        config::NotificationFormat::Slack => muninn_rlm::WebhookFormat::Slack,
+        config::NotificationFormat::Json => muninn_rlm::WebhookFormat::Json,
+    };
+    Some(
+        muninn_rlm::NotifierConfig::new(url)
+            .with_format(format)
+            .with_budget_events(notifications.budget)
+            .with_backend_events(notifications.backend)
+            .with_unhealthy_after(notifications.unhealthy_after)
+            .with_daily_token_thresholds(notifications.daily_token_thresholds.clone())
+            .with_cooldown(std::time::Duration::from_secs(notifications.cooldown_secs)),
+    )
+}
+
+/// Passthrough settings with `[upstream]` applied, checked up front so a
+/// bad CA file or proxy URL fails at startup rather than on first request.
+pub fn upstream_passthrough_config(
+    config: &Config,
+    muninn_dir: &Path,
+) -> Result<muninn_rlm::PassthroughConfig> {
+    let passthrough = config
+        .upstream
+        .apply(muninn_rlm::PassthroughConfig::default(), muninn_dir);
+    passthrough.build_client()?;
+    if config.upstream.base_url.is_some() {
+        info!("Upstream: {}", passthrough.base_url);
+    }
+    Ok(passthrough)
+}
+
+/// Create the file-backed token manager for a `.muninn` directory,
+/// honouring `[auth] encrypt_tokens`.
+pub fn file_token_manager(muninn_dir: &Path, config: &Config) -> Result<FileTokenManager> {
+    let manager = FileTokenManager::new(muninn_dir);
+    if !config.auth.encrypt_tokens {
+        return Ok(manager);
+    }
+    let source = TokenKeySource::from_config(&config.auth.key_source)?;
+    Ok(manager.with_encryption(TokenEncryption::new(source)))
+}
+
+/// Resolve the project root from config, canonicalized.
+pub fn project_root(config: &Config, config_dir: Option<&Path>) -> PathBuf {
+    let work_path = config_dir
+        .map(|d| d.join(&config.project.root))
+        .unwrap_or_else(|| config.project.root.clone());
+    work_path.canonicalize().unwrap_or(work_path)
+}
+
+/// Open the graph store if it exists.
+pub fn open_graph_store(path: &Path) -> Result<Option<SharedGraphStore>> {
+    if path.exists() {
+        info!("Opening graph store at {}", path.display());
+        let store = GraphStore::open(path)?;
+        Ok(Some(wrap_store(store)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Open the configured graph store: the `[graph] url` service when set,
+/// else the local database if it exists.
+pub fn open_configured_graph_store(
+    config: &Config,
+    muninn_dir: Option<&Path>,
+) -> Result<Option<SharedGraphStore>> {
+    match &config.graph.url {
+        Some(url) => {
+            info!("Using remote graph at {}", url);
+            Ok(Some(wrap_store(GraphStore::open_remote(url)?)))
+        }
+        None => open_graph_store(&config.resolve_graph_path(muninn_dir)),
+    }
+}
+
+/// Open the doc store if it exists.
+pub fn open_doc_store(path: &Path) -> Result<Option<SharedDocStore>> {
+    if path.exists() {
+        info!("Opening doc store at {}", path.display());
+        let store = DocStore::open(path)?;
+        Ok(Some(wrap_doc_store(store)))
+    } else {
+        debug!(
+            "No doc store at {} - doc tools will not be available",
+            path.display()
+        );
+        Ok(None)
+    }
+}
+
+/// Create a tool registry with all available tools.
+pub fn create_tools(
+    workdir: &Path,
+    graph_store: Option<SharedGraphStore>,
+    doc_store: Option<SharedDocStore>,
+    plugins: &LoadedPlugins,
+) -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+
+    // Add filesystem tools (internal, for RLM use)
+    for tool in create_fs_tools(workdir) {
+        registry.register_arc(Arc::from(tool));
+    }
+
+    // Add graph tools if we have a graph store (external, exposed via MCP)
+    if let Some(store) = graph_store {
+        for tool in create_graph_tools(store) {
+            registry.register_arc(Arc::from(tool));
+        }
+    }
+
+    // Add doc tools if we have a doc store (for library documentation search)
+    if let Some(store) = doc_store {
+        for tool in create_doc_tools(store) {
+            registry.register_arc(Arc::from(tool));
+        }
+    }
+
+    // Add tools from WASM plugins
+    plugins.register_tools(&mut registry);
+
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use muninn_tracing::SinkConfig;
+
+    #[test]
+    fn test_trace_sinks_resolve_relative_paths() {
+        let mut config = Config::default();
+        config.tracing.sinks = vec![
+            SinkConfig::Jsonl {
+                path: PathBuf::from("traces/extra.jsonl"),
+            },
+            SinkConfig::Sqlite {
+                path: PathBuf::from("/var/lib/muninn/traces.db"),
+            },
+        ];
+        let sinks = trace_sinks(&config, Path::new("/repo/.muninn"));
+        assert_eq!(
+            sinks,
+            vec![
+                SinkConfig::Jsonl {
+                    path: PathBuf::from("/repo/.muninn/traces/extra.jsonl"),
+                },
+                SinkConfig::Sqlite {
+                    path: PathBuf::from("/var/lib/muninn/traces.db"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_request_log_prefers_session_dir() {
+        let mut config = Config::default();
+        assert!(raw_request_log_config(&config, Path::new(".muninn"), None).is_none());
+
+        config.debug.log_raw_requests = true;
+        let log = raw_request_log_config(
+            &config,
+            Path::new(".muninn"),
+            Some(Path::new(".muninn/sessions/s1")),
+        )
+        .unwrap();
+        assert!(log.path.starts_with(".muninn/sessions/s1"));
+        let log = raw_request_log_config(&config, Path::new(".muninn"), None).unwrap();
+        assert!(log.path.starts_with(".muninn/debug"));
+    }
+
+    #[test]
+    fn test_prompt_dump_config() {
+        let mut config = Config::default();
+        assert!(prompt_dump_config(&config, Path::new(".muninn"), None).is_none());
+
+        config.debug.dump_prompts = true;
+        let dump = prompt_dump_config(
+            &config,
+            Path::new(".muninn"),
+            Some(Path::new(".muninn/sessions/s1")),
+        )
+        .unwrap();
+        assert_eq!(
+            dump.path,
+            Path::new(".muninn/sessions/s1").join(muninn_rlm::PROMPT_DUMP_FILE)
+        );
+        assert!(dump.redact);
+    }
+
+    #[test]
+    fn test_config_to_prompts_overrides_builtin() {
+        use muninn_rlm::RouteCategory;
+
+        let mut config = Config::default();
+        config.prompts.insert(
+            RouteCategory::Ops,
+            config::CategoryPromptConfig {
+                focus: Some("Look at the Makefile.".to_string()),
+                tools: None,
+            },
+        );
+        config.prompts.insert(
+            RouteCategory::Docs,
+            config::CategoryPromptConfig {
+                focus: None,
+                tools: Some(Vec::new()),
+            },
+        );
+        let prompts = config_to_prompts(&config.prompts);
+
+        let ops = prompts.get(RouteCategory::Ops).unwrap();
+        assert_eq!(ops.focus, "Look at the Makefile.");
+        assert!(!ops.allows("execute_code"));
+        let docs = prompts.get(RouteCategory::Docs).unwrap();
+        assert!(docs.focus.contains("## Focus: Documentation"));
+        assert!(docs.allows("execute_code"));
+        assert_eq!(
+            prompts.get(RouteCategory::Refactor),
+            PromptRegistry::builtin().get(RouteCategory::Refactor)
+        );
+    }
+}
@@ -2,12 +2,27 @@
 //!
 //! Provides infrastructure for collecting trace data scoped to async tasks.
 //! Consumers create spans and events; the collector aggregates them into a Trace.
+//!
+//! Prefer the scoped helpers ([`span`], [`span_with_data`], [`traced`]) over
+//! manual `start_span`/`end_span_*` pairs: they close the span on every exit
+//! path, including early returns, `?`, cancellation and panics.
+//!
+//! Panics inside a traced future are captured: the innermost open span gets a
+//! `panic` event and every open span is closed with an error outcome before
+//! the trace is finalized.
 
+use std::any::Any;
 use std::cell::RefCell;
+use std::fmt::Display;
+use std::future::Future;
 use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::Instant;
 
-use crate::types::{Span, Timing, Trace};
+use crate::context::TraceParent;
+use crate::types::{AttributeValue, Span, Timing, Trace};
 
 tokio::task_local! {
     static CURRENT_COLLECTOR: RefCell<TraceCollector>;
@@ -19,6 +34,7 @@ pub struct TraceCollector {
     trace: Trace,
     start_instant: Instant,
     span_stack: Vec<Span>,
+    sampled: bool,
 }
 
 impl TraceCollector {
@@ -28,6 +44,7 @@ impl TraceCollector {
             trace: Trace::new_random(),
             start_instant: Instant::now(),
             span_stack: Vec::new(),
+            sampled: true,
         }
     }
 
@@ -37,9 +54,28 @@ impl TraceCollector {
             trace: Trace::new(trace_id),
             start_instant: Instant::now(),
             span_stack: Vec::new(),
+            sampled: true,
         }
     }
 
+    /// Continue a remote trace: record the caller's span as this trace's parent.
+    pub fn set_trace_parent(&mut self, parent: &TraceParent) {
+        self.trace.parent_span_id = Some(parent.parent_id.clone());
+        self.sampled = parent.sampled;
+    }
+
+    /// Trace context for an outgoing request: parented on the innermost open
+    /// span, else the most recent top-level span.
+    pub fn trace_parent(&self) -> TraceParent {
+        let span_id = self
+            .span_stack
+            .last()
+            .or(self.trace.spans.last())
+            .map(|s| s.span_id.clone())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        TraceParent::new(&self.trace.trace_id, &span_id, self.sampled)
+    }
+
     /// Add metadata to the trace.
     pub fn add_metadata(&mut self, key: impl Into<String>, value: impl serde::Serialize) {
         if let Ok(v) = serde_json::to_value(value) {
@@ -64,6 +100,13 @@ impl TraceCollector {
         }
     }
 
+    /// Set an attribute on the current span.
+    pub fn set_span_attribute(&mut self, key: impl Into<String>, value: impl Into<AttributeValue>) {
+        if let Some(span) = self.span_stack.last_mut() {
+            span.set_attribute(key, value);
+        }
+    }
+
     /// Set timing breakdown for the current span.
     pub fn set_current_timing(&mut self, timing: Timing) {
         if let Some(span) = self.span_stack.last_mut() {
@@ -71,6 +114,13 @@ impl TraceCollector {
         }
     }
 
+    /// Add `ms` to a timing segment of the current span.
+    pub fn add_current_timing_segment(&mut self, name: impl Into<String>, ms: u64) {
+        if let Some(span) = self.span_stack.last_mut() {
+            span.timing.get_or_insert_default().add_segment(name, ms);
+        }
+    }
+
     /// End the current span successfully.
     pub fn end_span_ok(&mut self) {
         if let Some(mut span) = self.span_stack.pop() {
@@ -87,6 +137,55 @@ impl TraceCollector {
         }
     }
 
+    /// Number of currently open spans.
+    pub fn depth(&self) -> usize {
+        self.span_stack.len()
+    }
+
+    /// Close open spans until only `depth` remain.
+    ///
+    /// The outermost closed span (the one opened at `depth`) gets `outcome`;
+    /// anything opened above it and left dangling is closed as an error.
+    pub fn close_to(&mut self, depth: usize, outcome: Result<(), String>) {
+        let dangling = if std::thread::panicking() {
+            "panicked"
+        } else {
+            "span not explicitly closed"
+        };
+        while self.span_stack.len() > depth + 1 {
+            self.end_span_error(dangling);
+        }
+        if self.span_stack.len() == depth + 1 {
+            match outcome {
+                Ok(()) => self.end_span_ok(),
+                Err(message) => self.end_span_error(message),
+            }
+        }
+    }
+
+    /// Record a panic: a `panic` event on the innermost open span, trace
+    /// metadata, and every open span closed as an error.
+    pub fn record_panic(&mut self, message: &str) {
+        self.record_event("panic", Some(serde_json::json!({ "message": message })));
+        self.add_metadata("panic", message);
+        let outcome = format!("panicked: {message}");
+        while !self.span_stack.is_empty() {
+            self.end_span_error(outcome.clone());
+        }
+    }
+
+    /// Record a failed operation's error type and message in trace metadata,
+    /// closing any open spans with the error message.
+    pub fn record_error(&mut self, error_type: &str, message: &str) {
+        self.add_metadata(
+            "error",
+            serde_json::json!({ "type": error_type, "message": message }),
+        );
+        while !self.span_stack.is_empty() {
+            self.end_span_error(message);
+        }
+    }
+
     /// Add a complete span directly.
     pub fn add_span(&mut self, span: Span) {
         self.attach_span(span);
@@ -130,16 +229,63 @@ impl Default for TraceCollector {
     }
 }
 
-/// Execute an async operation with tracing enabled.
-///
-/// Returns both the operation result and the completed trace.
-pub async fn with_tracing<F, T>(f: F) -> (T, Trace)
+/// A panic caught by [`with_tracing_result`].
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("panicked: {message}")]
+pub struct TracedPanic {
+    /// The panic payload, if it was a string.
+    pub message: String,
+}
+
+/// Polls a future, catching panics and recording them in the current
+/// collector before the future (and any span guards it holds) is dropped.
+struct CatchPanic<F> {
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchPanic<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.inner;
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(value)) => Poll::Ready(Ok(value)),
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                let _ = CURRENT_COLLECTOR.try_with(|tc| {
+                    if let Ok(mut tc) = tc.try_borrow_mut() {
+                        tc.record_panic(&message);
+                    }
+                });
+                Poll::Ready(Err(payload))
+            }
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Run `f` in a fresh collector scope, returning its outcome and the
+/// finalized trace. A panic is recorded in the trace and returned as `Err`.
+async fn run_scoped<F, T>(
+    collector: TraceCollector,
+    f: F,
+) -> (Result<T, Box<dyn Any + Send>>, Trace)
 where
-    F: std::future::Future<Output = T>,
+    F: Future<Output = T>,
 {
     CURRENT_COLLECTOR
-        .scope(RefCell::new(TraceCollector::new()), async {
-            let result = f.await;
+        .scope(RefCell::new(collector), async {
+            let result = CatchPanic { inner: Box::pin(f) }.await;
             let trace = CURRENT_COLLECTOR.with(|tc| {
                 let collector = mem::take(&mut *tc.borrow_mut());
                 collector.finalize()
@@ -149,22 +295,69 @@ where
         .await
 }
 
+/// Execute an async operation with tracing enabled.
+///
+/// Returns both the operation result and the completed trace. If the
+/// operation panics, open spans are closed as errors and the panic is
+/// resumed; use [`with_tracing_result`] to keep the trace in that case.
+pub async fn with_tracing<F, T>(f: F) -> (T, Trace)
+where
+    F: Future<Output = T>,
+{
+    match run_scoped(TraceCollector::new(), f).await {
+        (Ok(result), trace) => (result, trace),
+        (Err(payload), _) => panic::resume_unwind(payload),
+    }
+}
+
 /// Execute an async operation with tracing, using a specific trace ID.
 pub async fn with_tracing_id<F, T>(trace_id: impl Into<String>, f: F) -> (T, Trace)
 where
-    F: std::future::Future<Output = T>,
+    F: Future<Output = T>,
 {
-    let collector = TraceCollector::with_trace_id(trace_id);
-    CURRENT_COLLECTOR
-        .scope(RefCell::new(collector), async {
-            let result = f.await;
-            let trace = CURRENT_COLLECTOR.with(|tc| {
-                let collector = mem::take(&mut *tc.borrow_mut());
-                collector.finalize()
+    match run_scoped(TraceCollector::with_trace_id(trace_id), f).await {
+        (Ok(result), trace) => (result, trace),
+        (Err(payload), _) => panic::resume_unwind(payload),
+    }
+}
+
+/// Execute a fallible async operation with tracing, always returning the trace.
+///
+/// On `Err`, the error's type name and message are stored under the `error`
+/// metadata key and any spans left open are closed with the message. A panic
+/// is recorded the same way (type `"panic"`) and converted into `E` through
+/// [`TracedPanic`], so the trace survives for writing.
+pub async fn with_tracing_result<F, T, E>(trace_id: Option<String>, f: F) -> (Result<T, E>, Trace)
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display + From<TracedPanic>,
+{
+    let collector = match trace_id {
+        Some(id) => TraceCollector::with_trace_id(id),
+        None => TraceCollector::new(),
+    };
+    let scoped = async {
+        let result = f.await;
+        if let Err(e) = &result {
+            let message = e.to_string();
+            let _ = CURRENT_COLLECTOR.try_with(|tc| {
+                tc.borrow_mut()
+                    .record_error(std::any::type_name::<E>(), &message)
             });
-            (result, trace)
-        })
-        .await
+        }
+        result
+    };
+    match run_scoped(collector, scoped).await {
+        (Ok(result), trace) => (result, trace),
+        (Err(payload), mut trace) => {
+            let message = panic_message(payload.as_ref());
+            trace.metadata.insert(
+                "error".into(),
+                serde_json::json!({ "type": "panic", "message": message }),
+            );
+            (Err(TracedPanic { message }.into()), trace)
+        }
+    }
 }
 
 /// Check if tracing is active in the current task.
@@ -192,11 +385,24 @@ pub fn record_event(name: impl Into<String>, data: Option<impl serde::Serialize>
     let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().record_event(name, data));
 }
 
+/// Set a typed attribute on the current span (no-op if tracing not active).
+/// Unlike [`add_metadata`], which applies to the whole trace, attributes
+/// describe one span and can be searched in the SQLite sink.
+pub fn set_span_attribute(key: impl Into<String>, value: impl Into<AttributeValue>) {
+    let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().set_span_attribute(key, value));
+}
+
 /// Set timing for the current span (no-op if tracing not active).
 pub fn set_timing(timing: Timing) {
     let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().set_current_timing(timing));
 }
 
+/// Add `ms` to a timing segment of the current span (no-op if tracing not
+/// active). Segments break a span's time down by phase.
+pub fn add_timing_segment(name: impl Into<String>, ms: u64) {
+    let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().add_current_timing_segment(name, ms));
+}
+
 /// End the current span successfully (no-op if tracing not active).
 pub fn end_span_ok() {
     let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().end_span_ok());
@@ -207,6 +413,135 @@ pub fn end_span_error(message: impl Into<String>) {
     let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().end_span_error(message));
 }
 
+/// RAII handle for a span opened with [`span`] or [`span_with_data`].
+///
+/// Close it explicitly with [`ok`](Self::ok), [`error`](Self::error) or
+/// [`finish`](Self::finish). A guard dropped without an outcome (early return,
+/// cancelled future, panic) closes its span as an error, so the span stack
+/// always stays balanced.
+#[must_use = "the span is closed as an error when the guard is dropped"]
+#[derive(Debug)]
+pub struct SpanGuard {
+    /// Stack depth the span was opened at; `None` when tracing is inactive.
+    depth: Option<usize>,
+}
+
+impl SpanGuard {
+    fn open(push: impl FnOnce(&mut TraceCollector)) -> Self {
+        let depth = CURRENT_COLLECTOR
+            .try_with(|tc| {
+                let mut tc = tc.borrow_mut();
+                let depth = tc.depth();
+                push(&mut tc);
+                depth
+            })
+            .ok();
+        Self { depth }
+    }
+
+    /// Record an event in this guard's span (or the innermost span opened under it).
+    pub fn record_event(&self, name: impl Into<String>, data: Option<impl serde::Serialize>) {
+        if self.depth.is_some() {
+            record_event(name, data);
+        }
+    }
+
+    /// Set an attribute on this guard's span (or the innermost span opened
+    /// under it).
+    pub fn attribute(&self, key: impl Into<String>, value: impl Into<AttributeValue>) {
+        if self.depth.is_some() {
+            set_span_attribute(key, value);
+        }
+    }
+
+    /// Add `ms` to a timing segment of this guard's span (or the innermost
+    /// span opened under it).
+    pub fn timing_segment(&self, name: impl Into<String>, ms: u64) {
+        if self.depth.is_some() {
+            add_timing_segment(name, ms);
+        }
+    }
+
+    /// Close the span successfully.
+    pub fn ok(mut self) {
+        self.close(Ok(()));
+    }
+
+    /// Close the span with an error.
+    pub fn error(mut self, message: impl Into<String>) {
+        self.close(Err(message.into()));
+    }
+
+    /// Close the span with the outcome of `result`.
+    pub fn finish<T, E: Display>(mut self, result: &Result<T, E>) {
+        self.close(result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+    }
+
+    fn close(&mut self, outcome: Result<(), String>) {
+        if let Some(depth) = self.depth.take() {
+            let _ = CURRENT_COLLECTOR.try_with(|tc| {
+                if let Ok(mut tc) = tc.try_borrow_mut() {
+                    tc.close_to(depth, outcome);
+                }
+            });
+        }
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let message = if std::thread::panicking() {
+            "panicked"
+        } else {
+            "span dropped without outcome"
+        };
+        self.close(Err(message.to_string()));
+    }
+}
+
+/// Open a span that closes when the returned guard is completed or dropped.
+pub fn span(name: impl Into<String>) -> SpanGuard {
+    SpanGuard::open(|tc| tc.start_span(name))
+}
+
+/// Open a span with attached data; see [`span`].
+pub fn span_with_data(name: impl Into<String>, data: impl serde::Serialize) -> SpanGuard {
+    SpanGuard::open(|tc| tc.start_span_with_data(name, data))
+}
+
+/// Run `fut` inside a span, closing it with the future's outcome.
+///
+/// `Ok` closes the span successfully, `Err` closes it with the error's
+/// `Display` text, and a panic or cancellation closes it as an error.
+pub async fn traced<F, T, E>(
+    name: impl Into<String>,
+    data: Option<impl serde::Serialize>,
+    fut: F,
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let guard = match data {
+        Some(data) => span_with_data(name, data),
+        None => span(name),
+    };
+    let result = fut.await;
+    guard.finish(&result);
+    result
+}
+
+/// Continue a remote trace in the current collector (no-op if tracing not active).
+pub fn set_trace_parent(parent: &TraceParent) {
+    let _ = CURRENT_COLLECTOR.try_with(|tc| tc.borrow_mut().set_trace_parent(parent));
+}
+
+pub(crate) fn outgoing_trace_parent() -> Option<TraceParent> {
+    CURRENT_COLLECTOR
+        .try_with(|tc| tc.borrow().trace_parent())
+        .ok()
+}
+
 /// Get the current trace ID (returns None if tracing not active).
 pub fn current_trace_id() -> Option<String> {
     CURRENT_COLLECTOR
@@ -217,6 +552,7 @@ pub fn current_trace_id() -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::SpanOutcome;
 
     #[tokio::test]
     async fn test_with_tracing() {
@@ -237,12 +573,229 @@ mod tests {
         assert_eq!(trace.spans[0].children[0].name, "inner");
     }
 
+    #[tokio::test]
+    async fn test_timing_segments_accumulate() {
+        let ((), trace) = with_tracing(async {
+            let outer = span("outer");
+            outer.timing_segment("backend_latency", 120);
+            let inner = span("inner");
+            add_timing_segment("execution", 7);
+            inner.ok();
+            add_timing_segment("backend_latency", 80);
+            outer.ok();
+        })
+        .await;
+
+        let outer = &trace.spans[0];
+        let timing = outer.timing.as_ref().unwrap();
+        assert_eq!(timing.segments["backend_latency"], 200);
+        assert!(!timing.segments.contains_key("execution"));
+        let inner = outer.children[0].timing.as_ref().unwrap();
+        assert_eq!(inner.segments["execution"], 7);
+
+        // Without tracing it's a no-op
+        add_timing_segment("backend_latency", 1);
+    }
+
+    #[tokio::test]
+    async fn test_span_attributes() {
+        let ((), trace) = with_tracing(async {
+            add_metadata("session", "s1");
+            let outer = span("tool_call");
+            outer.attribute("tool", "read_file");
+            let inner = span("read");
+            set_span_attribute("bytes", 512u64);
+            inner.ok();
+            set_span_attribute("tool", "grep");
+            outer.ok();
+        })
+        .await;
+
+        let outer = &trace.spans[0];
+        assert_eq!(outer.attributes["tool"], AttributeValue::from("grep"));
+        assert!(!outer.attributes.contains_key("bytes"));
+        assert_eq!(
+            outer.children[0].attributes["bytes"],
+            AttributeValue::Int(512)
+        );
+        assert!(!trace.metadata.contains_key("tool"));
+
+        // Without tracing it's a no-op
+        set_span_attribute("tool", "read_file");
+    }
+
+    #[tokio::test]
+    async fn test_span_guard_closes_on_early_return() {
+        fn step(fail: bool) -> Result<(), String> {
+            let guard = span("step");
+            if fail {
+                return Err("bailed".into());
+            }
+            guard.ok();
+            Ok(())
+        }
+
+        let (_, trace) = with_tracing(async {
+            let outer = span("outer");
+            let _ = step(true);
+            let _ = step(false);
+            start_span("dangling");
+            outer.ok();
+        })
+        .await;
+
+        let outer = &trace.spans[0];
+        assert_eq!(outer.outcome, Some(SpanOutcome::Ok));
+        let outcomes: Vec<_> = outer.children.iter().map(|c| c.outcome.clone()).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                Some(SpanOutcome::Error {
+                    message: "span dropped without outcome".into()
+                }),
+                Some(SpanOutcome::Ok),
+                Some(SpanOutcome::Error {
+                    message: "span not explicitly closed".into()
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_span_guard_closes_on_panic() {
+        let (_, trace) = with_tracing(async {
+            let result = std::panic::catch_unwind(|| {
+                let _guard = span("boom");
+                start_span("unguarded");
+                panic!("kaboom");
+            });
+            assert!(result.is_err());
+        })
+        .await;
+
+        let panicked = Some(SpanOutcome::Error {
+            message: "panicked".into(),
+        });
+        assert_eq!(trace.spans[0].name, "boom");
+        assert_eq!(trace.spans[0].outcome, panicked);
+        assert_eq!(trace.spans[0].children[0].outcome, panicked);
+    }
+
+    #[tokio::test]
+    async fn test_traced_uses_result_outcome() {
+        let (_, trace) = with_tracing(async {
+            let ok: Result<u32, String> = traced("good", Some("data"), async { Ok(1) }).await;
+            let err: Result<u32, String> =
+                traced("bad", None::<()>, async { Err("nope".to_string()) }).await;
+            assert_eq!(ok, Ok(1));
+            assert!(err.is_err());
+        })
+        .await;
+
+        assert_eq!(trace.spans.len(), 2);
+        assert_eq!(trace.spans[0].outcome, Some(SpanOutcome::Ok));
+        assert!(trace.spans[0].data.is_some());
+        assert_eq!(
+            trace.spans[1].outcome,
+            Some(SpanOutcome::Error {
+                message: "nope".into()
+            })
+        );
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    enum TestError {
+        #[error("bad input")]
+        BadInput,
+        #[error("{0}")]
+        Panicked(#[from] TracedPanic),
+    }
+
+    #[tokio::test]
+    async fn test_with_tracing_result_records_error() {
+        let (result, trace) = with_tracing_result(Some("t-1".into()), async {
+            start_span("outer");
+            start_span("inner");
+            Err::<(), _>(TestError::BadInput)
+        })
+        .await;
+
+        assert!(matches!(result, Err(TestError::BadInput)));
+        assert_eq!(trace.trace_id, "t-1");
+        let error = &trace.metadata["error"];
+        assert!(error["type"].as_str().unwrap().ends_with("TestError"));
+        assert_eq!(error["message"], "bad input");
+        let outer = &trace.spans[0];
+        let bad = Some(SpanOutcome::Error {
+            message: "bad input".into(),
+        });
+        assert_eq!(outer.outcome, bad);
+        assert_eq!(outer.children[0].outcome, bad);
+    }
+
+    #[tokio::test]
+    async fn test_with_tracing_result_captures_panic() {
+        let (result, trace) = with_tracing_result::<_, (), TestError>(None, async {
+            start_span("outer");
+            start_span("inner");
+            tokio::task::yield_now().await;
+            panic!("kaboom");
+        })
+        .await;
+
+        assert!(matches!(result, Err(TestError::Panicked(ref p)) if p.message == "kaboom"));
+        assert_eq!(trace.metadata["error"]["type"], "panic");
+        assert_eq!(trace.metadata["panic"], "kaboom");
+        let outer = &trace.spans[0];
+        let panicked = Some(SpanOutcome::Error {
+            message: "panicked: kaboom".into(),
+        });
+        assert_eq!(outer.outcome, panicked);
+        let inner = &outer.children[0];
+        assert_eq!(inner.outcome, panicked);
+        assert_eq!(inner.events[0].name, "panic");
+    }
+
+    #[tokio::test]
+    async fn test_with_tracing_resumes_panic() {
+        let handle = tokio::spawn(with_tracing(async {
+            start_span("doomed");
+            panic!("kaboom");
+        }));
+        let err = handle.await.unwrap_err();
+        assert!(err.is_panic());
+    }
+
+    #[tokio::test]
+    async fn test_trace_parent_continues_remote_trace() {
+        let incoming =
+            TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00").unwrap();
+        let (outgoing, trace) = with_tracing_id(incoming.trace_id.clone(), async {
+            set_trace_parent(&incoming);
+            let guard = span("upstream_call");
+            let outgoing = crate::context::current_traceparent().unwrap();
+            guard.ok();
+            outgoing
+        })
+        .await;
+
+        assert_eq!(trace.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_eq!(outgoing.trace_id, incoming.trace_id);
+        assert!(!outgoing.sampled);
+        assert_eq!(
+            outgoing.parent_id,
+            crate::context::hex_id(&trace.spans[0].span_id, 16)
+        );
+    }
+
     #[tokio::test]
     async fn test_no_tracing_context() {
         // These should be no-ops, not panics
         start_span("orphan");
         record_event("orphan_event", None::<()>);
         end_span_ok();
+        span("orphan_guard").ok();
+        drop(span("orphan_drop"));
 
         assert!(!is_tracing_active());
     }
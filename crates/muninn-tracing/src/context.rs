@@ -0,0 +1,174 @@
+//! W3C Trace Context propagation.
+//!
+//! Parses incoming `traceparent` headers so muninn traces join an external
+//! distributed trace, and builds the header for outgoing requests from the
+//! current trace and innermost open span.
+
+use std::fmt;
+use std::future::Future;
+
+/// Request header carrying the W3C trace context.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+tokio::task_local! {
+    static PROPAGATED: TraceParent;
+}
+
+/// A parsed `traceparent` header (`00-<trace-id>-<parent-id>-<flags>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceParent {
+    /// 32 lowercase hex digits.
+    pub trace_id: String,
+    /// 16 lowercase hex digits identifying the caller's span.
+    pub parent_id: String,
+    /// Whether the caller sampled this trace.
+    pub sampled: bool,
+}
+
+impl TraceParent {
+    /// Build a trace context from muninn IDs (UUIDs or hex), normalizing them
+    /// to the W3C widths.
+    pub fn new(trace_id: &str, parent_id: &str, sampled: bool) -> Self {
+        Self {
+            trace_id: hex_id(trace_id, 32),
+            parent_id: hex_id(parent_id, 16),
+            sampled,
+        }
+    }
+
+    /// Parse a `traceparent` header value.
+    ///
+    /// Returns `None` for malformed values, the invalid version `ff`, and
+    /// all-zero IDs. Fields after the flags (from future versions) are ignored.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        if !is_hex(version, 2) || version == "ff" {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            sampled: flags & 0x01 == 0x01,
+        })
+    }
+
+    /// The same trace with a different parent span.
+    pub fn child(&self, span_id: &str) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            parent_id: hex_id(span_id, 16),
+            sampled: self.sampled,
+        }
+    }
+}
+
+impl fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id,
+            self.parent_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+/// Run `f` with `parent` as the propagated context for code outside any
+/// `with_tracing` scope (e.g. passthrough requests that are not traced).
+pub async fn with_trace_parent<F: Future>(parent: TraceParent, f: F) -> F::Output {
+    PROPAGATED.scope(parent, f).await
+}
+
+/// The `traceparent` to send on outgoing requests, if any.
+///
+/// Inside a trace this points at the innermost open span; otherwise it is
+/// the context set by [`with_trace_parent`].
+pub fn current_traceparent() -> Option<TraceParent> {
+    crate::collector::outgoing_trace_parent().or_else(|| PROPAGATED.try_with(Clone::clone).ok())
+}
+
+/// Hex id of exactly `len` digits from an arbitrary id string.
+pub(crate) fn hex_id(id: &str, len: usize) -> String {
+    let hex: String = id
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .take(len)
+        .collect();
+    format!("{:0>len$}", hex, len = len)
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_round_trip() {
+        let tp = TraceParent::parse(HEADER).unwrap();
+        assert_eq!(tp.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(tp.parent_id, "00f067aa0ba902b7");
+        assert!(tp.sampled);
+        assert_eq!(tp.to_string(), HEADER);
+
+        let unsampled = TraceParent::parse(&HEADER.replace("-01", "-00")).unwrap();
+        assert!(!unsampled.sampled);
+        assert!(unsampled.to_string().ends_with("-00"));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        for bad in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert_eq!(TraceParent::parse(bad), None, "{bad}");
+        }
+        // Later versions may append fields
+        assert!(TraceParent::parse(&format!("01{}-extra", &HEADER[2..])).is_some());
+    }
+
+    #[test]
+    fn test_new_normalizes_uuids() {
+        let tp = TraceParent::new(
+            "6f1c0d2e-8a4b-4c3d-9e5f-1a2b3c4d5e6f",
+            "0a1b2c3d-4e5f-6071-8293-a4b5c6d7e8f9",
+            true,
+        );
+        assert_eq!(tp.trace_id, "6f1c0d2e8a4b4c3d9e5f1a2b3c4d5e6f");
+        assert_eq!(tp.parent_id, "0a1b2c3d4e5f6071");
+    }
+
+    #[tokio::test]
+    async fn test_propagated_outside_trace() {
+        assert_eq!(current_traceparent(), None);
+        let tp = TraceParent::parse(HEADER).unwrap();
+        let seen = with_trace_parent(tp.clone(), async { current_traceparent() }).await;
+        assert_eq!(seen, Some(tp));
+    }
+}
@@ -0,0 +1,214 @@
+//! Span filters for the trace writer.
+//!
+//! A [`SpanFilter`] trims each trace before it reaches any sink, so trace
+//! volume can be cut without touching the code that records spans:
+//!
+//! - `exclude` drops matching spans together with their children
+//!   (e.g. every `rlm_iteration`);
+//! - `include`, when non-empty, keeps only matching spans. Kept spans
+//!   nested under dropped ones move up to the nearest kept ancestor (or
+//!   the top level), so `router_decision` survives under `proxy_request`
+//!   even with everything in between filtered out.
+//!
+//! Exclusion wins over inclusion. Trace metadata is never filtered.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Span, Trace};
+
+/// Which spans a trace keeps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpanFilter {
+    /// Keep only spans matching one of these; all spans when empty.
+    pub include: Vec<SpanMatch>,
+    /// Drop spans (and their children) matching one of these.
+    pub exclude: Vec<SpanMatch>,
+}
+
+/// A span selector. Every field that is set has to match; a selector with
+/// neither `name` nor `attribute` matches nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpanMatch {
+    /// Span name, e.g. `"rlm_iteration"`.
+    pub name: Option<String>,
+    /// Key that must be present in the span's attributes or at the top
+    /// level of its data.
+    pub attribute: Option<String>,
+    /// Value `attribute` must have; any value when unset.
+    pub equals: Option<serde_json::Value>,
+}
+
+impl SpanMatch {
+    /// Match spans called `name`.
+    pub fn name(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Match spans whose data has `attribute` equal to `value`.
+    pub fn attribute(attribute: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Self {
+            attribute: Some(attribute.into()),
+            equals: Some(value.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn matches(&self, span: &Span) -> bool {
+        if self.name.is_none() && self.attribute.is_none() {
+            return false;
+        }
+        if self.name.as_ref().is_some_and(|name| *name != span.name) {
+            return false;
+        }
+        let Some(attribute) = &self.attribute else {
+            return true;
+        };
+        let value = match span.attributes.get(attribute) {
+            Some(value) => serde_json::to_value(value).ok(),
+            None => span
+                .data
+                .as_ref()
+                .and_then(|data| data.get(attribute))
+                .cloned(),
+        };
+        match value {
+            Some(value) => self
+                .equals
+                .as_ref()
+                .is_none_or(|expected| *expected == value),
+            None => false,
+        }
+    }
+}
+
+impl SpanFilter {
+    /// Keep only spans matching `selector` (added to any already listed).
+    pub fn include(mut self, selector: SpanMatch) -> Self {
+        self.include.push(selector);
+        self
+    }
+
+    /// Drop spans matching `selector`.
+    pub fn exclude(mut self, selector: SpanMatch) -> Self {
+        self.exclude.push(selector);
+        self
+    }
+
+    /// True when the filter keeps every span.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// A copy of `trace` with the spans this filter keeps.
+    pub fn apply(&self, trace: &Trace) -> Trace {
+        let mut filtered = trace.clone();
+        let spans = std::mem::take(&mut filtered.spans);
+        self.filter_into(spans, &mut filtered.spans);
+        filtered
+    }
+
+    fn filter_into(&self, spans: Vec<Span>, out: &mut Vec<Span>) {
+        for mut span in spans {
+            if self.exclude.iter().any(|m| m.matches(&span)) {
+                continue;
+            }
+            let children = std::mem::take(&mut span.children);
+            if self.include.is_empty() || self.include.iter().any(|m| m.matches(&span)) {
+                self.filter_into(children, &mut span.children);
+                out.push(span);
+            } else {
+                self.filter_into(children, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(spans: &[Span]) -> Vec<String> {
+        spans
+            .iter()
+            .map(|s| {
+                if s.children.is_empty() {
+                    s.name.clone()
+                } else {
+                    format!("{}[{}]", s.name, names(&s.children).join(","))
+                }
+            })
+            .collect()
+    }
+
+    fn sample() -> Trace {
+        let mut iteration = Span::new("rlm_iteration");
+        iteration.add_child(Span::new("tool_execution").with_data(json!({"tool": "read_file"})));
+        iteration.add_child(Span::new("tool_execution").with_data(json!({"tool": "grep"})));
+        let mut cycle = Span::new("rlm_cycle");
+        cycle.add_child(iteration);
+        let mut request = Span::new("proxy_request");
+        request.add_child(Span::new("router_decision").with_data(json!({"route": "rlm"})));
+        request.add_child(cycle);
+        let mut trace = Trace::new("t");
+        trace.add_span(request);
+        trace
+    }
+
+    #[test]
+    fn test_exclude_drops_subtree() {
+        let filter = SpanFilter::default().exclude(SpanMatch::name("rlm_iteration"));
+        assert_eq!(
+            names(&filter.apply(&sample()).spans),
+            ["proxy_request[router_decision,rlm_cycle]"]
+        );
+    }
+
+    #[test]
+    fn test_include_lifts_kept_descendants() {
+        let filter = SpanFilter::default()
+            .include(SpanMatch::name("proxy_request"))
+            .include(SpanMatch::name("router_decision"))
+            .include(SpanMatch::attribute("tool", "grep"));
+        assert_eq!(
+            names(&filter.apply(&sample()).spans),
+            ["proxy_request[router_decision,tool_execution]"]
+        );
+    }
+
+    #[test]
+    fn test_selector_fields_all_have_to_match() {
+        let span = Span::new("tool_execution").with_data(json!({"tool": "grep"}));
+        assert!(SpanMatch::attribute("tool", "grep").matches(&span));
+        assert!(!SpanMatch::attribute("tool", "read_file").matches(&span));
+        let any_tool = SpanMatch {
+            name: Some("tool_execution".to_string()),
+            attribute: Some("tool".to_string()),
+            equals: None,
+        };
+        assert!(any_tool.matches(&span));
+        assert!(!SpanMatch::name("rlm_cycle").matches(&span));
+        assert!(!SpanMatch::default().matches(&span));
+
+        let mut span = Span::new("tool_execution");
+        span.set_attribute("bytes", 512);
+        assert!(SpanMatch::attribute("bytes", 512).matches(&span));
+        assert!(!SpanMatch::attribute("bytes", "512").matches(&span));
+    }
+
+    #[test]
+    fn test_deserialize_from_toml_shape() {
+        let filter: SpanFilter = serde_json::from_value(json!({
+            "exclude": [{"name": "rlm_iteration"}],
+            "include": [{"attribute": "route", "equals": "rlm"}]
+        }))
+        .unwrap();
+        assert_eq!(filter.exclude, [SpanMatch::name("rlm_iteration")]);
+        assert_eq!(filter.include, [SpanMatch::attribute("route", "rlm")]);
+    }
+}
@@ -3,8 +3,14 @@
 //! This crate provides the foundation for structured tracing of agentic operations:
 //!
 //! - **Types**: Generic `Trace`, `Span`, `Event`, and `Timing` structures
-//! - **Collector**: Task-local collection via `with_tracing()` and helper functions
+//! - **Collector**: Task-local collection via `with_tracing()` and helper functions,
+//!   plus scoped span guards (`span()`, `traced()`) that always close their span,
+//!   and panic/error capture (`with_tracing_result()`)
+//! - **Context**: W3C `traceparent` parsing and propagation to outgoing requests
 //! - **Writer**: JSONL file persistence with daily rotation
+//! - **Sinks**: fan-out to extra exporters (JSONL, OTLP, webhook, SQLite)
+//! - **Filter**: include/exclude spans by name or data attribute before writing
+//! - **Testing** (`testing` feature): golden-trace snapshot assertions
 //!
 //! # Usage
 //!
@@ -45,15 +51,61 @@
 //!     confidence: 0.95,
 //! });
 //! ```
+//!
+//! # Span Attributes
+//!
+//! Metadata describes the whole trace; typed attributes describe one span
+//! and, with the SQLite sink, can be searched ("every `read_file` call
+//! slower than two seconds"):
+//!
+//! ```rust,ignore
+//! let guard = span("tool_call");
+//! guard.attribute("tool", "read_file");
+//! set_span_attribute("bytes", content.len());
+//! guard.ok();
+//!
+//! let slow = sqlite_sink.query_spans(
+//!     &SpanQuery::default().attribute("tool", "read_file").min_duration_ms(2000),
+//! )?;
+//! ```
+//!
+//! # Scoped Spans
+//!
+//! Guards close their span on every exit path; a guard dropped without an
+//! outcome (early return, cancellation, panic) records an error:
+//!
+//! ```rust,ignore
+//! let guard = span_with_data("tool_execution", &data);
+//! let output = run_tool()?; // span closed as an error if this bails
+//! guard.ok();
+//!
+//! // Or let the result decide the outcome:
+//! let response = traced("rlm_cycle", Some(&cycle_data), async move {
+//!     engine.explore(request).await
+//! })
+//! .await?;
+//! ```
 
 pub mod collector;
+pub mod context;
+pub mod filter;
+pub mod sink;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod types;
 pub mod writer;
 
 // Re-export main types
 pub use collector::{
-    TraceCollector, add_metadata, current_trace_id, end_span_error, end_span_ok, is_tracing_active,
-    record_event, set_timing, start_span, start_span_with_data, with_tracing, with_tracing_id,
+    SpanGuard, TraceCollector, TracedPanic, add_metadata, add_timing_segment, current_trace_id,
+    end_span_error, end_span_ok, is_tracing_active, record_event, set_span_attribute, set_timing,
+    set_trace_parent, span, span_with_data, start_span, start_span_with_data, traced, with_tracing,
+    with_tracing_id, with_tracing_result,
 };
-pub use types::{Event, Span, SpanOutcome, Timing, Trace};
-pub use writer::{TraceWriter, WriteError, WriterConfig};
+pub use context::{TRACEPARENT_HEADER, TraceParent, current_traceparent, with_trace_parent};
+pub use filter::{SpanFilter, SpanMatch};
+#[cfg(feature = "sqlite")]
+pub use sink::sqlite::{SpanQuery, SpanRecord, SqliteSink};
+pub use sink::{SinkConfig, TraceSink, otlp_request};
+pub use types::{AttributeValue, Event, Span, SpanOutcome, Timing, Trace};
+pub use writer::{JsonlSink, TraceWriter, WriteError, WriterConfig};
@@ -0,0 +1,820 @@
+//! Pluggable trace sinks.
+//!
+//! A [`TraceWriter`](crate::TraceWriter) fans every completed trace out to
+//! a list of [`TraceSink`]s: the JSONL file it always writes, plus any
+//! sinks listed in [`WriterConfig::sinks`](crate::WriterConfig::sinks) or
+//! added with [`TraceWriter::with_sink`](crate::TraceWriter::with_sink).
+//! A failing sink never stops the others.
+//!
+//! Built-in sinks, selected by [`SinkConfig`]:
+//!
+//! - [`SinkConfig::Jsonl`]: another JSONL file;
+//! - [`SinkConfig::Otlp`]: OTLP/HTTP JSON export to a collector
+//!   (`http` feature);
+//! - [`SinkConfig::Webhook`]: POST each trace as JSON (`http` feature);
+//! - [`SinkConfig::Sqlite`]: one row per trace in a SQLite table, plus
+//!   one per span with its attributes, searchable with
+//!   [`SpanQuery`](sqlite::SpanQuery) (`sqlite` feature).
+//!
+//! HTTP sinks hand traces to a background thread through a bounded queue,
+//! so a slow collector never blocks the request path; traces that don't
+//! fit in the queue are dropped.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::hex_id;
+use crate::types::Trace;
+use crate::writer::WriteError;
+
+/// Destination for completed traces.
+pub trait TraceSink: Send + Sync {
+    /// Short name used in error messages (e.g. `"otlp"`).
+    fn name(&self) -> &str;
+
+    /// Export one trace.
+    fn write(&self, trace: &Trace) -> Result<(), WriteError>;
+}
+
+/// Configuration for a built-in sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// Append traces to a JSONL file.
+    Jsonl { path: PathBuf },
+    /// Export spans to an OTLP/HTTP collector (`{endpoint}/v1/traces`).
+    Otlp {
+        endpoint: String,
+        #[serde(default = "default_service_name")]
+        service_name: String,
+    },
+    /// POST each trace as JSON to a URL.
+    Webhook { url: String },
+    /// Insert each trace into the `traces` table of a SQLite database, and
+    /// its spans into `spans` and `span_attributes`.
+    Sqlite { path: PathBuf },
+}
+
+fn default_service_name() -> String {
+    "muninn".to_string()
+}
+
+impl SinkConfig {
+    /// Build the sink this config describes.
+    pub fn build(&self) -> Result<Box<dyn TraceSink>, WriteError> {
+        match self {
+            SinkConfig::Jsonl { path } => Ok(Box::new(crate::writer::JsonlSink::session(path)?)),
+            #[cfg(feature = "http")]
+            SinkConfig::Otlp {
+                endpoint,
+                service_name,
+            } => Ok(Box::new(http::OtlpSink::new(endpoint, service_name))),
+            #[cfg(feature = "http")]
+            SinkConfig::Webhook { url } => Ok(Box::new(http::WebhookSink::new(url))),
+            #[cfg(not(feature = "http"))]
+            SinkConfig::Otlp { .. } | SinkConfig::Webhook { .. } => Err(WriteError::Unsupported(
+                "OTLP and webhook sinks need the `http` feature".to_string(),
+            )),
+            #[cfg(feature = "sqlite")]
+            SinkConfig::Sqlite { path } => Ok(Box::new(sqlite::SqliteSink::open(path)?)),
+            #[cfg(not(feature = "sqlite"))]
+            SinkConfig::Sqlite { .. } => Err(WriteError::Unsupported(
+                "the SQLite sink needs the `sqlite` feature".to_string(),
+            )),
+        }
+    }
+}
+
+/// OTLP/HTTP JSON export request for `trace`.
+///
+/// Span ids are derived from the trace's UUIDs (dashes stripped,
+/// truncated to 16 hex digits); span data and events become attributes.
+pub fn otlp_request(trace: &Trace, service_name: &str) -> serde_json::Value {
+    let trace_id = hex_id(&trace.trace_id, 32);
+    let parent = trace.parent_span_id.as_deref().map(|id| hex_id(id, 16));
+    let mut spans = Vec::new();
+    for span in &trace.spans {
+        push_otlp_span(&mut spans, span, &trace_id, parent.as_deref());
+    }
+    serde_json::json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [string_attr("service.name", service_name)],
+            },
+            "scopeSpans": [{
+                "scope": {"name": "muninn-tracing", "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn push_otlp_span(
+    out: &mut Vec<serde_json::Value>,
+    span: &crate::types::Span,
+    trace_id: &str,
+    parent: Option<&str>,
+) {
+    use crate::types::{AttributeValue, SpanOutcome};
+
+    let span_id = hex_id(&span.span_id, 16);
+    let nanos =
+        |t: chrono::DateTime<chrono::Utc>| t.timestamp_nanos_opt().unwrap_or_default().to_string();
+    let mut attributes = Vec::new();
+    if let Some(serde_json::Value::Object(data)) = &span.data {
+        for (key, value) in data {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            attributes.push(string_attr(&format!("muninn.{}", key), &value));
+        }
+    }
+    for (key, value) in &span.attributes {
+        let value = match value {
+            AttributeValue::Bool(b) => serde_json::json!({"boolValue": b}),
+            AttributeValue::Int(i) => serde_json::json!({"intValue": i.to_string()}),
+            AttributeValue::Float(f) => serde_json::json!({"doubleValue": f}),
+            AttributeValue::String(s) => serde_json::json!({"stringValue": s}),
+        };
+        attributes.push(serde_json::json!({"key": key, "value": value}));
+    }
+    let status = match &span.outcome {
+        Some(SpanOutcome::Ok) => serde_json::json!({"code": 1}),
+        Some(SpanOutcome::Error { message }) => serde_json::json!({"code": 2, "message": message}),
+        None => serde_json::json!({"code": 0}),
+    };
+    let events: Vec<_> = span
+        .events
+        .iter()
+        .map(|event| {
+            let mut attrs = Vec::new();
+            if let Some(data) = &event.data {
+                attrs.push(string_attr("muninn.data", &data.to_string()));
+            }
+            serde_json::json!({
+                "name": event.name,
+                "timeUnixNano": nanos(event.timestamp),
+                "attributes": attrs,
+            })
+        })
+        .collect();
+    let mut otlp = serde_json::json!({
+        "traceId": trace_id,
+        "spanId": span_id,
+        "name": span.name,
+        "kind": 1,
+        "startTimeUnixNano": nanos(span.started_at),
+        "endTimeUnixNano": nanos(span.ended_at.unwrap_or(span.started_at)),
+        "attributes": attributes,
+        "events": events,
+        "status": status,
+    });
+    if let Some(parent) = parent {
+        otlp["parentSpanId"] = serde_json::Value::String(parent.to_string());
+    }
+    out.push(otlp);
+    for child in &span.children {
+        push_otlp_span(out, child, trace_id, Some(&span_id));
+    }
+}
+
+fn string_attr(key: &str, value: &str) -> serde_json::Value {
+    serde_json::json!({"key": key, "value": {"stringValue": value}})
+}
+
+#[cfg(feature = "http")]
+mod http {
+    use std::sync::mpsc::{self, SyncSender, TrySendError};
+    use std::time::Duration;
+
+    use super::{TraceSink, otlp_request};
+    use crate::types::Trace;
+    use crate::writer::WriteError;
+
+    /// Traces queued per HTTP sink before new ones are dropped.
+    const QUEUE_DEPTH: usize = 256;
+
+    /// Background thread POSTing JSON bodies to one URL.
+    struct Poster {
+        tx: SyncSender<serde_json::Value>,
+    }
+
+    impl Poster {
+        fn spawn(url: String) -> Self {
+            let (tx, rx) = mpsc::sync_channel::<serde_json::Value>(QUEUE_DEPTH);
+            std::thread::Builder::new()
+                .name("muninn-trace-http".to_string())
+                .spawn(move || {
+                    let client = match reqwest::blocking::Client::builder()
+                        .timeout(Duration::from_secs(10))
+                        .build()
+                    {
+                        Ok(c) => c,
+                        Err(e) => {
+                            eprintln!("muninn-tracing: cannot build HTTP client: {}", e);
+                            return;
+                        }
+                    };
+                    for body in rx {
+                        match client.post(&url).json(&body).send() {
+                            Ok(r) if r.status().is_success() => {}
+                            Ok(r) => {
+                                eprintln!("muninn-tracing: {} rejected trace: {}", url, r.status())
+                            }
+                            Err(e) => {
+                                eprintln!("muninn-tracing: failed to export to {}: {}", url, e)
+                            }
+                        }
+                    }
+                })
+                .expect("spawn trace export thread");
+            Self { tx }
+        }
+
+        fn post(&self, sink: &str, body: serde_json::Value) -> Result<(), WriteError> {
+            self.tx.try_send(body).map_err(|e| WriteError::Sink {
+                sink: sink.to_string(),
+                message: match e {
+                    TrySendError::Full(_) => "export queue full; trace dropped".to_string(),
+                    TrySendError::Disconnected(_) => "export thread stopped".to_string(),
+                },
+            })
+        }
+    }
+
+    /// Exports spans to an OTLP/HTTP collector.
+    pub struct OtlpSink {
+        service_name: String,
+        poster: Poster,
+    }
+
+    impl OtlpSink {
+        pub fn new(endpoint: &str, service_name: &str) -> Self {
+            let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+            Self {
+                service_name: service_name.to_string(),
+                poster: Poster::spawn(url),
+            }
+        }
+    }
+
+    impl TraceSink for OtlpSink {
+        fn name(&self) -> &str {
+            "otlp"
+        }
+
+        fn write(&self, trace: &Trace) -> Result<(), WriteError> {
+            self.poster
+                .post(self.name(), otlp_request(trace, &self.service_name))
+        }
+    }
+
+    /// POSTs each trace as JSON.
+    pub struct WebhookSink {
+        poster: Poster,
+    }
+
+    impl WebhookSink {
+        pub fn new(url: &str) -> Self {
+            Self {
+                poster: Poster::spawn(url.to_string()),
+            }
+        }
+    }
+
+    impl TraceSink for WebhookSink {
+        fn name(&self) -> &str {
+            "webhook"
+        }
+
+        fn write(&self, trace: &Trace) -> Result<(), WriteError> {
+            self.poster.post(self.name(), serde_json::to_value(trace)?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::io::{Read, Write};
+
+        use super::*;
+
+        #[test]
+        fn test_webhook_sink_posts_trace() {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/traces", listener.local_addr().unwrap());
+            let server = std::thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                while !String::from_utf8_lossy(&request).contains("webhook-trace") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+                String::from_utf8_lossy(&request).to_string()
+            });
+
+            let sink = WebhookSink::new(&url);
+            sink.write(&Trace::new("webhook-trace")).unwrap();
+            let request = server.join().unwrap();
+            assert!(request.starts_with("POST /traces"));
+            assert!(request.contains("\"trace_id\":\"webhook-trace\""));
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite {
+    //! SQLite trace store.
+    //!
+    //! Besides the whole trace as JSON in `traces`, every span gets a row in
+    //! `spans` (with its parent, duration and error) and its attributes a
+    //! row each in `span_attributes`, so spans can be searched by name,
+    //! attribute and latency with [`SpanQuery`] or plain SQL. Attribute
+    //! values keep their SQLite type (integer, real or text; booleans are
+    //! stored as 0/1).
+
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use chrono::{DateTime, Utc};
+    use rusqlite::types::Value;
+    use serde::Serialize;
+
+    use super::TraceSink;
+    use crate::types::{AttributeValue, Span, SpanOutcome, Trace};
+    use crate::writer::WriteError;
+
+    const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS traces (
+             trace_id TEXT PRIMARY KEY,
+             started_at TEXT NOT NULL,
+             duration_ms INTEGER,
+             span_count INTEGER NOT NULL,
+             trace TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS traces_started_at ON traces (started_at);
+         CREATE TABLE IF NOT EXISTS spans (
+             trace_id TEXT NOT NULL,
+             span_id TEXT NOT NULL,
+             parent_span_id TEXT,
+             name TEXT NOT NULL,
+             started_at TEXT NOT NULL,
+             duration_ms INTEGER,
+             error TEXT,
+             PRIMARY KEY (trace_id, span_id)
+         );
+         CREATE INDEX IF NOT EXISTS spans_name ON spans (name, duration_ms);
+         CREATE TABLE IF NOT EXISTS span_attributes (
+             trace_id TEXT NOT NULL,
+             span_id TEXT NOT NULL,
+             key TEXT NOT NULL,
+             value,
+             PRIMARY KEY (trace_id, span_id, key)
+         );
+         CREATE INDEX IF NOT EXISTS span_attributes_key ON span_attributes (key, value);";
+
+    /// Stores traces in a `traces` table, one row per trace, and their spans
+    /// in `spans` and `span_attributes`.
+    pub struct SqliteSink {
+        conn: Mutex<rusqlite::Connection>,
+    }
+
+    fn sink_error(e: rusqlite::Error) -> WriteError {
+        WriteError::Sink {
+            sink: "sqlite".to_string(),
+            message: e.to_string(),
+        }
+    }
+
+    fn sql_value(value: &AttributeValue) -> Value {
+        match value {
+            AttributeValue::Bool(b) => Value::Integer(i64::from(*b)),
+            AttributeValue::Int(i) => Value::Integer(*i),
+            AttributeValue::Float(f) => Value::Real(*f),
+            AttributeValue::String(s) => Value::Text(s.clone()),
+        }
+    }
+
+    fn attribute_value(value: Value) -> Option<AttributeValue> {
+        match value {
+            Value::Integer(i) => Some(AttributeValue::Int(i)),
+            Value::Real(f) => Some(AttributeValue::Float(f)),
+            Value::Text(s) => Some(AttributeValue::String(s)),
+            Value::Null | Value::Blob(_) => None,
+        }
+    }
+
+    impl SqliteSink {
+        pub fn open(path: &Path) -> Result<Self, WriteError> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let conn = rusqlite::Connection::open(path).map_err(sink_error)?;
+            conn.execute_batch(SCHEMA).map_err(sink_error)?;
+            Ok(Self {
+                conn: Mutex::new(conn),
+            })
+        }
+
+        /// Spans matching `query`, oldest first.
+        pub fn query_spans(&self, query: &SpanQuery) -> Result<Vec<SpanRecord>, WriteError> {
+            let (sql, params) = query.to_sql();
+            let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+            let mut stmt = conn.prepare(&sql).map_err(sink_error)?;
+            let rows = stmt
+                .query_map(rusqlite::params_from_iter(params), |row| {
+                    let started_at: String = row.get(4)?;
+                    Ok(SpanRecord {
+                        trace_id: row.get(0)?,
+                        span_id: row.get(1)?,
+                        parent_span_id: row.get(2)?,
+                        name: row.get(3)?,
+                        started_at: started_at.parse().unwrap_or_default(),
+                        duration_ms: row.get::<_, Option<i64>>(5)?.map(|d| d.max(0) as u64),
+                        error: row.get(6)?,
+                        attributes: BTreeMap::new(),
+                    })
+                })
+                .map_err(sink_error)?;
+            let mut spans = rows.collect::<Result<Vec<_>, _>>().map_err(sink_error)?;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT key, value FROM span_attributes WHERE trace_id = ?1 AND span_id = ?2",
+                )
+                .map_err(sink_error)?;
+            for span in &mut spans {
+                let attributes = stmt
+                    .query_map([&span.trace_id, &span.span_id], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, Value>(1)?))
+                    })
+                    .map_err(sink_error)?;
+                for attribute in attributes {
+                    let (key, value) = attribute.map_err(sink_error)?;
+                    if let Some(value) = attribute_value(value) {
+                        span.attributes.insert(key, value);
+                    }
+                }
+            }
+            Ok(spans)
+        }
+    }
+
+    fn insert_span(
+        tx: &rusqlite::Transaction<'_>,
+        trace_id: &str,
+        span: &Span,
+        parent: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        let error = match &span.outcome {
+            Some(SpanOutcome::Error { message }) => Some(message.as_str()),
+            _ => None,
+        };
+        tx.execute(
+            "INSERT OR REPLACE INTO spans
+                 (trace_id, span_id, parent_span_id, name, started_at, duration_ms, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                trace_id,
+                span.span_id,
+                parent,
+                span.name,
+                span.started_at.to_rfc3339(),
+                span.timing.as_ref().map(|t| t.total_ms as i64),
+                error,
+            ],
+        )?;
+        for (key, value) in &span.attributes {
+            tx.execute(
+                "INSERT OR REPLACE INTO span_attributes (trace_id, span_id, key, value)
+                 VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![trace_id, span.span_id, key, sql_value(value)],
+            )?;
+        }
+        for child in &span.children {
+            insert_span(tx, trace_id, child, Some(&span.span_id))?;
+        }
+        Ok(())
+    }
+
+    impl TraceSink for SqliteSink {
+        fn name(&self) -> &str {
+            "sqlite"
+        }
+
+        fn write(&self, trace: &Trace) -> Result<(), WriteError> {
+            let json = serde_json::to_string(trace)?;
+            let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+            let tx = conn.transaction().map_err(sink_error)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO traces (trace_id, started_at, duration_ms, span_count, trace)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    trace.trace_id,
+                    trace.started_at.to_rfc3339(),
+                    trace.duration_ms.map(|d| d as i64),
+                    trace.spans.len() as i64,
+                    json,
+                ],
+            )
+            .map_err(sink_error)?;
+            tx.execute("DELETE FROM spans WHERE trace_id = ?1", [&trace.trace_id])
+                .map_err(sink_error)?;
+            tx.execute(
+                "DELETE FROM span_attributes WHERE trace_id = ?1",
+                [&trace.trace_id],
+            )
+            .map_err(sink_error)?;
+            for span in &trace.spans {
+                insert_span(&tx, &trace.trace_id, span, trace.parent_span_id.as_deref())
+                    .map_err(sink_error)?;
+            }
+            tx.commit().map_err(sink_error)
+        }
+    }
+
+    /// How an attribute is compared with a query value.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Comparison {
+        Equal,
+        Above,
+        Below,
+    }
+
+    /// Search over the spans of a [`SqliteSink`]. Every condition has to
+    /// hold; an empty query returns every span.
+    ///
+    /// ```rust,ignore
+    /// // Every read_file call that took longer than two seconds
+    /// let slow = sink.query_spans(
+    ///     &SpanQuery::default()
+    ///         .attribute("tool", "read_file")
+    ///         .min_duration_ms(2000),
+    /// )?;
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct SpanQuery {
+        name: Option<String>,
+        attributes: Vec<(String, Comparison, AttributeValue)>,
+        min_duration_ms: Option<u64>,
+        limit: Option<usize>,
+    }
+
+    impl SpanQuery {
+        /// Keep spans called `name`.
+        pub fn name(mut self, name: impl Into<String>) -> Self {
+            self.name = Some(name.into());
+            self
+        }
+
+        /// Keep spans whose attribute `key` equals `value`.
+        pub fn attribute(
+            mut self,
+            key: impl Into<String>,
+            value: impl Into<AttributeValue>,
+        ) -> Self {
+            self.attributes
+                .push((key.into(), Comparison::Equal, value.into()));
+            self
+        }
+
+        /// Keep spans whose numeric attribute `key` is greater than `value`.
+        pub fn attribute_above(
+            mut self,
+            key: impl Into<String>,
+            value: impl Into<AttributeValue>,
+        ) -> Self {
+            self.attributes
+                .push((key.into(), Comparison::Above, value.into()));
+            self
+        }
+
+        /// Keep spans whose numeric attribute `key` is less than `value`.
+        pub fn attribute_below(
+            mut self,
+            key: impl Into<String>,
+            value: impl Into<AttributeValue>,
+        ) -> Self {
+            self.attributes
+                .push((key.into(), Comparison::Below, value.into()));
+            self
+        }
+
+        /// Keep spans that took at least `ms` milliseconds.
+        pub fn min_duration_ms(mut self, ms: u64) -> Self {
+            self.min_duration_ms = Some(ms);
+            self
+        }
+
+        /// Return at most `limit` spans.
+        pub fn limit(mut self, limit: usize) -> Self {
+            self.limit = Some(limit);
+            self
+        }
+
+        fn to_sql(&self) -> (String, Vec<Value>) {
+            let mut sql =
+                "SELECT trace_id, span_id, parent_span_id, name, started_at, duration_ms, error
+                 FROM spans s WHERE 1 = 1"
+                    .to_string();
+            let mut params = Vec::new();
+            if let Some(name) = &self.name {
+                params.push(Value::Text(name.clone()));
+                sql.push_str(&format!(" AND s.name = ?{}", params.len()));
+            }
+            if let Some(ms) = self.min_duration_ms {
+                params.push(Value::Integer(i64::try_from(ms).unwrap_or(i64::MAX)));
+                sql.push_str(&format!(" AND s.duration_ms >= ?{}", params.len()));
+            }
+            for (key, comparison, value) in &self.attributes {
+                params.push(Value::Text(key.clone()));
+                let key_param = params.len();
+                params.push(sql_value(value));
+                let value_param = params.len();
+                // SQLite sorts every number below every string, so range
+                // conditions only look at numeric values
+                let condition = match comparison {
+                    Comparison::Equal => format!("a.value = ?{}", value_param),
+                    Comparison::Above => format!(
+                        "a.value > ?{} AND typeof(a.value) IN ('integer', 'real')",
+                        value_param
+                    ),
+                    Comparison::Below => format!(
+                        "a.value < ?{} AND typeof(a.value) IN ('integer', 'real')",
+                        value_param
+                    ),
+                };
+                sql.push_str(&format!(
+                    " AND EXISTS (SELECT 1 FROM span_attributes a
+                         WHERE a.trace_id = s.trace_id AND a.span_id = s.span_id
+                         AND a.key = ?{} AND {})",
+                    key_param, condition
+                ));
+            }
+            sql.push_str(" ORDER BY s.started_at, s.trace_id");
+            if let Some(limit) = self.limit {
+                sql.push_str(&format!(" LIMIT {}", limit));
+            }
+            (sql, params)
+        }
+    }
+
+    /// A span found by [`SqliteSink::query_spans`].
+    #[derive(Debug, Clone, Serialize)]
+    pub struct SpanRecord {
+        pub trace_id: String,
+        pub span_id: String,
+        /// Enclosing span, or the remote caller's span for top-level spans
+        /// of a continued trace.
+        pub parent_span_id: Option<String>,
+        pub name: String,
+        pub started_at: DateTime<Utc>,
+        pub duration_ms: Option<u64>,
+        /// Error message when the span failed.
+        pub error: Option<String>,
+        pub attributes: BTreeMap<String, AttributeValue>,
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_sqlite_sink() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("traces.db");
+            let sink = SqliteSink::open(&path).unwrap();
+            let mut trace = Trace::new("t-1");
+            trace.complete();
+            sink.write(&trace).unwrap();
+            sink.write(&trace).unwrap();
+
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            let count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM traces WHERE trace_id = 't-1'",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        fn tool_call(tool: &str, total_ms: u64) -> Span {
+            let mut span = Span::new("tool_call");
+            span.set_attribute("tool", tool);
+            span.set_attribute("bytes", total_ms * 10);
+            span.complete_ok();
+            span.timing.as_mut().unwrap().total_ms = total_ms;
+            span
+        }
+
+        #[test]
+        fn test_query_spans_by_attribute_and_latency() {
+            let dir = tempfile::tempdir().unwrap();
+            let sink = SqliteSink::open(&dir.path().join("traces.db")).unwrap();
+            let mut trace = Trace::new("t-1");
+            let mut iteration = Span::new("rlm_iteration");
+            iteration.set_attribute("tool", "read_file");
+            iteration.add_child(tool_call("read_file", 2500));
+            iteration.add_child(tool_call("read_file", 300));
+            iteration.add_child(tool_call("grep", 4000));
+            trace.add_span(iteration);
+            sink.write(&trace).unwrap();
+            // Rewriting a trace replaces its spans
+            sink.write(&trace).unwrap();
+
+            let slow_reads = sink
+                .query_spans(
+                    &SpanQuery::default()
+                        .name("tool_call")
+                        .attribute("tool", "read_file")
+                        .min_duration_ms(2000),
+                )
+                .unwrap();
+            assert_eq!(slow_reads.len(), 1);
+            let span = &slow_reads[0];
+            assert_eq!(span.duration_ms, Some(2500));
+            assert_eq!(span.parent_span_id, Some(trace.spans[0].span_id.clone()));
+            assert_eq!(span.attributes["bytes"], AttributeValue::Int(25000));
+
+            let big = sink
+                .query_spans(&SpanQuery::default().attribute_above("bytes", 10000))
+                .unwrap();
+            let mut sizes: Vec<_> = big.iter().map(|s| s.attributes["bytes"].clone()).collect();
+            sizes.sort_by_key(|v| format!("{:?}", v));
+            assert_eq!(
+                sizes,
+                [AttributeValue::Int(25000), AttributeValue::Int(40000)]
+            );
+            // Strings never pass a range condition
+            let none = sink
+                .query_spans(&SpanQuery::default().attribute_above("tool", 0))
+                .unwrap();
+            assert!(none.is_empty());
+            assert_eq!(sink.query_spans(&SpanQuery::default()).unwrap().len(), 4);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Span;
+
+    #[test]
+    fn test_sink_config_deserialize() {
+        let sinks: Vec<SinkConfig> = serde_json::from_value(serde_json::json!([
+            {"type": "otlp", "endpoint": "http://localhost:4318"},
+            {"type": "sqlite", "path": ".muninn/traces.db"},
+        ]))
+        .unwrap();
+        assert_eq!(
+            sinks[0],
+            SinkConfig::Otlp {
+                endpoint: "http://localhost:4318".to_string(),
+                service_name: "muninn".to_string(),
+            }
+        );
+        assert_eq!(
+            sinks[1],
+            SinkConfig::Sqlite {
+                path: PathBuf::from(".muninn/traces.db")
+            }
+        );
+    }
+
+    #[test]
+    fn test_otlp_request_nests_spans() {
+        let mut trace = Trace::new("0b2c1e4f-9a7d-4b3e-8f61-2d5c7a9e0f13");
+        let mut parent = Span::new("proxy_request").with_data(serde_json::json!({"model": "m"}));
+        let mut child = Span::new("tool_call");
+        child.complete_error("boom");
+        parent.add_child(child);
+        parent.complete_ok();
+        trace.add_span(parent);
+
+        let request = otlp_request(&trace, "svc");
+        let spans = request["resourceSpans"][0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0]["traceId"], "0b2c1e4f9a7d4b3e8f612d5c7a9e0f13");
+        assert_eq!(spans[0]["spanId"].as_str().unwrap().len(), 16);
+        assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_eq!(spans[0]["attributes"][0]["key"], "muninn.model");
+        assert_eq!(
+            request["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "svc"
+        );
+    }
+}
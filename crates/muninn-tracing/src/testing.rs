@@ -0,0 +1,321 @@
+//! Golden-trace snapshot testing.
+//!
+//! [`GoldenTrace`] compares a [`Trace`] against a JSON snapshot on disk,
+//! so tests can pin down an operation's observable behaviour (which spans
+//! ran, in what order, with what data) rather than just its final answer.
+//!
+//! Traces are normalized before comparison:
+//!
+//! - trace/span IDs, start/end times, durations and timings are dropped
+//! - UUID and RFC 3339 strings inside data become `"<uuid>"` / `"<timestamp>"`
+//! - `timestamp` fields, fields ending in `_ms` (latencies, durations), and
+//!   fields named in [`GoldenTrace::ignore_field`] become `"<ignored>"`
+//!
+//! A missing snapshot is written on first run. Set `MUNINN_UPDATE_GOLDEN=1`
+//! to rewrite snapshots after an intended behaviour change.
+//!
+//! ```rust,ignore
+//! use muninn_tracing::testing::GoldenTrace;
+//!
+//! let (_, trace) = with_tracing(run_engine()).await;
+//! GoldenTrace::new("tests/golden/tool_loop.json")
+//!     .ignore_field("request_id")
+//!     .assert_matches(&trace);
+//! ```
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::types::{Event, Span, SpanOutcome, Trace};
+
+/// Environment variable that forces snapshots to be rewritten.
+pub const UPDATE_GOLDEN_ENV: &str = "MUNINN_UPDATE_GOLDEN";
+
+/// Fields with run-dependent values, ignored by default wherever they occur
+/// (in addition to any field ending in `_ms`).
+const DEFAULT_IGNORED_FIELDS: &[&str] = &["timestamp"];
+
+/// A golden snapshot of a trace's shape.
+#[derive(Debug, Clone)]
+pub struct GoldenTrace {
+    path: PathBuf,
+    ignored: BTreeSet<String>,
+}
+
+impl GoldenTrace {
+    /// Snapshot stored at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ignored: DEFAULT_IGNORED_FIELDS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+
+    /// Mask a data/metadata field (matched by key, at any depth).
+    pub fn ignore_field(mut self, key: impl Into<String>) -> Self {
+        self.ignored.insert(key.into());
+        self
+    }
+
+    /// Path of the snapshot file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Normalize a trace into the form stored in snapshots.
+    pub fn normalize(&self, trace: &Trace) -> Value {
+        let mut out = Map::new();
+        if !trace.metadata.is_empty() {
+            let metadata: Map<String, Value> = trace
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), self.normalize_field(k, v)))
+                .collect();
+            // serde_json maps are sorted, so HashMap order doesn't leak in
+            out.insert("metadata".into(), Value::Object(metadata));
+        }
+        out.insert(
+            "spans".into(),
+            Value::Array(trace.spans.iter().map(|s| self.normalize_span(s)).collect()),
+        );
+        Value::Object(out)
+    }
+
+    /// Compare against the snapshot, writing it if missing or when
+    /// [`UPDATE_GOLDEN_ENV`] is set.
+    ///
+    /// Returns a description of the first difference on mismatch.
+    pub fn check(&self, trace: &Trace) -> Result<(), String> {
+        let actual = self.normalize(trace);
+        let update = std::env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|v| v != "0");
+        if update || !self.path.exists() {
+            return self.write(&actual);
+        }
+
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("failed to read {}: {}", self.path.display(), e))?;
+        let expected: Value = serde_json::from_str(&content)
+            .map_err(|e| format!("invalid golden file {}: {}", self.path.display(), e))?;
+
+        match first_difference(&expected, &actual, "$") {
+            None => Ok(()),
+            Some(diff) => Err(format!(
+                "trace does not match golden file {}\n{}\n\nactual:\n{}\n\n\
+                 (set {}=1 to update)",
+                self.path.display(),
+                diff,
+                serde_json::to_string_pretty(&actual).unwrap_or_default(),
+                UPDATE_GOLDEN_ENV,
+            )),
+        }
+    }
+
+    /// Like [`GoldenTrace::check`], panicking on mismatch.
+    #[track_caller]
+    pub fn assert_matches(&self, trace: &Trace) {
+        if let Err(message) = self.check(trace) {
+            panic!("{}", message);
+        }
+    }
+
+    fn write(&self, value: &Value) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(value).unwrap_or_default() + "\n";
+        std::fs::write(&self.path, content)
+            .map_err(|e| format!("failed to write {}: {}", self.path.display(), e))
+    }
+
+    fn normalize_span(&self, span: &Span) -> Value {
+        let mut out = Map::new();
+        out.insert("name".into(), Value::String(span.name.clone()));
+        if let Some(outcome) = &span.outcome {
+            let outcome = match outcome {
+                SpanOutcome::Ok => Value::String("ok".into()),
+                SpanOutcome::Error { message } => {
+                    Value::String(format!("error: {}", normalize_string(message)))
+                }
+            };
+            out.insert("outcome".into(), outcome);
+        }
+        if let Some(data) = &span.data {
+            out.insert("data".into(), self.normalize_value(data));
+        }
+        if !span.events.is_empty() {
+            let events = span
+                .events
+                .iter()
+                .map(|e| self.normalize_event(e))
+                .collect();
+            out.insert("events".into(), Value::Array(events));
+        }
+        if !span.children.is_empty() {
+            let children = span
+                .children
+                .iter()
+                .map(|c| self.normalize_span(c))
+                .collect();
+            out.insert("children".into(), Value::Array(children));
+        }
+        Value::Object(out)
+    }
+
+    fn normalize_event(&self, event: &Event) -> Value {
+        let mut out = Map::new();
+        out.insert("name".into(), Value::String(event.name.clone()));
+        if let Some(data) = &event.data {
+            out.insert("data".into(), self.normalize_value(data));
+        }
+        Value::Object(out)
+    }
+
+    fn normalize_field(&self, key: &str, value: &Value) -> Value {
+        if key.ends_with("_ms") || self.ignored.contains(key) {
+            Value::String("<ignored>".into())
+        } else {
+            self.normalize_value(value)
+        }
+    }
+
+    fn normalize_value(&self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.normalize_field(k, v)))
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.normalize_value(v)).collect())
+            }
+            Value::String(s) => Value::String(normalize_string(s)),
+            other => other.clone(),
+        }
+    }
+}
+
+/// Replace a run-dependent string with a placeholder.
+fn normalize_string(s: &str) -> String {
+    if is_uuid(s) {
+        "<uuid>".to_string()
+    } else if chrono::DateTime::parse_from_rfc3339(s).is_ok() {
+        "<timestamp>".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Whether `s` looks like a hyphenated UUID.
+fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Describe the first place two values differ, as a JSON path.
+fn first_difference(expected: &Value, actual: &Value, path: &str) -> Option<String> {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            let keys: BTreeSet<&String> = e.keys().chain(a.keys()).collect();
+            keys.into_iter().find_map(|key| {
+                let child = format!("{}.{}", path, key);
+                match (e.get(key), a.get(key)) {
+                    (Some(ev), Some(av)) => first_difference(ev, av, &child),
+                    (Some(_), None) => Some(format!("{}: missing from actual trace", child)),
+                    (None, Some(_)) => Some(format!("{}: not in golden file", child)),
+                    (None, None) => None,
+                }
+            })
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            let shared =
+                e.iter().zip(a).enumerate().find_map(|(i, (ev, av))| {
+                    first_difference(ev, av, &format!("{}[{}]", path, i))
+                });
+            shared.or_else(|| {
+                (e.len() != a.len())
+                    .then(|| format!("{}: expected {} items, got {}", path, e.len(), a.len()))
+            })
+        }
+        _ if expected == actual => None,
+        _ => Some(format!("{}: expected {}, got {}", path, expected, actual)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn sample_trace(tool: &str) -> Trace {
+        let mut trace = Trace::new_random().with_metadata("model", "test-model");
+        let mut root = Span::new("proxy_request").with_data(json!({
+            "request_id": uuid::Uuid::new_v4().to_string(),
+            "total_time_ms": 17,
+        }));
+        let mut call = Span::new("tool_call").with_data(json!({"tool": tool}));
+        call.record_event("tool_result", Some(json!({"at": chrono::Utc::now()})));
+        call.complete_ok();
+        root.add_child(call);
+        root.complete_ok();
+        trace.add_span(root);
+        trace.complete();
+        trace
+    }
+
+    #[test]
+    fn test_normalize_strips_volatile_fields() {
+        let golden = GoldenTrace::new("unused.json");
+        let normalized = golden.normalize(&sample_trace("read_file"));
+        assert_eq!(
+            normalized,
+            json!({
+                "metadata": {"model": "test-model"},
+                "spans": [{
+                    "name": "proxy_request",
+                    "outcome": "ok",
+                    "data": {"request_id": "<uuid>", "total_time_ms": "<ignored>"},
+                    "children": [{
+                        "name": "tool_call",
+                        "outcome": "ok",
+                        "data": {"tool": "read_file"},
+                        "events": [{"name": "tool_result", "data": {"at": "<timestamp>"}}]
+                    }]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_golden_written_then_matched() {
+        let dir = tempdir().unwrap();
+        let golden = GoldenTrace::new(dir.path().join("golden/trace.json"));
+
+        // First run writes the snapshot
+        golden.assert_matches(&sample_trace("read_file"));
+        assert!(golden.path().exists());
+
+        // Fresh IDs and timings still match
+        golden.assert_matches(&sample_trace("read_file"));
+
+        // A behavioural change is reported with its location
+        let err = golden.check(&sample_trace("grep")).unwrap_err();
+        assert!(err.contains("$.spans[0].children[0].data.tool"), "{}", err);
+    }
+
+    #[test]
+    fn test_first_difference_reports_length_mismatch() {
+        let diff = first_difference(&json!([1, 2]), &json!([1]), "$").unwrap();
+        assert_eq!(diff, "$: expected 2 items, got 1");
+        assert!(first_difference(&json!({"a": 1}), &json!({"a": 1}), "$").is_none());
+    }
+}
@@ -5,7 +5,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// A complete trace representing one logical operation (e.g., one request lifecycle).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +22,11 @@ pub struct Trace {
     /// Total duration in milliseconds.
     pub duration_ms: Option<u64>,
 
+    /// Span ID of the remote caller, when the trace continues an incoming
+    /// W3C `traceparent`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+
     /// Top-level spans in this trace.
     pub spans: Vec<Span>,
 
@@ -55,6 +60,11 @@ pub struct Span {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
 
+    /// Typed key/value attributes, set while the span is open and
+    /// searchable in the SQLite sink (e.g. `tool = "read_file"`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, AttributeValue>,
+
     /// Events that occurred during this span.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub events: Vec<Event>,
@@ -69,7 +79,7 @@ pub struct Span {
 }
 
 /// Outcome of a span's execution.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "status")]
 pub enum SpanOutcome {
     /// Span completed successfully.
@@ -81,6 +91,70 @@ pub enum SpanOutcome {
     Error { message: String },
 }
 
+/// Value of a span attribute.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<i32> for AttributeValue {
+    fn from(value: i32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<u64> for AttributeValue {
+    fn from(value: u64) -> Self {
+        Self::Int(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<usize> for AttributeValue {
+    fn from(value: usize) -> Self {
+        Self::Int(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
 /// A point-in-time occurrence within a span.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -114,6 +188,7 @@ impl Trace {
             started_at: Utc::now(),
             ended_at: None,
             duration_ms: None,
+            parent_span_id: None,
             spans: Vec::new(),
             metadata: HashMap::new(),
         }
@@ -155,6 +230,7 @@ impl Span {
             ended_at: None,
             timing: None,
             data: None,
+            attributes: BTreeMap::new(),
             events: Vec::new(),
             children: Vec::new(),
             outcome: None,
@@ -167,6 +243,11 @@ impl Span {
         self
     }
 
+    /// Set an attribute, replacing any earlier value for `key`.
+    pub fn set_attribute(&mut self, key: impl Into<String>, value: impl Into<AttributeValue>) {
+        self.attributes.insert(key.into(), value.into());
+    }
+
     /// Mark the span as complete with success.
     pub fn complete_ok(&mut self) {
         self.ended_at = Some(Utc::now());
@@ -236,6 +317,12 @@ impl Timing {
         self.segments.insert(name.into(), ms);
         self
     }
+
+    /// Add `ms` to a named segment, so a phase that runs several times
+    /// (one backend call per iteration) sums up.
+    pub fn add_segment(&mut self, name: impl Into<String>, ms: u64) {
+        *self.segments.entry(name.into()).or_default() += ms;
+    }
 }
 
 #[cfg(test)]
@@ -278,4 +365,27 @@ mod tests {
         assert!(json.contains("test-123"));
         assert!(json.contains("operation"));
     }
+
+    #[test]
+    fn test_span_attributes_round_trip() {
+        let mut span = Span::new("tool_call");
+        span.set_attribute("tool", "read_file");
+        span.set_attribute("bytes", 2048u64);
+        span.set_attribute("score", 0.5);
+        span.set_attribute("cached", false);
+
+        let json = serde_json::to_value(&span).unwrap();
+        assert_eq!(json["attributes"]["tool"], "read_file");
+        assert_eq!(json["attributes"]["bytes"], 2048);
+        let back: Span = serde_json::from_value(json).unwrap();
+        assert_eq!(back.attributes, span.attributes);
+        assert_eq!(back.attributes["bytes"], AttributeValue::Int(2048));
+
+        // Spans written before attributes existed still parse
+        let old: Span = serde_json::from_value(serde_json::json!({
+            "span_id": "s", "name": "n", "started_at": "2026-10-01T09:00:00Z", "ended_at": null,
+        }))
+        .unwrap();
+        assert!(old.attributes.is_empty());
+    }
 }
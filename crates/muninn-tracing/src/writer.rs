@@ -3,6 +3,11 @@
 //! Supports two modes:
 //! - **Session mode**: Writes to a single file (e.g., `session_dir/traces.jsonl`)
 //! - **Daily rotation**: Writes to dated files (e.g., `traces/2026-01-11.jsonl`)
+//!
+//! The JSONL file is the writer's primary [`TraceSink`]; extra sinks from
+//! [`WriterConfig::sinks`] receive every trace too (see [`crate::sink`]).
+//! [`WriterConfig::filter`] trims spans before any sink sees them (see
+//! [`crate::filter`]).
 
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufWriter, Write};
@@ -11,6 +16,8 @@ use std::sync::Mutex;
 
 use chrono::Utc;
 
+use crate::filter::SpanFilter;
+use crate::sink::{SinkConfig, TraceSink};
 use crate::types::Trace;
 
 /// Error type for trace writing operations.
@@ -21,6 +28,12 @@ pub enum WriteError {
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    #[error("{sink} sink: {message}")]
+    Sink { sink: String, message: String },
+
+    #[error("Unsupported sink: {0}")]
+    Unsupported(String),
 }
 
 /// Configuration for the trace writer.
@@ -36,6 +49,12 @@ pub struct WriterConfig {
 
     /// Session mode writes to a single file; daily rotation writes to dated files.
     pub session_mode: bool,
+
+    /// Additional sinks every trace is fanned out to.
+    pub sinks: Vec<SinkConfig>,
+
+    /// Spans to keep or drop before writing.
+    pub filter: SpanFilter,
 }
 
 impl Default for WriterConfig {
@@ -51,6 +70,8 @@ impl WriterConfig {
             trace_path: trace_file.into(),
             enabled: true,
             session_mode: true,
+            sinks: Vec::new(),
+            filter: SpanFilter::default(),
         }
     }
 
@@ -60,6 +81,8 @@ impl WriterConfig {
             trace_path: trace_dir.into(),
             enabled: true,
             session_mode: false,
+            sinks: Vec::new(),
+            filter: SpanFilter::default(),
         }
     }
 
@@ -74,43 +97,52 @@ impl WriterConfig {
             trace_path: PathBuf::new(),
             enabled: false,
             session_mode: false,
+            sinks: Vec::new(),
+            filter: SpanFilter::default(),
         }
     }
+
+    /// Also export every trace to `sink`.
+    pub fn with_sink(mut self, sink: SinkConfig) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Also export every trace to each of `sinks`.
+    pub fn with_sinks(mut self, sinks: impl IntoIterator<Item = SinkConfig>) -> Self {
+        self.sinks.extend(sinks);
+        self
+    }
+
+    /// Filter the spans of every trace before it is written.
+    pub fn with_filter(mut self, filter: SpanFilter) -> Self {
+        self.filter = filter;
+        self
+    }
 }
 
-/// Writes traces to JSONL files.
+/// Fans traces out to the JSONL file and any extra sinks.
 ///
-/// Thread-safe via internal mutex.
+/// Thread-safe; sinks synchronize internally.
 pub struct TraceWriter {
     config: WriterConfig,
-    current_file: Mutex<Option<CurrentFile>>,
-}
-
-struct CurrentFile {
-    /// For daily rotation: the date string. For session mode: "session".
-    key: String,
-    writer: BufWriter<File>,
+    sinks: Vec<Box<dyn TraceSink>>,
 }
 
 impl TraceWriter {
     /// Create a new trace writer with the given configuration.
+    ///
+    /// Fails if the trace directory can't be created or a configured sink
+    /// can't be built.
     pub fn new(config: WriterConfig) -> Result<Self, WriteError> {
+        let mut sinks: Vec<Box<dyn TraceSink>> = Vec::new();
         if config.enabled {
-            if config.session_mode {
-                // Session mode: create parent directory of trace file
-                if let Some(parent) = config.trace_path.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-            } else {
-                // Daily rotation: create trace directory
-                fs::create_dir_all(&config.trace_path)?;
+            sinks.push(Box::new(JsonlSink::new(&config)?));
+            for sink in &config.sinks {
+                sinks.push(sink.build()?);
             }
         }
-
-        Ok(Self {
-            config,
-            current_file: Mutex::new(None),
-        })
+        Ok(Self { config, sinks })
     }
 
     /// Create a trace writer with default configuration.
@@ -118,23 +150,121 @@ impl TraceWriter {
         Self::new(WriterConfig::default())
     }
 
-    /// Write a trace to the appropriate file.
+    /// Add a custom sink.
+    pub fn with_sink(mut self, sink: Box<dyn TraceSink>) -> Self {
+        if self.config.enabled {
+            self.sinks.push(sink);
+        }
+        self
+    }
+
+    /// Names of the active sinks, primary first.
+    pub fn sink_names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|s| s.name()).collect()
+    }
+
+    /// Write a trace to every sink, after the configured span filter.
+    ///
+    /// All sinks are attempted; the first failure is returned.
     pub fn write(&self, trace: &Trace) -> Result<(), WriteError> {
-        if !self.config.enabled {
-            return Ok(());
+        let filtered;
+        let trace = if self.config.filter.is_empty() || self.sinks.is_empty() {
+            trace
+        } else {
+            filtered = self.config.filter.apply(trace);
+            &filtered
+        };
+        let mut first_error = None;
+        for sink in &self.sinks {
+            if let Err(e) = sink.write(trace) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Get the path to the current trace file.
+    pub fn current_file_path(&self) -> PathBuf {
+        if self.config.session_mode {
+            self.config.trace_path.clone()
+        } else {
+            let today = Utc::now().format("%Y-%m-%d").to_string();
+            self.config.trace_path.join(format!("{}.jsonl", today))
         }
+    }
 
-        let mut guard = self.current_file.lock().unwrap();
+    /// List all trace files in the trace directory.
+    /// Note: Only works for daily rotation mode.
+    pub fn list_trace_files(&self) -> Result<Vec<PathBuf>, WriteError> {
+        if !self.config.enabled {
+            return Ok(Vec::new());
+        }
 
         if self.config.session_mode {
-            // Session mode: write to single file
-            self.write_session_mode(&mut guard, trace)?;
+            // In session mode, return the single trace file if it exists
+            if self.config.trace_path.exists() {
+                return Ok(vec![self.config.trace_path.clone()]);
+            }
+            return Ok(Vec::new());
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.config.trace_path)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+
+        files.sort();
+        Ok(files)
+    }
+
+    /// Read traces from a specific file.
+    pub fn read_traces(path: &Path) -> Result<Vec<Trace>, WriteError> {
+        let content = fs::read_to_string(path)?;
+        let traces: Result<Vec<Trace>, _> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect();
+        Ok(traces?)
+    }
+}
+
+/// The JSONL file sink behind [`TraceWriter`].
+pub struct JsonlSink {
+    path: PathBuf,
+    session_mode: bool,
+    current_file: Mutex<Option<CurrentFile>>,
+}
+
+struct CurrentFile {
+    /// For daily rotation: the date string. For session mode: "session".
+    key: String,
+    writer: BufWriter<File>,
+}
+
+impl JsonlSink {
+    /// Create the sink for `config`'s trace path and mode.
+    pub fn new(config: &WriterConfig) -> Result<Self, WriteError> {
+        if config.session_mode {
+            // Session mode: create parent directory of trace file
+            if let Some(parent) = config.trace_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
         } else {
-            // Daily rotation mode
-            self.write_daily_mode(&mut guard, trace)?;
+            // Daily rotation: create trace directory
+            fs::create_dir_all(&config.trace_path)?;
         }
+        Ok(Self {
+            path: config.trace_path.clone(),
+            session_mode: config.session_mode,
+            current_file: Mutex::new(None),
+        })
+    }
 
-        Ok(())
+    /// Append to the single file at `path`.
+    pub fn session(path: impl Into<PathBuf>) -> Result<Self, WriteError> {
+        Self::new(&WriterConfig::session(path))
     }
 
     fn write_session_mode(
@@ -147,7 +277,7 @@ impl TraceWriter {
             let file = OpenOptions::new()
                 .create(true)
                 .append(true)
-                .open(&self.config.trace_path)?;
+                .open(&self.path)?;
             *guard = Some(CurrentFile {
                 key: "session".to_string(),
                 writer: BufWriter::new(file),
@@ -177,7 +307,7 @@ impl TraceWriter {
         };
 
         if needs_new_file {
-            let file_path = self.config.trace_path.join(format!("{}.jsonl", today));
+            let file_path = self.path.join(format!("{}.jsonl", today));
             let file = OpenOptions::new()
                 .create(true)
                 .append(true)
@@ -196,51 +326,20 @@ impl TraceWriter {
 
         Ok(())
     }
+}
 
-    /// Get the path to the current trace file.
-    pub fn current_file_path(&self) -> PathBuf {
-        if self.config.session_mode {
-            self.config.trace_path.clone()
-        } else {
-            let today = Utc::now().format("%Y-%m-%d").to_string();
-            self.config.trace_path.join(format!("{}.jsonl", today))
-        }
+impl TraceSink for JsonlSink {
+    fn name(&self) -> &str {
+        "jsonl"
     }
 
-    /// List all trace files in the trace directory.
-    /// Note: Only works for daily rotation mode.
-    pub fn list_trace_files(&self) -> Result<Vec<PathBuf>, WriteError> {
-        if !self.config.enabled {
-            return Ok(Vec::new());
-        }
-
-        if self.config.session_mode {
-            // In session mode, return the single trace file if it exists
-            if self.config.trace_path.exists() {
-                return Ok(vec![self.config.trace_path.clone()]);
-            }
-            return Ok(Vec::new());
+    fn write(&self, trace: &Trace) -> Result<(), WriteError> {
+        let mut guard = self.current_file.lock().unwrap_or_else(|e| e.into_inner());
+        if self.session_mode {
+            self.write_session_mode(&mut guard, trace)
+        } else {
+            self.write_daily_mode(&mut guard, trace)
         }
-
-        let mut files: Vec<PathBuf> = fs::read_dir(&self.config.trace_path)?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
-            .collect();
-
-        files.sort();
-        Ok(files)
-    }
-
-    /// Read traces from a specific file.
-    pub fn read_traces(path: &Path) -> Result<Vec<Trace>, WriteError> {
-        let content = fs::read_to_string(path)?;
-        let traces: Result<Vec<Trace>, _> = content
-            .lines()
-            .filter(|line| !line.trim().is_empty())
-            .map(serde_json::from_str)
-            .collect();
-        Ok(traces?)
     }
 }
 
@@ -309,6 +408,85 @@ mod tests {
         assert!(files.is_empty());
     }
 
+    struct Counting(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl TraceSink for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn write(&self, _trace: &Trace) -> Result<(), WriteError> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl TraceSink for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn write(&self, _trace: &Trace) -> Result<(), WriteError> {
+            Err(WriteError::Sink {
+                sink: "failing".to_string(),
+                message: "down".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_fan_out_to_sinks() {
+        let dir = tempdir().unwrap();
+        let primary = dir.path().join("traces.jsonl");
+        let copy = dir.path().join("copy/traces.jsonl");
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let writer = TraceWriter::new(
+            WriterConfig::session(&primary).with_sink(SinkConfig::Jsonl { path: copy.clone() }),
+        )
+        .unwrap()
+        .with_sink(Box::new(Failing))
+        .with_sink(Box::new(Counting(count.clone())));
+        assert_eq!(
+            writer.sink_names(),
+            ["jsonl", "jsonl", "failing", "counting"]
+        );
+
+        let mut trace = Trace::new("fan-out");
+        trace.complete();
+        let err = writer.write(&trace).unwrap_err();
+        assert_eq!(err.to_string(), "failing sink: down");
+
+        // The failing sink doesn't stop the ones after it.
+        assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 1);
+        assert_eq!(TraceWriter::read_traces(&primary).unwrap().len(), 1);
+        assert_eq!(
+            TraceWriter::read_traces(&copy).unwrap()[0].trace_id,
+            "fan-out"
+        );
+    }
+
+    #[test]
+    fn test_filter_applies_before_sinks() {
+        let dir = tempdir().unwrap();
+        let primary = dir.path().join("traces.jsonl");
+        let writer = TraceWriter::new(WriterConfig::session(&primary).with_filter(
+            SpanFilter::default().exclude(crate::filter::SpanMatch::name("rlm_iteration")),
+        ))
+        .unwrap();
+
+        let mut trace = Trace::new("filtered");
+        trace.add_span(crate::types::Span::new("rlm_iteration"));
+        trace.add_span(crate::types::Span::new("router_decision"));
+        trace.complete();
+        writer.write(&trace).unwrap();
+
+        let written = TraceWriter::read_traces(&primary).unwrap();
+        let names: Vec<_> = written[0].spans.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["router_decision"]);
+    }
+
     #[test]
     fn test_legacy_api_compatibility() {
         let dir = tempdir().unwrap();
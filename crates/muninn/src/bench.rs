@@ -0,0 +1,457 @@
+//! `muninn bench backends`: the same probes against every backend, to
+//! choose router and RLM models on evidence rather than reputation.
+//!
+//! Each [`BenchTarget`] gets every probe in [`PROBES`] `runs` times. Plain
+//! completion probes pass when the reply contains the expected answer;
+//! tool-calling probes pass when the model calls the expected tool with the
+//! expected argument, which is what the router and RLM depend on. The
+//! [`BenchReport`] gives latency percentiles, output-token throughput, pass
+//! rates and, when `[pricing]` knows the model, what the run cost.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use muninn_rlm::{CompletionRequest, LLMBackend, Message, ToolDefinition};
+use muninn_sdk::config::ModelPricing;
+use serde::Serialize;
+
+/// Output tokens allowed per probe.
+const PROBE_MAX_TOKENS: u32 = 256;
+
+/// Error messages kept per backend.
+const MAX_FAILURES: usize = 3;
+
+/// What a probe's response has to contain.
+enum Expect {
+    /// Reply text containing this (case-insensitive).
+    Text(&'static str),
+    /// A call to `tool` whose `arg` contains `value`.
+    Tool {
+        tool: &'static str,
+        arg: &'static str,
+        value: &'static str,
+    },
+}
+
+struct Probe {
+    name: &'static str,
+    prompt: &'static str,
+    expect: Expect,
+}
+
+/// The battery. Tool probes offer both [`probe_tools`], so picking the
+/// right one counts.
+const PROBES: &[Probe] = &[
+    Probe {
+        name: "echo",
+        prompt: "Reply with exactly one word: pong",
+        expect: Expect::Text("pong"),
+    },
+    Probe {
+        name: "arithmetic",
+        prompt: "What is 17 * 23? Reply with the number only.",
+        expect: Expect::Text("391"),
+    },
+    Probe {
+        name: "code_reading",
+        prompt: "What does this Rust function return for n = 5?\n\n\
+                 fn f(n: u32) -> u32 { (1..=n).product() }\n\n\
+                 Reply with the number only.",
+        expect: Expect::Text("120"),
+    },
+    Probe {
+        name: "tool_read_file",
+        prompt: "Open src/config.rs so we can see how the config is loaded.",
+        expect: Expect::Tool {
+            tool: "read_file",
+            arg: "path",
+            value: "src/config.rs",
+        },
+    },
+    Probe {
+        name: "tool_search",
+        prompt: "Find where the function parse_manifest is defined in this repository.",
+        expect: Expect::Tool {
+            tool: "search_files",
+            arg: "pattern",
+            value: "parse_manifest",
+        },
+    },
+];
+
+fn probe_tools() -> Vec<ToolDefinition> {
+    vec![
+        ToolDefinition::new(
+            "read_file",
+            "Read a file from the repository.",
+            serde_json::json!({
+                "type": "object",
+                "properties": {"path": {"type": "string", "description": "File path"}},
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "search_files",
+            "Search file contents for a regex pattern.",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {"type": "string", "description": "Regex to search for"},
+                    "path": {"type": "string", "description": "Directory to search"}
+                },
+                "required": ["pattern"]
+            }),
+        ),
+    ]
+}
+
+/// A backend to benchmark.
+pub struct BenchTarget {
+    /// Where it came from: `router`, `rlm`, `router, rlm` or `candidate`.
+    pub role: String,
+    pub provider: String,
+    pub model: String,
+    /// `None` when the provider has no credentials.
+    pub backend: Option<Arc<dyn LLMBackend>>,
+}
+
+/// Results for one backend.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendResult {
+    pub role: String,
+    pub provider: String,
+    pub model: String,
+    /// False when the backend couldn't be created (missing credentials).
+    pub configured: bool,
+    /// Probe calls made.
+    pub calls: usize,
+    /// Calls that returned an error.
+    pub errors: usize,
+    /// Share of completion probes answered correctly.
+    pub completion_pass_rate: f64,
+    /// Share of tool probes with the right tool and argument.
+    pub tool_fidelity: f64,
+    pub p50_latency_ms: u64,
+    pub p95_latency_ms: u64,
+    /// Output tokens per second of call latency.
+    pub output_tokens_per_sec: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// USD, when `[pricing]` has the model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    /// First few failed probes, as `probe: reason`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+}
+
+/// Output of `muninn bench backends`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    /// Repetitions of each probe.
+    pub runs: usize,
+    pub probes: Vec<&'static str>,
+    pub backends: Vec<BackendResult>,
+}
+
+/// Run every probe `runs` times against each target, one call at a time.
+pub async fn run(
+    targets: &[BenchTarget],
+    runs: usize,
+    pricing: &HashMap<String, ModelPricing>,
+) -> BenchReport {
+    let mut backends = Vec::new();
+    for target in targets {
+        backends.push(bench_target(target, runs, pricing.get(&target.model)).await);
+    }
+    BenchReport {
+        runs,
+        probes: PROBES.iter().map(|p| p.name).collect(),
+        backends,
+    }
+}
+
+async fn bench_target(
+    target: &BenchTarget,
+    runs: usize,
+    pricing: Option<&ModelPricing>,
+) -> BackendResult {
+    let mut result = BackendResult {
+        role: target.role.clone(),
+        provider: target.provider.clone(),
+        model: target.model.clone(),
+        configured: target.backend.is_some(),
+        calls: 0,
+        errors: 0,
+        completion_pass_rate: 0.0,
+        tool_fidelity: 0.0,
+        p50_latency_ms: 0,
+        p95_latency_ms: 0,
+        output_tokens_per_sec: 0.0,
+        input_tokens: 0,
+        output_tokens: 0,
+        cost_usd: None,
+        failures: Vec::new(),
+    };
+    let Some(backend) = &target.backend else {
+        return result;
+    };
+
+    let mut latencies = Vec::new();
+    let (mut completion, mut completion_ok) = (0usize, 0usize);
+    let (mut tool, mut tool_ok) = (0usize, 0usize);
+    for _ in 0..runs {
+        for probe in PROBES {
+            let mut request = CompletionRequest::new(
+                &target.model,
+                vec![Message::user(probe.prompt)],
+                PROBE_MAX_TOKENS,
+            );
+            request.temperature = Some(0.0);
+            let is_tool = matches!(probe.expect, Expect::Tool { .. });
+            if is_tool {
+                request = request.with_tools(probe_tools());
+                tool += 1;
+            } else {
+                completion += 1;
+            }
+
+            result.calls += 1;
+            let start = Instant::now();
+            let outcome = backend.complete(request).await;
+            let elapsed = start.elapsed().as_millis() as u64;
+            let failure = match outcome {
+                Ok(response) => {
+                    latencies.push(elapsed);
+                    result.input_tokens += u64::from(response.usage.input_tokens);
+                    result.output_tokens += u64::from(response.usage.output_tokens);
+                    let failure = check(&probe.expect, &response);
+                    if failure.is_none() {
+                        if is_tool {
+                            tool_ok += 1;
+                        } else {
+                            completion_ok += 1;
+                        }
+                    }
+                    failure
+                }
+                Err(e) => {
+                    result.errors += 1;
+                    Some(e.to_string())
+                }
+            };
+            if let Some(reason) = failure
+                && result.failures.len() < MAX_FAILURES
+            {
+                result.failures.push(format!("{}: {}", probe.name, reason));
+            }
+        }
+    }
+
+    result.completion_pass_rate = ratio(completion_ok, completion);
+    result.tool_fidelity = ratio(tool_ok, tool);
+    latencies.sort_unstable();
+    if let Some(&p50) = latencies.get((latencies.len().max(1) - 1) / 2) {
+        result.p50_latency_ms = p50;
+        result.p95_latency_ms = latencies[(latencies.len() - 1) * 95 / 100];
+    }
+    let total_ms: u64 = latencies.iter().sum();
+    if total_ms > 0 {
+        result.output_tokens_per_sec = result.output_tokens as f64 * 1000.0 / total_ms as f64;
+    }
+    result.cost_usd = pricing.map(|p| p.cost(result.input_tokens, result.output_tokens));
+    result
+}
+
+/// `None` when the response meets `expect`, else why not.
+fn check(expect: &Expect, response: &muninn_rlm::CompletionResponse) -> Option<String> {
+    match expect {
+        Expect::Text(expected) => {
+            let text = response.text();
+            if text.to_lowercase().contains(&expected.to_lowercase()) {
+                None
+            } else {
+                Some(format!(
+                    "expected {:?}, got {:?}",
+                    expected,
+                    text.chars().take(80).collect::<String>()
+                ))
+            }
+        }
+        Expect::Tool { tool, arg, value } => {
+            let uses = response.tool_uses();
+            let Some(call) = uses.first() else {
+                return Some(format!("expected a {} call, got text", tool));
+            };
+            if call.name != *tool {
+                return Some(format!("expected {}, called {}", tool, call.name));
+            }
+            match call.input.get(*arg).and_then(|v| v.as_str()) {
+                Some(got) if got.contains(value) => None,
+                got => Some(format!("{}.{} = {:?}", tool, arg, got)),
+            }
+        }
+    }
+}
+
+fn ratio(n: usize, of: usize) -> f64 {
+    if of == 0 { 0.0 } else { n as f64 / of as f64 }
+}
+
+impl BenchReport {
+    /// Render the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Backend benchmark\n\n{} probes × {} runs per backend.\n\n\
+             | Role | Model | Provider | Completions | Tool fidelity | p50 ms | p95 ms | Out tok/s | Errors | Cost |\n\
+             |---|---|---|---|---|---|---|---|---|---|\n",
+            self.probes.len(),
+            self.runs
+        );
+        for b in &self.backends {
+            if !b.configured {
+                out.push_str(&format!(
+                    "| {} | `{}` | {} | not configured | | | | | | |\n",
+                    b.role, b.model, b.provider
+                ));
+                continue;
+            }
+            out.push_str(&format!(
+                "| {} | `{}` | {} | {:.0}% | {:.0}% | {} | {} | {:.1} | {}/{} | {} |\n",
+                b.role,
+                b.model,
+                b.provider,
+                b.completion_pass_rate * 100.0,
+                b.tool_fidelity * 100.0,
+                b.p50_latency_ms,
+                b.p95_latency_ms,
+                b.output_tokens_per_sec,
+                b.errors,
+                b.calls,
+                b.cost_usd.map_or("-".to_string(), |c| format!("${:.4}", c))
+            ));
+        }
+        let failing: Vec<&BackendResult> = self
+            .backends
+            .iter()
+            .filter(|b| !b.failures.is_empty())
+            .collect();
+        if !failing.is_empty() {
+            out.push_str("\n## Failures\n\n");
+            for b in failing {
+                for failure in &b.failures {
+                    out.push_str(&format!("- `{}` {}\n", b.model, failure));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use muninn_rlm::{CompletionResponse, ContentBlock, MockBackend, StopReason, Usage};
+
+    fn text(text: &str) -> CompletionResponse {
+        CompletionResponse::new(
+            "msg",
+            "m",
+            vec![ContentBlock::Text {
+                text: text.to_string(),
+                cache_control: None,
+            }],
+            StopReason::EndTurn,
+            Usage::new(10, 4),
+        )
+    }
+
+    fn tool_call(name: &str, input: serde_json::Value) -> CompletionResponse {
+        CompletionResponse::new(
+            "msg",
+            "m",
+            vec![ContentBlock::ToolUse {
+                id: "t1".to_string(),
+                name: name.to_string(),
+                input,
+                cache_control: None,
+            }],
+            StopReason::ToolUse,
+            Usage::new(10, 4),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_scores_probes_and_prices_the_run() {
+        // One run, answered in probe order: echo, arithmetic, code_reading,
+        // tool_read_file, tool_search
+        let backend = MockBackend::new(vec![
+            text("Pong."),
+            text("It is 391"),
+            text("24"),
+            tool_call("read_file", serde_json::json!({"path": "./src/config.rs"})),
+            tool_call("read_file", serde_json::json!({"path": "src/lib.rs"})),
+        ]);
+        let targets = [
+            BenchTarget {
+                role: "rlm".to_string(),
+                provider: "groq".to_string(),
+                model: "m".to_string(),
+                backend: Some(Arc::new(backend)),
+            },
+            BenchTarget {
+                role: "candidate".to_string(),
+                provider: "anthropic".to_string(),
+                model: "missing".to_string(),
+                backend: None,
+            },
+        ];
+        let pricing = HashMap::from([(
+            "m".to_string(),
+            ModelPricing {
+                input_per_mtok: 1.0,
+                output_per_mtok: 2.0,
+            },
+        )]);
+
+        let report = run(&targets, 1, &pricing).await;
+        let result = &report.backends[0];
+        assert_eq!((result.calls, result.errors), (5, 0));
+        assert!((result.completion_pass_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(result.tool_fidelity, 0.5);
+        assert_eq!((result.input_tokens, result.output_tokens), (50, 20));
+        assert_eq!(result.cost_usd, Some(90.0 / 1_000_000.0));
+        assert_eq!(
+            result.failures,
+            [
+                "code_reading: expected \"120\", got \"24\"",
+                "tool_search: expected search_files, called read_file"
+            ]
+        );
+        assert!(!report.backends[1].configured);
+
+        let markdown = report.to_markdown();
+        assert!(
+            markdown.contains("| rlm | `m` | groq | 67% | 50% |"),
+            "{markdown}"
+        );
+        assert!(markdown.contains("| candidate | `missing` | anthropic | not configured |"));
+    }
+
+    #[tokio::test]
+    async fn test_backend_errors_are_counted() {
+        let targets = [BenchTarget {
+            role: "router".to_string(),
+            provider: "groq".to_string(),
+            model: "m".to_string(),
+            backend: Some(Arc::new(MockBackend::new(vec![]))),
+        }];
+        let report = run(&targets, 2, &HashMap::new()).await;
+        let result = &report.backends[0];
+        assert_eq!((result.calls, result.errors), (10, 10));
+        assert_eq!(result.failures.len(), MAX_FAILURES);
+        assert_eq!(result.cost_usd, None);
+        assert_eq!(result.p50_latency_ms, 0);
+    }
+}
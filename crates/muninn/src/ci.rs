@@ -0,0 +1,471 @@
+//! `muninn ci` implementation: headless entry points for CI pipelines.
+//!
+//! `muninn ci ask` answers a question about the repository and
+//! `muninn ci review` reviews the diff between two git refs. Both run the
+//! RLM against an API-key backend only (no OAuth flow, no prompts), under
+//! a strict token/tool-call budget and a wall-clock timeout, and emit a
+//! JSON [`CiArtifact`]. The process exit code is the artifact's status:
+//!
+//! | code | status            | meaning                                   |
+//! |------|-------------------|-------------------------------------------|
+//! | 0    | `ok`              | answered; review passed                   |
+//! | 1    | `findings`        | review verdict was `fail`                 |
+//! | 2    | `error`           | backend, git or engine failure            |
+//! | 3    | `budget_exceeded` | token/tool-call/depth budget ran out      |
+//! | 4    | `timeout`         | wall-clock timeout hit                    |
+//! | 5    | `not_configured`  | no RLM backend credentials                |
+//!
+//! [`CiArtifact::findings`] reads the review's `path:line — problem —
+//! severity` lines back as findings, for SARIF output.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use muninn_core::{MuninnCoreError, MuninnEngine};
+use muninn_graph::{Finding, FindingLevel, Findings, Location};
+use serde::Serialize;
+
+/// Diff characters sent to the model; longer diffs are truncated.
+const MAX_DIFF_CHARS: usize = 60_000;
+
+/// Output tokens allowed for the final answer.
+const ANSWER_MAX_TOKENS: u32 = 4096;
+
+/// Outcome of a CI run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CiStatus {
+    Ok,
+    Findings,
+    Error,
+    BudgetExceeded,
+    Timeout,
+    NotConfigured,
+}
+
+impl CiStatus {
+    /// Process exit code for this status.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            CiStatus::Ok => 0,
+            CiStatus::Findings => 1,
+            CiStatus::Error => 2,
+            CiStatus::BudgetExceeded => 3,
+            CiStatus::Timeout => 4,
+            CiStatus::NotConfigured => 5,
+        }
+    }
+}
+
+/// Limits for one CI run.
+#[derive(Debug, Clone, Serialize)]
+pub struct CiBudget {
+    pub max_tokens: u64,
+    pub max_tool_calls: u32,
+    pub timeout_secs: u64,
+}
+
+/// Token usage reported by the engine.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CiUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Machine-readable result of `muninn ci ask` / `muninn ci review`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CiArtifact {
+    /// `ask` or `review`.
+    pub command: String,
+    pub status: CiStatus,
+    pub exit_code: i32,
+    /// The question, or the `base...head` range reviewed.
+    pub subject: String,
+    /// Files changed in the reviewed range.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed_files: Vec<String>,
+    /// `pass` or `fail`, for reviews that stated one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub model: String,
+    pub duration_ms: u64,
+    pub usage: CiUsage,
+    pub budget: CiBudget,
+}
+
+impl CiArtifact {
+    fn new(command: &str, subject: String, model: &str, budget: &CiBudget) -> Self {
+        Self {
+            command: command.to_string(),
+            status: CiStatus::Ok,
+            exit_code: 0,
+            subject,
+            changed_files: Vec::new(),
+            verdict: None,
+            answer: None,
+            error: None,
+            model: model.to_string(),
+            duration_ms: 0,
+            usage: CiUsage::default(),
+            budget: budget.clone(),
+        }
+    }
+
+    fn fail(&mut self, status: CiStatus, error: impl Into<String>) {
+        self.status = status;
+        self.exit_code = status.exit_code();
+        self.error = Some(error.into());
+    }
+
+    /// The review's findings: one `review` result per answer line shaped
+    /// like `path:line — problem — severity`. High severity is an error,
+    /// medium a warning, low a note.
+    pub fn findings(&self) -> Findings {
+        let mut findings = Findings::new();
+        findings.rule("review", "Problem found by the RLM review");
+        for line in self.answer.as_deref().unwrap_or_default().lines() {
+            if let Some(finding) = parse_finding(line) {
+                findings.push(finding);
+            }
+        }
+        findings
+    }
+}
+
+/// Answer `question` about the repository.
+pub async fn ask(
+    engine: Option<Arc<dyn MuninnEngine>>,
+    model: &str,
+    question: &str,
+    budget: &CiBudget,
+) -> CiArtifact {
+    let mut artifact = CiArtifact::new("ask", question.to_string(), model, budget);
+    run(&mut artifact, engine, question.to_string()).await;
+    artifact
+}
+
+/// Review the changes between `base` and `head` in the repository at `root`.
+pub async fn review(
+    engine: Option<Arc<dyn MuninnEngine>>,
+    model: &str,
+    root: &Path,
+    base: &str,
+    head: &str,
+    budget: &CiBudget,
+) -> CiArtifact {
+    let range = format!("{}...{}", base, head);
+    match git_diff(root, &range) {
+        Ok((files, diff)) => review_diff(engine, model, &range, None, files, diff, budget).await,
+        Err(e) => {
+            let mut artifact = CiArtifact::new("review", range, model, budget);
+            artifact.fail(CiStatus::Error, e.to_string());
+            artifact
+        }
+    }
+}
+
+/// Review an already-fetched unified `diff` of `subject`, optionally
+/// answering a reviewer's `question` about it.
+pub async fn review_diff(
+    engine: Option<Arc<dyn MuninnEngine>>,
+    model: &str,
+    subject: &str,
+    question: Option<&str>,
+    files: Vec<String>,
+    diff: String,
+    budget: &CiBudget,
+) -> CiArtifact {
+    let mut artifact = CiArtifact::new("review", subject.to_string(), model, budget);
+    if files.is_empty() {
+        artifact.answer = Some("No changes.".to_string());
+        artifact.verdict = Some("pass".to_string());
+        return artifact;
+    }
+    artifact.changed_files = files;
+    let diff = truncate_diff(diff);
+    run(
+        &mut artifact,
+        engine,
+        review_prompt(subject, question, &diff),
+    )
+    .await;
+
+    if artifact.status == CiStatus::Ok {
+        artifact.verdict = artifact.answer.as_deref().and_then(parse_verdict);
+        if artifact.verdict.as_deref() == Some("fail") {
+            artifact.status = CiStatus::Findings;
+            artifact.exit_code = CiStatus::Findings.exit_code();
+        }
+    }
+    artifact
+}
+
+/// Run one budgeted exploration and record the outcome on `artifact`.
+async fn run(artifact: &mut CiArtifact, engine: Option<Arc<dyn MuninnEngine>>, prompt: String) {
+    let Some(engine) = engine else {
+        artifact.fail(
+            CiStatus::NotConfigured,
+            "no RLM backend configured; set an API key for the [rlm] provider",
+        );
+        return;
+    };
+
+    let budget = muninn_rlm::BudgetConfig {
+        max_tokens: Some(artifact.budget.max_tokens),
+        max_tool_calls: Some(artifact.budget.max_tool_calls),
+        max_duration_secs: Some(artifact.budget.timeout_secs),
+        ..Default::default()
+    };
+    let request = muninn_rlm::CompletionRequest::new(
+        &artifact.model,
+        vec![muninn_rlm::Message::user(prompt)],
+        ANSWER_MAX_TOKENS,
+    )
+    .with_muninn(muninn_rlm::MuninnConfig::recursive().with_budget(budget));
+
+    let start = Instant::now();
+    let timeout = Duration::from_secs(artifact.budget.timeout_secs);
+    let result = tokio::time::timeout(timeout, engine.complete(request)).await;
+    artifact.duration_ms = start.elapsed().as_millis() as u64;
+
+    match result {
+        Err(_) => artifact.fail(
+            CiStatus::Timeout,
+            format!("timed out after {}s", artifact.budget.timeout_secs),
+        ),
+        Ok(Err(MuninnCoreError::BudgetExceeded(detail))) => {
+            artifact.fail(CiStatus::BudgetExceeded, detail)
+        }
+        Ok(Err(e)) => artifact.fail(CiStatus::Error, e.to_string()),
+        Ok(Ok(response)) => {
+            artifact.usage = CiUsage {
+                input_tokens: u64::from(response.usage.input_tokens),
+                output_tokens: u64::from(response.usage.output_tokens),
+            };
+            let text = response.text();
+            let text = text.trim();
+            artifact.answer =
+                Some(super::extract_final_capture(text).unwrap_or_else(|| text.to_string()));
+        }
+    }
+}
+
+/// Changed files and the (possibly truncated) unified diff for `range`.
+fn git_diff(root: &Path, range: &str) -> Result<(Vec<String>, String)> {
+    let git = |args: &[&str]| -> Result<String> {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .map_err(|e| anyhow!("failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let files = git(&["diff", "--name-only", range])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let diff = git(&["diff", "--unified=3", range])?;
+    Ok((files, diff))
+}
+
+/// Cut `diff` to [`MAX_DIFF_CHARS`] on a char boundary.
+fn truncate_diff(mut diff: String) -> String {
+    if diff.len() > MAX_DIFF_CHARS {
+        let mut cut = MAX_DIFF_CHARS;
+        while !diff.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        diff.truncate(cut);
+        diff.push_str("\n[diff truncated]\n");
+    }
+    diff
+}
+
+/// Prompt for the review pass. With a `question`, the model answers it
+/// about the change instead of producing a general review.
+fn review_prompt(subject: &str, question: Option<&str>, diff: &str) -> String {
+    let task = match question {
+        Some(question) => format!(
+            "A reviewer asked about the change {subject} below:\n\n> {question}\n\n\
+             Explore the codebase to answer it. Cite every claim as \
+             `path:line`. Flag any high-severity problem you find on the way."
+        ),
+        None => format!(
+            "Review the change {subject} below for a CI check. Explore the \
+             codebase to understand the code it touches and its callers.\n\
+             \n\
+             Report real problems only: bugs, broken callers, missing error \
+             handling, security issues, and behaviour changes the diff doesn't \
+             account for. Skip style nits. Answer with a Markdown list, one \
+             finding per line, formatted as `path:line — problem — \
+             high|medium|low`, or \"No findings.\"."
+        ),
+    };
+    format!(
+        "{task}\n\
+         \n\
+         End with a line `VERDICT: fail` if there is any high-severity \
+         problem, otherwise `VERDICT: pass`.\n\
+         \n\
+         ```diff\n{diff}```"
+    )
+}
+
+/// Strip the trailing `VERDICT:` line from an answer before showing it to
+/// people.
+pub fn strip_verdict(answer: &str) -> &str {
+    match answer.trim_end().rsplit_once('\n') {
+        Some((body, last)) if last.trim().trim_matches('*').starts_with("VERDICT:") => {
+            body.trim_end()
+        }
+        _ => answer.trim_end(),
+    }
+}
+
+/// The `VERDICT: pass|fail` line of a review answer, if present.
+fn parse_verdict(answer: &str) -> Option<String> {
+    answer.lines().rev().find_map(|line| {
+        let rest = line
+            .trim()
+            .trim_matches('*')
+            .strip_prefix("VERDICT:")?
+            .trim()
+            .trim_matches('*')
+            .to_ascii_lowercase();
+        matches!(rest.as_str(), "pass" | "fail").then_some(rest)
+    })
+}
+
+/// A `path:line — problem — high|medium|low` list item.
+fn parse_finding(line: &str) -> Option<Finding> {
+    let line = line.trim().trim_start_matches(['-', '*']).trim();
+    let mut parts = line.split(" — ");
+    let site = parts.next()?.trim().trim_matches('`');
+    let rest: Vec<&str> = parts.map(str::trim).collect();
+    let (severity, problem) = rest.split_last()?;
+    let level = match severity
+        .trim_matches(['*', '.'])
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "high" => FindingLevel::Error,
+        "medium" => FindingLevel::Warning,
+        "low" => FindingLevel::Note,
+        _ => return None,
+    };
+    let location = Location::parse(site);
+    if problem.is_empty() || location.line == 0 {
+        return None;
+    }
+    Some(Finding::new("review", level, problem.join(" — ")).at(location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget() -> CiBudget {
+        CiBudget {
+            max_tokens: 1000,
+            max_tool_calls: 5,
+            timeout_secs: 10,
+        }
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        assert_eq!(
+            parse_verdict("- a.rs:3 — bug — high\n\nVERDICT: fail"),
+            Some("fail".to_string())
+        );
+        assert_eq!(
+            parse_verdict("No findings.\n**VERDICT: PASS**"),
+            Some("pass".to_string())
+        );
+        assert_eq!(parse_verdict("VERDICT: maybe"), None);
+        assert_eq!(parse_verdict("No findings."), None);
+    }
+
+    #[test]
+    fn test_strip_verdict_and_truncate() {
+        assert_eq!(
+            strip_verdict("- a.rs:3 — ok\n\nVERDICT: pass\n"),
+            "- a.rs:3 — ok"
+        );
+        assert_eq!(strip_verdict("No findings."), "No findings.");
+
+        let diff = truncate_diff("é".repeat(MAX_DIFF_CHARS));
+        assert!(diff.ends_with("[diff truncated]\n"));
+        assert!(diff.len() < MAX_DIFF_CHARS + 32);
+        assert!(review_prompt("#1", Some("why?"), &diff).contains("> why?"));
+    }
+
+    #[test]
+    fn test_review_findings() {
+        let mut artifact = CiArtifact::new("review", "main...HEAD".into(), "m", &budget());
+        artifact.answer = Some(
+            "- `src/a.rs:3` — unwrap on user input — high\n\
+             - src/b.rs:10 — retries forever — it loops — **medium**\n\
+             - a general remark\n\
+             - src/c.rs — no line — low\n\
+             \n\
+             VERDICT: fail"
+                .to_string(),
+        );
+        let findings = artifact.findings();
+        let results: Vec<_> = findings
+            .results
+            .iter()
+            .map(|f| (f.level, f.message.as_str(), f.location.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                (
+                    FindingLevel::Error,
+                    "unwrap on user input",
+                    Location::new("src/a.rs", 3)
+                ),
+                (
+                    FindingLevel::Warning,
+                    "retries forever — it loops",
+                    Location::new("src/b.rs", 10)
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ask_without_backend_is_not_configured() {
+        let artifact = ask(None, "m", "what?", &budget()).await;
+        assert_eq!(artifact.status, CiStatus::NotConfigured);
+        assert_eq!(artifact.exit_code, 5);
+        let json = serde_json::to_value(&artifact).unwrap();
+        assert_eq!(json["status"], "not_configured");
+        assert_eq!(json["budget"]["max_tool_calls"], 5);
+        assert!(json.get("answer").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_review_outside_git_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = review(None, "m", dir.path(), "main", "HEAD", &budget()).await;
+        assert_eq!(artifact.status, CiStatus::Error);
+        assert_eq!(artifact.subject, "main...HEAD");
+        assert!(artifact.error.unwrap().contains("git"));
+    }
+}
@@ -0,0 +1,585 @@
+//! `muninn github` implementation: answer questions mentioned on PRs.
+//!
+//! Mentioning the configured handle in a pull request comment
+//! (`@muninn what calls this outside the parser?`) runs the `muninn ci`
+//! review pipeline over the PR's diff with the text after the mention as
+//! the question, and posts the cited answer back as a PR review comment.
+//! A bare mention asks for a general review. Only mentions from the
+//! repository's owners, members and collaborators, or from the logins in
+//! `[github] allowed_users`, are answered, as each answer is a paid run.
+//!
+//! Two ways to receive mentions:
+//!
+//! - `muninn github event` handles one `issue_comment` webhook payload,
+//!   by default `$GITHUB_EVENT_PATH`, so a GitHub Actions workflow on
+//!   `issue_comment` can drive it without a running server.
+//! - `muninn github watch` polls the repository's issue comments and keeps
+//!   its position in `.muninn/github.json`.
+//!
+//! Auth is a token from the `[github] token_env` variable (`GITHUB_TOKEN`
+//! by default), which needs `pull_requests: write`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use muninn_core::MuninnEngine;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::ci::{self, CiArtifact, CiBudget, CiStatus};
+use crate::config::GithubConfig;
+
+/// Watch-mode position file inside `.muninn/`.
+pub const STATE_FILE: &str = "github.json";
+
+/// Marker on every comment muninn posts, so it never answers itself.
+const COMMENT_MARKER: &str = "<!-- muninn -->";
+
+/// A PR comment that mentions the bot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    pub pr: u64,
+    pub comment_id: u64,
+    pub author: String,
+    /// Text after the mention; `None` asks for a general review.
+    pub question: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    login: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueComment {
+    id: u64,
+    #[serde(default)]
+    body: String,
+    user: User,
+    #[serde(default)]
+    author_association: String,
+    issue_url: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRef {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    head: PullRef,
+    base: PullRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullFile {
+    filename: String,
+}
+
+/// Minimal GitHub REST client for one repository.
+pub struct GithubClient {
+    http: reqwest::Client,
+    api_url: String,
+    repo: String,
+    token: String,
+}
+
+impl GithubClient {
+    /// Build a client from `[github]`, failing if the repo or token is missing.
+    pub fn from_config(config: &GithubConfig) -> Result<Self> {
+        let repo = config
+            .resolved_repo()
+            .ok_or_else(|| anyhow!("no repository: set [github] repo or GITHUB_REPOSITORY"))?;
+        let token = config
+            .resolved_token()
+            .ok_or_else(|| anyhow!("no GitHub token in ${}", config.token_env))?;
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("muninn/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            http,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            repo,
+            token,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.request_url(
+            method,
+            &format!("{}/repos/{}{}", self.api_url, self.repo, path),
+        )
+    }
+
+    fn request_url(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, url)
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("GitHub API {}: {}", status, body.trim()));
+        }
+        Ok(response)
+    }
+
+    /// Every page of a list request, following the `Link: rel="next"`
+    /// headers.
+    async fn get_all<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut request = request;
+        loop {
+            let response = self.send(request).await?;
+            let next = next_page(response.headers());
+            items.extend(response.json::<Vec<T>>().await?);
+            match next {
+                Some(url)
+                    if url
+                        .strip_prefix(&self.api_url)
+                        .is_some_and(|rest| rest.starts_with('/')) =>
+                {
+                    request = self.request_url(reqwest::Method::GET, &url);
+                }
+                Some(url) => return Err(anyhow!("GitHub pagination left the API: {}", url)),
+                None => return Ok(items),
+            }
+        }
+    }
+
+    /// Issue and PR comments created or edited since `since`, oldest first.
+    async fn comments_since(&self, since: DateTime<Utc>) -> Result<Vec<IssueComment>> {
+        let since = since.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let request = self
+            .request(reqwest::Method::GET, "/issues/comments")
+            .query(&[
+                ("since", since.as_str()),
+                ("sort", "created"),
+                ("direction", "asc"),
+                ("per_page", "100"),
+            ]);
+        self.get_all(request).await
+    }
+
+    async fn pull_request(&self, pr: u64) -> Result<PullRequest> {
+        let request = self.request(reqwest::Method::GET, &format!("/pulls/{}", pr));
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    async fn pull_files(&self, pr: u64) -> Result<Vec<String>> {
+        let request = self
+            .request(reqwest::Method::GET, &format!("/pulls/{}/files", pr))
+            .query(&[("per_page", "100")]);
+        let files: Vec<PullFile> = self.get_all(request).await?;
+        Ok(files.into_iter().map(|f| f.filename).collect())
+    }
+
+    async fn pull_diff(&self, pr: u64) -> Result<String> {
+        let request = self
+            .request(reqwest::Method::GET, &format!("/pulls/{}", pr))
+            .header("Accept", "application/vnd.github.diff");
+        Ok(self.send(request).await?.text().await?)
+    }
+
+    async fn post_review(&self, pr: u64, commit_id: &str, body: &str) -> Result<()> {
+        let request = self
+            .request(reqwest::Method::POST, &format!("/pulls/{}/reviews", pr))
+            .json(&serde_json::json!({
+                "commit_id": commit_id,
+                "body": body,
+                "event": "COMMENT",
+            }));
+        self.send(request).await?;
+        Ok(())
+    }
+}
+
+/// The question following `mention` in a comment body.
+///
+/// Returns `None` when the body doesn't mention the bot, `Some(None)` for
+/// a bare mention and `Some(Some(question))` otherwise.
+pub fn extract_question(body: &str, mention: &str) -> Option<Option<String>> {
+    if body.contains(COMMENT_MARKER) {
+        return None;
+    }
+    let lower = body.to_ascii_lowercase();
+    let needle = mention.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(pos) = lower[from..].find(&needle).map(|p| p + from) {
+        let end = pos + needle.len();
+        let boundary = lower[end..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '-' || c == '_'));
+        if boundary {
+            let question = body[end..]
+                .trim_start_matches([',', ':'])
+                .trim()
+                .to_string();
+            return Some((!question.is_empty()).then_some(question));
+        }
+        from = end;
+    }
+    None
+}
+
+/// The `rel="next"` URL of a `Link` response header.
+fn next_page(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == "rel=\"next\"")
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// PR number from an issue API URL (`.../issues/42`).
+fn issue_number(issue_url: &str) -> Option<u64> {
+    issue_url.rsplit('/').next()?.parse().ok()
+}
+
+/// The mention in an `issue_comment` webhook payload, if it is a newly
+/// created PR comment from a human `config` allows that mentions the bot.
+pub fn mention_from_event(payload: &serde_json::Value, config: &GithubConfig) -> Option<Mention> {
+    if payload["action"] != "created" || payload["issue"]["pull_request"].is_null() {
+        return None;
+    }
+    let comment = &payload["comment"];
+    if comment["user"]["type"] == "Bot" {
+        return None;
+    }
+    let author = comment["user"]["login"].as_str()?;
+    let association = comment["author_association"].as_str().unwrap_or_default();
+    let question = extract_question(comment["body"].as_str()?, &config.mention)?;
+    if !config.allows(author, association) {
+        info!(
+            author,
+            association, "Ignoring mention from a user without access"
+        );
+        return None;
+    }
+    Some(Mention {
+        pr: payload["issue"]["number"].as_u64()?,
+        comment_id: comment["id"].as_u64()?,
+        author: author.to_string(),
+        question,
+    })
+}
+
+/// Review comment body for an answered mention.
+pub fn render_comment(mention: &Mention, artifact: &CiArtifact) -> String {
+    let mut body = String::from(COMMENT_MARKER);
+    body.push('\n');
+    if let Some(question) = &mention.question {
+        for line in question.lines() {
+            body.push_str(&format!("> {}\n", line));
+        }
+        body.push('\n');
+    }
+    body.push_str(&format!("@{} ", mention.author));
+    match (&artifact.status, &artifact.answer) {
+        (CiStatus::Ok | CiStatus::Findings, Some(answer)) => {
+            body.push_str(ci::strip_verdict(answer));
+        }
+        _ => body.push_str(&format!(
+            "I couldn't answer this ({}).",
+            artifact.error.as_deref().unwrap_or("no answer")
+        )),
+    }
+    body.push_str(&format!(
+        "\n\n<sub>muninn · {} · {} in / {} out tokens · {:.1}s</sub>\n",
+        artifact.model,
+        artifact.usage.input_tokens,
+        artifact.usage.output_tokens,
+        artifact.duration_ms as f64 / 1000.0
+    ));
+    body
+}
+
+/// Answer one mention on its PR and post the review comment.
+pub async fn answer(
+    client: &GithubClient,
+    engine: Option<Arc<dyn MuninnEngine>>,
+    model: &str,
+    mention: &Mention,
+    budget: &CiBudget,
+) -> Result<CiArtifact> {
+    let pr = client.pull_request(mention.pr).await?;
+    let files = client.pull_files(mention.pr).await?;
+    let diff = client.pull_diff(mention.pr).await?;
+    let subject = format!(
+        "#{} ({}...{})",
+        mention.pr,
+        short(&pr.base.sha),
+        short(&pr.head.sha)
+    );
+    info!(pr = mention.pr, files = files.len(), "Answering PR mention");
+
+    let artifact = ci::review_diff(
+        engine,
+        model,
+        &subject,
+        mention.question.as_deref(),
+        files,
+        diff,
+        budget,
+    )
+    .await;
+    client
+        .post_review(
+            mention.pr,
+            &pr.head.sha,
+            &render_comment(mention, &artifact),
+        )
+        .await
+        .context("posting review comment")?;
+    Ok(artifact)
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// Position of `muninn github watch` in the comment stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchState {
+    /// Creation time of the newest comment seen.
+    pub since: DateTime<Utc>,
+    /// Highest comment id handled; ids only grow.
+    pub last_comment_id: u64,
+}
+
+impl WatchState {
+    /// Load the state in `muninn_dir`, starting from now if there is none,
+    /// so a first run doesn't answer old mentions.
+    pub fn load(muninn_dir: &Path) -> Self {
+        std::fs::read_to_string(state_path(muninn_dir))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_else(|| Self {
+                since: Utc::now(),
+                last_comment_id: 0,
+            })
+    }
+
+    /// Persist the state into `muninn_dir`.
+    pub fn save(&self, muninn_dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(muninn_dir)?;
+        std::fs::write(state_path(muninn_dir), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn state_path(muninn_dir: &Path) -> PathBuf {
+    muninn_dir.join(STATE_FILE)
+}
+
+/// One poll: answer every new mention and advance `state`.
+pub async fn poll_once(
+    client: &GithubClient,
+    engine: Option<Arc<dyn MuninnEngine>>,
+    model: &str,
+    config: &GithubConfig,
+    budget: &CiBudget,
+    state: &mut WatchState,
+) -> Result<usize> {
+    let mut answered = 0;
+    for comment in client.comments_since(state.since).await? {
+        if comment.id <= state.last_comment_id {
+            continue;
+        }
+        state.last_comment_id = comment.id;
+        state.since = state.since.max(comment.created_at);
+        if comment.user.kind == "Bot" {
+            continue;
+        }
+        let (Some(question), Some(pr)) = (
+            extract_question(&comment.body, &config.mention),
+            issue_number(&comment.issue_url),
+        ) else {
+            continue;
+        };
+        if !config.allows(&comment.user.login, &comment.author_association) {
+            info!(
+                pr,
+                author = %comment.user.login,
+                association = %comment.author_association,
+                "Ignoring mention from a user without access"
+            );
+            continue;
+        }
+        let mention = Mention {
+            pr,
+            comment_id: comment.id,
+            author: comment.user.login,
+            question,
+        };
+        // Plain issues have no pull request; skip them.
+        if client.pull_request(pr).await.is_err() {
+            continue;
+        }
+        match answer(client, engine.clone(), model, &mention, budget).await {
+            Ok(artifact) => {
+                info!(pr, status = ?artifact.status, "Answered mention");
+                answered += 1;
+            }
+            Err(e) => warn!(pr, error = %e, "Failed to answer mention"),
+        }
+    }
+    Ok(answered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_question() {
+        assert_eq!(
+            extract_question("@Muninn: what calls `parse`?", "@muninn"),
+            Some(Some("what calls `parse`?".to_string()))
+        );
+        assert_eq!(extract_question("thanks @muninn", "@muninn"), Some(None));
+        assert_eq!(extract_question("cc @muninn-bot", "@muninn"), None);
+        assert_eq!(
+            extract_question(&format!("{}\n@muninn hi", COMMENT_MARKER), "@muninn"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_mention_from_event() {
+        let payload = json!({
+            "action": "created",
+            "issue": {"number": 42, "pull_request": {"url": "..."}},
+            "comment": {
+                "id": 7,
+                "body": "@muninn is this safe?",
+                "user": {"login": "ana", "type": "User"},
+                "author_association": "MEMBER",
+            },
+        });
+        let config = GithubConfig::default();
+        assert_eq!(
+            mention_from_event(&payload, &config),
+            Some(Mention {
+                pr: 42,
+                comment_id: 7,
+                author: "ana".to_string(),
+                question: Some("is this safe?".to_string()),
+            })
+        );
+
+        let mut issue = payload.clone();
+        issue["issue"]["pull_request"] = serde_json::Value::Null;
+        assert_eq!(mention_from_event(&issue, &config), None);
+        let mut edited = payload;
+        edited["action"] = json!("edited");
+        assert_eq!(mention_from_event(&edited, &config), None);
+        assert_eq!(
+            issue_number("https://api.github.com/repos/o/r/issues/9"),
+            Some(9)
+        );
+    }
+
+    #[test]
+    fn test_mentions_need_repository_access() {
+        let mut payload = json!({
+            "action": "created",
+            "issue": {"number": 42, "pull_request": {"url": "..."}},
+            "comment": {
+                "id": 7,
+                "body": "@muninn review this",
+                "user": {"login": "drive-by", "type": "User"},
+                "author_association": "NONE",
+            },
+        });
+        let mut config = GithubConfig::default();
+        assert_eq!(mention_from_event(&payload, &config), None);
+        payload["comment"]["author_association"] = json!("CONTRIBUTOR");
+        assert_eq!(mention_from_event(&payload, &config), None);
+        payload["comment"]["author_association"] = json!("COLLABORATOR");
+        assert!(mention_from_event(&payload, &config).is_some());
+
+        payload["comment"]["author_association"] = json!("NONE");
+        config.allowed_users = vec!["Drive-By".to_string()];
+        assert!(mention_from_event(&payload, &config).is_some());
+    }
+
+    #[test]
+    fn test_next_page() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(next_page(&headers), None);
+        headers.insert(
+            reqwest::header::LINK,
+            "<https://api.github.com/repositories/1/issues/comments?page=2>; rel=\"next\", \
+             <https://api.github.com/repositories/1/issues/comments?page=5>; rel=\"last\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            next_page(&headers).as_deref(),
+            Some("https://api.github.com/repositories/1/issues/comments?page=2")
+        );
+        headers.insert(
+            reqwest::header::LINK,
+            "<https://api.github.com/x?page=1>; rel=\"prev\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(next_page(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_render_comment() {
+        let budget = CiBudget {
+            max_tokens: 10,
+            max_tool_calls: 1,
+            timeout_secs: 1,
+        };
+        let mention = Mention {
+            pr: 1,
+            comment_id: 2,
+            author: "ana".to_string(),
+            question: Some("why?".to_string()),
+        };
+        let artifact = ci::ask(None, "m", "why?", &budget).await;
+        let body = render_comment(&mention, &artifact);
+        assert!(body.starts_with(COMMENT_MARKER));
+        assert!(body.contains("> why?\n"));
+        assert!(body.contains("@ana I couldn't answer this (no RLM backend"));
+        assert_eq!(extract_question(&body, "@muninn"), None);
+    }
+
+    #[test]
+    fn test_watch_state_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = WatchState::load(dir.path());
+        assert_eq!(state.last_comment_id, 0);
+        state.last_comment_id = 99;
+        state.save(dir.path()).unwrap();
+        assert_eq!(WatchState::load(dir.path()).last_comment_id, 99);
+    }
+}
@@ -32,6 +32,22 @@
 //!   }
 //! }
 //! ```
+//!
+//! ## Claude Code settings (`muninn init --with-hooks`)
+//!
+//! [`install_hooks`] merges muninn's entries into the project's
+//! `.claude/settings.json` so the plugin step is optional:
+//!
+//! - a `UserPromptSubmit` hook running `muninn hook submit` (the same
+//!   command the muninn-cc plugin runs);
+//! - a `statusLine` running `muninn hook statusline`, which reads the
+//!   proxy's `/statusline` route stats. A status line the user already
+//!   configured is left alone;
+//! - `muninn` in `enabledMcpjsonServers`, so CC uses the `.mcp.json`
+//!   registration without prompting.
+//!
+//! Re-running replaces muninn's hook group in place and leaves every
+//! other hook untouched.
 
 use std::path::{Path, PathBuf};
 
@@ -92,6 +108,111 @@ pub fn muninn_mcp_entry() -> Value {
     })
 }
 
+/// Command the `UserPromptSubmit` hook runs. Mirrors
+/// `plugins/muninn-cc/hooks/user-prompt-submit.sh`: make sure the daemon
+/// is up, then inject context, collapsing every failure to a no-op.
+pub const HOOK_SUBMIT_COMMAND: &str =
+    "muninn daemon ensure >/dev/null 2>&1; muninn hook submit 2>/dev/null || true";
+
+/// Command the `statusLine` entry runs.
+pub const STATUS_LINE_COMMAND: &str = "muninn hook statusline";
+
+/// Seconds CC waits for the submit hook — matches the plugin's hooks.json.
+const HOOK_SUBMIT_TIMEOUT_SECS: u64 = 245;
+
+/// Project-level Claude Code settings file under `repo_root`.
+pub fn claude_settings_path(repo_root: &Path) -> PathBuf {
+    repo_root.join(".claude").join("settings.json")
+}
+
+/// Merge muninn's hook, status line and MCP approval into the Claude
+/// Code settings file at `path`.
+pub fn install_hooks(path: &Path, dry_run: bool) -> Result<InstallOutcome> {
+    let (mut root, existed) = read_or_empty_object(path)?;
+    let original = root.clone();
+    let obj = root
+        .as_object_mut()
+        .expect("read_or_empty_object guarantees an object");
+
+    let hooks = obj
+        .entry("hooks".to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    let submit = hooks
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("`hooks` in {path:?} is not an object"))?
+        .entry("UserPromptSubmit".to_string())
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let groups = submit
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("`hooks.UserPromptSubmit` in {path:?} is not an array"))?;
+    let ours = json!({
+        "matcher": "*",
+        "hooks": [{
+            "type": "command",
+            "command": HOOK_SUBMIT_COMMAND,
+            "timeout": HOOK_SUBMIT_TIMEOUT_SECS
+        }]
+    });
+    match groups.iter().position(is_muninn_hook_group) {
+        Some(index) => groups[index] = ours,
+        None => groups.push(ours),
+    }
+
+    let status_line_is_ours = obj
+        .get("statusLine")
+        .and_then(|s| s.get("command"))
+        .and_then(Value::as_str)
+        .is_none_or(|c| c.contains(STATUS_LINE_COMMAND));
+    if status_line_is_ours {
+        obj.insert(
+            "statusLine".to_string(),
+            json!({"type": "command", "command": STATUS_LINE_COMMAND}),
+        );
+    }
+
+    let enabled = obj
+        .entry("enabledMcpjsonServers".to_string())
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("`enabledMcpjsonServers` in {path:?} is not an array"))?;
+    if !enabled.iter().any(|s| s == MUNINN_SERVER_NAME) {
+        enabled.push(json!(MUNINN_SERVER_NAME));
+    }
+
+    if root == original {
+        return Ok(InstallOutcome::AlreadyPresent {
+            path: path.to_path_buf(),
+        });
+    }
+    if dry_run {
+        return Ok(InstallOutcome::DryRun {
+            path: path.to_path_buf(),
+            action: "merge muninn hooks, statusLine and MCP approval",
+            proposed: root,
+        });
+    }
+    let backup = if existed { backup_file(path)? } else { None };
+    write_json_pretty(path, &root)?;
+    Ok(InstallOutcome::Wrote {
+        path: path.to_path_buf(),
+        backup,
+    })
+}
+
+/// Whether a `UserPromptSubmit` hook group is one muninn wrote.
+fn is_muninn_hook_group(group: &Value) -> bool {
+    group
+        .get("hooks")
+        .and_then(Value::as_array)
+        .is_some_and(|hooks| {
+            hooks.iter().any(|h| {
+                h.get("command")
+                    .and_then(Value::as_str)
+                    .is_some_and(|c| c.contains("muninn hook submit"))
+            })
+        })
+}
+
 /// Result of an install/uninstall operation. Returned so the caller
 /// can render a helpful summary.
 #[derive(Debug, PartialEq)]
@@ -290,6 +411,29 @@ pub fn describe_install(outcome: &InstallOutcome, scope: InstallScope) -> String
     }
 }
 
+/// Render the `--with-hooks` settings summary for stdout.
+pub fn describe_hooks_install(outcome: &InstallOutcome) -> String {
+    match outcome {
+        InstallOutcome::Wrote { path, backup } => {
+            let mut s = format!(
+                "installed muninn hooks and status line into {}",
+                path.display()
+            );
+            if let Some(b) = backup {
+                s.push_str(&format!("\n  backup: {}", b.display()));
+            }
+            s
+        }
+        InstallOutcome::AlreadyPresent { path } => format!(
+            "muninn hooks already present in {} — no changes",
+            path.display()
+        ),
+        InstallOutcome::DryRun { path, action, .. } => {
+            format!("[dry-run] would {} in {}", action, path.display())
+        }
+    }
+}
+
 pub fn describe_uninstall(outcome: &UninstallOutcome, scope: InstallScope) -> String {
     match outcome {
         UninstallOutcome::Removed { path, backup } => {
@@ -530,6 +674,70 @@ mod tests {
         }
     }
 
+    /// Hooks install merges into existing settings, keeps a foreign
+    /// status line, and is idempotent.
+    #[test]
+    fn install_hooks_merges_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = claude_settings_path(dir.path());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            serde_json::to_string_pretty(&json!({
+                "permissions": {"allow": ["Bash(ls)"]},
+                "statusLine": {"type": "command", "command": "my-status"},
+                "hooks": {
+                    "UserPromptSubmit": [
+                        {"hooks": [{"type": "command", "command": "other-hook"}]},
+                        {"hooks": [{"type": "command", "command": "muninn hook submit"}]}
+                    ]
+                }
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let outcome = install_hooks(&path, false).unwrap();
+        assert!(matches!(
+            outcome,
+            InstallOutcome::Wrote {
+                backup: Some(_),
+                ..
+            }
+        ));
+        let root = read_json(&path);
+        assert_eq!(root["permissions"]["allow"][0], "Bash(ls)");
+        assert_eq!(root["statusLine"]["command"], "my-status");
+        assert_eq!(root["enabledMcpjsonServers"], json!(["muninn"]));
+        let groups = root["hooks"]["UserPromptSubmit"].as_array().unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0]["hooks"][0]["command"], "other-hook");
+        assert_eq!(groups[1]["hooks"][0]["command"], HOOK_SUBMIT_COMMAND);
+
+        let outcome = install_hooks(&path, false).unwrap();
+        assert!(matches!(outcome, InstallOutcome::AlreadyPresent { .. }));
+    }
+
+    /// A fresh settings file gets the muninn status line.
+    #[test]
+    fn install_hooks_into_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = claude_settings_path(dir.path());
+        assert!(matches!(
+            install_hooks(&path, true).unwrap(),
+            InstallOutcome::DryRun { .. }
+        ));
+        assert!(!path.exists());
+
+        install_hooks(&path, false).unwrap();
+        let root = read_json(&path);
+        assert_eq!(root["statusLine"]["command"], STATUS_LINE_COMMAND);
+        assert_eq!(
+            root["hooks"]["UserPromptSubmit"][0]["hooks"][0]["timeout"],
+            245
+        );
+    }
+
     // ─── Test helpers ────────────────────────────────────────────────
     //
     // The public install/uninstall fns take an InstallScope + config_dir
@@ -3,17 +3,24 @@
 //! Muninn sits between your coding agent (like Claude Code) and local LLMs,
 //! providing intelligent request routing and deep context exploration.
 
-mod config;
+mod bench;
+mod ci;
+mod github;
 mod install;
+mod models;
+mod onboard;
+mod refactor;
+mod revision;
 mod session;
+mod snapshot;
 
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use tracing::{debug, info};
+use clap::{Args, Parser, Subcommand};
+use tracing::info;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
@@ -40,96 +47,22 @@ fn split_args_at_agent() -> (Vec<String>, Option<(String, Vec<String>)>) {
     }
 }
 
-use config::Config;
 use muninn_graph::doc_store::{DocStore, Ecosystem};
 use muninn_graph::registry::{
     IndexerConfig, LlmsTxtIndexer, LlmsTxtIndexerConfig, PyDocIndexer, PyIndexerConfig,
     RustDocIndexer,
 };
-use muninn_graph::{GraphBuilder, GraphStore};
+use muninn_graph::{FileLock, GraphBuilder, GraphStore, IndexQueue};
 use muninn_rlm::{
-    AnthropicBackend, AnthropicConfig, BudgetConfig as RlmBudgetConfig, FileTokenManager,
-    GroqBackend, GroqConfig, OAuthConfig, OllamaBackend, OllamaConfig, PkceChallenge, ProxyConfig,
-    ProxyServer, RouterConfig, RouterStrategy, SharedDocStore, SharedGraphStore, TokenManager,
-    ToolRegistry, build_authorization_url, create_doc_tools, create_fs_tools, create_graph_tools,
-    create_token_manager, exchange_code_for_tokens, generate_state, parse_code_state,
-    wrap_doc_store, wrap_store,
+    FileTokenManager, IndexStatus, LazyGraphStore, OAuthConfig, PkceChallenge, ProxyServer,
+    SharedGraphStore, TokenManager, build_authorization_url, exchange_code_for_tokens,
+    generate_state, parse_code_state,
+};
+use muninn_sdk::config::{self, Config};
+use muninn_sdk::{
+    MuninnBuilder, create_router_backend, file_token_manager, open_configured_graph_store,
+    open_graph_store, parse_router_strategy, project_root, upstream_passthrough_config,
 };
-
-/// Convert config budget to RLM budget type.
-fn config_to_rlm_budget(config: &config::BudgetConfig) -> RlmBudgetConfig {
-    RlmBudgetConfig {
-        max_tokens: Some(config.max_tokens as u64),
-        max_depth: Some(config.max_depth),
-        max_tool_calls: Some(config.max_tool_calls),
-        max_duration_secs: Some(config.max_duration_secs),
-    }
-}
-
-/// Create a backend from provider and model configuration.
-///
-/// Returns None if required credentials are missing.
-fn create_backend_from_config(
-    provider: &str,
-    model: &str,
-    config: &Config,
-    _config_dir: Option<&std::path::Path>,
-) -> Result<Option<Arc<dyn muninn_rlm::LLMBackend>>> {
-    match provider {
-        "groq" => {
-            let key = config
-                .groq
-                .api_key
-                .clone()
-                .or_else(|| std::env::var("GROQ_API_KEY").ok());
-            match key {
-                Some(k) => {
-                    let groq_config = GroqConfig::new(k).with_model(model);
-                    Ok(Some(Arc::new(GroqBackend::new(groq_config)?)))
-                }
-                None => Ok(None),
-            }
-        }
-        "anthropic" => {
-            let key = config
-                .anthropic
-                .api_key
-                .clone()
-                .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
-            match key {
-                Some(k) => Ok(Some(Arc::new(AnthropicBackend::new(
-                    AnthropicConfig::new(k),
-                )?))),
-                None => Ok(None),
-            }
-        }
-        "ollama" => {
-            // Resolve base_url + api_key from [ollama] (with env var fallback
-            // for the key). Local Ollama works keyless; Ollama Cloud requires
-            // OLLAMA_API_KEY and is the new default base_url.
-            let base_url = config.ollama.resolved_base_url().to_string();
-            let api_key = config.ollama.resolved_api_key();
-            if config.ollama.needs_api_key() && api_key.is_none() {
-                // The validator already surfaces this, but guard the factory
-                // too so we never silently hit cloud without credentials.
-                return Ok(None);
-            }
-            let mut ollama_config = OllamaConfig::new()
-                .with_base_url(base_url)
-                .with_model(model);
-            if let Some(k) = api_key {
-                ollama_config = ollama_config.with_api_key(k);
-            }
-            if let Some(r) = config.ollama.max_retries {
-                ollama_config = ollama_config.with_max_retries(r);
-            }
-            Ok(Some(Arc::new(OllamaBackend::new(ollama_config)?)))
-        }
-        other => {
-            anyhow::bail!("Unknown provider: {}", other)
-        }
-    }
-}
 
 /// Privacy-first recursive context gateway for agentic coding
 ///
@@ -163,6 +96,12 @@ struct Cli {
     #[arg(long, global = true)]
     workdir: Option<PathBuf>,
 
+    /// Record full upstream request/response pairs (credentials redacted)
+    /// into the session directory as `upstream.jsonl`, and each agent
+    /// request with its route and answer as `requests.jsonl`
+    #[arg(long, global = true)]
+    record: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -198,6 +137,20 @@ enum Commands {
         /// that no longer matches a current source file.
         #[arg(long)]
         reset: bool,
+
+        /// Print counts, skipped files and per-file errors when done.
+        #[arg(long)]
+        report: bool,
+
+        /// Index this git revision (branch, tag or sha) into a named
+        /// partition under `.muninn/revisions/` instead of the live graph.
+        /// The working tree is left untouched.
+        #[arg(long, value_name = "REV")]
+        rev: Option<String>,
+
+        /// Partition name for `--rev` (default: the revision itself)
+        #[arg(long = "as", value_name = "NAME", requires = "rev")]
+        name: Option<String>,
     },
 
     /// Initialize a new .muninn directory with config file
@@ -205,6 +158,12 @@ enum Commands {
         /// Force overwrite existing config
         #[arg(long)]
         force: bool,
+
+        /// Also register muninn with Claude Code: the MCP server in
+        /// `.mcp.json`, plus the prompt hook and status line in
+        /// `.claude/settings.json`. An existing config is kept.
+        #[arg(long)]
+        with_hooks: bool,
     },
 
     /// Authenticate with Claude MAX subscription (OAuth flow)
@@ -264,6 +223,222 @@ enum Commands {
         dry_run: bool,
     },
 
+    /// Resend upstream requests captured with `--record`.
+    ///
+    /// Sends each recorded request body verbatim to the recorded URL with
+    /// fresh credentials and reports the upstream status next to the
+    /// originally recorded one.
+    #[command(name = "replay-upstream")]
+    ReplayUpstream {
+        /// Recording file (`.muninn/sessions/<id>/upstream.jsonl`)
+        file: PathBuf,
+
+        /// Replay only the exchange at this (0-based) line index
+        #[arg(long)]
+        index: Option<usize>,
+
+        /// Write the replayed exchanges to this JSONL file
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Replay agent requests captured with `--record` against this build.
+    ///
+    /// Routes each recorded request with the current router config and,
+    /// unless `--routes-only`, answers RLM-routed ones with the current
+    /// engine. Reports route decisions and answers that changed.
+    /// Passthrough requests are never sent upstream.
+    #[command(name = "replay-session")]
+    ReplaySession {
+        /// Recording file (`.muninn/sessions/<id>/requests.jsonl`)
+        file: PathBuf,
+
+        /// Replay only the request at this (0-based) line index
+        #[arg(long)]
+        index: Option<usize>,
+
+        /// Compare route decisions only; don't run the RLM engine
+        #[arg(long)]
+        routes_only: bool,
+
+        /// Write the replayed requests to this JSONL file
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Manage proxy session directories under `.muninn/sessions`
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommand,
+    },
+
+    /// Browse sessions, traces and exploration reports in a web page.
+    ///
+    /// Serves a small read-only UI over `.muninn/sessions`: each session's
+    /// requests and router decisions, a span timeline per trace, feedback
+    /// and exploration reports.
+    Ui {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7701")]
+        bind: SocketAddr,
+    },
+
+    /// Inspect `[experiment]` A/B runs
+    Experiments {
+        #[command(subcommand)]
+        command: ExperimentsCommand,
+    },
+
+    /// Manage client keys for a shared proxy (`[keys]`)
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+
+    /// Benchmark LLM backends to pick router and RLM models
+    Bench {
+        #[command(subcommand)]
+        command: BenchCommand,
+    },
+
+    /// Check local Ollama models against muninn's prompt sizes
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+
+    /// Headless RLM checks for CI pipelines.
+    ///
+    /// API-key backends only (never starts an OAuth flow), strict token,
+    /// tool-call and time budgets, and a JSON artifact on stdout or
+    /// `--output`. Exit codes: 0 ok, 1 review findings, 2 error,
+    /// 3 budget exceeded, 4 timeout, 5 no backend configured.
+    Ci {
+        #[command(subcommand)]
+        command: CiCommand,
+    },
+
+    /// Answer `@muninn` mentions on GitHub pull requests.
+    ///
+    /// Runs the `ci review` pipeline over the PR diff with the mention's
+    /// text as the question and posts the answer as a review comment.
+    /// Configure under `[github]`; the token comes from `$GITHUB_TOKEN`.
+    Github {
+        #[command(subcommand)]
+        command: GithubCommand,
+    },
+
+    /// Analyze the code graph
+    Graph {
+        #[command(subcommand)]
+        command: GraphCommand,
+    },
+
+    /// Report every site a rename or signature change of a symbol would touch.
+    ///
+    /// Combines the graph's definitions and callers with a text scan of the
+    /// project, grades each site high/medium/low confidence, and (unless
+    /// `--no-rlm`) asks the RLM to confirm uncertain sites and find misses.
+    #[command(name = "refactor-check")]
+    RefactorCheck {
+        /// Symbol name or qualified name (e.g. `parse_config`)
+        symbol: String,
+
+        /// Path to graph database (default: .muninn/graph.db)
+        #[arg(long)]
+        graph: Option<PathBuf>,
+
+        /// Skip the RLM review and report graph + text matches only
+        #[arg(long)]
+        no_rlm: bool,
+
+        /// Emit the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Generate a WALKTHROUGH.md for new contributors.
+    ///
+    /// Summarizes the graph (layout, entry points, most-called symbols),
+    /// then runs RLM explorations of the architecture and the key flows
+    /// from each entry point, citing code as `path:line`. Written to
+    /// `.muninn/WALKTHROUGH.md` unless `--in-repo` or `--output` is given.
+    Onboard {
+        /// Path to graph database (default: .muninn/graph.db)
+        #[arg(long)]
+        graph: Option<PathBuf>,
+
+        /// Number of entry-point flows to explore
+        #[arg(long, default_value = "3")]
+        flows: usize,
+
+        /// Skip the RLM explorations and write the graph sections only
+        #[arg(long)]
+        no_rlm: bool,
+
+        /// Write WALKTHROUGH.md at the project root instead of .muninn/
+        #[arg(long, conflicts_with = "output")]
+        in_repo: bool,
+
+        /// Write the walkthrough to this file
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show which files and symbols the RLM has read across sessions.
+    ///
+    /// Aggregates the tool calls recorded in the traces under `.muninn/`:
+    /// the most-read files and symbols, and the indexed files and
+    /// directories no exploration has touched yet.
+    Coverage {
+        /// Path to graph database (default: .muninn/graph.db)
+        #[arg(long)]
+        graph: Option<PathBuf>,
+
+        /// Number of hot files and symbols to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+
+        /// Emit the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// List symbols that touch security-sensitive APIs.
+    ///
+    /// Reports functions tagged at index time as calling into process
+    /// execution, dynamic evaluation, unsafe code, raw SQL, filesystem
+    /// writes or the network, grouped by category.
+    Audit {
+        /// Path to graph database (default: .muninn/graph.db)
+        #[arg(long)]
+        graph: Option<PathBuf>,
+
+        /// Only report this category
+        #[arg(long, value_parser = ["exec", "eval", "unsafe", "raw_sql", "fs_write", "network"])]
+        category: Option<String>,
+
+        /// Emit the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Emit the findings as a SARIF log, for code scanning upload
+        #[arg(long, conflicts_with = "json")]
+        sarif: bool,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
     /// Run a stdio MCP server backed by the muninn engine.
     ///
     /// Auto-ensures the daemon is running, connects a client, and
@@ -285,171 +460,606 @@ enum Commands {
     },
 }
 
-/// Subcommands for the local-IPC engine daemon.
+/// Subcommands for graph analysis.
 #[derive(Subcommand)]
-enum DaemonCommand {
-    /// Start the daemon in the foreground. Ctrl-C to stop.
-    Start {
-        /// Override the socket path. Defaults to the repo-scoped path
-        /// under `$XDG_RUNTIME_DIR/muninn/` (or platform equivalent).
+enum GraphCommand {
+    /// List public functions and methods with no incoming edges
+    /// (dead-code candidates).
+    ///
+    /// Entry points, tests and FFI exports are skipped unless the matching
+    /// `--include-*` flag is given. `--allow` patterns (and `[graph]
+    /// orphan_allow` in config) skip symbols whose name, qualified name or
+    /// file path matches; `*` is a wildcard.
+    Orphans {
+        /// Path to graph database (default: .muninn/graph.db)
         #[arg(long)]
-        socket: Option<PathBuf>,
-    },
-    /// Report whether a daemon is reachable at the socket path.
-    Status {
-        /// Override the socket path (see `start --socket`).
+        graph: Option<PathBuf>,
+
+        /// Skip symbols matching this pattern (repeatable)
+        #[arg(long = "allow", value_name = "PATTERN")]
+        allow: Vec<String>,
+
+        /// Report `main` functions and handlers too
         #[arg(long)]
-        socket: Option<PathBuf>,
-    },
-    /// Stop the daemon associated with the socket path. Sends SIGTERM
-    /// and escalates to SIGKILL if it doesn't exit within a few seconds.
-    Stop {
-        /// Override the socket path (see `start --socket`).
+        include_entry_points: bool,
+
+        /// Report tests and symbols in test files too
         #[arg(long)]
-        socket: Option<PathBuf>,
-    },
-    /// Make sure a daemon is alive at the socket path, spawning one
-    /// (detached) if not. Idempotent.
-    Ensure {
-        /// Override the socket path (see `start --socket`).
+        include_tests: bool,
+
+        /// Report FFI exports too
         #[arg(long)]
-        socket: Option<PathBuf>,
+        include_ffi: bool,
+
+        /// Emit the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Emit the findings as a SARIF log, for code scanning upload
+        #[arg(long, conflicts_with = "json")]
+        sarif: bool,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
     },
-}
 
-/// Subcommands for Claude Code hook integration.
-#[derive(Subcommand)]
-enum HookCommand {
-    /// Read a CC UserPromptSubmit hook-input from stdin and emit a
-    /// turn-start `additionalContext` block on stdout. Fires once per
-    /// user message before Claude starts, so muninn gets to pre-load
-    /// project context into the agent's working set on a local model.
+    /// Detect dependency cycles between functions, files or packages.
     ///
-    /// Silent-passthrough on any failure — never blocks the turn.
-    Submit,
-}
+    /// Reports each strongly connected component with the call sites
+    /// that create its dependencies.
+    Cycles {
+        /// Path to graph database (default: .muninn/graph.db)
+        #[arg(long)]
+        graph: Option<PathBuf>,
 
-/// Subcommands for documentation management.
-#[derive(Subcommand)]
-enum DocsCommand {
-    /// Index a Rust crate from crates.io
-    #[command(name = "index-crate")]
-    IndexCrate {
-        /// Name of the crate to index (e.g., 'tokio', 'serde')
-        name: String,
+        /// Granularity: calls (mutual recursion), files (module cycles) or
+        /// packages (top-level directories such as crates/foo)
+        #[arg(long, default_value = "files", value_parser = ["calls", "files", "packages"])]
+        level: String,
 
-        /// Specific version to index (default: latest)
+        /// Emit the report as JSON instead of Markdown
         #[arg(long)]
-        version: Option<String>,
+        json: bool,
 
-        /// Path to the doc store database (default: .muninn/docs.db)
-        #[arg(long)]
-        db: Option<PathBuf>,
-    },
+        /// Emit the findings as a SARIF log, for code scanning upload
+        #[arg(long, conflicts_with = "json")]
+        sarif: bool,
 
-    /// Index a Python package from PyPI
-    #[command(name = "index-package")]
-    IndexPackage {
-        /// Name of the package to index (e.g., 'requests', 'flask')
-        name: String,
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
 
-        /// Specific version to index (default: latest)
+    /// Serve the graph read-only over HTTP.
+    ///
+    /// Clients set `[graph] url = "http://<host>:<port>"` to query it
+    /// instead of holding a local index.
+    Serve {
+        /// Path to graph database (default: .muninn/graph.db)
         #[arg(long)]
-        version: Option<String>,
+        graph: Option<PathBuf>,
 
-        /// Path to the doc store database (default: .muninn/docs.db)
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7700")]
+        bind: std::net::SocketAddr,
+    },
+
+    /// Pack the graph into a compressed snapshot keyed to the current commit.
+    ///
+    /// Publish it (e.g. from CI) so teammates can `muninn graph unpack`
+    /// instead of re-indexing.
+    Pack {
+        /// Path to graph database (default: .muninn/graph.db)
         #[arg(long)]
-        db: Option<PathBuf>,
+        graph: Option<PathBuf>,
 
-        /// Python executable (deprecated, no longer needed - tree-sitter is used)
-        #[arg(long, default_value = "python3", hide = true)]
-        python: String,
+        /// Snapshot file to write (default: graph-<commit>.tar.gz)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
     },
 
-    /// List all indexed libraries
-    List {
-        /// Filter by ecosystem (rust, python)
-        #[arg(short, long)]
-        ecosystem: Option<String>,
+    /// List git revisions indexed with `muninn index --rev`.
+    Revisions,
 
-        /// Path to the doc store database (default: .muninn/docs.db)
+    /// List the file extensions the index parses and the grammar of each,
+    /// with `[graph.languages]` applied.
+    Languages {
+        /// Emit the list as JSON
         #[arg(long)]
-        db: Option<PathBuf>,
+        json: bool,
     },
 
-    /// Search documentation in indexed libraries
-    Search {
-        /// Library name to search (e.g., 'tokio', 'requests')
-        library: String,
+    /// Compare the symbols of two indexed revisions: what was added,
+    /// removed, or changed signature or file between them.
+    Diff {
+        /// Revision to compare from (a name from `muninn graph revisions`)
+        base: String,
 
-        /// Search query (e.g., 'spawn async task', 'HTTP request')
-        query: String,
+        /// Revision to compare to; `current` is the live graph
+        #[arg(default_value = muninn_rlm::CURRENT_REVISION)]
+        head: String,
 
-        /// Maximum results to return
-        #[arg(short = 'n', long, default_value = "20")]
-        limit: usize,
+        /// Path to the live graph database (default: .muninn/graph.db)
+        #[arg(long)]
+        graph: Option<PathBuf>,
 
-        /// Path to the doc store database (default: .muninn/docs.db)
+        /// Emit the report as JSON instead of Markdown
         #[arg(long)]
-        db: Option<PathBuf>,
+        json: bool,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
     },
 
-    /// Remove an indexed library
-    Remove {
-        /// Name of the library to remove
-        name: String,
+    /// Install a snapshot made by `muninn graph pack` and report how stale
+    /// it is against the local checkout.
+    Unpack {
+        /// Snapshot file to install
+        snapshot: PathBuf,
 
-        /// Path to the doc store database (default: .muninn/docs.db)
+        /// Path to graph database (default: .muninn/graph.db)
         #[arg(long)]
-        db: Option<PathBuf>,
+        graph: Option<PathBuf>,
 
-        /// Skip confirmation prompt
-        #[arg(short, long)]
+        /// Replace an existing graph
+        #[arg(long)]
         force: bool,
     },
 
-    /// Update (re-index) an existing library to a new version
-    Update {
-        /// Name of the library to update
-        name: String,
-
-        /// Specific version to update to (default: latest)
+    /// Write the graph's nodes, edges and file hashes as JSON Lines.
+    ///
+    /// The dump is plain text and sorted, so it can be committed, diffed,
+    /// or shipped to CI and read back with `muninn graph load`.
+    Dump {
+        /// Path to graph database (default: .muninn/graph.db)
         #[arg(long)]
-        version: Option<String>,
+        graph: Option<PathBuf>,
 
-        /// Path to the doc store database (default: .muninn/docs.db)
+        /// Write the dump to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Build a graph database from a `muninn graph dump` file.
+    Load {
+        /// Dump file to read
+        dump: PathBuf,
+
+        /// Path to graph database (default: .muninn/graph.db)
         #[arg(long)]
-        db: Option<PathBuf>,
+        graph: Option<PathBuf>,
 
-        /// Python executable (deprecated, no longer needed - tree-sitter is used)
-        #[arg(long, default_value = "python3", hide = true)]
-        python: String,
+        /// Replace an existing graph
+        #[arg(long)]
+        force: bool,
     },
+}
 
-    /// Index documentation from an llms.txt URL (fast-path for LLM-optimized docs)
-    #[command(name = "index-llms")]
-    IndexLlms {
-        /// URL to fetch llms.txt from (can be base URL or direct llms.txt URL)
-        url: String,
-
-        /// Path to the doc store database (default: .muninn/docs.db)
+/// Subcommands for A/B experiments.
+#[derive(Subcommand)]
+enum ExperimentsCommand {
+    /// Compare experiment variants by latency, tokens and how RLM
+    /// explorations ended, from the traces under `.muninn/`.
+    Report {
+        /// Only this experiment (default: every experiment in the traces)
         #[arg(long)]
-        db: Option<PathBuf>,
+        name: Option<String>,
 
-        /// Fast mode: only index descriptions, don't fetch linked content
+        /// Emit the report as JSON instead of Markdown
         #[arg(long)]
-        fast: bool,
+        json: bool,
 
-        /// Maximum number of links to fetch (0 = unlimited)
-        #[arg(long, default_value = "100")]
-        max_links: usize,
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
     },
 }
 
-/// Initialize logging for standalone commands (not agent mode).
-/// Logs to stderr for interactive use.
-fn init_logging(verbose: bool) {
-    let filter = if verbose {
+/// Subcommands for client keys.
+#[derive(Subcommand)]
+enum KeysCommand {
+    /// Issue a key. It is printed once; only its hash is stored.
+    Create {
+        /// Who the key is for; shown in traces and spend records
+        #[arg(long)]
+        name: String,
+
+        /// Input plus output tokens allowed per UTC day
+        #[arg(long)]
+        daily_tokens: Option<u64>,
+
+        /// Requests allowed per minute
+        #[arg(long)]
+        requests_per_minute: Option<u32>,
+
+        /// Role picking the key's tool grant under `[keys.roles]`
+        #[arg(long)]
+        role: Option<String>,
+    },
+
+    /// List issued keys
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Revoke a key by name
+    Revoke {
+        /// Name the key was issued to
+        #[arg(long)]
+        name: String,
+    },
+}
+
+/// Subcommands for benchmarks.
+#[derive(Subcommand)]
+enum BenchCommand {
+    /// Run completion and tool-calling probes against the configured router
+    /// and RLM backends (plus any `--backend` candidates) and report latency
+    /// percentiles, token throughput, tool-call fidelity and cost.
+    ///
+    /// Cost needs the model under `[pricing]`.
+    Backends {
+        /// Repetitions of each probe
+        #[arg(long, default_value = "3")]
+        runs: usize,
+
+        /// Extra backend to compare, as `provider:model` (repeatable)
+        #[arg(long = "backend", value_name = "PROVIDER:MODEL")]
+        backends: Vec<String>,
+
+        /// Emit the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Subcommands for local models.
+#[derive(Subcommand)]
+enum ModelsCommand {
+    /// Compare a local Ollama model's context window with a typical muninn
+    /// prompt (system prompt, directory tree, tools, conversation) and
+    /// suggest a `num_ctx` or an installed model with more context.
+    ///
+    /// When a `num_ctx` change is suggested, offers to create a derived
+    /// model with it set and point `.muninn/config.toml` at that model.
+    Advise {
+        /// Model to inspect (default: the RLM model, or the router model
+        /// when only the router runs on Ollama)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Apply the suggested override without asking
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Output the advice as JSON (applies nothing unless `--yes`)
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Subcommands for session management.
+#[derive(Subcommand)]
+enum SessionsCommand {
+    /// List sessions with their status and request totals.
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show one session's details, or its exploration reports.
+    Show {
+        /// Session ID (defaults to the most recent session)
+        session: Option<String>,
+
+        /// List the session's exploration reports (one markdown file per
+        /// RLM request) instead of its details
+        #[arg(long)]
+        explorations: bool,
+
+        /// List the answers rated with `@muninn good` / `@muninn bad`
+        /// (or `POST /feedback`), with how each request was routed,
+        /// instead of its details
+        #[arg(long, conflicts_with = "explorations")]
+        feedback: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Delete session directories and debug logs.
+    ///
+    /// Without flags, removes every session directory plus the raw request
+    /// logs in `.muninn/debug` (or `[debug] dir`).
+    Clean {
+        /// Only delete raw request logs, keeping traces, spend ledgers and
+        /// recordings
+        #[arg(long)]
+        debug_only: bool,
+
+        /// List what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Subcommands for headless CI runs.
+#[derive(Subcommand)]
+enum CiCommand {
+    /// Answer a question about the repository.
+    Ask {
+        /// The question
+        question: String,
+
+        #[command(flatten)]
+        opts: CiOptions,
+    },
+
+    /// Review the changes between two git refs.
+    ///
+    /// Exits 1 when the review's verdict is `fail` (a high-severity finding).
+    Review {
+        /// Base ref of the change
+        #[arg(long, default_value = "origin/main")]
+        base: String,
+
+        /// Head ref of the change
+        #[arg(long, default_value = "HEAD")]
+        head: String,
+
+        /// Also write the findings as a SARIF log to this file
+        #[arg(long, value_name = "PATH")]
+        sarif: Option<PathBuf>,
+
+        #[command(flatten)]
+        opts: CiOptions,
+    },
+}
+
+/// Subcommands for the GitHub integration.
+#[derive(Subcommand)]
+enum GithubCommand {
+    /// Handle one `issue_comment` webhook payload (e.g. in GitHub Actions).
+    Event {
+        /// Payload file (default: $GITHUB_EVENT_PATH)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        #[command(flatten)]
+        budget: CiBudgetArgs,
+    },
+
+    /// Poll the repository's PR comments and answer new mentions.
+    Watch {
+        /// Poll once and exit
+        #[arg(long)]
+        once: bool,
+
+        #[command(flatten)]
+        budget: CiBudgetArgs,
+    },
+}
+
+/// Output flags shared by the `ci` subcommands.
+#[derive(Args)]
+struct CiOptions {
+    #[command(flatten)]
+    budget: CiBudgetArgs,
+
+    /// Write the JSON artifact to this file instead of stdout
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// Exploration budget flags for headless runs.
+#[derive(Args)]
+struct CiBudgetArgs {
+    /// Token budget across all recursive calls
+    #[arg(long, default_value = "50000")]
+    max_tokens: u64,
+
+    /// Tool-call budget
+    #[arg(long, default_value = "25")]
+    max_tool_calls: u32,
+
+    /// Wall-clock timeout in seconds
+    #[arg(long, default_value = "300")]
+    timeout: u64,
+
+    /// Path to graph database (default: .muninn/graph.db)
+    #[arg(long)]
+    graph: Option<PathBuf>,
+}
+
+impl CiBudgetArgs {
+    fn budget(&self) -> ci::CiBudget {
+        ci::CiBudget {
+            max_tokens: self.max_tokens,
+            max_tool_calls: self.max_tool_calls,
+            timeout_secs: self.timeout,
+        }
+    }
+}
+
+/// Subcommands for the local-IPC engine daemon.
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Start the daemon in the foreground. Ctrl-C to stop.
+    Start {
+        /// Override the socket path. Defaults to the repo-scoped path
+        /// under `$XDG_RUNTIME_DIR/muninn/` (or platform equivalent).
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Report whether a daemon is reachable at the socket path.
+    Status {
+        /// Override the socket path (see `start --socket`).
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Stop the daemon associated with the socket path. Sends SIGTERM
+    /// and escalates to SIGKILL if it doesn't exit within a few seconds.
+    Stop {
+        /// Override the socket path (see `start --socket`).
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Make sure a daemon is alive at the socket path, spawning one
+    /// (detached) if not. Idempotent.
+    Ensure {
+        /// Override the socket path (see `start --socket`).
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+}
+
+/// Subcommands for Claude Code hook integration.
+#[derive(Subcommand)]
+enum HookCommand {
+    /// Read a CC UserPromptSubmit hook-input from stdin and emit a
+    /// turn-start `additionalContext` block on stdout. Fires once per
+    /// user message before Claude starts, so muninn gets to pre-load
+    /// project context into the agent's working set on a local model.
+    ///
+    /// Silent-passthrough on any failure — never blocks the turn.
+    Submit,
+
+    /// Print a one-line status for Claude Code's status bar from the
+    /// proxy's `/statusline` endpoint (found via `ANTHROPIC_BASE_URL`).
+    Statusline,
+}
+
+/// Subcommands for documentation management.
+#[derive(Subcommand)]
+enum DocsCommand {
+    /// Index a Rust crate from crates.io
+    #[command(name = "index-crate")]
+    IndexCrate {
+        /// Name of the crate to index (e.g., 'tokio', 'serde')
+        name: String,
+
+        /// Specific version to index (default: latest)
+        #[arg(long)]
+        version: Option<String>,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+
+    /// Index a Python package from PyPI
+    #[command(name = "index-package")]
+    IndexPackage {
+        /// Name of the package to index (e.g., 'requests', 'flask')
+        name: String,
+
+        /// Specific version to index (default: latest)
+        #[arg(long)]
+        version: Option<String>,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Python executable (deprecated, no longer needed - tree-sitter is used)
+        #[arg(long, default_value = "python3", hide = true)]
+        python: String,
+    },
+
+    /// List all indexed libraries
+    List {
+        /// Filter by ecosystem (rust, python)
+        #[arg(short, long)]
+        ecosystem: Option<String>,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+
+    /// Search documentation in indexed libraries
+    Search {
+        /// Library name to search (e.g., 'tokio', 'requests')
+        library: String,
+
+        /// Search query (e.g., 'spawn async task', 'HTTP request')
+        query: String,
+
+        /// Maximum results to return
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+
+    /// Remove an indexed library
+    Remove {
+        /// Name of the library to remove
+        name: String,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Skip confirmation prompt
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Update (re-index) an existing library to a new version
+    Update {
+        /// Name of the library to update
+        name: String,
+
+        /// Specific version to update to (default: latest)
+        #[arg(long)]
+        version: Option<String>,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Python executable (deprecated, no longer needed - tree-sitter is used)
+        #[arg(long, default_value = "python3", hide = true)]
+        python: String,
+    },
+
+    /// Index documentation from an llms.txt URL (fast-path for LLM-optimized docs)
+    #[command(name = "index-llms")]
+    IndexLlms {
+        /// URL to fetch llms.txt from (can be base URL or direct llms.txt URL)
+        url: String,
+
+        /// Path to the doc store database (default: .muninn/docs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Fast mode: only index descriptions, don't fetch linked content
+        #[arg(long)]
+        fast: bool,
+
+        /// Maximum number of links to fetch (0 = unlimited)
+        #[arg(long, default_value = "100")]
+        max_links: usize,
+    },
+}
+
+/// Initialize logging for standalone commands (not agent mode).
+/// Logs to stderr for interactive use.
+fn init_logging(verbose: bool) {
+    let filter = if verbose {
         EnvFilter::new("debug")
     } else {
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
@@ -498,32 +1108,6 @@ fn init_file_logging(muninn_dir: &std::path::Path, verbose: bool) {
         .init();
 }
 
-/// Initialize logging for agent mode - logs to file to keep terminal clean.
-fn init_agent_logging(muninn_dir: &std::path::Path) {
-    use tracing_subscriber::layer::SubscriberExt;
-
-    // Create logs directory
-    let log_dir = muninn_dir.join("logs");
-    std::fs::create_dir_all(&log_dir).ok();
-
-    // Create file appender
-    let file_appender = RollingFileAppender::new(Rotation::DAILY, log_dir, "muninn.log");
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-
-    // Keep guard alive - store in static to prevent drop
-    static AGENT_GUARD: std::sync::OnceLock<tracing_appender::non_blocking::WorkerGuard> =
-        std::sync::OnceLock::new();
-    let _ = AGENT_GUARD.set(_guard);
-
-    // Log to file with debug level
-    let filter = EnvFilter::new("debug");
-
-    tracing_subscriber::registry()
-        .with(fmt::layer().with_writer(non_blocking).with_ansi(false))
-        .with(filter)
-        .init();
-}
-
 /// Initialize logging for session-based mode.
 /// Logs to a single file in the session directory (no rotation).
 fn init_session_logging(session_dir: &std::path::Path, verbose: bool) {
@@ -563,72 +1147,117 @@ fn init_session_logging(session_dir: &std::path::Path, verbose: bool) {
         .init();
 }
 
-fn parse_router_strategy(s: &str) -> RouterStrategy {
-    match s.to_lowercase().as_str() {
-        "llm" => RouterStrategy::Llm,
-        "always-rlm" | "rlm" => RouterStrategy::AlwaysRlm,
-        "always-passthrough" | "passthrough" => RouterStrategy::AlwaysPassthrough,
-        _ => {
-            tracing::warn!("Unknown router strategy '{}', using llm", s);
-            RouterStrategy::Llm
-        }
-    }
-}
-
-/// Create a tool registry with all available tools.
-fn create_tools(
-    workdir: &PathBuf,
+/// Build an in-process RLM engine from the resolved `[rlm]` config, with
+/// the same tools and stores as the proxy and daemon.
+///
+/// Returns `None` when no backend is available (missing credentials).
+fn create_local_engine(
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+    work_path: &PathBuf,
     graph_store: Option<SharedGraphStore>,
-    doc_store: Option<SharedDocStore>,
-) -> ToolRegistry {
-    let mut registry = ToolRegistry::new();
+) -> Result<Option<Arc<dyn muninn_core::MuninnEngine>>> {
+    let engine = MuninnBuilder::new(config.clone())
+        .with_muninn_dir(config_dir.unwrap_or(std::path::Path::new(config::MUNINN_DIR)))
+        .with_work_dir(work_path)
+        .with_graph_store(graph_store)
+        .build_engine()?;
+    Ok(engine.map(|e| Arc::new(e) as Arc<dyn muninn_core::MuninnEngine>))
+}
 
-    // Add filesystem tools (internal, for RLM use)
-    for tool in create_fs_tools(workdir) {
-        registry.register_arc(Arc::from(tool));
-    }
+/// Build the proxy for `session`: traces and raw logs go to the session
+/// directory, and `session.json` plus a `started` event carrying
+/// `started` are written once the proxy is ready to run.
+fn session_proxy(
+    builder: MuninnBuilder,
+    session: &mut session::ProxySession,
+    addr: SocketAddr,
+    started: serde_json::Value,
+) -> Result<ProxyServer> {
+    let builder = builder
+        .with_session_dir(&session.dir)
+        .with_trace_writer(session.trace_writer());
+    let metadata = session::SessionMetadata::new(&session.id, builder.work_dir())
+        .with_router_strategy(format!("{:?}", builder.router_strategy()))
+        .with_rlm_model(builder.config().resolved_rlm().model);
+    let server = builder.build_proxy(addr)?;
+    session.start(&metadata, started)?;
+    info!("Session: {} -> {:?}", session.id, session.dir);
+    Ok(server)
+}
 
-    // Add graph tools if we have a graph store (external, exposed via MCP)
-    if let Some(store) = graph_store {
-        for tool in create_graph_tools(store) {
-            registry.register_arc(Arc::from(tool));
-        }
-    }
+/// Open the graph store, failing with a hint when it hasn't been built.
+fn require_graph_store(path: &std::path::Path) -> Result<SharedGraphStore> {
+    open_graph_store(path)?
+        .ok_or_else(|| anyhow::anyhow!("no graph at {}; run `muninn index` first", path.display()))
+}
 
-    // Add doc tools if we have a doc store (for library documentation search)
-    if let Some(store) = doc_store {
-        for tool in create_doc_tools(store) {
-            registry.register_arc(Arc::from(tool));
-        }
+/// The graph a report reads: `--graph` when given, else the configured
+/// store (`[graph] url` or the local database).
+fn require_report_graph_store(
+    graph: Option<PathBuf>,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<SharedGraphStore> {
+    match graph {
+        Some(path) => require_graph_store(&path),
+        None => open_configured_graph_store(config, config_dir)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "no graph at {}; run `muninn index` first",
+                config.resolve_graph_path(config_dir).display()
+            )
+        }),
     }
-
-    registry
 }
 
-/// Load or open the graph store, optionally starting background indexing if missing.
-fn open_graph_store(path: &PathBuf) -> Result<Option<SharedGraphStore>> {
-    if path.exists() {
-        info!("Opening graph store at {}", path.display());
-        let store = GraphStore::open(path)?;
-        Ok(Some(wrap_store(store)))
+/// Print a rendered report, or write it to `output` when given.
+/// The resolved router and RLM models (once each when they're the same)
+/// followed by the `provider:model` candidates.
+fn bench_targets(
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+    candidates: &[String],
+) -> Result<Vec<bench::BenchTarget>> {
+    let router = config.resolved_router();
+    let rlm = config.resolved_rlm();
+    let mut specs = vec![("router".to_string(), router.provider, router.model)];
+    if (&rlm.provider, &rlm.model) == (&specs[0].1, &specs[0].2) {
+        specs[0].0 = "router, rlm".to_string();
     } else {
-        Ok(None)
+        specs.push(("rlm".to_string(), rlm.provider, rlm.model));
+    }
+    for candidate in candidates {
+        let Some((provider, model)) = candidate.split_once(':') else {
+            anyhow::bail!("--backend expects provider:model, got {:?}", candidate);
+        };
+        if !specs.iter().any(|(_, p, m)| p == provider && m == model) {
+            specs.push(("candidate".to_string(), provider.into(), model.into()));
+        }
     }
+    specs
+        .into_iter()
+        .map(|(role, provider, model)| {
+            let backend =
+                muninn_sdk::create_backend_from_config(&provider, &model, config, config_dir)?;
+            Ok(bench::BenchTarget {
+                role,
+                provider,
+                model,
+                backend,
+            })
+        })
+        .collect()
 }
 
-/// Open the doc store if it exists.
-fn open_doc_store(path: &PathBuf) -> Result<Option<SharedDocStore>> {
-    if path.exists() {
-        info!("Opening doc store at {}", path.display());
-        let store = DocStore::open(path)?;
-        Ok(Some(wrap_doc_store(store)))
-    } else {
-        debug!(
-            "No doc store at {} - doc tools will not be available",
-            path.display()
-        );
-        Ok(None)
+fn write_report(rendered: &str, output: Option<&std::path::Path>, what: &str) -> Result<()> {
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered)?;
+            println!("Wrote {} to {}", what, path.display());
+        }
+        None => print!("{}", rendered),
     }
+    Ok(())
 }
 
 /// Load config from file or auto-discover from `.muninn/config.toml`.
@@ -697,6 +1326,7 @@ async fn main() -> Result<()> {
             groq_key: cli.groq_key,
             router_strategy: cli.router,
             workdir: cli.workdir,
+            record: cli.record,
             agent_cmd,
             agent_args,
             config,
@@ -721,13 +1351,10 @@ async fn main() -> Result<()> {
                 .clone()
                 .unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR));
 
-            // Generate session ID and create session directory
-            let session_id = session::SessionId::generate();
-            let session_dir = session::session_dir(&muninn_dir, &session_id);
-            std::fs::create_dir_all(&session_dir)?;
+            let mut session = session::ProxySession::create(&muninn_dir)?;
 
             // Initialize session-based logging
-            init_session_logging(&session_dir, cli.verbose);
+            init_session_logging(&session.dir, cli.verbose);
 
             let addr: SocketAddr = format!("{}:{}", host, cli.port).parse()?;
             info!("Starting Muninn proxy server on {}", addr);
@@ -736,139 +1363,34 @@ async fn main() -> Result<()> {
             config.warn_deprecated_backend();
 
             // Use CLI args or fall back to config
-            let router_strategy = cli
-                .router
-                .map(|s| parse_router_strategy(&s))
-                .unwrap_or_else(|| parse_router_strategy(&config.router.strategy));
-
-            let work_path = cli.workdir.unwrap_or_else(|| {
-                config_dir
-                    .as_ref()
-                    .map(|d| d.join(&config.project.root))
-                    .unwrap_or_else(|| config.project.root.clone())
-            });
-            // Canonicalize to resolve relative paths like "." or ".."
-            let work_path = work_path.canonicalize().unwrap_or(work_path);
-
-            // Resolve provider+model via the tiered config (router/rlm
-            // inherit from [default] when not overridden).
-            let resolved_router = config.resolved_router();
-            let resolved_rlm = config.resolved_rlm();
-
-            // Create separate backends for router and RLM
-            // If CLI provides groq_key, use it for both; otherwise use config
-            let (router_backend, rlm_backend) = if let Some(key) = cli.groq_key.clone() {
-                info!("Using Groq backend from CLI for both router and RLM");
-                let router_groq = GroqConfig::new(key.clone()).with_model(&resolved_router.model);
-                let rlm_groq = GroqConfig::new(key).with_model(&resolved_rlm.model);
-                (
-                    Some(
-                        Arc::new(GroqBackend::new(router_groq)?) as Arc<dyn muninn_rlm::LLMBackend>
-                    ),
-                    Some(Arc::new(GroqBackend::new(rlm_groq)?) as Arc<dyn muninn_rlm::LLMBackend>),
-                )
-            } else {
-                // Create router backend
-                let router_backend = create_backend_from_config(
-                    &resolved_router.provider,
-                    &resolved_router.model,
-                    &config,
-                    config_dir.as_deref(),
-                )?;
-
-                // Create RLM backend
-                let rlm_backend = create_backend_from_config(
-                    &resolved_rlm.provider,
-                    &resolved_rlm.model,
-                    &config,
-                    config_dir.as_deref(),
-                )?;
-
-                (router_backend, rlm_backend)
-            };
-
-            // Log which models are being used
-            info!(
-                "Router: {} via {}",
-                resolved_router.model, resolved_router.provider
-            );
-            info!("RLM: {} via {}", resolved_rlm.model, resolved_rlm.provider);
-
-            // Configure the router with its dedicated backend
-            let router_strategy_str = format!("{:?}", router_strategy);
-            let router_config = RouterConfig {
-                strategy: router_strategy,
-                enabled: config.router.enabled,
-                router_model: Some(resolved_router.model.clone()),
-            };
-
-            // Open graph store if available
-            let graph_path = config.resolve_graph_path(config_dir.as_deref());
-            let graph_store = open_graph_store(&graph_path)?;
-
-            // Open doc store if available (default: .muninn/docs.db)
-            let doc_path = config_dir
-                .as_ref()
-                .map(|d| d.join("docs.db"))
-                .unwrap_or_else(|| PathBuf::from(".muninn/docs.db"));
-            let doc_store = open_doc_store(&doc_path)?;
-
-            // Create tools
-            let tools: Arc<dyn muninn_rlm::ToolEnvironment> =
-                Arc::new(create_tools(&work_path, graph_store, doc_store));
-
-            // Create token manager for OAuth support
-            let muninn_dir = config_dir
-                .clone()
-                .unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR));
-            let token_manager = create_token_manager(&muninn_dir);
-
-            // Configure and start the proxy with OAuth support
-            let rlm_budget = config_to_rlm_budget(&config.budget);
-            info!(
-                "Budget config: max_depth={}, max_tool_calls={}, max_tokens={}",
-                config.budget.max_depth, config.budget.max_tool_calls, config.budget.max_tokens
-            );
-
-            // Write session metadata
-            let session_metadata = session::SessionMetadata::new(&session_id, work_path.clone())
-                .with_router_strategy(&router_strategy_str)
-                .with_rlm_model(&resolved_rlm.model);
-            session::write_metadata(&session_dir, &session_metadata)?;
-
-            info!("Session: {} -> {:?}", session_id, session_dir);
-
-            // Configure trace writer for session mode
-            let trace_writer_config =
-                muninn_tracing::WriterConfig::session(session_dir.join("traces.jsonl"));
-
-            let proxy_config = ProxyConfig::new(addr)
-                .with_token_manager(token_manager)
-                .with_budget(rlm_budget)
-                .with_work_dir(&work_path)
-                .with_session_dir(&session_dir)
-                .with_trace_writer(trace_writer_config);
-
-            // Build server with separate router and RLM backends
-            let server = match (router_backend, rlm_backend) {
-                (Some(router_be), Some(rlm_be)) => ProxyServer::with_separate_backends(
-                    proxy_config,
-                    router_be,
-                    rlm_be,
-                    tools,
-                    router_config,
-                ),
-                (_, Some(rlm_be)) => {
-                    // No router backend, use RLM backend for both
-                    info!("Router backend not available, using RLM backend for routing");
-                    ProxyServer::with_router(proxy_config, rlm_be, tools, router_config)
-                }
-                _ => {
-                    info!("No RLM backend configured, running in passthrough-only mode");
-                    ProxyServer::passthrough_only(proxy_config)
-                }
-            };
-            server.run().await?;
+            let mut builder = MuninnBuilder::new(config).with_upstream_recording(cli.record);
+            if let Some(dir) = config_dir.clone() {
+                builder = builder.with_muninn_dir(dir);
+            }
+            if let Some(dir) = cli.workdir {
+                builder = builder.with_work_dir(dir);
+            }
+            if let Some(strategy) = &cli.router {
+                builder = builder.with_router_strategy(parse_router_strategy(strategy));
+            }
+            if let Some(key) = cli.groq_key.clone() {
+                builder = builder.with_groq_key(key);
+            }
+            let server = session_proxy(
+                builder,
+                &mut session,
+                addr,
+                serde_json::json!({ "addr": addr.to_string() }),
+            )?;
+
+            let result = server
+                .run_with_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                    info!("Received interrupt, shutting down...");
+                })
+                .await;
+            session.finish();
+            result?;
         }
 
         Commands::Index {
@@ -876,6 +1398,9 @@ async fn main() -> Result<()> {
             output,
             watch,
             reset,
+            report,
+            rev,
+            name,
         } => {
             // Index uses file logging
             let muninn_dir = config_dir
@@ -892,113 +1417,58 @@ async fn main() -> Result<()> {
             // Canonicalize to resolve relative paths like "." or ".."
             let source_path = source_path.canonicalize().unwrap_or(source_path);
 
-            let graph_path =
-                output.unwrap_or_else(|| config.resolve_graph_path(config_dir.as_deref()));
-
-            // If --reset, remove the existing DB file before opening
-            // so the new GraphStore is empty. Keeps semantics clean:
-            // we don't have a per-table truncate, and rebuild is fast
-            // enough that wipe-and-rebuild is the simplest correct path.
-            if reset && graph_path.exists() {
-                info!("Resetting graph at {}", graph_path.display());
-                std::fs::remove_file(&graph_path)?;
-                // Also remove any sqlite sidecar files (WAL, SHM).
-                for suffix in ["-wal", "-shm", "-journal"] {
-                    let sidecar = graph_path.with_extension(format!(
-                        "{}{}",
-                        graph_path
-                            .extension()
-                            .and_then(|e| e.to_str())
-                            .unwrap_or(""),
-                        suffix
-                    ));
-                    let _ = std::fs::remove_file(sidecar);
+            if let Some(rev) = rev {
+                if watch || output.is_some() {
+                    anyhow::bail!("--rev can't be combined with --watch or --output");
+                }
+                let name = name.unwrap_or_else(|| rev.clone());
+                let mut stats = None;
+                let manifest = revision::index_revision(
+                    &source_path,
+                    &muninn_dir.join(muninn_graph::REVISIONS_DIR),
+                    &rev,
+                    &name,
+                    |checkout, partition| {
+                        stats = index_project(
+                            checkout,
+                            &partition.graph_path(),
+                            true,
+                            index_scope(&config)?,
+                            None,
+                            |_| {},
+                        )?;
+                        Ok(())
+                    },
+                )?;
+                if report {
+                    print_index_report(stats.as_ref());
                 }
-                // Drop the Merkle snapshot too — otherwise the
-                // incremental gate would see "no changes" against an
-                // empty graph and skip the rebuild we just asked for.
-                let _ = std::fs::remove_file(muninn_dir.join("incremental-state.bin"));
+                eprintln!(
+                    "Indexed {} ({}) as revision '{}'",
+                    manifest.revision, manifest.commit, manifest.name
+                );
+                return Ok(());
             }
 
-            info!(
-                "Indexing {} -> {}",
-                source_path.display(),
-                graph_path.display()
-            );
+            let graph_path =
+                output.unwrap_or_else(|| config.resolve_graph_path(config_dir.as_deref()));
 
-            // Create parent directory if needed
-            if let Some(parent) = graph_path.parent() {
-                std::fs::create_dir_all(parent)?;
+            let stats = index_project(
+                &source_path,
+                &graph_path,
+                reset,
+                index_scope(&config)?,
+                None,
+                |_| {},
+            )?;
+            if report {
+                print_index_report(stats.as_ref());
             }
-
-            // GraphStore::open creates the database if it doesn't exist
-            let store = GraphStore::open(&graph_path)?;
-
-            // Incremental gate: walk the tree, hash everything, compare
-            // against the previous Merkle snapshot. If nothing changed,
-            // skip extraction entirely — the graph is already up to date.
-            // On a fresh run (no prior snapshot) or `--reset` (snapshot
-            // deleted alongside graph.db) this falls through to the full
-            // build.
-            let state_path = muninn_dir.join("incremental-state.bin");
-            let no_op_parse = |_p: &std::path::Path| Ok(Vec::new());
-            let new_tree =
-                muninn_narsil_vendor::incremental::MerkleTree::build(&source_path, no_op_parse)?;
-
-            let skip = if reset {
-                false
-            } else if state_path.exists() {
-                match muninn_narsil_vendor::incremental::MerkleTree::load(&state_path) {
-                    Ok(old_tree) => {
-                        let cs = old_tree.diff(&new_tree);
-                        if cs.is_empty() {
-                            info!(
-                                "Graph already up to date for {} (no source changes since last index)",
-                                source_path.display()
-                            );
-                            true
-                        } else {
-                            info!(
-                                "Detected {} added / {} modified / {} deleted files since last index",
-                                cs.added.len(),
-                                cs.modified.len(),
-                                cs.deleted.len(),
-                            );
-                            false
-                        }
-                    }
-                    Err(e) => {
-                        info!("Could not load incremental state ({e}); doing full reindex");
-                        false
-                    }
-                }
-            } else {
-                false
-            };
-
-            if skip {
+            if stats.is_none() {
                 // We're done — no need to spin the extractor.
                 return Ok(());
             }
 
-            // Drive the vendored narsil extractor over the source tree.
-            // This is the only indexing path muninn supports.
-            let mut builder = GraphBuilder::new(store)?;
-            let stats = builder.build_directory(&source_path)?;
-            info!(
-                "Indexed {} files, {} nodes, {} edges",
-                stats.files_processed, stats.nodes_added, stats.edges_added
-            );
-
-            // Persist the new snapshot so the next `muninn index` can
-            // short-circuit if nothing changed.
-            if let Err(e) = new_tree.save(&state_path) {
-                tracing::warn!(
-                    "Failed to save incremental state to {}: {e}",
-                    state_path.display()
-                );
-            }
-
             if watch {
                 anyhow::bail!(
                     "--watch is not supported — re-run `muninn index` after \
@@ -1007,13 +1477,18 @@ async fn main() -> Result<()> {
             }
         }
 
-        Commands::Init { force } => {
+        Commands::Init { force, with_hooks } => {
             use config::{CONFIG_FILE, MUNINN_DIR};
 
             let muninn_dir = PathBuf::from(MUNINN_DIR);
             let config_path = muninn_dir.join(CONFIG_FILE);
 
             if config_path.exists() && !force {
+                if with_hooks {
+                    println!("Keeping {}", config_path.display());
+                    install_claude_code_hooks(&muninn_dir)?;
+                    return Ok(());
+                }
                 anyhow::bail!(".muninn/config.toml already exists. Use --force to overwrite.");
             }
 
@@ -1032,6 +1507,19 @@ root = ".."  # Parent directory (the actual project root)
 [graph]
 path = "graph.db"  # Stored in .muninn/graph.db
 extensions = ["rs", "py", "ts", "js", "go", "c", "cpp", "h"]
+# Paths indexing leaves out, on top of .gitignore and .ignore files
+# (gitignore syntax, relative to the project root).
+# exclude = ["vendor/", "*.generated.rs"]
+# Symbols `muninn graph orphans` never reports (name, qualified name or
+# file path; `*` is a wildcard).
+# orphan_allow = ["*::plugin::*", "src/generated/*"]
+# Print background indexing progress before the agent takes the terminal.
+# index_status_line = true
+# Map more extensions to a grammar, or stop indexing one with "none".
+# `muninn graph languages` lists what gets indexed.
+# [graph.languages]
+# mjs = "javascript"
+# java = "none"
 
 # Default LLM provider/model. Router and RLM inherit from this unless they
 # override `provider` / `model` in their own sections. The out-of-the-box
@@ -1063,6 +1551,14 @@ max_depth = 5
 max_tool_calls = 50
 max_duration_secs = 300
 
+# Per-tool limits within one exploration. When a limit is hit the model
+# is told so (with the hint) instead of the tool running.
+# [tools.search_files]
+# max_calls = 10
+# timeout_secs = 30
+# max_output_tokens = 20000
+# hint = "use the graph instead"
+
 # Provider credentials.
 #
 # Uncomment one block below to set credentials in this file, OR
@@ -1098,6 +1594,9 @@ max_duration_secs = 300
             // would silently swallow these.
             println!("Initialized {}", muninn_dir.display());
             println!("Wrote   {}", config_path.display());
+            if with_hooks {
+                install_claude_code_hooks(&muninn_dir)?;
+            }
             println!();
             println!("Next steps:");
             println!(
@@ -1106,11 +1605,19 @@ max_duration_secs = 300
                  OLLAMA_API_KEY / GROQ_API_KEY / ANTHROPIC_API_KEY in your \
                  shell. The default config targets Ollama Cloud."
             );
-            println!(
-                "  2. For Claude Code: `muninn install-cc` here, then inside CC \
-                 run `/plugin marketplace add colliery-io/muninn` and \
-                 `/plugin install muninn-cc`."
-            );
+            if with_hooks {
+                println!(
+                    "  2. Claude Code is configured for this project; start it \
+                     with `muninn claude` so the status line can reach the proxy. \
+                     Skip the muninn-cc plugin, which would run the prompt hook twice."
+                );
+            } else {
+                println!(
+                    "  2. For Claude Code: `muninn install-cc` here, then inside CC \
+                     run `/plugin marketplace add colliery-io/muninn` and \
+                     `/plugin install muninn-cc` (or rerun `muninn init --with-hooks`)."
+                );
+            }
             println!(
                 "  3. (Optional) `muninn index` to populate the code graph so \
                  the MCP `query_graph` tool returns non-empty results."
@@ -1126,7 +1633,7 @@ max_duration_secs = 300
                 std::fs::create_dir_all(&muninn_dir)?;
             }
 
-            let token_manager = FileTokenManager::new(&muninn_dir);
+            let token_manager = file_token_manager(&muninn_dir, &config)?;
 
             if logout {
                 // Delete stored tokens
@@ -1647,6 +2154,193 @@ max_duration_secs = 300
             println!("{}", install::describe_uninstall(&outcome, scope));
         }
 
+        Commands::ReplayUpstream {
+            file,
+            index,
+            output,
+        } => {
+            init_logging(cli.verbose);
+            run_replay_upstream(&file, index, output.as_deref(), &config, config_dir).await?;
+        }
+
+        Commands::ReplaySession {
+            file,
+            index,
+            routes_only,
+            output,
+        } => {
+            init_logging(cli.verbose);
+            let mut builder = MuninnBuilder::new(config);
+            if let Some(dir) = config_dir {
+                builder = builder.with_muninn_dir(dir);
+            }
+            if let Some(dir) = cli.workdir {
+                builder = builder.with_work_dir(dir);
+            }
+            if let Some(strategy) = &cli.router {
+                builder = builder.with_router_strategy(parse_router_strategy(strategy));
+            }
+            if let Some(key) = cli.groq_key.clone() {
+                builder = builder.with_groq_key(key);
+            }
+            run_replay_session(&file, index, routes_only, output.as_deref(), builder).await?;
+        }
+
+        Commands::Sessions { command } => {
+            init_logging_stderr_only(cli.verbose);
+            run_sessions_command(command, &config, config_dir.as_deref())?;
+        }
+
+        Commands::Ui { bind } => {
+            init_logging_stderr_only(cli.verbose);
+            let muninn_dir = config_dir.unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR));
+            eprintln!("Serving the session UI on http://{}", bind);
+            muninn_rlm::serve_session_ui(muninn_dir.join("sessions"), bind).await?;
+        }
+
+        Commands::Experiments {
+            command: ExperimentsCommand::Report { name, json, output },
+        } => {
+            init_logging_stderr_only(cli.verbose);
+            let muninn_dir = config_dir.unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR));
+            let report =
+                muninn_rlm::ExperimentReport::from_muninn_dir(&muninn_dir, name.as_deref())?;
+            let rendered = if json {
+                serde_json::to_string_pretty(&report)? + "\n"
+            } else {
+                report.to_markdown()
+            };
+            write_report(&rendered, output.as_deref(), "experiment report")?;
+        }
+
+        Commands::Keys { command } => {
+            init_logging_stderr_only(cli.verbose);
+            let muninn_dir = config_dir.unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR));
+            run_keys_command(command, &muninn_dir)?;
+        }
+
+        Commands::Bench {
+            command:
+                BenchCommand::Backends {
+                    runs,
+                    backends,
+                    json,
+                    output,
+                },
+        } => {
+            init_logging_stderr_only(cli.verbose);
+            let targets = bench_targets(&config, config_dir.as_deref(), &backends)?;
+            let report = bench::run(&targets, runs.max(1), &config.pricing).await;
+            let rendered = if json {
+                serde_json::to_string_pretty(&report)? + "\n"
+            } else {
+                report.to_markdown()
+            };
+            write_report(&rendered, output.as_deref(), "bench report")?;
+        }
+
+        Commands::Models { command } => {
+            init_logging_stderr_only(cli.verbose);
+            run_models_command(command, &config, config_dir.as_deref()).await?;
+        }
+
+        Commands::Ci { command } => {
+            // The artifact may go to stdout, so keep logs on stderr.
+            init_logging_stderr_only(cli.verbose);
+            let code = run_ci_command(command, &config, config_dir.as_deref()).await?;
+            std::process::exit(code);
+        }
+
+        Commands::Github { command } => {
+            init_logging_stderr_only(cli.verbose);
+            run_github_command(command, &config, config_dir.as_deref()).await?;
+        }
+
+        Commands::Graph { command } => {
+            // Reports go to stdout, so keep logs on stderr.
+            init_logging_stderr_only(cli.verbose);
+            run_graph_command(command, &config, config_dir.as_deref()).await?;
+        }
+
+        Commands::RefactorCheck {
+            symbol,
+            graph,
+            no_rlm,
+            json,
+            output,
+        } => {
+            // The report goes to stdout, so keep logs on stderr.
+            init_logging_stderr_only(cli.verbose);
+            run_refactor_check(
+                &symbol,
+                graph,
+                no_rlm,
+                json,
+                output.as_deref(),
+                &config,
+                config_dir.as_deref(),
+            )
+            .await?;
+        }
+
+        Commands::Onboard {
+            graph,
+            flows,
+            no_rlm,
+            in_repo,
+            output,
+        } => {
+            init_logging(cli.verbose);
+            run_onboard(
+                graph,
+                flows,
+                no_rlm,
+                in_repo,
+                output,
+                &config,
+                config_dir.as_deref(),
+            )
+            .await?;
+        }
+
+        Commands::Coverage {
+            graph,
+            top,
+            json,
+            output,
+        } => {
+            // The report goes to stdout, so keep logs on stderr.
+            init_logging_stderr_only(cli.verbose);
+            run_coverage(
+                graph,
+                top,
+                json,
+                output.as_deref(),
+                &config,
+                config_dir.as_deref(),
+            )?;
+        }
+
+        Commands::Audit {
+            graph,
+            category,
+            json,
+            sarif,
+            output,
+        } => {
+            // The report goes to stdout, so keep logs on stderr.
+            init_logging_stderr_only(cli.verbose);
+            run_audit(
+                graph,
+                category.as_deref(),
+                json,
+                sarif,
+                output.as_deref(),
+                &config,
+                config_dir.as_deref(),
+            )?;
+        }
+
         Commands::Mcp { socket, no_ensure } => {
             // CRITICAL: log to stderr only. stdout is reserved for MCP
             // protocol frames; mixing tracing output in would corrupt
@@ -1693,9 +2387,54 @@ async fn run_hook_command(
             run_hook_submit(config, config_dir).await;
             Ok(())
         }
+        HookCommand::Statusline => {
+            println!("{}", fetch_status_line().await);
+            Ok(())
+        }
     }
 }
 
+/// Register muninn in the project's Claude Code config for `muninn init
+/// --with-hooks`.
+fn install_claude_code_hooks(muninn_dir: &std::path::Path) -> Result<()> {
+    let repo_root = std::env::current_dir()?;
+    let mcp = install::install(install::InstallScope::Project, Some(muninn_dir), false)?;
+    println!(
+        "{}",
+        install::describe_install(&mcp, install::InstallScope::Project)
+    );
+    let settings_path = install::claude_settings_path(&repo_root);
+    let hooks = install::install_hooks(&settings_path, false)?;
+    println!("{}", install::describe_hooks_install(&hooks));
+    Ok(())
+}
+
+/// Status-bar line from the proxy CC is pointed at. CC runs the status
+/// line command with its own environment, so `ANTHROPIC_BASE_URL` is the
+/// proxy `muninn claude` started. Any failure yields a short offline line
+/// rather than an error, since CC shows stdout verbatim.
+async fn fetch_status_line() -> String {
+    const OFFLINE: &str = "muninn · offline";
+    let Ok(base_url) = std::env::var("ANTHROPIC_BASE_URL") else {
+        return OFFLINE.to_string();
+    };
+    let url = format!("{}/statusline", base_url.trim_end_matches('/'));
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_millis(500))
+        .build()
+    {
+        Ok(c) => c,
+        Err(_) => return OFFLINE.to_string(),
+    };
+    let snapshot: Option<serde_json::Value> = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response.json().await.ok(),
+        _ => None,
+    };
+    snapshot
+        .and_then(|s| s.get("line").and_then(|l| l.as_str()).map(str::to_string))
+        .unwrap_or_else(|| OFFLINE.to_string())
+}
+
 /// Resolve the daemon socket path the hook should target — the
 /// repo-scoped path that `muninn daemon ensure` would compute, so
 /// the hook and the daemon agree on where to find each other
@@ -1870,13 +2609,7 @@ async fn submit_inner(
     // default; small fast model). Passthrough cases skip the
     // expensive RLM call entirely.
     let resolved_router = config.resolved_router();
-    let router_backend = create_backend_from_config(
-        &resolved_router.provider,
-        &resolved_router.model,
-        config,
-        config_dir,
-    )?
-    .ok_or_else(|| {
+    let router_backend = create_router_backend(config, config_dir)?.ok_or_else(|| {
         anyhow::anyhow!(
             "no router backend (provider={}, model={})",
             resolved_router.provider,
@@ -1887,85 +2620,1099 @@ async fn submit_inner(
         strategy: muninn_rlm::RouterStrategy::Llm,
         enabled: true,
         router_model: Some(resolved_router.model.clone()),
+        json_mode: config.router.json_mode,
+        system_prompt: None,
     })
     .with_llm(router_backend);
 
-    let probe_request = muninn_rlm::CompletionRequest::new(
-        &resolved_router.model,
-        vec![muninn_rlm::Message::user(prompt)],
-        128,
+    let probe_request = muninn_rlm::CompletionRequest::new(
+        &resolved_router.model,
+        vec![muninn_rlm::Message::user(prompt)],
+        128,
+    );
+    let decision = router.route(&probe_request).await;
+    if decision.is_passthrough() {
+        return Ok(HookResponse::Passthrough);
+    }
+
+    // ── Stage 2: RLM exploration via the daemon ──
+    //
+    // Router said "code context matters." Drive the recursive engine
+    // to produce a complete answer; muninn replaces Claude's
+    // exploration work for this turn.
+    let resolved_rlm = config.resolved_rlm();
+    let user_message = format!("{}\n\nUser prompt:\n{}", SUBMIT_RLM_INSTRUCTION, prompt);
+    let rlm_request = muninn_rlm::CompletionRequest::new(
+        &resolved_rlm.model,
+        vec![muninn_rlm::Message::user(user_message)],
+        2048,
+    )
+    .with_muninn(muninn_rlm::MuninnConfig::recursive());
+
+    let response = {
+        use muninn_rlm::MuninnEngine;
+        client
+            .complete(rlm_request)
+            .await
+            .map_err(|e| anyhow::anyhow!("rlm complete: {e}"))?
+    };
+    let text = response.text();
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Ok(HookResponse::Passthrough);
+    }
+
+    // Prefer the FINAL(...) capture; otherwise relay the body.
+    let answer = extract_final_capture(trimmed)
+        .unwrap_or_else(|| trimmed.to_string())
+        .trim()
+        .to_string();
+    if answer.is_empty() {
+        return Ok(HookResponse::Passthrough);
+    }
+
+    // Answer-shaped framing: muninn primes the conversation with
+    // code context for this turn. The contract is explicit:
+    //   - This is a one-shot priming dump, not a re-callable tool.
+    //   - The answer persists in the agent's context for the whole
+    //     session, so follow-up turns work from what's already here.
+    //   - When a fresh exploration is genuinely warranted, the user
+    //     (or the agent reasoning on the user's behalf) re-triggers
+    //     with `@muninn explore <prompt>`.
+    //   - Quality caveats: file paths + verbatim snippets are
+    //     reliable; line numbers may drift by a few lines.
+    let block = format!(
+        "─── muninn turn-start answer ───\n\
+         Muninn primed this turn with the code context below. Prefer \
+         it as your starting point rather than re-doing the same \
+         exploration — file paths, structural claims, and verbatim \
+         code snippets are reliable; line numbers may be approximate. \
+         Verify what you'd reasonably double-check before acting; \
+         skip what you wouldn't.\n\
+         \n\
+         Scope: this priming is one-shot per user turn. It lives in \
+         your context for the rest of the session — follow-up turns \
+         should work from what's already here using your normal \
+         tools, and the user can re-trigger muninn explicitly with \
+         `@muninn explore <prompt>` when fresh exploration is \
+         warranted.\n\
+         \n\
+         {answer}\n\
+         ─────────────────────────────────"
+    );
+    Ok(HookResponse::Augment(block))
+}
+
+/// Dispatch `muninn graph` subcommands.
+async fn run_graph_command(
+    command: GraphCommand,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let work_path = project_root(config, config_dir);
+    match command {
+        GraphCommand::Orphans {
+            graph,
+            allow,
+            include_entry_points,
+            include_tests,
+            include_ffi,
+            json,
+            sarif,
+            output,
+        } => {
+            let graph_store = require_report_graph_store(graph, config, config_dir)?;
+            let store = graph_store
+                .lock()
+                .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+
+            let filter = muninn_graph::OrphanFilter {
+                skip_entry_points: !include_entry_points,
+                skip_tests: !include_tests,
+                skip_ffi: !include_ffi,
+                allow: config
+                    .graph
+                    .orphan_allow
+                    .iter()
+                    .cloned()
+                    .chain(allow)
+                    .collect(),
+            };
+            let report = muninn_graph::OrphanReport::from_store(&store, &work_path, &filter)?;
+            let rendered = if sarif {
+                serde_json::to_string_pretty(&report.findings().to_sarif())? + "\n"
+            } else if json {
+                serde_json::to_string_pretty(&report)? + "\n"
+            } else {
+                report.to_markdown()
+            };
+            write_report(&rendered, output.as_deref(), "orphan report")
+        }
+
+        GraphCommand::Cycles {
+            graph,
+            level,
+            json,
+            sarif,
+            output,
+        } => {
+            let level = muninn_graph::CycleLevel::parse(&level)
+                .ok_or_else(|| anyhow::anyhow!("unknown cycle level: {}", level))?;
+            let graph_store = require_report_graph_store(graph, config, config_dir)?;
+            let store = graph_store
+                .lock()
+                .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+
+            let report = muninn_graph::CycleReport::from_store(&store, &work_path, level)?;
+            let rendered = if sarif {
+                serde_json::to_string_pretty(&report.findings().to_sarif())? + "\n"
+            } else if json {
+                serde_json::to_string_pretty(&report)? + "\n"
+            } else {
+                report.to_markdown()
+            };
+            write_report(&rendered, output.as_deref(), "cycle report")
+        }
+
+        GraphCommand::Serve { graph, bind } => {
+            let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+            let graph_store = require_graph_store(&graph_path)?;
+            eprintln!("Serving {} on http://{}", graph_path.display(), bind);
+            muninn_rlm::serve_graph(graph_store, bind).await?;
+            Ok(())
+        }
+
+        GraphCommand::Pack { graph, output } => {
+            let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+            if !graph_path.exists() {
+                anyhow::bail!(
+                    "no graph at {}; run `muninn index` first",
+                    graph_path.display()
+                );
+            }
+            let manifest =
+                snapshot::manifest_for(&work_path, &graph_path, &config.graph.extensions)?;
+            let output = output.unwrap_or_else(|| snapshot::default_output(&manifest));
+            muninn_graph::pack_snapshot(&graph_path, &output, &manifest)?;
+            eprintln!(
+                "Packed {} nodes, {} edges at {}{} into {}",
+                manifest.node_count,
+                manifest.edge_count,
+                manifest.commit,
+                if manifest.dirty {
+                    " (with uncommitted changes)"
+                } else {
+                    ""
+                },
+                output.display()
+            );
+            Ok(())
+        }
+
+        GraphCommand::Revisions => {
+            let revisions_dir = revisions_dir(config_dir);
+            let manifests = muninn_graph::GraphPartition::list(&revisions_dir);
+            if manifests.is_empty() {
+                println!("No indexed revisions; run `muninn index --rev <rev>`");
+            }
+            for m in manifests {
+                let when = chrono::DateTime::from_timestamp(m.indexed_at as i64, 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    m.name,
+                    &m.commit[..m.commit.len().min(12)],
+                    m.revision,
+                    when
+                );
+            }
+            Ok(())
+        }
+
+        GraphCommand::Languages { json } => {
+            let languages = index_scope(config)?.languages;
+            let configured = |ext: &str| {
+                config
+                    .graph
+                    .languages
+                    .keys()
+                    .any(|k| k.trim_start_matches('.') == ext)
+            };
+            let mut rows: Vec<(String, Option<&str>, &str)> = languages
+                .extensions()
+                .map(|(ext, language)| {
+                    let source = if configured(ext) { "config" } else { "default" };
+                    (ext.to_string(), Some(language), source)
+                })
+                .collect();
+            for (ext, language) in &config.graph.languages {
+                if language == muninn_graph::language::DISABLED {
+                    rows.push((ext.trim_start_matches('.').to_string(), None, "config"));
+                }
+            }
+            rows.sort();
+            if json {
+                let rows: Vec<_> = rows
+                    .iter()
+                    .map(|(ext, language, source)| {
+                        serde_json::json!({ "extension": ext, "language": language, "source": source })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+                return Ok(());
+            }
+            println!("{:<11}{:<13}Source", "Extension", "Language");
+            for (ext, language, source) in rows {
+                println!(
+                    "{:<11}{:<13}{}",
+                    format!(".{}", ext),
+                    language.unwrap_or("(disabled)"),
+                    source
+                );
+            }
+            println!(
+                "\nBuild files (Dockerfile, Makefile, BUILD, ...) and extensionless \
+                 scripts with a #! line are indexed as well."
+            );
+            Ok(())
+        }
+
+        GraphCommand::Diff {
+            base,
+            head,
+            graph,
+            json,
+            output,
+        } => {
+            let revisions_dir = revisions_dir(config_dir);
+            let open = |name: &str| -> Result<(SharedGraphStore, PathBuf)> {
+                if name == muninn_rlm::CURRENT_REVISION {
+                    let store = require_report_graph_store(graph.clone(), config, config_dir)?;
+                    return Ok((store, work_path.clone()));
+                }
+                let partition = muninn_graph::GraphPartition::new(&revisions_dir, name)?;
+                let manifest = partition.manifest().map_err(|_| {
+                    anyhow::anyhow!(
+                        "revision '{}' is not indexed; run `muninn index --rev {}`",
+                        name,
+                        name
+                    )
+                })?;
+                Ok((muninn_rlm::wrap_store(partition.open()?), manifest.root))
+            };
+            let (base_store, base_root) = open(&base)?;
+            let (head_store, head_root) = open(&head)?;
+            let base_store = base_store
+                .lock()
+                .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+            let head_store = head_store
+                .lock()
+                .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+
+            let diff = muninn_graph::RevisionDiff::between(
+                (&base, &base_store, &base_root),
+                (&head, &head_store, &head_root),
+            )?;
+            let rendered = if json {
+                serde_json::to_string_pretty(&diff)? + "\n"
+            } else {
+                diff.to_markdown()
+            };
+            write_report(&rendered, output.as_deref(), "revision diff")
+        }
+
+        GraphCommand::Unpack {
+            snapshot: snapshot_path,
+            graph,
+            force,
+        } => {
+            let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+            if graph_path.exists() && !force {
+                anyhow::bail!(
+                    "{} already exists; pass --force to replace it",
+                    graph_path.display()
+                );
+            }
+            let manifest = muninn_graph::unpack_snapshot(&snapshot_path, &graph_path)?;
+            let staleness =
+                snapshot::Staleness::check(&work_path, &manifest, &config.graph.extensions);
+            eprintln!(
+                "Installed {} ({} nodes, indexed at {}): {}",
+                graph_path.display(),
+                manifest.node_count,
+                manifest.commit,
+                staleness.describe()
+            );
+            Ok(())
+        }
+
+        GraphCommand::Dump { graph, output } => {
+            let graph_store = require_report_graph_store(graph, config, config_dir)?;
+            let store = graph_store
+                .lock()
+                .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+            let stats = match &output {
+                Some(path) => {
+                    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                    store.dump(file)?
+                }
+                None => store.dump(std::io::stdout().lock())?,
+            };
+            eprintln!(
+                "Dumped {} nodes, {} edges, {} file hashes{}",
+                stats.nodes,
+                stats.edges,
+                stats.file_hashes,
+                output
+                    .map(|p| format!(" to {}", p.display()))
+                    .unwrap_or_default()
+            );
+            Ok(())
+        }
+
+        GraphCommand::Load { dump, graph, force } => {
+            let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+            let reader = std::io::BufReader::new(std::fs::File::open(&dump)?);
+            if let Some(parent) = graph_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let lock = FileLock::try_acquire(&graph_path)?;
+            if graph_path.exists() {
+                if !force {
+                    anyhow::bail!(
+                        "{} already exists; pass --force to replace it",
+                        graph_path.display()
+                    );
+                }
+                remove_graph_db(&graph_path)?;
+            }
+            let store = GraphStore::open_locked(&graph_path, lock)?;
+            let stats = store.load(reader)?;
+            eprintln!(
+                "Loaded {} nodes, {} edges, {} file hashes into {}",
+                stats.nodes,
+                stats.edges,
+                stats.file_hashes,
+                graph_path.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Where `muninn index --rev` puts revision partitions.
+fn revisions_dir(config_dir: Option<&std::path::Path>) -> PathBuf {
+    config_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR))
+        .join(muninn_graph::REVISIONS_DIR)
+}
+
+/// Body of `muninn refactor-check`.
+async fn run_refactor_check(
+    symbol: &str,
+    graph: Option<PathBuf>,
+    no_rlm: bool,
+    json: bool,
+    output: Option<&std::path::Path>,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let work_path = project_root(config, config_dir);
+    let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+    let graph_store = require_graph_store(&graph_path)?;
+
+    let mut report = {
+        let store = graph_store
+            .lock()
+            .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+        refactor::build_report(&store, symbol, &work_path)?
+    };
+
+    if !no_rlm {
+        match create_local_engine(config, config_dir, &work_path, Some(graph_store))? {
+            Some(engine) => {
+                info!("Reviewing {} sites with the RLM...", report.sites.len());
+                let model = config.resolved_rlm().model;
+                if let Err(e) = refactor::review_report(&mut report, engine, &model).await {
+                    tracing::warn!("RLM review skipped: {}", e);
+                }
+            }
+            None => tracing::warn!("No RLM backend configured; skipping review"),
+        }
+    }
+
+    let rendered = if json {
+        serde_json::to_string_pretty(&report)? + "\n"
+    } else {
+        report.to_markdown()
+    };
+    write_report(&rendered, output, "refactor report")
+}
+
+/// Body of `muninn ci`. Returns the process exit code.
+async fn run_ci_command(
+    command: CiCommand,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<i32> {
+    let opts = match &command {
+        CiCommand::Ask { opts, .. } | CiCommand::Review { opts, .. } => opts,
+    };
+    let budget = opts.budget.budget();
+    let work_path = project_root(config, config_dir);
+    let engine = create_headless_engine(config, config_dir, &work_path, &opts.budget)?;
+    let model = config.resolved_rlm().model;
+
+    let artifact = match &command {
+        CiCommand::Ask { question, .. } => ci::ask(engine, &model, question, &budget).await,
+        CiCommand::Review { base, head, .. } => {
+            ci::review(engine, &model, &work_path, base, head, &budget).await
+        }
+    };
+    let rendered = format!("{}\n", serde_json::to_string_pretty(&artifact)?);
+    match &opts.output {
+        Some(path) => std::fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    if let CiCommand::Review {
+        sarif: Some(path), ..
+    } = &command
+    {
+        let sarif = serde_json::to_string_pretty(&artifact.findings().to_sarif())?;
+        std::fs::write(path, sarif + "\n")?;
+    }
+    if let Some(error) = &artifact.error {
+        eprintln!("muninn ci: {}", error);
+    }
+    Ok(artifact.exit_code)
+}
+
+/// In-process engine for `ci` and `github` runs.
+///
+/// `create_local_engine` only uses API-key credentials, so a headless run
+/// can never block on an interactive login.
+fn create_headless_engine(
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+    work_path: &PathBuf,
+    args: &CiBudgetArgs,
+) -> Result<Option<Arc<dyn muninn_core::MuninnEngine>>> {
+    let graph_path = args
+        .graph
+        .clone()
+        .unwrap_or_else(|| config.resolve_graph_path(config_dir));
+    let graph_store = open_graph_store(&graph_path)?;
+    create_local_engine(config, config_dir, work_path, graph_store)
+}
+
+/// Body of `muninn github`.
+async fn run_github_command(
+    command: GithubCommand,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let client = github::GithubClient::from_config(&config.github)?;
+    let work_path = project_root(config, config_dir);
+    let model = config.resolved_rlm().model;
+
+    match command {
+        GithubCommand::Event { path, budget } => {
+            let path = path
+                .or_else(|| std::env::var_os("GITHUB_EVENT_PATH").map(PathBuf::from))
+                .ok_or_else(|| anyhow::anyhow!("no --path and GITHUB_EVENT_PATH is unset"))?;
+            let payload: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let Some(mention) = github::mention_from_event(&payload, &config.github) else {
+                info!(
+                    "Event doesn't mention {} from an allowed user; nothing to do",
+                    config.github.mention
+                );
+                return Ok(());
+            };
+            let engine = create_headless_engine(config, config_dir, &work_path, &budget)?;
+            let artifact =
+                github::answer(&client, engine, &model, &mention, &budget.budget()).await?;
+            println!("{}", serde_json::to_string_pretty(&artifact)?);
+        }
+        GithubCommand::Watch { once, budget } => {
+            let muninn_dir = config_dir.unwrap_or(std::path::Path::new(config::MUNINN_DIR));
+            let engine = create_headless_engine(config, config_dir, &work_path, &budget)?;
+            let budget = budget.budget();
+            let mut state = github::WatchState::load(muninn_dir);
+            let interval = std::time::Duration::from_secs(config.github.poll_interval_secs.max(5));
+            info!("Watching for {} mentions", config.github.mention);
+            loop {
+                match github::poll_once(
+                    &client,
+                    engine.clone(),
+                    &model,
+                    &config.github,
+                    &budget,
+                    &mut state,
+                )
+                .await
+                {
+                    Ok(n) if n > 0 => info!("Answered {} mention(s)", n),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("GitHub poll failed: {}", e),
+                }
+                state.save(muninn_dir)?;
+                if once {
+                    break;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Body of `muninn sessions`.
+async fn run_models_command(
+    command: ModelsCommand,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let ModelsCommand::Advise { model, yes, json } = command;
+    if config.ollama.needs_api_key() {
+        anyhow::bail!(
+            "models advise inspects a local Ollama, but [ollama] base_url is {}",
+            config.ollama.resolved_base_url()
+        );
+    }
+    let model = match model {
+        Some(model) => model,
+        None => [config.resolved_rlm(), config.resolved_router()]
+            .into_iter()
+            .find(|llm| llm.provider == "ollama")
+            .map(|llm| llm.model)
+            .ok_or_else(|| anyhow::anyhow!("no Ollama model configured; pass --model"))?,
+    };
+
+    let api = models::OllamaApi::new(config.ollama.resolved_base_url())?;
+    let info = api.show(&model).await?;
+    let installed = api.installed().await?;
+    let advice = models::advise(
+        info,
+        models_prompt_estimate(config, config_dir)?,
+        &installed,
+    );
+    if json {
+        println!("{}", serde_json::to_string_pretty(&advice)?);
+    } else {
+        print!("{}", advice.to_text());
+    }
+
+    let Some(num_ctx) = advice.recommended_num_ctx else {
+        return Ok(());
+    };
+    let derived = models::derived_name(&model);
+    if !yes {
+        if json {
+            return Ok(());
+        }
+        use std::io::{self, Write};
+        print!(
+            "\nCreate {} with num_ctx {} and use it? [y/N] ",
+            derived, num_ctx
+        );
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim().to_lowercase();
+        if input != "y" && input != "yes" {
+            return Ok(());
+        }
+    }
+
+    api.create(&derived, &model, num_ctx).await?;
+    eprintln!("Created {}", derived);
+    let config_path = config_dir
+        .unwrap_or(std::path::Path::new(config::MUNINN_DIR))
+        .join(config::CONFIG_FILE);
+    let updated = std::fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|text| models::replace_model(&text, &model, &derived));
+    match updated {
+        Some(text) => {
+            std::fs::write(&config_path, text)?;
+            eprintln!("Updated {} to use {}", config_path.display(), derived);
+        }
+        None => eprintln!(
+            "{} doesn't name {}; set model = \"{}\" where the model is configured",
+            config_path.display(),
+            model,
+            derived
+        ),
+    }
+    Ok(())
+}
+
+/// A typical RLM prompt for this project: the uncategorised system
+/// prompt, the directory tree, every tool and the conversation that
+/// `[compaction]` lets through.
+fn models_prompt_estimate(
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<models::PromptEstimate> {
+    let root = project_root(config, config_dir);
+    let muninn_dir = config_dir.unwrap_or(std::path::Path::new(config::MUNINN_DIR));
+    let tools = muninn_sdk::create_tools(
+        &root,
+        open_configured_graph_store(config, config_dir)?,
+        muninn_sdk::open_doc_store(&muninn_dir.join("docs.db"))?,
+        &muninn_sdk::LoadedPlugins::default(),
+    );
+    let tools = serde_json::to_string(&muninn_rlm::ToolEnvironment::available_tools(&tools))?;
+    let system = muninn_sdk::config_to_prompts(&config.prompts).system_prompt(None);
+    let dir_tree = muninn_rlm::engine::generate_dir_tree(&root).unwrap_or_default();
+    Ok(models::PromptEstimate {
+        system: models::approx_tokens(&system),
+        dir_tree: models::approx_tokens(&dir_tree),
+        tools: models::approx_tokens(&tools),
+        conversation: if config.compaction.enabled {
+            config.compaction.threshold_tokens
+        } else {
+            models::TYPICAL_CONVERSATION_TOKENS
+        },
+        output: models::OUTPUT_RESERVE_TOKENS,
+    })
+}
+
+fn run_keys_command(command: KeysCommand, muninn_dir: &std::path::Path) -> Result<()> {
+    let file = muninn_rlm::ClientKeyFile::in_dir(muninn_dir);
+    match command {
+        KeysCommand::Create {
+            name,
+            daily_tokens,
+            requests_per_minute,
+            role,
+        } => {
+            let quota = muninn_rlm::KeyQuota {
+                daily_tokens,
+                requests_per_minute,
+            };
+            let (key, _) = file.create(&name, quota, role.as_deref())?;
+            println!("{}", key);
+            eprintln!(
+                "Issued a key for '{}'. It is not stored and won't be shown again.",
+                name
+            );
+            eprintln!("Clients send it as x-api-key; enable [keys] in config.toml to require it.");
+        }
+        KeysCommand::List { json } => {
+            let keys = file.load()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&keys)?);
+                return Ok(());
+            }
+            if keys.is_empty() {
+                println!("No keys");
+                return Ok(());
+            }
+            println!(
+                "{:<20} {:<12} {:<12} {:<17} {:>12} {:>8} {:<7}",
+                "NAME", "KEY", "ROLE", "CREATED", "DAILY TOKENS", "PER MIN", "STATUS"
+            );
+            for key in &keys {
+                let limit = |l: Option<String>| l.unwrap_or_else(|| "-".to_string());
+                println!(
+                    "{:<20} {:<12} {:<12} {:<17} {:>12} {:>8} {:<7}",
+                    key.name,
+                    key.hint,
+                    key.role.as_deref().unwrap_or("-"),
+                    key.created_at.format("%Y-%m-%d %H:%M"),
+                    limit(key.quota.daily_tokens.map(|t| t.to_string())),
+                    limit(key.quota.requests_per_minute.map(|r| r.to_string())),
+                    if key.revoked { "revoked" } else { "active" }
+                );
+            }
+        }
+        KeysCommand::Revoke { name } => {
+            if !file.revoke(&name)? {
+                anyhow::bail!("No active key named '{}'", name);
+            }
+            println!("Revoked the key for '{}'", name);
+        }
+    }
+    Ok(())
+}
+
+fn run_sessions_command(
+    command: SessionsCommand,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let muninn_dir = config_dir.unwrap_or(std::path::Path::new(config::MUNINN_DIR));
+    match command {
+        SessionsCommand::List { json } => {
+            let sessions = session::list_sessions(muninn_dir);
+            if json {
+                let now = chrono::Utc::now();
+                let entries: Vec<_> = sessions
+                    .iter()
+                    .map(|m| {
+                        let mut value = serde_json::to_value(m).unwrap_or_default();
+                        value["status"] = m.status(now).to_string().into();
+                        value
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+                return Ok(());
+            }
+            if sessions.is_empty() {
+                println!("No sessions");
+                return Ok(());
+            }
+            let now = chrono::Utc::now();
+            println!(
+                "{:<26} {:<6} {:<17} {:<17} {:>8} {:>6} {:>10} {:>10} {:>7} {:>8}",
+                "SESSION",
+                "STATUS",
+                "STARTED",
+                "LAST ACTIVITY",
+                "REQUESTS",
+                "RLM",
+                "IN TOKENS",
+                "OUT TOKENS",
+                "QUALITY",
+                "FEEDBACK"
+            );
+            let format_time = |t: Option<chrono::DateTime<chrono::Utc>>| {
+                t.map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|| "-".to_string())
+            };
+            for m in &sessions {
+                let feedback = if m.totals.good_feedback + m.totals.bad_feedback > 0 {
+                    format!("+{}/-{}", m.totals.good_feedback, m.totals.bad_feedback)
+                } else {
+                    "-".to_string()
+                };
+                println!(
+                    "{:<26} {:<6} {:<17} {:<17} {:>8} {:>6} {:>10} {:>10} {:>7} {:>8}",
+                    m.session_id,
+                    m.status(now),
+                    format_time(Some(m.started_at)),
+                    format_time(m.last_activity),
+                    m.totals.requests,
+                    m.totals.rlm_requests,
+                    m.totals.input_tokens,
+                    m.totals.output_tokens,
+                    m.totals
+                        .mean_quality
+                        .map_or("-".to_string(), |q| format!("{:.2}", q)),
+                    feedback
+                );
+            }
+            Ok(())
+        }
+        SessionsCommand::Show {
+            session,
+            explorations,
+            feedback,
+            json,
+        } => {
+            let metadata = match session {
+                Some(id) => session::read_metadata(&session::session_dir(
+                    muninn_dir,
+                    &session::SessionId::from_string(id.as_str()),
+                ))
+                .map_err(|e| anyhow::anyhow!("Unknown session {}: {}", id, e))?,
+                None => session::list_sessions(muninn_dir)
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("No sessions"))?,
+            };
+            let dir = session::session_dir(
+                muninn_dir,
+                &session::SessionId::from_string(metadata.session_id.as_str()),
+            );
+            if explorations {
+                let reports = muninn_rlm::exploration_reports(&dir);
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&reports)?);
+                } else if reports.is_empty() {
+                    println!("No exploration reports in session {}", metadata.session_id);
+                } else {
+                    for report in &reports {
+                        let question = report.question.as_deref().unwrap_or("-");
+                        let question: String = question.chars().take(80).collect();
+                        println!("{}  {}", report.title, question);
+                        println!("    {}", report.path.display());
+                    }
+                }
+                return Ok(());
+            }
+            if feedback {
+                let handling: std::collections::HashMap<String, String> =
+                    muninn_rlm::SpendLedger::in_dir(&dir)
+                        .read()?
+                        .into_iter()
+                        .map(|e| (e.trace_id, e.handling))
+                        .collect();
+                let entries = muninn_rlm::FeedbackLog::in_dir(&dir).read()?;
+                if json {
+                    let labeled: Vec<serde_json::Value> = entries
+                        .iter()
+                        .map(|entry| {
+                            let mut value = serde_json::to_value(entry).unwrap_or_default();
+                            value["handling"] = handling.get(&entry.trace_id).cloned().into();
+                            value
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&labeled)?);
+                } else if entries.is_empty() {
+                    println!("No feedback in session {}", metadata.session_id);
+                } else {
+                    for entry in &entries {
+                        let question = entry.question.as_deref().unwrap_or("-");
+                        let question: String = question.chars().take(80).collect();
+                        println!(
+                            "{:<4} {:<11} {}  {}",
+                            entry.rating.as_str(),
+                            handling.get(&entry.trace_id).map_or("-", String::as_str),
+                            entry.trace_id,
+                            question
+                        );
+                        if let Some(comment) = &entry.comment {
+                            println!("    {}", comment);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            if json {
+                let mut value = serde_json::to_value(&metadata)?;
+                value["status"] = metadata.status(chrono::Utc::now()).to_string().into();
+                println!("{}", serde_json::to_string_pretty(&value)?);
+                return Ok(());
+            }
+            let totals = &metadata.totals;
+            println!("Session:      {}", metadata.session_id);
+            println!("Status:       {}", metadata.status(chrono::Utc::now()));
+            println!("Started:      {}", metadata.started_at);
+            println!("Work dir:     {}", metadata.work_dir.display());
+            if let Some(strategy) = &metadata.router_strategy {
+                println!("Router:       {}", strategy);
+            }
+            if let Some(model) = &metadata.rlm_model {
+                println!("RLM model:    {}", model);
+            }
+            println!(
+                "Requests:     {} ({} RLM)",
+                totals.requests, totals.rlm_requests
+            );
+            println!(
+                "Tokens:       {} in, {} out",
+                totals.input_tokens, totals.output_tokens
+            );
+            if let Some(quality) = totals.mean_quality {
+                println!("Quality:      {:.2}", quality);
+            }
+            if totals.good_feedback + totals.bad_feedback > 0 {
+                println!(
+                    "Feedback:     {} good, {} bad (muninn sessions show {} --feedback)",
+                    totals.good_feedback, totals.bad_feedback, metadata.session_id
+                );
+            }
+            println!(
+                "Explorations: {} (muninn sessions show {} --explorations)",
+                muninn_rlm::exploration_reports(&dir).len(),
+                metadata.session_id
+            );
+            println!("Directory:    {}", dir.display());
+            Ok(())
+        }
+        SessionsCommand::Clean {
+            debug_only,
+            dry_run,
+        } => {
+            let plan = session::plan_clean(muninn_dir, config.debug.dir.as_deref(), debug_only);
+            if plan.paths.is_empty() {
+                println!("Nothing to clean");
+                return Ok(());
+            }
+            for path in &plan.paths {
+                println!("{}", path.display());
+            }
+            let size = format!("{:.1} MB", plan.bytes as f64 / (1024.0 * 1024.0));
+            if dry_run {
+                println!("Would delete {} paths ({})", plan.paths.len(), size);
+            } else {
+                session::apply_clean(&plan)?;
+                println!("Deleted {} paths ({})", plan.paths.len(), size);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Body of `muninn coverage`.
+fn run_coverage(
+    graph: Option<PathBuf>,
+    top: usize,
+    json: bool,
+    output: Option<&std::path::Path>,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let work_path = project_root(config, config_dir);
+    let muninn_dir = config_dir.unwrap_or(std::path::Path::new(config::MUNINN_DIR));
+    let coverage = muninn_rlm::ExplorationCoverage::from_muninn_dir(muninn_dir, &work_path)?;
+
+    // Without a graph there is nothing to compare against, so only the
+    // hot spots are reported.
+    let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+    let known_files = match open_graph_store(&graph_path)? {
+        Some(graph_store) => {
+            let store = graph_store
+                .lock()
+                .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+            store
+                .file_paths()?
+                .into_iter()
+                .map(|path| {
+                    let path = std::path::Path::new(&path);
+                    path.strip_prefix(&work_path)
+                        .unwrap_or(path)
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let report = coverage.report(known_files, top);
+    let rendered = if json {
+        serde_json::to_string_pretty(&report)? + "\n"
+    } else {
+        report.to_markdown()
+    };
+    write_report(&rendered, output, "coverage report")
+}
+
+/// Body of `muninn audit`.
+fn run_audit(
+    graph: Option<PathBuf>,
+    category: Option<&str>,
+    json: bool,
+    sarif: bool,
+    output: Option<&std::path::Path>,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let category = category
+        .map(|c| {
+            muninn_graph::Sensitivity::parse(c)
+                .ok_or_else(|| anyhow::anyhow!("unknown sensitivity category: {}", c))
+        })
+        .transpose()?;
+    let work_path = project_root(config, config_dir);
+    let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+    let graph_store = require_graph_store(&graph_path)?;
+    let store = graph_store
+        .lock()
+        .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+
+    let report = muninn_graph::AuditReport::from_store(&store, &work_path, category)?;
+    let rendered = if sarif {
+        serde_json::to_string_pretty(&report.findings().to_sarif())? + "\n"
+    } else if json {
+        serde_json::to_string_pretty(&report)? + "\n"
+    } else {
+        report.to_markdown()
+    };
+    write_report(&rendered, output, "audit report")
+}
+
+/// Body of `muninn onboard`.
+async fn run_onboard(
+    graph: Option<PathBuf>,
+    flows: usize,
+    no_rlm: bool,
+    in_repo: bool,
+    output: Option<PathBuf>,
+    config: &Config,
+    config_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let work_path = project_root(config, config_dir);
+    let graph_path = graph.unwrap_or_else(|| config.resolve_graph_path(config_dir));
+    let graph_store = require_graph_store(&graph_path)?;
+
+    let overview = {
+        let store = graph_store
+            .lock()
+            .map_err(|e| anyhow::anyhow!("graph store lock poisoned: {}", e))?;
+        muninn_graph::GraphOverview::from_store(&store, &work_path)?
+    };
+    info!(
+        "Graph: {} symbols, {} entry points",
+        overview.symbol_count,
+        overview.entry_points.len()
     );
-    let decision = router.route(&probe_request).await;
-    if decision.is_passthrough() {
-        return Ok(HookResponse::Passthrough);
+
+    let engine = if no_rlm {
+        None
+    } else {
+        match create_local_engine(config, config_dir, &work_path, Some(graph_store))? {
+            Some(engine) => Some((engine, config.resolved_rlm().model)),
+            None => {
+                tracing::warn!("No RLM backend configured; writing graph sections only");
+                None
+            }
+        }
+    };
+
+    let project = onboard::project_name(&work_path);
+    let document = onboard::build_walkthrough(&project, &overview, engine, flows).await;
+
+    let path = match output {
+        Some(path) => path,
+        None if in_repo => work_path.join("WALKTHROUGH.md"),
+        None => config_dir
+            .map(PathBuf::from)
+            .unwrap_or_else(|| work_path.join(".muninn"))
+            .join("WALKTHROUGH.md"),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    std::fs::write(&path, document)?;
+    println!("Wrote walkthrough to {}", path.display());
+    Ok(())
+}
 
-    // ── Stage 2: RLM exploration via the daemon ──
-    //
-    // Router said "code context matters." Drive the recursive engine
-    // to produce a complete answer; muninn replaces Claude's
-    // exploration work for this turn.
-    let resolved_rlm = config.resolved_rlm();
-    let user_message = format!("{}\n\nUser prompt:\n{}", SUBMIT_RLM_INSTRUCTION, prompt);
-    let rlm_request = muninn_rlm::CompletionRequest::new(
-        &resolved_rlm.model,
-        vec![muninn_rlm::Message::user(user_message)],
-        2048,
+/// Run one recursive exploration on a local engine and return its
+/// answer (the FINAL(...) capture when present, otherwise the body).
+async fn explore_once(
+    engine: &Arc<dyn muninn_core::MuninnEngine>,
+    model: &str,
+    prompt: String,
+    max_tokens: u32,
+) -> Result<String> {
+    let request = muninn_rlm::CompletionRequest::new(
+        model,
+        vec![muninn_rlm::Message::user(prompt)],
+        max_tokens,
     )
     .with_muninn(muninn_rlm::MuninnConfig::recursive());
-
-    let response = {
-        use muninn_rlm::MuninnEngine;
-        client
-            .complete(rlm_request)
-            .await
-            .map_err(|e| anyhow::anyhow!("rlm complete: {e}"))?
-    };
+    let response = engine
+        .complete(request)
+        .await
+        .map_err(|e| anyhow::anyhow!("rlm complete: {e}"))?;
     let text = response.text();
     let trimmed = text.trim();
-    if trimmed.is_empty() {
-        return Ok(HookResponse::Passthrough);
-    }
-
-    // Prefer the FINAL(...) capture; otherwise relay the body.
-    let answer = extract_final_capture(trimmed)
-        .unwrap_or_else(|| trimmed.to_string())
-        .trim()
-        .to_string();
-    if answer.is_empty() {
-        return Ok(HookResponse::Passthrough);
-    }
-
-    // Answer-shaped framing: muninn primes the conversation with
-    // code context for this turn. The contract is explicit:
-    //   - This is a one-shot priming dump, not a re-callable tool.
-    //   - The answer persists in the agent's context for the whole
-    //     session, so follow-up turns work from what's already here.
-    //   - When a fresh exploration is genuinely warranted, the user
-    //     (or the agent reasoning on the user's behalf) re-triggers
-    //     with `@muninn explore <prompt>`.
-    //   - Quality caveats: file paths + verbatim snippets are
-    //     reliable; line numbers may drift by a few lines.
-    let block = format!(
-        "─── muninn turn-start answer ───\n\
-         Muninn primed this turn with the code context below. Prefer \
-         it as your starting point rather than re-doing the same \
-         exploration — file paths, structural claims, and verbatim \
-         code snippets are reliable; line numbers may be approximate. \
-         Verify what you'd reasonably double-check before acting; \
-         skip what you wouldn't.\n\
-         \n\
-         Scope: this priming is one-shot per user turn. It lives in \
-         your context for the rest of the session — follow-up turns \
-         should work from what's already here using your normal \
-         tools, and the user can re-trigger muninn explicitly with \
-         `@muninn explore <prompt>` when fresh exploration is \
-         warranted.\n\
-         \n\
-         {answer}\n\
-         ─────────────────────────────────"
-    );
-    Ok(HookResponse::Augment(block))
+    Ok(extract_final_capture(trimmed).unwrap_or_else(|| trimmed.to_string()))
 }
 
 /// Extract the FINAL(...) capture from an RLM response if present.
@@ -2104,61 +3851,28 @@ async fn run_daemon_command(
             let socket_path = resolve_daemon_socket(socket, config_dir);
 
             // Build a default engine using the resolved tiered config.
-            let resolved_rlm = config.resolved_rlm();
-            let rlm_backend = create_backend_from_config(
-                &resolved_rlm.provider,
-                &resolved_rlm.model,
-                config,
-                config_dir,
-            )?
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "no backend available for daemon (provider={}, model={}). \
-                     Configure credentials and retry.",
-                    resolved_rlm.provider,
-                    resolved_rlm.model
-                )
-            })?;
-
-            // Build minimal tools + stores aligned with the proxy path's
-            // construction. The daemon shares the same engine shape.
-            let work_path = config_dir
-                .map(|d| d.join(&config.project.root))
-                .unwrap_or_else(|| config.project.root.clone());
-            let work_path = work_path.canonicalize().unwrap_or(work_path);
-
-            let graph_path = config.resolve_graph_path(config_dir);
-            let graph_store = open_graph_store(&graph_path)?;
-
-            let doc_path = config_dir
-                .map(|d| d.join("docs.db"))
-                .unwrap_or_else(|| PathBuf::from(".muninn/docs.db"));
-            let doc_store = open_doc_store(&doc_path)?;
-
-            // Keep a handle to the graph store for the engine; the
-            // tools layer needs its own clone, so split before
-            // consuming into create_tools.
-            let engine_graph_store = graph_store.clone();
-            let tools: Arc<dyn muninn_rlm::ToolEnvironment> =
-                Arc::new(create_tools(&work_path, graph_store, doc_store));
-
-            let engine = muninn_rlm::engine::default_engine_with_graph(
-                rlm_backend,
-                tools,
-                Some(config_to_rlm_budget(&config.budget)),
-                Some(work_path.clone()),
-                engine_graph_store,
-            );
+            let work_path = project_root(config, config_dir);
+            let graph_store = open_configured_graph_store(config, config_dir)?;
+            let engine = create_local_engine(config, config_dir, &work_path, graph_store.clone())?
+                .ok_or_else(|| {
+                    let resolved_rlm = config.resolved_rlm();
+                    anyhow::anyhow!(
+                        "no backend available for daemon (provider={}, model={}). \
+                         Configure credentials and retry.",
+                        resolved_rlm.provider,
+                        resolved_rlm.model
+                    )
+                })?;
 
             // The daemon does NOT auto-reindex. Narsil's extraction
             // is fast enough to re-run on demand (a few seconds even
             // for medium repos), and the watch-and-rebuild pattern
             // introduced its own correctness gaps. Users re-run
             // `muninn index` when they want fresh graph state.
-            if !graph_path.exists() {
+            if graph_store.is_none() {
                 tracing::info!(
                     "graph DB missing at {}; daemon will run with an empty graph. Run `muninn index` to populate.",
-                    graph_path.display()
+                    config.resolve_graph_path(config_dir).display()
                 );
             }
 
@@ -2203,6 +3917,167 @@ async fn run_daemon_command(
     }
 }
 
+/// Resend recorded upstream exchanges and summarize the results.
+async fn run_replay_upstream(
+    file: &std::path::Path,
+    index: Option<usize>,
+    output: Option<&std::path::Path>,
+    config: &Config,
+    config_dir: Option<PathBuf>,
+) -> Result<()> {
+    let recordings = muninn_rlm::read_recordings(file)?;
+    let selected: Vec<(usize, &muninn_rlm::RecordedExchange)> = match index {
+        Some(i) => {
+            let exchange = recordings.get(i).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} has {} recorded exchanges; index {} is out of range",
+                    file.display(),
+                    recordings.len(),
+                    i
+                )
+            })?;
+            vec![(i, exchange)]
+        }
+        None => recordings.iter().enumerate().collect(),
+    };
+
+    let muninn_dir = config_dir.unwrap_or_else(|| PathBuf::from(config::MUNINN_DIR));
+    let token_manager: muninn_rlm::SharedTokenManager =
+        Arc::new(file_token_manager(&muninn_dir, config)?);
+    let passthrough = muninn_rlm::Passthrough::try_with_config(upstream_passthrough_config(
+        config,
+        &muninn_dir,
+    )?)?
+    .with_token_manager(token_manager);
+    let api_key = std::env::var("ANTHROPIC_API_KEY").ok();
+
+    let mut replayed = Vec::new();
+    for (i, recorded) in selected {
+        let result = passthrough.replay(recorded, api_key.as_deref()).await?;
+        let status = |s: Option<u16>| s.map_or("-".to_string(), |s| s.to_string());
+        println!(
+            "[{}] {} {}: recorded {} -> replayed {} ({} ms)",
+            i,
+            result.method,
+            result.url,
+            status(recorded.status),
+            status(result.status),
+            result.duration_ms
+        );
+        if !result.status.is_some_and(|s| (200..300).contains(&s)) {
+            let body = result
+                .response
+                .as_ref()
+                .map(|v| v.to_string())
+                .or_else(|| result.response_body.clone())
+                .unwrap_or_default();
+            println!("    {}", body);
+        }
+        replayed.push(result);
+    }
+
+    if let Some(path) = output {
+        let recorder = muninn_rlm::UpstreamRecorder::new(path);
+        for exchange in replayed {
+            recorder.record(exchange);
+        }
+        println!("Wrote replayed exchanges to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Re-route (and re-answer) recorded agent requests and summarize what
+/// changed.
+async fn run_replay_session(
+    file: &std::path::Path,
+    index: Option<usize>,
+    routes_only: bool,
+    output: Option<&std::path::Path>,
+    builder: MuninnBuilder,
+) -> Result<()> {
+    let recordings = muninn_rlm::read_session_recording(file)?;
+    let selected: Vec<(usize, &muninn_rlm::RecordedRequest)> = match index {
+        Some(i) => {
+            let recorded = recordings.get(i).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} has {} recorded requests; index {} is out of range",
+                    file.display(),
+                    recordings.len(),
+                    i
+                )
+            })?;
+            vec![(i, recorded)]
+        }
+        None => recordings.iter().enumerate().collect(),
+    };
+
+    let mut replayer = muninn_rlm::SessionReplayer::new(builder.build_router()?);
+    if !routes_only {
+        match builder.build_engine()? {
+            Some(engine) => replayer = replayer.with_engine(Arc::new(engine)),
+            None => println!("No RLM backend configured; comparing routes only"),
+        }
+    }
+
+    let mut route_changes = 0;
+    let mut answer_changes = 0;
+    let mut similarities = Vec::new();
+    let mut replayed = Vec::new();
+    for (i, recorded) in selected {
+        let result = replayer.replay(recorded).await;
+        let marker = if result.route_changed() { "!" } else { " " };
+        println!(
+            "{}[{}] route: recorded {} -> replayed {}{}",
+            marker,
+            i,
+            result.recorded.route,
+            result.replayed.route,
+            result
+                .replayed
+                .reason
+                .as_deref()
+                .map(|r| format!(" ({})", r))
+                .unwrap_or_default()
+        );
+        if result.route_changed() {
+            route_changes += 1;
+        }
+        if let Some(error) = &result.replayed.error {
+            println!("    answer failed: {}", error);
+        } else if let Some(similarity) = result.answer_similarity() {
+            println!("    answer similarity: {:.2}", similarity);
+            similarities.push(similarity);
+            if result.answer_changed() {
+                answer_changes += 1;
+            }
+        }
+        replayed.push(result.replayed);
+    }
+
+    let total = replayed.len();
+    print!("{} requests, {} route changes", total, route_changes);
+    if similarities.is_empty() {
+        println!();
+    } else {
+        let mean = similarities.iter().sum::<f64>() / similarities.len() as f64;
+        println!(
+            ", {} of {} answers changed (mean similarity {:.2})",
+            answer_changes,
+            similarities.len(),
+            mean
+        );
+    }
+
+    if let Some(path) = output {
+        let recorder = muninn_rlm::SessionRecorder::new(path);
+        for recorded in replayed {
+            recorder.record(recorded);
+        }
+        println!("Wrote replayed requests to {}", path.display());
+    }
+    Ok(())
+}
+
 /// Configuration for launching an agent with muninn proxy.
 struct AgentLaunchConfig {
     /// Port for the proxy server (0 = auto-select).
@@ -2213,6 +4088,8 @@ struct AgentLaunchConfig {
     router_strategy: Option<String>,
     /// Working directory override.
     workdir: Option<PathBuf>,
+    /// Record upstream request/response pairs.
+    record: bool,
     /// The agent command to run (e.g., "claude", "cursor").
     agent_cmd: String,
     /// Arguments to pass to the agent.
@@ -2251,12 +4128,15 @@ async fn run_with_agent(launch: AgentLaunchConfig) -> Result<()> {
         }
     };
 
-    // Initialize logging to file (keeps terminal clean for agent)
+    // Every agent run gets a session, like `muninn proxy`
+    let mut session = session::ProxySession::create(&muninn_dir)?;
+
+    // Initialize logging to the session (keeps terminal clean for agent)
     if launch.verbose {
-        // In verbose mode, also log to terminal
+        // In verbose mode, log to terminal instead
         init_logging(true);
     } else {
-        init_agent_logging(&muninn_dir);
+        init_session_logging(&session.dir, true);
     }
 
     // Find an available port if port is 0
@@ -2277,78 +4157,8 @@ async fn run_with_agent(launch: AgentLaunchConfig) -> Result<()> {
     // Emit deprecation warning if using old [backend] section
     launch.config.warn_deprecated_backend();
 
-    // Configure router strategy
-    let router_strategy = launch
-        .router_strategy
-        .map(|s| parse_router_strategy(&s))
-        .unwrap_or_else(|| parse_router_strategy(&launch.config.router.strategy));
-
-    let resolved_router = launch.config.resolved_router();
-    let resolved_rlm = launch.config.resolved_rlm();
-
-    let router_config = RouterConfig {
-        strategy: router_strategy,
-        enabled: launch.config.router.enabled,
-        router_model: Some(resolved_router.model.clone()),
-    };
-
-    // Open graph store if available, or start background indexing
-    let graph_path = launch.config.resolve_graph_path(Some(&muninn_dir));
-    let graph_store = open_graph_store(&graph_path)?;
-
-    // Open doc store if available (default: .muninn/docs.db)
-    let doc_path = muninn_dir.join("docs.db");
-    let doc_store = open_doc_store(&doc_path)?;
-
-    // Note: this legacy agent-launch path does NOT auto-bootstrap
-    // the graph. Run `muninn index` once before launching if you
-    // want a populated graph. The watcher / background-build paths
-    // were removed when we adopted narsil's extractor.
-    let _ = (&graph_store, &graph_path, &launch.config.graph.extensions);
-
-    // Create separate backends for router and RLM
-    // If CLI provides groq_key, use it for both; otherwise use config
-    let (router_backend, rlm_backend) = if let Some(key) = launch.groq_key.clone() {
-        info!("Using Groq backend from CLI for both router and RLM");
-        let router_groq = GroqConfig::new(key.clone()).with_model(&resolved_router.model);
-        let rlm_groq = GroqConfig::new(key).with_model(&resolved_rlm.model);
-        (
-            Some(Arc::new(GroqBackend::new(router_groq)?) as Arc<dyn muninn_rlm::LLMBackend>),
-            Some(Arc::new(GroqBackend::new(rlm_groq)?) as Arc<dyn muninn_rlm::LLMBackend>),
-        )
-    } else {
-        // Create router backend
-        let router_backend = create_backend_from_config(
-            &resolved_router.provider,
-            &resolved_router.model,
-            &launch.config,
-            Some(&muninn_dir),
-        )?;
-
-        // Create RLM backend
-        let rlm_backend = create_backend_from_config(
-            &resolved_rlm.provider,
-            &resolved_rlm.model,
-            &launch.config,
-            Some(&muninn_dir),
-        )?;
-
-        (router_backend, rlm_backend)
-    };
-
-    // Log which models are being used
-    info!(
-        "Router: {} via {}",
-        resolved_router.model, resolved_router.provider
-    );
-    info!("RLM: {} via {}", resolved_rlm.model, resolved_rlm.provider);
-
-    // Create tools
-    let tools: Arc<dyn muninn_rlm::ToolEnvironment> =
-        Arc::new(create_tools(&work_path, graph_store, doc_store));
-
     // Token manager uses the muninn_dir we resolved earlier
-    let token_manager = FileTokenManager::new(&muninn_dir);
+    let token_manager = file_token_manager(&muninn_dir, &launch.config)?;
 
     // Check if API key is available as fallback
     let has_api_key = std::env::var("ANTHROPIC_API_KEY").is_ok();
@@ -2384,41 +4194,61 @@ async fn run_with_agent(launch: AgentLaunchConfig) -> Result<()> {
         run_oauth_flow(&token_manager).await?;
     }
 
-    let shared_token_manager = create_token_manager(&muninn_dir);
+    let mut builder = MuninnBuilder::new(launch.config.clone())
+        .with_muninn_dir(&muninn_dir)
+        .with_work_dir(&work_path)
+        .with_upstream_recording(launch.record);
+    if let Some(strategy) = &launch.router_strategy {
+        builder = builder.with_router_strategy(parse_router_strategy(strategy));
+    }
+    if let Some(key) = launch.groq_key.clone() {
+        builder = builder.with_groq_key(key);
+    }
+    let profile = launch.config.agent_profile(&launch.agent_cmd);
+    if profile.api == config::AgentApi::Openai {
+        builder = builder.with_chat_translation(muninn_rlm::ChatTranslation::Anthropic {
+            default_model: profile.model.clone(),
+        });
+    }
+    builder = builder
+        .with_answer_format(profile.answer_format)
+        .with_tool_delta_coalescing(profile.coalesce_tool_deltas);
 
-    // Create and start the proxy server with OAuth support
-    let rlm_budget = config_to_rlm_budget(&launch.config.budget);
-    info!(
-        "Budget config: max_depth={}, max_tool_calls={}, max_tokens={}",
-        launch.config.budget.max_depth,
-        launch.config.budget.max_tool_calls,
-        launch.config.budget.max_tokens
-    );
+    // Without a graph yet, index in the background and add the graph
+    // tools once it's built (a remote graph is never indexed locally)
+    let graph_path = launch.config.resolve_graph_path(Some(&muninn_dir));
+    let lazy_graph =
+        (launch.config.graph.url.is_none() && !graph_path.exists()).then(LazyGraphStore::new);
+    let index_status = lazy_graph.as_ref().map(|_| IndexStatus::new());
+    let index_queue = IndexQueue::new();
+    let scope = index_scope(&launch.config)?;
+    if let (Some(lazy), Some(status)) = (&lazy_graph, &index_status) {
+        builder = builder
+            .with_lazy_graph(lazy.clone())
+            .with_index_status(status.clone())
+            .with_index_queue(index_queue.clone());
+    }
 
-    let proxy_config = ProxyConfig::new(addr)
-        .with_token_manager(shared_token_manager)
-        .with_budget(rlm_budget)
-        .with_work_dir(&work_path);
-
-    // Build server with separate router and RLM backends
-    let server = match (router_backend, rlm_backend) {
-        (Some(router_be), Some(rlm_be)) => ProxyServer::with_separate_backends(
-            proxy_config,
-            router_be,
-            rlm_be,
-            tools,
-            router_config,
-        ),
-        (_, Some(rlm_be)) => {
-            // No router backend, use RLM backend for both
-            info!("Router backend not available, using RLM backend for routing");
-            ProxyServer::with_router(proxy_config, rlm_be, tools, router_config)
-        }
-        _ => {
-            info!("No RLM backend configured, running in passthrough-only mode");
-            ProxyServer::passthrough_only(proxy_config)
-        }
-    };
+    if launch.record {
+        info!("Recording upstream traffic to {:?}", session.dir);
+    }
+    let server = session_proxy(
+        builder,
+        &mut session,
+        addr,
+        serde_json::json!({ "agent": launch.agent_cmd, "addr": addr.to_string() }),
+    )?;
+    if let (Some(lazy), Some(status)) = (lazy_graph, index_status.clone()) {
+        spawn_background_index(
+            lazy,
+            status,
+            index_queue,
+            work_path.clone(),
+            graph_path,
+            scope,
+            session.dir.clone(),
+        );
+    }
 
     // Channel to signal proxy is ready
     let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
@@ -2449,15 +4279,18 @@ async fn run_with_agent(launch: AgentLaunchConfig) -> Result<()> {
     // Clear screen before launching agent for clean TUI handoff
     // This ensures no shell prompt residue when Claude takes over the terminal
     print!("\x1b[2J\x1b[H");
+    if let Some(status) = &index_status
+        && launch.config.graph.index_status_line
+        && let Some(line) = status.snapshot().line()
+    {
+        println!("muninn: {line} (graph tools attach when done; see /statusline)");
+    }
     std::io::Write::flush(&mut std::io::stdout())?;
 
-    // Launch agent with environment configured
-    // Claude Code uses ANTHROPIC_AUTH_TOKEN (not API_KEY) for custom endpoints
+    // Launch agent with the environment its profile asks for
     let mut cmd = Command::new(&launch.agent_cmd);
     cmd.args(&launch.agent_args)
-        .env("ANTHROPIC_BASE_URL", &proxy_url)
-        .env("ANTHROPIC_AUTH_TOKEN", &api_key)
-        .env("NO_PROXY", "127.0.0.1") // Prevent proxy interference
+        .envs(profile.render_env(&proxy_url, &api_key))
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
@@ -2495,11 +4328,271 @@ async fn run_with_agent(launch: AgentLaunchConfig) -> Result<()> {
 
     // Shutdown proxy
     proxy_handle.abort();
+    session.finish();
     info!("Muninn proxy stopped");
 
     Ok(())
 }
 
+/// Delete a graph database and any sqlite sidecar files (WAL, SHM).
+fn remove_graph_db(graph_path: &std::path::Path) -> Result<()> {
+    std::fs::remove_file(graph_path)?;
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let sidecar = graph_path.with_extension(format!(
+            "{}{}",
+            graph_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or(""),
+            suffix
+        ));
+        let _ = std::fs::remove_file(sidecar);
+    }
+    Ok(())
+}
+
+/// Index `source_path` into the graph at `graph_path`. Files whose content
+/// hash matches the one recorded in the graph are left as they are.
+///
+/// Progress is logged at every tenth of the files and also handed to
+/// `on_progress`. Returns `None` when the graph was already up to date.
+fn index_project(
+    source_path: &std::path::Path,
+    graph_path: &std::path::Path,
+    reset: bool,
+    scope: IndexScope,
+    queue: Option<IndexQueue>,
+    on_progress: impl Fn(&muninn_graph::IndexProgress) + Send + Sync + 'static,
+) -> Result<Option<muninn_graph::BuildStats>> {
+    // Create parent directory if needed
+    if let Some(parent) = graph_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Take the writer lock before touching the DB at all — a
+    // concurrent indexer would otherwise have graph.db deleted
+    // out from under it by --reset below.
+    let graph_lock = FileLock::try_acquire(graph_path)?;
+
+    // If --reset, remove the existing DB file before opening
+    // so the new GraphStore is empty. Keeps semantics clean:
+    // we don't have a per-table truncate, and rebuild is fast
+    // enough that wipe-and-rebuild is the simplest correct path.
+    if reset && graph_path.exists() {
+        info!("Resetting graph at {}", graph_path.display());
+        remove_graph_db(graph_path)?;
+    }
+
+    info!(
+        "Indexing {} -> {}",
+        source_path.display(),
+        graph_path.display()
+    );
+
+    // GraphStore::open_locked creates the database if it doesn't
+    // exist and holds the lock until the store is dropped.
+    let store = GraphStore::open_locked(graph_path, graph_lock)?;
+
+    // Drive the vendored narsil extractor over the source tree.
+    // This is the only indexing path muninn supports.
+    let logged_tenths = std::sync::atomic::AtomicUsize::new(0);
+    let mut builder = GraphBuilder::new(store)?.with_progress(move |progress| {
+        on_progress(progress);
+        let tenths = progress.files_done * 10 / progress.files_total.max(1);
+        if tenths > logged_tenths.swap(tenths, std::sync::atomic::Ordering::Relaxed) {
+            info!(
+                "Indexed {}/{} files{}",
+                progress.files_done,
+                progress.files_total,
+                progress
+                    .eta()
+                    .map(|eta| format!(", ~{}s left", eta.as_secs()))
+                    .unwrap_or_default()
+            );
+        }
+    });
+    builder = builder
+        .with_ignore_rules(scope.ignore)
+        .with_languages(scope.languages);
+    if let Some(queue) = queue {
+        builder = builder.with_queue(queue);
+    }
+    let stats = builder.build_directory(source_path)?;
+    let up_to_date = stats.files_processed == 0
+        && stats.removed == 0
+        && stats.skipped.is_empty()
+        && stats.errors.is_empty();
+    if up_to_date {
+        info!(
+            "Graph already up to date for {} (no source changes since last index)",
+            source_path.display()
+        );
+        return Ok(None);
+    }
+    info!(
+        "Indexed {} files ({} unchanged, {} removed), {} nodes, {} edges",
+        stats.files_processed, stats.unchanged, stats.removed, stats.nodes_added, stats.edges_added
+    );
+    for skipped in &stats.skipped {
+        tracing::warn!(
+            "Skipped {} ({} bytes, {} lines): larger than the index file limit",
+            skipped.path,
+            skipped.bytes,
+            skipped.lines
+        );
+    }
+    for error in &stats.errors {
+        tracing::warn!("Could not index {}: {}", error.path, error.reason);
+    }
+
+    Ok(Some(stats))
+}
+
+/// Which files indexing reads and how it parses them, from `[graph]` in
+/// the config.
+struct IndexScope {
+    ignore: muninn_graph::IgnoreRules,
+    languages: muninn_graph::LanguageMap,
+}
+
+fn index_scope(config: &Config) -> Result<IndexScope> {
+    Ok(IndexScope {
+        ignore: muninn_graph::IgnoreRules {
+            exclude: config.graph.exclude.clone(),
+            ..Default::default()
+        },
+        languages: config
+            .graph
+            .language_map()
+            .map_err(|e| anyhow::anyhow!("[graph.languages] {}", e))?,
+    })
+}
+
+/// Print the outcome of `muninn index --report`; `None` means the graph
+/// was already up to date.
+fn print_index_report(stats: Option<&muninn_graph::BuildStats>) {
+    let Some(stats) = stats else {
+        println!("Graph is up to date; nothing indexed.");
+        return;
+    };
+    println!(
+        "Indexed {} files: {} nodes, {} edges",
+        stats.files_processed, stats.nodes_added, stats.edges_added
+    );
+    if !stats.languages.is_empty() {
+        let languages: Vec<String> = stats
+            .languages
+            .iter()
+            .map(|(language, files)| format!("{} {}", language, files))
+            .collect();
+        println!("By language: {}", languages.join(", "));
+    }
+    if !stats.skipped.is_empty() {
+        println!("\nSkipped {} oversized files:", stats.skipped.len());
+        for skipped in &stats.skipped {
+            println!(
+                "  {} ({} bytes, {} lines)",
+                skipped.path, skipped.bytes, skipped.lines
+            );
+        }
+    }
+    if !stats.errors.is_empty() {
+        println!("\n{} files could not be indexed:", stats.errors.len());
+        for error in &stats.errors {
+            println!("  {}: {}", error.path, error.reason);
+        }
+    }
+}
+
+/// Build the first graph index off the request path, indexing the paths
+/// hinted on `queue` first. The graph is attached to the running proxy as
+/// soon as the first priority batch is stored (or the build finishes),
+/// with a `graph_attached` session event.
+fn spawn_background_index(
+    lazy: LazyGraphStore,
+    status: IndexStatus,
+    queue: IndexQueue,
+    source_path: PathBuf,
+    graph_path: PathBuf,
+    scope: IndexScope,
+    session_dir: PathBuf,
+) -> tokio::task::JoinHandle<()> {
+    let attach = {
+        let (lazy, graph_path, session_dir) = (lazy.clone(), graph_path.clone(), session_dir);
+        move |store: SharedGraphStore, data: serde_json::Value| {
+            if !lazy.attach(store) {
+                return;
+            }
+            let mut data = data;
+            data["graph"] = serde_json::json!(graph_path);
+            if let Err(e) = session::append_event(&session_dir, "graph_attached", Some(data)) {
+                tracing::debug!("Failed to log graph_attached event: {}", e);
+            }
+        }
+    };
+    let on_progress = {
+        let (lazy, status, graph_path, attach) = (
+            lazy.clone(),
+            status.clone(),
+            graph_path.clone(),
+            attach.clone(),
+        );
+        move |progress: &muninn_graph::IndexProgress| {
+            status.update(progress);
+            if progress.files_indexed == 0 || lazy.is_attached() {
+                return;
+            }
+            match open_graph_store(&graph_path) {
+                Ok(Some(store)) => attach(
+                    store,
+                    serde_json::json!({ "partial": true, "files_indexed": progress.files_indexed }),
+                ),
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Partial graph not readable yet: {}", e),
+            }
+        }
+    };
+    tokio::task::spawn_blocking(move || {
+        info!(
+            "No graph at {}; indexing {} in the background",
+            graph_path.display(),
+            source_path.display()
+        );
+        let result = index_project(
+            &source_path,
+            &graph_path,
+            false,
+            scope,
+            Some(queue),
+            on_progress,
+        )
+        .and_then(|stats| Ok((stats, open_graph_store(&graph_path)?)));
+        match result {
+            Ok((stats, Some(store))) => {
+                attach(
+                    store,
+                    serde_json::json!({
+                        "files_processed": stats.as_ref().map(|s| s.files_processed),
+                        "nodes_added": stats.as_ref().map(|s| s.nodes_added),
+                    }),
+                );
+                status.finish();
+            }
+            Ok((_, None)) => {
+                tracing::warn!(
+                    "Background indexing left no graph at {}",
+                    graph_path.display()
+                );
+                status.fail("no graph written");
+            }
+            Err(e) => {
+                tracing::warn!("Background indexing failed: {}", e);
+                status.fail(e.to_string());
+            }
+        }
+    })
+}
+
 /// Run the OAuth PKCE flow for Claude MAX authentication.
 async fn run_oauth_flow(token_manager: &FileTokenManager) -> Result<()> {
     use std::io::{self, Write};
@@ -0,0 +1,444 @@
+//! `muninn models advise`: checks that a local Ollama model can hold the
+//! prompts muninn sends it.
+//!
+//! An RLM prompt carries the system prompt, the project's directory tree,
+//! every tool definition and the (compacted or recent) conversation, plus
+//! room for the answer. Ollama silently truncates anything past the model's
+//! `num_ctx`, which shows up as a model that forgets its instructions or
+//! its tools. [`advise`] compares a [`PromptEstimate`] with what
+//! `/api/show` reports for the model and recommends a `num_ctx`, or
+//! installed models with enough context when the model itself is too small.
+//!
+//! Ollama's OpenAI-compatible endpoint ignores per-request options, so the
+//! override is applied by creating a derived model with the new `num_ctx`
+//! baked in ([`OllamaApi::create`]) and pointing the config at it
+//! ([`replace_model`]).
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// `num_ctx` Ollama uses when neither the model nor the server sets one.
+pub const OLLAMA_DEFAULT_NUM_CTX: u64 = 4096;
+
+/// Conversation tokens assumed when `[compaction]` is off: a handful of
+/// recent turns with tool results, which is what most RLM calls carry.
+pub const TYPICAL_CONVERSATION_TOKENS: u64 = 16_000;
+
+/// Tokens kept free for the model's answer.
+pub const OUTPUT_RESERVE_TOKENS: u64 = 4096;
+
+/// `num_ctx` recommendations are rounded up to a multiple of this.
+const NUM_CTX_STEP: u64 = 4096;
+
+/// Suffix of the derived model created by `--apply`.
+pub const DERIVED_SUFFIX: &str = "muninn";
+
+/// Quantizations heavy enough that a smaller one frees real memory for the
+/// KV cache a larger context needs.
+const HEAVY_QUANTIZATIONS: &[&str] = &["F32", "F16", "BF16", "Q8_0"];
+
+/// Approximate tokens in `text` (4 characters per token).
+pub fn approx_tokens(text: &str) -> u64 {
+    (text.len() / 4) as u64
+}
+
+/// What Ollama reports about one model.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    /// Longest context the model was trained for.
+    pub context_length: Option<u64>,
+    /// `num_ctx` set in the model's parameters, if any.
+    pub num_ctx: Option<u64>,
+    pub quantization: Option<String>,
+    pub parameter_size: Option<String>,
+}
+
+impl ModelInfo {
+    /// Parse an `/api/show` response for `name`.
+    pub fn from_show(name: &str, show: &Value) -> Self {
+        let context_length = show["model_info"].as_object().and_then(|info| {
+            info.iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .and_then(|(_, v)| v.as_u64())
+        });
+        let num_ctx = show["parameters"].as_str().and_then(|params| {
+            params.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                (parts.next() == Some("num_ctx"))
+                    .then(|| parts.next()?.parse().ok())
+                    .flatten()
+            })
+        });
+        let detail = |key: &str| {
+            show["details"][key]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            name: name.to_string(),
+            context_length,
+            num_ctx,
+            quantization: detail("quantization_level"),
+            parameter_size: detail("parameter_size"),
+        }
+    }
+
+    /// The context Ollama actually gives the model.
+    pub fn effective_context(&self) -> u64 {
+        let num_ctx = self.num_ctx.unwrap_or(OLLAMA_DEFAULT_NUM_CTX);
+        self.context_length.map_or(num_ctx, |max| num_ctx.min(max))
+    }
+}
+
+/// Token estimate for a typical RLM prompt, by part.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PromptEstimate {
+    pub system: u64,
+    pub dir_tree: u64,
+    pub tools: u64,
+    pub conversation: u64,
+    pub output: u64,
+}
+
+impl PromptEstimate {
+    pub fn total(&self) -> u64 {
+        self.system + self.dir_tree + self.tools + self.conversation + self.output
+    }
+}
+
+/// The verdict for one model.
+#[derive(Debug, Clone, Serialize)]
+pub struct Advice {
+    pub model: ModelInfo,
+    pub estimate: PromptEstimate,
+    pub effective_context: u64,
+    /// `num_ctx` to set, when the model can hold the prompt but isn't
+    /// configured to.
+    pub recommended_num_ctx: Option<u64>,
+    /// Installed models whose context holds the prompt, when this one
+    /// can't.
+    pub alternatives: Vec<ModelInfo>,
+    pub notes: Vec<String>,
+}
+
+impl Advice {
+    /// True when the current setup already fits.
+    pub fn fits(&self) -> bool {
+        self.effective_context >= self.estimate.total()
+    }
+
+    pub fn to_text(&self) -> String {
+        let e = &self.estimate;
+        let m = &self.model;
+        let or_unknown = |v: Option<String>| v.unwrap_or_else(|| "unknown".to_string());
+        let mut out = format!("Model: {}\n", m.name);
+        out.push_str(&format!(
+            "  parameters: {}, quantization: {}\n",
+            or_unknown(m.parameter_size.clone()),
+            or_unknown(m.quantization.clone())
+        ));
+        out.push_str(&format!(
+            "  context: {} (num_ctx {}, trained for {})\n\n",
+            self.effective_context,
+            m.num_ctx.map_or_else(
+                || format!("default {}", OLLAMA_DEFAULT_NUM_CTX),
+                |n| n.to_string()
+            ),
+            or_unknown(m.context_length.map(|n| n.to_string()))
+        ));
+        out.push_str("Typical muninn prompt (approx. tokens):\n");
+        for (part, tokens) in [
+            ("system prompt", e.system),
+            ("directory tree", e.dir_tree),
+            ("tool definitions", e.tools),
+            ("conversation", e.conversation),
+            ("answer", e.output),
+        ] {
+            out.push_str(&format!("  {:<18}{:>8}\n", part, tokens));
+        }
+        out.push_str(&format!("  {:<18}{:>8}\n\n", "total", e.total()));
+
+        if self.fits() {
+            out.push_str("The configured context holds a typical prompt.\n");
+        } else if let Some(num_ctx) = self.recommended_num_ctx {
+            out.push_str(&format!(
+                "Prompts will be truncated. Set num_ctx to {}.\n",
+                num_ctx
+            ));
+        } else {
+            out.push_str("The model's context is too small for a typical prompt.\n");
+            if self.alternatives.is_empty() {
+                out.push_str(
+                    "No installed model has enough context; pull a larger-context model.\n",
+                );
+            } else {
+                out.push_str("Installed models with enough context:\n");
+                for alt in &self.alternatives {
+                    out.push_str(&format!(
+                        "  {} ({} context)\n",
+                        alt.name,
+                        or_unknown(alt.context_length.map(|n| n.to_string()))
+                    ));
+                }
+            }
+        }
+        for note in &self.notes {
+            out.push_str(&format!("Note: {}\n", note));
+        }
+        out
+    }
+}
+
+/// Compare `model` against `estimate`. `installed` is searched for
+/// alternatives when `model` can't hold the prompt at any `num_ctx`.
+pub fn advise(model: ModelInfo, estimate: PromptEstimate, installed: &[ModelInfo]) -> Advice {
+    let needed = estimate.total();
+    let effective_context = model.effective_context();
+    let mut advice = Advice {
+        effective_context,
+        recommended_num_ctx: None,
+        alternatives: Vec::new(),
+        notes: Vec::new(),
+        model,
+        estimate,
+    };
+    if effective_context >= needed {
+        return advice;
+    }
+
+    let num_ctx = needed.div_ceil(NUM_CTX_STEP) * NUM_CTX_STEP;
+    match advice.model.context_length {
+        Some(max) if max < needed => {
+            advice.alternatives = installed
+                .iter()
+                .filter(|m| m.name != advice.model.name)
+                .filter(|m| m.context_length.is_some_and(|max| max >= needed))
+                .cloned()
+                .collect();
+        }
+        Some(max) => advice.recommended_num_ctx = Some(num_ctx.min(max)),
+        None => advice.recommended_num_ctx = Some(num_ctx),
+    }
+    if advice.recommended_num_ctx.is_some()
+        && advice
+            .model
+            .quantization
+            .as_deref()
+            .is_some_and(|q| HEAVY_QUANTIZATIONS.contains(&q.to_uppercase().as_str()))
+    {
+        advice.notes.push(format!(
+            "{} weights leave less memory for a larger context; a Q4_K_M tag of the \
+             same model holds it more comfortably.",
+            advice.model.quantization.as_deref().unwrap_or_default()
+        ));
+    }
+    advice
+}
+
+/// Name of the model `--apply` derives from `model`.
+pub fn derived_name(model: &str) -> String {
+    let (base, tag) = model.split_once(':').unwrap_or((model, "latest"));
+    format!("{}:{}-{}", base, tag, DERIVED_SUFFIX)
+}
+
+/// `config` with every `model = "<old>"` line pointing at `new`, or `None`
+/// when no line names `old` (the model came from the built-in defaults).
+pub fn replace_model(config: &str, old: &str, new: &str) -> Option<String> {
+    let quoted = format!("\"{}\"", old);
+    let mut replaced = false;
+    let lines: Vec<String> = config
+        .lines()
+        .map(|line| {
+            let Some((key, value)) = line.split_once('=') else {
+                return line.to_string();
+            };
+            let value = value.trim_start();
+            if key.trim() == "model" && value.starts_with(&quoted) {
+                replaced = true;
+                format!("{}= \"{}\"{}", key, new, &value[quoted.len()..])
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    replaced.then(|| {
+        let mut out = lines.join("\n");
+        if config.ends_with('\n') {
+            out.push('\n');
+        }
+        out
+    })
+}
+
+/// Client for Ollama's native API (`/api/*`).
+pub struct OllamaApi {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl OllamaApi {
+    /// `base_url` is the configured OpenAI-compatible URL; a trailing `/v1`
+    /// is dropped to reach the native API.
+    pub fn new(base_url: &str) -> Result<Self> {
+        let base_url = base_url.trim_end_matches('/');
+        let base_url = base_url.strip_suffix("/v1").unwrap_or(base_url);
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("muninn/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            http,
+            base_url: base_url.to_string(),
+        })
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("Ollama at {} is not reachable: {}", self.base_url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Ollama API {}: {}", status, body.trim()));
+        }
+        Ok(response.json().await?)
+    }
+
+    /// `/api/show` for `model`.
+    pub async fn show(&self, model: &str) -> Result<ModelInfo> {
+        let show = self
+            .send(
+                self.http
+                    .post(format!("{}/api/show", self.base_url))
+                    .json(&json!({"model": model})),
+            )
+            .await?;
+        Ok(ModelInfo::from_show(model, &show))
+    }
+
+    /// Every installed model, with its details.
+    pub async fn installed(&self) -> Result<Vec<ModelInfo>> {
+        let tags = self
+            .send(self.http.get(format!("{}/api/tags", self.base_url)))
+            .await?;
+        let mut models = Vec::new();
+        for name in tags["models"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m["name"].as_str())
+        {
+            models.push(self.show(name).await?);
+        }
+        Ok(models)
+    }
+
+    /// Create `name` from `from` with `num_ctx` set.
+    pub async fn create(&self, name: &str, from: &str, num_ctx: u64) -> Result<()> {
+        self.send(
+            self.http
+                .post(format!("{}/api/create", self.base_url))
+                .json(&json!({
+                    "model": name,
+                    "from": from,
+                    "parameters": {"num_ctx": num_ctx},
+                    "stream": false,
+                })),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(context_length: u64, parameters: &str, quantization: &str) -> Value {
+        json!({
+            "parameters": parameters,
+            "details": {"parameter_size": "7.6B", "quantization_level": quantization},
+            "model_info": {"general.architecture": "qwen2", "qwen2.context_length": context_length},
+        })
+    }
+
+    fn estimate(conversation: u64) -> PromptEstimate {
+        PromptEstimate {
+            system: 1500,
+            dir_tree: 800,
+            tools: 3000,
+            conversation,
+            output: OUTPUT_RESERVE_TOKENS,
+        }
+    }
+
+    #[test]
+    fn test_model_info_from_show() {
+        let info = ModelInfo::from_show(
+            "qwen2.5-coder:7b",
+            &show(32768, "stop \"<|im_end|>\"\nnum_ctx 8192", "Q4_K_M"),
+        );
+        assert_eq!(info.context_length, Some(32768));
+        assert_eq!(info.num_ctx, Some(8192));
+        assert_eq!(info.quantization.as_deref(), Some("Q4_K_M"));
+        assert_eq!(info.effective_context(), 8192);
+
+        let unset = ModelInfo::from_show("m", &show(2048, "", ""));
+        assert_eq!(unset.num_ctx, None);
+        assert_eq!(unset.quantization, None);
+        assert_eq!(unset.effective_context(), 2048);
+    }
+
+    #[test]
+    fn test_advise() {
+        let model = ModelInfo::from_show("qwen2.5-coder:7b", &show(32768, "", "F16"));
+        let advice = advise(model.clone(), estimate(TYPICAL_CONVERSATION_TOKENS), &[]);
+        assert!(!advice.fits());
+        // 25_300 tokens rounds up to the next 4096 step
+        assert_eq!(advice.recommended_num_ctx, Some(28_672));
+        assert_eq!(advice.notes.len(), 1);
+
+        let small = ModelInfo::from_show("phi3:mini", &show(4096, "", "Q4_0"));
+        let large = ModelInfo::from_show("llama3.1:8b", &show(131_072, "", "Q4_K_M"));
+        let advice = advise(
+            small.clone(),
+            estimate(TYPICAL_CONVERSATION_TOKENS),
+            &[small.clone(), model.clone(), large],
+        );
+        assert_eq!(advice.recommended_num_ctx, None);
+        let names: Vec<_> = advice
+            .alternatives
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(names, ["qwen2.5-coder:7b", "llama3.1:8b"]);
+
+        let mut configured = model;
+        configured.num_ctx = Some(32768);
+        assert!(advise(configured, estimate(1000), &[]).fits());
+    }
+
+    #[test]
+    fn test_replace_model() {
+        let config = "[default]\nprovider = \"ollama\"\nmodel = \"qwen2.5-coder:7b\"\n\n\
+                      [rlm]\nmodel = \"qwen2.5-coder:7b\" # local\n\n\
+                      [router]\nmodel = \"llama3.2:3b\"\n";
+        let updated = replace_model(
+            config,
+            "qwen2.5-coder:7b",
+            &derived_name("qwen2.5-coder:7b"),
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            "[default]\nprovider = \"ollama\"\nmodel = \"qwen2.5-coder:7b-muninn\"\n\n\
+             [rlm]\nmodel = \"qwen2.5-coder:7b-muninn\" # local\n\n\
+             [router]\nmodel = \"llama3.2:3b\"\n"
+        );
+        assert!(replace_model(config, "gemma4:31b", "x").is_none());
+        assert_eq!(derived_name("mistral"), "mistral:latest-muninn");
+    }
+}
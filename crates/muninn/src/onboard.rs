@@ -0,0 +1,203 @@
+//! `muninn onboard` implementation.
+//!
+//! Produces a `WALKTHROUGH.md` for new contributors by chaining:
+//!
+//! 1. a [`GraphOverview`] of the indexed code (size, layout, entry points,
+//!    most-called symbols), rendered straight from the graph;
+//! 2. an RLM exploration of the overall architecture;
+//! 3. one targeted RLM exploration per key entry point, tracing the flow
+//!    that starts there.
+//!
+//! Every exploration is asked to cite code as `path:line`, so the document
+//! doubles as an index into the source. Without a backend (or with
+//! `--no-rlm`) the graph sections are still written.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use muninn_core::MuninnEngine;
+use muninn_graph::{EntryKind, EntryPoint, GraphOverview};
+
+/// Output tokens allowed per exploration.
+const FLOW_MAX_TOKENS: u32 = 2048;
+
+/// Citation rules shared by every exploration prompt.
+const CITATION_RULES: &str = "Cite every claim about the code as `path:line` \
+     (paths relative to the repository root). Quote short snippets only when \
+     they carry the point. Answer in Markdown, without a top-level heading; \
+     use `###` and below if you need headings.";
+
+/// Assemble the walkthrough document.
+///
+/// `engine` is `None` when no RLM backend is available or the RLM passes
+/// were disabled; the document then carries only the graph sections.
+pub async fn build_walkthrough(
+    project: &str,
+    overview: &GraphOverview,
+    engine: Option<(Arc<dyn MuninnEngine>, String)>,
+    max_flows: usize,
+) -> String {
+    let mut out = format!("# {} walkthrough\n\n", project);
+    out.push_str(&format!(
+        "_Generated by `muninn onboard` on {}. Citations are `path:line`; \
+         line numbers drift as the code changes, so re-run to refresh._\n\n",
+        chrono::Utc::now().format("%Y-%m-%d")
+    ));
+    out.push_str(&overview.to_markdown());
+
+    let Some((engine, model)) = engine else {
+        return out;
+    };
+
+    out.push_str("\n## Architecture\n\n");
+    match super::explore_once(
+        &engine,
+        &model,
+        architecture_prompt(overview),
+        FLOW_MAX_TOKENS,
+    )
+    .await
+    {
+        Ok(answer) => out.push_str(answer.trim()),
+        Err(e) => {
+            tracing::warn!("architecture exploration failed: {}", e);
+            out.push_str("_Exploration failed; re-run `muninn onboard` to retry._");
+        }
+    }
+    out.push('\n');
+
+    let flows = key_flows(overview, max_flows);
+    if !flows.is_empty() {
+        out.push_str("\n## Key flows\n");
+    }
+    for entry in flows {
+        tracing::info!("Exploring flow from {}", entry.qualified_name);
+        out.push_str(&format!(
+            "\n### `{}` (`{}:{}`)\n\n",
+            entry.qualified_name, entry.file_path, entry.line
+        ));
+        match super::explore_once(&engine, &model, flow_prompt(entry), FLOW_MAX_TOKENS).await {
+            Ok(answer) => out.push_str(answer.trim()),
+            Err(e) => {
+                tracing::warn!(entry = %entry.qualified_name, "flow exploration failed: {}", e);
+                out.push_str("_Exploration failed; re-run `muninn onboard` to retry._");
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Entry points worth a dedicated exploration: mains and binaries first,
+/// then handlers, up to `max`.
+fn key_flows(overview: &GraphOverview, max: usize) -> Vec<&EntryPoint> {
+    let (mut flows, handlers): (Vec<&EntryPoint>, Vec<&EntryPoint>) = overview
+        .entry_points
+        .iter()
+        .partition(|e| e.kind != EntryKind::Handler);
+    flows.extend(handlers);
+    flows.truncate(max);
+    flows
+}
+
+/// Prompt for the architecture exploration.
+fn architecture_prompt(overview: &GraphOverview) -> String {
+    format!(
+        "Write the architecture section of an onboarding walkthrough for a \
+         new contributor to this repository. Explain what the project does, \
+         the main components and how they depend on each other, and where a \
+         newcomer should start reading. Explore the code rather than \
+         guessing from names.\n\
+         \n\
+         {rules}\n\
+         \n\
+         Graph summary for orientation:\n\
+         \n\
+         {summary}",
+        rules = CITATION_RULES,
+        summary = overview.to_markdown(),
+    )
+}
+
+/// Prompt for one entry point's flow.
+fn flow_prompt(entry: &EntryPoint) -> String {
+    format!(
+        "Trace what happens when `{name}` (defined at `{path}:{line}`, a {kind} \
+         entry point) runs. Walk through the key steps in order: which \
+         functions it calls, which modules the flow passes through, where \
+         state is read or written, and where errors are handled. End with the \
+         two or three places a contributor changing this flow should read \
+         first.\n\
+         \n\
+         {rules}",
+        name = entry.qualified_name,
+        path = entry.file_path,
+        line = entry.line,
+        kind = entry.kind,
+        rules = CITATION_RULES,
+    )
+}
+
+/// Project name for the document title: the root directory's name.
+pub fn project_name(root: &Path) -> String {
+    root.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Project".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn entry(name: &str, kind: EntryKind) -> EntryPoint {
+        EntryPoint {
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            file_path: format!("src/{}.rs", name),
+            line: 1,
+            kind,
+        }
+    }
+
+    fn overview(entry_points: Vec<EntryPoint>) -> GraphOverview {
+        GraphOverview {
+            symbol_count: 3,
+            call_count: 0,
+            file_count: 3,
+            kinds: BTreeMap::from([("function".to_string(), 3)]),
+            directories: vec![("src".to_string(), 3)],
+            entry_points,
+            hubs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_key_flows_prefers_mains() {
+        let overview = overview(vec![
+            entry("handle_a", EntryKind::Handler),
+            entry("main", EntryKind::Main),
+            entry("tool", EntryKind::Binary),
+        ]);
+        let names: Vec<_> = key_flows(&overview, 2)
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main", "tool"]);
+    }
+
+    #[tokio::test]
+    async fn test_walkthrough_without_rlm() {
+        let doc = build_walkthrough(
+            "demo",
+            &overview(vec![entry("main", EntryKind::Main)]),
+            None,
+            3,
+        )
+        .await;
+        assert!(doc.starts_with("# demo walkthrough\n"));
+        assert!(doc.contains("## Entry points"));
+        assert!(doc.contains("- `main` (main) at `src/main.rs:1`"));
+        assert!(!doc.contains("## Key flows"));
+    }
+}
@@ -0,0 +1,88 @@
+//! `muninn refactor-check` implementation.
+//!
+//! Builds an [`ImpactReport`] for a symbol from the code graph (definitions
+//! and call edges), sweeps the source tree for textual mentions the graph
+//! can't see, then optionally hands the draft to the RLM to confirm
+//! uncertain sites and point out anything missed (trait impls, dynamic
+//! dispatch, string references, generated code).
+//!
+//! The RLM pass is advisory: its notes land in the report's `Review`
+//! section and never rewrite the graph-derived site list.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use muninn_core::MuninnEngine;
+use muninn_graph::{GraphStore, ImpactReport};
+
+/// Output tokens allowed for the RLM review.
+const REVIEW_MAX_TOKENS: u32 = 2048;
+
+/// Build the graph + text-scan report for `symbol`.
+pub fn build_report(store: &GraphStore, symbol: &str, root: &Path) -> Result<ImpactReport> {
+    let mut report = ImpactReport::from_graph(store, symbol)
+        .map_err(|e| anyhow!("graph lookup for `{}` failed: {}", symbol, e))?;
+    let added = report.add_text_matches(root);
+    tracing::debug!(symbol, added, "text matches added to impact report");
+    Ok(report)
+}
+
+/// Ask the RLM to review the draft report, storing its notes in
+/// `report.review`.
+pub async fn review_report(
+    report: &mut ImpactReport,
+    engine: Arc<dyn MuninnEngine>,
+    model: &str,
+) -> Result<()> {
+    let review = super::explore_once(&engine, model, review_prompt(report), REVIEW_MAX_TOKENS)
+        .await
+        .map_err(|e| anyhow!("rlm review failed: {}", e))?;
+    if !review.is_empty() {
+        report.review = Some(review);
+    }
+    Ok(())
+}
+
+/// Prompt for the RLM review pass.
+fn review_prompt(report: &ImpactReport) -> String {
+    format!(
+        "We are about to rename `{symbol}` or change its signature. Below is a \
+         draft list of affected sites built from the code graph (definitions \
+         and recorded calls) plus a plain-text scan.\n\
+         \n\
+         Explore the codebase to check it:\n\
+         - Confirm or reject the medium and low confidence sites.\n\
+         - Find sites the draft missed: trait impls and overrides, dynamic \
+         dispatch, re-exports and imports, macros, tests, docs, config and \
+         string references.\n\
+         \n\
+         Answer with a Markdown list, one site per line, formatted as \
+         `path:line — why it needs updating — high|medium|low`. Put false \
+         positives under a separate \"Not affected\" list. Do not repeat \
+         confirmed high confidence sites.\n\
+         \n\
+         {draft}",
+        symbol = report.symbol,
+        draft = report.to_markdown(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_prompt_embeds_draft() {
+        let report = ImpactReport {
+            symbol: "parse_config".to_string(),
+            definitions: vec!["config::parse_config".to_string()],
+            sites: Vec::new(),
+            review: None,
+        };
+        let prompt = review_prompt(&report);
+        assert!(prompt.contains("rename `parse_config`"));
+        assert!(prompt.contains("# Refactor check: `parse_config`"));
+        assert!(prompt.contains("Definitions: config::parse_config"));
+    }
+}
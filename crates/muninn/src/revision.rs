@@ -0,0 +1,64 @@
+//! `muninn index --rev`: indexing a git revision into a named partition.
+//!
+//! The revision is checked out into a temporary detached worktree, so the
+//! working tree and the live graph are left alone, and the worktree is
+//! removed once the partition is written.
+
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use muninn_graph::{GraphPartition, RevisionManifest};
+
+use crate::snapshot::git;
+
+/// Index `rev` of the repository at `root` into the partition `name` under
+/// `revisions_dir`. `index` builds the graph for a checkout into a partition.
+pub fn index_revision(
+    root: &Path,
+    revisions_dir: &Path,
+    rev: &str,
+    name: &str,
+    index: impl FnOnce(&Path, &GraphPartition) -> Result<()>,
+) -> Result<RevisionManifest> {
+    let partition = GraphPartition::new(revisions_dir, name)?;
+    let commit = git(
+        root,
+        &["rev-parse", "--verify", &format!("{}^{{commit}}", rev)],
+    )
+    .map_err(|e| anyhow!("cannot resolve revision '{}': {}", rev, e))?;
+
+    let checkout = std::env::temp_dir().join(format!(
+        "muninn-rev-{}-{}",
+        std::process::id(),
+        name.replace('/', "__")
+    ));
+    let checkout_arg = checkout.to_string_lossy();
+    git(
+        root,
+        &["worktree", "add", "--detach", &checkout_arg, &commit],
+    )?;
+
+    let result = (|| {
+        // Start from an empty graph: a partition always reflects exactly
+        // one commit.
+        if partition.dir().exists() {
+            std::fs::remove_dir_all(partition.dir())?;
+        }
+        std::fs::create_dir_all(partition.dir())?;
+        index(&checkout, &partition)?;
+        let manifest = RevisionManifest {
+            name: name.to_string(),
+            revision: rev.to_string(),
+            commit: commit.clone(),
+            root: checkout.clone(),
+            indexed_at: chrono::Utc::now().timestamp().max(0) as u64,
+        };
+        partition.write_manifest(&manifest)?;
+        Ok(manifest)
+    })();
+
+    if let Err(e) = git(root, &["worktree", "remove", "--force", &checkout_arg]) {
+        tracing::warn!("Failed to remove worktree {}: {}", checkout.display(), e);
+    }
+    result
+}
@@ -1,14 +1,27 @@
 //! Session management for proxy runs.
 //!
 //! Each proxy run gets a unique session ID and directory for isolated logging.
+//! `session.json` is written at startup and refreshed every
+//! [`HEARTBEAT_INTERVAL`] with totals from the spend ledger and the
+//! feedback log; lifecycle events (start, shutdown) are appended to
+//! `events.jsonl`.
 
 use std::fmt;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
+use muninn_rlm::{FeedbackLog, Rating, SpendLedger};
 use serde::{Deserialize, Serialize};
 
+/// How often a running session rewrites `session.json`.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// File name of the lifecycle event log inside the session directory.
+pub const EVENTS_FILE: &str = "events.jsonl";
+
 /// Unique identifier for a proxy session.
 ///
 /// Format: `YYYY-MM-DDTHH-MM-SS_XXXX` where XXXX is a short UUID suffix.
@@ -70,6 +83,66 @@ pub struct SessionMetadata {
     /// RLM model being used.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rlm_model: Option<String>,
+
+    /// Last heartbeat from the running process.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+
+    /// When the last request finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_activity: Option<DateTime<Utc>>,
+
+    /// When the session shut down cleanly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+
+    /// Request and token totals so far.
+    #[serde(default)]
+    pub totals: SessionTotals,
+}
+
+/// Request and token totals for a session, summed from its spend ledger.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionTotals {
+    /// Requests handled.
+    pub requests: u64,
+    /// Requests handled by the RLM rather than passed through.
+    pub rlm_requests: u64,
+    /// Input tokens, including cache reads and writes.
+    pub input_tokens: u64,
+    /// Output tokens.
+    pub output_tokens: u64,
+    /// RLM requests with a self-assessed quality score (`[rlm] self_score`).
+    pub scored_requests: u64,
+    /// Mean quality score of those, from 0 to 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_quality: Option<f64>,
+    /// Answers the user rated `@muninn good`.
+    pub good_feedback: u64,
+    /// Answers the user rated `@muninn bad`.
+    pub bad_feedback: u64,
+}
+
+/// Whether a session is still running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionStatus {
+    /// Heartbeat is recent.
+    Live,
+    /// Shut down cleanly.
+    Ended,
+    /// Never marked ended and the heartbeat stopped (crash or kill).
+    Stale,
+}
+
+impl fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            SessionStatus::Live => "live",
+            SessionStatus::Ended => "ended",
+            SessionStatus::Stale => "stale",
+        })
+    }
 }
 
 impl SessionMetadata {
@@ -81,6 +154,25 @@ impl SessionMetadata {
             work_dir,
             router_strategy: None,
             rlm_model: None,
+            updated_at: None,
+            last_activity: None,
+            ended_at: None,
+            totals: SessionTotals::default(),
+        }
+    }
+
+    /// Session status as of `now`. A session whose heartbeat is more than
+    /// two intervals old is considered gone.
+    pub fn status(&self, now: DateTime<Utc>) -> SessionStatus {
+        if self.ended_at.is_some() {
+            return SessionStatus::Ended;
+        }
+        let heartbeat = self.updated_at.unwrap_or(self.started_at);
+        let max_age = chrono::Duration::from_std(HEARTBEAT_INTERVAL * 2).unwrap_or_default();
+        if now - heartbeat <= max_age {
+            SessionStatus::Live
+        } else {
+            SessionStatus::Stale
         }
     }
 
@@ -106,7 +198,6 @@ pub fn write_metadata(session_dir: &Path, metadata: &SessionMetadata) -> anyhow:
 }
 
 /// Read session metadata from a session directory.
-#[allow(dead_code)]
 pub fn read_metadata(session_dir: &Path) -> anyhow::Result<SessionMetadata> {
     let path = session_dir.join("session.json");
     let json = fs::read_to_string(&path)?;
@@ -114,11 +205,253 @@ pub fn read_metadata(session_dir: &Path) -> anyhow::Result<SessionMetadata> {
     Ok(metadata)
 }
 
+/// Recompute totals from the spend ledger and record a heartbeat.
+pub fn refresh_metadata(session_dir: &Path) -> anyhow::Result<SessionMetadata> {
+    let mut metadata = read_metadata(session_dir)?;
+    let entries = SpendLedger::in_dir(session_dir).read()?;
+    let mut totals = SessionTotals::default();
+    let mut quality_sum = 0.0;
+    for entry in &entries {
+        totals.requests += 1;
+        if entry.handling == "rlm" {
+            totals.rlm_requests += 1;
+        }
+        totals.input_tokens += entry.usage.input_tokens
+            + entry.usage.cache_creation_input_tokens
+            + entry.usage.cache_read_input_tokens;
+        totals.output_tokens += entry.usage.output_tokens;
+        if let Some(quality) = entry.quality {
+            totals.scored_requests += 1;
+            quality_sum += quality;
+        }
+    }
+    if totals.scored_requests > 0 {
+        totals.mean_quality = Some(quality_sum / totals.scored_requests as f64);
+    }
+    for feedback in FeedbackLog::in_dir(session_dir).read()? {
+        match feedback.rating {
+            Rating::Good => totals.good_feedback += 1,
+            Rating::Bad => totals.bad_feedback += 1,
+        }
+    }
+    metadata.totals = totals;
+    metadata.last_activity = entries.iter().map(|e| e.timestamp).max();
+    metadata.updated_at = Some(Utc::now());
+    write_metadata(session_dir, &metadata)?;
+    Ok(metadata)
+}
+
+/// Final refresh on shutdown: stamps `ended_at` and logs an `ended` event.
+pub fn finish_session(session_dir: &Path) -> anyhow::Result<()> {
+    let mut metadata = refresh_metadata(session_dir)?;
+    metadata.ended_at = metadata.updated_at;
+    write_metadata(session_dir, &metadata)?;
+    append_event(
+        session_dir,
+        "ended",
+        Some(serde_json::to_value(metadata.totals)?),
+    )
+}
+
+/// Refresh `session.json` every [`HEARTBEAT_INTERVAL`] until aborted.
+pub fn spawn_heartbeat(session_dir: PathBuf) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = refresh_metadata(&session_dir) {
+                tracing::debug!(error = %e, "Failed to refresh session metadata");
+            }
+        }
+    })
+}
+
+/// The session of one proxy run, whichever command started it.
+///
+/// [`create`](Self::create) makes the directory, [`start`](Self::start)
+/// writes `session.json` and the `started` event and begins the
+/// heartbeat, and [`finish`](Self::finish) stamps the end on shutdown.
+pub struct ProxySession {
+    /// The session ID.
+    pub id: SessionId,
+    /// The session directory.
+    pub dir: PathBuf,
+    heartbeat: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl ProxySession {
+    /// Create a fresh session directory under `muninn_dir`.
+    pub fn create(muninn_dir: &Path) -> anyhow::Result<Self> {
+        let id = SessionId::generate();
+        let dir = session_dir(muninn_dir, &id);
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            id,
+            dir,
+            heartbeat: None,
+        })
+    }
+
+    /// Trace writer settings for the session's `traces.jsonl`.
+    pub fn trace_writer(&self) -> muninn_tracing::WriterConfig {
+        muninn_tracing::WriterConfig::session(self.dir.join("traces.jsonl"))
+    }
+
+    /// Write `session.json`, log a `started` event with `data`, and keep
+    /// the metadata fresh until [`finish`](Self::finish).
+    pub fn start(
+        &mut self,
+        metadata: &SessionMetadata,
+        data: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        write_metadata(&self.dir, metadata)?;
+        append_event(&self.dir, "started", Some(data))?;
+        self.heartbeat = Some(spawn_heartbeat(self.dir.clone()));
+        Ok(())
+    }
+
+    /// Stop the heartbeat and finalize the metadata, logging any failure.
+    pub fn finish(self) {
+        if let Some(heartbeat) = self.heartbeat {
+            heartbeat.abort();
+        }
+        if let Err(e) = finish_session(&self.dir) {
+            tracing::warn!("Failed to finalize session metadata: {}", e);
+        }
+    }
+}
+
+/// A lifecycle event in `events.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// When it happened.
+    pub at: DateTime<Utc>,
+    /// Event name (`started`, `ended`, ...).
+    pub event: String,
+    /// Event-specific data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+/// Append a lifecycle event to the session's event log.
+pub fn append_event(
+    session_dir: &Path,
+    event: &str,
+    data: Option<serde_json::Value>,
+) -> anyhow::Result<()> {
+    let event = SessionEvent {
+        at: Utc::now(),
+        event: event.to_string(),
+        data,
+    };
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(session_dir.join(EVENTS_FILE))?;
+    writeln!(file, "{}", serde_json::to_string(&event)?)?;
+    Ok(())
+}
+
+/// Every session under `muninn_dir` with readable metadata, newest first.
+pub fn list_sessions(muninn_dir: &Path) -> Vec<SessionMetadata> {
+    let mut sessions: Vec<SessionMetadata> = fs::read_dir(muninn_dir.join("sessions"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| read_metadata(&e.path()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    sessions.sort_by_key(|m| std::cmp::Reverse(m.started_at));
+    sessions
+}
+
+/// Files and directories `muninn sessions clean` removes.
+#[derive(Debug, Default)]
+pub struct CleanPlan {
+    /// Paths to delete; directories are removed recursively.
+    pub paths: Vec<PathBuf>,
+    /// Total size of everything in `paths`.
+    pub bytes: u64,
+}
+
+/// Work out what to delete under `muninn_dir`.
+///
+/// With `debug_only`, only raw request logs (live and rotated) are
+/// selected: those in each session directory, in `.muninn/debug`, and in
+/// `debug_dir` when it is configured elsewhere. Otherwise every session
+/// directory goes, along with the out-of-session debug logs.
+pub fn plan_clean(muninn_dir: &Path, debug_dir: Option<&Path>, debug_only: bool) -> CleanPlan {
+    let mut plan = CleanPlan::default();
+    let mut sessions: Vec<PathBuf> = fs::read_dir(muninn_dir.join("sessions"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    sessions.sort();
+
+    let mut debug_dirs = vec![muninn_dir.join("debug")];
+    debug_dirs.extend(debug_dir.map(Path::to_path_buf));
+    if debug_only {
+        debug_dirs.extend(sessions);
+    } else {
+        plan.paths.extend(sessions);
+    }
+    for dir in debug_dirs {
+        for log in muninn_rlm::raw_request_logs(&dir) {
+            if !plan.paths.contains(&log) {
+                plan.paths.push(log);
+            }
+        }
+    }
+    plan.bytes = plan.paths.iter().map(|p| disk_usage(p)).sum();
+    plan
+}
+
+/// Delete everything in `plan`.
+pub fn apply_clean(plan: &CleanPlan) -> anyhow::Result<()> {
+    for path in &plan.paths {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn disk_usage(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| disk_usage(&e.path())).sum())
+            .unwrap_or(0)
+    } else {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    /// Read the session's lifecycle events, oldest first.
+    fn read_events(session_dir: &Path) -> anyhow::Result<Vec<SessionEvent>> {
+        let content = match fs::read_to_string(session_dir.join(EVENTS_FILE)) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
     #[test]
     fn test_session_id_format() {
         let id = SessionId::generate();
@@ -142,6 +475,152 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_plan_clean() {
+        let dir = tempdir().unwrap();
+        let session = dir.path().join("sessions/2026-01-11T17-34-52_a3f2");
+        fs::create_dir_all(&session).unwrap();
+        fs::write(session.join("raw_requests.jsonl"), "{}\n").unwrap();
+        fs::write(session.join("raw_requests.1.jsonl"), "{}\n").unwrap();
+        fs::write(session.join("traces.jsonl"), "{}\n").unwrap();
+        fs::create_dir_all(dir.path().join("debug")).unwrap();
+        fs::write(dir.path().join("debug/raw_requests.jsonl"), "{}\n").unwrap();
+        fs::write(dir.path().join("debug/upstream.jsonl"), "{}\n").unwrap();
+
+        let plan = plan_clean(dir.path(), None, true);
+        let mut names: Vec<_> = plan
+            .paths
+            .iter()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("debug/raw_requests.jsonl"),
+                PathBuf::from("sessions/2026-01-11T17-34-52_a3f2/raw_requests.1.jsonl"),
+                PathBuf::from("sessions/2026-01-11T17-34-52_a3f2/raw_requests.jsonl"),
+            ]
+        );
+        assert_eq!(plan.bytes, 9);
+
+        apply_clean(&plan).unwrap();
+        assert!(session.join("traces.jsonl").exists());
+        assert!(dir.path().join("debug/upstream.jsonl").exists());
+
+        let plan = plan_clean(dir.path(), None, false);
+        assert_eq!(plan.paths, vec![session.clone()]);
+        apply_clean(&plan).unwrap();
+        assert!(!session.exists());
+    }
+
+    #[test]
+    fn test_refresh_and_finish() {
+        let muninn_dir = tempdir().unwrap();
+        let session_id = SessionId::generate();
+        let session_dir = session_dir(muninn_dir.path(), &session_id);
+        fs::create_dir_all(&session_dir).unwrap();
+        let dir = session_dir.as_path();
+        write_metadata(
+            dir,
+            &SessionMetadata::new(&session_id, PathBuf::from("/test/project")),
+        )
+        .unwrap();
+        let ledger = SpendLedger::in_dir(dir);
+        let usage = muninn_rlm::TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_read_input_tokens: 90,
+            ..Default::default()
+        };
+        ledger.record(&muninn_rlm::SpendEntry::new(
+            "t1",
+            "m",
+            "passthrough",
+            true,
+            usage,
+        ));
+        ledger.record(
+            &muninn_rlm::SpendEntry::new("t2", "m", "rlm", false, usage).with_quality(Some(0.75)),
+        );
+        let feedback = FeedbackLog::in_dir(dir);
+        feedback.remember("t2", Some("why?".to_string()));
+        feedback.rate(
+            muninn_rlm::Feedback {
+                rating: Rating::Bad,
+                comment: None,
+            },
+            None,
+        );
+
+        let metadata = refresh_metadata(dir).unwrap();
+        assert_eq!(
+            metadata.totals,
+            SessionTotals {
+                requests: 2,
+                rlm_requests: 1,
+                input_tokens: 200,
+                output_tokens: 10,
+                scored_requests: 1,
+                mean_quality: Some(0.75),
+                good_feedback: 0,
+                bad_feedback: 1,
+            }
+        );
+        assert!(metadata.last_activity.is_some());
+        assert_eq!(metadata.status(Utc::now()), SessionStatus::Live);
+        let later = Utc::now() + chrono::Duration::minutes(5);
+        assert_eq!(metadata.status(later), SessionStatus::Stale);
+
+        append_event(dir, "started", None).unwrap();
+        finish_session(dir).unwrap();
+        let metadata = read_metadata(dir).unwrap();
+        assert_eq!(metadata.status(later), SessionStatus::Ended);
+        let events = read_events(dir).unwrap();
+        let names: Vec<_> = events.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(names, vec!["started", "ended"]);
+        assert_eq!(events[1].data.as_ref().unwrap()["requests"], 2);
+
+        let listed = list_sessions(muninn_dir.path());
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].totals.requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_session_lifecycle() {
+        let temp = tempdir().unwrap();
+        let mut session = ProxySession::create(temp.path()).unwrap();
+        assert!(session.dir.starts_with(temp.path().join("sessions")));
+        assert!(session.dir.is_dir());
+
+        let metadata = SessionMetadata::new(&session.id, temp.path().to_path_buf())
+            .with_rlm_model("gemma4:31b");
+        session
+            .start(&metadata, serde_json::json!({ "agent": "claude" }))
+            .unwrap();
+        let dir = session.dir.clone();
+        session.finish();
+
+        let metadata = read_metadata(&dir).unwrap();
+        assert_eq!(metadata.rlm_model.as_deref(), Some("gemma4:31b"));
+        assert!(metadata.ended_at.is_some());
+        let events: Vec<String> = read_events(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.event)
+            .collect();
+        assert_eq!(events, vec!["started", "ended"]);
+    }
+
+    #[test]
+    fn test_metadata_without_runtime_fields() {
+        // session.json written before runtime updates existed
+        let json = r#"{"session_id":"s","started_at":"2026-01-11T17:34:52Z","work_dir":"/p"}"#;
+        let metadata: SessionMetadata = serde_json::from_str(json).unwrap();
+        assert_eq!(metadata.totals, SessionTotals::default());
+        assert!(metadata.ended_at.is_none());
+    }
+
     #[test]
     fn test_metadata_roundtrip() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,178 @@
+//! `muninn graph pack` / `unpack`: sharing prebuilt graph snapshots.
+//!
+//! Packing records the checkout's commit in the snapshot; unpacking
+//! compares it against the local checkout so a teammate knows whether the
+//! downloaded index is current or how far behind it is.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use muninn_graph::{GraphStore, SNAPSHOT_FORMAT, SnapshotManifest};
+
+/// Run git in `root`, returning trimmed stdout.
+pub(crate) fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Manifest for a snapshot of `db_path` taken from the checkout at `root`.
+/// The checkout counts as dirty when files with an indexed `extension`
+/// have uncommitted changes.
+pub fn manifest_for(
+    root: &Path,
+    db_path: &Path,
+    extensions: &[String],
+) -> Result<SnapshotManifest> {
+    let commit = git(root, &["rev-parse", "HEAD"])
+        .map_err(|e| anyhow!("graph pack needs a git checkout: {}", e))?;
+    let status = git(root, &["status", "--porcelain"])?;
+    let changed: String = status
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(|path| format!("{}\n", path))
+        .collect();
+    let dirty = !changed_indexed_files(&changed, extensions).is_empty();
+    let stats = GraphStore::open(db_path)?.stats()?;
+    Ok(SnapshotManifest {
+        format: SNAPSHOT_FORMAT,
+        commit,
+        dirty,
+        created_at: chrono::Utc::now().timestamp().max(0) as u64,
+        muninn_version: env!("CARGO_PKG_VERSION").to_string(),
+        node_count: stats.node_count,
+        edge_count: stats.edge_count,
+    })
+}
+
+/// Default snapshot file name: `graph-<short commit>.tar.gz`.
+pub fn default_output(manifest: &SnapshotManifest) -> PathBuf {
+    let short = &manifest.commit[..manifest.commit.len().min(12)];
+    PathBuf::from(format!("graph-{}.tar.gz", short))
+}
+
+/// How a snapshot relates to the local checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Staleness {
+    /// Indexed at the checked-out commit with no indexed files changed since.
+    Current,
+    /// Indexed at an ancestor or sibling of the checkout.
+    Behind {
+        /// Commits on HEAD not in the snapshot's commit.
+        commits: u64,
+        /// Indexed files changed since the snapshot, including uncommitted edits.
+        changed_files: Vec<String>,
+    },
+    /// The snapshot's commit isn't in the local history (not fetched yet).
+    UnknownCommit,
+}
+
+impl Staleness {
+    /// Compare `manifest` against the checkout at `root`, counting only
+    /// files with one of the indexed `extensions` as changed.
+    pub fn check(root: &Path, manifest: &SnapshotManifest, extensions: &[String]) -> Self {
+        let range = format!("{}..HEAD", manifest.commit);
+        let Ok(commits) = git(root, &["rev-list", "--count", &range]) else {
+            return Self::UnknownCommit;
+        };
+        let commits = commits.parse().unwrap_or(0);
+        let changed_files = changed_indexed_files(
+            &git(root, &["diff", "--name-only", &manifest.commit]).unwrap_or_default(),
+            extensions,
+        );
+        if commits == 0 && changed_files.is_empty() {
+            Self::Current
+        } else {
+            Self::Behind {
+                commits,
+                changed_files,
+            }
+        }
+    }
+
+    /// One-line summary for the terminal.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Current => "up to date with the checkout".to_string(),
+            Self::Behind {
+                commits,
+                changed_files,
+            } => format!(
+                "{} commit(s) behind HEAD, {} indexed file(s) changed since; run `muninn index` to refresh",
+                commits,
+                changed_files.len()
+            ),
+            Self::UnknownCommit => {
+                "snapshot commit is not in the local history; fetch it or run `muninn index`"
+                    .to_string()
+            }
+        }
+    }
+}
+
+/// Lines of `git diff --name-only` output for files with an indexed extension.
+fn changed_indexed_files(diff: &str, extensions: &[String]) -> Vec<String> {
+    diff.lines()
+        .filter(|path| {
+            Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| extensions.iter().any(|x| x == ext))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(commit: &str) -> SnapshotManifest {
+        SnapshotManifest {
+            format: SNAPSHOT_FORMAT,
+            commit: commit.to_string(),
+            dirty: false,
+            created_at: 0,
+            muninn_version: "0.0.0".to_string(),
+            node_count: 0,
+            edge_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_default_output_uses_short_commit() {
+        let m = manifest("0123456789abcdef0123");
+        assert_eq!(
+            default_output(&m),
+            PathBuf::from("graph-0123456789ab.tar.gz")
+        );
+    }
+
+    #[test]
+    fn test_changed_indexed_files_filters_extensions() {
+        let diff = "src/main.rs\nREADME.md\nweb/app.ts\nMakefile\n";
+        let exts = vec!["rs".to_string(), "ts".to_string()];
+        assert_eq!(
+            changed_indexed_files(diff, &exts),
+            ["src/main.rs", "web/app.ts"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_commit_outside_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let staleness = Staleness::check(dir.path(), &manifest("deadbeef"), &[]);
+        assert_eq!(staleness, Staleness::UnknownCommit);
+    }
+}
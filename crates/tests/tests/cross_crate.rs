@@ -6,6 +6,7 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use muninn_rlm::testing::{ChaosBackend, Fault};
 use muninn_rlm::{
     CompletionRequest, CompletionResponse, ContentBlock, Message, MockBackend, ProxyConfig,
     ProxyServer, RouterConfig, RouterStrategy, StopReason, ToolRegistry, Usage,
@@ -231,6 +232,7 @@ async fn test_e2e_graph_tools_integration() {
         cyclomatic: None,
         cognitive: None,
         call_degree: None,
+        sensitivity: Vec::new(),
     };
     store.insert_node(&test_symbol).unwrap();
 
@@ -316,3 +318,99 @@ async fn test_e2e_graph_tools_integration() {
     shutdown_tx.send(()).unwrap();
     let _ = server_handle.await;
 }
+
+/// Start an always-RLM proxy over `backend` and return its port plus a
+/// shutdown handle.
+async fn spawn_rlm_proxy(
+    backend: Arc<dyn muninn_rlm::LLMBackend>,
+    tools: ToolRegistry,
+) -> (
+    u16,
+    tokio::sync::oneshot::Sender<()>,
+    tokio::task::JoinHandle<std::io::Result<()>>,
+) {
+    let port = get_test_port();
+    let config = ProxyConfig::new(format!("127.0.0.1:{}", port).parse().unwrap());
+    let router_config = RouterConfig {
+        strategy: RouterStrategy::AlwaysRlm,
+        ..Default::default()
+    };
+    let server = ProxyServer::with_router(config, backend, Arc::new(tools), router_config);
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let server_handle = tokio::spawn(async move {
+        server
+            .run_with_shutdown(async {
+                shutdown_rx.await.ok();
+            })
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    (port, shutdown_tx, server_handle)
+}
+
+/// Resilience: a rate-limited RLM backend surfaces as a backend error,
+/// still tagged with a trace ID.
+#[tokio::test]
+async fn test_chaos_rate_limit_surfaces_backend_error() {
+    let backend = ChaosBackend::new(Arc::new(MockBackend::new(vec![]))).then(Fault::RateLimit);
+    let (port, shutdown_tx, server_handle) =
+        spawn_rlm_proxy(Arc::new(backend), ToolRegistry::new()).await;
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Explore")], 256);
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/v1/messages", port))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 502);
+    assert!(response.headers().contains_key(muninn_rlm::TRACE_ID_HEADER));
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "backend_error");
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+/// Resilience: the engine survives a malformed tool call and lets the
+/// model answer on the next turn.
+#[tokio::test]
+async fn test_chaos_malformed_tool_call_recovers() {
+    let temp = tempfile::tempdir().unwrap();
+    let final_response = CompletionResponse::new(
+        "msg_final",
+        "test-model",
+        vec![ContentBlock::Text {
+            text: "Recovered".to_string(),
+            cache_control: None,
+        }],
+        StopReason::EndTurn,
+        Usage::new(10, 5),
+    );
+    let inner = Arc::new(MockBackend::new(vec![final_response]));
+    let backend = Arc::new(ChaosBackend::new(inner).then(Fault::MalformedToolCall));
+
+    let mut tools = ToolRegistry::new();
+    for tool in muninn_rlm::create_fs_tools(temp.path()) {
+        tools.register_arc(Arc::from(tool));
+    }
+    let (port, shutdown_tx, server_handle) = spawn_rlm_proxy(backend.clone(), tools).await;
+
+    let request = CompletionRequest::new("test-model", vec![Message::user("Read a file")], 256);
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/v1/messages", port))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let completion: CompletionResponse = response.json().await.unwrap();
+    assert_eq!(completion.text(), "Recovered");
+    assert_eq!(backend.injected(), vec![Fault::MalformedToolCall]);
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}